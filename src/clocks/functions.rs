@@ -1,12 +1,13 @@
 //! The impls and functions
 //!
-use chrono::Local;
-use std::{sync::mpsc::channel, time::Instant};
+use chrono::{Duration, Local, NaiveDateTime};
+use std::{collections::{BTreeMap, BTreeSet}, sync::mpsc::channel, time::Instant};
 use scraper::{Html, Selector};
 use log::*;
 use anyhow::Result;
 use crate::isleader::AllIsLeader;
 use crate::utility;
+use crate::utility::Config;
 use crate::snapshot;
 use crate::clocks::{AllClocks, Clocks};
 use crate::Opts;
@@ -17,13 +18,14 @@ impl AllClocks {
         ports: &Vec<&str>,
         snapshot_number: i32,
         parallel: usize ,
+        config: &Config,
     ) -> Result<()>
     {
         info!("begin snapshot");
         let timer = Instant::now();
 
-        let allstoredclocks = AllClocks::read_clocks(hosts, ports, parallel).await?;
-        snapshot::save_snapshot_json(snapshot_number, "clocks", allstoredclocks.clocks)?;
+        let allstoredclocks = AllClocks::read_clocks(hosts, ports, parallel, config).await?;
+        snapshot::save_snapshot_json(snapshot_number, "clocks", allstoredclocks.clocks, config)?;
 
         info!("end snapshot: {:?}", timer.elapsed());
 
@@ -33,7 +35,8 @@ impl AllClocks {
     pub async fn read_clocks (
         hosts: &Vec<&str>,
         ports: &Vec<&str>,
-        parallel: usize
+        parallel: usize,
+        config: &Config,
     ) -> Result<AllClocks>
     {
         info!("begin parallel http read");
@@ -48,9 +51,9 @@ impl AllClocks {
                     let tx = tx.clone();
                     s.spawn(move |_| {
                         let detail_snapshot_time = Local::now();
-                        let mut clocks = AllClocks::read_http(host, port);
+                        let mut clocks = AllClocks::read_http(host, port, config);
                         clocks.iter_mut().for_each(|r| r.timestamp = Some(detail_snapshot_time));
-                        clocks.iter_mut().for_each(|r| r.hostname_port = Some(format!("{}:{}", host, port)));
+                        clocks.iter_mut().for_each(|r| r.hostname_port = Some(utility::hostname_port(host, port, config)));
                         tx.send(clocks).expect("error sending data via tx");
                     });
                 }
@@ -73,9 +76,10 @@ impl AllClocks {
     fn read_http(
         host: &str,
         port: &str,
+        config: &Config,
     ) -> Vec<Clocks>
     {
-        let data_from_http = utility::http_get(host, port, "tablet-server-clocks");
+        let data_from_http = utility::http_get_endpoint(host, port, "clocks", config);
         AllClocks::parse_clocks(data_from_http)
     }
     fn parse_clocks(
@@ -116,51 +120,101 @@ impl AllClocks {
         // This table is the first one to run into (there are two on this page).
         for table in html.select(&table_selector)
         {
-            // table has got the table it found in it.
-            match table
+            // These are the table column names of the table we want to get the data from.
+            // th = table header
+            // The headers are collected first, so a table with less or no headers does not panic.
+            let table_headers: Vec<String> = table.select(&th_selector).map(|th| th.text().collect::<String>()).collect();
+            // The columns are looked up by name, so a version that adds or moves a column is still parsed.
+            // A column that is not in the table is empty.
+            let column_number = |name: &str| table_headers.iter().position(|header| header == name);
+            if column_number("Server").is_some() && column_number("Physical Time (UTC)").is_some()
             {
-                // These are the table column names of the table we want to get the data from.
-                // th = table header
-                th
-                if th.select(&th_selector).next().unwrap().text().collect::<String>() == *"Server"
-                    && th.select(&th_selector).nth(1).unwrap().text().collect::<String>() == *"Time since heartbeat"
-                    && th.select(&th_selector).nth(2).unwrap().text().collect::<String>() == *"Status & Uptime"
-                    && th.select(&th_selector).nth(3).unwrap().text().collect::<String>() == *"Physical Time (UTC)"
-                    && th.select(&th_selector).nth(4).unwrap().text().collect::<String>() == *"Hybrid Time (UTC)"
-                    && th.select(&th_selector).nth(5).unwrap().text().collect::<String>() == *"Heartbeat RTT"
-                    && th.select(&th_selector).nth(6).unwrap().text().collect::<String>() == *"Cloud"
-                    && th.select(&th_selector).nth(7).unwrap().text().collect::<String>() == *"Region"
-                    && th.select(&th_selector).nth(8).unwrap().text().collect::<String>() == *"Zone" =>
+                // These are the table column data values (td) from the table row (tr).
+                // The first table row is skipped, that is the heading as seen above.
+                // A row with missing columns gets the missing fields empty, instead of panicking.
+                for tr in table.select(&tr_selector).skip(1)
                 {
-                    // These are the table column data values (td) from the table row (tr).
-                    // The first table row is skipped, that is the heading as seen above.
-                    for tr in table.select(&tr_selector).skip(1)
+                    let td: Vec<String> = tr.select(&td_selector).map(|td| td.text().collect::<String>()).collect();
+                    if td.len() < table_headers.len()
                     {
-                        clocks.push( Clocks {
-                            server: tr.select(&td_selector).next().unwrap().text().collect::<String>(),
-                            time_since_heartbeat: tr.select(&td_selector).nth(1).unwrap().text().collect::<String>(),
-                            status_uptime: tr.select(&td_selector).nth(2).unwrap().text().collect::<String>(),
-                            physical_time_utc: tr.select(&td_selector).nth(3).unwrap().text().collect::<String>(),
-                            hybrid_time_utc: tr.select(&td_selector).nth(4).unwrap().text().collect::<String>(),
-                            heartbeat_rtt: tr.select(&td_selector).nth(5).unwrap().text().collect::<String>(),
-                            cloud: tr.select(&td_selector).nth(6).unwrap().text().collect::<String>(),
-                            region: tr.select(&td_selector).nth(7).unwrap().text().collect::<String>(),
-                            zone: tr.select(&td_selector).nth(8).unwrap().text().collect::<String>(),
-                            ..Default::default()
-                        });
+                        warn!("Tablet server clocks row has {} columns, expected {}: {:?}", td.len(), table_headers.len(), td);
                     }
-                },
-                _ => {
-                    info!("Found another table, this shouldn't happen.");
-                },
+                    let column = |name: &str| -> String { column_number(name).and_then(|number| td.get(number)).cloned().unwrap_or_default() };
+                    clocks.push( Clocks {
+                        server: column("Server"),
+                        time_since_heartbeat: column("Time since heartbeat"),
+                        status_uptime: column("Status & Uptime"),
+                        physical_time_utc: column("Physical Time (UTC)"),
+                        hybrid_time_utc: column("Hybrid Time (UTC)"),
+                        heartbeat_rtt: column("Heartbeat RTT"),
+                        cloud: column("Cloud"),
+                        region: column("Region"),
+                        zone: column("Zone"),
+                        ..Default::default()
+                    });
+                }
+            }
+            else
+            {
+                info!("Found another table, this shouldn't happen.");
             }
         }
         clocks
     }
+    /// Parse a physical or hybrid time as shown on /tablet-server-clocks, such as: `2023-01-17 14:22:18.908376`.
+    ///
+    /// The format can differ between versions, so a time that can not be parsed returns None,
+    /// instead of failing the clocks report.
+    fn parse_clock_time(
+        clock_time: &str,
+    ) -> Option<NaiveDateTime>
+    {
+        NaiveDateTime::parse_from_str(clock_time.trim(), "%Y-%m-%d %H:%M:%S%.f")
+            .map_err(|error| debug!("Could not parse clock time: {}, error: {}", clock_time, error))
+            .ok()
+    }
+    /// Return the clock skew as the difference between the highest and lowest physical time of the tablet servers
+    /// reported by the master leader, together with the number of tablet servers the skew is calculated for,
+    /// and the number of tablet servers for which the physical time could not be parsed.
+    /// The tablet servers for which the physical time could not be parsed are excluded from the skew.
+    pub fn clock_skew(
+        &self,
+        leader_hostname: &str,
+    ) -> (Option<Duration>, usize, usize)
+    {
+        let mut physical_times = Vec::new();
+        let mut parse_errors = 0;
+        for row in self.clocks.iter().filter(|row| row.hostname_port.as_deref() == Some(leader_hostname))
+        {
+            match AllClocks::parse_clock_time(&row.physical_time_utc)
+            {
+                Some(physical_time) => physical_times.push(physical_time),
+                None => parse_errors += 1,
+            }
+        }
+        let skew = match (physical_times.iter().min(), physical_times.iter().max())
+        {
+            (Some(min), Some(max)) => Some(*max - *min),
+            _ => None,
+        };
+        (skew, physical_times.len(), parse_errors)
+    }
+    /// Show a clock time as is when it can be parsed, otherwise mark it as a parse error and show the raw value.
+    fn display_clock_time(
+        clock_time: &str,
+    ) -> String
+    {
+        match AllClocks::parse_clock_time(clock_time)
+        {
+            Some(_) => clock_time.to_string(),
+            None => format!("parse error: {}", clock_time),
+        }
+    }
     pub fn print(
         &self,
         details_enable: &bool,
         leader_hostname: String,
+        config: &Config,
     ) -> Result<()>
     {
         info!("print tablet server clocks");
@@ -168,7 +222,7 @@ impl AllClocks {
 
         if *details_enable
         {
-            println!("{:20} {:20} {:10} {:20} {:26} {:46} {:6} {:10} {:10} {:10}",
+            writeln!(config.output(), "{:20} {:20} {:10} {:20} {:26} {:46} {:6} {:10} {:10} {:10}",
                      "hostname",
                      "server",
                      "HB",
@@ -179,11 +233,11 @@ impl AllClocks {
                      "cloud",
                      "region",
                      "zone"
-            );
+            )?;
         }
         else
         {
-            println!("{:20} {:10} {:20} {:26} {:46} {:6} {:10} {:10} {:10}",
+            writeln!(config.output(), "{:20} {:10} {:20} {:26} {:46} {:6} {:10} {:10} {:10}",
                      "server",
                      "HB",
                      "status uptime",
@@ -193,52 +247,105 @@ impl AllClocks {
                      "cloud",
                      "region",
                      "zone"
-            );
+            )?;
         }
         for row in &self.clocks {
             if row.hostname_port == Some(leader_hostname.clone())
                 && !*details_enable
             {
-                println!("{:20} {:10} {:20} {:26} {:46} {:6} {:10} {:10} {:10}",
+                writeln!(config.output(), "{:20} {:10} {:20} {:26} {:46} {:6} {:10} {:10} {:10}",
                          row.server.split_whitespace().next().unwrap_or_default(),
                          row.time_since_heartbeat,
                          row.status_uptime,
-                         row.physical_time_utc,
-                         row.hybrid_time_utc,
+                         AllClocks::display_clock_time(&row.physical_time_utc),
+                         AllClocks::display_clock_time(&row.hybrid_time_utc),
                          row.heartbeat_rtt,
                          row.cloud,
                          row.region,
                          row.zone
-                );
+                )?;
             }
             if *details_enable
             {
-                println!("{}: {} {} {} {} {} {} {} {} {}", row.hostname_port.as_ref().unwrap(), row.server, row.time_since_heartbeat, row.status_uptime, row.physical_time_utc, row.hybrid_time_utc, row.heartbeat_rtt, row.cloud, row.region, row.zone);
+                writeln!(config.output(), "{}: {} {} {} {} {} {} {} {} {}", row.hostname_port.as_ref().unwrap(), row.server, row.time_since_heartbeat, row.status_uptime, AllClocks::display_clock_time(&row.physical_time_utc), AllClocks::display_clock_time(&row.hybrid_time_utc), row.heartbeat_rtt, row.cloud, row.region, row.zone)?;
             }
         }
+        let (skew, parsed, parse_errors) = self.clock_skew(&leader_hostname);
+        if let Some(skew) = skew
+        {
+            writeln!(config.output(), "Physical time skew: {} ms between {} tablet servers",
+                     utility::format_float(skew.num_microseconds().unwrap_or_default() as f64 / 1000_f64, 0, 3, config),
+                     parsed,
+            )?;
+        }
+        if parse_errors > 0
+        {
+            writeln!(config.output(), "{} tablet server(s) with a physical time that could not be parsed, excluded from the skew", parse_errors)?;
+        }
         Ok(())
     }
     pub async fn print_latency(
         &self,
         details_enable: &bool,
         leader_hostname: String,
+        config: &Config,
     ) -> Result<()>
     {
         info!("print adhoc tablet servers clocks latency");
 
+        if *details_enable
+        {
+            let (destinations, rows) = self.latency_matrix(&leader_hostname);
+            let mut headers = vec!["source"];
+            headers.extend(destinations.iter().map(|destination| destination.as_str()));
+            utility::print_table(&headers, &rows, config)?;
+            return Ok(());
+        }
 
         for row in &self.clocks {
             if row.hostname_port == Some(leader_hostname.clone())
-                && !*details_enable
             {
-                println!("{} -> {}: {} RTT ({} {} {})", leader_hostname.clone(), row.server.split_whitespace().next().unwrap_or_default(), row.heartbeat_rtt, row.cloud, row.region, row.zone);
+                writeln!(config.output(), "{} -> {}: {} RTT ({} {} {})", leader_hostname.clone(), row.server.split_whitespace().next().unwrap_or_default(), row.heartbeat_rtt, row.cloud, row.region, row.zone)?;
             }
-            if *details_enable
+        }
+        Ok(())
+    }
+    /// The heartbeat round trip times as a matrix, with a row per source and a column per destination.
+    ///
+    /// The only latencies available are the heartbeat round trip times from the masters to the tablet servers,
+    /// so the sources are the masters that served `/tablet-server-clocks`, and the destinations the tablet servers.
+    /// This allows comparing the latencies from each master's perspective, instead of the master leader only.
+    /// The master leader is marked with `(leader)`, and a missing pair is shown as `-`.
+    fn latency_matrix(
+        &self,
+        leader_hostname: &str,
+    ) -> (Vec<String>, Vec<Vec<String>>)
+    {
+        let mut latencies: BTreeMap<String, BTreeMap<String, String>> = BTreeMap::new();
+        let mut destinations: BTreeSet<String> = BTreeSet::new();
+        for row in &self.clocks
+        {
+            let source = row.hostname_port.clone().unwrap_or_default();
+            let destination = row.server.split_whitespace().next().unwrap_or_default().to_string();
+            destinations.insert(destination.clone());
+            if !row.heartbeat_rtt.is_empty()
+            {
+                latencies.entry(source).or_default().insert(destination, row.heartbeat_rtt.clone());
+            }
+            else
             {
-                println!("{} {} -> {}: {} RTT ({} {} {})", row.hostname_port.as_ref().unwrap(), leader_hostname.clone(), row.server.split_whitespace().next().unwrap_or_default(), row.heartbeat_rtt, row.cloud, row.region, row.zone);
+                latencies.entry(source).or_default();
             }
         }
-        Ok(())
+        let rows = latencies
+            .iter()
+            .map(|(source, destination_latencies)| {
+                let mut row = vec![if source == leader_hostname { format!("{} (leader)", source) } else { source.clone() }];
+                row.extend(destinations.iter().map(|destination| destination_latencies.get(destination).cloned().unwrap_or_else(|| "-".to_string())));
+                row
+            })
+            .collect();
+        (destinations.into_iter().collect(), rows)
     }
 }
 
@@ -247,20 +354,21 @@ pub async fn print_clocks(
     ports: Vec<&str>,
     parallel: usize,
     options: &Opts,
+    config: &Config,
 ) -> Result<()>
 {
     match options.print_clocks.as_ref().unwrap() {
         Some(snapshot_number) => {
             let mut allclocks = AllClocks::new();
-            allclocks.clocks = snapshot::read_snapshot_json(snapshot_number, "clocks")?;
-            let leader_hostname = AllIsLeader::return_leader_snapshot(snapshot_number)?;
+            allclocks.clocks = snapshot::read_snapshot_json(snapshot_number, "clocks", config)?;
+            let leader_hostname = AllIsLeader::return_leader_snapshot(snapshot_number, config)?;
 
-            allclocks.print(&options.details_enable, leader_hostname)?;
+            allclocks.print(&options.details_enable, leader_hostname, config)?;
         },
         None => {
-            let allclocks = AllClocks::read_clocks(&hosts, &ports, parallel).await?;
-            let leader_hostname = AllIsLeader::return_leader_http(&hosts, &ports, parallel).await;
-            allclocks.print(&options.details_enable, leader_hostname)?;
+            let allclocks = AllClocks::read_clocks(&hosts, &ports, parallel, config).await?;
+            let leader_hostname = AllIsLeader::return_leader_http(&hosts, &ports, parallel, config).await;
+            allclocks.print(&options.details_enable, leader_hostname, config)?;
         },
     }
     Ok(())
@@ -271,21 +379,22 @@ pub async fn print_latencies(
     ports: Vec<&str>,
     parallel: usize,
     options: &Opts,
+    config: &Config,
 ) -> Result<()>
 {
     match options.print_latencies.as_ref().unwrap() {
         Some(snapshot_number) => {
             let mut allclocks = AllClocks::new();
-            allclocks.clocks = snapshot::read_snapshot_json(snapshot_number, "clocks")?;
-            let leader_hostname = AllIsLeader::return_leader_snapshot(snapshot_number)?;
+            allclocks.clocks = snapshot::read_snapshot_json(snapshot_number, "clocks", config)?;
+            let leader_hostname = AllIsLeader::return_leader_snapshot(snapshot_number, config)?;
 
-            allclocks.print_latency(&options.details_enable, leader_hostname).await?;
+            allclocks.print_latency(&options.details_enable, leader_hostname, config).await?;
         },
         None => {
-            let allstoredclocks = AllClocks::read_clocks(&hosts, &ports, parallel).await?;
-            let leader_hostname = AllIsLeader::return_leader_http(&hosts, &ports, parallel).await;
+            let allstoredclocks = AllClocks::read_clocks(&hosts, &ports, parallel, config).await?;
+            let leader_hostname = AllIsLeader::return_leader_http(&hosts, &ports, parallel, config).await;
 
-            allstoredclocks.print_latency(&options.details_enable, leader_hostname).await?;
+            allstoredclocks.print_latency(&options.details_enable, leader_hostname, config).await?;
         },
     }
     Ok(())
@@ -367,13 +476,111 @@ mod tests {
         assert_eq!(result[0].zone, "local1");
     }
 
+    #[test]
+    fn unit_parse_clocks_malformed_data() {
+        // A row with missing columns, and a table without headers, should not panic.
+        let clocks = r#"<table class='table table-striped'>
+  <tr><td>no headers</td></tr>
+</table>
+<table class='table table-striped'>
+    <tr>
+      <th>Server</th>
+      <th>Time since </br>heartbeat</th>
+      <th>Status & Uptime</th>
+      <th>Physical Time (UTC)</th>
+      <th>Hybrid Time (UTC)</th>
+      <th>Heartbeat RTT</th>
+      <th>Cloud</th>
+      <th>Region</th>
+      <th>Zone</th>
+    </tr>
+  <tr>
+  <td><a href="http://yb-1.local:9000/">yb-1.local:9000</a></br>  fa8b3f29b2a54eadb73ae546454ce1bb</td><td>1.0s</td>    <td style="color:Green">ALIVE: 4:20:46</td>    <td>1670945390817063</td>  </tr>
+</table>"#.to_string();
+        let result = AllClocks::parse_clocks(clocks);
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].physical_time_utc, "1670945390817063");
+        assert_eq!(result[0].hybrid_time_utc, "");
+        assert_eq!(result[0].zone, "");
+    }
+
+    #[test]
+    fn unit_parse_clocks_added_column() {
+        // A version that adds a column is parsed by the column names.
+        let clocks = r#"<table class='table table-striped'>
+    <tr>
+      <th>Server</th>
+      <th>Time since </br>heartbeat</th>
+      <th>Status & Uptime</th>
+      <th>Physical Time (UTC)</th>
+      <th>Hybrid Time (UTC)</th>
+      <th>Heartbeat RTT</th>
+      <th>Cloud</th>
+      <th>Region</th>
+      <th>Zone</th>
+      <th>Uptime</th>
+    </tr>
+  <tr>
+  <td><a href="http://yb-1.local:9000/">yb-1.local:9000</a></br>  fa8b3f29b2a54eadb73ae546454ce1bb</td><td>1.0s</td>    <td style="color:Green">ALIVE: 4:20:46</td>    <td>2023-02-02 13:12:21.164655</td>    <td>2023-02-02 13:12:21.164655</td>    <td>0.81ms</td>    <td>local</td>    <td>local</td>    <td>local1</td>    <td>4:20:46</td>  </tr>
+</table>"#.to_string();
+        let result = AllClocks::parse_clocks(clocks);
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].heartbeat_rtt, "0.81ms");
+        assert_eq!(result[0].zone, "local1");
+    }
+
+    #[test]
+    fn unit_clock_skew_excludes_parse_errors() {
+        let mut allclocks = AllClocks::new();
+        for physical_time_utc in ["2022-12-13 15:29:50.817063", "2022-12-13 15:29:50.818563", "1670945390817063", "{ physical: 1670945390817063 }"]
+        {
+            allclocks.clocks.push(Clocks {
+                hostname_port: Some("yb-1.local:7000".to_string()),
+                physical_time_utc: physical_time_utc.to_string(),
+                ..Default::default()
+            });
+        }
+        let (skew, parsed, parse_errors) = allclocks.clock_skew("yb-1.local:7000");
+        assert_eq!(skew, Some(Duration::microseconds(1500)));
+        assert_eq!(parsed, 2);
+        assert_eq!(parse_errors, 2);
+        assert_eq!(AllClocks::display_clock_time("1670945390817063"), "parse error: 1670945390817063");
+        assert_eq!(AllClocks::display_clock_time("2022-12-13 15:29:50.817063"), "2022-12-13 15:29:50.817063");
+    }
+
+    #[test]
+    fn unit_latency_matrix() {
+        let mut allclocks = AllClocks::new();
+        for (source, destination, heartbeat_rtt) in [
+            ("yb-1.local:7000", "yb-1.local:9000 3a8f5e2b", "0.81ms"),
+            ("yb-1.local:7000", "yb-2.local:9000 7c1d9e4a", "12.40ms"),
+            ("yb-2.local:7000", "yb-1.local:9000 3a8f5e2b", "0.95ms"),
+            ("yb-2.local:7000", "yb-2.local:9000 7c1d9e4a", ""),
+        ]
+        {
+            allclocks.clocks.push(Clocks {
+                hostname_port: Some(source.to_string()),
+                server: destination.to_string(),
+                heartbeat_rtt: heartbeat_rtt.to_string(),
+                ..Default::default()
+            });
+        }
+        let (destinations, rows) = allclocks.latency_matrix("yb-1.local:7000");
+        assert_eq!(destinations, vec!["yb-1.local:9000", "yb-2.local:9000"]);
+        assert_eq!(rows, vec![
+            vec!["yb-1.local:7000 (leader)".to_string(), "0.81ms".to_string(), "12.40ms".to_string()],
+            vec!["yb-2.local:7000".to_string(), "0.95ms".to_string(), "-".to_string()],
+        ]);
+    }
+
     #[tokio::test]
     async fn integration_parse_clocks() -> Result<()>
     {
+        let config = &Config::default();
         let hostname = utility::get_hostname_master();
         let port = utility::get_port_master();
 
-        let allclocks = AllClocks::read_clocks(&vec![&hostname], &vec![&port], 1_usize).await?;
+        let allclocks = AllClocks::read_clocks(&vec![&hostname], &vec![&port], 1_usize, config).await?;
         assert!(!allclocks.clocks.is_empty());
 
         Ok(())
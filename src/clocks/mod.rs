@@ -16,6 +16,9 @@
 //!
 //! The `/tablet-server-clocks` endpoint is only available on the masters, default port 7000.
 //!
+//! The heartbeat round trip times are the only latencies available, which `--print-latencies` prints from the master leader to the tablet servers.
+//! With `--details-enable`, these are printed as a matrix of the masters (rows) to the tablet servers (columns), from each master's perspective.
+//!
 //! The clocks functionality is called from:
 //! - [crate::snapshot::perform_snapshot] -> [crate::clocks::AllClocks::perform_snapshot] (general snapshot, saves masters data)
 //! - [crate] -> [print_clocks] (print adhoc (live) or snapshot clocks info)
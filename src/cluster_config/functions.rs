@@ -1,32 +1,47 @@
 //! The impls and functions
 //!
 use chrono::Local;
-use std::{sync::mpsc::channel, time::Instant};
+use std::{sync::mpsc::channel, time::Instant, collections::BTreeMap};
 use log::*;
 use anyhow::{Result, Context};
+use colored::*;
 use crate::isleader::AllIsLeader;
 use crate::utility;
+use crate::utility::Config;
 use crate::snapshot;
-use crate::cluster_config::{AllSysClusterConfigEntryPB, SysClusterConfigEntryPB};
+use crate::cluster_config::{AllSysClusterConfigEntryPB, SysClusterConfigEntryPB, BlacklistPB, BlacklistedServer, ClusterConfigDiff};
+use crate::tablet_servers::AllTabletServers;
 use crate::Opts;
 
 impl SysClusterConfigEntryPB {
     fn new() -> Self { Default::default() }
 }
 
+impl BlacklistPB {
+    /// The hosts in the blacklist as host:port.
+    pub fn hostname_ports(&self) -> Vec<String>
+    {
+        self.hosts
+            .as_ref()
+            .map(|hosts| hosts.iter().map(|hostport| format!("{}:{}", hostport.host, hostport.port)).collect())
+            .unwrap_or_default()
+    }
+}
+
 impl AllSysClusterConfigEntryPB {
     pub async fn perform_snapshot(
         hosts: &Vec<&str>,
         ports: &Vec<&str>,
         snapshot_number: i32,
         parallel: usize,
+        config: &Config,
     ) -> Result<()>
     {
         info!("begin snapshot");
         let timer = Instant::now();
 
-        let allsysclusterconfigentrypb = AllSysClusterConfigEntryPB::read_cluster_config(hosts, ports, parallel).await;
-        snapshot::save_snapshot_json(snapshot_number, "cluster-config", allsysclusterconfigentrypb.sysclusterconfigentrypb)?;
+        let allsysclusterconfigentrypb = AllSysClusterConfigEntryPB::read_cluster_config(hosts, ports, parallel, config).await;
+        snapshot::save_snapshot_json(snapshot_number, "cluster-config", allsysclusterconfigentrypb.sysclusterconfigentrypb, config)?;
 
         info!("end snapshot: {:?}", timer.elapsed());
 
@@ -35,10 +50,30 @@ impl AllSysClusterConfigEntryPB {
     pub fn new() -> Self {
         Default::default()
     }
+    /// The cluster config of each master as text for --text-diff, which is the JSON without the fields added by yb_stats.
+    pub fn text_per_host(&self) -> Result<BTreeMap<String, String>>
+    {
+        let mut text_per_host = BTreeMap::new();
+        for sysclusterconfigentrypb in &self.sysclusterconfigentrypb
+        {
+            let mut cluster_config = serde_json::to_value(sysclusterconfigentrypb)?;
+            if let Some(fields) = cluster_config.as_object_mut()
+            {
+                fields.remove("hostname_port");
+                fields.remove("timestamp");
+            }
+            text_per_host.insert(
+                sysclusterconfigentrypb.hostname_port.clone().expect("hostname:port should be set"),
+                format!("{}\n", serde_json::to_string_pretty(&cluster_config)?),
+            );
+        }
+        Ok(text_per_host)
+    }
     pub async fn read_cluster_config(
         hosts: &Vec<&str>,
         ports: &Vec<&str>,
         parallel: usize,
+        config: &Config,
     ) -> AllSysClusterConfigEntryPB
     {
         info!("begin parallel http read");
@@ -52,9 +87,9 @@ impl AllSysClusterConfigEntryPB {
                     let tx = tx.clone();
                     s.spawn(move |_| {
                         let detail_snapshot_time = Local::now();
-                        let mut cluster_config = AllSysClusterConfigEntryPB::read_http(host, port);
+                        let mut cluster_config = AllSysClusterConfigEntryPB::read_http(host, port, config);
                         cluster_config.timestamp = Some(detail_snapshot_time);
-                        cluster_config.hostname_port = Some(format!("{}:{}", host, port));
+                        cluster_config.hostname_port = Some(utility::hostname_port(host, port, config));
                         tx.send(cluster_config).expect("error sending data via tx");
                     });
                 }
@@ -76,9 +111,10 @@ impl AllSysClusterConfigEntryPB {
     pub fn read_http(
         host: &str,
         port: &str,
+        config: &Config,
     ) -> SysClusterConfigEntryPB
     {
-        let data_from_http = utility::http_get(host, port, "api/v1/cluster-config");
+        let data_from_http = utility::http_get_endpoint(host, port, "cluster_config", config);
         AllSysClusterConfigEntryPB::parse_cluster_config(data_from_http, host, port)
     }
     fn parse_cluster_config(
@@ -94,15 +130,128 @@ impl AllSysClusterConfigEntryPB {
     }
     pub fn print(
         &self,
-        leader_hostname: String
+        leader_hostname: String,
+        config: &Config,
     ) -> Result<()>
     {
 
-        println!("{}", serde_json::to_string_pretty( &self.sysclusterconfigentrypb
+        writeln!(config.output(), "{}", serde_json::to_string_pretty( &self.sysclusterconfigentrypb
             .iter()
             .find(|r| r.hostname_port == Some(leader_hostname.clone()))
             .with_context(|| "Unable to find current master leader")?
-        )?);
+        )?)?;
+        Ok(())
+    }
+    /// The placements (cloud, region, zone) of the live replicas in the cluster config of the master leader.
+    /// A placement field that is not set is returned as an empty string.
+    /// Returns None if the cluster config of the master leader is not found, or has no placement blocks.
+    pub fn configured_placements(
+        &self,
+        leader_hostname: &str,
+    ) -> Option<Vec<(String, String, String)>>
+    {
+        let placement_blocks = self.sysclusterconfigentrypb
+            .iter()
+            .find(|r| r.hostname_port.as_deref() == Some(leader_hostname))?
+            .replication_info
+            .as_ref()?
+            .live_replicas
+            .as_ref()?
+            .placement_blocks
+            .as_ref()?;
+        if placement_blocks.is_empty() {
+            return None;
+        }
+        Some(placement_blocks
+            .iter()
+            .filter_map(|placement_block| placement_block.cloud_info.as_ref())
+            .map(|cloud_info| (
+                cloud_info.placement_cloud.clone().unwrap_or_default(),
+                cloud_info.placement_region.clone().unwrap_or_default(),
+                cloud_info.placement_zone.clone().unwrap_or_default(),
+            ))
+            .collect())
+    }
+    /// The server blacklist and the leader blacklist as host:port in the cluster config of the master leader.
+    /// Both are empty if the cluster config of the master leader is not found.
+    pub fn blacklists(
+        &self,
+        leader_hostname: &str,
+    ) -> (Vec<String>, Vec<String>)
+    {
+        self.sysclusterconfigentrypb
+            .iter()
+            .find(|r| r.hostname_port.as_deref() == Some(leader_hostname))
+            .map(|r| (
+                r.server_blacklist.as_ref().map(BlacklistPB::hostname_ports).unwrap_or_default(),
+                r.leader_blacklist.as_ref().map(BlacklistPB::hostname_ports).unwrap_or_default(),
+            ))
+            .unwrap_or_default()
+    }
+    /// The blacklisted servers in the cluster config of the master leader, joined with the tablet servers as reported by the master leader.
+    ///
+    /// The blacklist contains the RPC port, and the tablet servers are reported with the HTTP port,
+    /// so a tablet server is matched on the host only.
+    pub fn blacklisted_servers(
+        &self,
+        leader_hostname: &str,
+        alltabletservers: &AllTabletServers,
+    ) -> Vec<BlacklistedServer>
+    {
+        let (server_blacklist, leader_blacklist) = self.blacklists(leader_hostname);
+        let blacklists = server_blacklist
+            .into_iter()
+            .map(|hostname_port| ("server", hostname_port))
+            .chain(leader_blacklist.into_iter().map(|hostname_port| ("leader", hostname_port)));
+
+        let mut blacklisted_servers = Vec::new();
+        for (blacklist, hostname_port) in blacklists
+        {
+            let host = hostname_port.rsplit_once(':').map(|(host, _)| host).unwrap_or(&hostname_port);
+            let tablet_server = alltabletservers.tabletservers
+                .iter()
+                .filter(|r| r.hostname_port.as_deref() == Some(leader_hostname))
+                .find(|r| r.tablet_server_hostname_port
+                    .as_deref()
+                    .and_then(|tablet_server| tablet_server.rsplit_once(':'))
+                    .map(|(tablet_server_host, _)| tablet_server_host == host)
+                    .unwrap_or_default()
+                );
+            blacklisted_servers.push(BlacklistedServer {
+                blacklist: blacklist.to_string(),
+                hostname_port: hostname_port.clone(),
+                tablet_server: tablet_server.and_then(|r| r.tablet_server_hostname_port.clone()).unwrap_or_default(),
+                status: tablet_server.map(|r| r.status.clone()).unwrap_or_default(),
+            });
+        }
+        blacklisted_servers
+    }
+    /// Print the blacklisted servers, and the tablet server and its status for each of them.
+    /// A blacklisted server that does not match a tablet server is shown with status "NOT FOUND".
+    pub fn print_blacklists(
+        &self,
+        leader_hostname: &str,
+        alltabletservers: &AllTabletServers,
+        config: &Config,
+    ) -> Result<()>
+    {
+        let blacklisted_servers = self.blacklisted_servers(leader_hostname, alltabletservers);
+        writeln!(config.output(), "{}", "Blacklisted servers:".bold())?;
+        if blacklisted_servers.is_empty()
+        {
+            writeln!(config.output(), "  none")?;
+            return Ok(());
+        }
+        let rows: Vec<Vec<String>> = blacklisted_servers
+            .iter()
+            .map(|r| vec![
+                r.blacklist.clone(),
+                r.hostname_port.clone(),
+                r.tablet_server.clone(),
+                if r.status.is_empty() { "NOT FOUND".to_string() } else { r.status.clone() },
+            ])
+            .collect();
+        utility::print_table(&["blacklist", "hostname_port", "tablet_server", "status"], &rows, config)?;
         Ok(())
     }
 }
@@ -112,27 +261,125 @@ pub async fn print_cluster_config(
     ports: Vec<&str>,
     parallel: usize,
     options: &Opts,
+    config: &Config,
 ) -> Result<()>
 {
     match options.print_cluster_config.as_ref().unwrap() {
         Some(snapshot_number) => {
 
             let mut allsysclusterconfigentrypb = AllSysClusterConfigEntryPB::new();
-            allsysclusterconfigentrypb.sysclusterconfigentrypb = snapshot::read_snapshot_json(snapshot_number, "cluster-config")?;
-            let leader_hostname = AllIsLeader::return_leader_snapshot(snapshot_number)?;
+            allsysclusterconfigentrypb.sysclusterconfigentrypb = snapshot::read_snapshot_json(snapshot_number, "cluster-config", config)?;
+            let leader_hostname = AllIsLeader::return_leader_snapshot(snapshot_number, config)?;
 
-            allsysclusterconfigentrypb.print(leader_hostname)?;
+            allsysclusterconfigentrypb.print(leader_hostname.clone(), config)?;
+            if options.details_enable
+            {
+                let mut alltabletservers = AllTabletServers::new();
+                alltabletservers.tabletservers = snapshot::read_snapshot_json(snapshot_number, "tablet_servers", config)?;
+                allsysclusterconfigentrypb.print_blacklists(&leader_hostname, &alltabletservers, config)?;
+            }
 
         }
         None => {
-            let allsysclusterconfigentrypb = AllSysClusterConfigEntryPB::read_cluster_config(&hosts, &ports, parallel).await;
-            let leader_hostname = AllIsLeader::return_leader_http(&hosts, &ports, parallel).await;
-            allsysclusterconfigentrypb.print(leader_hostname)?;
+            let allsysclusterconfigentrypb = AllSysClusterConfigEntryPB::read_cluster_config(&hosts, &ports, parallel, config).await;
+            let leader_hostname = AllIsLeader::return_leader_http(&hosts, &ports, parallel, config).await;
+            allsysclusterconfigentrypb.print(leader_hostname.clone(), config)?;
+            if options.details_enable
+            {
+                let alltabletservers = AllTabletServers::read_tabletservers(&hosts, &ports, parallel, config).await;
+                allsysclusterconfigentrypb.print_blacklists(&leader_hostname, &alltabletservers, config)?;
+            }
         }
     }
     Ok(())
 }
 
+impl ClusterConfigDiff {
+    pub fn new() -> Self { Default::default() }
+    pub fn snapshot_diff(
+        begin_snapshot: &String,
+        end_snapshot: &String,
+        config: &Config,
+    ) -> Result<ClusterConfigDiff>
+    {
+        let mut clusterconfigdiff = ClusterConfigDiff::new();
+
+        let mut allsysclusterconfigentrypb = AllSysClusterConfigEntryPB::new();
+        allsysclusterconfigentrypb.sysclusterconfigentrypb = snapshot::read_snapshot_json(begin_snapshot, "cluster-config", config)?;
+        let master_leader = AllIsLeader::return_leader_snapshot(begin_snapshot, config)?;
+        clusterconfigdiff.first_snapshot(allsysclusterconfigentrypb, master_leader);
+
+        let mut allsysclusterconfigentrypb = AllSysClusterConfigEntryPB::new();
+        allsysclusterconfigentrypb.sysclusterconfigentrypb = snapshot::read_snapshot_json(end_snapshot, "cluster-config", config)?;
+        let master_leader = AllIsLeader::return_leader_snapshot(end_snapshot, config)?;
+        clusterconfigdiff.second_snapshot(allsysclusterconfigentrypb, master_leader);
+
+        Ok(clusterconfigdiff)
+    }
+    fn first_snapshot(
+        &mut self,
+        allsysclusterconfigentrypb: AllSysClusterConfigEntryPB,
+        master_leader: String,
+    )
+    {
+        self.master_found = !master_leader.is_empty();
+        (self.first_server_blacklist, self.first_leader_blacklist) = allsysclusterconfigentrypb.blacklists(&master_leader);
+    }
+    fn second_snapshot(
+        &mut self,
+        allsysclusterconfigentrypb: AllSysClusterConfigEntryPB,
+        master_leader: String,
+    )
+    {
+        (self.second_server_blacklist, self.second_leader_blacklist) = allsysclusterconfigentrypb.blacklists(&master_leader);
+    }
+    /// The nodes that entered ("+") and left ("-") the server and leader blacklist.
+    fn changes(&self) -> Vec<(&str, &str, &String)>
+    {
+        let mut changes = Vec::new();
+        for (blacklist, first, second) in [
+            ("server", &self.first_server_blacklist, &self.second_server_blacklist),
+            ("leader", &self.first_leader_blacklist, &self.second_leader_blacklist),
+        ]
+        {
+            for hostname_port in first.iter().filter(|r| !second.contains(r))
+            {
+                changes.push(("-", blacklist, hostname_port));
+            }
+            for hostname_port in second.iter().filter(|r| !first.contains(r))
+            {
+                changes.push(("+", blacklist, hostname_port));
+            }
+        }
+        changes
+    }
+    /// Print the changes of the blacklists, and return the number of nodes that are added to or removed from a blacklist.
+    pub fn print(
+        &self,
+        config: &Config,
+    ) -> Result<usize>
+    {
+        if !self.master_found
+        {
+            writeln!(config.output(), "Master leader was not found, skipping cluster-config diff.")?;
+            return Ok(0);
+        }
+        let changes = self.changes();
+        for &(change, blacklist, hostname_port) in &changes
+        {
+            if change == "-"
+            {
+                writeln!(config.output(), "{} Cluster Config: node removed from {} blacklist: {}", change.green(), blacklist, hostname_port)?;
+            }
+            else
+            {
+                writeln!(config.output(), "{} Cluster Config: node added to {} blacklist: {}", change.red(), blacklist, hostname_port)?;
+            }
+        }
+        Ok(changes.len())
+    }
+}
+
 
 
 #[cfg(test)]
@@ -488,12 +735,45 @@ mod tests {
         //assert!(result.cluster_uuid, "6cfdbce0-b98d-4aed-a5ec-372a726258b2");
     }
 
+    #[test]
+    fn unit_blacklisted_servers_and_diff() {
+        let first = r#"[{"hostname_port":"yb-1:7000","timestamp":null,"version":1,"replication_info":null,"server_blacklist":{"hosts":[{"host":"yb-2","port":9100}],"initial_replica_load":3},"cluster_uuid":"u","encryption_info":null,"consumer_registry":null,"leader_blacklist":null}]"#;
+        let second = r#"[{"hostname_port":"yb-1:7000","timestamp":null,"version":2,"replication_info":null,"server_blacklist":{"hosts":[{"host":"yb-3","port":9100}],"initial_replica_load":3},"cluster_uuid":"u","encryption_info":null,"consumer_registry":null,"leader_blacklist":{"hosts":[{"host":"yb-3","port":9100}],"initial_leader_load":1}}]"#;
+        let first = AllSysClusterConfigEntryPB { sysclusterconfigentrypb: serde_json::from_str(first).unwrap() };
+        let second = AllSysClusterConfigEntryPB { sysclusterconfigentrypb: serde_json::from_str(second).unwrap() };
+        let tabletservers = r#"[{"hostname_port":"yb-1:7000","timestamp":null,"tablet_server_hostname_port":"yb-2:9000","time_since_hb":"0.5s","time_since_hb_sec":0.5,"status":"ALIVE","uptime_seconds":45,"ram_used":"0 B","ram_used_bytes":0,"num_sst_files":0,"total_sst_file_size":"0 B","total_sst_file_size_bytes":0,"uncompressed_sst_file_size":"0 B","uncompressed_sst_file_size_bytes":0,"path_metrics":[],"read_ops_per_sec":0,"write_ops_per_sec":0,"user_tablets_total":1,"user_tablets_leaders":0,"system_tablets_total":12,"system_tablets_leaders":0,"active_tablets":13,"cloud":"local","region":"local","zone":"local1"}]"#;
+        let alltabletservers = AllTabletServers { tabletservers: serde_json::from_str(tabletservers).unwrap() };
+
+        let blacklisted_servers = first.blacklisted_servers("yb-1:7000", &alltabletservers);
+        assert_eq!(blacklisted_servers.len(), 1);
+        assert_eq!(blacklisted_servers[0].blacklist, "server");
+        assert_eq!(blacklisted_servers[0].hostname_port, "yb-2:9100");
+        assert_eq!(blacklisted_servers[0].tablet_server, "yb-2:9000");
+        assert_eq!(blacklisted_servers[0].status, "ALIVE");
+        let blacklisted_servers = second.blacklisted_servers("yb-1:7000", &alltabletservers);
+        assert_eq!(blacklisted_servers.len(), 2);
+        assert_eq!(blacklisted_servers[1].blacklist, "leader");
+        assert_eq!(blacklisted_servers[1].status, "");
+        assert!(first.blacklisted_servers("yb-2:7000", &alltabletservers).is_empty());
+
+        let mut clusterconfigdiff = ClusterConfigDiff::new();
+        clusterconfigdiff.first_snapshot(first, "yb-1:7000".to_string());
+        clusterconfigdiff.second_snapshot(second, "yb-1:7000".to_string());
+        let changes = clusterconfigdiff.changes();
+        assert_eq!(changes, vec![
+            ("-", "server", &"yb-2:9100".to_string()),
+            ("+", "server", &"yb-3:9100".to_string()),
+            ("+", "leader", &"yb-3:9100".to_string()),
+        ]);
+    }
+
     #[tokio::test]
     async fn integration_parse_cluster_config() {
+        let config = &Config::default();
         let hostname = utility::get_hostname_master();
         let port = utility::get_port_master();
 
-        let allsysclusterconfigentrypb = AllSysClusterConfigEntryPB::read_cluster_config(&vec![&hostname], &vec![&port], 1).await;
+        let allsysclusterconfigentrypb = AllSysClusterConfigEntryPB::read_cluster_config(&vec![&hostname], &vec![&port], 1, config).await;
 
         assert!(!allsysclusterconfigentrypb.sysclusterconfigentrypb.is_empty());
     }
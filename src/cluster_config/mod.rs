@@ -14,7 +14,8 @@
 //!
 //! The cluster-config functionality is called from:
 //! - [crate::snapshot::perform_snapshot] -> [crate::cluster_config::AllSysClusterConfigEntryPB::perform_snapshot] (general snapshot, saves cluster-config data)
-//! - [crate] -> [print_cluster_config] (prints adhoc (live) or snapshot cluster-config info)
+//! - [crate] -> [print_cluster_config] (prints adhoc (live) or snapshot cluster-config info, and the blacklisted servers with details-enable)
+//! - [crate::snapshot::snapshot_diff] -> [crate::cluster_config::ClusterConfigDiff::snapshot_diff] (shows nodes added to or removed from the blacklists)
 //!
 mod structs;
 mod functions;
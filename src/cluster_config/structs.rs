@@ -441,3 +441,25 @@ pub enum ColocatedTableIdentifierPBValue {
 pub struct QLTupleExpressionPB {
     pub elems: Option<Vec<QLExpressionPB>>,
 }
+/// A server in the server or leader blacklist of the cluster config, joined with the tablet server of the same host.
+#[derive(Debug, Default)]
+pub struct BlacklistedServer {
+    /// "server" or "leader"
+    pub blacklist: String,
+    /// The host:port as set in the blacklist, which is the RPC port.
+    pub hostname_port: String,
+    /// The tablet server (HTTP host:port) as known by the master leader, empty if not found.
+    pub tablet_server: String,
+    /// The status of the tablet server as known by the master leader, empty if not found.
+    pub status: String,
+}
+// diff
+// the first and second vectors hold the host:port entries of the blacklists of the master leader.
+#[derive(Debug, Default)]
+pub struct ClusterConfigDiff {
+    pub first_server_blacklist: Vec<String>,
+    pub second_server_blacklist: Vec<String>,
+    pub first_leader_blacklist: Vec<String>,
+    pub second_leader_blacklist: Vec<String>,
+    pub master_found: bool,
+}
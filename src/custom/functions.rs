@@ -0,0 +1,228 @@
+//! The impls and functions
+//!
+use chrono::Local;
+use std::{collections::HashSet, sync::mpsc::channel, time::Instant};
+use regex::Regex;
+use log::*;
+use anyhow::{Result, Context, bail};
+use crate::utility;
+use crate::utility::Config;
+use crate::snapshot;
+use crate::custom::{CustomEndpoint, CustomResponse, AllCustomResponses};
+use crate::Opts;
+
+impl AllCustomResponses {
+    pub fn new() -> Self {
+        Default::default()
+    }
+    pub async fn perform_snapshot(
+        hosts: &Vec<&str>,
+        ports: &Vec<&str>,
+        snapshot_number: i32,
+        parallel: usize,
+        custom_endpoints: &[CustomEndpoint],
+        config: &Config,
+    ) -> Result<()>
+    {
+        info!("begin snapshot");
+        let timer = Instant::now();
+
+        for custom_endpoint in custom_endpoints
+        {
+            let allcustomresponses = AllCustomResponses::read_custom(hosts, ports, parallel, &custom_endpoint.path, config).await;
+            snapshot::save_snapshot_json(snapshot_number, &custom_data_type(&custom_endpoint.name), allcustomresponses.customresponses, config)?;
+        }
+
+        info!("end snapshot: {:?}", timer.elapsed());
+        Ok(())
+    }
+    pub async fn read_custom(
+        hosts: &Vec<&str>,
+        ports: &Vec<&str>,
+        parallel: usize,
+        path: &str,
+        config: &Config,
+    ) -> AllCustomResponses
+    {
+        info!("begin parallel http read");
+        let timer = Instant::now();
+
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(parallel).build().unwrap();
+        let (tx, rx) = channel();
+        pool.scope(move |s| {
+            for host in hosts {
+                for port in ports {
+                    let tx = tx.clone();
+                    s.spawn(move |_| {
+                        let detail_snapshot_time = Local::now();
+                        let data = AllCustomResponses::read_http(host, port, path, config);
+                        tx.send((utility::hostname_port(host, port, config), detail_snapshot_time, data)).expect("error sending data via tx");
+                    });
+                }
+            }
+        });
+
+        info!("end parallel http read {:?}", timer.elapsed());
+
+        let mut allcustomresponses = AllCustomResponses::new();
+
+        for (hostname_port, detail_snapshot_time, data) in rx
+        {
+            if let Some(data) = data
+            {
+                allcustomresponses.customresponses.push(CustomResponse {
+                    hostname_port: Some(hostname_port),
+                    timestamp: Some(detail_snapshot_time),
+                    data,
+                });
+            }
+        }
+
+        allcustomresponses
+    }
+    fn read_http(
+        host: &str,
+        port: &str,
+        path: &str,
+        config: &Config,
+    ) -> Option<serde_json::Value>
+    {
+        let data_from_http = utility::http_get(host, port, path, config);
+        AllCustomResponses::parse_custom(data_from_http, host, port)
+    }
+    /// Parse the response as JSON, without assuming a schema.
+    /// A response that is not JSON, such as the empty response of a failed request, or the html of a port that does not serve the endpoint, is skipped.
+    fn parse_custom(
+        http_data: String,
+        host: &str,
+        port: &str,
+    ) -> Option<serde_json::Value>
+    {
+        serde_json::from_str(&http_data)
+            .map_err(|e| debug!("({}:{}) could not parse custom endpoint response as JSON: {}", host, port, e))
+            .ok()
+    }
+    pub fn print(
+        &self,
+        hostname_filter: &Regex,
+        config: &Config,
+    ) -> Result<()>
+    {
+        let mut customresponses: Vec<&CustomResponse> = self.customresponses
+            .iter()
+            .filter(|row| hostname_filter.is_match(row.hostname_port.as_deref().unwrap_or_default()))
+            .collect();
+        customresponses.sort_by(|a, b| a.hostname_port.cmp(&b.hostname_port));
+
+        for row in customresponses
+        {
+            writeln!(config.output(), "{} {}", row.hostname_port.as_deref().unwrap_or_default(), row.timestamp.map(|timestamp| utility::format_timestamp(&timestamp, config)).unwrap_or_default())?;
+            writeln!(config.output(), "{}", serde_json::to_string_pretty(&row.data)?)?;
+        }
+        Ok(())
+    }
+}
+
+/// The snapshot data type of a custom endpoint.
+fn custom_data_type(
+    name: &str,
+) -> String
+{
+    format!("custom_{}", name)
+}
+
+/// Validate that the name of a custom endpoint is safe to use in a filename: only letters, digits, `_` and `-`.
+fn validate_custom_name(
+    name: &str,
+) -> Result<()>
+{
+    if name.is_empty() || !name.chars().all(|character| character.is_ascii_alphanumeric() || character == '_' || character == '-')
+    {
+        bail!("Invalid custom endpoint name: {:?}, the name may only contain letters, digits, _ and -", name);
+    }
+    Ok(())
+}
+
+/// Parse and validate the custom endpoints in the form of `name=path`.
+pub fn parse_custom_endpoints(
+    custom_endpoints: &[String],
+) -> Result<Vec<CustomEndpoint>>
+{
+    let mut endpoints = Vec::new();
+    let mut names = HashSet::new();
+    for custom_endpoint in custom_endpoints
+    {
+        let (name, path) = custom_endpoint
+            .split_once('=')
+            .with_context(|| format!("Invalid custom endpoint: {}, expected: name=path", custom_endpoint))?;
+        validate_custom_name(name)?;
+        if path.trim_start_matches('/').is_empty()
+        {
+            bail!("Invalid custom endpoint: {}, the path is empty", custom_endpoint);
+        }
+        if !names.insert(name)
+        {
+            bail!("Duplicate custom endpoint name: {}", name);
+        }
+        endpoints.push(CustomEndpoint { name: name.to_string(), path: path.trim_start_matches('/').to_string() });
+    }
+    Ok(endpoints)
+}
+
+pub async fn print_custom(
+    hosts: Vec<&str>,
+    ports: Vec<&str>,
+    parallel: usize,
+    options: &Opts,
+    config: &Config,
+) -> Result<()>
+{
+    let name = options.print_custom.as_ref().unwrap();
+    validate_custom_name(name)?;
+    let hostname_filter = utility::set_regex(&options.hostname_match);
+    match &options.custom_snapshot
+    {
+        Some(snapshot_number) => {
+            let mut allcustomresponses = AllCustomResponses::new();
+            allcustomresponses.customresponses = snapshot::read_snapshot_json(snapshot_number, &custom_data_type(name), config)
+                .with_context(|| format!("Snapshot {} has no custom endpoint {}, it is only added to a snapshot with --custom-endpoint {}=<path>", snapshot_number, name, name))?;
+            allcustomresponses.print(&hostname_filter, config)?;
+        },
+        None => {
+            let custom_endpoint = parse_custom_endpoints(&options.custom_endpoint)?
+                .into_iter()
+                .find(|custom_endpoint| custom_endpoint.name == *name)
+                .with_context(|| format!("The path of custom endpoint {} is unknown: set it with --custom-endpoint {}=<path>, or print it from a snapshot with --custom-snapshot", name, name))?;
+            let allcustomresponses = AllCustomResponses::read_custom(&hosts, &ports, parallel, &custom_endpoint.path, config).await;
+            allcustomresponses.print(&hostname_filter, config)?;
+        },
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unit_parse_custom_endpoints() {
+        let custom_endpoints = parse_custom_endpoints(&["xcluster_safe_time=/api/v1/xcluster-safe-time".to_string(), "load-move=api/v1/tablet-replication".to_string()]).unwrap();
+        assert_eq!(custom_endpoints, vec![
+            CustomEndpoint { name: "xcluster_safe_time".to_string(), path: "api/v1/xcluster-safe-time".to_string() },
+            CustomEndpoint { name: "load-move".to_string(), path: "api/v1/tablet-replication".to_string() },
+        ]);
+        assert!(parse_custom_endpoints(&["api/v1/health-check".to_string()]).is_err());
+        assert!(parse_custom_endpoints(&["../masters=api/v1/masters".to_string()]).is_err());
+        assert!(parse_custom_endpoints(&["=api/v1/masters".to_string()]).is_err());
+        assert!(parse_custom_endpoints(&["masters=/".to_string()]).is_err());
+        assert!(parse_custom_endpoints(&["masters=api/v1/masters".to_string(), "masters=api/v1/tablet-servers".to_string()]).is_err());
+    }
+
+    #[test]
+    fn unit_parse_custom() {
+        let data = AllCustomResponses::parse_custom(r#"{"master_uuid":"6d2d1ae8e3d04d4c9e1f7ad2d2bd0e18","leader":true}"#.to_string(), "yb-1.local", "7000").unwrap();
+        assert_eq!(data["leader"], serde_json::Value::Bool(true));
+        assert!(AllCustomResponses::parse_custom("<html><body>not found</body></html>".to_string(), "yb-1.local", "7000").is_none());
+        assert!(AllCustomResponses::parse_custom(String::new(), "yb-1.local", "7000").is_none());
+    }
+}
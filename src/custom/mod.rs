@@ -0,0 +1,18 @@
+//! Module for reading custom JSON endpoints on the masters and tablet servers, set with `--custom-endpoint name=path`.
+//!
+//! A custom endpoint allows to add the data of an endpoint that yb_stats has no native support for, such as a new
+//! or undocumented endpoint, to a snapshot. No schema is assumed: the response of every host and port is stored as JSON
+//! into [CustomResponse] in the snapshot as `custom_<name>`, and printed as pretty printed JSON with `--print-custom <name>`.
+//! A response that is not valid JSON, such as from a port that does not serve the endpoint, is not stored.
+//!
+//! The name is used in the filename of the snapshot data, and therefore may only contain letters, digits, `_` and `-`.
+//!
+//! The custom endpoint functionality is called from:
+//! - [crate::snapshot::perform_snapshot] -> [crate::custom::AllCustomResponses::perform_snapshot] (general snapshot, saves the custom endpoints)
+//! - [crate] -> [print_custom] (print adhoc (live) or snapshot custom endpoint data)
+//!
+mod structs;
+mod functions;
+
+pub use structs::*;
+pub use functions::*;
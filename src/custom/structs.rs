@@ -0,0 +1,25 @@
+//! The structs
+//!
+use chrono::{DateTime, Local};
+/// A custom endpoint set with `--custom-endpoint name=path`.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct CustomEndpoint {
+    /// The name, which is used for the snapshot data as `custom_<name>`.
+    pub name: String,
+    /// The http path, without the leading `/`.
+    pub path: String,
+}
+/// The JSON response of a custom endpoint of a host and port, as stored in the snapshot.
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct CustomResponse {
+    /// yb_stats added to allow understanding the source host.
+    pub hostname_port: Option<String>,
+    /// yb_stats added to allow understanding the timestamp.
+    pub timestamp: Option<DateTime<Local>>,
+    /// The response, which is stored as is.
+    pub data: serde_json::Value,
+}
+#[derive(Debug, Default)]
+pub struct AllCustomResponses {
+    pub customresponses: Vec<CustomResponse>,
+}
@@ -1,33 +1,105 @@
 //! The impls and functions
 //!
 use chrono::Local;
-use std::{sync::mpsc::channel, time::Instant};
+use std::{collections::BTreeMap, sync::mpsc::channel, time::Instant};
 use scraper::{Html, Selector};
 use log::*;
 use anyhow::Result;
 use regex::Regex;
 use crate::utility;
+use crate::utility::Config;
 use crate::snapshot;
 use crate::drives::{AllDrives, Drives, Drive};
+use crate::gflags::AllGFlags;
 use crate::Opts;
 
+const DRIVE_HEADERS: [&str; 7] = ["hostname_port", "path", "role", "total_space", "used_space", "used_pct", "warning"];
+
+/// The data and WAL directories of a server from the `fs_data_dirs` and `fs_wal_dirs` gflags, which are comma separated.
+/// If `fs_wal_dirs` is not set, the WAL directories are the data directories.
+#[derive(Debug, Default)]
+struct ServerDirectories {
+    data: Vec<String>,
+    wal: Vec<String>,
+}
+
+impl ServerDirectories {
+    /// The directories per hostname_port, for the servers that have `fs_data_dirs` or `fs_wal_dirs` set.
+    fn from_gflags(gflags: &AllGFlags) -> BTreeMap<String, ServerDirectories>
+    {
+        let directories = |value: &str| value.split(',').map(str::trim).filter(|directory| !directory.is_empty()).map(str::to_string).collect::<Vec<_>>();
+        let mut serverdirectories: BTreeMap<String, ServerDirectories> = BTreeMap::new();
+        for gflag in gflags.gflags.iter().filter(|gflag| gflag.name == "fs_data_dirs" || gflag.name == "fs_wal_dirs")
+        {
+            let Some(hostname_port) = &gflag.hostname_port else { continue };
+            let server = serverdirectories.entry(hostname_port.clone()).or_default();
+            match gflag.name.as_str() {
+                "fs_data_dirs" => server.data = directories(&gflag.value),
+                _ => server.wal = directories(&gflag.value),
+            }
+        }
+        for server in serverdirectories.values_mut().filter(|server| server.wal.is_empty())
+        {
+            server.wal = server.data.clone();
+        }
+        serverdirectories
+    }
+}
+
+/// Whether a directory is on the drive: the drive path itself, or a directory below it.
+fn on_drive(
+    directory: &str,
+    path: &str,
+) -> bool
+{
+    let (directory, path) = (directory.trim_end_matches('/'), path.trim_end_matches('/'));
+    directory == path || directory.starts_with(&format!("{}/", path))
+}
+
 impl Drives {
     pub fn new() -> Self { Default::default() }
 }
+impl Drive {
+    /// The used space as a percentage of the total space, if both sizes can be parsed.
+    pub fn used_pct(&self) -> Option<f64>
+    {
+        match (utility::size_to_bytes(&self.used_space), utility::size_to_bytes(&self.total_space)) {
+            (Some(used), Some(total)) if total > 0.0 => Some(used / total * 100.0),
+            _ => None,
+        }
+    }
+}
 impl AllDrives {
     pub fn new() -> Self { Default::default() }
+    /// The drives of each host as text for the adhoc diff, a line per drive with its used and total space.
+    pub fn text_per_host(&self) -> BTreeMap<String, String>
+    {
+        self.drives
+            .iter()
+            .map(|drives| {
+                let mut lines: Vec<String> = drives.drive
+                    .iter()
+                    .flatten()
+                    .map(|drive| format!("{} used: {} total: {}\n", drive.path, drive.used_space, drive.total_space))
+                    .collect();
+                lines.sort();
+                (drives.hostname_port.clone().expect("hostname:port should be set"), lines.concat())
+            })
+            .collect()
+    }
     pub async fn perform_snapshot(
         hosts: &Vec<&str>,
         ports: &Vec<&str>,
         snapshot_number: i32,
         parallel: usize,
+        config: &Config,
     ) -> Result<()>
     {
         info!("begin snapshot");
         let timer = Instant::now();
 
-        let alldrives = AllDrives::read_drives(hosts, ports, parallel).await;
-        snapshot::save_snapshot_json(snapshot_number, "drives", alldrives.drives)?;
+        let alldrives = AllDrives::read_drives(hosts, ports, parallel, config).await;
+        snapshot::save_snapshot_json(snapshot_number, "drives", alldrives.drives, config)?;
 
         info!("end snapshot: {:?}", timer.elapsed());
 
@@ -37,6 +109,7 @@ impl AllDrives {
         hosts: &Vec<&str>,
         ports: &Vec<&str>,
         parallel: usize,
+        config: &Config,
     ) -> AllDrives
     {
         info!("begin parallel http read");
@@ -51,9 +124,9 @@ impl AllDrives {
                     let tx = tx.clone();
                     s.spawn(move |_| {
                         let detail_snapshot_time = Local::now();
-                        let mut drives = AllDrives::read_http(host, port);
+                        let mut drives = AllDrives::read_http(host, port, config);
                         drives.timestamp = Some(detail_snapshot_time);
-                        drives.hostname_port = Some(format!("{}:{}", host, port));
+                        drives.hostname_port = Some(utility::hostname_port(host, port, config));
                         tx.send(drives).expect("error sending data via tx");
                     });
                 }
@@ -74,9 +147,10 @@ impl AllDrives {
     fn read_http(
         host: &str,
         port: &str,
+        config: &Config,
     ) -> Drives
     {
-        let data_from_http = utility::http_get(host, port, "drives");
+        let data_from_http = utility::http_get_endpoint(host, port, "drives", config);
         AllDrives::parse_drives(data_from_http)
     }
     fn parse_drives(
@@ -122,23 +196,77 @@ impl AllDrives {
         }
         drives
     }
-    pub fn print(
+    /// The rows of the drives table, and the WAL directories that are not on a drive shown by `/drives`.
+    ///
+    /// The role of a drive is whether it holds data and/or WAL directories, based on the gflags of the server.
+    /// A drive that holds WAL is warned about at the lower wal_drive_pct_warn percentage, because a full WAL drive
+    /// stops the writes of all the tablets with WAL on it. Without the gflags, the role is unknown and empty.
+    fn rows(
         &self,
-        hostname_filter: &Regex
-    ) -> Result<()>
+        hostname_filter: &Regex,
+        gflags: &AllGFlags,
+        drive_pct_warn: f64,
+        wal_drive_pct_warn: f64,
+    ) -> (Vec<Vec<String>>, Vec<String>)
     {
+        let serverdirectories = ServerDirectories::from_gflags(gflags);
+        let mut rows = Vec::new();
+        let mut notes = Vec::new();
         for drives in self.drives.iter().filter(|row| hostname_filter.is_match(row.hostname_port.as_ref().unwrap()))
         {
-            for drive in &drives.drive
+            let hostname_port = drives.hostname_port.as_ref().unwrap();
+            let server = serverdirectories.get(hostname_port);
+            for drive in drives.drive.iter().flatten()
             {
-                    println!("{:20} {:40} {:20} {:20}",
-                        drives.hostname_port.as_ref().unwrap(),
-                        drive.as_ref().unwrap().path,
-                        drive.as_ref().unwrap().total_space,
-                        drive.as_ref().unwrap().used_space,
-                    );
+                let data = server.is_some_and(|server| server.data.iter().any(|directory| on_drive(directory, &drive.path)));
+                let wal = server.is_some_and(|server| server.wal.iter().any(|directory| on_drive(directory, &drive.path)));
+                let role = match (data, wal) {
+                    (true, true) => "data,wal",
+                    (true, false) => "data",
+                    (false, true) => "wal",
+                    (false, false) => "",
+                };
+                let used_pct = drive.used_pct();
+                let warning = match used_pct {
+                    Some(used_pct) if wal && used_pct >= wal_drive_pct_warn => format!("WAL drive over {}% used", wal_drive_pct_warn),
+                    Some(used_pct) if used_pct >= drive_pct_warn => format!("over {}% used", drive_pct_warn),
+                    _ => String::new(),
+                };
+                rows.push(vec![
+                    hostname_port.clone(),
+                    drive.path.clone(),
+                    role.to_string(),
+                    drive.total_space.clone(),
+                    drive.used_space.clone(),
+                    used_pct.map(|used_pct| format!("{:.1}", used_pct)).unwrap_or_default(),
+                    warning,
+                ]);
+            }
+            for directory in server.map(|server| server.wal.as_slice()).unwrap_or_default()
+            {
+                if !drives.drive.iter().flatten().any(|drive| on_drive(directory, &drive.path))
+                {
+                    notes.push(format!("{}: WAL directory {} is not on a drive shown by /drives, its space is not checked", hostname_port, directory));
+                }
             }
         }
+        (rows, notes)
+    }
+    pub fn print(
+        &self,
+        hostname_filter: &Regex,
+        gflags: &AllGFlags,
+        drive_pct_warn: f64,
+        wal_drive_pct_warn: f64,
+        config: &Config,
+    ) -> Result<()>
+    {
+        let (rows, notes) = self.rows(hostname_filter, gflags, drive_pct_warn, wal_drive_pct_warn);
+        utility::print_table(&DRIVE_HEADERS, &rows, config)?;
+        for note in notes
+        {
+            writeln!(config.output(), "{}", note)?;
+        }
         Ok(())
     }
 }
@@ -148,26 +276,34 @@ pub async fn print_drives(
     ports: Vec<&str>,
     parallel: usize,
     options: &Opts,
+    config: &Config,
 ) -> Result<()>
 {
     let hostname_filter = utility::set_regex(&options.hostname_match);
-    match options.print_drives.as_ref().unwrap() {
+    let (alldrives, allgflags) = match options.print_drives.as_ref().unwrap() {
         Some(snapshot_number) => {
             let mut alldrives = AllDrives::new();
-            alldrives.drives = snapshot::read_snapshot_json(snapshot_number, "drives")?;
-            alldrives.print(&hostname_filter)?;
+            alldrives.drives = snapshot::read_snapshot_json(snapshot_number, "drives", config)?;
+            // the gflags are only used for the role of the drives, so the drives are printed without them.
+            let mut allgflags = AllGFlags::new();
+            allgflags.gflags = snapshot::read_snapshot_json(snapshot_number, "gflags", config).unwrap_or_else(|error| {
+                warn!("Unable to read the gflags of snapshot {}, the drive roles are unknown: {}", snapshot_number, error);
+                Vec::new()
+            });
+            (alldrives, allgflags)
         },
         None => {
-            let alldrives = AllDrives::read_drives(&hosts, &ports, parallel).await;
-            alldrives.print(&hostname_filter)?;
+            (AllDrives::read_drives(&hosts, &ports, parallel, config).await, AllGFlags::read_gflags(&hosts, &ports, parallel, config).await)
         },
-    }
+    };
+    alldrives.print(&hostname_filter, &allgflags, options.drive_pct_warn, options.wal_drive_pct_warn, config)?;
     Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::gflags::GFlag;
 
     #[test]
     fn unit_parse_drives_simple_one_drive() {
@@ -197,22 +333,68 @@ mod tests {
         assert_eq!(result.drive[0].as_ref().unwrap().total_space, "9.99G");
     }
 
+    #[test]
+    fn unit_drives_text_per_host() {
+        let alldrives = AllDrives { drives: vec![Drives {
+            hostname_port: Some("yb-1:9000".to_string()),
+            drive: vec![
+                Some(Drive { path: "/mnt/d1".to_string(), used_space: "2.00G".to_string(), total_space: "10.00G".to_string() }),
+                Some(Drive { path: "/mnt/d0".to_string(), used_space: "1.00G".to_string(), total_space: "10.00G".to_string() }),
+            ],
+            ..Default::default()
+        }]};
+        assert_eq!(alldrives.text_per_host()["yb-1:9000"], "/mnt/d0 used: 1.00G total: 10.00G\n/mnt/d1 used: 2.00G total: 10.00G\n");
+    }
+
+    #[test]
+    fn unit_drives_rows() {
+        let drives = |hostname_port: &str, drive: Vec<(&str, &str, &str)>| Drives {
+            hostname_port: Some(hostname_port.to_string()),
+            drive: drive.into_iter().map(|(path, used_space, total_space)| Some(Drive { path: path.to_string(), used_space: used_space.to_string(), total_space: total_space.to_string() })).collect(),
+            ..Default::default()
+        };
+        let alldrives = AllDrives { drives: vec![
+            drives("yb-1:9000", vec![("/mnt/d0", "7.50G", "10.00G"), ("/mnt/d1", "8.00G", "10.00G"), ("/mnt/d2", "7.50G", "10.00G")]),
+            drives("yb-2:9000", vec![("/mnt/d0", "7.50G", "10.00G")]),
+        ]};
+        let gflag = |hostname_port: &str, name: &str, value: &str| GFlag { hostname_port: Some(hostname_port.to_string()), name: name.to_string(), value: value.to_string(), ..Default::default() };
+        let allgflags = AllGFlags { gflags: vec![
+            gflag("yb-1:9000", "fs_data_dirs", "/mnt/d0,/mnt/d1"),
+            gflag("yb-1:9000", "fs_wal_dirs", "/mnt/d2/wal,/mnt/wal"),
+            gflag("yb-2:9000", "fs_data_dirs", "/mnt/d0"),
+            gflag("yb-2:9000", "fs_wal_dirs", ""),
+        ]};
+        let (rows, notes) = alldrives.rows(&utility::set_regex(&None), &allgflags, 80.0, 70.0);
+        assert_eq!(rows[0][2..], ["data", "10.00G", "7.50G", "75.0", ""]);
+        assert_eq!(rows[1][2..], ["data", "10.00G", "8.00G", "80.0", "over 80% used"]);
+        assert_eq!(rows[2][2..], ["wal", "10.00G", "7.50G", "75.0", "WAL drive over 70% used"]);
+        // without fs_wal_dirs, the WAL is in the data directories
+        assert_eq!(rows[3][2..], ["data,wal", "10.00G", "7.50G", "75.0", "WAL drive over 70% used"]);
+        assert_eq!(notes, vec!["yb-1:9000: WAL directory /mnt/wal is not on a drive shown by /drives, its space is not checked"]);
+        let (rows, _) = alldrives.rows(&utility::set_regex(&None), &AllGFlags::new(), 80.0, 70.0);
+        assert_eq!(rows[0][2..], ["", "10.00G", "7.50G", "75.0", ""]);
+        assert!(on_drive("/mnt/d0/", "/mnt/d0"));
+        assert!(!on_drive("/mnt/d01", "/mnt/d0"));
+    }
+
     #[tokio::test]
     async fn integration_parse_master_drives() {
+        let config = &Config::default();
         let hostname = utility::get_hostname_master();
         let port = utility::get_port_master();
 
-        let result = AllDrives::read_drives(&vec![&hostname], &vec![&port], 1).await;
+        let result = AllDrives::read_drives(&vec![&hostname], &vec![&port], 1, config).await;
 
         assert!(!result.drives.is_empty());
     }
 
     #[tokio::test]
     async fn integration_parse_tablet_server_drives() {
+        let config = &Config::default();
         let hostname = utility::get_hostname_tserver();
         let port = utility::get_port_tserver();
 
-        let result = AllDrives::read_drives(&vec![&hostname], &vec![&port], 1).await;
+        let result = AllDrives::read_drives(&vec![&hostname], &vec![&port], 1, config).await;
 
         assert!(!result.drives.is_empty());
     }
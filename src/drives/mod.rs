@@ -6,6 +6,9 @@
 //!
 //! This is all in HTML tables.
 //!
+//! The drives are correlated with the `fs_data_dirs` and `fs_wal_dirs` gflags of the server to show whether a drive
+//! holds data and/or WAL directories. The drives holding WAL are warned about at a lower percentage used.
+//!
 //! The `/drives` endpoint is available on:
 //! - the masters, default port 7000
 //! - the tablet servers, default port 9000
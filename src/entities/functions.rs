@@ -134,22 +134,158 @@
 //! to: .colocation.parent.uuid & .colocation.parent.tablename
 //!
 use chrono::Local;
-use std::{time::Instant, sync::mpsc::channel};
+use std::{collections::{BTreeMap, BTreeSet}, time::Instant, sync::mpsc::channel};
 use log::*;
 use regex::Regex;
 use colored::*;
 use anyhow::{Result, bail};
 use crate::isleader::AllIsLeader;
 use crate::utility;
+use crate::utility::Config;
 use crate::snapshot;
-use crate::entities::{Entities, AllEntities, EntitiesDiff, KeyspaceDiff, TablesDiff, TabletsDiff, ReplicasDiff};
+use crate::entities::{Entities, AllEntities, EntitiesDiff, KeyspaceDiff, TablesDiff, TabletsDiff, ReplicasDiff, TabletMovement, TabletMovementDiff};
 use crate::health_check::AllHealthCheck;
+use crate::tablet_detail::AllTablets;
 use crate::Opts;
 
+/// The ycql system, system_schema, system_auth and ysql template1 and template0 keyspaces.
+fn is_system_keyspace(keyspace: &str) -> bool
+{
+    matches!(keyspace, "00000000000000000000000000000001" |   // ycql system
+                       "00000000000000000000000000000002" |   // ycql system_schema
+                       "00000000000000000000000000000003" |   // ycql system_auth
+                       "00000001000030008000000000000000" |   // ysql template1
+                       "000033e5000030008000000000000000")    // ysql template0
+}
+
+/// The object oid number from a table_id.
+fn object_oid_number(oid: &str) -> u32
+{
+    // The oid entry normally is a 32 byte UUID for both ycql and ysql, which only contains hexadecimal numbers.
+    // However, there is a single entry in system_schema that has a table_id that is 'sys.catalog.uuid'
+    if oid.len() == 32_usize {
+        let true_oid = &oid[24..];
+        u32::from_str_radix(true_oid, 16).unwrap_or_default()
+    } else {
+        0
+    }
+}
+
 impl Entities {
     fn new() -> Self {
         Default::default()
     }
+    /// Find the structural orphans:
+    /// - running tablets that have no replica on a live tablet server.
+    /// - running user tables that have no tablets, excluding ysql catalog tables and tables in a colocated database.
+    /// - ycql keyspaces that have no tables.
+    ///   A ysql keyspace without tables is a dropped database, which remains in the keyspaces list.
+    fn anomalies(
+        &self,
+        dead_nodes: &[String],
+    ) -> Vec<String>
+    {
+        let keyspace = |keyspace_id: &str| self.keyspaces
+            .iter()
+            .find(|keyspace| keyspace.keyspace_id == keyspace_id);
+        let mut anomalies = Vec::new();
+
+        for tablet in self.tablets
+            .iter()
+            .filter(|tablet| tablet.state == "RUNNING" && tablet.table_id != "sys.catalog.uuid")
+        {
+            if !tablet.replicas
+                .as_ref()
+                .is_some_and(|replicas| replicas.iter().any(|replica| !dead_nodes.contains(&replica.server_uuid)))
+            {
+                anomalies.push(format!("Tablet without live replica: {} table_id: {}", tablet.tablet_id, tablet.table_id));
+            }
+        }
+
+        for table in self.tables
+            .iter()
+            .filter(|table| table.state == "RUNNING" && !is_system_keyspace(&table.keyspace_id))
+        {
+            let Some(table_keyspace) = keyspace(&table.keyspace_id) else { continue };
+            if table_keyspace.keyspace_type == "ysql"
+                && (object_oid_number(&table.table_id) < 16384
+                    || self.tables
+                        .iter()
+                        .any(|colocated| colocated.keyspace_id == table.keyspace_id && (colocated.table_id.ends_with(".colocated.parent.uuid") || colocated.table_id.ends_with(".colocation.parent.uuid"))))
+            {
+                continue;
+            }
+            if !self.tablets.iter().any(|tablet| tablet.table_id == table.table_id)
+            {
+                anomalies.push(format!("Table without tablets: {}.{}.{} id: {}", table_keyspace.keyspace_type, table_keyspace.keyspace_name, table.table_name, table.table_id));
+            }
+        }
+
+        for keyspace in self.keyspaces
+            .iter()
+            .filter(|keyspace| keyspace.keyspace_type == "ycql" && !is_system_keyspace(&keyspace.keyspace_id))
+        {
+            if !self.tables.iter().any(|table| table.keyspace_id == keyspace.keyspace_id)
+            {
+                anomalies.push(format!("Keyspace without tables: {}.{} id: {}", keyspace.keyspace_type, keyspace.keyspace_name, keyspace.keyspace_id));
+            }
+        }
+        anomalies
+    }
+    /// Reconcile the tablets in the catalog with the tablets that the tablet servers report to host:
+    /// - running tablets in the catalog that no tablet server hosts.
+    /// - tablets hosted by a tablet server that are not in the catalog.
+    ///   A tablet that is shutdown on a tablet server, such as a tombstoned replica after a move, is not considered hosted.
+    ///
+    /// Tablet servers that could not be read do not report tablets, which makes their tablets look not hosted.
+    fn tablet_reconciliation(
+        &self,
+        alltablets: &AllTablets,
+    ) -> Vec<String>
+    {
+        let mut hosted: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+        for tablet in &alltablets.tablet
+        {
+            for tabletbasic in tablet.tabletbasic.iter().filter(|tabletbasic| tabletbasic.state != "SHUTDOWN")
+            {
+                hosted
+                    .entry(tabletbasic.tablet_id.as_str())
+                    .or_default()
+                    .push(tablet.hostname_port.as_deref().unwrap_or_default());
+            }
+        }
+        let mut reconciliation = Vec::new();
+
+        for tablet in self.tablets
+            .iter()
+            .filter(|tablet| tablet.state == "RUNNING" && tablet.table_id != "sys.catalog.uuid")
+            .filter(|tablet| !hosted.contains_key(tablet.tablet_id.as_str()))
+        {
+            reconciliation.push(format!("Tablet in catalog not hosted by a tablet server: {} table_id: {} replicas: {}",
+                tablet.tablet_id,
+                tablet.table_id,
+                tablet.replicas.iter().flatten().map(|replica| replica.addr.as_str()).collect::<Vec<_>>().join(","),
+            ));
+        }
+
+        for (tablet_id, hostname_ports) in hosted
+            .iter()
+            .filter(|(tablet_id, _)| !self.tablets.iter().any(|tablet| tablet.tablet_id == **tablet_id))
+        {
+            let tabletbasic = alltablets.tablet
+                .iter()
+                .flat_map(|tablet| &tablet.tabletbasic)
+                .find(|tabletbasic| tabletbasic.tablet_id == *tablet_id)
+                .expect("hosted tablet should be in the tablet server tablets");
+            reconciliation.push(format!("Tablet hosted but not in catalog: {} table: {}.{} hosted on: {}",
+                tablet_id,
+                tabletbasic.namespace,
+                tabletbasic.table_name,
+                hostname_ports.join(","),
+            ));
+        }
+        reconciliation
+    }
 }
 
 impl AllEntities
@@ -159,13 +295,14 @@ impl AllEntities
         ports: &Vec<&str>,
         snapshot_number: i32,
         parallel: usize,
+        config: &Config,
     ) -> Result<()>
     {
         info!("begin snapshot");
         let timer = Instant::now();
 
-        let allentities = AllEntities::read_entities(hosts, ports, parallel).await;
-        snapshot::save_snapshot_json(snapshot_number,"entities", allentities.entities)?;
+        let allentities = AllEntities::read_entities(hosts, ports, parallel, config).await;
+        snapshot::save_snapshot_json(snapshot_number, "entities", allentities.entities, config)?;
 
         info!("end snapshot: {:?}", timer.elapsed());
 
@@ -178,6 +315,7 @@ impl AllEntities
         hosts: &Vec<&str>,
         ports: &Vec<&str>,
         parallel: usize,
+        config: &Config,
     ) -> AllEntities
     {
         info!("begin parallel http read");
@@ -197,16 +335,16 @@ impl AllEntities
                     let detail_snapshot_time = Local::now();
                     debug!("before read http");
                     let entities = AllStoredEntities::read_http(host.clone(), port.clone()).await;
-                    //tx.send((format!("{}:{}", host, port), detail_snapshot_time, entities)).await.expect("error sending data via tx (entities)");
+                    //tx.send((utility::hostname_port(host, port), detail_snapshot_time, entities)).await.expect("error sending data via tx (entities)");
                     debug!("before send");
-                    tx.send((format!("{}:{}", host, port), detail_snapshot_time, entities)).unwrap();
+                    tx.send((utility::hostname_port(host, port), detail_snapshot_time, entities)).unwrap();
                 });
                 handles.push(handle);
                 /*
                 s.spawn(move |_| {
                     let detail_snapshot_time = Local::now();
                     let entities = AllStoredEntities::read_http(host, port);
-                    tx.send((format!("{}:{}", host, port), detail_snapshot_time, entities)).expect("error sending data via tx (entities)");
+                    tx.send((utility::hostname_port(host, port), detail_snapshot_time, entities)).expect("error sending data via tx (entities)");
                 });
                  */
             }
@@ -222,9 +360,9 @@ impl AllEntities
                     let tx = tx.clone();
                     s.spawn(move |_|  {
                         let detail_snapshot_time = Local::now();
-                        let mut entities = AllEntities::read_http(host, port);
+                        let mut entities = AllEntities::read_http(host, port, config);
                         entities.timestamp = Some(detail_snapshot_time);
-                        entities.hostname_port = Some(format!("{}:{}", host, port));
+                        entities.hostname_port = Some(utility::hostname_port(host, port, config));
                         tx.send(entities).expect("error sending data via tx");
                     });
                 }
@@ -244,9 +382,10 @@ impl AllEntities
     fn read_http(
         host: &str,
         port: &str,
+        config: &Config,
     ) -> Entities
     {
-        let data_from_http = utility::http_get(host, port, "dump-entities");
+        let data_from_http = utility::http_get_endpoint(host, port, "entities", config);
         AllEntities::parse_entities(data_from_http, host, port)
     }
     fn parse_entities(
@@ -261,10 +400,42 @@ impl AllEntities
                 Entities::new()
             })
     }
+    /// Find the tables of the master leader with the name `keyspace.table`, or `table` in any keyspace,
+    /// as (keyspace type, keyspace name, table name, table id), for `--table-name`.
+    ///
+    /// A name can be found multiple times: in a YSQL and a YCQL keyspace with the same name,
+    /// or in different schemas of a YSQL database, therefore all tables are returned.
+    /// Deleted tables are left out.
+    pub fn find_tables(
+        &self,
+        leader_hostname: &str,
+        keyspace_table: &str,
+    ) -> Vec<(String, String, String, String)>
+    {
+        let (keyspace_name, table_name) = match keyspace_table.split_once('.') {
+            Some((keyspace_name, table_name)) => (Some(keyspace_name), table_name),
+            None => (None, keyspace_table),
+        };
+        let mut tables = Vec::new();
+        for entity in self.entities.iter().filter(|entity| entity.hostname_port.as_deref() == Some(leader_hostname))
+        {
+            for table in entity.tables.iter().filter(|table| table.table_name == table_name && !table.state.starts_with("DELET"))
+            {
+                let Some(keyspace) = entity.keyspaces.iter().find(|keyspace| keyspace.keyspace_id == table.keyspace_id) else { continue };
+                if keyspace_name.is_some_and(|keyspace_name| keyspace_name != keyspace.keyspace_name)
+                {
+                    continue;
+                }
+                tables.push((keyspace.keyspace_type.clone(), keyspace.keyspace_name.clone(), table.table_name.clone(), table.table_id.clone()));
+            }
+        }
+        tables
+    }
     pub fn print_coloc_leader_host(
         &self,
         leader_hostname: String,
         colocated_database: &str,
+        config: &Config,
     ) -> Result<()>
     {
         for entity in self.entities.iter()
@@ -319,7 +490,7 @@ impl AllEntities
                                 .collect::<String>();
                             if !leader_host.is_empty()
                             {
-                                println!("{}", leader_host);
+                                writeln!(config.output(), "{}", leader_host)?;
                                 return Ok(())
                             }
                             else
@@ -350,6 +521,7 @@ impl AllEntities
         }
         bail!("Database name not found.")
     }
+    #[allow(clippy::too_many_arguments)]
     pub fn print(
         &self,
         table_name_filter: &Regex,
@@ -358,16 +530,11 @@ impl AllEntities
         hostname_filter: &Regex,
         dead_nodes: Vec<String>,
         under_replicated_tablets: Vec<String>,
+        alltablets: &AllTablets,
+        config: &Config,
     ) -> Result<()>
     {
-        let is_system_keyspace = |keyspace: &str| -> bool {
-            matches!(keyspace, "00000000000000000000000000000001" |   // ycql system
-                               "00000000000000000000000000000002" |   // ycql system_schema
-                               "00000000000000000000000000000003" |   // ycql system_auth
-                               "00000001000030008000000000000000" |   // ysql template1
-                               "000033e5000030008000000000000000")    // ysql template0
-        };
-
+        let mut rows = utility::RowWriter::new(config);
         for entity in self.entities.iter()
         {
             // only pick the leader hostname if details_enable is not set
@@ -410,9 +577,9 @@ impl AllEntities
                     // print keyspace details.
                     if *details_enable
                     {
-                        print!("{} ", entity.hostname_port.clone().unwrap());
+                        write!(rows, "{} ", entity.hostname_port.clone().unwrap())?;
                     }
-                    println!("Keyspace:     {}.{} id: {} {}", row.keyspace_type, row.keyspace_name, row.keyspace_id, colocation);
+                    writeln!(rows, "Keyspace:     {}.{} id: {} {}", row.keyspace_type, row.keyspace_name, row.keyspace_id, colocation)?;
                     if colocation == "[colocated]"
                     {
                         for tablet in entity.tablets
@@ -421,7 +588,7 @@ impl AllEntities
                         {
                             if *details_enable
                             {
-                                print!("{} ", entity.hostname_port.clone().unwrap());
+                                write!(rows, "{} ", entity.hostname_port.clone().unwrap())?;
                             }
                             let under_replication_warning = if under_replicated_tablets
                                 .iter()
@@ -433,19 +600,19 @@ impl AllEntities
                             {
                                 "".yellow()
                             };
-                            println!("  Tablet:     {}.{}.{} state: {} {}",
+                            writeln!(rows, "  Tablet:     {}.{}.{} state: {} {}",
                                      row.keyspace_type,
                                      row.keyspace_name,
                                      tablet.tablet_id,
                                      tablet.state,
                                      under_replication_warning,
-                            );
+                            )?;
                             // replicas
                             if *details_enable
                             {
-                                print!("{} ", entity.hostname_port.clone().unwrap());
+                                write!(rows, "{} ", entity.hostname_port.clone().unwrap())?;
                             }
-                            println!("    Replicas: ({})", tablet.replicas.clone()
+                            writeln!(rows, "    Replicas: ({})", tablet.replicas.clone()
                                 .unwrap_or_default()
                                 .iter()
                                 .map(|r|
@@ -481,28 +648,18 @@ impl AllEntities
                                     })
                                 .collect::<String>()
                                 .trim()
-                            );
+                            )?;
                         }
                     }
                 } else if row.keyspace_type != "ysql"
                 {
                     if *details_enable
                     {
-                        print!("{} ", entity.hostname_port.clone().unwrap());
+                        write!(rows, "{} ", entity.hostname_port.clone().unwrap())?;
                     }
-                    println!("Keyspace:     {}.{} id: {}", row.keyspace_type, row.keyspace_name, row.keyspace_id);
+                    writeln!(rows, "Keyspace:     {}.{} id: {}", row.keyspace_type, row.keyspace_name, row.keyspace_id)?;
                 }
             }
-            let object_oid_number = |oid: &str| -> u32 {
-                // The oid entry normally is a 32 byte UUID for both ycql and ysql, which only contains hexadecimal numbers.
-                // However, there is a single entry in system_schema that has a table_id that is 'sys.catalog.uuid'
-                if oid.len() == 32_usize {
-                    let true_oid = &oid[24..];
-                    u32::from_str_radix(true_oid, 16).unwrap()
-                } else {
-                    0
-                }
-            };
             for row in entity.tables.iter() {
                 if is_system_keyspace(row.keyspace_id.as_str()) && !*details_enable
                 {
@@ -554,9 +711,9 @@ impl AllEntities
                 };
                 if *details_enable
                 {
-                    print!("{} ", entity.hostname_port.clone().unwrap());
+                    write!(rows, "{} ", entity.hostname_port.clone().unwrap())?;
                 }
-                println!("Object:       {}.{}.{}, state: {}, id: {} {}",
+                writeln!(rows, "Object:       {}.{}.{}, state: {}, id: {} {}",
                     &entity.keyspaces
                         .iter()
                         .find(|r| r.keyspace_id == row.keyspace_id)
@@ -571,14 +728,14 @@ impl AllEntities
                     row.state,
                     row.table_id,
                     colocation,
-                );
+                )?;
                 for tablet in entity.tablets
                     .iter()
                     .filter(|r| r.table_id == row.table_id)
                 {
                     if *details_enable
                     {
-                        print!("{} ", entity.hostname_port.clone().unwrap());
+                        write!(rows, "{} ", entity.hostname_port.clone().unwrap())?;
                     }
                     let under_replication_warning = if under_replicated_tablets
                         .iter()
@@ -590,7 +747,7 @@ impl AllEntities
                     {
                         "".yellow()
                     };
-                    println!("  Tablet:     {}.{}.{}.{} state: {} {}",
+                    writeln!(rows, "  Tablet:     {}.{}.{}.{} state: {} {}",
                              &entity.keyspaces
                                  .iter()
                                  .find(|r| r.keyspace_id == row.keyspace_id)
@@ -605,13 +762,13 @@ impl AllEntities
                              tablet.tablet_id,
                              tablet.state,
                              under_replication_warning,
-                    );
+                    )?;
                     // replicas
                     if *details_enable
                     {
-                        print!("{} ", entity.hostname_port.clone().unwrap());
+                        write!(rows, "{} ", entity.hostname_port.clone().unwrap())?;
                     }
-                    println!("    Replicas: ({})", tablet.replicas.clone()
+                    writeln!(rows, "    Replicas: ({})", tablet.replicas.clone()
                         .unwrap_or_default()
                         .iter()
                         .map(|r|
@@ -647,11 +804,39 @@ impl AllEntities
                             })
                         .collect::<String>()
                         .trim()
-                    );
+                    )?;
+                }
+            }
+            // anomalies are shown as a separate section with details_enable.
+            if *details_enable
+            {
+                let anomalies = entity.anomalies(&dead_nodes);
+                rows.header(format_args!("{} {}", entity.hostname_port.clone().unwrap(), "Anomalies:".bold()))?;
+                if anomalies.is_empty()
+                {
+                    writeln!(rows, "{}   none", entity.hostname_port.clone().unwrap())?;
+                }
+                for anomaly in anomalies
+                {
+                    writeln!(rows, "{}   {}", entity.hostname_port.clone().unwrap(), anomaly.yellow())?;
+                }
+                // the reconciliation requires the tablets of the tablet servers, which are not in older snapshots.
+                if !alltablets.tablet.is_empty()
+                {
+                    let reconciliation = entity.tablet_reconciliation(alltablets);
+                    rows.header(format_args!("{} {} (catalog against {} tablet servers)", entity.hostname_port.clone().unwrap(), "Tablet reconciliation:".bold(), alltablets.tablet.len()))?;
+                    if reconciliation.is_empty()
+                    {
+                        writeln!(rows, "{}   none", entity.hostname_port.clone().unwrap())?;
+                    }
+                    for discrepancy in reconciliation
+                    {
+                        writeln!(rows, "{}   {}", entity.hostname_port.clone().unwrap(), discrepancy.yellow())?;
+                    }
                 }
             }
         };
-        Ok(())
+        rows.finish()
     }
 }
 
@@ -660,18 +845,19 @@ impl EntitiesDiff {
     pub fn snapshot_diff(
         begin_snapshot: &String,
         end_snapshot: &String,
+        config: &Config,
     ) -> Result<EntitiesDiff>
     {
         let mut entitiesdiff = EntitiesDiff::new();
 
         let mut allentities = AllEntities::new();
-        allentities.entities = snapshot::read_snapshot_json(begin_snapshot, "entities")?;
-        let master_leader = AllIsLeader::return_leader_snapshot(begin_snapshot)?;
+        allentities.entities = snapshot::read_snapshot_json(begin_snapshot, "entities", config)?;
+        let master_leader = AllIsLeader::return_leader_snapshot(begin_snapshot, config)?;
         entitiesdiff.first_snapshot(allentities, master_leader);
 
         let mut allentities = AllEntities::new();
-        allentities.entities = snapshot::read_snapshot_json(end_snapshot, "entities")?;
-        let master_leader = AllIsLeader::return_leader_snapshot(begin_snapshot)?;
+        allentities.entities = snapshot::read_snapshot_json(end_snapshot, "entities", config)?;
+        let master_leader = AllIsLeader::return_leader_snapshot(begin_snapshot, config)?;
         entitiesdiff.second_snapshot(allentities, master_leader);
 
         Ok(entitiesdiff)
@@ -843,33 +1029,69 @@ impl EntitiesDiff {
         &mut self,
         hosts: &Vec<&str>,
         ports: &Vec<&str>,
-        parallel: usize
+        parallel: usize,
+        config: &Config,
     )
     {
-        let allentities = AllEntities::read_entities(hosts, ports, parallel).await;
-        let master_leader= AllIsLeader::return_leader_http(hosts, ports, parallel).await;
+        let allentities = AllEntities::read_entities(hosts, ports, parallel, config).await;
+        let master_leader= AllIsLeader::return_leader_http(hosts, ports, parallel, config).await;
         self.first_snapshot(allentities, master_leader);
     }
     pub async fn adhoc_read_second_snapshot(
         &mut self,
         hosts: &Vec<&str>,
         ports: &Vec<&str>,
-        parallel: usize
+        parallel: usize,
+        config: &Config,
     )
     {
-        let allentities = AllEntities::read_entities(hosts, ports, parallel).await;
-        let master_leader= AllIsLeader::return_leader_http(hosts, ports, parallel).await;
+        let allentities = AllEntities::read_entities(hosts, ports, parallel, config).await;
+        let master_leader= AllIsLeader::return_leader_http(hosts, ports, parallel, config).await;
         self.second_snapshot(allentities, master_leader);
     }
+    /// Return the tables for which the number of running tablets changed between the snapshots,
+    /// which is what happens with tablet splitting and merging.
+    /// The tables must exist in both snapshots, and have running tablets in both snapshots.
+    ///
+    /// The key is the table_id, the value is the (first, second) number of running tablets.
+    fn tablet_count_changes(
+        &self,
+    ) -> BTreeMap<String, (usize, usize)>
+    {
+        let mut tablet_count_changes = BTreeMap::new();
+        for (table_id, table_row) in &self.btreetablesdiff
+        {
+            if table_row.first_table_name.is_empty() || table_row.second_table_name.is_empty()
+            {
+                continue;
+            }
+            let first_tablet_count = self.btreetabletsdiff
+                .values()
+                .filter(|tablet_row| tablet_row.first_table_id == *table_id && tablet_row.first_state == "RUNNING")
+                .count();
+            let second_tablet_count = self.btreetabletsdiff
+                .values()
+                .filter(|tablet_row| tablet_row.second_table_id == *table_id && tablet_row.second_state == "RUNNING")
+                .count();
+            if first_tablet_count > 0 && second_tablet_count > 0 && first_tablet_count != second_tablet_count
+            {
+                tablet_count_changes.insert(table_id.clone(), (first_tablet_count, second_tablet_count));
+            }
+        }
+        tablet_count_changes
+    }
+    /// Print the changes of the entities, and return the number of changes: the added, removed and changed databases, objects, tablets and replicas.
     pub fn print(
         &self,
-    )
+        config: &Config,
+    ) -> Result<usize>
     {
         debug!("entering print function");
         if !self.master_found {
-            println!("Master leader was not found in hosts specified, skipping entity diff.");
-            return;
+            writeln!(config.output(), "Master leader was not found in hosts specified, skipping entity diff.")?;
+            return Ok(0);
         }
+        let mut rows = utility::RowWriter::new(config);
         //let is_system_keyspace = |keyspace: &str| -> bool {
         //    matches!(keyspace, "00000000000000000000000000000001" |   // ycql system
         //                       "00000000000000000000000000000002" |   // ycql system_schema
@@ -913,13 +1135,13 @@ impl EntitiesDiff {
                         } else {
                             ""
                         };
-                        println!("{} Database: {}.{}, id: {} {}",
+                        writeln!(rows, "{} Database: {}.{}, id: {} {}",
                                  "-".to_string().red(),
                                  keyspace_row.first_keyspace_type,
                                  keyspace_row.first_keyspace_name,
                                  keyspace_id,
                                  colocation
-                        );
+                        )?;
                     } else if first_snapshot_table_count == 0 && second_snapshot_table_count > 0
                     // the first table count is 0 and the second table count is > 0: the deleted database got undeleted (?)
                     // this is not possible, hence error.
@@ -950,13 +1172,13 @@ impl EntitiesDiff {
                 } else {
                     ""
                 };
-                println!("{} Database: {}.{}, id: {} {}",
+                writeln!(rows, "{} Database: {}.{}, id: {} {}",
                          "+".to_string().green(),
                          keyspace_row.second_keyspace_type,
                          keyspace_row.second_keyspace_name,
                          keyspace_id,
                          colocation
-                );
+                )?;
             }
             // the second snapshot fields are empty, which means the first snapshot fields are filled out:
             // this is a removed keyspace.
@@ -973,13 +1195,13 @@ impl EntitiesDiff {
                 } else {
                     ""
                 };
-                println!("{} Database: {}.{}, id: {} {}",
+                writeln!(rows, "{} Database: {}.{}, id: {} {}",
                          "-".to_string().red(),
                          keyspace_row.first_keyspace_type,
                          keyspace_row.first_keyspace_name,
                          keyspace_id,
                          colocation
-                );
+                )?;
             } else {
                 // at this point the fields between first and second snapshot are not alike.
                 // this leaves one option: the keyspace name has changed.
@@ -992,14 +1214,14 @@ impl EntitiesDiff {
                 } else {
                     ""
                 };
-                println!("{} Database: {}.{}->{}, id: {} {}",
+                writeln!(rows, "{} Database: {}.{}->{}, id: {} {}",
                          "=".to_string().yellow(),
                          keyspace_row.first_keyspace_type,
                          keyspace_row.first_keyspace_name.yellow(),
                          keyspace_row.second_keyspace_name.yellow(),
                          keyspace_id,
                          colocation
-                );
+                )?;
             }
         }
         let object_oid_number = |oid: &str| -> u32 {
@@ -1064,7 +1286,7 @@ impl EntitiesDiff {
                 {
                     ""
                 };
-                println!("{} Object:   {}.{}.{}, state: {}, id: {} {}",
+                writeln!(rows, "{} Object:   {}.{}.{}, state: {}, id: {} {}",
                         "+".to_string().green(),
                         &self.btreekeyspacediff
                             .get(&table_row.second_keyspace_id)
@@ -1078,7 +1300,7 @@ impl EntitiesDiff {
                         table_row.second_state,
                         table_id,
                         colocation,
-                );
+                )?;
             }
             // second snapshot fields are emtpy, which means first snapshot fields are filled out:
             // this is a removed object.
@@ -1116,7 +1338,7 @@ impl EntitiesDiff {
                 {
                     ""
                 };
-                println!("{} Object:   {}.{}.{}, state: {}, id: {} {}",
+                writeln!(rows, "{} Object:   {}.{}.{}, state: {}, id: {} {}",
                         "-".to_string().red(),
                         &self.btreekeyspacediff
                             .get(&table_row.first_keyspace_id)
@@ -1130,7 +1352,7 @@ impl EntitiesDiff {
                         table_row.first_state,
                         table_id,
                         colocation,
-                );
+                )?;
             } else {
                 // at this point the table properties are not alike.
                 //
@@ -1153,7 +1375,7 @@ impl EntitiesDiff {
                 {
                     ""
                 };
-                print!("{} Object:   {}.{}.",
+                write!(rows, "{} Object:   {}.{}.",
                          "=".to_string().yellow(),
                          &self.btreekeyspacediff
                              .get(&table_row.first_keyspace_id)
@@ -1163,31 +1385,74 @@ impl EntitiesDiff {
                              .get(&table_row.first_keyspace_id)
                              .map(|r| r.first_keyspace_name.clone())
                              .unwrap_or_default(),
-                );
+                )?;
                 // table name different (alter table rename to)
                 if table_row.first_table_name != table_row.second_table_name
                 {
-                    print!("{}->{}, ", table_row.first_table_name.yellow(), table_row.second_table_name.yellow());
+                    write!(rows, "{}->{}, ", table_row.first_table_name.yellow(), table_row.second_table_name.yellow())?;
                 }
                 else
                 {
-                    print!("{}, ", table_row.first_table_name);
+                    write!(rows, "{}, ", table_row.first_table_name)?;
                 }
                 if table_row.first_state != table_row.second_state
                 {
-                    print!("state: {}->{}, ", table_row.first_state.yellow(), table_row.second_state.yellow());
+                    write!(rows, "state: {}->{}, ", table_row.first_state.yellow(), table_row.second_state.yellow())?;
                 }
                 else
                 {
-                    print!("state: {}, ", table_row.first_state);
+                    write!(rows, "state: {}, ", table_row.first_state)?;
                 }
-                println!("id: {} {}",
+                writeln!(rows, "id: {} {}",
                          table_id,
                          colocation,
-                );
+                )?;
             }
         }
+        // tables for which the tablet count changed are shown as split or merged,
+        // instead of showing each added and removed tablet.
+        let tablet_count_changes = self.tablet_count_changes();
+        for (table_id, (first_tablet_count, second_tablet_count)) in &tablet_count_changes
+        {
+            let table_row = self.btreetablesdiff.get(table_id).expect("table_id should be in btreetablesdiff");
+            let split_or_merge = if second_tablet_count > first_tablet_count
+            {
+                format!("split (+{} tablets)", second_tablet_count - first_tablet_count)
+            }
+            else
+            {
+                format!("merged (-{} tablets)", first_tablet_count - second_tablet_count)
+            };
+            writeln!(rows, "{} Object:   {}.{}.{}, {}, tablets: {}->{}, id: {}",
+                     "=".to_string().yellow(),
+                     &self.btreekeyspacediff
+                         .get(&table_row.second_keyspace_id)
+                         .map(|r| r.second_keyspace_type.clone())
+                         .unwrap_or_default(),
+                     &self.btreekeyspacediff
+                         .get(&table_row.second_keyspace_id)
+                         .map(|r| r.second_keyspace_name.clone())
+                         .unwrap_or_default(),
+                     table_row.second_table_name,
+                     split_or_merge.yellow(),
+                     first_tablet_count,
+                     second_tablet_count,
+                     table_id,
+            )?;
+        }
+        // a tablet that is added or removed for a table that is split or merged.
+        let is_split_or_merged_tablet = |tablet_id: &String| -> bool {
+            self.btreetabletsdiff
+                .get(tablet_id)
+                .map(|tablet_row| (tablet_row.first_table_id.is_empty() && tablet_count_changes.contains_key(&tablet_row.second_table_id))
+                    || (tablet_row.second_table_id.is_empty() && tablet_count_changes.contains_key(&tablet_row.first_table_id)))
+                .unwrap_or_default()
+        };
         for (tablet_id, tablet_row) in &self.btreetabletsdiff {
+            if is_split_or_merged_tablet(tablet_id)
+            {
+                continue;
+            }
             if tablet_row.first_table_id == tablet_row.second_table_id
                 && tablet_row.first_state == tablet_row.second_state
                 && tablet_row.first_leader == tablet_row.second_leader
@@ -1202,7 +1467,7 @@ impl EntitiesDiff {
                 && tablet_row.first_state.is_empty()
                 && tablet_row.first_leader.is_empty()
             {
-                println!("{} Tablet:   {}.{}.{}.{}, state: {}, leader: {}",
+                writeln!(rows, "{} Tablet:   {}.{}.{}.{}, state: {}, leader: {}",
                     "+".to_string().green(),
                     self.btreetablesdiff
                         .get(&tablet_row.second_table_id)
@@ -1233,7 +1498,7 @@ impl EntitiesDiff {
                         .find(|((replica_tablet_id, replica_server_uuid), _replicadiff)| replica_tablet_id == tablet_id && replica_server_uuid.clone() == tablet_row.second_leader )
                         .map(|((_replica_tablet_id, _replica_server_uuid), replicadiff)| replicadiff.second_addr.clone())
                         .unwrap_or_default()
-                );
+                )?;
             }
             // second snapshot fields are empty, which means first snapshot fields are filled out:
             // this is a deleted tablet object.
@@ -1241,7 +1506,7 @@ impl EntitiesDiff {
                 && tablet_row.second_state.is_empty()
                 && tablet_row.second_leader.is_empty()
             {
-                println!("{} Tablet:   {}.{}.{}.{}, state: {}, leader: {}",
+                writeln!(rows, "{} Tablet:   {}.{}.{}.{}, state: {}, leader: {}",
                          "-".to_string().red(),
                          self.btreetablesdiff
                              .get(&tablet_row.first_table_id)
@@ -1272,10 +1537,10 @@ impl EntitiesDiff {
                              .find(|((replica_tablet_id, replica_server_uuid), _replicadiff)| replica_tablet_id == tablet_id && replica_server_uuid.clone() == tablet_row.first_leader )
                              .map(|((_replica_tablet_id, _replica_server_uuid), replicadiff)| replicadiff.first_addr.clone())
                              .unwrap_or_default()
-                );
+                )?;
             } else {
                 // at this point we know the tablets are not alike, but not added or removed.
-                print!("{} Tablet:   {}.{}.{}.{}, ",
+                write!(rows, "{} Tablet:   {}.{}.{}.{}, ",
                     "=".to_string().yellow(),
                     self.btreetablesdiff
                         .get(&tablet_row.second_table_id)
@@ -1300,18 +1565,18 @@ impl EntitiesDiff {
                         .map(|r| r.second_table_name.clone())
                         .unwrap_or_default(),
                     tablet_id,
-                );
+                )?;
                 if tablet_row.first_state != tablet_row.second_state
                 {
-                    print!("state: {}->{}, ", tablet_row.first_state.yellow(), tablet_row.second_state.yellow());
+                    write!(rows, "state: {}->{}, ", tablet_row.first_state.yellow(), tablet_row.second_state.yellow())?;
                 }
                 else
                 {
-                    print!("state: {}", tablet_row.second_state);
+                    write!(rows, "state: {}", tablet_row.second_state)?;
                 }
                 if tablet_row.first_leader != tablet_row.second_leader
                 {
-                    println!(" leader: {}->{}",
+                    writeln!(rows, " leader: {}->{}",
                              self.btreereplicasdiff
                                  .iter()
                                  .find(|((replica_tablet_id, replica_server_uuid), _replicadiff)| replica_tablet_id == tablet_id && replica_server_uuid.clone() == tablet_row.first_leader )
@@ -1324,22 +1589,26 @@ impl EntitiesDiff {
                                  .map(|((_, _), replicadiff)| replicadiff.second_addr.clone())
                                  .unwrap_or_default()
                                  .yellow(),
-                    );
+                    )?;
                 }
                 else
                 {
-                    println!(" leader: {}",
+                    writeln!(rows, " leader: {}",
                              self.btreereplicasdiff
                                  .iter()
                                  .find(|((replica_tablet_id, replica_server_uuid), _replicadiff)| replica_tablet_id == tablet_id && replica_server_uuid.clone() == tablet_row.second_leader )
                                  .map(|((_, _), replicadiff)| replicadiff.second_addr.clone())
                                  .unwrap_or_default(),
-                    );
+                    )?;
                 }
             }
         }
         for ((tablet_id, _), replica_row) in &self.btreereplicasdiff
         {
+            if is_split_or_merged_tablet(tablet_id)
+            {
+                continue;
+            }
             if replica_row.first_replica_type == replica_row.second_replica_type
                 && replica_row.first_addr == replica_row.second_addr
             {
@@ -1350,7 +1619,7 @@ impl EntitiesDiff {
             if replica_row.first_replica_type.is_empty()
                 && replica_row.first_addr.is_empty()
             {
-                println!("{} Replica:  {}:{}.{}.{}.{}, Type: {}",
+                writeln!(rows, "{} Replica:  {}:{}.{}.{}.{}, Type: {}",
                     "+".to_string().green(),
                     replica_row.second_addr,
                     self.btreetabletsdiff
@@ -1392,13 +1661,13 @@ impl EntitiesDiff {
                         .unwrap_or_default(),
                     tablet_id,
                     replica_row.second_replica_type,
-                );
+                )?;
             }
             // if the second replica info is empty, it means a replica was removed.
             else if replica_row.second_replica_type.is_empty()
                 && replica_row.second_addr.is_empty()
             {
-                println!("{} Replica:  {}:{}.{}.{}.{}, Type: {}",
+                writeln!(rows, "{} Replica:  {}:{}.{}.{}.{}, Type: {}",
                          "-".to_string().red(),
                          replica_row.first_addr,
                          self.btreetabletsdiff
@@ -1440,29 +1709,29 @@ impl EntitiesDiff {
                              .unwrap_or_default(),
                          tablet_id,
                          replica_row.first_replica_type,
-                );
+                )?;
             }
             else
             {
                 // the entries have changed?
                 //println!("{} Replica: {}.{}.{}.{}.{}, Type: {}",
-                print!("{} Replica: ",
+                write!(rows, "{} Replica: ",
                     "=".to_string().yellow(),
-                );
+                )?;
                 if replica_row.first_addr != replica_row.second_addr
                 {
-                    print!("{}->{}:",
+                    write!(rows, "{}->{}:",
                         replica_row.first_addr.yellow(),
                         replica_row.second_addr.yellow(),
-                    );
+                    )?;
                 }
                 else
                 {
-                    print!("{}:",
+                    write!(rows, "{}:",
                         replica_row.second_addr,
-                    );
+                    )?;
                 }
-                print!("{}.{}.{}.{}, ",
+                write!(rows, "{}.{}.{}.{}, ",
                     self.btreetabletsdiff
                         .get(&tablet_id.clone())
                         .map(|tablet| {
@@ -1501,36 +1770,219 @@ impl EntitiesDiff {
                         } )
                         .unwrap_or_default(),
                     tablet_id,
-                );
+                )?;
                 if replica_row.first_replica_type != replica_row.second_replica_type
                 {
-                    println!("Type: {}->{}",
+                    writeln!(rows, "Type: {}->{}",
                     replica_row.first_replica_type.yellow(),
                     replica_row.second_replica_type.yellow(),
-                    );
+                    )?;
                 }
                 else
                 {
-                    println!("Type: {}", replica_row.second_replica_type);
+                    writeln!(rows, "Type: {}", replica_row.second_replica_type)?;
                 };
             };
         }
+        let changes = rows.rows();
+        rows.finish()?;
+        Ok(changes)
     }
 }
 
 pub async fn entity_diff(
     options: &Opts,
+    config: &Config,
 ) -> Result<()>
 {
     info!("entity diff");
     if options.begin.is_none() || options.end.is_none() {
-        snapshot::Snapshot::print()?;
+        snapshot::Snapshot::print(config)?;
     }
     if options.snapshot_list { return Ok(()) };
     let (begin_snapshot, end_snapshot, _begin_snapshot_row) = snapshot::Snapshot::read_begin_end_snapshot_from_user(options.begin, options.end)?;
+    snapshot::Snapshot::print_diff_header(&begin_snapshot, &end_snapshot, config)?;
 
-    let entity_diff = EntitiesDiff::snapshot_diff(&begin_snapshot, &end_snapshot)?;
-    entity_diff.print();
+    let entity_diff = EntitiesDiff::snapshot_diff(&begin_snapshot, &end_snapshot, config)?;
+    let changes = entity_diff.print(config)?;
+    utility::add_diff_changes(changes, config);
+
+    Ok(())
+}
+
+impl TabletMovementDiff {
+    pub fn new() -> Self { Default::default() }
+    pub fn snapshot_diff(
+        begin_snapshot: &String,
+        end_snapshot: &String,
+        config: &Config,
+    ) -> Result<TabletMovementDiff>
+    {
+        let mut tabletmovementdiff = TabletMovementDiff::new();
+
+        let mut allentities = AllEntities::new();
+        allentities.entities = snapshot::read_snapshot_json(begin_snapshot, "entities", config)?;
+        let master_leader = AllIsLeader::return_leader_snapshot(begin_snapshot, config)?;
+        tabletmovementdiff.add_snapshot(&allentities, &master_leader, true);
+
+        let mut allentities = AllEntities::new();
+        allentities.entities = snapshot::read_snapshot_json(end_snapshot, "entities", config)?;
+        let master_leader = AllIsLeader::return_leader_snapshot(end_snapshot, config)?;
+        tabletmovementdiff.add_snapshot(&allentities, &master_leader, false);
+
+        Ok(tabletmovementdiff)
+    }
+    /// Add the replica locations of the tablets from the entities of the master leader, as the first or the second snapshot.
+    fn add_snapshot(
+        &mut self,
+        allentities: &AllEntities,
+        master_leader: &str,
+        first: bool,
+    )
+    {
+        let Some(entities) = allentities.entities
+            .iter()
+            .find(|entity| entity.hostname_port.as_deref() == Some(master_leader))
+        else {
+            self.master_found = false;
+            return;
+        };
+        self.master_found = first || self.master_found;
+        for tablet in &entities.tablets
+        {
+            let table_name = entities.tables
+                .iter()
+                .find(|table| table.table_id == tablet.table_id)
+                .map(|table| match entities.keyspaces.iter().find(|keyspace| keyspace.keyspace_id == table.keyspace_id) {
+                    Some(keyspace) => format!("{}.{}", keyspace.keyspace_name, table.table_name),
+                    None => table.table_name.clone(),
+                })
+                .unwrap_or_default();
+            let replicas: BTreeSet<String> = tablet.replicas
+                .iter()
+                .flatten()
+                .map(|replica| replica.addr.clone())
+                .collect();
+            let tabletmovement = self.btreetabletmovement.entry(tablet.tablet_id.clone()).or_default();
+            tabletmovement.table_name = table_name;
+            if first { tabletmovement.first_replicas = replicas } else { tabletmovement.second_replicas = replicas };
+        }
+    }
+    /// The tablets that are in both snapshots with changed replica locations, with the tablet servers the replicas moved from and to,
+    /// sorted by the number of replicas that moved, most first.
+    /// A tablet that is created or deleted, such as by a split or a drop, did not move.
+    fn moved_tablets(
+        &self,
+        table_name_filter: &Regex,
+    ) -> Vec<(&String, &TabletMovement, Vec<&String>, Vec<&String>)>
+    {
+        let mut moved_tablets: Vec<_> = self.btreetabletmovement
+            .iter()
+            .filter(|(_, movement)| !movement.first_replicas.is_empty()
+                && !movement.second_replicas.is_empty()
+                && movement.first_replicas != movement.second_replicas
+                && table_name_filter.is_match(&movement.table_name))
+            .map(|(tablet_id, movement)| (
+                tablet_id,
+                movement,
+                movement.first_replicas.difference(&movement.second_replicas).collect::<Vec<_>>(),
+                movement.second_replicas.difference(&movement.first_replicas).collect::<Vec<_>>(),
+            ))
+            .collect();
+        moved_tablets.sort_by_key(|(_, _, moved_from, moved_to)| std::cmp::Reverse(moved_from.len().max(moved_to.len())));
+        moved_tablets
+    }
+    /// The rows per tablet server: the number of replicas in the begin and end snapshot, the number of moved tablets it gained and lost, and the net change,
+    /// sorted by the number of moved tablets, most first.
+    fn tablet_server_rows(
+        &self,
+        table_name_filter: &Regex,
+    ) -> Vec<Vec<String>>
+    {
+        // tablet server: (replicas first, replicas second, gained, lost)
+        let mut tablet_servers: BTreeMap<&String, (usize, usize, usize, usize)> = BTreeMap::new();
+        for movement in self.btreetabletmovement.values().filter(|movement| table_name_filter.is_match(&movement.table_name))
+        {
+            movement.first_replicas.iter().for_each(|addr| tablet_servers.entry(addr).or_default().0 += 1);
+            movement.second_replicas.iter().for_each(|addr| tablet_servers.entry(addr).or_default().1 += 1);
+        }
+        for (_, _, moved_from, moved_to) in self.moved_tablets(table_name_filter)
+        {
+            moved_from.into_iter().for_each(|addr| tablet_servers.entry(addr).or_default().3 += 1);
+            moved_to.into_iter().for_each(|addr| tablet_servers.entry(addr).or_default().2 += 1);
+        }
+        let mut tablet_servers: Vec<_> = tablet_servers.into_iter().collect();
+        tablet_servers.sort_by_key(|(_, (_, _, gained, lost))| std::cmp::Reverse(gained + lost));
+        tablet_servers
+            .into_iter()
+            .map(|(addr, (first, second, gained, lost))| vec![
+                addr.clone(),
+                first.to_string(),
+                second.to_string(),
+                gained.to_string(),
+                lost.to_string(),
+                format!("{:+}", gained as i64 - lost as i64),
+            ])
+            .collect()
+    }
+    /// Print the tablets that moved and the tablet servers, and return the number of tablets that moved.
+    pub fn print(
+        &self,
+        table_name_filter: &Regex,
+        config: &Config,
+    ) -> Result<usize>
+    {
+        if !self.master_found
+        {
+            writeln!(config.output(), "Master leader not found in the begin or end snapshot, tablet movement cannot be shown.")?;
+            return Ok(0);
+        }
+        let moved_tablets = self.moved_tablets(table_name_filter);
+        let compared_tablets = self.btreetabletmovement
+            .values()
+            .filter(|movement| !movement.first_replicas.is_empty() && !movement.second_replicas.is_empty() && table_name_filter.is_match(&movement.table_name))
+            .count();
+        writeln!(config.output(), "{} of {} tablets moved", moved_tablets.len(), compared_tablets)?;
+        if !moved_tablets.is_empty()
+        {
+            let rows: Vec<Vec<String>> = moved_tablets
+                .iter()
+                .map(|(tablet_id, movement, moved_from, moved_to)| vec![
+                    tablet_id.to_string(),
+                    movement.table_name.clone(),
+                    movement.first_replicas.iter().cloned().collect::<Vec<_>>().join(","),
+                    movement.second_replicas.iter().cloned().collect::<Vec<_>>().join(","),
+                    moved_from.iter().map(|addr| addr.red().to_string()).collect::<Vec<_>>().join(","),
+                    moved_to.iter().map(|addr| addr.green().to_string()).collect::<Vec<_>>().join(","),
+                ])
+                .collect();
+            utility::print_table(&["tablet_id", "table_name", "before", "after", "moved_from", "moved_to"], &rows, config)?;
+        }
+        writeln!(config.output())?;
+        utility::print_table(&["tablet_server", "replicas_before", "replicas_after", "gained", "lost", "net"], &self.tablet_server_rows(table_name_filter), config)?;
+        Ok(moved_tablets.len())
+    }
+}
+
+/// Show the tablets of which the replicas moved to other tablet servers between the begin and end snapshot,
+/// and the number of tablets gained and lost per tablet server, such as by a rebalance or a decommission.
+pub async fn tablet_movement_diff(
+    options: &Opts,
+    config: &Config,
+) -> Result<()>
+{
+    info!("tablet movement diff");
+    if options.begin.is_none() || options.end.is_none() {
+        snapshot::Snapshot::print(config)?;
+    }
+    if options.snapshot_list { return Ok(()) };
+    let table_name_filter = utility::set_regex(&options.table_name_match);
+    let (begin_snapshot, end_snapshot, _begin_snapshot_row) = snapshot::Snapshot::read_begin_end_snapshot_from_user(options.begin, options.end)?;
+    snapshot::Snapshot::print_diff_header(&begin_snapshot, &end_snapshot, config)?;
+
+    let tabletmovementdiff = TabletMovementDiff::snapshot_diff(&begin_snapshot, &end_snapshot, config)?;
+    let changes = tabletmovementdiff.print(&table_name_filter, config)?;
+    utility::add_diff_changes(changes, config);
 
     Ok(())
 }
@@ -1540,6 +1992,7 @@ pub async fn print_entities(
     ports: Vec<&str>,
     parallel: usize,
     options: &Opts,
+    config: &Config,
 ) -> Result<()>
 {
     let table_name_filter = utility::set_regex(&options.table_name_match);
@@ -1550,17 +2003,24 @@ pub async fn print_entities(
         Some(snapshot_number) =>
         {
             let mut allentities = AllEntities::new();
-            allentities.entities = snapshot::read_snapshot_json(snapshot_number, "entities")?;
-            let leader_hostname = AllIsLeader::return_leader_snapshot(snapshot_number)?;
-            let (dead_nodes, under_replicated_tablets) = AllHealthCheck::return_dead_nodes_and_under_replicated_tablets_snapshot(snapshot_number, &leader_hostname)?;
-            allentities.print(&table_name_filter, &options.details_enable, leader_hostname, &hostname_filter, dead_nodes, under_replicated_tablets)?;
+            allentities.entities = snapshot::read_snapshot_json(snapshot_number, "entities", config)?;
+            let leader_hostname = AllIsLeader::return_leader_snapshot(snapshot_number, config)?;
+            let (dead_nodes, under_replicated_tablets) = AllHealthCheck::return_dead_nodes_and_under_replicated_tablets_snapshot(snapshot_number, &leader_hostname, config)?;
+            let mut alltablets = AllTablets::new();
+            if options.details_enable
+                && snapshot::snapshot_data_types(snapshot_number)?.iter().any(|data_type| data_type == "tablets")
+            {
+                alltablets.tablet = snapshot::read_snapshot_json(snapshot_number, "tablets", config)?;
+            }
+            allentities.print(&table_name_filter, &options.details_enable, leader_hostname, &hostname_filter, dead_nodes, under_replicated_tablets, &alltablets, config)?;
         },
         None =>
         {
-            let allentities = AllEntities::read_entities(&hosts, &ports, parallel).await;
-            let leader_hostname = AllIsLeader::return_leader_http(&hosts, &ports, parallel).await;
-            let (dead_nodes, under_replicated_tablets) = AllHealthCheck::return_dead_nodes_and_under_replicated_tablets_http(&hosts, &ports, parallel, &leader_hostname).await?;
-            allentities.print(&table_name_filter, &options.details_enable, leader_hostname, &hostname_filter, dead_nodes, under_replicated_tablets)?;
+            let allentities = AllEntities::read_entities(&hosts, &ports, parallel, config).await;
+            let leader_hostname = AllIsLeader::return_leader_http(&hosts, &ports, parallel, config).await;
+            let (dead_nodes, under_replicated_tablets) = AllHealthCheck::return_dead_nodes_and_under_replicated_tablets_http(&hosts, &ports, parallel, &leader_hostname, config).await?;
+            let alltablets = if options.details_enable { AllTablets::read_tablets(&hosts, &ports, parallel, &false, config).await } else { AllTablets::new() };
+            allentities.print(&table_name_filter, &options.details_enable, leader_hostname, &hostname_filter, dead_nodes, under_replicated_tablets, &alltablets, config)?;
         },
     }
     Ok(())
@@ -1571,13 +2031,14 @@ pub async fn print_coloc_leader_host(
     ports: Vec<&str>,
     parallel: usize,
     options: &Opts,
+    config: &Config,
 ) -> Result<()>
 {
     let colocated_database = &options.get_coloc_leader_host.as_ref().unwrap();
 
-    let allentities = AllEntities::read_entities(&hosts, &ports, parallel).await;
-    let leader_hostname = AllIsLeader::return_leader_http(&hosts, &ports, parallel).await;
-    allentities.print_coloc_leader_host(leader_hostname, colocated_database)?;
+    let allentities = AllEntities::read_entities(&hosts, &ports, parallel, config).await;
+    let leader_hostname = AllIsLeader::return_leader_http(&hosts, &ports, parallel, config).await;
+    allentities.print_coloc_leader_host(leader_hostname, colocated_database, config)?;
 
     Ok(())
 }
@@ -1585,6 +2046,8 @@ pub async fn print_coloc_leader_host(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::tablet_detail::{Tablet, TabletBasic};
+    use crate::entities::{Keyspaces, Tables, Tablets, Replicas};
 
     #[test]
     fn unit_parse_simple_entities_dump() {
@@ -1646,12 +2109,197 @@ mod tests {
         assert_eq!(result.tablets[1].leader.as_ref().unwrap(),"a3f5a16532bb4ed4a061e794831168f8");
     }
 
+    #[test]
+    fn unit_entities_anomalies() {
+        let json = r#"
+{
+  "keyspaces": [
+    {"keyspace_id": "ks_used", "keyspace_name": "used", "keyspace_type": "ycql"},
+    {"keyspace_id": "ks_empty", "keyspace_name": "empty", "keyspace_type": "ycql"},
+    {"keyspace_id": "00004000000030008000000000000000", "keyspace_name": "dropped", "keyspace_type": "ysql"}
+  ],
+  "tables": [
+    {"table_id": "table_ok", "keyspace_id": "ks_used", "table_name": "ok", "state": "RUNNING"},
+    {"table_id": "table_dead", "keyspace_id": "ks_used", "table_name": "dead", "state": "RUNNING"},
+    {"table_id": "table_none", "keyspace_id": "ks_used", "table_name": "none", "state": "RUNNING"}
+  ],
+  "tablets": [
+    {"table_id": "sys.catalog.uuid", "tablet_id": "00000000000000000000000000000000", "state": "RUNNING"},
+    {"table_id": "table_ok", "tablet_id": "tablet_ok", "state": "RUNNING",
+     "replicas": [{"type": "VOTER", "server_uuid": "ts_1", "addr": "yb-1.local:9100"}, {"type": "VOTER", "server_uuid": "ts_2", "addr": "yb-2.local:9100"}]},
+    {"table_id": "table_dead", "tablet_id": "tablet_dead", "state": "RUNNING",
+     "replicas": [{"type": "VOTER", "server_uuid": "ts_2", "addr": "yb-2.local:9100"}]}
+  ]
+}
+        "#.to_string();
+        let entities = AllEntities::parse_entities(json, "", "");
+        let anomalies = entities.anomalies(&["ts_2".to_string()]);
+        assert_eq!(anomalies, vec![
+            "Tablet without live replica: tablet_dead table_id: table_dead",
+            "Table without tablets: ycql.used.none id: table_none",
+            "Keyspace without tables: ycql.empty id: ks_empty",
+        ]);
+    }
+
+    #[test]
+    fn unit_entities_find_tables() {
+        let json = r#"
+{
+  "keyspaces": [
+    {"keyspace_id": "ks_ysql", "keyspace_name": "app", "keyspace_type": "ysql"},
+    {"keyspace_id": "ks_ycql", "keyspace_name": "app", "keyspace_type": "ycql"},
+    {"keyspace_id": "ks_other", "keyspace_name": "other", "keyspace_type": "ysql"}
+  ],
+  "tables": [
+    {"table_id": "table_ysql", "keyspace_id": "ks_ysql", "table_name": "orders", "state": "RUNNING"},
+    {"table_id": "table_ycql", "keyspace_id": "ks_ycql", "table_name": "orders", "state": "RUNNING"},
+    {"table_id": "table_other", "keyspace_id": "ks_other", "table_name": "orders", "state": "RUNNING"},
+    {"table_id": "table_dropped", "keyspace_id": "ks_other", "table_name": "items", "state": "DELETED"}
+  ],
+  "tablets": []
+}
+        "#.to_string();
+        let mut entities = AllEntities::parse_entities(json, "yb-1.local", "7000");
+        entities.hostname_port = Some("yb-1.local:7000".to_string());
+        let allentities = AllEntities { entities: vec![entities] };
+        let table_ids = |keyspace_table: &str| allentities.find_tables("yb-1.local:7000", keyspace_table).into_iter().map(|(_, _, _, table_id)| table_id).collect::<Vec<_>>();
+        assert_eq!(table_ids("other.orders"), vec!["table_other"]);
+        assert_eq!(table_ids("app.orders"), vec!["table_ysql", "table_ycql"]);
+        assert_eq!(table_ids("orders").len(), 3);
+        assert!(table_ids("other.items").is_empty());
+        assert!(allentities.find_tables("yb-2.local:7000", "other.orders").is_empty());
+    }
+
+    #[test]
+    fn unit_entities_tablet_reconciliation() {
+        let json = r#"
+{
+  "keyspaces": [
+    {"keyspace_id": "ks_used", "keyspace_name": "used", "keyspace_type": "ycql"}
+  ],
+  "tables": [
+    {"table_id": "table_1", "keyspace_id": "ks_used", "table_name": "t1", "state": "RUNNING"}
+  ],
+  "tablets": [
+    {"table_id": "sys.catalog.uuid", "tablet_id": "00000000000000000000000000000000", "state": "RUNNING"},
+    {"table_id": "table_1", "tablet_id": "tablet_hosted", "state": "RUNNING",
+     "replicas": [{"type": "VOTER", "server_uuid": "ts_1", "addr": "yb-1.local:9100"}]},
+    {"table_id": "table_1", "tablet_id": "tablet_lost", "state": "RUNNING",
+     "replicas": [{"type": "VOTER", "server_uuid": "ts_2", "addr": "yb-2.local:9100"}]},
+    {"table_id": "table_1", "tablet_id": "tablet_deleted", "state": "DELETED"}
+  ]
+}
+        "#.to_string();
+        let entities = AllEntities::parse_entities(json, "", "");
+        let tablet_basic = |tablet_id: &str, state: &str| TabletBasic {
+            namespace: "used".to_string(),
+            table_name: "t1".to_string(),
+            tablet_id: tablet_id.to_string(),
+            state: state.to_string(),
+            ..Default::default()
+        };
+        let alltablets = AllTablets {
+            tablet: vec![
+                Tablet {
+                    hostname_port: Some("yb-1.local:9000".to_string()),
+                    tabletbasic: vec![tablet_basic("tablet_hosted", "RUNNING"), tablet_basic("tablet_orphan", "RUNNING"), tablet_basic("tablet_deleted", "SHUTDOWN")],
+                    ..Default::default()
+                },
+                Tablet {
+                    hostname_port: Some("yb-2.local:9000".to_string()),
+                    // a tombstoned replica is not hosted.
+                    tabletbasic: vec![tablet_basic("tablet_lost", "SHUTDOWN"), tablet_basic("tablet_orphan", "RUNNING")],
+                    ..Default::default()
+                },
+            ],
+        };
+        assert_eq!(entities.tablet_reconciliation(&alltablets), vec![
+            "Tablet in catalog not hosted by a tablet server: tablet_lost table_id: table_1 replicas: yb-2.local:9100",
+            "Tablet hosted but not in catalog: tablet_orphan table: used.t1 hosted on: yb-1.local:9000,yb-2.local:9000",
+        ]);
+    }
+
+    #[test]
+    fn unit_entities_diff_tablet_count_changes() {
+        let mut entitiesdiff = EntitiesDiff::new();
+        for (table_id, table_name) in [("split_table", "t1"), ("unchanged_table", "t2")]
+        {
+            entitiesdiff.btreetablesdiff.insert(table_id.to_string(), TablesDiff {
+                first_table_name: table_name.to_string(),
+                second_table_name: table_name.to_string(),
+                ..Default::default()
+            });
+        }
+        // the parent tablet of split_table is split into two child tablets.
+        for (tablet_id, first_table_id, second_table_id, second_state) in [
+            ("parent", "split_table", "split_table", "REPLACED"),
+            ("child1", "", "split_table", "RUNNING"),
+            ("child2", "", "split_table", "RUNNING"),
+            ("other", "unchanged_table", "unchanged_table", "RUNNING"),
+        ]
+        {
+            entitiesdiff.btreetabletsdiff.insert(tablet_id.to_string(), TabletsDiff {
+                first_table_id: first_table_id.to_string(),
+                first_state: if first_table_id.is_empty() { "".to_string() } else { "RUNNING".to_string() },
+                second_table_id: second_table_id.to_string(),
+                second_state: second_state.to_string(),
+                ..Default::default()
+            });
+        }
+        let result = entitiesdiff.tablet_count_changes();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result.get("split_table"), Some(&(1, 2)));
+    }
+
+    #[test]
+    fn unit_entities_tablet_movement() {
+        let entities = |tablets: &[(&str, &[&str])]| AllEntities {
+            entities: vec![Entities {
+                hostname_port: Some("yb-1.local:7000".to_string()),
+                keyspaces: vec![Keyspaces { keyspace_id: "ks".to_string(), keyspace_name: "yugabyte".to_string(), keyspace_type: "ysql".to_string() }],
+                tables: vec![Tables { table_id: "t".to_string(), keyspace_id: "ks".to_string(), table_name: "orders".to_string(), state: "RUNNING".to_string() }],
+                tablets: tablets
+                    .iter()
+                    .map(|(tablet_id, addrs)| Tablets {
+                        table_id: "t".to_string(),
+                        tablet_id: tablet_id.to_string(),
+                        state: "RUNNING".to_string(),
+                        replicas: Some(addrs.iter().map(|addr| Replicas { replica_type: "VOTER".to_string(), server_uuid: addr.to_string(), addr: addr.to_string() }).collect()),
+                        leader: None,
+                    })
+                    .collect(),
+                ..Default::default()
+            }],
+        };
+        let mut tabletmovementdiff = TabletMovementDiff::new();
+        tabletmovementdiff.add_snapshot(&entities(&[("unchanged", &["a:9100", "b:9100", "c:9100"]), ("one", &["a:9100", "b:9100", "c:9100"]), ("two", &["a:9100", "b:9100", "c:9100"]), ("dropped", &["a:9100", "b:9100", "c:9100"])]), "yb-1.local:7000", true);
+        tabletmovementdiff.add_snapshot(&entities(&[("unchanged", &["a:9100", "b:9100", "c:9100"]), ("one", &["a:9100", "b:9100", "d:9100"]), ("two", &["a:9100", "d:9100", "e:9100"]), ("new", &["a:9100", "b:9100", "c:9100"])]), "yb-1.local:7000", false);
+        assert!(tabletmovementdiff.master_found);
+
+        let moved_tablets = tabletmovementdiff.moved_tablets(&utility::set_regex(&None));
+        assert_eq!(moved_tablets.len(), 2);
+        let (tablet_id, movement, moved_from, moved_to) = &moved_tablets[0];
+        assert_eq!(tablet_id.as_str(), "two");
+        assert_eq!(movement.table_name, "yugabyte.orders");
+        assert_eq!(moved_from, &vec!["b:9100", "c:9100"]);
+        assert_eq!(moved_to, &vec!["d:9100", "e:9100"]);
+        assert_eq!(moved_tablets[1].0.as_str(), "one");
+
+        let rows = tabletmovementdiff.tablet_server_rows(&utility::set_regex(&None));
+        // c lost both moved tablets, d gained both; the dropped and new tablets are counted in the replicas only.
+        assert_eq!(rows[0], vec!["c:9100", "4", "2", "0", "2", "-2"]);
+        assert_eq!(rows[1], vec!["d:9100", "0", "2", "2", "0", "+2"]);
+        assert_eq!(rows.last().unwrap(), &vec!["a:9100", "4", "4", "0", "0", "+0"]);
+        assert!(tabletmovementdiff.moved_tablets(&utility::set_regex(&Some("other".to_string()))).is_empty());
+    }
+
     #[test]
     fn integration_parse_entities() {
+        let config = &Config::default();
         let hostname = utility::get_hostname_master();
         let port = utility::get_port_master();
 
-        let entities = AllEntities::read_http(&hostname, &port);
+        let entities = AllEntities::read_http(&hostname, &port, config);
 
         assert!(!entities.keyspaces.is_empty());
         assert!(!entities.tables.is_empty());
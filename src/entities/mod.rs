@@ -15,6 +15,7 @@
 //! - [crate::snapshot::snapshot_diff] -> [crate::entities::MastersDiff::snapshot_diff] (general diff, show masters diff based on snapshot data)
 //! - [crate] -> [print_entities] (prints adhoc (live) or snapshot masters info)
 //! - [crate] -> [entity_diff] (prints entities diff only, based on snapshot data)
+//! - [crate] -> [tablet_movement_diff] (prints the tablets that moved between tablet servers, based on snapshot data)
 //! - [crate::snapshot::adhoc_diff] -> [crate::entities::MastersDiff::adhoc_read_first_snapshot],
 //! [crate::masters::MastersDiff::adhoc_read_second_snapshot],
 //! [crate::masters::MastersDiff::print] (prints masters diff, based on live data)
@@ -1,7 +1,7 @@
 //! The structs
 //!
 use chrono::{DateTime, Local};
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 
 /// The root struct for deserializing `/dump-entities`
 ///
@@ -143,4 +143,18 @@ pub struct ReplicasDiff {
     pub first_addr: String,
     pub second_replica_type: String,
     pub second_addr: String,
-}
\ No newline at end of file
+}
+// tablet movement
+/// The replica locations (the tablet server addresses) of a tablet in the begin and end snapshot.
+#[derive(Debug, Default, PartialEq)]
+pub struct TabletMovement {
+    pub table_name: String,
+    pub first_replicas: BTreeSet<String>,
+    pub second_replicas: BTreeSet<String>,
+}
+#[derive(Debug, Default)]
+pub struct TabletMovementDiff {
+    /// String = tablet_id
+    pub btreetabletmovement: BTreeMap<String, TabletMovement>,
+    pub master_found: bool,
+}
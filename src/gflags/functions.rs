@@ -1,29 +1,50 @@
 //! The impls and functions
 //!
-use std::{time::Instant, sync::mpsc::channel};
+use std::{time::Instant, sync::mpsc::channel, collections::BTreeMap};
 use chrono::Local;
 use regex::Regex;
 use log::*;
 use anyhow::Result;
 use crate::Opts;
 use crate::utility;
+use crate::utility::Config;
 use crate::snapshot;
-use crate::gflags::{AllGFlags, GFlag};
+use crate::gflags::{AllGFlags, GFlag, GFlagsCollapsed};
 
 impl AllGFlags {
     pub fn new() -> Self { Default::default() }
+    /// The gflags of each host as text for --text-diff, a line per gflag like in a flagfile: `--name=value`.
+    pub fn text_per_host(&self) -> BTreeMap<String, String>
+    {
+        let mut text_per_host: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for gflag in &self.gflags
+        {
+            text_per_host
+                .entry(gflag.hostname_port.clone().expect("hostname:port should be set"))
+                .or_default()
+                .push(format!("--{}={}\n", gflag.name, gflag.value));
+        }
+        text_per_host
+            .into_iter()
+            .map(|(hostname_port, mut lines)| {
+                lines.sort();
+                (hostname_port, lines.concat())
+            })
+            .collect()
+    }
     pub async fn perform_snapshot(
         hosts: &Vec<&str>,
         ports: &Vec<&str>,
         snapshot_number: i32,
         parallel: usize,
+        config: &Config,
     ) -> Result<()>
     {
         info!("begin snapshot");
         let timer = Instant::now();
 
-        let allgflags = AllGFlags::read_gflags(hosts, ports, parallel).await;
-        snapshot::save_snapshot_json(snapshot_number, "gflags", allgflags.gflags)?;
+        let allgflags = AllGFlags::read_gflags(hosts, ports, parallel, config).await;
+        snapshot::save_snapshot_json(snapshot_number, "gflags", allgflags.gflags, config)?;
 
         info!("end snapshot: {:?}", timer.elapsed());
 
@@ -33,6 +54,7 @@ impl AllGFlags {
         hosts: &Vec<&str>,
         ports: &Vec<&str>,
         parallel: usize,
+        config: &Config,
     ) -> AllGFlags
     {
         info!("begin parallel http read");
@@ -47,9 +69,9 @@ impl AllGFlags {
                     let tx = tx.clone();
                     s.spawn(move |_| {
                         let detail_snapshot_time = Local::now();
-                        let mut gflags = AllGFlags::read_http(host, port);
+                        let mut gflags = AllGFlags::read_http(host, port, config);
                         gflags.iter_mut().for_each(|r| r.timestamp = Some(detail_snapshot_time));
-                        gflags.iter_mut().for_each(|r| r.hostname_port = Some(format!("{}:{}", host, port)));
+                        gflags.iter_mut().for_each(|r| r.hostname_port = Some(utility::hostname_port(host, port, config)));
                         tx.send(gflags).expect("error sending data via tx");
                     });
                 }
@@ -73,9 +95,10 @@ impl AllGFlags {
     fn read_http(
         host: &str,
         port: &str,
+        config: &Config,
     ) -> Vec<GFlag>
     {
-        let data_from_http = utility::http_get(host, port, "varz?raw");
+        let data_from_http = utility::http_get_endpoint(host, port, "gflags", config);
         AllGFlags::parse_gflags(data_from_http)
     }
     fn parse_gflags(
@@ -100,24 +123,115 @@ impl AllGFlags {
         &self,
         hostname_filter: &Regex,
         stat_name_filter: &Regex,
+        config: &Config,
     ) -> Result<()>
     {
         info!("print_gflags");
 
+        let mut rows = utility::RowWriter::new(config);
         let mut previous_hostname_port = String::from("");
         for row in &self.gflags {
             if hostname_filter.is_match(&row.hostname_port.clone().expect("hostname:port should be set")) &&
                 stat_name_filter.is_match( &row.name) {
                 if row.hostname_port.clone().expect("hostname:port should be set") != previous_hostname_port {
-                    println!("--------------------------------------------------------------------------------------------------------------------------------------");
-                    println!("Host: {} Snapshot time: {}", &row.hostname_port.clone().expect("hostname:port should be set").to_string(), row.timestamp.expect("timestamp should be et"));
-                    println!("--------------------------------------------------------------------------------------------------------------------------------------");
+                    rows.header(format_args!("--------------------------------------------------------------------------------------------------------------------------------------"))?;
+                    rows.header(format_args!("Host: {} Snapshot time: {}", &row.hostname_port.clone().expect("hostname:port should be set").to_string(), utility::format_timestamp(&row.timestamp.expect("timestamp should be et"), config)))?;
+                    rows.header(format_args!("--------------------------------------------------------------------------------------------------------------------------------------"))?;
                     previous_hostname_port = row.hostname_port.clone().expect("hostname:port should be set").to_string();
                 }
-                println!("{:80} {:30}", row.name, row.value);
+                writeln!(rows, "{:80} {:30}", row.name, row.value)?;
             }
         }
-        Ok(())
+        rows.finish()
+    }
+    /// Collapse the gflags per port into the flags that are the same on all servers, and the flags that differ.
+    /// A flag that is not set on all servers of a port is a flag that differs.
+    pub fn collapse(
+        &self,
+        hostname_filter: &Regex,
+        stat_name_filter: &Regex,
+    ) -> BTreeMap<String, GFlagsCollapsed>
+    {
+        // port, name, hostname:port, value
+        let mut values: BTreeMap<String, BTreeMap<String, BTreeMap<String, String>>> = BTreeMap::new();
+        let mut hosts: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for row in &self.gflags
+        {
+            let hostname_port = row.hostname_port.clone().expect("hostname:port should be set");
+            if !hostname_filter.is_match(&hostname_port) { continue };
+            let port = hostname_port.rsplit_once(':').map(|(_, port)| port.to_string()).unwrap_or_default();
+            let port_hosts = hosts.entry(port.clone()).or_default();
+            if !port_hosts.contains(&hostname_port) {
+                port_hosts.push(hostname_port.clone());
+            }
+            if stat_name_filter.is_match(&row.name)
+            {
+                values
+                    .entry(port)
+                    .or_default()
+                    .entry(row.name.clone())
+                    .or_default()
+                    .insert(hostname_port, row.value.clone());
+            }
+        }
+
+        let mut collapsed = BTreeMap::new();
+        for (port, mut port_hosts) in hosts
+        {
+            port_hosts.sort();
+            let mut gflagscollapsed = GFlagsCollapsed { hosts: port_hosts, ..Default::default() };
+            for (name, host_values) in values.remove(&port).unwrap_or_default()
+            {
+                let mut distinct_values = host_values.values();
+                let first_value = distinct_values.next().cloned().unwrap_or_default();
+                if host_values.len() == gflagscollapsed.hosts.len() && distinct_values.all(|value| *value == first_value)
+                {
+                    gflagscollapsed.common.insert(name, first_value);
+                }
+                else
+                {
+                    let per_host = gflagscollapsed.hosts
+                        .iter()
+                        .map(|hostname_port| (hostname_port.clone(), host_values.get(hostname_port).cloned()))
+                        .collect();
+                    gflagscollapsed.differ.insert(name, per_host);
+                }
+            }
+            collapsed.insert(port, gflagscollapsed);
+        }
+        collapsed
+    }
+    /// Print each gflag once per port with its common value, followed by the gflags that differ with the value per host.
+    pub fn print_collapsed(
+        &self,
+        hostname_filter: &Regex,
+        stat_name_filter: &Regex,
+        config: &Config,
+    ) -> Result<()>
+    {
+        info!("print_gflags_collapsed");
+
+        let mut rows = utility::RowWriter::new(config);
+        for (port, gflagscollapsed) in self.collapse(hostname_filter, stat_name_filter)
+        {
+            rows.header(format_args!("--------------------------------------------------------------------------------------------------------------------------------------"))?;
+            rows.header(format_args!("Port: {} Hosts: {}", port, gflagscollapsed.hosts.join(", ")))?;
+            rows.header(format_args!("--------------------------------------------------------------------------------------------------------------------------------------"))?;
+            for (name, value) in &gflagscollapsed.common
+            {
+                writeln!(rows, "{:80} {:30}", name, value)?;
+            }
+            rows.header(format_args!("Differs between hosts: {}", gflagscollapsed.differ.len()))?;
+            for (name, per_host) in &gflagscollapsed.differ
+            {
+                writeln!(rows, "{}", name)?;
+                for (hostname_port, value) in per_host
+                {
+                    writeln!(rows, "  {:78} {:30}", hostname_port, value.as_deref().unwrap_or("(not set)"))?;
+                }
+            }
+        }
+        rows.finish()
     }
 }
 
@@ -126,6 +240,7 @@ pub async fn print_gflags(
     ports: Vec<&str>,
     parallel: usize,
     options: &Opts,
+    config: &Config,
 ) -> Result<()>
 {
     let hostname_filter = utility::set_regex(&options.hostname_match);
@@ -134,12 +249,20 @@ pub async fn print_gflags(
     match options.print_gflags.as_ref().unwrap() {
         Some(snapshot_number) => {
             let mut allgflags = AllGFlags::new();
-            allgflags.gflags = snapshot::read_snapshot_json(snapshot_number, "gflags")?;
-            allgflags.print(&hostname_filter, &stat_name_filter)?;
+            allgflags.gflags = snapshot::read_snapshot_json(snapshot_number, "gflags", config)?;
+            if options.gflags_collapse {
+                allgflags.print_collapsed(&hostname_filter, &stat_name_filter, config)?;
+            } else {
+                allgflags.print(&hostname_filter, &stat_name_filter, config)?;
+            }
         },
         None => {
-            let allgflags = AllGFlags::read_gflags(&hosts, &ports, parallel).await;
-            allgflags.print(&hostname_filter, &stat_name_filter)?;
+            let allgflags = AllGFlags::read_gflags(&hosts, &ports, parallel, config).await;
+            if options.gflags_collapse {
+                allgflags.print_collapsed(&hostname_filter, &stat_name_filter, config)?;
+            } else {
+                allgflags.print(&hostname_filter, &stat_name_filter, config)?;
+            }
         }
     }
     Ok(())
@@ -987,21 +1110,45 @@ Command-line Flags--TEST_xcluster_simulate_have_more_records=false
         assert_eq!(result.len(), 829);
     }
 
+    #[test]
+    fn unit_collapse_gflags() {
+        let gflags = r#"[{"hostname_port":"yb-1:9000","timestamp":null,"name":"same","value":"1"},{"hostname_port":"yb-1:9000","timestamp":null,"name":"different","value":"a"},{"hostname_port":"yb-1:9000","timestamp":null,"name":"partial","value":"x"},
+                         {"hostname_port":"yb-2:9000","timestamp":null,"name":"same","value":"1"},{"hostname_port":"yb-2:9000","timestamp":null,"name":"different","value":"b"},
+                         {"hostname_port":"yb-1:7000","timestamp":null,"name":"same","value":"2"}]"#;
+        let allgflags = AllGFlags { gflags: serde_json::from_str(gflags).unwrap() };
+        let all = utility::set_regex(&None);
+        let collapsed = allgflags.collapse(&all, &all);
+        assert_eq!(collapsed.len(), 2);
+        // the master on port 7000 is compared with the other masters only.
+        assert_eq!(collapsed["7000"].common["same"], "2");
+        assert!(collapsed["7000"].differ.is_empty());
+        let tservers = &collapsed["9000"];
+        assert_eq!(tservers.hosts, vec!["yb-1:9000".to_string(), "yb-2:9000".to_string()]);
+        assert_eq!(tservers.common.len(), 1);
+        assert_eq!(tservers.differ["different"]["yb-2:9000"], Some("b".to_string()));
+        assert_eq!(tservers.differ["partial"]["yb-2:9000"], None);
+        // a host that is filtered out is not compared.
+        let collapsed = allgflags.collapse(&utility::set_regex(&Some("yb-1".to_string())), &all);
+        assert!(collapsed["9000"].differ.is_empty());
+    }
+
     #[tokio::test]
     async fn integration_parse_gflags_master() {
+        let config = &Config::default();
         let hostname = utility::get_hostname_master();
         let port = utility::get_port_master();
 
-        let allgflags = AllGFlags::read_gflags(&vec![&hostname], &vec![&port], 1).await;
+        let allgflags = AllGFlags::read_gflags(&vec![&hostname], &vec![&port], 1, config).await;
         // the master must have gflags
         assert!(!allgflags.gflags.is_empty());
     }
     #[tokio::test]
     async fn integration_parse_gflags_tserver() {
+        let config = &Config::default();
         let hostname = utility::get_hostname_tserver();
         let port = utility::get_port_tserver();
 
-        let allgflags = AllGFlags::read_gflags(&vec![&hostname], &vec![&port], 1).await;
+        let allgflags = AllGFlags::read_gflags(&vec![&hostname], &vec![&port], 1, config).await;
         // the tserver must have gflags
         assert!(!allgflags.gflags.is_empty());
     }
@@ -1,5 +1,6 @@
 //! The structs
 //!
+use std::collections::BTreeMap;
 use chrono::{DateTime, Local};
 
 /// The root structure for deserializing the gflags
@@ -30,3 +31,16 @@ pub struct GFlag {
 pub struct AllGFlags {
     pub gflags: Vec<GFlag>,
 }
+
+/// The gflags of a group of servers, collapsed to the flags that are the same on all servers and the flags that differ.
+///
+/// The servers are grouped on port, so masters and tablet servers are compared with their own kind.
+#[derive(Debug, Default, PartialEq)]
+pub struct GFlagsCollapsed {
+    /// The hostname:port of the servers in the group.
+    pub hosts: Vec<String>,
+    /// The gflags with the same value on all servers: name, value.
+    pub common: BTreeMap<String, String>,
+    /// The gflags with a different value between the servers, or that are not set on all servers: name, hostname:port, value.
+    pub differ: BTreeMap<String, BTreeMap<String, Option<String>>>,
+}
@@ -1,14 +1,23 @@
 //! The impls and functions
 //!
 use chrono::Local;
-use std::{sync::mpsc::channel, time::Instant};
+use std::{collections::{BTreeMap, BTreeSet}, sync::mpsc::channel, time::Instant};
 use log::*;
 use colored::*;
-use anyhow::{Result, Context};
+use anyhow::{bail, Result, Context};
 use crate::isleader::AllIsLeader;
 use crate::utility;
+use crate::utility::Config;
 use crate::snapshot;
-use crate::health_check::{AllHealthCheck, Health_Check, HealthCheckDiff};
+use crate::health_check::{AllHealthCheck, Health_Check, HealthCheckDiff, UnderReplicatedTablet, AllUnderReplicatedTablets, HealthScoreFactor};
+use crate::entities::AllEntities;
+use crate::cluster_config::AllSysClusterConfigEntryPB;
+use crate::tablet_servers::AllTabletServers;
+use crate::tablet_replication::AllTabletReplication;
+use crate::clocks::AllClocks;
+use crate::drives::AllDrives;
+use crate::versions::AllVersions;
+use crate::metrics::MetricAssertion;
 use crate::Opts;
 
 impl Health_Check {
@@ -26,13 +35,14 @@ impl AllHealthCheck {
         ports: &Vec<&str>,
         snapshot_number: i32,
         parallel: usize,
+        config: &Config,
     ) -> Result<()>
     {
         info!("begin snapshot");
         let timer = Instant::now();
 
-        let allhealth_check = AllHealthCheck::read_health_check(hosts, ports, parallel).await;
-        snapshot::save_snapshot_json(snapshot_number, "health-check", allhealth_check.health_check)?;
+        let allhealth_check = AllHealthCheck::read_health_check(hosts, ports, parallel, config).await;
+        snapshot::save_snapshot_json(snapshot_number, "health-check", allhealth_check.health_check, config)?;
 
         info!("end snapshot: {:?}", timer.elapsed());
 
@@ -42,6 +52,7 @@ impl AllHealthCheck {
         hosts: &Vec<&str>,
         ports: &Vec<&str>,
         parallel: usize,
+        config: &Config,
     ) -> AllHealthCheck
     {
         info!("begin parallel http read");
@@ -55,9 +66,9 @@ impl AllHealthCheck {
                     let tx = tx.clone();
                     s.spawn(move |_| {
                         let detail_snapshot_time = Local::now();
-                        let mut health_check = AllHealthCheck::read_http(host, port);
+                        let mut health_check = AllHealthCheck::read_http(host, port, config);
                         health_check.timestamp = Some(detail_snapshot_time);
-                        health_check.hostname_port = Some(format!("{}:{}", host, port));
+                        health_check.hostname_port = Some(utility::hostname_port(host, port, config));
                         tx.send(health_check).expect("error sending data via tx");
                     });
                 }
@@ -79,9 +90,10 @@ impl AllHealthCheck {
     pub fn read_http(
         host: &str,
         port: &str,
+        config: &Config,
     ) -> Health_Check
     {
-        let data_from_http = utility::http_get(host, port, "api/v1/health-check");
+        let data_from_http = utility::http_get_endpoint(host, port, "health_check", config);
         AllHealthCheck::parse_health_check(data_from_http, host, port)
     }
     fn parse_health_check(
@@ -99,25 +111,65 @@ impl AllHealthCheck {
     }
     pub fn print(
         &self,
-        leader_hostname: String
+        leader_hostname: String,
+        details_enable: &bool,
+        alltabletreplication: &AllTabletReplication,
+        config: &Config,
     ) -> Result<()>
     {
 
-        println!("{}", serde_json::to_string_pretty( &self.health_check
+        writeln!(config.output(), "{}", serde_json::to_string_pretty( &self.health_check
             .iter()
             .find(|r| r.hostname_port == Some(leader_hostname.clone()))
             .with_context(|| "Unable to find current master leader")?
-        )?);
+        )?)?;
+        if *details_enable
+        {
+            let rows = self.tablet_rows(&leader_hostname, alltabletreplication);
+            if !rows.is_empty()
+            {
+                writeln!(config.output())?;
+                utility::print_table(&["state", "tablet_id", "table_name"], &rows, config)?;
+            }
+        }
         Ok(())
     }
+    /// The under replicated tablets of the health check and the leaderless tablets of the tablet replication data
+    /// of the master leader, as rows of state, tablet id and table name.
+    fn tablet_rows(
+        &self,
+        leader_hostname: &str,
+        alltabletreplication: &AllTabletReplication,
+    ) -> Vec<Vec<String>>
+    {
+        let table_names = table_names(alltabletreplication, leader_hostname);
+        let table_name = |tablet_id: &String| table_names.get(tablet_id).cloned().unwrap_or_default();
+        let mut rows = Vec::new();
+        for healthcheck in self.health_check
+            .iter()
+            .filter(|r| r.hostname_port.as_deref() == Some(leader_hostname))
+        {
+            for tablet_id in healthcheck.under_replicated_tablets.as_ref().unwrap_or(&Vec::new())
+            {
+                rows.push(vec!["under replicated".to_string(), tablet_id.clone(), table_name(tablet_id)]);
+            }
+        }
+        for tablet_id in leaderless_tablets(alltabletreplication, leader_hostname)
+        {
+            let table_name = table_name(&tablet_id);
+            rows.push(vec!["leaderless".to_string(), tablet_id, table_name]);
+        }
+        rows
+    }
     pub async fn return_dead_nodes_and_under_replicated_tablets_http(
         hosts: &Vec<&str>,
         ports: &Vec<&str>,
         parallel: usize,
         leader_hostname: &str,
+        config: &Config,
     ) -> Result<(Vec<String>, Vec<String>)>
     {
-        let allhealthcheck = AllHealthCheck::read_health_check(hosts, ports, parallel).await;
+        let allhealthcheck = AllHealthCheck::read_health_check(hosts, ports, parallel, config).await;
         allhealthcheck.health_check
             .iter()
             .find(|r| r.hostname_port == Some(leader_hostname.to_owned()))
@@ -127,10 +179,11 @@ impl AllHealthCheck {
     pub fn return_dead_nodes_and_under_replicated_tablets_snapshot (
         snapshot_number: &String,
         leader_hostname: &str,
+        config: &Config,
     ) -> Result<(Vec<String>, Vec<String>)>
     {
         let mut allhealthcheck = AllHealthCheck::new();
-        allhealthcheck.health_check = snapshot::read_snapshot_json(snapshot_number, "health-check")?;
+        allhealthcheck.health_check = snapshot::read_snapshot_json(snapshot_number, "health-check", config)?;
         allhealthcheck.health_check
             .iter()
             .find(|r| r.hostname_port == Some(leader_hostname.to_owned()))
@@ -144,24 +197,541 @@ pub async fn print_health_check(
     ports: Vec<&str>,
     parallel: usize,
     options: &Opts,
+    config: &Config,
 ) -> Result<()>
 {
     match options.print_health_check.as_ref().unwrap() {
         Some(snapshot_number) => {
 
             let mut allhealthcheck = AllHealthCheck::new();
-            allhealthcheck.health_check = snapshot::read_snapshot_json(snapshot_number, "health-check")?;
-            let leader_hostname = AllIsLeader::return_leader_snapshot(snapshot_number)?;
+            allhealthcheck.health_check = snapshot::read_snapshot_json(snapshot_number, "health-check", config)?;
+            let leader_hostname = AllIsLeader::return_leader_snapshot(snapshot_number, config)?;
+            let alltabletreplication = if options.details_enable {
+                read_tablet_replication_snapshot(snapshot_number, config)?
+            } else {
+                AllTabletReplication::new()
+            };
+
+            allhealthcheck.print(leader_hostname, &options.details_enable, &alltabletreplication, config)?;
+
+        }
+        None => {
+            let allhealthcheck = AllHealthCheck::read_health_check(&hosts, &ports, parallel, config).await;
+            let leader_hostname = AllIsLeader::return_leader_http(&hosts, &ports, parallel, config).await;
+            let alltabletreplication = if options.details_enable {
+                AllTabletReplication::read_tablet_replication(&hosts, &ports, parallel, config).await
+            } else {
+                AllTabletReplication::new()
+            };
+            allhealthcheck.print(leader_hostname, &options.details_enable, &alltabletreplication, config)?;
+        }
+    }
+    Ok(())
+}
+
+pub async fn health_check_diff(
+    options: &Opts,
+    config: &Config,
+) -> Result<()>
+{
+    if options.begin.is_none() || options.end.is_none() {
+        snapshot::Snapshot::print(config)?;
+    }
+    if options.snapshot_list { return Ok(()) };
+
+    let (begin_snapshot, end_snapshot, _begin_snapshot_row) = snapshot::Snapshot::read_begin_end_snapshot_from_user(options.begin, options.end)?;
+
+    snapshot::Snapshot::print_diff_header(&begin_snapshot, &end_snapshot, config)?;
+
+    let healthcheckdiff = HealthCheckDiff::snapshot_diff(&begin_snapshot, &end_snapshot, config)?;
+    let changes = healthcheckdiff.print(config)?;
+    utility::add_diff_changes(changes, config);
+    healthcheckdiff.print_summary(config)?;
+
+    Ok(())
+}
+
+/// Read the tablet replication data of a snapshot, which is empty for snapshots taken before it was gathered.
+fn read_tablet_replication_snapshot(
+    snapshot_number: &String,
+    config: &Config,
+) -> Result<AllTabletReplication>
+{
+    let mut alltabletreplication = AllTabletReplication::new();
+    if snapshot::snapshot_data_types(snapshot_number)?.iter().any(|data_type| data_type == "tablet_replication")
+    {
+        alltabletreplication.tablet_replication = snapshot::read_snapshot_json(snapshot_number, "tablet_replication", config)?;
+    }
+    Ok(alltabletreplication)
+}
+
+/// The tablet ids of the leaderless tablets in the tablet replication data of the master leader.
+fn leaderless_tablets(
+    alltabletreplication: &AllTabletReplication,
+    leader_hostname: &str,
+) -> Vec<String>
+{
+    alltabletreplication.tablet_replication
+        .iter()
+        .filter(|tablet_replication| tablet_replication.hostname_port.as_deref() == Some(leader_hostname))
+        .flat_map(|tablet_replication| tablet_replication.leaderless_tablets.iter().flatten())
+        .map(|leaderless_tablet| leaderless_tablet.tablet_id.clone())
+        .collect()
+}
+
+/// The table names by tablet id of the leaderless and under replicated tablets in the tablet replication data of the master leader.
+fn table_names(
+    alltabletreplication: &AllTabletReplication,
+    leader_hostname: &str,
+) -> BTreeMap<String, String>
+{
+    let mut table_names = BTreeMap::new();
+    for tablet_replication in alltabletreplication.tablet_replication
+        .iter()
+        .filter(|tablet_replication| tablet_replication.hostname_port.as_deref() == Some(leader_hostname))
+    {
+        for leaderless_tablet in tablet_replication.leaderless_tablets.iter().flatten()
+        {
+            table_names.insert(leaderless_tablet.tablet_id.clone(), leaderless_tablet.table_name.clone());
+        }
+        for under_replicated_tablet in tablet_replication.under_replicated_tablets.iter().flatten()
+        {
+            table_names.insert(under_replicated_tablet.tablet_id.clone(), under_replicated_tablet.table_name.clone());
+        }
+    }
+    table_names
+}
+
+/// The entries that are only in the first list (recovered), and the entries that are only in the second list (new).
+fn recovered_and_new<'a>(
+    first: &'a [String],
+    second: &'a [String],
+) -> (Vec<&'a String>, Vec<&'a String>)
+{
+    (
+        first.iter().filter(|entry| !second.contains(entry)).collect(),
+        second.iter().filter(|entry| !first.contains(entry)).collect(),
+    )
+}
+
+/// The names that can be used in a health assertion with --assert, with what they count or measure.
+/// All are taken from the master leader.
+const HEALTH_QUANTITIES: [(&str, &str); 6] = [
+    ("tserver_down", "the number of tablet servers that are not ALIVE"),
+    ("tserver_alive", "the number of tablet servers that are ALIVE"),
+    ("leaderless_tablets", "the number of tablets without a leader"),
+    ("under_replicated_tablets", "the number of under-replicated tablets"),
+    ("max_clock_skew_ms", "the difference between the highest and lowest physical time of the tablet servers in milliseconds"),
+    ("max_heartbeat_age_s", "the highest time since the last heartbeat of a tablet server in seconds"),
+];
+
+/// Calculate the health quantities of [HEALTH_QUANTITIES] from the data of the master leader.
+/// A quantity that cannot be calculated, such as the clock skew without parsable physical times, is None.
+fn health_quantities(
+    alltabletservers: &AllTabletServers,
+    alltabletreplication: &AllTabletReplication,
+    allclocks: &AllClocks,
+    leader_hostname: &str,
+) -> BTreeMap<&'static str, Option<f64>>
+{
+    let tabletservers: Vec<_> = alltabletservers.tabletservers
+        .iter()
+        .filter(|tabletserver| tabletserver.hostname_port.as_deref() == Some(leader_hostname))
+        .collect();
+    let tablet_replication = alltabletreplication.tablet_replication
+        .iter()
+        .find(|tablet_replication| tablet_replication.hostname_port.as_deref() == Some(leader_hostname));
+    let (skew, _, _) = allclocks.clock_skew(leader_hostname);
+
+    BTreeMap::from([
+        ("tserver_down", Some(tabletservers.iter().filter(|tabletserver| tabletserver.status != "ALIVE").count() as f64)),
+        ("tserver_alive", Some(tabletservers.iter().filter(|tabletserver| tabletserver.status == "ALIVE").count() as f64)),
+        ("leaderless_tablets", tablet_replication.map(|tablet_replication| tablet_replication.leaderless_tablets.iter().flatten().count() as f64)),
+        ("under_replicated_tablets", tablet_replication.map(|tablet_replication| tablet_replication.under_replicated_tablets.iter().flatten().count() as f64)),
+        ("max_clock_skew_ms", skew.and_then(|skew| skew.num_microseconds()).map(|skew| skew as f64 / 1000_f64)),
+        ("max_heartbeat_age_s", tabletservers.iter().map(|tabletserver| tabletserver.time_since_hb_sec as f64).reduce(f64::max)),
+    ])
+}
+
+/// Evaluate the assertions against the health quantities, print the outcome of every assertion, and return the failed ones.
+/// An assertion on a quantity that cannot be calculated fails.
+fn evaluate_health_assertions<'a>(
+    expressions: &'a [String],
+    quantities: &BTreeMap<&str, Option<f64>>,
+    config: &Config,
+) -> Result<Vec<&'a str>>
+{
+    let mut failed = Vec::new();
+    for expression in expressions
+    {
+        let assertion = MetricAssertion::parse(expression)?;
+        let Some(quantity) = quantities.get(assertion.metric.as_str()) else {
+            bail!("Unknown name in assertion: {}, valid are: {} (for metrics, use --assert with --metrics-diff or --print-metrics)",
+                assertion.metric,
+                HEALTH_QUANTITIES.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(", ")
+            );
+        };
+        if assertion.field.is_some()
+        {
+            bail!("Invalid assertion: {}, value and delta can only be used for metrics", expression);
+        }
+        match quantity
+        {
+            Some(value) if assertion.check(*value) => writeln!(config.output(), "{} {}: {} {} {}", "PASS".green(), assertion.metric, value, assertion.operator, assertion.number)?,
+            Some(value) => {
+                writeln!(config.output(), "{} {}: {} {} {}", "FAIL".red(), assertion.metric, value, assertion.operator, assertion.number)?;
+                failed.push(expression.as_str());
+            },
+            None => {
+                writeln!(config.output(), "{} {}: not available", "FAIL".red(), assertion.metric)?;
+                failed.push(expression.as_str());
+            },
+        }
+    }
+    Ok(failed)
+}
+
+/// Check the assertions of --assert against the current state of the cluster, and fail if any doesn't hold,
+/// so yb_stats returns a non-zero exit code, for use as a gate in CI or deploys.
+pub async fn assert_health(
+    hosts: Vec<&str>,
+    ports: Vec<&str>,
+    parallel: usize,
+    options: &Opts,
+    config: &Config,
+) -> Result<()>
+{
+    let leader_hostname = AllIsLeader::return_leader_http(&hosts, &ports, parallel, config).await;
+    if leader_hostname.is_empty()
+    {
+        bail!("Assertion failed: no master leader found");
+    }
+    let alltabletservers = AllTabletServers::read_tabletservers(&hosts, &ports, parallel, config).await;
+    let alltabletreplication = AllTabletReplication::read_tablet_replication(&hosts, &ports, parallel, config).await;
+    let allclocks = AllClocks::read_clocks(&hosts, &ports, parallel, config).await?;
+
+    let quantities = health_quantities(&alltabletservers, &alltabletreplication, &allclocks, &leader_hostname);
+    let failed = evaluate_health_assertions(&options.assert, &quantities, config)?;
+    if !failed.is_empty()
+    {
+        bail!("Assertion failed: {}", failed.join(", "));
+    }
+    Ok(())
+}
+
+/// The factors of the cluster health score, with the default weights, which add up to 100.
+/// The weights can be changed with --health-score-weight factor=weight.
+const HEALTH_SCORE_FACTORS: [HealthScoreFactor; 6] = [
+    HealthScoreFactor { name: "tserver_down", weight: 30.0, start: 0.0, full: 2.0 },
+    HealthScoreFactor { name: "under_replicated_tablets", weight: 20.0, start: 0.0, full: 10.0 },
+    HealthScoreFactor { name: "leaderless_tablets", weight: 20.0, start: 0.0, full: 1.0 },
+    HealthScoreFactor { name: "max_clock_skew_ms", weight: 10.0, start: 50.0, full: 500.0 },
+    HealthScoreFactor { name: "max_drive_used_pct", weight: 10.0, start: 70.0, full: 95.0 },
+    HealthScoreFactor { name: "server_versions", weight: 10.0, start: 1.0, full: 2.0 },
+];
+
+/// The bands of the health score: the lowest score in the band, the name, and the exit code of yb_stats.
+const HEALTH_SCORE_BANDS: [(f64, &str, i32); 3] = [
+    (90.0, "healthy", 0),
+    (70.0, "degraded", 1),
+    (0.0, "critical", 2),
+];
+
+/// The health score factors with the weights changed by --health-score-weight in the form of `factor=weight`.
+fn health_score_factors(
+    weights: &[String],
+) -> Result<Vec<HealthScoreFactor>>
+{
+    let names: Vec<&str> = HEALTH_SCORE_FACTORS.iter().map(|factor| factor.name).collect();
+    let default_weights: Vec<f64> = HEALTH_SCORE_FACTORS.iter().map(|factor| factor.weight).collect();
+    let weights = utility::parse_weights(&weights.iter().map(String::as_str).collect::<Vec<_>>(), &names, &default_weights, "health score factor")?;
+    Ok(HEALTH_SCORE_FACTORS
+        .iter()
+        .zip(weights)
+        .map(|(factor, weight)| HealthScoreFactor { weight, ..factor.clone() })
+        .collect())
+}
+
+/// The quantities of the health score that are not part of [HEALTH_QUANTITIES]:
+/// the highest used percentage of the drives, and the number of different versions of the servers.
+fn health_score_quantities(
+    alldrives: &AllDrives,
+    allversions: &AllVersions,
+) -> [(&'static str, Option<f64>); 2]
+{
+    let versions: BTreeSet<_> = allversions.versions
+        .iter()
+        .filter(|version| !version.version_number.is_empty())
+        .map(|version| (version.version_number.as_str(), version.build_number.as_str()))
+        .collect();
+    [
+        ("max_drive_used_pct", alldrives.drives.iter().flat_map(|drives| drives.drive.iter().flatten()).filter_map(|drive| drive.used_pct()).reduce(f64::max)),
+        ("server_versions", (!versions.is_empty()).then_some(versions.len() as f64)),
+    ]
+}
+
+/// The name, the value if available, and the points subtracted of a health score factor.
+type HealthScoreSubtracted = (&'static str, Option<f64>, f64);
+
+/// Calculate the health score from 0 to 100, with the value and the points subtracted per factor.
+///
+/// A factor subtracts its weight relative to the total of the weights, so the weights don't need to add up to 100.
+/// A factor of which the quantity is not available subtracts its full weight, because an unknown value can not be assumed healthy,
+/// the same as an unknown value fails an assertion.
+fn health_score(
+    quantities: &BTreeMap<&str, Option<f64>>,
+    factors: &[HealthScoreFactor],
+) -> (f64, Vec<HealthScoreSubtracted>)
+{
+    let total_weight: f64 = factors.iter().map(|factor| factor.weight).sum();
+    let subtracted: Vec<_> = factors
+        .iter()
+        .map(|factor| {
+            let value = quantities.get(factor.name).copied().flatten();
+            let fraction = value.map_or(1.0, |value| ((value - factor.start) / (factor.full - factor.start)).clamp(0.0, 1.0));
+            (factor.name, value, fraction * factor.weight / total_weight * 100.0)
+        })
+        .collect();
+    let score = 100.0 - subtracted.iter().map(|(_, _, points)| points).sum::<f64>();
+    (score.max(0.0), subtracted)
+}
+
+/// The name and exit code of the band of a health score.
+fn health_score_band(
+    score: f64,
+) -> (&'static str, i32)
+{
+    HEALTH_SCORE_BANDS
+        .iter()
+        .find(|(lowest_score, _, _)| score >= *lowest_score)
+        .map(|(_, name, exit_code)| (*name, *exit_code))
+        .unwrap_or(("critical", 2))
+}
+
+/// Print the cluster health score, which combines the health quantities into a single number from 0 to 100,
+/// with the factors that subtract points, most first. Returns the exit code of yb_stats, which is the band of the score.
+/// Without a master leader, the score is 0.
+pub async fn print_health_score(
+    hosts: Vec<&str>,
+    ports: Vec<&str>,
+    parallel: usize,
+    options: &Opts,
+    config: &Config,
+) -> Result<i32>
+{
+    let factors = health_score_factors(&options.health_score_weight)?;
+    let (leader_hostname, alltabletservers, alltabletreplication, allclocks, alldrives, allversions) = match options.health_score.as_ref().unwrap() {
+        Some(snapshot_number) => {
+            let leader_hostname = AllIsLeader::return_leader_snapshot(snapshot_number, config)?;
+            let mut alltabletservers = AllTabletServers::new();
+            alltabletservers.tabletservers = snapshot::read_snapshot_json(snapshot_number, "tablet_servers", config)?;
+            let alltabletreplication = read_tablet_replication_snapshot(snapshot_number, config)?;
+            // the data that is missing in a snapshot makes the factor not available.
+            let mut allclocks = AllClocks::new();
+            allclocks.clocks = snapshot::read_snapshot_json(snapshot_number, "clocks", config).unwrap_or_default();
+            let mut alldrives = AllDrives::new();
+            alldrives.drives = snapshot::read_snapshot_json(snapshot_number, "drives", config).unwrap_or_default();
+            let mut allversions = AllVersions::new();
+            allversions.versions = snapshot::read_snapshot_json(snapshot_number, "versions", config).unwrap_or_default();
+            (leader_hostname, alltabletservers, alltabletreplication, allclocks, alldrives, allversions)
+        },
+        None => {
+            let leader_hostname = AllIsLeader::return_leader_http(&hosts, &ports, parallel, config).await;
+            let alltabletservers = AllTabletServers::read_tabletservers(&hosts, &ports, parallel, config).await;
+            let alltabletreplication = AllTabletReplication::read_tablet_replication(&hosts, &ports, parallel, config).await;
+            let allclocks = AllClocks::read_clocks(&hosts, &ports, parallel, config).await?;
+            let alldrives = AllDrives::read_drives(&hosts, &ports, parallel, config).await;
+            let allversions = AllVersions::read_versions(&hosts, &ports, parallel, config).await;
+            (leader_hostname, alltabletservers, alltabletreplication, allclocks, alldrives, allversions)
+        },
+    };
+
+    if leader_hostname.is_empty()
+    {
+        let (band, exit_code) = health_score_band(0.0);
+        writeln!(config.output(), "Cluster health score: 0 ({}): no master leader found", band)?;
+        return Ok(exit_code);
+    }
+
+    let mut quantities = health_quantities(&alltabletservers, &alltabletreplication, &allclocks, &leader_hostname);
+    quantities.extend(health_score_quantities(&alldrives, &allversions));
+    let (score, mut subtracted) = health_score(&quantities, &factors);
+    let score = score.round();
+    let (band, mut exit_code) = health_score_band(score);
+    // the factors with weight that are not available make the score incomplete, which never gives exit code 0.
+    let not_available: Vec<&str> = subtracted
+        .iter()
+        .filter(|(_, value, points)| value.is_none() && *points > 0.0)
+        .map(|(name, _, _)| *name)
+        .collect();
+    if not_available.is_empty()
+    {
+        writeln!(config.output(), "Cluster health score: {} ({})", score, band)?;
+    }
+    else
+    {
+        writeln!(config.output(), "Cluster health score: {} ({}), incomplete: not available: {}", score, band, not_available.join(", "))?;
+        exit_code = exit_code.max(1);
+    }
 
-            allhealthcheck.print(leader_hostname)?;
+    subtracted.sort_by(|a, b| b.2.total_cmp(&a.2));
+    let rows: Vec<Vec<String>> = subtracted
+        .iter()
+        .filter_map(|(name, value, points)| factors.iter().find(|factor| factor.name == *name).map(|factor| vec![
+            name.to_string(),
+            value.map_or_else(|| "not available".to_string(), |value| format!("{}", (value * 10.0).round() / 10.0)),
+            format!("{}-{}", factor.start, factor.full),
+            factor.weight.to_string(),
+            format!("{:.1}", points),
+        ]))
+        .collect();
+    utility::print_table(&["factor", "value", "range", "weight", "subtracted"], &rows, config)?;
+    Ok(exit_code)
+}
 
+impl UnderReplicatedTablet {
+    /// The number of replicas that are missing to satisfy the replication factor.
+    /// If the replication factor is not known, this is the number of replicas on dead nodes.
+    fn missing_replicas(&self) -> i32
+    {
+        match self.replication_factor {
+            Some(replication_factor) => replication_factor - self.live_replicas.len() as i32,
+            None => self.dead_replicas.len() as i32,
+        }
+    }
+}
+
+impl AllUnderReplicatedTablets {
+    pub fn new() -> Self { Default::default() }
+    /// Join the under replicated tablets from the health check with the replicas from the entities of the master leader,
+    /// and sort them by the number of missing replicas, most under replicated first.
+    fn join(
+        allentities: &AllEntities,
+        leader_hostname: &str,
+        dead_nodes: &[String],
+        under_replicated_tablets: &[String],
+        replication_factor: Option<i32>,
+    ) -> AllUnderReplicatedTablets
+    {
+        let mut allunderreplicatedtablets = AllUnderReplicatedTablets::new();
+        let Some(entities) = allentities.entities
+            .iter()
+            .find(|entity| entity.hostname_port.as_deref() == Some(leader_hostname))
+        else {
+            return allunderreplicatedtablets;
+        };
+        for tablet_id in under_replicated_tablets
+        {
+            let mut underreplicatedtablet = UnderReplicatedTablet {
+                tablet_id: tablet_id.clone(),
+                replication_factor,
+                ..Default::default()
+            };
+            if let Some(tablet) = entities.tablets.iter().find(|tablet| tablet.tablet_id == *tablet_id)
+            {
+                underreplicatedtablet.table_name = entities.tables
+                    .iter()
+                    .find(|table| table.table_id == tablet.table_id)
+                    .map(|table| table.table_name.clone())
+                    .unwrap_or_default();
+                for replica in tablet.replicas.as_ref().unwrap_or(&Vec::new())
+                {
+                    if dead_nodes.contains(&replica.server_uuid)
+                    {
+                        underreplicatedtablet.dead_replicas.push(replica.addr.clone());
+                    }
+                    else
+                    {
+                        underreplicatedtablet.live_replicas.push(replica.addr.clone());
+                    }
+                }
+            }
+            allunderreplicatedtablets.under_replicated_tablet.push(underreplicatedtablet);
+        }
+        allunderreplicatedtablets.under_replicated_tablet.sort_by_key(|tablet| std::cmp::Reverse(tablet.missing_replicas()));
+        allunderreplicatedtablets
+    }
+    pub fn print(
+        &self,
+        config: &Config,
+    ) -> Result<()>
+    {
+        if self.under_replicated_tablet.is_empty()
+        {
+            writeln!(config.output(), "No under replicated tablets found.")?;
+            return Ok(());
+        }
+        let rows: Vec<Vec<String>> = self.under_replicated_tablet
+            .iter()
+            .map(|tablet| {
+                let replicas = match tablet.replication_factor {
+                    Some(replication_factor) => format!("{}/{}", tablet.live_replicas.len(), replication_factor),
+                    None => format!("{}/-", tablet.live_replicas.len()),
+                };
+                let reason = if tablet.dead_replicas.is_empty() {
+                    "".to_string()
+                } else {
+                    format!("node {} is down", tablet.dead_replicas.join(", "))
+                };
+                vec![
+                    tablet.tablet_id.clone(),
+                    tablet.table_name.clone(),
+                    replicas,
+                    tablet.live_replicas.join(","),
+                    reason,
+                ]
+            })
+            .collect();
+        utility::print_table(&["tablet_id", "table_name", "replicas", "live_replica_locations", "reason"], &rows, config)?;
+        Ok(())
+    }
+}
+
+/// Get the replication factor for the live replicas from the cluster config of the master leader.
+fn replication_factor(
+    allsysclusterconfigentrypb: &AllSysClusterConfigEntryPB,
+    leader_hostname: &str,
+) -> Option<i32>
+{
+    allsysclusterconfigentrypb.sysclusterconfigentrypb
+        .iter()
+        .find(|row| row.hostname_port.as_deref() == Some(leader_hostname))
+        .and_then(|row| row.replication_info.as_ref())
+        .and_then(|replication_info| replication_info.live_replicas.as_ref())
+        .and_then(|live_replicas| live_replicas.num_replicas)
+}
+
+pub async fn print_under_replicated(
+    hosts: Vec<&str>,
+    ports: Vec<&str>,
+    parallel: usize,
+    options: &Opts,
+    config: &Config,
+) -> Result<()>
+{
+    let mut allentities = AllEntities::new();
+    let mut allsysclusterconfigentrypb = AllSysClusterConfigEntryPB::new();
+    let leader_hostname;
+    let dead_nodes;
+    let under_replicated_tablets;
+
+    match options.print_under_replicated.as_ref().unwrap() {
+        Some(snapshot_number) => {
+            allentities.entities = snapshot::read_snapshot_json(snapshot_number, "entities", config)?;
+            allsysclusterconfigentrypb.sysclusterconfigentrypb = snapshot::read_snapshot_json(snapshot_number, "cluster-config", config)?;
+            leader_hostname = AllIsLeader::return_leader_snapshot(snapshot_number, config)?;
+            (dead_nodes, under_replicated_tablets) = AllHealthCheck::return_dead_nodes_and_under_replicated_tablets_snapshot(snapshot_number, &leader_hostname, config)?;
         }
         None => {
-            let allhealthcheck = AllHealthCheck::read_health_check(&hosts, &ports, parallel).await;
-            let leader_hostname = AllIsLeader::return_leader_http(&hosts, &ports, parallel).await;
-            allhealthcheck.print(leader_hostname)?;
+            allentities = AllEntities::read_entities(&hosts, &ports, parallel, config).await;
+            allsysclusterconfigentrypb = AllSysClusterConfigEntryPB::read_cluster_config(&hosts, &ports, parallel, config).await;
+            leader_hostname = AllIsLeader::return_leader_http(&hosts, &ports, parallel, config).await;
+            (dead_nodes, under_replicated_tablets) = AllHealthCheck::return_dead_nodes_and_under_replicated_tablets_http(&hosts, &ports, parallel, &leader_hostname, config).await?;
         }
     }
+    let replication_factor = replication_factor(&allsysclusterconfigentrypb, &leader_hostname);
+    AllUnderReplicatedTablets::join(&allentities, &leader_hostname, &dead_nodes, &under_replicated_tablets, replication_factor).print(config)?;
+
     Ok(())
 }
 
@@ -170,25 +740,29 @@ impl HealthCheckDiff {
     pub fn snapshot_diff(
         begin_snapshot: &String,
         end_snapshot: &String,
+        config: &Config,
     ) -> Result<HealthCheckDiff>
     {
         let mut healthcheckdiff = HealthCheckDiff::new();
 
         let mut allhealthcheck = AllHealthCheck::new();
-        allhealthcheck.health_check = snapshot::read_snapshot_json(begin_snapshot, "health-check")?;
-        let master_leader = AllIsLeader::return_leader_snapshot(begin_snapshot)?;
-        healthcheckdiff.first_snapshot(allhealthcheck, master_leader);
+        allhealthcheck.health_check = snapshot::read_snapshot_json(begin_snapshot, "health-check", config)?;
+        let master_leader = AllIsLeader::return_leader_snapshot(begin_snapshot, config)?;
+        let alltabletreplication = read_tablet_replication_snapshot(begin_snapshot, config)?;
+        healthcheckdiff.first_snapshot(allhealthcheck, alltabletreplication, master_leader);
 
         let mut allhealthcheck = AllHealthCheck::new();
-        allhealthcheck.health_check = snapshot::read_snapshot_json(end_snapshot, "health-check")?;
-        let master_leader = AllIsLeader::return_leader_snapshot(end_snapshot)?;
-        healthcheckdiff.second_snapshot(allhealthcheck, master_leader);
+        allhealthcheck.health_check = snapshot::read_snapshot_json(end_snapshot, "health-check", config)?;
+        let master_leader = AllIsLeader::return_leader_snapshot(end_snapshot, config)?;
+        let alltabletreplication = read_tablet_replication_snapshot(end_snapshot, config)?;
+        healthcheckdiff.second_snapshot(allhealthcheck, alltabletreplication, master_leader);
 
         Ok(healthcheckdiff)
     }
     fn first_snapshot(
         &mut self,
         allhealthcheck: AllHealthCheck,
+        alltabletreplication: AllTabletReplication,
         master_leader: String,
     )
     {
@@ -217,10 +791,13 @@ impl HealthCheckDiff {
                 .to_vec()
                 .clone();
         }
+        self.first_leaderless_tablets = leaderless_tablets(&alltabletreplication, &master_leader);
+        self.table_names.extend(table_names(&alltabletreplication, &master_leader));
     }
     fn second_snapshot(
         &mut self,
         allhealthcheck: AllHealthCheck,
+        alltabletreplication: AllTabletReplication,
         master_leader: String,
     )
     {
@@ -239,66 +816,122 @@ impl HealthCheckDiff {
                 .to_vec()
                 .clone();
         }
+        self.second_leaderless_tablets = leaderless_tablets(&alltabletreplication, &master_leader);
+        self.table_names.extend(table_names(&alltabletreplication, &master_leader));
     }
+    /// Print the changes of the health check, and return the number of changes: the dead nodes and tablets that are found or recovered.
     pub fn print(
         &self,
-    )
+        config: &Config,
+    ) -> Result<usize>
     {
         if !self.master_found
         {
-            println!("Master leader was not found, skipping health-check diff.");
-            return;
+            writeln!(config.output(), "Master leader was not found, skipping health-check diff.")?;
+            return Ok(0);
         }
+        let mut changes = 0;
         for first_dead_node in &self.first_dead_nodes
         {
             if ! self.second_dead_nodes.iter().any(|r| r == first_dead_node )
             {
-               println!("{} Health Check: dead node removed: {}", "-".to_string().green(), first_dead_node);
+               changes += 1;
+               writeln!(config.output(), "{} Health Check: dead node removed: {}", "-".to_string().green(), first_dead_node)?;
             }
         }
         for second_dead_node in &self.second_dead_nodes
         {
             if ! self.first_dead_nodes.iter().any(|r| r == second_dead_node )
             {
-                println!("{} Health Check: dead node found: {}", "+".to_string().red(), second_dead_node);
+                changes += 1;
+                writeln!(config.output(), "{} Health Check: dead node found: {}", "+".to_string().red(), second_dead_node)?;
             }
         }
-        for first_under_replicated_tablet in &self.first_under_replicated_tablets
+        for (state, first, second) in [
+            ("under replicated", &self.first_under_replicated_tablets, &self.second_under_replicated_tablets),
+            ("leaderless", &self.first_leaderless_tablets, &self.second_leaderless_tablets),
+        ]
         {
-            if ! self.second_under_replicated_tablets.iter().any(|r| r == first_under_replicated_tablet )
+            let (recovered, new) = recovered_and_new(first, second);
+            changes += recovered.len() + new.len();
+            for tablet_id in recovered
             {
-                println!("{} Health Check: under replicated tablet removed: {}", "-".to_string().green(), first_under_replicated_tablet);
+                writeln!(config.output(), "{} Health Check: {} tablet recovered: {}{}", "-".to_string().green(), state, tablet_id, self.table_name(tablet_id))?;
             }
-        }
-        for second_under_replicated_tablet in &self.second_under_replicated_tablets
-        {
-            if ! self.first_under_replicated_tablets.iter().any(|r| r == second_under_replicated_tablet )
+            for tablet_id in new
             {
-                println!("{} Health Check: under replicated tablet found: {}", "+".to_string().red(), second_under_replicated_tablet);
+                writeln!(config.output(), "{} Health Check: {} tablet found: {}{}", "+".to_string().red(), state, tablet_id, self.table_name(tablet_id))?;
             }
         }
+        Ok(changes)
+    }
+    /// The table name of a tablet as a suffix for printing, empty if it is not known.
+    fn table_name(
+        &self,
+        tablet_id: &String,
+    ) -> String
+    {
+        self.table_names
+            .get(tablet_id)
+            .map(|table_name| format!(" (table: {})", table_name))
+            .unwrap_or_default()
+    }
+    /// The number of under replicated and leaderless tablets in both snapshots, with the number recovered and newly found.
+    fn summary(
+        &self,
+    ) -> Vec<String>
+    {
+        let mut summary = Vec::new();
+        for (state, first, second) in [
+            ("under replicated", &self.first_under_replicated_tablets, &self.second_under_replicated_tablets),
+            ("leaderless", &self.first_leaderless_tablets, &self.second_leaderless_tablets),
+        ]
+        {
+            let (recovered, new) = recovered_and_new(first, second);
+            summary.push(format!("{} tablets: {} -> {} ({} recovered, {} new)", state, first.len(), second.len(), recovered.len(), new.len()));
+        }
+        summary
+    }
+    pub fn print_summary(
+        &self,
+        config: &Config,
+    ) -> Result<()>
+    {
+        if !self.master_found
+        {
+            return Ok(());
+        }
+        for line in self.summary()
+        {
+            writeln!(config.output(), "Health Check: {}", line)?;
+        }
+        Ok(())
     }
     pub async fn adhoc_read_first_snapshot(
         &mut self,
         hosts: &Vec<&str>,
         ports: &Vec<&str>,
         parallel: usize,
+        config: &Config,
     )
     {
-        let allhealthcheck = AllHealthCheck::read_health_check(hosts, ports, parallel).await;
-        let master_leader = AllIsLeader::return_leader_http(hosts, ports, parallel).await;
-        self.first_snapshot(allhealthcheck, master_leader);
+        let allhealthcheck = AllHealthCheck::read_health_check(hosts, ports, parallel, config).await;
+        let master_leader = AllIsLeader::return_leader_http(hosts, ports, parallel, config).await;
+        let alltabletreplication = AllTabletReplication::read_tablet_replication(hosts, ports, parallel, config).await;
+        self.first_snapshot(allhealthcheck, alltabletreplication, master_leader);
     }
     pub async fn adhoc_read_second_snapshot(
         &mut self,
         hosts: &Vec<&str>,
         ports: &Vec<&str>,
         parallel: usize,
+        config: &Config,
     )
     {
-        let allhealthcheck = AllHealthCheck::read_health_check(hosts, ports, parallel).await;
-        let master_leader = AllIsLeader::return_leader_http(hosts, ports, parallel).await;
-        self.second_snapshot(allhealthcheck, master_leader);
+        let allhealthcheck = AllHealthCheck::read_health_check(hosts, ports, parallel, config).await;
+        let master_leader = AllIsLeader::return_leader_http(hosts, ports, parallel, config).await;
+        let alltabletreplication = AllTabletReplication::read_tablet_replication(hosts, ports, parallel, config).await;
+        self.second_snapshot(allhealthcheck, alltabletreplication, master_leader);
     }
 }
 
@@ -308,6 +941,109 @@ impl HealthCheckDiff {
 mod tests {
     use super::*;
 
+    #[test]
+    fn unit_health_assertions() {
+        let config = &Config::default();
+        let tabletservers = r#"[{"hostname_port":"yb-1:7000","timestamp":null,"tablet_server_hostname_port":"yb-1:9000","time_since_hb":"0.5s","time_since_hb_sec":0.5,"status":"ALIVE","uptime_seconds":45,"ram_used":"0 B","ram_used_bytes":0,"num_sst_files":0,"total_sst_file_size":"0 B","total_sst_file_size_bytes":0,"uncompressed_sst_file_size":"0 B","uncompressed_sst_file_size_bytes":0,"path_metrics":[],"read_ops_per_sec":0,"write_ops_per_sec":0,"user_tablets_total":1,"user_tablets_leaders":0,"system_tablets_total":12,"system_tablets_leaders":0,"active_tablets":13,"cloud":"local","region":"local","zone":"local1"},
+                                {"hostname_port":"yb-1:7000","timestamp":null,"tablet_server_hostname_port":"yb-2:9000","time_since_hb":"70.0s","time_since_hb_sec":70.0,"status":"DEAD","uptime_seconds":45,"ram_used":"0 B","ram_used_bytes":0,"num_sst_files":0,"total_sst_file_size":"0 B","total_sst_file_size_bytes":0,"uncompressed_sst_file_size":"0 B","uncompressed_sst_file_size_bytes":0,"path_metrics":[],"read_ops_per_sec":0,"write_ops_per_sec":0,"user_tablets_total":1,"user_tablets_leaders":0,"system_tablets_total":12,"system_tablets_leaders":0,"active_tablets":13,"cloud":"local","region":"local","zone":"local2"}]"#;
+        let alltabletservers = AllTabletServers { tabletservers: serde_json::from_str(tabletservers).unwrap() };
+        let tablet_replication = r#"[{"hostname_port":"yb-1:7000","timestamp":null,"leaderless_tablets":[],"under_replicated_tablets":[{"table_name":"t","table_uuid":"table_t","tablet_id":"tablet_1","tablet_replication_count":"2"}]}]"#;
+        let alltabletreplication = AllTabletReplication { tablet_replication: serde_json::from_str(tablet_replication).unwrap() };
+        let quantities = health_quantities(&alltabletservers, &alltabletreplication, &AllClocks::new(), "yb-1:7000");
+        assert_eq!(quantities["tserver_down"], Some(1_f64));
+        assert_eq!(quantities["tserver_alive"], Some(1_f64));
+        assert_eq!(quantities["leaderless_tablets"], Some(0_f64));
+        assert_eq!(quantities["under_replicated_tablets"], Some(1_f64));
+        assert_eq!(quantities["max_clock_skew_ms"], None);
+        assert_eq!(quantities["max_heartbeat_age_s"], Some(70_f64));
+        assert_eq!(quantities.len(), HEALTH_QUANTITIES.len());
+
+        let expressions = ["tserver_down == 0".to_string(), "leaderless_tablets == 0".to_string(), "max_clock_skew_ms < 200".to_string()];
+        assert_eq!(evaluate_health_assertions(&expressions, &quantities, config).unwrap(), vec!["tserver_down == 0", "max_clock_skew_ms < 200"]);
+        assert!(evaluate_health_assertions(&["unknown_name == 0".to_string()], &quantities, config).is_err());
+        assert!(evaluate_health_assertions(&["tserver_down delta == 0".to_string()], &quantities, config).is_err());
+    }
+
+    #[test]
+    fn unit_health_score() {
+        let factors = health_score_factors(&[]).unwrap();
+        let mut quantities = BTreeMap::from([
+            ("tserver_down", Some(0_f64)),
+            ("under_replicated_tablets", Some(0_f64)),
+            ("leaderless_tablets", Some(0_f64)),
+            ("max_clock_skew_ms", Some(2_f64)),
+            ("max_drive_used_pct", Some(45_f64)),
+            ("server_versions", Some(1_f64)),
+        ]);
+        let (score, _) = health_score(&quantities, &factors);
+        assert_eq!(score, 100.0);
+        assert_eq!(health_score_band(score), ("healthy", 0));
+
+        // a dead tablet server subtracts half of its weight, 5 under replicated tablets half of theirs,
+        // and a drive at 82.5% used half of its weight: 15 + 10 + 5.
+        quantities.insert("tserver_down", Some(1_f64));
+        quantities.insert("under_replicated_tablets", Some(5_f64));
+        quantities.insert("max_drive_used_pct", Some(82.5_f64));
+        let (score, subtracted) = health_score(&quantities, &factors);
+        assert_eq!(score, 70.0);
+        assert_eq!(subtracted.iter().find(|(name, _, _)| *name == "tserver_down").unwrap().2, 15.0);
+        assert_eq!(health_score_band(score), ("degraded", 1));
+        // a quantity that is not available subtracts its full weight.
+        quantities.insert("max_clock_skew_ms", None);
+        let (score, subtracted) = health_score(&quantities, &factors);
+        assert_eq!(score, 60.0);
+        assert_eq!(subtracted.iter().find(|(name, _, _)| *name == "max_clock_skew_ms").unwrap(), &("max_clock_skew_ms", None, 10.0));
+        assert_eq!(health_score_band(score), ("critical", 2));
+
+        // the weights are relative: with only tserver_down weighted, a dead tablet server subtracts half of the score,
+        // and a quantity that is not available with weight 0 subtracts nothing.
+        let factors = health_score_factors(&["under_replicated_tablets=0".to_string(), "leaderless_tablets=0".to_string(), "max_clock_skew_ms=0".to_string(), "max_drive_used_pct=0".to_string(), "server_versions=0".to_string()]).unwrap();
+        let (score, _) = health_score(&quantities, &factors);
+        assert_eq!(score, 50.0);
+        assert_eq!(health_score_band(score), ("critical", 2));
+
+        assert!(health_score_factors(&["tserver_down".to_string()]).is_err());
+        assert!(health_score_factors(&["unknown=10".to_string()]).is_err());
+        assert!(health_score_factors(&["tserver_down=-1".to_string()]).is_err());
+        assert!(health_score_factors(&["tserver_down=0".to_string(), "under_replicated_tablets=0".to_string(), "leaderless_tablets=0".to_string(), "max_clock_skew_ms=0".to_string(), "max_drive_used_pct=0".to_string(), "server_versions=0".to_string()]).is_err());
+    }
+
+    #[test]
+    fn unit_health_check_diff_tablets() {
+        let first_health_check = r#"[{"hostname_port":"yb-1:7000","timestamp":null,"dead_nodes":[],"most_recent_uptime":100,"under_replicated_tablets":["tablet_1","tablet_2"],"failed_tablets":null}]"#;
+        let first_tablet_replication = r#"[{"hostname_port":"yb-1:7000","timestamp":null,"leaderless_tablets":[{"table_name":"t","table_uuid":"table_t","tablet_id":"tablet_3"}],"under_replicated_tablets":[{"table_name":"t","table_uuid":"table_t","tablet_id":"tablet_1","tablet_replication_count":"2"}]}]"#;
+        let second_health_check = r#"[{"hostname_port":"yb-1:7000","timestamp":null,"dead_nodes":[],"most_recent_uptime":200,"under_replicated_tablets":["tablet_2","tablet_4"],"failed_tablets":null}]"#;
+
+        let mut healthcheckdiff = HealthCheckDiff::new();
+        healthcheckdiff.first_snapshot(
+            AllHealthCheck { health_check: serde_json::from_str(first_health_check).unwrap() },
+            AllTabletReplication { tablet_replication: serde_json::from_str(first_tablet_replication).unwrap() },
+            "yb-1:7000".to_string(),
+        );
+        // a snapshot without tablet replication data has no leaderless tablets.
+        healthcheckdiff.second_snapshot(
+            AllHealthCheck { health_check: serde_json::from_str(second_health_check).unwrap() },
+            AllTabletReplication::new(),
+            "yb-1:7000".to_string(),
+        );
+        assert_eq!(healthcheckdiff.first_leaderless_tablets, vec!["tablet_3"]);
+        assert!(healthcheckdiff.second_leaderless_tablets.is_empty());
+        assert_eq!(recovered_and_new(&healthcheckdiff.first_under_replicated_tablets, &healthcheckdiff.second_under_replicated_tablets), (vec![&"tablet_1".to_string()], vec![&"tablet_4".to_string()]));
+        assert_eq!(healthcheckdiff.table_name(&"tablet_1".to_string()), " (table: t)");
+        assert_eq!(healthcheckdiff.table_name(&"tablet_4".to_string()), "");
+        assert_eq!(healthcheckdiff.summary(), vec!["under replicated tablets: 2 -> 2 (1 recovered, 1 new)", "leaderless tablets: 1 -> 0 (1 recovered, 0 new)"]);
+        // the changes are the tablets that recovered or are found.
+        assert_eq!(healthcheckdiff.print(&Config::default()).unwrap(), 3);
+
+        let allhealthcheck = AllHealthCheck { health_check: serde_json::from_str(first_health_check).unwrap() };
+        let alltabletreplication = AllTabletReplication { tablet_replication: serde_json::from_str(first_tablet_replication).unwrap() };
+        assert_eq!(allhealthcheck.tablet_rows("yb-1:7000", &alltabletreplication), vec![
+            vec!["under replicated", "tablet_1", "t"],
+            vec!["under replicated", "tablet_2", ""],
+            vec!["leaderless", "tablet_3", "t"],
+        ]);
+    }
+
     #[test]
     fn unit_parse_simple() {
         let json = r#"
@@ -355,21 +1091,70 @@ mod tests {
         assert_eq!(result.most_recent_uptime.unwrap(), 60497);
         assert_eq!(result.under_replicated_tablets.as_ref().unwrap()[0], "3563bc4d087346908b9ac4081449d6bb");
     }
+
+    #[test]
+    fn unit_join_under_replicated_tablets() {
+        let json = r#"
+{
+  "hostname_port": "yb-1.local:7000",
+  "keyspaces": [],
+  "tables": [
+    { "table_id": "8df210c6ccf442bf8b324ab827478505", "keyspace_id": "000033e8000030008000000000000000", "table_name": "t", "state": "RUNNING" }
+  ],
+  "tablets": [
+    {
+      "table_id": "8df210c6ccf442bf8b324ab827478505",
+      "tablet_id": "08b1f1b9fd47407db33c696ebf10d847",
+      "state": "RUNNING",
+      "replicas": [
+        { "type": "VOTER", "server_uuid": "0ec4306a3fe2421c8e891acde43f4276", "addr": "yb-3.local:9100" },
+        { "type": "VOTER", "server_uuid": "a9f3342741564167824c25b3303ac7c1", "addr": "yb-1.local:9100" }
+      ],
+      "leader": "a9f3342741564167824c25b3303ac7c1"
+    },
+    {
+      "table_id": "8df210c6ccf442bf8b324ab827478505",
+      "tablet_id": "3563bc4d087346908b9ac4081449d6bb",
+      "state": "RUNNING",
+      "replicas": [
+        { "type": "VOTER", "server_uuid": "0ec4306a3fe2421c8e891acde43f4276", "addr": "yb-3.local:9100" },
+        { "type": "VOTER", "server_uuid": "a9f3342741564167824c25b3303ac7c1", "addr": "yb-1.local:9100" },
+        { "type": "VOTER", "server_uuid": "eda624cb7e864ff6aa2b25dfc27e64ea", "addr": "yb-2.local:9100" }
+      ],
+      "leader": "a9f3342741564167824c25b3303ac7c1"
+    }
+  ]
+}
+        "#;
+        let mut allentities = AllEntities::new();
+        allentities.entities.push(serde_json::from_str(json).unwrap());
+        let dead_nodes = vec!["0ec4306a3fe2421c8e891acde43f4276".to_string()];
+        let under_replicated_tablets = vec!["3563bc4d087346908b9ac4081449d6bb".to_string(), "08b1f1b9fd47407db33c696ebf10d847".to_string()];
+        let result = AllUnderReplicatedTablets::join(&allentities, "yb-1.local:7000", &dead_nodes, &under_replicated_tablets, Some(3));
+        // the tablet with only one live replica must be sorted first.
+        assert_eq!(result.under_replicated_tablet[0].tablet_id, "08b1f1b9fd47407db33c696ebf10d847");
+        assert_eq!(result.under_replicated_tablet[0].table_name, "t");
+        assert_eq!(result.under_replicated_tablet[0].live_replicas, vec!["yb-1.local:9100"]);
+        assert_eq!(result.under_replicated_tablet[0].dead_replicas, vec!["yb-3.local:9100"]);
+        assert_eq!(result.under_replicated_tablet[1].live_replicas.len(), 2);
+    }
     #[tokio::test]
     async fn integration_parse_master_health_check() {
+        let config = &Config::default();
         let hostname = utility::get_hostname_master();
         let port = utility::get_port_master();
 
-        let allhealthcheck = AllHealthCheck::read_health_check(&vec![&hostname], &vec![&port], 1).await;
+        let allhealthcheck = AllHealthCheck::read_health_check(&vec![&hostname], &vec![&port], 1, config).await;
 
         assert!(!allhealthcheck.health_check.is_empty());
     }
     #[tokio::test]
     async fn integration_parse_tablet_server_health_check() {
+        let config = &Config::default();
         let hostname = utility::get_hostname_tserver();
         let port = utility::get_port_tserver();
 
-        let allhealthcheck = AllHealthCheck::read_health_check(&vec![&hostname], &vec![&port], 1).await;
+        let allhealthcheck = AllHealthCheck::read_health_check(&vec![&hostname], &vec![&port], 1, config).await;
 
         assert!(!allhealthcheck.health_check.is_empty());
     }
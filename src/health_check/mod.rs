@@ -9,6 +9,16 @@
 //! The `/api/v1/health-check` endpoint is only available on the masters, default port 7000.
 //! It is available on all the masters, the leader as well as the followers.
 //!
+//! `--print-under-replicated` joins the under replicated tablets with the replicas from `/dump-entities`,
+//! the dead nodes and the replication factor from `/api/v1/cluster-config`.
+//!
+//! The leaderless tablets are not part of the health check, and are taken from the tablet replication data
+//! of the master leader. With `--details-enable`, `--print-health-check` lists the under replicated and leaderless tablets,
+//! and `--health-check-diff` shows the tablets that recovered or were newly found between two snapshots.
+//!
+//! `--health-score` combines the health quantities of `--assert` with the drives and the versions of the servers
+//! into a weighted score from 0 to 100, and sets the exit code of yb_stats to the band of the score.
+//!
 mod structs;
 mod functions;
 
@@ -3,6 +3,7 @@
 #![allow(non_camel_case_types)]
 
 use chrono::{DateTime, Local};
+use std::collections::BTreeMap;
 
 /// This struct is a wrapper for the HealthCheck struct.
 ///
@@ -52,6 +53,39 @@ pub struct HealthCheckDiff {
     pub second_dead_nodes: Vec<String>,
     pub first_under_replicated_tablets: Vec<String>,
     pub second_under_replicated_tablets: Vec<String>,
+    /// The leaderless tablets are not part of the health check, and are taken from the tablet replication data of the master leader.
+    pub first_leaderless_tablets: Vec<String>,
+    pub second_leaderless_tablets: Vec<String>,
+    /// The table name by tablet id, from the tablet replication data of both snapshots.
+    pub table_names: BTreeMap<String, String>,
     pub master_found: bool,
 }
-
+// under replicated
+/// An under replicated tablet, joined with its replica locations and the replication factor.
+#[derive(Debug, Default)]
+pub struct UnderReplicatedTablet {
+    pub tablet_id: String,
+    pub table_name: String,
+    /// The replication factor from the cluster config, which is not set if the default is used.
+    pub replication_factor: Option<i32>,
+    /// The addresses of the replicas on tablet servers that are alive.
+    pub live_replicas: Vec<String>,
+    /// The addresses of the replicas on tablet servers that are in the dead nodes list.
+    pub dead_replicas: Vec<String>,
+}
+/// This struct is a wrapper for the UnderReplicatedTablet struct.
+#[derive(Debug, Default)]
+pub struct AllUnderReplicatedTablets {
+    pub under_replicated_tablet: Vec<UnderReplicatedTablet>,
+}
+// health score
+/// A factor of the cluster health score: a health quantity that subtracts up to its weight from the score.
+///
+/// The quantity subtracts nothing up to `start`, and the full weight from `full` on, linearly in between.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HealthScoreFactor {
+    pub name: &'static str,
+    pub weight: f64,
+    pub start: f64,
+    pub full: f64,
+}
@@ -3,10 +3,12 @@
 use chrono::Local;
 use std::{time::Instant, sync::mpsc::channel};
 use log::*;
-use anyhow::Result;
+use anyhow::{bail, Result};
 use crate::utility;
+use crate::utility::Config;
 use crate::snapshot;
 use crate::isleader::{AllIsLeader, IsLeader};
+use crate::Opts;
 
 impl IsLeader {
     fn new() -> Self { Default::default() }
@@ -17,13 +19,14 @@ impl AllIsLeader {
         ports: &Vec<&str>,
         snapshot_number: i32,
         parallel: usize,
+        config: &Config,
     ) -> Result<()>
     {
         info!("begin snapshot");
         let timer = Instant::now();
 
-        let allisleader = AllIsLeader::read_isleader(hosts, ports, parallel).await;
-        snapshot::save_snapshot_json(snapshot_number, "isleader", allisleader.isleader)?;
+        let allisleader = AllIsLeader::read_isleader(hosts, ports, parallel, config).await;
+        snapshot::save_snapshot_json(snapshot_number, "isleader", allisleader.isleader, config)?;
 
         info!("end snapshot: {:?}", timer.elapsed());
 
@@ -33,11 +36,12 @@ impl AllIsLeader {
         Default::default()
     }
     pub fn return_leader_snapshot (
-       snapshot_number: &String
+       snapshot_number: &String,
+       config: &Config,
     ) -> Result<String>
     {
         let mut allisleader = AllIsLeader::new();
-        allisleader.isleader = snapshot::read_snapshot_json(snapshot_number, "isleader")?;
+        allisleader.isleader = snapshot::read_snapshot_json(snapshot_number, "isleader", config)?;
         // please note the expect() is necessary to unwrap the option/some()
         // unwrap_or_default() allows to obtain data with '--details-enable' even if the master leader cannot be found
         Ok(allisleader.isleader.iter()
@@ -50,9 +54,10 @@ impl AllIsLeader {
         hosts: &Vec<&str>,
         ports: &Vec<&str>,
         parallel: usize,
+        config: &Config,
     ) -> String
     {
-        let allisleader = AllIsLeader::read_isleader(hosts, ports, parallel).await;
+        let allisleader = AllIsLeader::read_isleader(hosts, ports, parallel, config).await;
         // please note the expect() is necessary to unwrap the option/some()
         // unwrap_or_default() allows to obtain data with '--details-enable' even if the master leader cannot be found
         allisleader.isleader.iter()
@@ -60,10 +65,11 @@ impl AllIsLeader {
             .map(|r| r.hostname_port.as_ref().expect("None found").to_string())
             .unwrap_or_default()
     }
-    async fn read_isleader (
+    pub async fn read_isleader (
         hosts: &Vec<&str>,
         ports: &Vec<&str>,
-        parallel: usize
+        parallel: usize,
+        config: &Config,
     ) -> AllIsLeader
     {
         info!("begin parallel http read");
@@ -77,9 +83,9 @@ impl AllIsLeader {
                     let tx = tx.clone();
                     s.spawn(move |_| {
                         let detail_snapshot_time = Local::now();
-                        let mut isleader = AllIsLeader::read_http(host, port);
+                        let mut isleader = AllIsLeader::read_http(host, port, config);
                         isleader.timestamp = Some(detail_snapshot_time);
-                        isleader.hostname_port = Some(format!("{}:{}", host, port));
+                        isleader.hostname_port = Some(utility::hostname_port(host, port, config));
                         debug!("{:?}",&isleader);
                         tx.send(isleader).expect("error sending data via tx");
                     });
@@ -101,9 +107,10 @@ impl AllIsLeader {
     fn read_http(
         host: &str,
         port: &str,
+        config: &Config,
     ) -> IsLeader
     {
-        let data_from_http = utility::http_get(host, port, "api/v1/is-leader");
+        let data_from_http = utility::http_get_endpoint(host, port, "isleader", config);
         AllIsLeader::parse_isleader(data_from_http)
     }
     // This function parses the http output.
@@ -117,6 +124,56 @@ impl AllIsLeader {
     }
 }
 
+impl AllIsLeader {
+    /// The hostname_port of the masters that claim to be the leader, sorted.
+    fn leaders(&self) -> Vec<String>
+    {
+        let mut leaders: Vec<String> = self.isleader.iter()
+            .filter(|r| r.status == "OK")
+            .filter_map(|r| r.hostname_port.clone())
+            .collect();
+        leaders.sort();
+        leaders.dedup();
+        leaders
+    }
+    /// Cross-check the leadership claims of the masters: exactly one master should claim to be the leader.
+    ///
+    /// More than one leader is a split brain, for example after a network partition, and no leader means no master could be elected.
+    /// Both are an error. `/api/v1/is-leader` does not show the term, so the claims at the moment of reading are compared:
+    /// an old leader that did not learn about a newer term yet is reported too.
+    fn check_leaders(&self) -> Result<String>
+    {
+        let leaders = self.leaders();
+        match leaders.len() {
+            0 => bail!("No master leader found: none of the masters claims to be the leader"),
+            1 => Ok(format!("Master leader: {} (a single master claims to be the leader)", leaders[0])),
+            _ => bail!("Split brain: {} masters claim to be the leader: {}", leaders.len(), leaders.join(", ")),
+        }
+    }
+}
+
+/// Check that exactly one master claims to be the master leader, and fail otherwise, see [AllIsLeader::check_leaders].
+pub async fn check_master_leader(
+    hosts: Vec<&str>,
+    ports: Vec<&str>,
+    parallel: usize,
+    options: &Opts,
+    config: &Config,
+) -> Result<()>
+{
+    let mut allisleader = AllIsLeader::new();
+    match options.check_master_leader.as_ref().unwrap() {
+        Some(snapshot_number) => {
+            allisleader.isleader = snapshot::read_snapshot_json(snapshot_number, "isleader", config)?;
+        },
+        None => {
+            allisleader = AllIsLeader::read_isleader(&hosts, &ports, parallel, config).await;
+        },
+    }
+    writeln!(config.output(), "{}", allisleader.check_leaders()?)?;
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -153,14 +210,25 @@ File not found
         assert_eq!(result.status, "");
     }
 
+    #[test]
+    fn unit_check_leaders() {
+        let isleader = |hostname_port: &str, status: &str| IsLeader { hostname_port: Some(hostname_port.to_string()), status: status.to_string(), ..Default::default() };
+        let allisleader = AllIsLeader { isleader: vec![isleader("yb-2:7000", "OK")] };
+        assert_eq!(allisleader.check_leaders().unwrap(), "Master leader: yb-2:7000 (a single master claims to be the leader)");
+        let allisleader = AllIsLeader { isleader: vec![isleader("yb-3:7000", "OK"), isleader("yb-2:7000", ""), isleader("yb-1:7000", "OK")] };
+        assert_eq!(allisleader.check_leaders().unwrap_err().to_string(), "Split brain: 2 masters claim to be the leader: yb-1:7000, yb-3:7000");
+        assert!(AllIsLeader::new().check_leaders().is_err());
+    }
+
     #[ignore = "the master specified might not be the leader, and thus fail the test"]
     #[tokio::test]
     async fn integration_find_master_leader() {
+        let config = &Config::default();
 
         let hostname = utility::get_hostname_master();
         let port = utility::get_port_master();
 
-        let leader = AllIsLeader::return_leader_http(&vec![&hostname], &vec![&port], 1_usize).await;
+        let leader = AllIsLeader::return_leader_http(&vec![&hostname], &vec![&port], 1_usize, config).await;
         assert!(!leader.is_empty())
     }
 }
\ No newline at end of file
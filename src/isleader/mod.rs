@@ -7,7 +7,9 @@
 //!
 //! The `/api/v1/is-leader` endpoint is only available on the masters, default port 7000.
 //! 
-//! The isleader functionality is a helper module, it is not used directly.
+//! The isleader functionality is mostly a helper module to find the master leader.
+//! It is used directly by `--check-master-leader`, which cross-checks the leadership claims of the masters,
+//! and fails if more than one master claims to be the leader (split brain), or none does.
 //! 
 mod structs;
 mod functions;
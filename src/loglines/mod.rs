@@ -6,6 +6,8 @@
 //! - the masters, default port 7000
 //! - the tablet servers, default port 9000.
 //!
+//! The loglines can also be read from glog log files, which can be compressed with gzip or zstd ([AllLogLines::read_log_files]).
+//!
 mod structs;
 mod functions;
 
@@ -17,10 +17,9 @@ extern crate serde_json;
 extern crate serde_derive;
 extern crate csv;
 
-use clap::Parser;
-use std::collections::HashMap;
-use dotenv::dotenv;
-use anyhow::Result;
+use clap::{CommandFactory, FromArgMatches, Parser};
+use std::{collections::HashMap, sync::{Arc, atomic::Ordering}};
+use anyhow::{bail, Result};
 
 mod snapshot;
 mod statements;
@@ -49,6 +48,12 @@ mod tasks;
 mod tablet_replication;
 mod tablet_server_operations;
 mod drives;
+mod xcluster;
+mod ysql_metrics;
+mod transactions;
+mod custom;
+#[cfg(feature = "tui")]
+mod tui;
 
 // constants
 const DEFAULT_HOSTS: &str = "192.168.66.80,192.168.66.81,192.168.66.82";
@@ -66,11 +71,38 @@ pub struct Opts {
     /// Snapshot input hostnames (comma separated)
     #[arg(short = 'H', long, value_name = "hostname,hostname")]
     hosts: Option<String>,
+    /// Snapshot input hostnames from a file or stdin ('-'), one hostname per line
+    #[arg(long, value_name = "path")]
+    hosts_file: Option<String>,
     /// Snapshot input port numbers (comma separated)
     #[arg(short = 'P', long, value_name = "port,port")]
     ports: Option<String>,
-    /// Snapshot capture parallelism (default 1)
-    #[arg(short = 'p', long, value_name = "nr")]
+    /// Use localhost as host and the default ports that are listening on it, for a local single-node cluster. This overrides the hosts and ports
+    #[arg(long)]
+    local: bool,
+    /// Discover the masters and tablet servers via a single master webserver, such as a load balancer address, and read these instead of the hosts.
+    /// The hosts and ports are used if the discovery fails
+    #[arg(long, value_name = "host:port", conflicts_with = "local")]
+    discover_from: Option<String>,
+    /// Use the pods of a Kubernetes stateful set as hosts, via the DNS names of the headless service: <name>-<ordinal>.<service>.<namespace>.svc.cluster.local.
+    /// This overrides the hosts
+    #[arg(long, value_name = "name", conflicts_with = "local")]
+    sts: Option<String>,
+    /// The number of replicas (pods) of the stateful set of --sts
+    #[arg(long, value_name = "n", default_value = "3", requires = "sts")]
+    replicas: usize,
+    /// The Kubernetes namespace of the stateful set of --sts
+    #[arg(long, value_name = "namespace", default_value = "default", requires = "sts")]
+    namespace: String,
+    /// The headless service of the stateful set of --sts, if it is not named as the stateful set, such as yb-tservers for yb-tserver
+    #[arg(long, value_name = "name", requires = "sts")]
+    sts_service: Option<String>,
+    /// Read and write the options of the previous run (hosts, ports, parallel) in '.env.<name>' instead of '.env', to keep the options of multiple clusters apart
+    #[arg(long, value_name = "name")]
+    profile: Option<String>,
+    /// Snapshot capture parallelism (default 1), or auto: one per host:port combination,
+    /// with at most 2 per host and 16 in total
+    #[arg(short = 'p', long, value_name = "nr|auto")]
     parallel: Option<String>,
     /// Output filter for statistic names as regex
     #[arg(short, long, value_name = "regex")]
@@ -78,24 +110,81 @@ pub struct Opts {
     /// Output filter for table names as regex (requires --details-enable)
     #[arg(short, long, value_name = "regex")]
     table_name_match: Option<String>,
+    /// Snapshot setting to only read the tablet server, ycql, ysql and node_exporter data of the tablet servers with a placement matching the regex,
+    /// such as: gcp.us-west1.* (the placement is cloud.region.zone, as registered with the masters)
+    #[arg(long, value_name = "regex")]
+    placement_match: Option<String>,
     /// Output filter for hostname or ports as regex
     #[arg(long, value_name = "regex")]
     hostname_match: Option<String>,
     /// Output setting to add statistics that are not counters
     #[arg(short, long)]
     gauges_enable: bool,
+    /// Output setting to only show metrics of a metric type: counter, gauge or histogram (can be repeated).
+    /// Selecting histogram adds the percentiles p75, p95, p99 and max; there is no p50, because YugabyteDB histograms do not provide the median
+    #[arg(long, value_name = "counter|gauge|histogram", value_parser = ["counter", "gauge", "histogram"])]
+    metric_type: Vec<String>,
+    /// Output setting for --print-metrics to only show the metrics of a single granularity: server, table or tablet
+    #[arg(long, value_name = "server|table|tablet", value_parser = ["server", "table", "tablet"], requires = "print_metrics")]
+    granularity: Option<String>,
     /// Output setting to increase detail, such as report each table and tablet individually
     #[arg(short, long)]
     details_enable: bool,
+    /// Output setting to sum the tablet metrics per table in the metrics diff (implies --details-enable)
+    #[arg(long)]
+    rollup_table: bool,
     /// Snapshot setting to be as silent as possible, only errors are printed
     #[arg(long)]
     silent: bool,
+    /// Output setting for --snapshot and the diffs of stored snapshots to only print a single summary line, such as: snapshot 42 created: 5 hosts, 0 errors, 1.2s.
+    /// A snapshot with failed requests prints the line as error, and exits with a non-zero exit code.
+    /// The metrics diff prints the number of changed and suppressed metrics, the other diffs print the number of changes: the added, removed and changed rows
+    #[arg(long)]
+    summary_only: bool,
+    /// Print the effective configuration after merging the arguments, the .env file and the defaults, with the source of each setting, and exit
+    #[arg(long)]
+    show_config: bool,
     /// Perform a snapshot (creates stored JSON files)
     #[arg(long)]
     snapshot: bool,
+    /// Snapshot setting for the file format of the metrics, node_exporter and statements data: json (default) or parquet, with typed columns for analysis with other tools.
+    /// The diffs and the other snapshot commands read both formats
+    #[arg(long, value_name = "json|parquet", value_parser = ["json", "parquet"], requires = "snapshot")]
+    format: Option<String>,
+    /// Snapshot setting to take a snapshot every number of seconds, until --snapshot-count snapshots are taken or until interrupted (Ctrl-C)
+    #[arg(long, value_name = "seconds", requires = "snapshot", value_parser = clap::value_parser!(u64).range(1..))]
+    snapshot_every: Option<u64>,
+    /// Snapshot setting for the number of snapshots to take with --snapshot-every
+    #[arg(long, value_name = "nr", requires = "snapshot_every", value_parser = clap::value_parser!(u64).range(1..))]
+    snapshot_count: Option<u64>,
     /// Snapshot add comment in snapshot overview
     #[arg(long, value_name = "\"comment\"")]
     snapshot_comment: Option<String>,
+    /// Retry getting the data of the requests that failed for an existing snapshot, as recorded in its manifest, and add it to the snapshot
+    #[arg(long, value_name = "snapshot number")]
+    snapshot_retry: Option<String>,
+    /// Delete snapshots, such as: 3,5,7-9. This asks for confirmation, unless --yes is set
+    #[arg(long, value_name = "snapshot numbers")]
+    snapshot_delete: Option<String>,
+    /// Export a snapshot to a single JSON file, set with --to
+    #[arg(long, value_name = "snapshot number", requires = "to")]
+    snapshot_export: Option<String>,
+    /// Output setting for the file to export a snapshot to with --snapshot-export
+    #[arg(long, value_name = "file", requires = "snapshot_export")]
+    to: Option<String>,
+    /// Import a snapshot exported with --snapshot-export as a new snapshot
+    #[arg(long, value_name = "file")]
+    snapshot_import: Option<String>,
+    /// Verify a snapshot: read the data, and compare it with the data types and row counts in the manifest of the snapshot
+    #[arg(long, value_name = "snapshot number")]
+    snapshot_verify: Option<String>,
+    /// Migrate the snapshot index and all snapshots to the current snapshot format version.
+    /// Reading a snapshot of an older version migrates its data in memory only
+    #[arg(long)]
+    snapshot_migrate_all: bool,
+    /// Confirm destructive commands such as --snapshot-delete upfront, for use in scripts
+    #[arg(long)]
+    yes: bool,
     /// Create a performance diff report using a begin and an end snapshot number.
     #[arg(long)]
     snapshot_diff: bool,
@@ -105,9 +194,15 @@ pub struct Opts {
     /// Create a metric diff report using a begin and end snapshot number.
     #[arg(long)]
     metrics_diff: bool,
+    /// Create a report of the metric names that appeared or disappeared between a begin and end snapshot number, ignoring the values.
+    #[arg(long)]
+    metrics_schema_diff: bool,
     /// Create an entity diff report using a begin and end snapshot number.
     #[arg(long)]
     entity_diff: bool,
+    /// Create a report of the tablets that moved between tablet servers using a begin and end snapshot number.
+    #[arg(long)]
+    diff_by_tablet_movement: bool,
     /// Create a masters diff report using a begin and end snapshot number.
     #[arg(long)]
     masters_diff: bool,
@@ -126,6 +221,27 @@ pub struct Opts {
     /// Create a versions diff report using a begin and end snapshot number.
     #[arg(long)]
     versions_diff: bool,
+    /// Output setting for --versions-diff to leave out the nodes of which the version did not change
+    #[arg(long, requires = "versions_diff")]
+    only_changed: bool,
+    /// Create a health check diff report of the dead nodes, and the under replicated and leaderless tablets that recovered or were found, using a begin and end snapshot number.
+    #[arg(long)]
+    health_check_diff: bool,
+    /// Create a YSQL webserver connections and statement rates diff report using a begin and end snapshot number.
+    #[arg(long)]
+    ysql_metrics_diff: bool,
+    /// Create a diff report of the queued and running compaction tasks of the tablet servers using a begin and end snapshot number.
+    #[arg(long)]
+    compaction_queues_diff: bool,
+    /// Create a diff report of the memory (/memz) of the masters and tablet servers using a begin and end snapshot number.
+    #[arg(long)]
+    mems_diff: bool,
+    /// Create a diff report of the threads with the most cpu time between a begin and end snapshot number.
+    #[arg(long)]
+    threads_diff: bool,
+    /// Create a diff report of the gflags, vars and cluster-config in unified diff format using a begin and end snapshot number.
+    #[arg(long)]
+    text_diff: bool,
     /// Create an adhoc diff report only for metrics
     #[arg(long)]
     adhoc_metrics_diff: bool,
@@ -135,6 +251,12 @@ pub struct Opts {
     /// Create an adhoc diff report excluding metrics
     #[arg(long)]
     adhoc_nonmetrics_diff: bool,
+    /// Create an adhoc diff report, which is the default action if no other action is specified
+    #[arg(long)]
+    adhoc_diff: bool,
+    /// Output setting for the adhoc diff to only snapshot and report the given data types (comma separated, such as: masters,versions), which can also be gflags and drives
+    #[arg(long, value_name = "type,type")]
+    only: Option<String>,
     /// Lists the snapshots in the yb_stats.snapshots in the current directory.
     #[arg(short = 'l', long)]
     snapshot_list: bool,
@@ -144,36 +266,158 @@ pub struct Opts {
     /// Output setting to specify the end snapshot number for diff report.
     #[arg(short = 'e', long, value_name = "snapshot number")]
     end: Option<i32>,
+    /// Output setting to diff the Nth snapshot from the last against the last snapshot instead of --begin and --end (default 1: the last two snapshots)
+    #[arg(long, value_name = "N", num_args = 0..=1, default_missing_value = "1", conflicts_with_all = ["begin", "end"])]
+    last: Option<usize>,
+    /// Output setting to use the metrics of the given snapshots merged as the begin of a metrics diff, with the gauges averaged and the counters and histograms of the first snapshot
+    #[arg(long, value_name = "nr,nr", value_delimiter = ',', conflicts_with_all = ["begin", "last"])]
+    baseline_snapshots: Vec<i32>,
     /// Print memtrackers data for the given snapshot number
     #[arg(long, value_name = "snapshot number")]
     print_memtrackers: Option<Option<String>>,
+    /// Print memtrackers as a flat list instead of a tree with percentages
+    #[arg(long, requires = "print_memtrackers")]
+    flat: bool,
+    /// Highlight memtrackers in the memtrackers tree that use more than the given percentage of root
+    #[arg(long, value_name = "percentage", requires = "print_memtrackers")]
+    mem_pct_warn: Option<f64>,
+    /// Print the memory (/memz) of the masters and tablet servers for the given snapshot number, or get current
+    #[arg(long, value_name = "snapshot number")]
+    print_mems: Option<Option<String>>,
     /// tail log data
     #[arg(long)]
     tail_log: bool,
+    /// Stop --tail-log after the given number of seconds
+    #[arg(long, value_name = "seconds", requires = "tail_log")]
+    tail_duration: Option<u64>,
+    /// Stop --tail-log after the given number of log lines are shown
+    #[arg(long, value_name = "lines", requires = "tail_log")]
+    tail_lines: Option<usize>,
     /// Print log data for the given snapshot number
     #[arg(long, value_name = "snapshot number")]
     print_log: Option<Option<String>>,
     /// Output log data severity to include: optional: I (use with --print_log)
     #[arg(long, default_value = "WEF")]
     log_severity: String,
+    /// Output log data since a time: a duration ago such as 30s, 10m, 2h or 1d, or a local time such as "2023-01-31 12:00:00" (use with --print-log or --tail-log)
+    #[arg(long, value_name = "time")]
+    log_since: Option<String>,
+    /// Output setting to replace the matches of the regex in the log messages with *** before printing, such as email addresses or connection strings (can be repeated, use with --print-log or --tail-log)
+    #[arg(long, value_name = "regex")]
+    log_redact: Vec<String>,
+    /// Print the log data of glog log files instead, such as yb-tserver.INFO, which can be compressed with gzip (.gz) or zstd (.zst) (can be repeated, use with --print-log)
+    #[arg(long, value_name = "file", requires = "print_log")]
+    log_file: Vec<String>,
     /// Print entity data for snapshot number, or get current.
     #[arg(long, value_name = "snapshot number")]
     print_entities: Option<Option<String>>,
     /// Print master server data for snapshot number, or get current.
     #[arg(long, value_name = "snapshot number")]
     print_masters: Option<Option<String>>,
+    /// Check that exactly one master claims to be the leader for the given snapshot number, or get current, and fail if more than one (split brain) or none does.
+    #[arg(long, value_name = "snapshot number")]
+    check_master_leader: Option<Option<String>>,
+    /// Print metrics for the given snapshot number, or get current.
+    #[arg(long, value_name = "snapshot number")]
+    print_metrics: Option<Option<String>>,
+    /// Output setting for --print-metrics to print the metrics with the biggest absolute delta cluster-wide between the --begin snapshot and the current metrics
+    #[arg(long, requires_all = ["print_metrics", "begin"])]
+    top_movers: bool,
+    /// Output setting for the number of metrics to print with --top-movers, or the number of threads per host with --thread-cpu and --threads-diff
+    #[arg(long, value_name = "nr", default_value = "10")]
+    limit: usize,
+    /// Output setting to only print the metrics outside the ranges in a baseline CSV file (metric,min,max) (use with --print-metrics)
+    #[arg(long, value_name = "file")]
+    baseline: Option<String>,
+    /// Output setting to add a metric computed from the value metrics per host with +, -, * and /, in the metrics diff (from the deltas) and --print-metrics (from the values),
+    /// such as: "name=hit_rate expr=rocksdb_block_cache_hit/(rocksdb_block_cache_hit+rocksdb_block_cache_miss)" (can be repeated)
+    #[arg(long, value_name = "name=<name> expr=<expression>")]
+    compute: Vec<String>,
+    /// Output setting to add metric renames between YugabyteDB versions from a CSV file (old_name,new_name), merged over the built-in renames, for comparing snapshots of different versions in the metrics diff
+    #[arg(long, value_name = "file")]
+    metric_rename_file: Option<String>,
+    /// Output setting to hide the changes in the metrics diff with an absolute delta below the threshold: counter deltas, gauge changes and histogram counts
+    #[arg(long, value_name = "n")]
+    diff_threshold: Option<f64>,
+    /// Output setting to hide the changes in the metrics diff with an absolute percent change below the threshold
+    #[arg(long, value_name = "percentage")]
+    pct_threshold: Option<f64>,
+    /// Output setting for --metrics-diff to only show the error metrics that increased: the counters and histograms with a name
+    /// matching the error pattern (default: error|fail|reject|timeout|timed_out|retry|retries)
+    #[arg(long)]
+    only_errors: bool,
+    /// Output setting to override the error pattern of --only-errors as regex
+    #[arg(long, value_name = "regex", requires = "only_errors")]
+    error_pattern: Option<String>,
+    /// Output setting to sort the metrics diff on the biggest relative (percent) change
+    #[arg(long)]
+    sort_by_pct: bool,
+    /// Output setting to show the unchanged counters of the same metric name prefix or table as a changed metric dimmed in the metrics diff
+    #[arg(long, value_name = "prefix|table", value_parser = ["prefix", "table"])]
+    diff_context: Option<String>,
+    /// Output setting to highlight the hosts in the metrics diff with a delta that is an outlier compared to the other hosts
+    #[arg(long)]
+    highlight_outliers: bool,
+    /// Output setting for the number of standard deviations from the mean a delta must be to be an outlier with --highlight-outliers
+    #[arg(long, value_name = "sigma", default_value = "2")]
+    outlier_sigma: f64,
+    /// Check an assertion on the current cluster state and fail if it doesn't hold, such as: "tserver_down == 0" (can be repeated, names: tserver_down, tserver_alive, leaderless_tablets, under_replicated_tablets, max_clock_skew_ms, max_heartbeat_age_s).
+    /// With --metrics-diff or --print-metrics, check a metric instead of printing: "[metric=]name [value|delta]op number"
+    #[arg(long, value_name = "expression")]
+    assert: Vec<String>,
+    /// Print a cluster health score from 0 to 100 for the given snapshot number, or get current, with the factors that subtract points.
+    /// The exit code is the band of the score: 0 healthy (90 or more), 1 degraded (70 or more), 2 critical
+    #[arg(long, value_name = "snapshot number")]
+    health_score: Option<Option<String>>,
+    /// Output setting to change the weight of a --health-score factor, such as: tserver_down=50 (can be repeated, factors: tserver_down, under_replicated_tablets,
+    /// leaderless_tablets, max_clock_skew_ms, max_drive_used_pct, server_versions)
+    #[arg(long, value_name = "factor=weight", requires = "health_score")]
+    health_score_weight: Vec<String>,
     /// Print tablet server data for snapshot number, or get current.
     #[arg(long, value_name = "snapshot number")]
     print_tablet_servers: Option<Option<String>>,
+    /// Output setting to flag tablet servers that did not heartbeat to the master for longer than the given seconds, and sort on the heartbeat time
+    #[arg(long, value_name = "seconds", requires = "print_tablet_servers")]
+    heartbeat_warn: Option<f64>,
+    /// Output setting to print the tablet servers sorted by a load score of the tablets, leaders, disk and memory, with the dimension that drives the score
+    #[arg(long, requires = "print_tablet_servers")]
+    load_score: bool,
+    /// Output setting to change the weights of the --load-score dimensions, such as: tablets=2,leaders=1,disk=1,memory=1 (a dimension that is not set has the weight 1)
+    #[arg(long, value_name = "dimension=weight,...", requires = "load_score")]
+    load_weights: Option<String>,
     /// Print vars for snapshot number, or get current
     #[arg(long, value_name = "snapshot number")]
     print_vars: Option<Option<String>>,
     /// Print version data for snapshot number, or get current.
     #[arg(long, value_name = "snapshot number")]
     print_version: Option<Option<String>>,
+    /// Check with --print-version that all nodes have at least this version, such as: 2.14.1.0 or 2.14.1.0-b36
+    #[arg(long, value_name = "x.y.z.w", requires = "print_version")]
+    min_version: Option<String>,
     /// Print rpcs for the given snapshot number, or get current.
     #[arg(long, value_name = "snapshot number")]
     print_rpcs: Option<Option<String>>,
+    /// Print the YSQL webserver (default port 13000) connections and statement counts, which are separate from the tablet server metrics, for the given snapshot number, or get current.
+    #[arg(long, value_name = "snapshot number")]
+    print_ysql_metrics: Option<Option<String>>,
+    /// Print the active distributed transactions of the tablets on the tablet servers, oldest first, for the given snapshot number (requires a snapshot with --extra-data), or get current.
+    #[arg(long, value_name = "snapshot number")]
+    print_transactions: Option<Option<String>>,
+    /// Output setting for --print-transactions to only print the transactions with at least this age, such as: 500ms, 30s, 5m
+    #[arg(long, value_name = "duration", requires = "print_transactions")]
+    txn_min_age: Option<String>,
+    /// Print the JSON of the custom endpoint with the given name, read with the path set with --custom-endpoint, or from the snapshot set with --custom-snapshot.
+    #[arg(long, value_name = "name")]
+    print_custom: Option<String>,
+    /// Output setting for --print-custom to print the custom endpoint from the given snapshot number
+    #[arg(long, value_name = "snapshot number", requires = "print_custom")]
+    custom_snapshot: Option<String>,
+    /// Output setting for --print-rpcs to aggregate the active calls by method, caller or state, instead of listing the connections
+    #[arg(long, value_name = "method|caller|state", value_parser = ["method", "caller", "state"], requires = "print_rpcs")]
+    rpc_group_by: Option<String>,
+    /// Output setting for the number of slowest in-flight rpcs per server to keep the trace of (requires --extra-data)
+    #[arg(long, value_name = "nr", default_value = "10")]
+    rpc_trace_limit: usize,
     /// print clocks for the given snapshot number, or get current.
     #[arg(long, value_name = "snapshot_number")]
     print_clocks: Option<Option<String>>,
@@ -183,18 +427,40 @@ pub struct Opts {
     /// Print threads data for the given snapshot number, or get current.
     #[arg(long, value_name = "snapshot number")]
     print_threads: Option<Option<String>>,
+    /// Output setting for --print-threads to print the --limit threads with the most cpu time per host
+    #[arg(long, requires = "print_threads")]
+    thread_cpu: bool,
     /// Print gflags for the given snapshot number, or get current.
     #[arg(long, value_name = "snapshot number")]
     print_gflags: Option<Option<String>>,
+    /// Output setting for --print-gflags to show each gflag once per port with its common value, and the gflags that differ between the hosts separately
+    #[arg(long, requires = "print_gflags")]
+    gflags_collapse: bool,
     /// Print cluster-config for the given snapshot number, or get current.
     #[arg(long, value_name = "snapshot number")]
     print_cluster_config: Option<Option<String>>,
+    /// Print the xCluster replication groups, streams and lag for the given snapshot number, or get current.
+    #[arg(long, value_name = "snapshot number")]
+    print_xcluster: Option<Option<String>>,
+    /// Explore the data of the given snapshot number in a terminal UI (requires the tui feature)
+    #[cfg(feature = "tui")]
+    #[arg(long, value_name = "snapshot number")]
+    tui: Option<String>,
     /// Print health-check for the given snapshot number, or get current.
     #[arg(long, value_name = "snapshot number")]
     print_health_check: Option<Option<String>>,
+    /// Print the under replicated tablets with their replica locations for the given snapshot number, or get current.
+    #[arg(long, value_name = "snapshot number")]
+    print_under_replicated: Option<Option<String>>,
     /// Print the drive info for the given snapshot number, or get current.
     #[arg(long, value_name = "snapshot number")]
     print_drives: Option<Option<String>>,
+    /// Output setting for --print-drives to warn about the drives that are used more than the percentage
+    #[arg(long, value_name = "percentage", default_value = "85", requires = "print_drives")]
+    drive_pct_warn: f64,
+    /// Output setting for --print-drives to warn about the drives with WAL directories that are used more than the percentage
+    #[arg(long, value_name = "percentage", default_value = "70", requires = "print_drives")]
+    wal_drive_pct_warn: f64,
     /// Print the tablet server operations for the given snapshot number, or get current.
     #[arg(long, value_name = "snapshot number")]
     print_tablet_server_operations: Option<Option<String>>,
@@ -210,74 +476,326 @@ pub struct Opts {
     /// UUID for table-detail
     #[arg(long, value_name = "uuid", default_value = "")]
     uuid: String,
+    /// Output setting to show the table detail of the table with the name keyspace.table (or table), instead of --uuid, with --print-table-detail
+    #[arg(long, value_name = "keyspace.table", requires = "print_table_detail")]
+    table_name: Option<String>,
+    /// Output setting to show the peers, leader, term, sizes and operations of a single tablet with --print-tablet-detail
+    #[arg(long, value_name = "tablet id", requires = "print_tablet_detail")]
+    tablet_id: Option<String>,
+    /// Output setting to list all tablets of a tablet server with --print-tablet-detail, such as: yb-1.local:9000.
+    /// With --details-enable, the read and write operations per second of every tablet since the --begin snapshot are added
+    #[arg(long, value_name = "host:port", requires = "print_tablet_detail")]
+    server: Option<String>,
+    /// Output setting to sort the tablet list of --print-tablet-detail without --uuid, or with --server (total_size, sst_size, sst_files, wal_size, intents_size).
+    /// The list of --server is sorted on total_size by default
+    #[arg(long, value_name = "column")]
+    sort_by: Option<String>,
+    /// Output setting to flag tablets with a WAL size above this size in --print-tablet-detail, which indicates replication lag or slow flushes
+    #[arg(long, value_name = "size", default_value = "1G")]
+    wal_size_warn: String,
+    /// Output setting to flag tablets with an intents size above this size in --print-tablet-detail, which indicates long-running transactions (requires a snapshot with --extra-data)
+    #[arg(long, value_name = "size", default_value = "100M")]
+    intents_size_warn: String,
+    /// Output setting to flag the followers of the tablet of --tablet-id in --print-tablet-detail that lag more than this number of ops behind the leader
+    #[arg(long, value_name = "ops", default_value = "1000")]
+    follower_lag_warn: u64,
     /// Snapshot disable gathering of thread stacks from /threadz
     #[arg(long)]
     disable_threads: bool,
     /// Snapshot add very detailed data to snapshot
     #[arg(long)]
     extra_data: bool,
+    /// Snapshot keep the raw response of every endpoint in the raw directory of the snapshot, named by host, port and data type, for reproducing a parsing problem
+    #[arg(long, requires = "snapshot")]
+    keep_raw: bool,
     /// Output setting for the length of the SQL text to display
     #[arg(long, value_name = "nr", default_value = "80")]
     sql_length: usize,
+    /// Output setting to write the yb_stats log records (set with RUST_LOG) as JSON
+    #[arg(long)]
+    log_json: bool,
+    /// Output setting to record the size of every endpoint response, and print the size per data type and the largest responses at the end
+    #[arg(long)]
+    sizes: bool,
+    /// Output setting for the maximum number of rows to print for tabular output and the metrics, gflags, vars, statements, memtrackers, loglines and entities output, 0 means all rows
+    #[arg(long, value_name = "nr", default_value = "0")]
+    max_rows: usize,
+    /// Output setting for the timezone to display timestamps in: local, utc or a timezone name such as Europe/Amsterdam
+    #[arg(long, value_name = "tz|utc|local")]
+    timezone: Option<String>,
+    /// Snapshot setting to use another http endpoint path for a data type, such as: versions=api/v1/version (can be repeated)
+    #[arg(long, value_name = "type=path")]
+    endpoint_override: Vec<String>,
+    /// Snapshot setting to add the JSON of an endpoint that is not natively supported to the snapshot, read from all hosts and ports and stored as custom_<name>, such as: xcluster_safe_time=api/v1/xcluster-safe-time (can be repeated)
+    #[arg(long, value_name = "name=path")]
+    custom_endpoint: Vec<String>,
+    /// Snapshot setting to set the role of a port, which determines the data types read from it, such as: 17000=master (can be repeated, roles: master, tserver, ycql, ysql, node_exporter, all)
+    #[arg(long, value_name = "port=role")]
+    port_role: Vec<String>,
+    /// Snapshot setting to only collect node_exporter series with a label value matching the regex, such as: mountpoint=/data.* (can be repeated, all must match, series without the label are excluded)
+    #[arg(long, value_name = "key=regex")]
+    ne_label_match: Vec<String>,
+    /// Output setting to always write the output of the print and diff commands to the pager ($PAGER, default less) if stdout is a terminal, even if it fits on the screen
+    #[arg(long, conflicts_with = "no_paginate")]
+    paginate: bool,
+    /// Output setting to never write the output to a pager
+    #[arg(long)]
+    no_paginate: bool,
+    /// Output setting to print calculated values such as rates and averages verbatim: full precision and no padding, instead of rounded
+    #[arg(long)]
+    raw: bool,
+    /// Output setting to replace hostnames by pseudonyms (node-1, node-2, ...) in the output
+    #[arg(long)]
+    anonymize: bool,
+    /// Output setting to write the hostname to pseudonym mapping of --anonymize as CSV to a file
+    #[arg(long, value_name = "file", requires = "anonymize")]
+    anonymize_mapping: Option<String>,
+    /// Snapshot setting to not follow http redirects, such as issued by load balancers
+    #[arg(long)]
+    no_follow_redirects: bool,
     /// Get the hostname for the tablet leader of a colocated YSQL database.
     #[arg(long, hide = true, value_name = "ysql colocated database name")]
     get_coloc_leader_host: Option<String>,
 }
 
+/// The name of the diff of stored snapshots that is requested, which is used for the summary line of `--summary-only`.
+fn diff_name(
+    options: &Opts,
+) -> Option<&'static str>
+{
+    [
+        (options.snapshot_diff, "snapshot diff"),
+        (options.snapshot_nonmetrics_diff, "snapshot nonmetrics diff"),
+        (options.metrics_diff, "metrics diff"),
+        (options.metrics_schema_diff, "metrics schema diff"),
+        (options.entity_diff, "entity diff"),
+        (options.diff_by_tablet_movement, "tablet movement diff"),
+        (options.masters_diff, "masters diff"),
+        (options.tablet_servers_diff, "tablet servers diff"),
+        (options.vars_diff, "vars diff"),
+        (options.health_check_diff, "health check diff"),
+        (options.text_diff, "text diff"),
+        (options.node_exporter_diff, "node_exporter diff"),
+        (options.statements_diff, "statements diff"),
+        (options.versions_diff, "versions diff"),
+        (options.ysql_metrics_diff, "ysql metrics diff"),
+        (options.compaction_queues_diff, "compaction queues diff"),
+        (options.mems_diff, "mems diff"),
+        (options.threads_diff, "threads diff"),
+    ].into_iter().find(|(set, _)| *set).map(|(_, name)| name)
+}
+
+/// Whether the output of the command can be written to a pager: the print and diff commands that don't ask for input.
+/// A diff asks for the begin and end snapshot if these are not set, and the adhoc diffs wait for enter.
+fn pageable(
+    options: &Opts,
+) -> bool
+{
+    let diff = diff_name(options).is_some();
+    let print = options.snapshot_list
+        || options.print_memtrackers.is_some()
+        || options.print_mems.is_some()
+        || options.print_metrics.is_some()
+        || options.print_version.is_some()
+        || options.print_threads.is_some()
+        || options.print_entities.is_some()
+        || options.print_masters.is_some()
+        || options.print_tablet_servers.is_some()
+        || options.print_vars.is_some()
+        || options.print_clocks.is_some()
+        || options.print_latencies.is_some()
+        || options.print_rpcs.is_some()
+        || options.print_transactions.is_some()
+        || options.print_custom.is_some()
+        || options.print_ysql_metrics.is_some()
+        || options.print_log.is_some()
+        || options.print_gflags.is_some()
+        || options.print_cluster_config.is_some()
+        || options.print_xcluster.is_some()
+        || options.print_health_check.is_some()
+        || options.print_under_replicated.is_some()
+        || options.print_drives.is_some()
+        || options.print_tablet_server_operations.is_some()
+        || options.print_master_tasks.is_some()
+        || options.print_tablet_detail.is_some()
+        // a table name that is found more than once asks for the table.
+        || (options.print_table_detail.is_some() && options.table_name.is_none());
+    (diff && options.begin.is_some() && options.end.is_some()) || print
+}
+
 /// The entrypoint of the executable.
 #[tokio::main]
 async fn main() -> Result<()>
 {
-    env_logger::init();
     let mut changed_options = HashMap::new();
-    dotenv().ok();
-    let options = Opts::parse();
+    // the matches are kept to tell which options are set on the command line, for --show-config.
+    let matches = Opts::command().get_matches();
+    let mut options = Opts::from_arg_matches(&matches)?;
+    utility::init_logger(options.log_json);
+    if let Some(last) = options.last {
+        let (begin, end) = snapshot::Snapshot::read_last_begin_end(last)?;
+        options.begin = Some(begin);
+        options.end = Some(end);
+    }
+    let dotenv_filename = utility::dotenv_filename(&options.profile)?;
+    dotenv::from_filename(&dotenv_filename).ok();
+
+    // the discovery requests the masters, and uses the same http settings as the other requests.
+    let mut config = utility::Config::from_opts(&options)?;
+    if let Some(baseline_begin) = config.baseline_snapshots.first() {
+        options.begin = Some(*baseline_begin);
+    }
+    custom::parse_custom_endpoints(&options.custom_endpoint)?;
+    let discovered_servers = match &options.discover_from {
+        Some(entry_point) => masters::discover_servers(entry_point, options.silent, &config).await
+            .map_err(|e| eprintln!("Discovery via {} failed, using the hosts and ports instead: {}", entry_point, e))
+            .ok(),
+        None => None,
+    };
+
+    let (hosts, ports) = if options.local {
+        utility::set_local_hosts_ports(options.silent)?
+    } else if let Some(servers) = &discovered_servers {
+        // the roles set with --port-role are added after the discovered roles, so these take precedence.
+        options.port_role.splice(0..0, masters::discovered_port_roles(servers));
+        utility::set_port_roles(&options.port_role, &mut config)?;
+        masters::discovered_hosts_ports(servers, utility::set_ports(&options.ports, &mut changed_options, options.silent))
+    } else if let Some(sts) = &options.sts {
+        (utility::set_statefulset_hosts(sts, &options.sts_service, options.replicas, &options.namespace), utility::set_ports(&options.ports, &mut changed_options, options.silent))
+    } else {
+        (utility::set_hosts(&options.hosts, &options.hosts_file, &mut changed_options, options.silent)?, utility::set_ports(&options.ports, &mut changed_options, options.silent))
+    };
+    let parallel = utility::set_parallel(&options.parallel, &hosts, &ports, &mut changed_options)?;
+    log::debug!("effective options: {:?}", options);
+    if options.show_config
+    {
+        return match utility::print_table(&["setting", "value", "source"], &utility::effective_config(&options, &matches, &hosts, &ports, parallel, &dotenv_filename, discovered_servers.is_some()), &config) {
+            Err(error) if !utility::is_broken_pipe(&error) => Err(error),
+            _ => Ok(()),
+        };
+    }
+    config.placement_hosts = tablet_servers::resolve_placement_match(&options.placement_match, &hosts, &ports, parallel, &config).await?;
+    if options.anonymize && (options.snapshot || options.snapshot_retry.is_some())
+    {
+        bail!("--anonymize only changes the output, and cannot be used with --snapshot or --snapshot-retry");
+    }
+    utility::set_anonymize_hosts(&hosts, &config);
+    // the snapshot tasks are spawned, and share the settings.
+    let config = Arc::new(config);
+    let config = &config;
+    if options.summary_only && !options.snapshot && diff_name(&options).is_none()
+    {
+        bail!("--summary-only can only be used with --snapshot or a diff of stored snapshots");
+    }
+    if options.only_errors && !options.metrics_diff
+    {
+        bail!("--only-errors can only be used with --metrics-diff");
+    }
 
-    let hosts = utility::set_hosts(&options.hosts, &mut changed_options);
-    let ports = utility::set_ports(&options.ports, &mut changed_options);
-    let parallel = utility::set_parallel(&options.parallel, &mut changed_options);
+    if !options.no_paginate && !options.summary_only && pageable(&options)
+    {
+        utility::start_pager(options.paginate, config);
+    }
+    // the output of a diff is counted instead of written, the summary is the number of changes the diff counted,
+    // or the number of lines of its output if it did not count these. The metrics diff prints its own summary.
+    let counted_lines = diff_name(&options)
+        .filter(|_| options.summary_only && !options.metrics_diff)
+        .map(|name| (name, utility::count_output_lines(config)));
 
-    match &options {
-        Opts { snapshot, ..                 } if *snapshot                       => snapshot::perform_snapshot(hosts, ports, parallel, &options).await?,
-        Opts { snapshot_diff, ..            } if *snapshot_diff                  => snapshot::snapshot_diff(&options).await?,
-        Opts { snapshot_nonmetrics_diff, .. } if *snapshot_nonmetrics_diff       => snapshot::snapshot_nonmetrics_diff(&options).await?,
-        Opts { snapshot_list, ..            } if *snapshot_list                  => snapshot::snapshot_diff(&options).await?,
-        Opts { metrics_diff, ..              } if *metrics_diff                    => metrics::metrics_diff(&options).await?,
-        Opts { entity_diff, ..              } if *entity_diff                    => entities::entity_diff(&options).await?,
-        Opts { masters_diff, ..             } if *masters_diff                   => masters::masters_diff(&options).await?,
-        Opts { tablet_servers_diff, ..             } if *tablet_servers_diff                   => tablet_servers::tablet_servers_diff(&options).await?,
-        Opts { vars_diff, ..             } if *vars_diff                   => vars::vars_diff(&options).await?,
-        Opts { node_exporter_diff, ..             } if *node_exporter_diff                   => node_exporter::node_exporter_diff(&options).await?,
-        Opts { statements_diff, ..             } if *statements_diff                   => statements::statements_diff(&options).await?,
-        Opts { versions_diff, ..            } if *versions_diff                  => versions::versions_diff(&options).await?,
-        Opts { print_memtrackers, ..        } if print_memtrackers.is_some()     => memtrackers::print_memtrackers(hosts, ports, parallel, &options).await?,
-        Opts { print_version, ..            } if print_version.is_some()         => versions::print_version(hosts, ports, parallel, &options).await?,
-        Opts { print_threads, ..            } if print_threads.is_some()         => threads::print_threads(hosts, ports, parallel, &options).await?,
-        Opts { print_entities, ..           } if print_entities.is_some()        => entities::print_entities(hosts, ports, parallel, &options).await?,
-        Opts { print_masters, ..            } if print_masters.is_some()         => masters::print_masters(hosts, ports, parallel, &options).await?,
-        Opts { print_tablet_servers, ..     } if print_tablet_servers.is_some()  => tablet_servers::print_tablet_servers(hosts, ports, parallel, &options).await?,
-        Opts { print_vars, ..               } if print_vars.is_some()            => vars::print_vars(hosts, ports, parallel, &options).await?,
-        Opts { print_clocks, ..             } if print_clocks.is_some()          => clocks::print_clocks(hosts, ports, parallel, &options).await?,
-        Opts { print_latencies, ..          } if print_latencies.is_some()       => clocks::print_latencies(hosts, ports, parallel, &options).await?,
-        Opts { print_rpcs, ..               } if print_rpcs.is_some()            => rpcs::print_rpcs(hosts, ports, parallel, &options).await?,
-        Opts { print_log, ..                } if print_log.is_some()             => loglines::print_loglines(hosts, ports, parallel, &options).await?,
-        Opts { tail_log, ..                 } if *tail_log                       => loglines::tail_loglines(hosts, ports, parallel, &options).await?,
-        Opts { adhoc_metrics_diff, ..       } if *adhoc_metrics_diff             => snapshot::adhoc_metrics_diff(hosts, ports, parallel, &options).await?,
-        Opts { adhoc_node_exporter_diff, ..       } if *adhoc_node_exporter_diff             => snapshot::adhoc_node_exporter_diff(hosts, ports, parallel, &options).await?,
-        Opts { adhoc_nonmetrics_diff, ..    } if *adhoc_nonmetrics_diff          => snapshot::adhoc_nonmetrics_diff(hosts, ports, parallel, &options).await?,
-        Opts { print_gflags, ..             } if print_gflags.is_some()          => gflags::print_gflags(hosts, ports, parallel, &options).await?,
-        Opts { print_cluster_config, ..     } if print_cluster_config.is_some()  => cluster_config::print_cluster_config(hosts, ports, parallel, &options).await?,
-        Opts { print_health_check, ..       } if print_health_check.is_some()    => health_check::print_health_check(hosts, ports, parallel, &options).await?,
-        Opts { print_drives, ..       } if print_drives.is_some()    => drives::print_drives(hosts, ports, parallel, &options).await?,
-        Opts { print_tablet_server_operations, ..       } if print_tablet_server_operations.is_some()    => tablet_server_operations::print_operations(hosts, ports, parallel, &options).await?,
-        Opts { print_master_tasks, ..       } if print_master_tasks.is_some()    => tasks::print_tasks(hosts, ports, parallel, &options).await?,
-        Opts { print_table_detail, ..       } if print_table_detail.is_some()    => table_detail::print_table_detail(hosts, ports, parallel, &options).await?,
-        Opts { print_tablet_detail, ..       } if print_tablet_detail.is_some()    => tablet_detail::print_tablet_detail(hosts, ports, parallel, &options).await?,
-        Opts { get_coloc_leader_host, ..    } if get_coloc_leader_host.is_some() => entities::print_coloc_leader_host(hosts, ports, parallel, &options).await?,
-        _                                                                        => snapshot::adhoc_diff(hosts, ports, parallel, &options).await?,
+    let result: Result<i32> = async {
+        let mut exit_code = 0;
+        match &options {
+            Opts { snapshot, ..                 } if *snapshot                       => snapshot::perform_snapshot(hosts, ports, parallel, &options, config).await?,
+            Opts { snapshot_retry, ..           } if snapshot_retry.is_some()        => snapshot::snapshot_retry(parallel, &options, config).await?,
+            Opts { snapshot_delete, ..          } if snapshot_delete.is_some()       => snapshot::snapshot_delete(&options, config)?,
+            Opts { snapshot_export, ..          } if snapshot_export.is_some()       => snapshot::snapshot_export(&options, config)?,
+            Opts { snapshot_import, ..          } if snapshot_import.is_some()       => snapshot::snapshot_import(&options, config)?,
+            Opts { snapshot_verify, ..          } if snapshot_verify.is_some()       => snapshot::snapshot_verify(&options, config)?,
+            Opts { snapshot_migrate_all, ..     } if *snapshot_migrate_all           => snapshot::snapshot_migrate_all(config)?,
+            Opts { snapshot_diff, ..            } if *snapshot_diff                  => snapshot::snapshot_diff(&options, config).await?,
+            Opts { snapshot_nonmetrics_diff, .. } if *snapshot_nonmetrics_diff       => snapshot::snapshot_nonmetrics_diff(&options, config).await?,
+            Opts { snapshot_list, ..            } if *snapshot_list                  => snapshot::snapshot_diff(&options, config).await?,
+            Opts { metrics_diff, ..              } if *metrics_diff                    => metrics::metrics_diff(&options, config).await?,
+            Opts { metrics_schema_diff, ..       } if *metrics_schema_diff             => metrics::metrics_schema_diff(&options, config).await?,
+            Opts { entity_diff, ..              } if *entity_diff                    => entities::entity_diff(&options, config).await?,
+            Opts { diff_by_tablet_movement, .. } if *diff_by_tablet_movement     => entities::tablet_movement_diff(&options, config).await?,
+            Opts { masters_diff, ..             } if *masters_diff                   => masters::masters_diff(&options, config).await?,
+            Opts { tablet_servers_diff, ..             } if *tablet_servers_diff                   => tablet_servers::tablet_servers_diff(&options, config).await?,
+            Opts { vars_diff, ..             } if *vars_diff                   => vars::vars_diff(&options, config).await?,
+            Opts { health_check_diff, ..        } if *health_check_diff              => health_check::health_check_diff(&options, config).await?,
+            Opts { text_diff, ..             } if *text_diff                   => snapshot::text_diff(&options, config).await?,
+            Opts { node_exporter_diff, ..             } if *node_exporter_diff                   => node_exporter::node_exporter_diff(&options, config).await?,
+            Opts { statements_diff, ..             } if *statements_diff                   => statements::statements_diff(&options, config).await?,
+            Opts { versions_diff, ..            } if *versions_diff                  => versions::versions_diff(&options, config).await?,
+            Opts { ysql_metrics_diff, ..        } if *ysql_metrics_diff              => ysql_metrics::ysql_metrics_diff(&options, config).await?,
+            Opts { compaction_queues_diff, ..   } if *compaction_queues_diff         => tablet_server_operations::compaction_queues_diff(&options, config).await?,
+            Opts { mems_diff, ..                } if *mems_diff                      => mems::mems_diff(&options, config).await?,
+            Opts { threads_diff, ..             } if *threads_diff                   => threads::threads_diff(&options, config).await?,
+            Opts { print_memtrackers, ..        } if print_memtrackers.is_some()     => memtrackers::print_memtrackers(hosts, ports, parallel, &options, config).await?,
+            Opts { print_mems, ..               } if print_mems.is_some()            => mems::print_mems(hosts, ports, parallel, &options, config).await?,
+            Opts { print_metrics, ..            } if print_metrics.is_some()         => metrics::print_metrics(hosts, ports, parallel, &options, config).await?,
+            Opts { print_version, ..            } if print_version.is_some()         => versions::print_version(hosts, ports, parallel, &options, config).await?,
+            Opts { print_threads, ..            } if print_threads.is_some()         => threads::print_threads(hosts, ports, parallel, &options, config).await?,
+            Opts { print_entities, ..           } if print_entities.is_some()        => entities::print_entities(hosts, ports, parallel, &options, config).await?,
+            Opts { print_masters, ..            } if print_masters.is_some()         => masters::print_masters(hosts, ports, parallel, &options, config).await?,
+            Opts { check_master_leader, ..      } if check_master_leader.is_some()   => isleader::check_master_leader(hosts, ports, parallel, &options, config).await?,
+            Opts { print_tablet_servers, ..     } if print_tablet_servers.is_some()  => tablet_servers::print_tablet_servers(hosts, ports, parallel, &options, config).await?,
+            Opts { print_vars, ..               } if print_vars.is_some()            => vars::print_vars(hosts, ports, parallel, &options, config).await?,
+            Opts { print_clocks, ..             } if print_clocks.is_some()          => clocks::print_clocks(hosts, ports, parallel, &options, config).await?,
+            Opts { print_latencies, ..          } if print_latencies.is_some()       => clocks::print_latencies(hosts, ports, parallel, &options, config).await?,
+            Opts { print_rpcs, ..               } if print_rpcs.is_some()            => rpcs::print_rpcs(hosts, ports, parallel, &options, config).await?,
+            Opts { print_transactions, ..       } if print_transactions.is_some()    => transactions::print_transactions(hosts, ports, parallel, &options, config).await?,
+            Opts { print_custom, ..             } if print_custom.is_some()          => custom::print_custom(hosts, ports, parallel, &options, config).await?,
+            Opts { print_ysql_metrics, ..       } if print_ysql_metrics.is_some()    => ysql_metrics::print_ysql_metrics(hosts, ports, parallel, &options, config).await?,
+            Opts { print_log, ..                } if print_log.is_some()             => loglines::print_loglines(hosts, ports, parallel, &options, config).await?,
+            Opts { tail_log, ..                 } if *tail_log                       => loglines::tail_loglines(hosts, ports, parallel, &options, config).await?,
+            Opts { adhoc_metrics_diff, ..       } if *adhoc_metrics_diff             => snapshot::adhoc_metrics_diff(hosts, ports, parallel, &options, config).await?,
+            Opts { adhoc_node_exporter_diff, ..       } if *adhoc_node_exporter_diff             => snapshot::adhoc_node_exporter_diff(hosts, ports, parallel, &options, config).await?,
+            Opts { adhoc_nonmetrics_diff, ..    } if *adhoc_nonmetrics_diff          => snapshot::adhoc_nonmetrics_diff(hosts, ports, parallel, &options, config).await?,
+            Opts { adhoc_diff, ..               } if *adhoc_diff                     => snapshot::adhoc_diff(hosts, ports, parallel, &options, config).await?,
+            Opts { print_gflags, ..             } if print_gflags.is_some()          => gflags::print_gflags(hosts, ports, parallel, &options, config).await?,
+            Opts { print_cluster_config, ..     } if print_cluster_config.is_some()  => cluster_config::print_cluster_config(hosts, ports, parallel, &options, config).await?,
+            #[cfg(feature = "tui")]
+            Opts { tui, ..                      } if tui.is_some()                   => tui::tui(&options, config).await?,
+            Opts { print_xcluster, ..           } if print_xcluster.is_some()        => xcluster::print_xcluster(hosts, ports, parallel, &options, config).await?,
+            Opts { print_health_check, ..       } if print_health_check.is_some()    => health_check::print_health_check(hosts, ports, parallel, &options, config).await?,
+            Opts { print_under_replicated, ..   } if print_under_replicated.is_some() => health_check::print_under_replicated(hosts, ports, parallel, &options, config).await?,
+            Opts { print_drives, ..       } if print_drives.is_some()    => drives::print_drives(hosts, ports, parallel, &options, config).await?,
+            Opts { print_tablet_server_operations, ..       } if print_tablet_server_operations.is_some()    => tablet_server_operations::print_operations(hosts, ports, parallel, &options, config).await?,
+            Opts { print_master_tasks, ..       } if print_master_tasks.is_some()    => tasks::print_tasks(hosts, ports, parallel, &options, config).await?,
+            Opts { print_table_detail, ..       } if print_table_detail.is_some()    => table_detail::print_table_detail(hosts, ports, parallel, &options, config).await?,
+            Opts { print_tablet_detail, ..       } if print_tablet_detail.is_some()    => tablet_detail::print_tablet_detail(hosts, ports, parallel, &options, config).await?,
+            Opts { get_coloc_leader_host, ..    } if get_coloc_leader_host.is_some() => entities::print_coloc_leader_host(hosts, ports, parallel, &options, config).await?,
+            Opts { assert, ..                   } if !assert.is_empty()              => health_check::assert_health(hosts, ports, parallel, &options, config).await?,
+            Opts { health_score, ..             } if health_score.is_some()          => exit_code = health_check::print_health_score(hosts, ports, parallel, &options, config).await?,
+            _                                                                        => snapshot::adhoc_diff(hosts, ports, parallel, &options, config).await?,
+        };
+        Ok(exit_code)
+    }.await;
+    // the pager is stopped after all the output, which waits for the user to quit it.
+    utility::stop_pager(config);
+    // a write to a pager that is quit, or to a pipe that is closed such as head, ends the output as normal.
+    let exit_code = match result {
+        Err(error) if utility::is_broken_pipe(&error) => 0,
+        result => result?,
     };
-    // if we are allowed to write, and changed_options does contain values, write them to '.env'
-    utility::dotenv_writer(WRITE_DOTENV, changed_options)?;
+    if let Some((name, lines)) = counted_lines
+    {
+        match *config.diff_changes.lock().unwrap()
+        {
+            Some(changes) => println!("{}: {} changed", name, changes),
+            None => println!("{}: {} lines", name, lines.load(Ordering::Relaxed)),
+        }
+    }
+    utility::print_response_sizes(config)?;
+    utility::write_anonymize_mapping(&options.anonymize_mapping, config)?;
+    // if we are allowed to write, and changed_options does contain values, write them to '.env' or the '.env' file of the profile
+    utility::dotenv_writer(WRITE_DOTENV, changed_options, &dotenv_filename)?;
 
+    // a command can report a state via the exit code, such as the band of --health-score.
+    if exit_code != 0
+    {
+        std::process::exit(exit_code);
+    }
     Ok(())
 }
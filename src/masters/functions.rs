@@ -1,15 +1,18 @@
 //! The impls and functions
 //!
 use chrono::Local;
-use std::{fmt, sync::mpsc::channel, time::Instant};
+use std::{collections::BTreeSet, fmt, sync::mpsc::channel, time::Instant};
 use log::*;
 use colored::*;
-use anyhow::Result;
+use anyhow::{bail, Result};
 
 use crate::isleader::AllIsLeader;
+use crate::cluster_config::AllSysClusterConfigEntryPB;
 use crate::utility;
+use crate::utility::Config;
 use crate::snapshot;
-use crate::masters::{Masters, MastersDiff, PeerRole, MastersDiffFields};
+use crate::masters::{Masters, MastersDiff, PeerRole, MastersDiffFields, GetMasterRegistrationRequestPB, DiscoveredServer};
+use crate::tablet_servers::AllTabletServers;
 use crate::Opts;
 use crate::snapshot::read_snapshot_json;
 
@@ -25,13 +28,14 @@ impl Masters {
         ports: &Vec<&str>,
         snapshot_number: i32,
         parallel: usize,
+        config: &Config,
     ) -> Result<()>
     {
         info!("begin snapshot");
         let timer = Instant::now();
 
-        let masters = Masters::read_masters(hosts, ports, parallel).await;
-        snapshot::save_snapshot_json(snapshot_number, "masters", masters.masters)?;
+        let masters = Masters::read_masters(hosts, ports, parallel, config).await;
+        snapshot::save_snapshot_json(snapshot_number, "masters", masters.masters, config)?;
 
         info!("end snapshot: {:?}", timer.elapsed());
         Ok(())
@@ -43,6 +47,7 @@ impl Masters {
         hosts: &Vec<&str>,
         ports: &Vec<&str>,
         parallel: usize,
+        config: &Config,
     ) -> Masters
     {
         info!("begin parallel http read");
@@ -56,9 +61,9 @@ impl Masters {
                     let tx = tx.clone();
                     s.spawn(move |_| {
                         let detail_snapshot_time = Local::now();
-                        let mut masters = Masters::read_http(host, port);
+                        let mut masters = Masters::read_http(host, port, config);
                         masters.masters.iter_mut().for_each(|r| r.timestamp = Some(detail_snapshot_time));
-                        masters.masters.iter_mut().for_each(|r| r.hostname_port = Some(format!("{}:{}", host, port)));
+                        masters.masters.iter_mut().for_each(|r| r.hostname_port = Some(utility::hostname_port(host, port, config)));
                         tx.send(masters).expect("error sending data via tx");
                     });
                 }
@@ -80,9 +85,10 @@ impl Masters {
     pub fn read_http(
         host: &str,
         port: &str,
+        config: &Config,
     ) -> Masters
     {
-        let data_from_http = utility::http_get(host, port, "api/v1/masters");
+        let data_from_http = utility::http_get_endpoint(host, port, "masters", config);
         Masters::parse_masters(data_from_http, host, port)
     }
     fn parse_masters(
@@ -96,10 +102,15 @@ impl Masters {
                 Masters::new()
             })
     }
+    /// Print the masters.
+    ///
+    /// If the configured placements from the cluster config are known, masters that are placed outside of them are flagged.
     pub fn print(
         &self,
         details_enable: &bool,
         leader_hostname: String,
+        configured_placements: &Option<Vec<(String, String, String)>>,
+        config: &Config,
     ) -> Result<()>
     {
         for row in &self.masters {
@@ -114,18 +125,18 @@ impl Masters {
             // first row
             if *details_enable
             {
-                print!("{} ", row.hostname_port.as_ref().unwrap());
+                write!(config.output(), "{} ", row.hostname_port.as_ref().unwrap())?;
             };
-            print!("{} ", row.instance_id.permanent_uuid);
+            write!(config.output(), "{} ", row.instance_id.permanent_uuid)?;
             // highlighted role
             match row.role.as_ref().unwrap_or(&PeerRole::default())
             {
-                &PeerRole::LEADER => { print!("{} ", "LEADER".to_string().green().bold()) }
-                &PeerRole::FOLLOWER => { print!("{} ", "FOLLOWER".to_string().green()) }
-                &PeerRole::UNKNOWN_ROLE => { print!("{} ", "UNKNOWN_ROLE".to_string().red()) }
-                others => { print!("{} ", others.to_string().yellow())}
+                &PeerRole::LEADER => { write!(config.output(), "{} ", "LEADER".to_string().green().bold())? }
+                &PeerRole::FOLLOWER => { write!(config.output(), "{} ", "FOLLOWER".to_string().green())? }
+                &PeerRole::UNKNOWN_ROLE => { write!(config.output(), "{} ", "UNKNOWN_ROLE".to_string().red())? }
+                others => { write!(config.output(), "{} ", others.to_string().yellow())?}
             }
-            println!("Placement: {}.{}.{}",
+            write!(config.output(), "Placement: {}.{}.{}",
                      row.registration
                          .as_ref()
                          .and_then(|registration| registration.cloud_info.as_ref())
@@ -141,68 +152,74 @@ impl Masters {
                          .and_then(|registration| registration.cloud_info.as_ref())
                          .and_then(|cloud_info| cloud_info.placement_zone.as_ref())
                          .unwrap_or(&"-".to_string())
-            );
+            )?;
+            match configured_placements
+            {
+                Some(placements) if !placement_matches(row, placements) => writeln!(config.output(), " {}", "NOT IN CONFIGURED PLACEMENT".to_string().red().bold())?,
+                Some(_) => writeln!(config.output(), " {}", "placement ok".to_string().green())?,
+                None => writeln!(config.output())?,
+            }
             // second row
             if *details_enable {
-                print!("{} ", row.hostname_port
+                write!(config.output(), "{} ", row.hostname_port
                     .as_ref()
                     .unwrap()
-                );
+                )?;
             };
             // blank space, sequence_no, start_time_us
-            println!("{} Seqno: {} Start time: {}",
+            writeln!(config.output(), "{} Seqno: {} Start time: {}",
                      " ".repeat(32),
                      row.instance_id.instance_seqno,
                      row.instance_id.start_time_us.unwrap_or_default()
-            );
+            )?;
             // third row
             if *details_enable {
-                print!("{} ", row.hostname_port
+                write!(config.output(), "{} ", row.hostname_port
                     .as_ref()
                     .unwrap()
-                );
+                )?;
             };
             // blank space, list of rpc addresses
-            print!("{} RPC addresses: ( ", " ".repeat(32));
+            write!(config.output(), "{} RPC addresses: ( ", " ".repeat(32))?;
             for addresses in row.registration
                 .as_ref()
                 .and_then(|registration| registration.private_rpc_addresses.as_ref())
                 .iter() {
                 for address in *addresses {
-                    print!("{}:{} ", address.host, address.port);
+                    write!(config.output(), "{}:{} ", address.host, address.port)?;
                 }
             };
-            println!(")");
+            writeln!(config.output(), ")")?;
             // fourth row
             if *details_enable {
-                print!("{} ", row.hostname_port
+                write!(config.output(), "{} ", row.hostname_port
                     .as_ref()
                     .unwrap()
-                );
+                )?;
             };
             // blank space, list of http addresses
-            print!("{} HTTP addresses: ( ", " ".repeat(32));
+            write!(config.output(), "{} HTTP addresses: ( ", " ".repeat(32))?;
             for addresses in row.registration
                 .as_ref()
                 .and_then(|registration| registration.http_addresses.as_ref())
                 .iter() {
                 for address in *addresses {
-                    print!("{}:{} ", address.host, address.port);
+                    write!(config.output(), "{}:{} ", address.host, address.port)?;
                 }
             };
-            println!(")");
+            writeln!(config.output(), ")")?;
             // fifth row: only if errors are reported
             if row.error.is_some() {
                 if *details_enable {
-                    print!("{} ", row.hostname_port
+                    write!(config.output(), "{} ", row.hostname_port
                         .as_ref()
                         .unwrap()
-                    );
+                    )?;
                 };
-                println!("{:#?}", row.error
+                writeln!(config.output(), "{:#?}", row.error
                     .as_ref()
                     .unwrap()
-                );
+                )?;
             };
         }
         Ok(())
@@ -214,18 +231,19 @@ impl MastersDiff {
     pub fn snapshot_diff(
         begin_snapshot: &String,
         end_snapshot: &String,
+        config: &Config,
     ) -> Result<MastersDiff>
     {
         let mut mastersdiff = MastersDiff::new();
 
         let mut masters = Masters::new();
-        masters.masters = snapshot::read_snapshot_json(begin_snapshot, "masters")?;
-        let master_leader = AllIsLeader::return_leader_snapshot(begin_snapshot)?;
+        masters.masters = snapshot::read_snapshot_json(begin_snapshot, "masters", config)?;
+        let master_leader = AllIsLeader::return_leader_snapshot(begin_snapshot, config)?;
         mastersdiff.first_snapshot(masters, master_leader);
 
         let mut masters = Masters::new();
-        masters.masters = read_snapshot_json(end_snapshot, "masters")?;
-        let master_leader = AllIsLeader::return_leader_snapshot(end_snapshot)?;
+        masters.masters = read_snapshot_json(end_snapshot, "masters", config)?;
+        let master_leader = AllIsLeader::return_leader_snapshot(end_snapshot, config)?;
         mastersdiff.second_snapshot(masters, master_leader);
 
         Ok(mastersdiff)
@@ -236,6 +254,7 @@ impl MastersDiff {
         master_leader: String,
     )
     {
+        self.first_master_leader = master_leader.clone();
         if master_leader == *"" {
             self.master_found = false;
             return
@@ -308,6 +327,7 @@ impl MastersDiff {
         master_leader: String,
     )
     {
+        self.second_master_leader = master_leader.clone();
         if master_leader == *"" {
             self.master_found = false;
             return
@@ -416,13 +436,49 @@ impl MastersDiff {
                 });
         }
     }
+    /// Return the first and second master leader if the master leader changed between the snapshots.
+    ///
+    /// The masters data does not contain the RAFT term, so a leader change is detected by
+    /// comparing the master leader hostname_port (from /api/v1/is-leader) of both snapshots.
+    /// If the leader is unknown in either snapshot, a change cannot be determined.
+    pub fn leader_change(
+        &self,
+    ) -> Option<(&String, &String)>
+    {
+        if self.first_master_leader.is_empty()
+            || self.second_master_leader.is_empty()
+            || self.first_master_leader == self.second_master_leader
+        {
+            None
+        }
+        else
+        {
+            Some((&self.first_master_leader, &self.second_master_leader))
+        }
+    }
+    /// Print the changes of the masters, and return the number of changes: the added, removed and changed masters, and a leader change.
     pub fn print(
         &self,
-    )
+        config: &Config,
+    ) -> Result<usize>
     {
         if ! self.master_found {
-            println!("Master leader was not found in the hosts specified, skipping masters diff.");
-            return;
+            writeln!(config.output(), "Master leader was not found in the hosts specified, skipping masters diff.")?;
+            return Ok(0);
+        }
+        let mut changes = 0;
+        match self.leader_change() {
+            Some((first_master_leader, second_master_leader)) => {
+                changes += 1;
+                writeln!(config.output(), "{} Master leader changed: {} -> {}",
+                    "*".to_string().yellow(),
+                    first_master_leader,
+                    second_master_leader.yellow(),
+                )?;
+            },
+            None => {
+                writeln!(config.output(), "  Master leader: no leader change ({})", self.second_master_leader)?;
+            },
         }
         for (permanent_uuid, row) in &self.btreemastersdiff {
             debug!("uuid: {}", permanent_uuid);
@@ -450,191 +506,196 @@ impl MastersDiff {
                 debug!("role: {}->{} placement: {}.{}.{}->{}.{}.{}", row.first_role, row.second_role, row.first_placement_cloud, row.first_placement_region, row.first_placement_cloud, row.second_placement_cloud, row.second_placement_region, row.second_placement_cloud);
                 // if the second instance_seqno is zero, it means the permanent_uuid is gone in the second snapshot.
                 // this means the masters is gone.
-                println!("{} Masters:  {} Role: {}, Previous placement: {}.{}.{}",
+                changes += 1;
+                writeln!(config.output(), "{} Masters:  {} Role: {}, Previous placement: {}.{}.{}",
                     "-".to_string().red(),
                     permanent_uuid,
                     row.first_role,
                     row.first_placement_cloud,
                     row.first_placement_region,
                     row.first_placement_zone,
-                );
+                )?;
                 debug!("Seq#:{}->{} Start time:{}->{}", row.first_instance_seqno, row.second_instance_seqno, row.first_start_time_us, row.second_start_time_us);
-                println!("{} Seq#: {}, Start time: {}",
+                writeln!(config.output(), "{} Seq#: {}, Start time: {}",
                     " ".repeat(44),
                     row.first_instance_seqno,
                     row.first_start_time_us,
-                );
+                )?;
                 debug!("rpc: {}->{}", row.first_private_rpc_addresses, row.second_private_rpc_addresses);
-                println!("{} RPC ( {} )",
+                writeln!(config.output(), "{} RPC ( {} )",
                     " ".repeat(44),
                     row.first_private_rpc_addresses,
-                );
+                )?;
                 debug!("http: {}->{}", row.first_http_addresses, row.second_http_addresses);
-                println!("{} HTTP ( {} )",
+                writeln!(config.output(), "{} HTTP ( {} )",
                     " ".repeat(44),
                     row.first_http_addresses,
-                );
+                )?;
             }
             else if row.first_instance_seqno == 0
             {
                 // if the first instance_seqno is zero, then the second_instance_seqno likely is
                 // non-zero, indicating that this master only was alive in the second snapshot.
                 // this means it's a new master.
+                changes += 1;
                 debug!("role: {}->{} placement: {}.{}.{}->{}.{}.{}", row.first_role, row.second_role, row.first_placement_cloud, row.first_placement_region, row.first_placement_cloud, row.second_placement_cloud, row.second_placement_region, row.second_placement_cloud);
-                println!("{} Masters:  {} Role: {}, Placement: {}.{}.{}",
+                writeln!(config.output(), "{} Masters:  {} Role: {}, Placement: {}.{}.{}",
                     "+".to_string().green(),
                     permanent_uuid,
                     row.second_role,
                     row.second_placement_cloud,
                     row.second_placement_region,
                     row.second_placement_zone,
-                );
+                )?;
                 debug!("Seq#:{}->{} Start time:{}->{}", row.first_instance_seqno, row.second_instance_seqno, row.first_start_time_us, row.second_start_time_us);
-                println!("{} Seq#: {}, Start time: {}",
+                writeln!(config.output(), "{} Seq#: {}, Start time: {}",
                     " ".repeat(44),
                     row.second_instance_seqno,
                     row.second_start_time_us,
-                );
+                )?;
                 debug!("rpc: {}->{}", row.first_private_rpc_addresses, row.second_private_rpc_addresses);
-                println!("{} RPC ( {} )",
+                writeln!(config.output(), "{} RPC ( {} )",
                     " ".repeat(44),
                     row.second_private_rpc_addresses,
-                );
+                )?;
                 debug!("http: {}->{}", row.first_http_addresses, row.second_http_addresses);
-                println!("{} HTTP ( {} )",
+                writeln!(config.output(), "{} HTTP ( {} )",
                     " ".repeat(44),
                     row.second_http_addresses,
-                );
+                )?;
             }
             else
             {
                     // okay, so they are not equal.
                     // print out the master, and highlight the changes
+                    changes += 1;
 
                     // first row
-                    print!("{} Masters:  {} ",
+                    write!(config.output(), "{} Masters:  {} ",
                         "=".to_string().yellow(),
                         permanent_uuid,
-                    );
+                    )?;
                     debug!("Role: {}->{} Placement: {}.{}.{}->{}.{}.{}", row.first_role, row.second_role, row.first_placement_cloud, row.first_placement_region, row.first_placement_cloud, row.second_placement_cloud, row.second_placement_region, row.second_placement_cloud);
                     if row.first_role != row.second_role
                     {
-                        print!("Role: {}->{} ",
+                        write!(config.output(), "Role: {}->{} ",
                             row.first_role.to_string().yellow(),
                             row.second_role.to_string().yellow(),
-                        );
+                        )?;
                     }
                     else
                     {
-                        print!("Role: {} ", row.second_role);
+                        write!(config.output(), "Role: {} ", row.second_role)?;
                     };
                     if row.first_placement_cloud != row.second_placement_cloud
                     {
-                        print!("{}->{}.",
+                        write!(config.output(), "{}->{}.",
                             row.first_placement_cloud.to_string().yellow(),
                             row.second_placement_cloud.to_string().yellow(),
-                        );
+                        )?;
                     }
                     else
                     {
-                        print!("Placement: {}.", row.second_placement_cloud);
+                        write!(config.output(), "Placement: {}.", row.second_placement_cloud)?;
                     };
                     if row.first_placement_region != row.second_placement_region
                     {
-                        print!("{}->{}.",
+                        write!(config.output(), "{}->{}.",
                             row.first_placement_region.to_string().yellow(),
                             row.second_placement_region.to_string().yellow(),
-                        );
+                        )?;
                     }
                     else
                     {
-                        print!("{}.", row.second_placement_region);
+                        write!(config.output(), "{}.", row.second_placement_region)?;
                     };
                     if row.first_placement_zone != row.second_placement_zone
                     {
-                        println!("{}->{}",
+                        writeln!(config.output(), "{}->{}",
                             row.first_placement_zone.to_string().yellow(),
                             row.second_placement_zone.to_string().yellow(),
-                        );
+                        )?;
                     }
                     else
                     {
-                        println!("{}", row.second_placement_zone);
+                        writeln!(config.output(), "{}", row.second_placement_zone)?;
                     };
                     // second row
                     debug!("Seq#: {}->{} Start time: {}->{}", row.first_instance_seqno, row.second_instance_seqno, row.first_start_time_us, row.second_start_time_us);
                     if row.first_instance_seqno != row.second_instance_seqno
                     {
-                        print!("{} Seq#: {}->{} ",
+                        write!(config.output(), "{} Seq#: {}->{} ",
                             " ".repeat(44),
                             row.first_instance_seqno.to_string().yellow(),
                             row.second_instance_seqno.to_string().yellow(),
-                        );
+                        )?;
                     }
                     else
                     {
-                        print!("{} Seq#: {} ",
+                        write!(config.output(), "{} Seq#: {} ",
                             " ".repeat(44),
                             row.second_instance_seqno,
-                        );
+                        )?;
                     }
                     if row.first_start_time_us != row.second_start_time_us
                     {
-                        println!("Start time: {}->{}",
+                        writeln!(config.output(), "Start time: {}->{}",
                             row.first_start_time_us.to_string().yellow(),
                             row.second_start_time_us.to_string().yellow()
-                        );
+                        )?;
                     }
                     else
                     {
-                        println!("Start time: {}", row.second_start_time_us);
+                        writeln!(config.output(), "Start time: {}", row.second_start_time_us)?;
                     }
                     // third row
                     debug!("rpc: {} - {}", row.first_http_addresses.clone(), row.second_http_addresses.clone());
                     if row.first_private_rpc_addresses != row.second_private_rpc_addresses
                     {
-                        println!("{} RPC: {}->{}",
+                        writeln!(config.output(), "{} RPC: {}->{}",
                             " ".repeat(44),
                             row.first_private_rpc_addresses.clone().yellow(),
                             row.second_private_rpc_addresses.clone().yellow(),
-                        );
+                        )?;
                     }
                     else
                     {
-                        println!("{} RPC: {}",
+                        writeln!(config.output(), "{} RPC: {}",
                             " ".repeat(44),
                             row.second_private_rpc_addresses,
-                        );
+                        )?;
                     }
                     // fourth row
                     debug!("http: {} - {}", row.first_http_addresses.clone(), row.second_http_addresses.clone());
                     if row.first_http_addresses != row.second_http_addresses
                     {
-                        println!("{} HTTP: {}->{}",
+                        writeln!(config.output(), "{} HTTP: {}->{}",
                             " ".repeat(44),
                             row.first_http_addresses.clone().yellow(),
                             row.second_http_addresses.clone().yellow(),
-                        );
+                        )?;
                     }
                     else
                     {
-                        println!("{} HTTP: {}",
+                        writeln!(config.output(), "{} HTTP: {}",
                             " ".repeat(44),
                             row.second_http_addresses,
-                        );
+                        )?;
                     }
             }
         }
+        Ok(changes)
     }
     pub async fn adhoc_read_first_snapshot(
         &mut self,
         hosts: &Vec<&str>,
         ports: &Vec<&str>,
         parallel: usize,
+        config: &Config,
     )
     {
-        let masters = Masters::read_masters(hosts, ports, parallel).await;
-        let master_leader = AllIsLeader::return_leader_http(hosts, ports, parallel).await;
+        let masters = Masters::read_masters(hosts, ports, parallel, config).await;
+        let master_leader = AllIsLeader::return_leader_http(hosts, ports, parallel, config).await;
         self.first_snapshot(masters, master_leader);
     }
     pub async fn adhoc_read_second_snapshot(
@@ -642,27 +703,32 @@ impl MastersDiff {
         hosts: &Vec<&str>,
         ports: &Vec<&str>,
         parallel: usize,
+        config: &Config,
     )
     {
-        let masters = Masters::read_masters(hosts, ports, parallel).await;
-        let master_leader = AllIsLeader::return_leader_http(hosts, ports, parallel).await;
+        let masters = Masters::read_masters(hosts, ports, parallel, config).await;
+        let master_leader = AllIsLeader::return_leader_http(hosts, ports, parallel, config).await;
         self.second_snapshot(masters, master_leader);
     }
 }
 
 pub async fn masters_diff(
     options: &Opts,
+    config: &Config,
 ) -> Result<()>
 {
     if options.begin.is_none() || options.end.is_none() {
-        snapshot::Snapshot::print()?;
+        snapshot::Snapshot::print(config)?;
     }
     if options.snapshot_list { return Ok(()) };
 
     let (begin_snapshot, end_snapshot, _begin_snapshot_row) = snapshot::Snapshot::read_begin_end_snapshot_from_user(options.begin, options.end)?;
 
-    let mastersdiff = MastersDiff::snapshot_diff(&begin_snapshot, &end_snapshot)?;
-    mastersdiff.print();
+    snapshot::Snapshot::print_diff_header(&begin_snapshot, &end_snapshot, config)?;
+
+    let mastersdiff = MastersDiff::snapshot_diff(&begin_snapshot, &end_snapshot, config)?;
+    let changes = mastersdiff.print(config)?;
+    utility::add_diff_changes(changes, config);
 
     Ok(())
 }
@@ -672,31 +738,180 @@ pub async fn print_masters(
     ports: Vec<&str>,
     parallel: usize,
     options: &Opts,
+    config: &Config,
 ) -> Result<()>
 {
     match options.print_masters.as_ref().unwrap() {
         Some(snapshot_number) => {
 
             let mut masters = Masters::new();
-            masters.masters = snapshot::read_snapshot_json(snapshot_number, "masters")?;
-            let leader_hostname = AllIsLeader::return_leader_snapshot(snapshot_number)?;
-            masters.print(&options.details_enable, leader_hostname)?;
+            masters.masters = snapshot::read_snapshot_json(snapshot_number, "masters", config)?;
+            let leader_hostname = AllIsLeader::return_leader_snapshot(snapshot_number, config)?;
+            // older snapshots might not contain the cluster config, then the placement is shown without the check.
+            let mut allsysclusterconfigentrypb = AllSysClusterConfigEntryPB::new();
+            allsysclusterconfigentrypb.sysclusterconfigentrypb = snapshot::read_snapshot_json(snapshot_number, "cluster-config", config)
+                .unwrap_or_else(|e| {
+                    info!("Cluster config not available in snapshot {}: {}", snapshot_number, e);
+                    Vec::new()
+                });
+            let configured_placements = allsysclusterconfigentrypb.configured_placements(&leader_hostname);
+            masters.print(&options.details_enable, leader_hostname, &configured_placements, config)?;
 
         }
         None => {
-            let masters = Masters::read_masters(&hosts, &ports, parallel).await;
-            let leader_hostname = AllIsLeader::return_leader_http(&hosts, &ports, parallel).await;
-            masters.print(&options.details_enable, leader_hostname)?;
+            let masters = Masters::read_masters(&hosts, &ports, parallel, config).await;
+            let leader_hostname = AllIsLeader::return_leader_http(&hosts, &ports, parallel, config).await;
+            let allsysclusterconfigentrypb = AllSysClusterConfigEntryPB::read_cluster_config(&hosts, &ports, parallel, config).await;
+            let configured_placements = allsysclusterconfigentrypb.configured_placements(&leader_hostname);
+            masters.print(&options.details_enable, leader_hostname, &configured_placements, config)?;
         }
     }
     Ok(())
 }
 
+/// Return if the placement of a master matches one of the configured placements (cloud, region, zone).
+/// A placement field that is not set in the configured placement matches any value.
+fn placement_matches(
+    master: &GetMasterRegistrationRequestPB,
+    configured_placements: &[(String, String, String)],
+) -> bool
+{
+    let cloud_info = master.registration
+        .as_ref()
+        .and_then(|registration| registration.cloud_info.as_ref());
+    let placement_cloud = cloud_info.and_then(|cloud_info| cloud_info.placement_cloud.clone()).unwrap_or_default();
+    let placement_region = cloud_info.and_then(|cloud_info| cloud_info.placement_region.clone()).unwrap_or_default();
+    let placement_zone = cloud_info.and_then(|cloud_info| cloud_info.placement_zone.clone()).unwrap_or_default();
+
+    configured_placements
+        .iter()
+        .any(|(cloud, region, zone)| {
+            (cloud.is_empty() || *cloud == placement_cloud)
+                && (region.is_empty() || *region == placement_region)
+                && (zone.is_empty() || *zone == placement_zone)
+        })
+}
+
+/// The servers of the cluster: the masters from `/api/v1/masters` with the first of their http addresses,
+/// and the tablet servers from `/api/v1/tablet-servers`, which lists the tablet servers by their http address.
+fn discovered_servers(
+    masters: &Masters,
+    alltabletservers: &AllTabletServers,
+) -> Vec<DiscoveredServer>
+{
+    let masters = masters.masters
+        .iter()
+        .filter_map(|master| master.registration.as_ref()?.http_addresses.as_ref()?.first())
+        .map(|http_address| DiscoveredServer { role: "master", host: http_address.host.clone(), port: http_address.port.to_string() });
+    let tabletservers = alltabletservers.tabletservers
+        .iter()
+        .filter_map(|tabletserver| tabletserver.tablet_server_hostname_port.as_deref()?.rsplit_once(':'))
+        .map(|(host, port)| DiscoveredServer { role: "tserver", host: host.to_string(), port: port.to_string() });
+    let mut servers: Vec<DiscoveredServer> = masters.chain(tabletservers).collect();
+    servers.sort();
+    servers.dedup();
+    servers
+}
+
+/// Discover the masters and tablet servers of the cluster via a single master, for `--discover-from`,
+/// so the cluster can be read when only a single entry point, such as a load balancer address, is known.
+///
+/// Every master lists the masters, but only the master leader lists the tablet servers,
+/// therefore the tablet servers are read from the discovered masters until a master lists them.
+/// The discovered servers are printed, unless silent is set.
+pub async fn discover_servers(
+    entry_point: &str,
+    silent: bool,
+    config: &Config,
+) -> Result<Vec<DiscoveredServer>>
+{
+    let (host, port) = entry_point.rsplit_once(':').unwrap_or((entry_point, "7000"));
+    let masters = Masters::read_masters(&vec![host], &vec![port], 1, config).await;
+    if masters.masters.is_empty()
+    {
+        bail!("No masters found via {}:{}, the address must be the webserver of a master", host, port);
+    }
+    let mut alltabletservers = AllTabletServers::new();
+    for master in discovered_servers(&masters, &AllTabletServers::new())
+    {
+        alltabletservers = AllTabletServers::read_tabletservers(&vec![master.host.as_str()], &vec![master.port.as_str()], 1, config).await;
+        if !alltabletservers.tabletservers.is_empty() { break };
+    }
+    let servers = discovered_servers(&masters, &alltabletservers);
+    info!("discovered via {}:{}: {:?}", host, port, servers);
+    if !silent
+    {
+        for role in ["master", "tserver"]
+        {
+            eprintln!("Discovered {} servers via {}:{}: {}", role, host, port, servers.iter()
+                .filter(|server| server.role == role)
+                .map(|server| format!("{}:{}", server.host, server.port))
+                .collect::<Vec<_>>()
+                .join(", "));
+        }
+    }
+    Ok(servers)
+}
+
+/// The hosts and ports to read for the discovered servers: the hosts of the servers,
+/// and the ports with the webserver ports of the servers added, so the other ports, such as ysql and ycql, are read too.
+pub fn discovered_hosts_ports(
+    servers: &[DiscoveredServer],
+    ports: Vec<&'static str>,
+) -> (Vec<&'static str>, Vec<&'static str>)
+{
+    let hosts: BTreeSet<&str> = servers.iter().map(|server| server.host.as_str()).collect();
+    let hosts = hosts.into_iter().map(|host| &*Box::leak(host.to_string().into_boxed_str())).collect();
+    let mut ports = ports;
+    for server in servers
+    {
+        if !ports.contains(&server.port.as_str())
+        {
+            ports.push(Box::leak(server.port.clone().into_boxed_str()));
+        }
+    }
+    (hosts, ports)
+}
+
+/// The port roles (`port=role`) of the webserver ports of the discovered servers that are not the default ports,
+/// so only the data of the role is requested from these ports.
+pub fn discovered_port_roles(
+    servers: &[DiscoveredServer],
+) -> Vec<String>
+{
+    let port_roles: BTreeSet<String> = servers
+        .iter()
+        .filter(|server| !matches!((server.role, server.port.as_str()), ("master", "7000") | ("tserver", "9000")))
+        .map(|server| format!("{}={}", server.port, server.role))
+        .collect();
+    port_roles.into_iter().collect()
+}
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn unit_discovered_servers() {
+        let masters: Masters = serde_json::from_str(r#"{"masters": [
+            {"instance_id": {"permanent_uuid": "1", "instance_seqno": 1}, "registration": {"http_addresses": [{"host": "yb-2.local", "port": 7000}]}, "role": "FOLLOWER"},
+            {"instance_id": {"permanent_uuid": "2", "instance_seqno": 1}, "registration": {"http_addresses": [{"host": "yb-1.local", "port": 17000}]}, "role": "LEADER"},
+            {"instance_id": {"permanent_uuid": "3", "instance_seqno": 1}, "error": {"code": "NETWORK_ERROR"}}
+        ]}"#).unwrap();
+        let tabletserver = |hostname_port: &str| serde_json::from_str(&format!(r#"{{"tablet_server_hostname_port": "{}", "time_since_hb": "0.5s", "time_since_hb_sec": 0.5, "status": "ALIVE", "uptime_seconds": 45, "ram_used": "26.21 MB", "ram_used_bytes": 26214400, "num_sst_files": 0, "total_sst_file_size": "0 B", "total_sst_file_size_bytes": 0, "uncompressed_sst_file_size": "0 B", "uncompressed_sst_file_size_bytes": 0, "path_metrics": [], "read_ops_per_sec": 0, "write_ops_per_sec": 0, "user_tablets_total": 1, "user_tablets_leaders": 0, "system_tablets_total": 12, "system_tablets_leaders": 0, "active_tablets": 13, "cloud": "local", "region": "local", "zone": "local1"}}"#, hostname_port)).unwrap();
+        let tabletservers = AllTabletServers { tabletservers: vec![tabletserver("yb-3.local:9000"), tabletserver("yb-1.local:9000")] };
+
+        // the master without a registration is left out.
+        let servers = discovered_servers(&masters, &tabletservers);
+        assert_eq!(servers.iter().map(|server| format!("{} {}:{}", server.role, server.host, server.port)).collect::<Vec<_>>(),
+                   vec!["master yb-1.local:17000", "master yb-2.local:7000", "tserver yb-1.local:9000", "tserver yb-3.local:9000"]);
+
+        let (hosts, ports) = discovered_hosts_ports(&servers, vec!["7000", "9000", "13000"]);
+        assert_eq!(hosts, vec!["yb-1.local", "yb-2.local", "yb-3.local"]);
+        assert_eq!(ports, vec!["7000", "9000", "13000", "17000"]);
+        assert_eq!(discovered_port_roles(&servers), vec!["17000=master"]);
+    }
+
     #[test]
     fn unit_parse_master_data() {
         let json = r#"
@@ -792,6 +1007,31 @@ mod tests {
         let result = Masters::parse_masters(json, "", "");
         assert!(result.masters[0].error.is_none());
     }
+
+    #[test]
+    fn unit_placement_matches() {
+        let json = r#"{"masters":[{"instance_id":{"permanent_uuid":"3fc1141619304cffa2f0a345d37a51c2","instance_seqno":1657972299220554},
+                       "registration":{"cloud_info":{"placement_cloud":"aws","placement_region":"us-east-1","placement_zone":"us-east-1a"}},"role":"LEADER"}]}"#.to_string();
+        let masters = Masters::parse_masters(json, "", "");
+        let placement = |cloud: &str, region: &str, zone: &str| (cloud.to_string(), region.to_string(), zone.to_string());
+        assert!(placement_matches(&masters.masters[0], &[placement("aws", "us-east-1", "us-east-1b"), placement("aws", "us-east-1", "us-east-1a")]));
+        assert!(!placement_matches(&masters.masters[0], &[placement("aws", "us-east-1", "us-east-1b")]));
+        // a configured placement without a zone matches all zones of the region.
+        assert!(placement_matches(&masters.masters[0], &[placement("aws", "us-east-1", "")]));
+    }
+
+    #[test]
+    fn unit_masters_diff_leader_change() {
+        let mut mastersdiff = MastersDiff::new();
+        mastersdiff.first_snapshot(Masters::new(), "yb-1.local:7000".to_string());
+        mastersdiff.second_snapshot(Masters::new(), "yb-1.local:7000".to_string());
+        assert!(mastersdiff.leader_change().is_none());
+
+        let mut mastersdiff = MastersDiff::new();
+        mastersdiff.first_snapshot(Masters::new(), "yb-1.local:7000".to_string());
+        mastersdiff.second_snapshot(Masters::new(), "yb-2.local:7000".to_string());
+        assert_eq!(mastersdiff.leader_change(), Some((&"yb-1.local:7000".to_string(), &"yb-2.local:7000".to_string())));
+    }
 /*
     #[tokio::test]
     async fn integration_parse_masters() {
@@ -24,6 +24,7 @@
 //! - [crate::snapshot::snapshot_diff] -> [crate::masters::MastersDiff::snapshot_diff] (general diff, show masters diff based on snapshot data)
 //! - [crate] -> [print_masters] (prints adhoc (live) or snapshot masters info)
 //! - [crate] -> [masters_diff] (prints masters diff only, based on snapshot data)
+//! - [crate] -> [discover_servers] (discovers the masters and tablet servers via a single master for `--discover-from`)
 //! - [crate::snapshot::adhoc_diff] -> [crate::masters::MastersDiff::adhoc_read_first_snapshot],
 //! [crate::masters::MastersDiff::adhoc_read_second_snapshot],
 //! [crate::masters::MastersDiff::print] (prints masters diff, based on live data)
@@ -151,6 +151,14 @@ pub enum PeerRole {
   UNKNOWN_ROLE = 7,
 }
 
+/// A server of the cluster found via `--discover-from`: the role (master or tserver), and the host and port of its webserver.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DiscoveredServer {
+    pub role: &'static str,
+    pub host: String,
+    pub port: String,
+}
+
 // diff
 /// BTreeMap for storing a master diff struct per `permanent_uuid`.
 type BTreeMastersDiff = BTreeMap<String, MastersDiffFields>;
@@ -159,6 +167,10 @@ type BTreeMastersDiff = BTreeMap<String, MastersDiffFields>;
 pub struct MastersDiff {
     pub btreemastersdiff: BTreeMastersDiff,
     pub master_found: bool,
+    /// The hostname_port of the master leader in the first snapshot, empty if not found.
+    pub first_master_leader: String,
+    /// The hostname_port of the master leader in the second snapshot, empty if not found.
+    pub second_master_leader: String,
 }
 /// The masters diff struct.
 ///
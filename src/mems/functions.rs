@@ -1,11 +1,23 @@
 //! Module for reading the /memz output for the master and tablet server.
 //!
 use std::{fs, io::Write, sync::mpsc::channel, time::Instant, env};
+use regex::Regex;
 use log::*;
-use anyhow::{Result, Context};
+use colored::*;
+use anyhow::{Result, Context, bail};
 use crate::utility;
+use crate::utility::Config;
+use crate::snapshot;
+use crate::mems::{Mems, Mem, MemComponent, AllMems, MemsDiff, MemsDiffFields};
+use crate::Opts;
 
-pub struct Mems;
+/// The start of the `/memz` output, the output of a server that does not serve `/memz` is different.
+const MEMZ_START: &str = "------------------------------------------------";
+/// The summary of the memory of a server: the memory used by tcmalloc (physical + swap) and the part of it in use by the application.
+const MEM_TOTAL: &str = "Actual memory used (physical + swap)";
+const MEM_USED: &str = "Bytes in use by application";
+const MEM_VIRTUAL: &str = "Virtual address space used";
+const MEM_HEADERS: [&str; 5] = ["hostname_port", "total", "used", "reserved", "virtual"];
 
 impl Mems {
     pub async fn perform_snapshot(
@@ -13,12 +25,13 @@ impl Mems {
         ports: &Vec<&str>,
         snapshot_number: i32,
         parallel: usize,
+        config: &Config,
     ) -> Result<()>
     {
         info!("begin snapshot");
         let timer = Instant::now();
 
-        Mems::read_and_write_mems(hosts, ports, snapshot_number, parallel).await?;
+        Mems::read_and_write_mems(hosts, ports, snapshot_number, parallel, config).await?;
 
         info!("end snapshot: {:?}", timer.elapsed());
 
@@ -29,6 +42,7 @@ impl Mems {
         ports: &Vec<&str>,
         snapshot_number: i32,
         parallel: usize,
+        config: &Config,
     ) -> Result<()>
     {
         info!("begin parallel http read");
@@ -42,8 +56,8 @@ impl Mems {
                 for port in ports {
                     let tx = tx.clone();
                     s.spawn(move |_| {
-                        let mems_data = Mems::read_http(host, port);
-                        tx.send((format!("{}:{}", host, port), mems_data)).expect("error sending data via tx (mems)");
+                        let mems_data = Mems::read_http(host, port, config);
+                        tx.send((utility::hostname_port(host, port, config), mems_data)).expect("error sending data via tx (mems)");
                     });
                 }
             }
@@ -52,7 +66,7 @@ impl Mems {
         info!("end parallel http read {:?}", timer.elapsed());
 
         for (hostname_port, mems_data) in rx {
-            if mems_data.starts_with("------------------------------------------------")
+            if mems_data.starts_with(MEMZ_START)
                 && snapshot_number >= 0
             {
                 let current_directory = env::current_dir()?;
@@ -74,34 +88,398 @@ impl Mems {
     fn read_http(
         host: &str,
         port: &str,
+        config: &Config,
     ) -> String
     {
-        utility::http_get(host, port, "memz?raw=true")
+        utility::http_get_endpoint(host, port, "mems", config)
     }
 }
 
+impl Mem {
+    /// Parse the lines of the `/memz` output with an amount of bytes into the components.
+    /// Returns None if the output is not `/memz` output.
+    fn parse_mem(
+        mems_data: &str,
+        hostname_port: &str,
+    ) -> Option<Mem>
+    {
+        if !mems_data.starts_with(MEMZ_START) { return None };
+        let component_line = Regex::new(r"^MALLOC:\s*[+=]?\s*(\d+)\s+\(\s*[0-9.]+ MiB\)\s+(.+?)\s*$").unwrap();
+        let mut mem = Mem { hostname_port: hostname_port.to_string(), ..Default::default() };
+        for captures in mems_data.lines().filter_map(|line| component_line.captures(line))
+        {
+            let name = captures[2].to_string();
+            // the detailed tcmalloc statistics might repeat a component, the first one is the summary.
+            if mem.components.iter().any(|component| component.name == name) { continue };
+            mem.components.push(MemComponent { name, bytes: captures[1].parse().unwrap_or_default() });
+        }
+        Some(mem)
+    }
+    fn bytes(&self, name: &str) -> Option<u64> {
+        self.components.iter().find(|component| component.name == name).map(|component| component.bytes)
+    }
+    /// The summary of the memory: total (used by tcmalloc), used (by the application), reserved (by tcmalloc but not in use, such as the freelists)
+    /// and virtual (the address space, including the memory released to the OS).
+    fn summary(&self) -> Vec<(&str, Option<u64>)> {
+        let total = self.bytes(MEM_TOTAL);
+        let used = self.bytes(MEM_USED);
+        vec![
+            ("total", total),
+            ("used", used),
+            ("reserved", total.zip(used).map(|(total, used)| total.saturating_sub(used))),
+            ("virtual", self.bytes(MEM_VIRTUAL)),
+        ]
+    }
+}
+
+/// Format bytes as a size, with a sign for a change.
+fn format_bytes(
+    bytes: Option<i64>,
+) -> String
+{
+    match bytes
+    {
+        Some(bytes) if bytes < 0 => format!("-{}", utility::bytes_to_size(bytes.unsigned_abs() as f64)),
+        Some(bytes) => utility::bytes_to_size(bytes as f64),
+        None => "-".to_string(),
+    }
+}
+
+impl AllMems {
+    pub fn new() -> Self { Default::default() }
+    /// Read `/memz` from all hosts and ports.
+    /// The servers that should serve `/memz` but did not return it are skipped with a warning.
+    pub async fn read_mems(
+        hosts: &Vec<&str>,
+        ports: &Vec<&str>,
+        parallel: usize,
+        config: &Config,
+    ) -> AllMems
+    {
+        info!("begin parallel http read");
+        let timer = Instant::now();
+
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(parallel).build().unwrap();
+        let (tx, rx) = channel();
+        pool.scope(move |s| {
+            for host in hosts {
+                for port in ports {
+                    let tx = tx.clone();
+                    s.spawn(move |_| {
+                        let mems_data = Mems::read_http(host, port, config);
+                        let selected = utility::endpoint_selected(host, port, "mems", config);
+                        tx.send((utility::hostname_port(host, port, config), selected, mems_data)).expect("error sending data via tx (mems)");
+                    });
+                }
+            }
+        });
+
+        info!("end parallel http read {:?}", timer.elapsed());
+
+        let mut allmems = AllMems::new();
+        let mut skipped = Vec::new();
+        for (hostname_port, selected, mems_data) in rx
+        {
+            match Mem::parse_mem(&mems_data, &hostname_port)
+            {
+                Some(mem) => allmems.mems.push(mem),
+                None if selected => skipped.push(hostname_port),
+                None => {},
+            }
+        }
+        if !skipped.is_empty()
+        {
+            skipped.sort();
+            eprintln!("Warning: no /memz data, skipped: {}", skipped.join(","));
+        }
+        allmems.mems.sort_by(|a, b| a.hostname_port.cmp(&b.hostname_port));
+        allmems
+    }
+    /// Read the `/memz` text files (`mems_<hostname:port>.txt`) of a snapshot.
+    pub fn read_snapshot(
+        snapshot_number: &String,
+    ) -> Result<AllMems>
+    {
+        let snapshot_directory = env::current_dir()?.join("yb_stats.snapshots").join(snapshot_number);
+        let mut allmems = AllMems::new();
+        for entry in fs::read_dir(&snapshot_directory)
+            .with_context(|| format!("Error reading snapshot directory: {}", snapshot_directory.display()))?
+            .filter_map(|entry| entry.ok())
+        {
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            let Some(hostname_port) = file_name.strip_prefix("mems_").and_then(|file_name| file_name.strip_suffix(".txt")) else { continue };
+            let mems_data = fs::read_to_string(entry.path())
+                .with_context(|| format!("Error reading snapshot: {}", entry.path().display()))?;
+            match Mem::parse_mem(&mems_data, hostname_port)
+            {
+                Some(mem) => allmems.mems.push(mem),
+                None => eprintln!("Warning: {} is not /memz data, skipped", entry.path().display()),
+            }
+        }
+        if allmems.mems.is_empty()
+        {
+            bail!("Snapshot {} has no /memz data", snapshot_number);
+        }
+        allmems.mems.sort_by(|a, b| a.hostname_port.cmp(&b.hostname_port));
+        Ok(allmems)
+    }
+    /// Print the summary of the memory per server, and with details_enable all the components of `/memz` per server.
+    pub fn print(
+        &self,
+        hostname_filter: &Regex,
+        details_enable: bool,
+        config: &Config,
+    ) -> Result<()>
+    {
+        let mems: Vec<&Mem> = self.mems
+            .iter()
+            .filter(|mem| hostname_filter.is_match(&mem.hostname_port))
+            .collect();
+        let rows: Vec<Vec<String>> = mems
+            .iter()
+            .map(|mem| std::iter::once(mem.hostname_port.clone())
+                .chain(mem.summary().into_iter().map(|(_, bytes)| format_bytes(bytes.map(|bytes| bytes as i64))))
+                .collect())
+            .collect();
+        utility::print_table(&MEM_HEADERS, &rows, config)?;
+        if details_enable
+        {
+            let rows: Vec<Vec<String>> = mems
+                .iter()
+                .flat_map(|mem| mem.components
+                    .iter()
+                    .map(|component| vec![mem.hostname_port.clone(), component.name.clone(), component.bytes.to_string(), format_bytes(Some(component.bytes as i64))]))
+                .collect();
+            writeln!(config.output())?;
+            utility::print_table(&["hostname_port", "component", "bytes", "size"], &rows, config)?;
+        }
+        Ok(())
+    }
+}
+
+impl MemsDiff {
+    pub fn new() -> Self { Default::default() }
+    pub fn snapshot_diff(
+        begin_snapshot: &String,
+        end_snapshot: &String,
+    ) -> Result<MemsDiff>
+    {
+        let mut memsdiff = MemsDiff::new();
+        memsdiff.first_snapshot(AllMems::read_snapshot(begin_snapshot)?);
+        memsdiff.second_snapshot(AllMems::read_snapshot(end_snapshot)?);
+        Ok(memsdiff)
+    }
+    fn first_snapshot(
+        &mut self,
+        allmems: AllMems,
+    )
+    {
+        for mem in allmems.mems
+        {
+            let hostname_port = mem.hostname_port.clone();
+            self.btreememsdiff.entry(hostname_port).or_default().first_mem = Some(mem);
+        }
+    }
+    fn second_snapshot(
+        &mut self,
+        allmems: AllMems,
+    )
+    {
+        for mem in allmems.mems
+        {
+            let hostname_port = mem.hostname_port.clone();
+            self.btreememsdiff.entry(hostname_port).or_default().second_mem = Some(mem);
+        }
+    }
+    /// The rows of the diff: the summary of the memory per server with the begin, end and change, and with details_enable all the components,
+    /// and the number of these that changed, were added or were removed.
+    /// The servers that are not in both snapshots are returned separately.
+    fn rows(
+        &self,
+        hostname_filter: &Regex,
+        details_enable: bool,
+    ) -> (Vec<Vec<String>>, usize, Vec<&String>)
+    {
+        let mut rows = Vec::new();
+        let mut changes = 0;
+        let mut skipped = Vec::new();
+        for (hostname_port, MemsDiffFields { first_mem, second_mem }) in self.btreememsdiff.iter().filter(|(hostname_port, _)| hostname_filter.is_match(hostname_port))
+        {
+            let (Some(first_mem), Some(second_mem)) = (first_mem, second_mem) else {
+                skipped.push(hostname_port);
+                continue;
+            };
+            let mut measures: Vec<(&str, Option<u64>, Option<u64>)> = first_mem.summary()
+                .into_iter()
+                .zip(second_mem.summary())
+                .map(|((name, first), (_, second))| (name, first, second))
+                .collect();
+            if details_enable
+            {
+                // the components of the end snapshot, followed by the components that are only in the begin snapshot.
+                measures.extend(second_mem.components
+                    .iter()
+                    .map(|component| (component.name.as_str(), first_mem.bytes(&component.name), Some(component.bytes)))
+                    .chain(first_mem.components
+                        .iter()
+                        .filter(|component| second_mem.bytes(&component.name).is_none())
+                        .map(|component| (component.name.as_str(), Some(component.bytes), None))));
+            }
+            for (name, first, second) in measures
+            {
+                if first != second { changes += 1 };
+                let change = first.zip(second).map(|(first, second)| second as i64 - first as i64);
+                let change = match change
+                {
+                    Some(change) if change > 0 => format_bytes(Some(change)).red().to_string(),
+                    Some(change) if change < 0 => format_bytes(Some(change)).green().to_string(),
+                    _ => format_bytes(change),
+                };
+                rows.push(vec![
+                    hostname_port.clone(),
+                    name.to_string(),
+                    format_bytes(first.map(|bytes| bytes as i64)),
+                    format_bytes(second.map(|bytes| bytes as i64)),
+                    change,
+                ]);
+            }
+        }
+        (rows, changes, skipped)
+    }
+    /// Print the memory of the servers, and return the number of memory measures that changed.
+    pub fn print(
+        &self,
+        hostname_filter: &Regex,
+        details_enable: bool,
+        config: &Config,
+    ) -> Result<usize>
+    {
+        let (rows, changes, skipped) = self.rows(hostname_filter, details_enable);
+        utility::print_table(&["hostname_port", "memory", "begin", "end", "change"], &rows, config)?;
+        if !skipped.is_empty()
+        {
+            eprintln!("Warning: no /memz data in the begin or end snapshot, skipped: {}", skipped.iter().map(|hostname_port| hostname_port.as_str()).collect::<Vec<_>>().join(","));
+        }
+        Ok(changes)
+    }
+}
+
+pub async fn mems_diff(
+    options: &Opts,
+    config: &Config,
+) -> Result<()>
+{
+    info!("mems diff");
+
+    if options.begin.is_none() || options.end.is_none() {
+        snapshot::Snapshot::print(config)?;
+    }
+    if options.snapshot_list { return Ok(()) };
+
+    let hostname_filter = utility::set_regex(&options.hostname_match);
+
+    let (begin_snapshot, end_snapshot, _begin_snapshot_row) = snapshot::Snapshot::read_begin_end_snapshot_from_user(options.begin, options.end)?;
+
+    snapshot::Snapshot::print_diff_header(&begin_snapshot, &end_snapshot, config)?;
+    let memsdiff = MemsDiff::snapshot_diff(&begin_snapshot, &end_snapshot)?;
+    let changes = memsdiff.print(&hostname_filter, options.details_enable, config)?;
+    utility::add_diff_changes(changes, config);
+
+    Ok(())
+}
+
+pub async fn print_mems(
+    hosts: Vec<&str>,
+    ports: Vec<&str>,
+    parallel: usize,
+    options: &Opts,
+    config: &Config,
+) -> Result<()>
+{
+    let hostname_filter = utility::set_regex(&options.hostname_match);
+    let allmems = match options.print_mems.as_ref().unwrap() {
+        Some(snapshot_number) => AllMems::read_snapshot(snapshot_number)?,
+        None => AllMems::read_mems(&hosts, &ports, parallel, config).await,
+    };
+    allmems.print(&hostname_filter, options.details_enable, config)?;
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     //use crate::utility_test::*;
 
+    const MEMZ: &str = r#"------------------------------------------------
+MALLOC:       52340184 (   49.9 MiB) Bytes in use by application
+MALLOC: +     10067968 (    9.6 MiB) Bytes in page heap freelist
+MALLOC: +      1513376 (    1.4 MiB) Bytes in central cache freelist
+MALLOC: +      2478336 (    2.4 MiB) Bytes in transfer cache freelist
+MALLOC: +      4316712 (    4.1 MiB) Bytes in thread cache freelists
+MALLOC: +      3801088 (    3.6 MiB) Bytes in malloc metadata
+MALLOC:   ------------
+MALLOC: =     74517664 (   71.1 MiB) Actual memory used (physical + swap)
+MALLOC: +     18735104 (   17.9 MiB) Bytes released to OS (aka unmapped)
+MALLOC:   ------------
+MALLOC: =     93252768 (   88.9 MiB) Virtual address space used
+MALLOC:
+MALLOC:           2463              Spans in use
+MALLOC:             39              Thread heaps in use
+MALLOC:           8192              Tcmalloc page size
+------------------------------------------------
+Call ReleaseFreeMemory() to release freelist memory to the OS (via madvise()).
+Bytes released to the OS take up virtual address space but no physical memory.
+"#;
+
+    #[test]
+    fn unit_parse_mem() {
+        let mem = Mem::parse_mem(MEMZ, "yb-1.local:9000").unwrap();
+        assert_eq!(mem.components.len(), 9);
+        assert_eq!(mem.components[0], MemComponent { name: "Bytes in use by application".to_string(), bytes: 52340184 });
+        assert_eq!(mem.summary(), vec![("total", Some(74517664)), ("used", Some(52340184)), ("reserved", Some(22177480)), ("virtual", Some(93252768))]);
+        assert!(Mem::parse_mem("<html><body>not found</body></html>", "yb-1.local:13000").is_none());
+        assert!(Mem::parse_mem("", "yb-1.local:9000").is_none());
+    }
+
+    #[test]
+    fn unit_mems_diff() {
+        let first = Mem::parse_mem(MEMZ, "yb-1.local:9000").unwrap();
+        let second = Mem::parse_mem(&MEMZ.replace("52340184", "62340184").replace("74517664", "84517664"), "yb-1.local:9000").unwrap();
+        let mut memsdiff = MemsDiff::new();
+        memsdiff.first_snapshot(AllMems { mems: vec![first, Mem::parse_mem(MEMZ, "yb-2.local:9000").unwrap()] });
+        memsdiff.second_snapshot(AllMems { mems: vec![second] });
+        let (rows, changes, skipped) = memsdiff.rows(&utility::set_regex(&None), false);
+        assert_eq!(rows.len(), 4);
+        // the total and the memory that is changed, the reserved memory is unchanged.
+        assert_eq!(changes, 2);
+        assert_eq!(rows[0][..4], ["yb-1.local:9000", "total", "71.07M", "80.60M"]);
+        assert!(rows[0][4].contains("9.54M"));
+        assert_eq!(rows[2], vec!["yb-1.local:9000", "reserved", "21.15M", "21.15M", "0B"]);
+        assert_eq!(skipped, vec!["yb-2.local:9000"]);
+        let (rows, _, _) = memsdiff.rows(&utility::set_regex(&None), true);
+        assert_eq!(rows.len(), 13);
+        assert_eq!(format_bytes(Some(-2048)), "-2.00K");
+    }
+
     #[tokio::test]
     async fn integration_parse_mems_tserver()
     {
+        let config = &Config::default();
         // currently, the mems "parsing" is not much parsing.
         // What currently is done, is that the hostname:port/memz output is stored in a file in the snapshot directory named <hostname>:<port>_mems.
         let hostname = utility::get_hostname_tserver();
         let port = utility::get_port_tserver();
-        Mems::read_and_write_mems(&vec![&hostname], &vec![&port], -1, 1).await.unwrap();
+        Mems::read_and_write_mems(&vec![&hostname], &vec![&port], -1, 1, config).await.unwrap();
     }
 
     #[tokio::test]
     async fn integration_parse_mems_master()
     {
+        let config = &Config::default();
         // currently, the mems "parsing" is not much parsing.
         // What currently is done, is that the hostname:port/memz output is stored in a file in the snapshot directory named <hostname>:<port>_mems.
         let hostname = utility::get_hostname_master();
         let port = utility::get_port_master();
-        Mems::read_and_write_mems(&vec![&hostname], &vec![&port], -1, 1).await.unwrap();
+        Mems::read_and_write_mems(&vec![&hostname], &vec![&port], -1, 1, config).await.unwrap();
     }
 }
\ No newline at end of file
@@ -8,6 +8,16 @@
 //! - check if the returned data start with "------------------------------------------------".
 //! - If so, save the result as "mems_*hostname:port*"
 //!
+//! For printing and the diff, the lines of the tcmalloc output with an amount of bytes are parsed ([Mem]).
+//!
+//! The mems functionality is called from:
+//! - [crate::snapshot::perform_snapshot] -> [Mems::perform_snapshot] (general snapshot, saves the `/memz` text)
+//! - [crate] -> [print_mems] (prints adhoc (live) or snapshot memory per server)
+//! - [crate] -> [mems_diff] (prints the memory diff per server, based on snapshot data)
+//!
+mod structs;
 mod functions;
 
+pub use structs::*;
+
 pub use functions::*;
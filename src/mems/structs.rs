@@ -0,0 +1,57 @@
+//! The structs
+//!
+use std::collections::BTreeMap;
+
+/// The struct for performing the snapshot of `/memz`, which is stored as text in the snapshot directory.
+pub struct Mems;
+
+/// The memory of a master or tablet server as reported by tcmalloc in `/memz`.
+///
+/// The `/memz` output is text, of which the lines with an amount of bytes are parsed:
+/// ```text
+/// ------------------------------------------------
+/// MALLOC:       52340184 (   49.9 MiB) Bytes in use by application
+/// MALLOC: +     10067968 (    9.6 MiB) Bytes in page heap freelist
+/// MALLOC: +      1513376 (    1.4 MiB) Bytes in central cache freelist
+/// MALLOC: +      2478336 (    2.4 MiB) Bytes in transfer cache freelist
+/// MALLOC: +      4316712 (    4.1 MiB) Bytes in thread cache freelists
+/// MALLOC: +      3801088 (    3.6 MiB) Bytes in malloc metadata
+/// MALLOC:   ------------
+/// MALLOC: =     74517664 (   71.1 MiB) Actual memory used (physical + swap)
+/// MALLOC: +     18735104 (   17.9 MiB) Bytes released to OS (aka unmapped)
+/// MALLOC:   ------------
+/// MALLOC: =     93252768 (   88.9 MiB) Virtual address space used
+/// MALLOC:
+/// MALLOC:           2463              Spans in use
+/// ...
+/// ```
+#[derive(Debug, Default, PartialEq)]
+pub struct Mem {
+    pub hostname_port: String,
+    /// The components in the order of `/memz`.
+    pub components: Vec<MemComponent>,
+}
+#[derive(Debug, PartialEq)]
+pub struct MemComponent {
+    pub name: String,
+    pub bytes: u64,
+}
+#[derive(Debug, Default)]
+pub struct AllMems {
+    pub mems: Vec<Mem>,
+}
+// diff
+/// BTreeMap for storing a mems diff struct per `hostname_port`
+type BTreeMemsDiff = BTreeMap<String, MemsDiffFields>;
+/// The wrapper struct for holding the btreemap holding the diff structs.
+#[derive(Debug, Default)]
+pub struct MemsDiff {
+    pub btreememsdiff: BTreeMemsDiff,
+}
+/// The diff struct holding the memory of a server in the begin and end snapshot.
+/// A server that has no `/memz` data in a snapshot has no [Mem] for it.
+#[derive(Debug, Default)]
+pub struct MemsDiffFields {
+    pub first_mem: Option<Mem>,
+    pub second_mem: Option<Mem>,
+}
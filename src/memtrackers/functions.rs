@@ -5,11 +5,13 @@ use regex::Regex;
 use std::{sync::mpsc::channel, time::Instant};
 use scraper::{Html, Selector};
 use log::*;
+use colored::*;
 use anyhow::Result;
 use crate::snapshot;
 use crate::memtrackers::{MemTrackers, AllMemTrackers};
 use crate::Opts;
 use crate::utility;
+use crate::utility::Config;
 
 impl AllMemTrackers {
     pub async fn perform_snapshot(
@@ -17,13 +19,14 @@ impl AllMemTrackers {
         ports: &Vec<&str>,
         snapshot_number: i32,
         parallel: usize ,
+        config: &Config,
     ) -> Result<()>
     {
         info!("begin snapshot");
         let timer = Instant::now();
 
-        let allmemtrackers = AllMemTrackers::read_memtrackers(hosts, ports, parallel).await;
-        snapshot::save_snapshot_json(snapshot_number,"memtrackers", allmemtrackers.memtrackers)?;
+        let allmemtrackers = AllMemTrackers::read_memtrackers(hosts, ports, parallel, config).await;
+        snapshot::save_snapshot_json(snapshot_number, "memtrackers", allmemtrackers.memtrackers, config)?;
 
         info!("end snapshot: {:?}", timer.elapsed());
 
@@ -34,6 +37,7 @@ impl AllMemTrackers {
         hosts: &Vec<&str>,
         ports: &Vec<&str>,
         parallel: usize,
+        config: &Config,
     ) -> AllMemTrackers
     {
         info!("begin parallel http read");
@@ -48,9 +52,9 @@ impl AllMemTrackers {
                     let tx = tx.clone();
                     s.spawn(move |_| {
                         let detail_snapshot_time = Local::now();
-                        let mut memtrackers = AllMemTrackers::read_http(host, port);
+                        let mut memtrackers = AllMemTrackers::read_http(host, port, config);
                         memtrackers.iter_mut().for_each(|r| r.timestamp = detail_snapshot_time);
-                        memtrackers.iter_mut().for_each(|r| r.hostname_port = format!("{}:{}", host, port));
+                        memtrackers.iter_mut().for_each(|r| r.hostname_port = utility::hostname_port(host, port, config));
                         tx.send(memtrackers).expect("error sending data via tx");
                     });
                 }
@@ -74,9 +78,10 @@ impl AllMemTrackers {
     fn read_http(
         host: &str,
         port: &str,
+        config: &Config,
     ) -> Vec<MemTrackers>
     {
-        let data_from_http = utility::http_get(host, port, "mem-trackers");
+        let data_from_http = utility::http_get_endpoint(host, port, "memtrackers", config);
         AllMemTrackers::parse_memtrackers(data_from_http)
     }
     fn parse_memtrackers(
@@ -152,10 +157,12 @@ impl AllMemTrackers {
         &self,
         hostname_filter: &Regex,
         stat_name_filter: &Regex,
+        config: &Config,
     ) -> Result<()>
     {
         info!("print_memtrackers");
 
+        let mut rows = utility::RowWriter::new(config);
         let mut previous_hostname_port = String::from("");
         for row in &self.memtrackers
         {
@@ -164,48 +171,154 @@ impl AllMemTrackers {
             {
                 if row.hostname_port.clone() != previous_hostname_port
                 {
-                    println!("{}", "-".repeat(174));
-                    println!("Host: {}, Snapshot time: {}", &row.hostname_port.clone(), row.timestamp);
-                    println!("{}", "-".repeat(174));
-                    println!("{:20} {:90} {:>20} {:>20} {:>20}",
+                    rows.header(format_args!("{}", "-".repeat(174)))?;
+                    rows.header(format_args!("Host: {}, Snapshot time: {}", &row.hostname_port.clone(), utility::format_timestamp(&row.timestamp, config)))?;
+                    rows.header(format_args!("{}", "-".repeat(174)))?;
+                    rows.header(format_args!("{:20} {:90} {:>20} {:>20} {:>20}",
                              "hostname_port",
                              "id",
                              "current_consumption",
                              "peak_consumption",
-                             "limit");
-                    println!("{}", "-".repeat(174));
+                             "limit"))?;
+                    rows.header(format_args!("{}", "-".repeat(174)))?;
                     previous_hostname_port = row.hostname_port.clone();
                 }
                 let indented_id = " ".repeat(row.depth.parse::<usize>().unwrap()) + &row.id;
-                println!("{:20} {:90} {:>20} {:>20} {:>20}", row.hostname_port.clone(), indented_id, row.current_consumption, row.peak_consumption, row.limit)
+                writeln!(rows, "{:20} {:90} {:>20} {:>20} {:>20}", row.hostname_port.clone(), indented_id, row.current_consumption, row.peak_consumption, row.limit)?
             }
         }
-        Ok(())
+        rows.finish()
+    }
+    /// Print the memtrackers per host as a tree, with the current consumption as a percentage of the parent and of the root.
+    ///
+    /// The percentages are calculated before the stat name filter is applied, so a filtered tracker still shows its share.
+    /// A tracker (other than root) that takes more than mem_pct_warn percent of root is highlighted.
+    pub fn print_tree(
+        &self,
+        hostname_filter: &Regex,
+        stat_name_filter: &Regex,
+        mem_pct_warn: &Option<f64>,
+        config: &Config,
+    ) -> Result<()>
+    {
+        info!("print_memtrackers tree");
+
+        let mut rows = utility::RowWriter::new(config);
+        for memtrackers in self.memtrackers.chunk_by(|first, second| first.hostname_port == second.hostname_port)
+        {
+            if !hostname_filter.is_match(&memtrackers[0].hostname_port)
+            {
+                continue;
+            }
+            rows.header(format_args!("{}", "-".repeat(174)))?;
+            rows.header(format_args!("Host: {}, Snapshot time: {}", &memtrackers[0].hostname_port, utility::format_timestamp(&memtrackers[0].timestamp, config)))?;
+            rows.header(format_args!("{}", "-".repeat(174)))?;
+            rows.header(format_args!("{:90} {:>20} {:>10} {:>10} {:>20} {:>20}",
+                     "id",
+                     "current_consumption",
+                     "% parent",
+                     "% root",
+                     "peak_consumption",
+                     "limit"))?;
+            rows.header(format_args!("{}", "-".repeat(174)))?;
+            for (memtracker, (percentage_parent, percentage_root)) in memtrackers.iter().zip(memtracker_percentages(memtrackers))
+            {
+                if !stat_name_filter.is_match(&memtracker.id)
+                {
+                    continue;
+                }
+                let depth = memtracker.depth.parse::<usize>().unwrap_or_default();
+                let indented_name = "  ".repeat(depth) + memtracker.id.rsplit("->").next().unwrap_or_default();
+                let line = format!("{:90} {:>20} {:>10} {:>10} {:>20} {:>20}",
+                                   indented_name,
+                                   memtracker.current_consumption,
+                                   format_percentage(percentage_parent, config),
+                                   format_percentage(percentage_root, config),
+                                   memtracker.peak_consumption,
+                                   memtracker.limit
+                );
+                if depth > 0
+                    && mem_pct_warn.zip(percentage_root).is_some_and(|(mem_pct_warn, percentage_root)| percentage_root > mem_pct_warn)
+                {
+                    writeln!(rows, "{}", line.red())?;
+                }
+                else
+                {
+                    writeln!(rows, "{}", line)?;
+                }
+            }
+        }
+        rows.finish()
     }
 }
 
+/// Calculate the percentage of the current consumption of every memtracker of a host of its parent and of root.
+///
+/// The memtrackers are in the order of the mem-trackers page: every tracker follows its parent,
+/// so the parent is the last tracker seen with a depth one lower.
+/// The current consumption can contain a second number between brackets, such as "43.15M (58.42M)", only the first number is used.
+fn memtracker_percentages(
+    memtrackers: &[MemTrackers],
+) -> Vec<(Option<f64>, Option<f64>)>
+{
+    let percentage = |bytes: Option<f64>, total: Option<f64>| -> Option<f64> {
+        bytes.zip(total)
+            .filter(|(_, total)| *total > 0_f64)
+            .map(|(bytes, total)| bytes / total * 100_f64)
+    };
+
+    let mut parents: Vec<Option<f64>> = Vec::new();
+    let mut percentages = Vec::new();
+    for memtracker in memtrackers
+    {
+        let bytes = memtracker.current_consumption
+            .split_whitespace()
+            .next()
+            .and_then(utility::size_to_bytes);
+        parents.truncate(memtracker.depth.parse::<usize>().unwrap_or_default());
+        let percentage_parent = parents.last().and_then(|parent| percentage(bytes, *parent));
+        let percentage_root = percentage(bytes, parents.first().copied().unwrap_or(bytes));
+        percentages.push((percentage_parent, percentage_root));
+        parents.push(bytes);
+    }
+    percentages
+}
+
+fn format_percentage(percentage: Option<f64>, config: &Config) -> String
+{
+    percentage
+        .map(|percentage| format!("{}%", utility::format_float(percentage, 0, 1, config)))
+        .unwrap_or_else(|| "-".to_string())
+}
+
 pub async fn print_memtrackers(
     hosts: Vec<&str>,
     ports: Vec<&str>,
     parallel: usize,
     options: &Opts,
+    config: &Config,
 ) -> Result<()>
 {
     let hostname_filter = utility::set_regex(&options.hostname_match);
     let stat_name_filter = utility::set_regex(&options.stat_name_match);
 
+    let mut allmemtrackers = AllMemTrackers::new();
     match options.print_memtrackers.as_ref().unwrap() {
         Some(snapshot_number) => {
-            let mut allmemtrackers = AllMemTrackers::new();
-            allmemtrackers.memtrackers = snapshot::read_snapshot_json(snapshot_number, "memtrackers")?;
-            allmemtrackers.print(&hostname_filter, &stat_name_filter)?;
+            allmemtrackers.memtrackers = snapshot::read_snapshot_json(snapshot_number, "memtrackers", config)?;
         },
         None => {
-            let allmemtrackers = AllMemTrackers::read_memtrackers(&hosts, &ports, parallel).await;
-            allmemtrackers.print(&hostname_filter, &stat_name_filter)?;
+            allmemtrackers = AllMemTrackers::read_memtrackers(&hosts, &ports, parallel, config).await;
         },
     }
-    Ok(())
+    if options.flat
+    {
+        allmemtrackers.print(&hostname_filter, &stat_name_filter, config)
+    }
+    else
+    {
+        allmemtrackers.print_tree(&hostname_filter, &stat_name_filter, &options.mem_pct_warn, config)
+    }
 }
 
 #[cfg(test)]
@@ -1272,21 +1385,47 @@ server uuid 05b8d17620eb4cd79eddaddb2fbcbb42</pre></div></footer></body></html>
         assert_eq!(result.len(), 345);
     }
 
+    #[test]
+    fn unit_memtracker_percentages() {
+        let config = &Config::default();
+        let memtracker = |id: &str, current_consumption: &str, depth: &str| MemTrackers {
+            id: id.to_string(),
+            current_consumption: current_consumption.to_string(),
+            depth: depth.to_string(),
+            ..Default::default()
+        };
+        let memtrackers = vec![
+            memtracker("root", "100.00M", "0"),
+            memtracker("root->server", "50.00M (58.42M)", "1"),
+            memtracker("root->server->BlockBasedTable", "25.00M", "2"),
+            memtracker("root->TCMalloc Thread Cache", "0B", "1"),
+        ];
+        let percentages = memtracker_percentages(&memtrackers);
+        assert_eq!(percentages[0], (None, Some(100_f64)));
+        assert_eq!(percentages[1], (Some(50_f64), Some(50_f64)));
+        assert_eq!(percentages[2], (Some(50_f64), Some(25_f64)));
+        assert_eq!(percentages[3], (Some(0_f64), Some(0_f64)));
+        assert_eq!(format_percentage(percentages[2].1, config), "25.0%");
+        assert_eq!(format_percentage(None, config), "-");
+    }
+
     #[tokio::test]
     async fn integration_parse_memtrackers_master() {
+        let config = &Config::default();
         let hostname = utility::get_hostname_master();
         let port = utility::get_port_master();
 
-        let allmemtrackers = AllMemTrackers::read_memtrackers(&vec![&hostname], &vec![&port], 1).await;
+        let allmemtrackers = AllMemTrackers::read_memtrackers(&vec![&hostname], &vec![&port], 1, config).await;
         // memtrackers must return some rows
         assert!(!allmemtrackers.memtrackers.is_empty());
     }
     #[tokio::test]
     async fn parse_memtrackers_tserver() {
+        let config = &Config::default();
         let hostname = utility::get_hostname_tserver();
         let port = utility::get_port_tserver();
 
-        let allmemtrackers = AllMemTrackers::read_memtrackers(&vec![&hostname], &vec![&port], 1).await;
+        let allmemtrackers = AllMemTrackers::read_memtrackers(&vec![&hostname], &vec![&port], 1, config).await;
         // memtrackers must return some rows
         assert!(!allmemtrackers.memtrackers.is_empty());
     }
@@ -1,14 +1,74 @@
 //! The  impls and functions
 //!
-use std::{sync::mpsc::channel, time::Instant};
+use std::{collections::{BTreeMap, BTreeSet, HashSet}, fs, io::Read, sync::{Arc, mpsc::channel}, time::Instant};
+use arrow::{array::{Array, ArrayRef, Float64Array, Int64Array, StringArray, TimestampMicrosecondArray, UInt64Array}, record_batch::RecordBatch};
 use chrono::{DateTime, Local};
 use regex::Regex;
 use log::*;
-use anyhow::Result;
+use colored::*;
+use anyhow::{bail, Context, Result};
 use crate::{metrics, utility};
 use crate::snapshot;
-use crate::metrics::{Metrics::{MetricValue, MetricCountSum, MetricCountSumRows}, MetricEntity, AllMetricEntity, MetricEntityDiff, MetricDiffValues, Attributes, MetricDiffCountSum, MetricDiffCountSumRows};
+use crate::metrics::{Metrics::{MetricValue, MetricCountSum, MetricCountSumRows, RejectedU64MetricValue, RejectedBooleanMetricValue}, MetricEntity, AllMetricEntity, MetricEntityDiff, MetricDiffValues, Attributes, MetricDiffCountSum, MetricDiffCountSumRows, MetricEntityPrint, MetricValues, MetricCountSumValues, MetricCountSumRowsValues, MetricBaseline, AllMetricBaseline, MetricAssertion, MetricAssertionField, MetricSchemaDiff, MetricPercentiles, MetricRename, ComputedMetric, MetricCompute, MetricExpression, MetricDiffThreshold};
 use crate::Opts;
+use crate::utility::Config;
+
+impl Attributes {
+    /// All attributes as `key=value` pairs sorted on key and separated by commas,
+    /// such as: `namespace_name=yugabyte,table_id=000033e8000030008000000000004000,table_name=t`.
+    pub fn labels(&self) -> String
+    {
+        let mut labels: BTreeMap<&str, String> = self.other
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str().map(str::to_string).unwrap_or_else(|| value.to_string())))
+            .collect();
+        for (key, value) in [
+            ("stream_id", &self.stream_id),
+            ("namespace_name", &self.namespace_name),
+            ("table_name", &self.table_name),
+            ("table_id", &self.table_id),
+        ]
+        {
+            if let Some(value) = value
+            {
+                labels.insert(key, value.clone());
+            }
+        }
+        labels
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+    /// The attributes of the labels of [Attributes::labels], such as the attributes read back from a parquet snapshot.
+    /// The values of the other attributes are strings. A value with a comma is kept: a label without `=` continues the previous value.
+    pub fn from_labels(labels: &str) -> Attributes
+    {
+        let mut pairs: Vec<(String, String)> = Vec::new();
+        for label in labels.split(',').filter(|label| !label.is_empty())
+        {
+            match (label.split_once('='), pairs.last_mut())
+            {
+                (Some((key, value)), _) => pairs.push((key.to_string(), value.to_string())),
+                (None, Some((_, value))) => { value.push(','); value.push_str(label) },
+                (None, None) => {},
+            }
+        }
+        let mut attributes = Attributes::default();
+        for (key, value) in pairs
+        {
+            match key.as_str()
+            {
+                "stream_id" => attributes.stream_id = Some(value),
+                "namespace_name" => attributes.namespace_name = Some(value),
+                "table_name" => attributes.table_name = Some(value),
+                "table_id" => attributes.table_id = Some(value),
+                _ => { attributes.other.insert(key, serde_json::Value::String(value)); },
+            }
+        }
+        attributes
+    }
+}
 
 impl AllMetricEntity {
     pub fn new() -> Self {
@@ -19,21 +79,27 @@ impl AllMetricEntity {
         ports: &Vec<&str>,
         snapshot_number: i32,
         parallel: usize,
+        config: &Config,
     ) -> Result<()>
     {
         info!("begin snapshot");
         let timer = Instant::now();
 
-        let allmetricentity = AllMetricEntity::read_metrics(hosts, ports, parallel).await;
-        snapshot::save_snapshot_json(snapshot_number, "metrics", allmetricentity.metricentity)?;
+        let allmetricentity = AllMetricEntity::read_metrics(hosts, ports, parallel, config).await;
+        if config.parquet_output {
+            snapshot::save_snapshot_parquet(snapshot_number, "metrics", allmetricentity.record_batch()?, allmetricentity.metricentity.len(), config)?;
+        } else {
+            snapshot::save_snapshot_json(snapshot_number, "metrics", allmetricentity.metricentity, config)?;
+        }
 
         info!("end snapshot: {:?}", timer.elapsed());
         Ok(())
     }
-    async fn read_metrics(
+    pub async fn read_metrics(
         hosts: &Vec<&str>,
         ports: &Vec<&str>,
         parallel: usize,
+        config: &Config,
     ) -> AllMetricEntity
     {
         info!("begin parallel http read");
@@ -47,8 +113,8 @@ impl AllMetricEntity {
                     let tx = tx.clone();
                     s.spawn(move |_| {
                         let detail_snapshot_time = Local::now();
-                        let mut metrics = AllMetricEntity::read_http(host, port);
-                        metrics.iter_mut().for_each(|r| r.hostname_port = Some(format!("{}:{}", host, port)));
+                        let mut metrics = AllMetricEntity::read_http(host, port, config);
+                        metrics.iter_mut().for_each(|r| r.hostname_port = Some(utility::hostname_port(host, port, config)));
                         metrics.iter_mut().for_each(|r| r.timestamp = Some(detail_snapshot_time));
                         tx.send(metrics).expect("error sending data via tx");
                     });
@@ -73,11 +139,208 @@ impl AllMetricEntity {
     pub fn read_http(
         host: &str,
         port: &str,
+        config: &Config,
     ) -> Vec<MetricEntity>
     {
-        let data_from_http = utility::http_get(host, port, "metrics?reset_histograms=false");
+        let data_from_http = utility::http_get_endpoint(host, port, "metrics", config);
         AllMetricEntity::parse_metrics(data_from_http, host, port)
     }
+    /// Create a record batch with a row per metric, for saving as parquet.
+    ///
+    /// The columns of the metric types that do not apply to a metric are null,
+    /// such as total_count for a value statistic. Rejected metric values are not included.
+    pub fn record_batch(
+        &self,
+    ) -> Result<RecordBatch>
+    {
+        let mut hostname_port = Vec::new();
+        let mut timestamp = Vec::new();
+        let mut entity_type = Vec::new();
+        let mut entity_id = Vec::new();
+        let mut namespace = Vec::new();
+        let mut table_name = Vec::new();
+        let mut attributes = Vec::new();
+        let mut metric_name = Vec::new();
+        let mut value = Vec::new();
+        let mut count = Vec::new();
+        let mut sum = Vec::new();
+        let mut rows = Vec::new();
+        let mut min = Vec::new();
+        let mut mean = Vec::new();
+        let mut percentile_75 = Vec::new();
+        let mut percentile_95 = Vec::new();
+        let mut percentile_99 = Vec::new();
+        let mut percentile_99_9 = Vec::new();
+        let mut percentile_99_99 = Vec::new();
+        let mut max = Vec::new();
+
+        for metricentity in &self.metricentity
+        {
+            for metric in &metricentity.metrics
+            {
+                let name = match metric
+                {
+                    MetricValue { name, value: metric_value } => {
+                        value.push(Some(*metric_value));
+                        count.push(None);
+                        sum.push(None);
+                        rows.push(None);
+                        min.push(None);
+                        mean.push(None);
+                        percentile_75.push(None);
+                        percentile_95.push(None);
+                        percentile_99.push(None);
+                        percentile_99_9.push(None);
+                        percentile_99_99.push(None);
+                        max.push(None);
+                        name
+                    },
+                    MetricCountSum { name, total_count, min: metric_min, mean: metric_mean, percentile_75: metric_percentile_75, percentile_95: metric_percentile_95, percentile_99: metric_percentile_99, percentile_99_9: metric_percentile_99_9, percentile_99_99: metric_percentile_99_99, max: metric_max, total_sum } => {
+                        value.push(None);
+                        count.push(Some(*total_count));
+                        sum.push(Some(*total_sum));
+                        rows.push(None);
+                        min.push(Some(*metric_min));
+                        mean.push(Some(*metric_mean));
+                        percentile_75.push(Some(*metric_percentile_75));
+                        percentile_95.push(Some(*metric_percentile_95));
+                        percentile_99.push(Some(*metric_percentile_99));
+                        percentile_99_9.push(Some(*metric_percentile_99_9));
+                        percentile_99_99.push(Some(*metric_percentile_99_99));
+                        max.push(Some(*metric_max));
+                        name
+                    },
+                    MetricCountSumRows { name, count: metric_count, sum: metric_sum, rows: metric_rows } => {
+                        value.push(None);
+                        count.push(Some(*metric_count));
+                        sum.push(Some(*metric_sum));
+                        rows.push(Some(*metric_rows));
+                        min.push(None);
+                        mean.push(None);
+                        percentile_75.push(None);
+                        percentile_95.push(None);
+                        percentile_99.push(None);
+                        percentile_99_9.push(None);
+                        percentile_99_99.push(None);
+                        max.push(None);
+                        name
+                    },
+                    _ => continue,
+                };
+                hostname_port.push(metricentity.hostname_port.clone());
+                timestamp.push(metricentity.timestamp.map(|timestamp| timestamp.timestamp_micros()));
+                entity_type.push(metricentity.metrics_type.clone());
+                entity_id.push(metricentity.id.clone());
+                namespace.push(metricentity.attributes.as_ref().and_then(|attributes| attributes.namespace_name.clone()));
+                table_name.push(metricentity.attributes.as_ref().and_then(|attributes| attributes.table_name.clone()));
+                attributes.push(metricentity.attributes.as_ref().map(Attributes::labels));
+                metric_name.push(name.clone());
+            }
+        }
+
+        RecordBatch::try_from_iter(vec![
+            ("hostname_port", Arc::new(StringArray::from(hostname_port)) as ArrayRef),
+            ("timestamp", Arc::new(TimestampMicrosecondArray::from(timestamp).with_timezone("UTC")) as ArrayRef),
+            ("entity_type", Arc::new(StringArray::from(entity_type)) as ArrayRef),
+            ("entity_id", Arc::new(StringArray::from(entity_id)) as ArrayRef),
+            ("namespace", Arc::new(StringArray::from(namespace)) as ArrayRef),
+            ("table_name", Arc::new(StringArray::from(table_name)) as ArrayRef),
+            ("attributes", Arc::new(StringArray::from(attributes)) as ArrayRef),
+            ("metric_name", Arc::new(StringArray::from(metric_name)) as ArrayRef),
+            ("value", Arc::new(Int64Array::from(value)) as ArrayRef),
+            ("count", Arc::new(UInt64Array::from(count)) as ArrayRef),
+            ("sum", Arc::new(UInt64Array::from(sum)) as ArrayRef),
+            ("rows", Arc::new(UInt64Array::from(rows)) as ArrayRef),
+            ("min", Arc::new(UInt64Array::from(min)) as ArrayRef),
+            ("mean", Arc::new(Float64Array::from(mean)) as ArrayRef),
+            ("percentile_75", Arc::new(UInt64Array::from(percentile_75)) as ArrayRef),
+            ("percentile_95", Arc::new(UInt64Array::from(percentile_95)) as ArrayRef),
+            ("percentile_99", Arc::new(UInt64Array::from(percentile_99)) as ArrayRef),
+            ("percentile_99_9", Arc::new(UInt64Array::from(percentile_99_9)) as ArrayRef),
+            ("percentile_99_99", Arc::new(UInt64Array::from(percentile_99_99)) as ArrayRef),
+            ("max", Arc::new(UInt64Array::from(max)) as ArrayRef),
+        ]).with_context(|| "Error creating the metrics record batch")
+    }
+    /// Read the metric entities back from the record batches of a parquet snapshot, see [AllMetricEntity::record_batch].
+    ///
+    /// The metrics of an entity are consecutive rows. Entities without metrics and rejected metric values are not in the parquet data,
+    /// and the attributes are read back from their labels, so these are strings.
+    pub fn from_record_batches(
+        record_batches: &[RecordBatch],
+    ) -> Result<AllMetricEntity>
+    {
+        let mut allmetricentity = AllMetricEntity::new();
+        for record_batch in record_batches
+        {
+            let hostname_port = snapshot::parquet_column::<StringArray>(record_batch, "hostname_port")?;
+            let timestamp = snapshot::parquet_column::<TimestampMicrosecondArray>(record_batch, "timestamp")?;
+            let entity_type = snapshot::parquet_column::<StringArray>(record_batch, "entity_type")?;
+            let entity_id = snapshot::parquet_column::<StringArray>(record_batch, "entity_id")?;
+            let attributes = snapshot::parquet_column::<StringArray>(record_batch, "attributes")?;
+            let metric_name = snapshot::parquet_column::<StringArray>(record_batch, "metric_name")?;
+            let value = snapshot::parquet_column::<Int64Array>(record_batch, "value")?;
+            let count = snapshot::parquet_column::<UInt64Array>(record_batch, "count")?;
+            let sum = snapshot::parquet_column::<UInt64Array>(record_batch, "sum")?;
+            let rows = snapshot::parquet_column::<UInt64Array>(record_batch, "rows")?;
+            let min = snapshot::parquet_column::<UInt64Array>(record_batch, "min")?;
+            let mean = snapshot::parquet_column::<Float64Array>(record_batch, "mean")?;
+            let percentile_75 = snapshot::parquet_column::<UInt64Array>(record_batch, "percentile_75")?;
+            let percentile_95 = snapshot::parquet_column::<UInt64Array>(record_batch, "percentile_95")?;
+            let percentile_99 = snapshot::parquet_column::<UInt64Array>(record_batch, "percentile_99")?;
+            let percentile_99_9 = snapshot::parquet_column::<UInt64Array>(record_batch, "percentile_99_9")?;
+            let percentile_99_99 = snapshot::parquet_column::<UInt64Array>(record_batch, "percentile_99_99")?;
+            let max = snapshot::parquet_column::<UInt64Array>(record_batch, "max")?;
+
+            for row in 0..record_batch.num_rows()
+            {
+                let name = metric_name.value(row).to_string();
+                let metric = if value.is_valid(row)
+                {
+                    MetricValue { name, value: value.value(row) }
+                }
+                else if rows.is_valid(row)
+                {
+                    MetricCountSumRows { name, count: count.value(row), sum: sum.value(row), rows: rows.value(row) }
+                }
+                else
+                {
+                    MetricCountSum {
+                        name,
+                        total_count: count.value(row),
+                        min: min.value(row),
+                        mean: mean.value(row),
+                        percentile_75: percentile_75.value(row),
+                        percentile_95: percentile_95.value(row),
+                        percentile_99: percentile_99.value(row),
+                        percentile_99_9: percentile_99_9.value(row),
+                        percentile_99_99: percentile_99_99.value(row),
+                        max: max.value(row),
+                        total_sum: sum.value(row),
+                    }
+                };
+                let row_hostname_port = hostname_port.is_valid(row).then(|| hostname_port.value(row).to_string());
+                let row_timestamp = timestamp.is_valid(row)
+                    .then(|| DateTime::from_timestamp_micros(timestamp.value(row)).map(|timestamp| timestamp.with_timezone(&Local)))
+                    .flatten();
+                match allmetricentity.metricentity.last_mut()
+                {
+                    Some(metricentity) if metricentity.hostname_port == row_hostname_port
+                        && metricentity.timestamp == row_timestamp
+                        && metricentity.metrics_type == entity_type.value(row)
+                        && metricentity.id == entity_id.value(row) => metricentity.metrics.push(metric),
+                    _ => allmetricentity.metricentity.push(MetricEntity {
+                        hostname_port: row_hostname_port,
+                        timestamp: row_timestamp,
+                        metrics_type: entity_type.value(row).to_string(),
+                        id: entity_id.value(row).to_string(),
+                        attributes: attributes.is_valid(row).then(|| Attributes::from_labels(attributes.value(row))),
+                        metrics: vec![metric],
+                    }),
+                }
+            }
+        }
+        Ok(allmetricentity)
+    }
     fn parse_metrics(
         http_data: String,
         host: &str,
@@ -99,17 +362,21 @@ impl MetricEntityDiff {
         end_snapshot: &String,
         begin_snapshot_time: &DateTime<Local>,
         details_enable: &bool,
+        rollup_table: &bool,
+        metric_renames: &[MetricRename],
+        config: &Config,
     ) -> Result<MetricEntityDiff>
     {
         let mut metricentitydiff = MetricEntityDiff::new();
 
-        let mut allmetricentity = AllMetricEntity::new();
-        allmetricentity.metricentity = snapshot::read_snapshot_json(begin_snapshot, "metrics")?;
-        metricentitydiff.first_snapshot(allmetricentity, details_enable);
+        let mut first_allmetricentity = AllMetricEntity::new();
+        first_allmetricentity.metricentity = read_begin_metrics(begin_snapshot, end_snapshot, config)?;
+        let mut second_allmetricentity = AllMetricEntity::new();
+        second_allmetricentity.metricentity = snapshot::read_snapshot_json(end_snapshot, "metrics", config)?;
+        metricentitydiff.renamed_metrics = apply_metric_renames(metric_renames, &mut first_allmetricentity, &mut second_allmetricentity);
 
-        let mut allmetricentity = AllMetricEntity::new();
-        allmetricentity.metricentity = snapshot::read_snapshot_json(end_snapshot, "metrics")?;
-        metricentitydiff.second_snapshot(allmetricentity, details_enable, begin_snapshot_time);
+        metricentitydiff.first_snapshot(first_allmetricentity, details_enable, rollup_table);
+        metricentitydiff.second_snapshot(second_allmetricentity, details_enable, rollup_table, begin_snapshot_time);
 
         Ok(metricentitydiff)
     }
@@ -117,26 +384,18 @@ impl MetricEntityDiff {
         &mut self,
         allmetricentity: AllMetricEntity,
         details_enable: &bool,
+        rollup_table: &bool,
     )
     {
         for metricentity in allmetricentity.metricentity
         {
+            let (changed_metrics_id, summed) = metric_entity_id(&metricentity, details_enable, rollup_table);
             for metric in metricentity.metrics
             {
                 match metric
                 {
                     MetricValue { name, value } =>
                         {
-                            let changed_metrics_id = if !*details_enable
-                                && (metricentity.metrics_type.clone() == "table"
-                                || metricentity.metrics_type.clone() == "tablet"
-                                || metricentity.metrics_type.clone() == "cdc"
-                                || metricentity.metrics_type.clone() == "cdcsdk")
-                            {
-                                "-".to_string()
-                            } else {
-                                metricentity.id.clone()
-                            };
                             self.btreemetricdiffvalue
                                 .entry((
                                     metricentity.hostname_port
@@ -144,15 +403,11 @@ impl MetricEntityDiff {
                                         .expect("hostname:port should be set"),
                                     metricentity.metrics_type
                                         .clone(),
-                                    changed_metrics_id,
+                                    changed_metrics_id.clone(),
                                     name.clone()
                                 ))
                                 .and_modify(|row| {
-                                    if !*details_enable
-                                        && (metricentity.metrics_type.clone() == "table"
-                                        || metricentity.metrics_type.clone() == "tablet"
-                                        || metricentity.metrics_type.clone() == "cdc"
-                                        || metricentity.metrics_type.clone() == "cdcsdk")
+                                    if summed
                                     {
                                         row.first_value += value;
                                     } else {
@@ -189,18 +444,9 @@ impl MetricEntityDiff {
                                     ..Default::default()
                                 });
                         }
-                    MetricCountSum { name, total_count, total_sum, .. } =>
+                    MetricCountSum { name, total_count, total_sum, percentile_75, percentile_95, percentile_99, max, .. } =>
                         {
-                            let changed_metrics_id = if !*details_enable
-                                && (metricentity.metrics_type.clone() == "table"
-                                || metricentity.metrics_type.clone() == "tablet"
-                                || metricentity.metrics_type.clone() == "cdc"
-                                || metricentity.metrics_type.clone() == "cdcsdk")
-                            {
-                                "-".to_string()
-                            } else {
-                                metricentity.id.clone()
-                            };
+                            let percentiles = MetricPercentiles { percentile_75, percentile_95, percentile_99, max };
                             self.btreemetricdiffcountsum
                                 .entry((
                                     metricentity.hostname_port
@@ -208,18 +454,15 @@ impl MetricEntityDiff {
                                         .expect("hostname:port should be set"),
                                     metricentity.metrics_type
                                         .clone(),
-                                    changed_metrics_id,
+                                    changed_metrics_id.clone(),
                                     name.clone()
                                 ))
                                 .and_modify(|row| {
-                                    if !*details_enable
-                                        && (metricentity.metrics_type.clone() == "table"
-                                        || metricentity.metrics_type.clone() == "tablet"
-                                        || metricentity.metrics_type.clone() == "cdc"
-                                        || metricentity.metrics_type.clone() == "cdcsdk")
+                                    if summed
                                     {
                                         row.first_total_count += total_count;
                                         row.first_total_sum += total_sum;
+                                        row.first_percentiles.merge(&percentiles);
                                     } else {
                                         warn!("First snapshot duplicate entry: hostname_port: {}, metrics_type: {}, id: {}, name: {}",
                                             metricentity.hostname_port
@@ -252,6 +495,7 @@ impl MetricEntityDiff {
                                         .unwrap_or_default(),
                                     first_total_count: total_count,
                                     first_total_sum: total_sum,
+                                    first_percentiles: percentiles,
                                     ..Default::default()
                                 });
                         }
@@ -315,27 +559,19 @@ impl MetricEntityDiff {
         &mut self,
         allmetricentity: AllMetricEntity,
         details_enable: &bool,
+        rollup_table: &bool,
         begin_snapshot_timestamp: &DateTime<Local>,
     )
     {
         for metricentity in allmetricentity.metricentity
         {
+            let (changed_metrics_id, _) = metric_entity_id(&metricentity, details_enable, rollup_table);
             for metric in metricentity.metrics
             {
                 match metric
                 {
                     MetricValue { name, value } =>
                         {
-                            let changed_metrics_id = if !*details_enable
-                                && (metricentity.metrics_type.clone() == "table"
-                                || metricentity.metrics_type.clone() == "tablet"
-                                || metricentity.metrics_type.clone() == "cdc"
-                                || metricentity.metrics_type.clone() == "cdcsdk")
-                            {
-                                "-".to_string()
-                            } else {
-                                metricentity.id.clone()
-                            };
                             self.btreemetricdiffvalue
                                 .entry((
                                     metricentity.hostname_port
@@ -343,7 +579,7 @@ impl MetricEntityDiff {
                                         .expect("hostname:port should be set"),
                                     metricentity.metrics_type
                                         .clone(),
-                                    changed_metrics_id,
+                                    changed_metrics_id.clone(),
                                     name.clone(),
                                 ))
                                 .and_modify(|row| {
@@ -372,18 +608,9 @@ impl MetricEntityDiff {
                                     ..Default::default()
                                 });
                         }
-                    MetricCountSum { name, total_count, total_sum, .. } =>
+                    MetricCountSum { name, total_count, total_sum, percentile_75, percentile_95, percentile_99, max, .. } =>
                         {
-                            let changed_metrics_id = if !*details_enable
-                                && (metricentity.metrics_type.clone() == "table"
-                                || metricentity.metrics_type.clone() == "tablet"
-                                || metricentity.metrics_type.clone() == "cdc"
-                                || metricentity.metrics_type.clone() == "cdcsdk")
-                            {
-                                "-".to_string()
-                            } else {
-                                metricentity.id.clone()
-                            };
+                            let percentiles = MetricPercentiles { percentile_75, percentile_95, percentile_99, max };
                             self.btreemetricdiffcountsum
                                 .entry((
                                     metricentity.hostname_port
@@ -391,13 +618,14 @@ impl MetricEntityDiff {
                                         .expect("hostname:port should be set"),
                                     metricentity.metrics_type
                                         .clone(),
-                                    changed_metrics_id,
+                                    changed_metrics_id.clone(),
                                     name.clone()
                                 ))
                                 .and_modify(|row| {
                                     row.second_snapshot_time = metricentity.timestamp.unwrap_or_default();
                                     row.second_total_count += total_count;
                                     row.second_total_sum += total_sum;
+                                    row.second_percentiles.merge(&percentiles);
                                 })
                                 .or_insert(MetricDiffCountSum {
                                     table_name: metricentity.attributes
@@ -419,6 +647,7 @@ impl MetricEntityDiff {
                                         .unwrap_or_default(),
                                     second_total_count: total_count,
                                     second_total_sum: total_sum,
+                                    second_percentiles: percentiles,
                                     ..Default::default()
                                 });
                         }
@@ -472,20 +701,42 @@ impl MetricEntityDiff {
             }
         }
     }
-    pub async fn print(
+    /// The lines of the diff that match the filters, and the number of changes that are suppressed by the thresholds.
+    /// Every line has its percent change, which it is sorted on with sort_by_pct, and whether it is a context line of `--diff-context`,
+    /// which is an unchanged counter.
+    #[allow(clippy::too_many_arguments)]
+    fn lines(
         &self,
         hostname_filter: &Regex,
         stat_name_filter: &Regex,
         table_name_filter: &Regex,
         details_enable: &bool,
         gauges_enable: &bool,
-    )
+        sort_by_pct: &bool,
+        metric_types: &[String],
+        diff_context: &Option<String>,
+        outlier_sigma: &Option<f64>,
+        threshold: &MetricDiffThreshold,
+        config: &Config,
+    ) -> (Vec<(Option<f64>, bool, String)>, usize)
     {
-        // value_diff
         let value_statistics = metrics::ValueStatistics::create();
+        let context_groups = self.diff_context_groups(hostname_filter, stat_name_filter, table_name_filter, metric_types, diff_context, &value_statistics);
+        let outliers = outlier_sigma.map(|outlier_sigma| self.outliers(hostname_filter, outlier_sigma)).unwrap_or_default();
+        // every line is stored with its percent change, so the lines can be sorted on it with sort_by_pct.
+        let mut lines: Vec<(Option<f64>, bool, String)> = Vec::new();
+        // the changes below --diff-threshold or --pct-threshold are counted, and reported after the lines.
+        let mut suppressed = 0;
+        // value_diff
         for ((hostname, metric_type, metric_id, metric_name), row) in &self.btreemetricdiffvalue
         {
             let metadata = value_statistics.lookup(metric_name);
+            let pct = percent_change(row.first_value as f64, row.second_value as f64);
+            // with --diff-context, an unchanged counter is shown dimmed if a metric of the same group did change.
+            let context = row.second_value == row.first_value
+                && diff_context.as_ref().is_some_and(|diff_context| diff_context_group(diff_context, metric_name, &row.namespace, &row.table_name)
+                    .is_some_and(|group| context_groups.contains(&(hostname.clone(), metric_type.clone(), metric_id.clone(), group))));
+            let outlier = outliers.contains(&(hostname.clone(), metric_type.clone(), metric_id.clone(), metric_name.clone()));
             // if second_value = 0, the statistic was zero, meaning no use,
             // or it wasn't filled out for the second snapshot, meaning the endppoint went away.
             // in both cases do not report.
@@ -497,9 +748,14 @@ impl MetricEntityDiff {
                 // show as counter.
                 // the choice of != gauge is deliberate here: if it's unknown, it'll be shown as counter.
                 if metadata.stat_type != "gauge"
-                    && row.second_value - row.first_value != 0
+                    && metric_type_selected(metric_types, "counter")
+                    && (row.second_value - row.first_value != 0 || context)
                 {
-                    if *details_enable
+                    if !context && threshold.suppresses((row.second_value - row.first_value) as f64, pct)
+                    {
+                        suppressed += 1;
+                    }
+                    else if *details_enable
                     {
                         let table_info = if row.namespace.is_empty() && row.table_name.is_empty()
                         {
@@ -515,7 +771,7 @@ impl MetricEntityDiff {
                         };
 
                         //println!("{:20} {:8} {:32} {:15} {:30} {:70} {:15} {:6} {:>15.3} /s",
-                        println!("{:20} {:8} {:32} {:30} {:70} {:15} {:6} {:>15.3} /s",
+                        lines.push((pct, context, mark_line(context, outlier, format!("{:20} {:8} {:32} {:30} {:70} {:15} {:6} {} /s {:>8}",
                                  hostname,
                                  metric_type,
                                  metric_id,
@@ -523,28 +779,36 @@ impl MetricEntityDiff {
                                  metric_name,
                                  row.second_value - row.first_value,
                                  metadata.unit_suffix,
-                                 ((row.second_value - row.first_value) as f64 / (row.second_snapshot_time - row.first_snapshot_time).num_milliseconds() as f64 * 1000_f64)
-                        );
+                                 utility::format_float((row.second_value - row.first_value) as f64 / (row.second_snapshot_time - row.first_snapshot_time).num_milliseconds() as f64 * 1000_f64, 15, 3, config),
+                                 format_percent_change(pct)
+                        ))));
                     }
                     else
                     {
-                        println!("{:20} {:8} {:70} {:15} {:6} {:>15.3} /s",
+                        lines.push((pct, context, mark_line(context, outlier, format!("{:20} {:8} {:70} {:15} {:6} {} /s {:>8}",
                                  hostname,
                                  metric_type,
                                  metric_name,
                                  row.second_value - row.first_value,
                                  metadata.unit_suffix,
-                                 ((row.second_value - row.first_value) as f64 / (row.second_snapshot_time - row.first_snapshot_time).num_milliseconds() as f64 * 1000_f64)
-                        );
+                                 utility::format_float((row.second_value - row.first_value) as f64 / (row.second_snapshot_time - row.first_snapshot_time).num_milliseconds() as f64 * 1000_f64, 15, 3, config),
+                                 format_percent_change(pct)
+                        ))));
                     }
                 }
                 // show as gauge
                 // gauges are shown when the difference between first and second snapshot is zero,
                 // provided the absolute second value is higher than 0.
+                // selecting gauges with --metric-type shows gauges too.
                 if metadata.stat_type == "gauge"
-                    && *gauges_enable
+                    && (*gauges_enable || metric_types.iter().any(|selected| selected == "gauge"))
+                    && metric_type_selected(metric_types, "gauge")
                 {
-                    if *details_enable
+                    if threshold.suppresses((row.second_value - row.first_value) as f64, pct)
+                    {
+                        suppressed += 1;
+                    }
+                    else if *details_enable
                     {
                         let table_info = if row.namespace.is_empty() && row.table_name.is_empty()
                         {
@@ -558,7 +822,7 @@ impl MetricEntityDiff {
                         {
                             format!("{}.{}", row.namespace, row.table_name)
                         };
-                        println!("{:20} {:8} {:32} {:30} {:70} {:15} {:6} {:+15}",
+                        lines.push((pct, false, mark_line(false, outlier, format!("{:20} {:8} {:32} {:30} {:70} {:15} {:6} {:+15} {:>8}",
                                  hostname,
                                  metric_type,
                                  metric_id,
@@ -566,29 +830,36 @@ impl MetricEntityDiff {
                                  metric_name,
                                  row.second_value,
                                  metadata.unit_suffix,
-                                 row.second_value - row.first_value
-                        );
+                                 row.second_value - row.first_value,
+                                 format_percent_change(pct)
+                        ))));
                     }
                     else
                     {
-                        println!("{:20} {:8} {:70} {:15} {:6} {:+15}",
+                        lines.push((pct, false, mark_line(false, outlier, format!("{:20} {:8} {:70} {:15} {:6} {:+15} {:>8}",
                                  hostname,
                                  metric_type,
                                  metric_name,
                                  row.second_value,
                                  metadata.unit_suffix,
-                                 row.second_value - row.first_value
-                        );
+                                 row.second_value - row.first_value,
+                                 format_percent_change(pct)
+                        ))));
                     }
                 }
             }
         }
         // countsum_diff
         let countsum_statistics = metrics::CountSumStatistics::create();
+        // selecting histograms with --metric-type adds the change of the percentiles.
+        let show_percentiles = metric_types.iter().any(|selected| selected == "histogram");
         for ((hostname, metric_type, metric_id, metric_name), row) in &self.btreemetricdiffcountsum
         {
             let metadata = countsum_statistics.lookup(metric_name);
+            let pct = percent_change(row.first_total_count as f64, row.second_total_count as f64);
+            let outlier = outliers.contains(&(hostname.clone(), metric_type.clone(), metric_id.clone(), metric_name.clone()));
             if row.second_total_count > 0
+                && metric_type_selected(metric_types, "histogram")
                 && hostname_filter.is_match(hostname)
                 && stat_name_filter.is_match(metric_name)
                 && table_name_filter.is_match(&row.table_name)
@@ -600,7 +871,19 @@ impl MetricEntityDiff {
                 // saturating_sub halts the value at 0 the first value happens to be higher.
                 if row.second_total_count.saturating_sub(row.first_total_count) != 0
                 {
-                    if *details_enable
+                    let percentiles_text = if show_percentiles
+                    {
+                        format!(" {}", format_percentiles_change(&row.first_percentiles, &row.second_percentiles))
+                    }
+                    else
+                    {
+                        "".to_string()
+                    };
+                    if threshold.suppresses(row.second_total_count.saturating_sub(row.first_total_count) as f64, pct)
+                    {
+                        suppressed += 1;
+                    }
+                    else if *details_enable
                     {
                         let table_info = if row.namespace.is_empty() && row.table_name.is_empty()
                         {
@@ -614,31 +897,35 @@ impl MetricEntityDiff {
                         {
                             format!("{}.{}", row.namespace, row.table_name)
                         };
-                        println!("{:20} {:8} {:32} {:30} {:70} {:15}        {:>15.3} /s avg: {:9.0} tot: {:>15.3} {:10}",
+                        lines.push((pct, false, mark_line(false, outlier, format!("{:20} {:8} {:32} {:30} {:70} {:15}        {} /s avg: {} tot: {:>15.3} {:10} {:>8}{}",
                                  hostname,
                                  metric_type,
                                  metric_id,
                                  table_info,
                                  metric_name,
                                  row.second_total_count - row.first_total_count,
-                                 (row.second_total_count - row.first_total_count) as f64 / (row.second_snapshot_time - row.first_snapshot_time).num_milliseconds() as f64 * 1000_f64,
-                                 ((row.second_total_sum - row.first_total_sum) / (row.second_total_count - row.first_total_count)) as f64,
+                                 utility::format_float((row.second_total_count - row.first_total_count) as f64 / (row.second_snapshot_time - row.first_snapshot_time).num_milliseconds() as f64 * 1000_f64, 15, 3, config),
+                                 utility::format_float((row.second_total_sum - row.first_total_sum) as f64 / (row.second_total_count - row.first_total_count) as f64, 9, 0, config),
                                  row.second_total_sum - row.first_total_sum,
-                                 metadata.unit_suffix
-                        );
+                                 metadata.unit_suffix,
+                                 format_percent_change(pct),
+                                 percentiles_text,
+                        ))));
                     }
                     else
                     {
-                        println!("{:20} {:8} {:70} {:15}        {:>15.3} /s avg: {:9.0} tot: {:>15.3} {:10}",
+                        lines.push((pct, false, mark_line(false, outlier, format!("{:20} {:8} {:70} {:15}        {} /s avg: {} tot: {:>15.3} {:10} {:>8}{}",
                                  hostname,
                                  metric_type,
                                  metric_name,
                                  row.second_total_count - row.first_total_count,
-                                 (row.second_total_count - row.first_total_count) as f64 / (row.second_snapshot_time - row.first_snapshot_time).num_milliseconds() as f64 * 1000_f64,
-                                 ((row.second_total_sum - row.first_total_sum) / (row.second_total_count - row.first_total_count)) as f64,
+                                 utility::format_float((row.second_total_count - row.first_total_count) as f64 / (row.second_snapshot_time - row.first_snapshot_time).num_milliseconds() as f64 * 1000_f64, 15, 3, config),
+                                 utility::format_float((row.second_total_sum - row.first_total_sum) as f64 / (row.second_total_count - row.first_total_count) as f64, 9, 0, config),
                                  row.second_total_sum - row.first_total_sum,
-                                 metadata.unit_suffix
-                        );
+                                 metadata.unit_suffix,
+                                 format_percent_change(pct),
+                                 percentiles_text,
+                        ))));
                     }
                 }
             }
@@ -648,19 +935,271 @@ impl MetricEntityDiff {
         {
             if hostname_filter.is_match(hostname)
                 && stat_name_filter.is_match(metric_name)
+                && metric_type_selected(metric_types, "counter")
                 && row.second_count - row.first_count != 0
             {
-                println!("{:20} {:70} {:>15} avg: {:>15.3} tot: {:>15.3} ms, avg: {:>15} tot: {:>15} rows",
+                let pct = percent_change(row.first_count as f64, row.second_count as f64);
+                if threshold.suppresses((row.second_count - row.first_count) as f64, pct)
+                {
+                    suppressed += 1;
+                    continue;
+                }
+                lines.push((pct, false, format!("{:20} {:70} {:>15} avg: {} tot: {} ms, avg: {} tot: {:>15} rows {:>8}",
                          hostname,
                          metric_name,
                          row.second_count - row.first_count,
-                         ((row.second_sum as f64 - row.first_sum as f64) / 1000.0) / (row.second_count - row.first_count) as f64,
-                         (row.second_sum as f64 - row.first_sum as f64) / 1000.0,
-                         (row.second_rows - row.first_rows) / (row.second_count - row.first_count),
-                         row.second_rows - row.first_rows
-                );
+                         utility::format_float(((row.second_sum as f64 - row.first_sum as f64) / 1000.0) / (row.second_count - row.first_count) as f64, 15, 3, config),
+                         utility::format_float((row.second_sum as f64 - row.first_sum as f64) / 1000.0, 15, 3, config),
+                         utility::format_float((row.second_rows - row.first_rows) as f64 / (row.second_count - row.first_count) as f64, 15, 0, config),
+                         row.second_rows - row.first_rows,
+                         format_percent_change(pct)
+                )));
+            }
+        }
+        if *sort_by_pct
+        {
+            // sort on the absolute percent change, highest first; new metrics (no begin value) are sorted first.
+            lines.sort_by(|(first, ..), (second, ..)| {
+                let first = first.map(f64::abs).unwrap_or(f64::INFINITY);
+                let second = second.map(f64::abs).unwrap_or(f64::INFINITY);
+                second.partial_cmp(&first).unwrap_or(std::cmp::Ordering::Equal)
+            });
+        }
+        (lines, suppressed)
+    }
+    /// Print the metric renames that are applied to compare the snapshots, as part of the diff header.
+    /// With --summary-only there is no header, and the renames are part of the summary line, see [MetricEntityDiff::summary_line].
+    pub fn print_renamed_metrics(
+        &self,
+        config: &Config,
+    ) -> Result<()>
+    {
+        if config.summary_only
+        {
+            return Ok(());
+        }
+        for metric_rename in &self.renamed_metrics
+        {
+            writeln!(config.output(), "Metric renamed between the snapshots: {} -> {}", metric_rename.old_name, metric_rename.new_name)?;
+        }
+        Ok(())
+    }
+    /// Print the metrics diff, and return the number of changed metrics that are shown, without the context lines, see [MetricEntityDiff::summary].
+    #[allow(clippy::too_many_arguments)]
+    pub async fn print(
+        &self,
+        hostname_filter: &Regex,
+        stat_name_filter: &Regex,
+        table_name_filter: &Regex,
+        details_enable: &bool,
+        gauges_enable: &bool,
+        sort_by_pct: &bool,
+        metric_types: &[String],
+        diff_context: &Option<String>,
+        outlier_sigma: &Option<f64>,
+        computes: &[MetricCompute],
+        threshold: &MetricDiffThreshold,
+        config: &Config,
+    ) -> Result<usize>
+    {
+        let (lines, suppressed) = self.lines(hostname_filter, stat_name_filter, table_name_filter, details_enable, gauges_enable, sort_by_pct, metric_types, diff_context, outlier_sigma, threshold, config);
+        let changes = lines.iter().filter(|(_, context, _)| !context).count();
+        let mut rows = utility::RowWriter::new(config);
+        for (_, _, line) in lines
+        {
+            writeln!(rows, "{}", line)?;
+        }
+        // computed metrics are shown after the metrics they are computed from, and are labeled as computed.
+        if metric_type_selected(metric_types, "counter")
+        {
+            for computed in self.computed_metrics()
+                .iter()
+                .filter(|computed| hostname_filter.is_match(&computed.hostname_port)
+                    && stat_name_filter.is_match(&computed.name)
+                    && table_name_filter.is_match(&computed.table_name))
+            {
+                print_computed_metric(computed, details_enable, &mut rows, config)?;
+            }
+        }
+        // the metrics computed with --compute are always shown, because they are explicitly requested.
+        let deltas = self.btreemetricdiffvalue
+            .iter()
+            .map(|(key, row)| (key, &row.table_name, &row.namespace, (row.second_value - row.first_value) as f64));
+        for computed in compute_metrics(deltas, computes)
+            .iter()
+            .filter(|computed| hostname_filter.is_match(&computed.hostname_port)
+                && table_name_filter.is_match(&computed.table_name))
+        {
+            print_computed_metric(computed, details_enable, &mut rows, config)?;
+        }
+        rows.finish()?;
+        if suppressed > 0
+        {
+            writeln!(config.output(), "{} changes below the diff threshold not shown.", suppressed)?;
+        }
+        Ok(changes)
+    }
+    /// The computed metrics of every hostname_port, metric type and id, see [COMPUTED_METRICS].
+    ///
+    /// A computed metric is only returned if all counters it is computed from are found,
+    /// and the counters it is computed from changed.
+    fn computed_metrics(&self) -> Vec<ComputedMetric>
+    {
+        let mut entities: BTreeMap<(&String, &String, &String), BTreeMap<&str, &MetricDiffValues>> = BTreeMap::new();
+        for ((hostname_port, metric_type, metric_id, metric_name), row) in &self.btreemetricdiffvalue
+        {
+            entities.entry((hostname_port, metric_type, metric_id)).or_default().insert(metric_name.as_str(), row);
+        }
+
+        let mut computed_metrics = Vec::new();
+        for ((hostname_port, metric_type, metric_id), rows) in entities
+        {
+            for (name, unit, counters, per) in COMPUTED_METRICS
+            {
+                let Some(counter_rows) = counters.iter().map(|counter| rows.get(counter)).collect::<Option<Vec<_>>>() else { continue };
+                let delta: i64 = counter_rows.iter().map(|row| row.second_value - row.first_value).sum();
+                if delta == 0 { continue };
+                let value = match per
+                {
+                    Some(per) => {
+                        let Some(per_row) = rows.get(per) else { continue };
+                        if per_row.second_value == per_row.first_value { continue };
+                        delta as f64 / (per_row.second_value - per_row.first_value) as f64
+                    },
+                    None => {
+                        let milliseconds = (counter_rows[0].second_snapshot_time - counter_rows[0].first_snapshot_time).num_milliseconds();
+                        if milliseconds == 0 { continue };
+                        delta as f64 / milliseconds as f64 * 1000_f64
+                    },
+                };
+                computed_metrics.push(ComputedMetric {
+                    hostname_port: hostname_port.clone(),
+                    metric_type: metric_type.clone(),
+                    metric_id: metric_id.clone(),
+                    table_name: counter_rows[0].table_name.clone(),
+                    table_info: table_info(&counter_rows[0].namespace, &counter_rows[0].table_name),
+                    name: name.to_string(),
+                    unit,
+                    value,
+                });
+            }
+        }
+        computed_metrics
+    }
+    /// The summary line of --summary-only, with the number of changed and suppressed metrics, see [MetricEntityDiff::summary],
+    /// and the metric renames that are applied.
+    pub fn summary_line(
+        &self,
+        changed: usize,
+        suppressed: usize,
+    ) -> String
+    {
+        let mut line = format!("metrics diff: {} changed, {} suppressed", changed, suppressed);
+        if !self.renamed_metrics.is_empty()
+        {
+            line.push_str(&format!(", renamed: {}", self.renamed_metrics.iter().map(|metric_rename| format!("{} -> {}", metric_rename.old_name, metric_rename.new_name)).collect::<Vec<_>>().join(", ")));
+        }
+        line
+    }
+    /// The number of metrics the diff shows, without the context lines, and the number of changes that are suppressed
+    /// by the thresholds, for --summary-only. These are counted from the lines of the diff, so these match what the diff shows.
+    #[allow(clippy::too_many_arguments)]
+    pub fn summary(
+        &self,
+        hostname_filter: &Regex,
+        stat_name_filter: &Regex,
+        table_name_filter: &Regex,
+        details_enable: &bool,
+        gauges_enable: &bool,
+        metric_types: &[String],
+        diff_context: &Option<String>,
+        threshold: &MetricDiffThreshold,
+        config: &Config,
+    ) -> (usize, usize)
+    {
+        let (lines, suppressed) = self.lines(hostname_filter, stat_name_filter, table_name_filter, details_enable, gauges_enable, &false, metric_types, diff_context, &None, threshold, config);
+        (lines.iter().filter(|(_, context, _)| !context).count(), suppressed)
+    }
+    /// The metrics of hosts that are outliers, for --highlight-outliers.
+    ///
+    /// For every metric (metric type, id and name), the deltas of the hosts are compared,
+    /// which is the value delta for counters and gauges, and the count delta for histograms.
+    /// Only the hosts that match the hostname filter are compared.
+    fn outliers(
+        &self,
+        hostname_filter: &Regex,
+        outlier_sigma: f64,
+    ) -> HashSet<(String, String, String, String)>
+    {
+        let mut metric_host_deltas: MetricHostDeltas = BTreeMap::new();
+        for ((hostname, metric_type, metric_id, metric_name), row) in self.btreemetricdiffvalue
+            .iter()
+            .filter(|((hostname, ..), _)| hostname_filter.is_match(hostname))
+        {
+            metric_host_deltas.entry((metric_type, metric_id, metric_name)).or_default().push((hostname, (row.second_value - row.first_value) as f64));
+        }
+        for ((hostname, metric_type, metric_id, metric_name), row) in self.btreemetricdiffcountsum
+            .iter()
+            .filter(|((hostname, ..), _)| hostname_filter.is_match(hostname))
+        {
+            metric_host_deltas.entry((metric_type, metric_id, metric_name)).or_default().push((hostname, row.second_total_count.saturating_sub(row.first_total_count) as f64));
+        }
+
+        let mut outliers = HashSet::new();
+        for ((metric_type, metric_id, metric_name), host_deltas) in &metric_host_deltas
+        {
+            for hostname in outlier_hosts(host_deltas, outlier_sigma)
+            {
+                outliers.insert((hostname.clone(), metric_type.to_string(), metric_id.to_string(), metric_name.to_string()));
+            }
+        }
+        outliers
+    }
+    /// The groups that have a changed counter or histogram that is printed, for --diff-context.
+    /// A group is per hostname, metric type and metric id, and the group of the metric, see [diff_context_group].
+    fn diff_context_groups(
+        &self,
+        hostname_filter: &Regex,
+        stat_name_filter: &Regex,
+        table_name_filter: &Regex,
+        metric_types: &[String],
+        diff_context: &Option<String>,
+        value_statistics: &metrics::ValueStatistics,
+    ) -> HashSet<(String, String, String, String)>
+    {
+        let mut context_groups = HashSet::new();
+        let Some(diff_context) = diff_context else { return context_groups };
+        for ((hostname, metric_type, metric_id, metric_name), row) in &self.btreemetricdiffvalue
+        {
+            if row.second_value > 0
+                && row.second_value - row.first_value != 0
+                && value_statistics.lookup(metric_name).stat_type != "gauge"
+                && metric_type_selected(metric_types, "counter")
+                && hostname_filter.is_match(hostname)
+                && stat_name_filter.is_match(metric_name)
+                && table_name_filter.is_match(&row.table_name)
+            {
+                if let Some(group) = diff_context_group(diff_context, metric_name, &row.namespace, &row.table_name)
+                {
+                    context_groups.insert((hostname.clone(), metric_type.clone(), metric_id.clone(), group));
+                }
+            }
+        }
+        for ((hostname, metric_type, metric_id, metric_name), row) in &self.btreemetricdiffcountsum
+        {
+            if row.second_total_count.saturating_sub(row.first_total_count) != 0
+                && metric_type_selected(metric_types, "histogram")
+                && hostname_filter.is_match(hostname)
+                && stat_name_filter.is_match(metric_name)
+                && table_name_filter.is_match(&row.table_name)
+            {
+                if let Some(group) = diff_context_group(diff_context, metric_name, &row.namespace, &row.table_name)
+                {
+                    context_groups.insert((hostname.clone(), metric_type.clone(), metric_id.clone(), group));
+                }
             }
         }
+        context_groups
     }
     pub async fn adhoc_read_first_snapshot(
         &mut self,
@@ -668,11 +1207,14 @@ impl MetricEntityDiff {
         ports: &Vec<&str>,
         parallel: usize,
         details_enable: bool,
+        rollup_table: bool,
+        config: &Config,
     )
     {
-        let allmetricentity = AllMetricEntity::read_metrics(hosts, ports, parallel).await;
-        self.first_snapshot(allmetricentity, &details_enable);
+        let allmetricentity = AllMetricEntity::read_metrics(hosts, ports, parallel, config).await;
+        self.first_snapshot(allmetricentity, &details_enable, &rollup_table);
     }
+    #[allow(clippy::too_many_arguments)]
     pub async fn adhoc_read_second_snapshot(
         &mut self,
         hosts: &Vec<&str>,
@@ -680,114 +1222,1560 @@ impl MetricEntityDiff {
         parallel: usize,
         first_snapshot_time: &DateTime<Local>,
         details_enable: bool,
+        rollup_table: bool,
+        config: &Config,
     )
     {
-        let allmetricentity = AllMetricEntity::read_metrics(hosts, ports, parallel).await;
-        self.second_snapshot(allmetricentity, &details_enable, first_snapshot_time);
+        let allmetricentity = AllMetricEntity::read_metrics(hosts, ports, parallel, config).await;
+        self.second_snapshot(allmetricentity, &details_enable, &rollup_table, first_snapshot_time);
     }
 }
 
 pub async fn metrics_diff(
     options: &Opts,
+    config: &Config,
 ) -> Result<()>
 {
     if options.begin.is_none() || options.end.is_none() {
-        snapshot::Snapshot::print()?;
+        snapshot::Snapshot::print(config)?;
     }
     if options.snapshot_list { return Ok(()) };
 
     let hostname_filter = utility::set_regex(&options.hostname_match);
     let stat_name_filter = utility::set_regex(&options.stat_name_match);
     let table_name_filter = utility::set_regex(&options.table_name_match);
+    let computes = parse_computes(&options.compute)?;
 
     let (begin_snapshot, end_snapshot, begin_snapshot_row) = snapshot::Snapshot::read_begin_end_snapshot_from_user(options.begin, options.end)?;
 
-    let metricsdiff = MetricEntityDiff::snapshot_diff(&begin_snapshot, &end_snapshot, &begin_snapshot_row.timestamp, &options.details_enable)?;
-    metricsdiff.print(&hostname_filter, &stat_name_filter, &table_name_filter, &options.details_enable, &options.gauges_enable).await;
+    snapshot::Snapshot::print_diff_header(&begin_snapshot, &end_snapshot, config)?;
+
+    // --rollup-table shows a row per table, which requires the details to tell the tables apart.
+    let details_enable = options.details_enable || options.rollup_table;
+    let metric_renames = read_metric_renames(&options.metric_rename_file)?;
+    let metricsdiff = MetricEntityDiff::snapshot_diff(&begin_snapshot, &end_snapshot, &begin_snapshot_row.timestamp, &details_enable, &options.rollup_table, &metric_renames, config)?;
+    metricsdiff.print_renamed_metrics(config)?;
+    if !options.assert.is_empty() {
+        let mut failed = Vec::new();
+        for expression in &options.assert
+        {
+            let assertion = MetricAssertion::parse(expression)?;
+            if !metricsdiff.assert(&assertion, &hostname_filter, config)? {
+                failed.push(expression.as_str());
+            }
+        }
+        if !failed.is_empty() {
+            bail!("Assertion failed: {}", failed.join(", "));
+        }
+    } else if options.only_errors {
+        let error_filter = Regex::new(options.error_pattern.as_deref().unwrap_or(ERROR_METRIC_PATTERN))
+            .with_context(|| format!("Invalid --error-pattern: {}", options.error_pattern.as_deref().unwrap_or_default()))?;
+        let rows = metricsdiff.error_increases(&hostname_filter, &table_name_filter, &error_filter, config);
+        if rows.is_empty() {
+            writeln!(config.output(), "No error metrics increased.")?;
+        } else {
+            utility::print_table(&ERROR_INCREASE_HEADERS, &rows, config)?;
+        }
+    } else if options.summary_only {
+        let (changed, suppressed) = metricsdiff.summary(&hostname_filter, &stat_name_filter, &table_name_filter, &details_enable, &options.gauges_enable, &options.metric_type, &options.diff_context, &MetricDiffThreshold { delta: options.diff_threshold, pct: options.pct_threshold }, config);
+        writeln!(config.output(), "{}", metricsdiff.summary_line(changed, suppressed))?;
+    } else {
+        metricsdiff.print(&hostname_filter, &stat_name_filter, &table_name_filter, &details_enable, &options.gauges_enable, &options.sort_by_pct, &options.metric_type, &options.diff_context, &options.highlight_outliers.then_some(options.outlier_sigma), &computes, &MetricDiffThreshold { delta: options.diff_threshold, pct: options.pct_threshold }, config).await?;
+    }
 
     Ok(())
 }
 
-#[cfg(test)]
-mod tests {
-    use crate::metrics::Metrics::{RejectedBooleanMetricValue, RejectedU64MetricValue};
-    use super::*;
-
-    #[test]
-    /// cdcsdk (change data capture software development kit) metrics value
-    /// Please mind type cdc has an extra, unique, attribute: stream_id. This is currently not parsed.
-    fn unit_parse_metrics_cdcsdk_value() {
-        let json = r#"
-[
+/// Read the metrics of the begin snapshot, or with `--baseline-snapshots` the baseline merged from the baseline snapshots,
+/// which must all be before the end snapshot.
+fn read_begin_metrics(
+    begin_snapshot: &String,
+    end_snapshot: &String,
+    config: &Config,
+) -> Result<Vec<MetricEntity>>
+{
+    let baseline_snapshots = &config.baseline_snapshots;
+    if baseline_snapshots.is_empty()
     {
-        "type": "cdcsdk",
-        "id": ":face4edb05934e77b564857878cf5015:4457a26b28a64393ac626504aba5f571",
-        "attributes": {
-            "stream_id": "face4edb05934e77b564857878cf5015",
-            "table_name": "table0",
-            "namespace_name": "test",
-            "table_id": "c70ffbbe28f14e84b0559c405ae20197"
-        },
-        "metrics": [
-            {
-                "name": "async_replication_sent_lag_micros",
-                "value": 0
-            }
-        ]
+        return snapshot::read_snapshot_json(begin_snapshot, "metrics", config);
     }
-]"#.to_string();
-        let result = AllMetricEntity::parse_metrics(json, "", "");
-        assert_eq!(result[0].metrics_type,"cdcsdk");
-        if let MetricValue { name, value } = &result[0].metrics[0]
+    if let Ok(end_snapshot_number) = end_snapshot.parse::<i32>()
+    {
+        if baseline_snapshots.iter().any(|number| *number >= end_snapshot_number)
         {
-            assert_eq!(name.clone(), "async_replication_sent_lag_micros");
-            assert_eq!(value, &0);
-        };
+            bail!("The baseline snapshots must be before the end snapshot {}", end_snapshot);
+        }
+    }
+    let mut snapshots = Vec::new();
+    for number in baseline_snapshots
+    {
+        snapshots.push(snapshot::read_snapshot_json(&number.to_string(), "metrics", config)?);
     }
+    Ok(merge_baseline_metrics(snapshots))
+}
 
-    #[test]
-    /// cdc (change data capture) metrics value
-    /// Please mind type cdc has an extra, unique, attribute: stream_id.
-    fn unit_parse_metrics_cdc_value() {
-        let json = r#"
-[
+/// Merge the metrics of the baseline snapshots, ordered from first to last, into a single baseline:
+/// - gauges (value metrics with the gauge statistic type) are the average of the value in the snapshots that have the metric, rounded to a whole number.
+/// - counters (the other value metrics) are the value of the first snapshot, so the difference is the total since the first snapshot.
+/// - histograms (countsum) and countsumrows metrics are the counts, sums, rows and percentiles of the first snapshot.
+///
+/// The entities and metrics are the ones of the first snapshot, with its timestamp.
+/// A metric that is not in the first snapshot is not in the baseline, and is shown as new in the diff.
+fn merge_baseline_metrics(
+    snapshots: Vec<Vec<MetricEntity>>,
+) -> Vec<MetricEntity>
+{
+    let value_statistics = metrics::ValueStatistics::create();
+    let mut gauges: BTreeMap<(String, String, String, String), (i64, i64)> = BTreeMap::new();
+    for metricentity in snapshots.iter().flatten()
     {
-        "type": "cdc",
-        "id": ":face4edb05934e77b564857878cf5015:4457a26b28a64393ac626504aba5f571",
-        "attributes": {
-            "stream_id": "face4edb05934e77b564857878cf5015",
-            "table_name": "table0",
-            "namespace_name": "test",
-            "table_id": "c70ffbbe28f14e84b0559c405ae20197"
-        },
-        "metrics": [
+        for metric in &metricentity.metrics
+        {
+            let MetricValue { name, value } = metric else { continue };
+            if value_statistics.lookup(name).stat_type != "gauge" { continue };
+            let (sum, count) = gauges
+                .entry((metricentity.hostname_port.clone().unwrap_or_default(), metricentity.metrics_type.clone(), metricentity.id.clone(), name.clone()))
+                .or_default();
+            *sum += value;
+            *count += 1;
+        }
+    }
+    let mut baseline = snapshots.into_iter().next().unwrap_or_default();
+    for metricentity in baseline.iter_mut()
+    {
+        let (hostname_port, metrics_type, id) = (metricentity.hostname_port.clone().unwrap_or_default(), metricentity.metrics_type.clone(), metricentity.id.clone());
+        for metric in metricentity.metrics.iter_mut()
+        {
+            let MetricValue { name, value } = metric else { continue };
+            if let Some((sum, count)) = gauges.get(&(hostname_port.clone(), metrics_type.clone(), id.clone(), name.clone()))
             {
-                "name": "async_replication_sent_lag_micros",
-                "value": 0
+                *value = (*sum as f64 / *count as f64).round() as i64;
             }
-        ]
-    }
-]"#.to_string();
-        let result = AllMetricEntity::parse_metrics(json, "", "");
-        assert_eq!(result[0].metrics_type,"cdc");
-        if let MetricValue { name, value} = &result[0].metrics[0]
-                {
-                    assert_eq!(name.clone(), "async_replication_sent_lag_micros");
-                    assert_eq!(value, &0);
-                };
+        }
     }
+    baseline
+}
 
-        #[test]
-        /// cdc (change data capture) metrics value
-        /// Please mind type cdc has an extra, unique, attribute: stream_id.
-        fn unit_parse_metrics_cdc_countsum() {
-            let json = r#"
-    [
-        {
-            "type": "cdc",
-            "id": ":face4edb05934e77b564857878cf5015:4457a26b28a64393ac626504aba5f571",
-            "attributes": {
-                "stream_id": "face4edb05934e77b564857878cf5015",
+/// The built-in metric renames between YugabyteDB versions, as (old name, new name).
+/// The renames of `--metric-rename-file` are merged over these, see [merge_metric_renames].
+const BUILTIN_METRIC_RENAMES: &[(&str, &str)] = &[];
+
+/// Read the metric renames: the built-in renames, with the renames of the metric rename CSV file merged over these if it is set.
+/// The file has the header `old_name,new_name`, lines starting with '#' are treated as comments.
+pub fn read_metric_renames(
+    filename: &Option<String>,
+) -> Result<Vec<MetricRename>>
+{
+    let file_renames = match filename
+    {
+        Some(filename) => {
+            let file = fs::File::open(filename)
+                .with_context(|| format!("Error opening metric rename file: {}", filename))?;
+            parse_metric_renames(file)?
+        },
+        None => Vec::new(),
+    };
+    Ok(merge_metric_renames(BUILTIN_METRIC_RENAMES, file_renames))
+}
+
+/// Merge the renames of the metric rename file over the built-in renames.
+/// A rename of the file replaces the built-in rename of the same old name, and a rename of a name to itself removes it,
+/// so the file can correct or disable a built-in rename.
+fn merge_metric_renames(
+    builtin_renames: &[(&str, &str)],
+    file_renames: Vec<MetricRename>,
+) -> Vec<MetricRename>
+{
+    let mut metric_renames: BTreeMap<String, String> = builtin_renames
+        .iter()
+        .map(|(old_name, new_name)| (old_name.to_string(), new_name.to_string()))
+        .collect();
+    metric_renames.extend(file_renames.into_iter().map(|metric_rename| (metric_rename.old_name, metric_rename.new_name)));
+    metric_renames
+        .into_iter()
+        .filter(|(old_name, new_name)| old_name != new_name)
+        .map(|(old_name, new_name)| MetricRename { old_name, new_name })
+        .collect()
+}
+
+fn parse_metric_renames<R: Read>(
+    reader: R,
+) -> Result<Vec<MetricRename>>
+{
+    let mut metric_renames = Vec::new();
+    let mut reader = csv::ReaderBuilder::new()
+        .comment(Some(b'#'))
+        .trim(csv::Trim::All)
+        .from_reader(reader);
+    for row in reader.deserialize()
+    {
+        let metric_rename: MetricRename = row
+            .with_context(|| "Error deserialize metric rename row")?;
+        metric_renames.push(metric_rename);
+    }
+    Ok(metric_renames)
+}
+
+/// The metric names of a snapshot.
+fn metric_names(
+    allmetricentity: &AllMetricEntity,
+) -> BTreeSet<&str>
+{
+    allmetricentity.metricentity
+        .iter()
+        .flat_map(|metricentity| metricentity.metrics.iter())
+        .map(|metric| match metric
+        {
+            MetricValue { name, .. } |
+            MetricCountSum { name, .. } |
+            MetricCountSumRows { name, .. } |
+            RejectedU64MetricValue { name, .. } |
+            RejectedBooleanMetricValue { name, .. } => name.as_str(),
+        })
+        .collect()
+}
+
+/// Rename the metrics in a snapshot from the old name to the new name.
+fn rename_metric(
+    allmetricentity: &mut AllMetricEntity,
+    metric_rename: &MetricRename,
+)
+{
+    for metric in allmetricentity.metricentity
+        .iter_mut()
+        .flat_map(|metricentity| metricentity.metrics.iter_mut())
+    {
+        let (MetricValue { name, .. } |
+            MetricCountSum { name, .. } |
+            MetricCountSumRows { name, .. } |
+            RejectedU64MetricValue { name, .. } |
+            RejectedBooleanMetricValue { name, .. }) = metric;
+        if *name == metric_rename.old_name
+        {
+            *name = metric_rename.new_name.clone();
+        }
+    }
+}
+
+/// Apply the metric renames for comparing the snapshots, and return the renames that are applied.
+///
+/// A rename is only applied if one snapshot has the old name and not the new name, and the other snapshot the new name and not the old name,
+/// which means the snapshots are from different versions.
+/// This also makes a rename work for a diff from a newer to an older version.
+fn apply_metric_renames(
+    metric_renames: &[MetricRename],
+    first: &mut AllMetricEntity,
+    second: &mut AllMetricEntity,
+) -> Vec<MetricRename>
+{
+    let (first_names, second_names): (BTreeSet<String>, BTreeSet<String>) = (
+        metric_names(first).into_iter().map(str::to_string).collect(),
+        metric_names(second).into_iter().map(str::to_string).collect(),
+    );
+    let only = |names: &BTreeSet<String>, name: &str, other: &str| names.contains(name) && !names.contains(other);
+    let mut applied = Vec::new();
+    for metric_rename in metric_renames
+    {
+        let (old_name, new_name) = (metric_rename.old_name.as_str(), metric_rename.new_name.as_str());
+        if only(&first_names, old_name, new_name) && only(&second_names, new_name, old_name)
+        {
+            rename_metric(first, metric_rename);
+        }
+        else if only(&second_names, old_name, new_name) && only(&first_names, new_name, old_name)
+        {
+            rename_metric(second, metric_rename);
+        }
+        else
+        {
+            continue;
+        }
+        info!("metric rename applied: {} -> {}", old_name, new_name);
+        applied.push(metric_rename.clone());
+    }
+    applied
+}
+
+/// The metric names with the hosts they are found on.
+type MetricNameHosts = Vec<(String, Vec<String>)>;
+
+impl MetricSchemaDiff {
+    pub fn new() -> Self { Default::default() }
+    pub fn snapshot_diff(
+        begin_snapshot: &String,
+        end_snapshot: &String,
+        config: &Config,
+    ) -> Result<MetricSchemaDiff>
+    {
+        let mut metricschemadiff = MetricSchemaDiff::new();
+
+        let mut allmetricentity = AllMetricEntity::new();
+        allmetricentity.metricentity = snapshot::read_snapshot_json(begin_snapshot, "metrics", config)?;
+        metricschemadiff.add_snapshot(allmetricentity, true);
+
+        let mut allmetricentity = AllMetricEntity::new();
+        allmetricentity.metricentity = snapshot::read_snapshot_json(end_snapshot, "metrics", config)?;
+        metricschemadiff.add_snapshot(allmetricentity, false);
+
+        Ok(metricschemadiff)
+    }
+    /// Add the metric names per host of a snapshot, the values are ignored.
+    fn add_snapshot(
+        &mut self,
+        allmetricentity: AllMetricEntity,
+        first_snapshot: bool,
+    )
+    {
+        for metricentity in allmetricentity.metricentity
+        {
+            let hostname_port = metricentity.hostname_port
+                .clone()
+                .expect("hostname:port should be set");
+            if first_snapshot {
+                self.first_hosts.insert(hostname_port.clone());
+            } else {
+                self.second_hosts.insert(hostname_port.clone());
+            }
+            for metric in metricentity.metrics
+            {
+                let name = match metric
+                {
+                    MetricValue { name, .. } => name,
+                    MetricCountSum { name, .. } => name,
+                    MetricCountSumRows { name, .. } => name,
+                    _ => {
+                        debug!("Encountered unknown metric type: {:?}", metric);
+                        continue;
+                    },
+                };
+                let metricschemahosts = self.metricschema.entry(name).or_default();
+                if first_snapshot {
+                    metricschemahosts.first_hosts.insert(hostname_port.clone());
+                } else {
+                    metricschemahosts.second_hosts.insert(hostname_port.clone());
+                }
+            }
+        }
+    }
+    /// Return the metric names that appeared and disappeared, with the hosts they appeared or disappeared on.
+    ///
+    /// Only hosts that are present in both snapshots are considered,
+    /// so a host that could not be reached in one of the snapshots does not show all its metrics as changed.
+    fn changes(
+        &self,
+        hostname_filter: &Regex,
+    ) -> (MetricNameHosts, MetricNameHosts)
+    {
+        let mut appeared = Vec::new();
+        let mut disappeared = Vec::new();
+        for (name, metricschemahosts) in &self.metricschema
+        {
+            let appeared_hosts: Vec<String> = metricschemahosts.second_hosts
+                .difference(&metricschemahosts.first_hosts)
+                .filter(|hostname_port| self.first_hosts.contains(*hostname_port))
+                .filter(|hostname_port| hostname_filter.is_match(hostname_port))
+                .cloned()
+                .collect();
+            if !appeared_hosts.is_empty() {
+                appeared.push((name.clone(), appeared_hosts));
+            }
+            let disappeared_hosts: Vec<String> = metricschemahosts.first_hosts
+                .difference(&metricschemahosts.second_hosts)
+                .filter(|hostname_port| self.second_hosts.contains(*hostname_port))
+                .filter(|hostname_port| hostname_filter.is_match(hostname_port))
+                .cloned()
+                .collect();
+            if !disappeared_hosts.is_empty() {
+                disappeared.push((name.clone(), disappeared_hosts));
+            }
+        }
+        (appeared, disappeared)
+    }
+    /// Print the metric names that appeared or disappeared, and return the number of these metric names.
+    pub fn print(
+        &self,
+        hostname_filter: &Regex,
+        config: &Config,
+    ) -> Result<usize>
+    {
+        let (appeared, disappeared) = self.changes(hostname_filter);
+        for (name, hostnames) in &appeared
+        {
+            writeln!(config.output(), "{} {:70} {}", "+".to_string().green(), name, hostnames.join(", "))?;
+        }
+        for (name, hostnames) in &disappeared
+        {
+            writeln!(config.output(), "{} {:70} {}", "-".to_string().red(), name, hostnames.join(", "))?;
+        }
+        if appeared.is_empty() && disappeared.is_empty() {
+            writeln!(config.output(), "No metric names appeared or disappeared.")?;
+        }
+        Ok(appeared.len() + disappeared.len())
+    }
+}
+
+pub async fn metrics_schema_diff(
+    options: &Opts,
+    config: &Config,
+) -> Result<()>
+{
+    if options.begin.is_none() || options.end.is_none() {
+        snapshot::Snapshot::print(config)?;
+    }
+    if options.snapshot_list { return Ok(()) };
+
+    let hostname_filter = utility::set_regex(&options.hostname_match);
+
+    let (begin_snapshot, end_snapshot, _begin_snapshot_row) = snapshot::Snapshot::read_begin_end_snapshot_from_user(options.begin, options.end)?;
+
+    snapshot::Snapshot::print_diff_header(&begin_snapshot, &end_snapshot, config)?;
+
+    let metricschemadiff = MetricSchemaDiff::snapshot_diff(&begin_snapshot, &end_snapshot, config)?;
+    let changes = metricschemadiff.print(&hostname_filter, config)?;
+    utility::add_diff_changes(changes, config);
+
+    Ok(())
+}
+
+impl MetricEntityPrint {
+    pub fn new() -> Self { Default::default() }
+    /// Add the metrics of a single snapshot.
+    ///
+    /// If details_enable is not set, the table, tablet and cdc metrics are summed per server,
+    /// identical to how the metrics diff works.
+    fn add_metrics(
+        &mut self,
+        allmetricentity: AllMetricEntity,
+        details_enable: &bool,
+    )
+    {
+        for metricentity in allmetricentity.metricentity
+        {
+            let summed_type = !*details_enable
+                && (metricentity.metrics_type == "table"
+                || metricentity.metrics_type == "tablet"
+                || metricentity.metrics_type == "cdc"
+                || metricentity.metrics_type == "cdcsdk");
+            let metrics_id = if summed_type { "-".to_string() } else { metricentity.id.clone() };
+            let hostname_port = metricentity.hostname_port.clone().expect("hostname:port should be set");
+            let attributes = metricentity.attributes.unwrap_or_default();
+            let table_name = attributes.table_name.unwrap_or_default();
+            let namespace = attributes.namespace_name.unwrap_or_default();
+
+            for metric in metricentity.metrics
+            {
+                match metric
+                {
+                    MetricValue { name, value } =>
+                        {
+                            self.btreemetricvalue
+                                .entry((hostname_port.clone(), metricentity.metrics_type.clone(), metrics_id.clone(), name))
+                                .and_modify(|row| row.value += value)
+                                .or_insert(MetricValues {
+                                    table_name: table_name.clone(),
+                                    namespace: namespace.clone(),
+                                    value,
+                                });
+                        }
+                    MetricCountSum { name, total_count, total_sum, percentile_75, percentile_95, percentile_99, max, .. } =>
+                        {
+                            let percentiles = MetricPercentiles { percentile_75, percentile_95, percentile_99, max };
+                            self.btreemetriccountsum
+                                .entry((hostname_port.clone(), metricentity.metrics_type.clone(), metrics_id.clone(), name))
+                                .and_modify(|row| {
+                                    row.total_count += total_count;
+                                    row.total_sum += total_sum;
+                                    row.percentiles.merge(&percentiles);
+                                })
+                                .or_insert(MetricCountSumValues {
+                                    table_name: table_name.clone(),
+                                    namespace: namespace.clone(),
+                                    total_count,
+                                    total_sum,
+                                    percentiles,
+                                });
+                        }
+                    MetricCountSumRows { name, count, sum, rows } =>
+                        {
+                            self.btreemetriccountsumrows
+                                .entry((hostname_port.clone(), metricentity.metrics_type.clone(), metricentity.id.clone(), name))
+                                .or_insert(MetricCountSumRowsValues {
+                                    count,
+                                    sum,
+                                    rows,
+                                });
+                        }
+                    _ =>
+                        {
+                            debug!("Encountered unknown metric type: {:?}", metric);
+                        }
+                }
+            }
+        }
+    }
+    #[allow(clippy::too_many_arguments)]
+    pub fn print(
+        &self,
+        hostname_filter: &Regex,
+        stat_name_filter: &Regex,
+        table_name_filter: &Regex,
+        details_enable: &bool,
+        metric_types: &[String],
+        computes: &[MetricCompute],
+        config: &Config,
+    ) -> Result<()>
+    {
+        let mut rows = utility::RowWriter::new(config);
+        let value_statistics = metrics::ValueStatistics::create();
+        for ((hostname, metric_type, metric_id, metric_name), row) in &self.btreemetricvalue
+        {
+            if row.value != 0
+                && hostname_filter.is_match(hostname)
+                && stat_name_filter.is_match(metric_name)
+                && table_name_filter.is_match(&row.table_name)
+            {
+                let metadata = value_statistics.lookup(metric_name);
+                if !metric_type_selected(metric_types, value_metric_type(&metadata.stat_type))
+                {
+                    continue;
+                }
+                if *details_enable
+                {
+                    writeln!(rows, "{:20} {:8} {:32} {:30} {:70} {:15} {:6}",
+                             hostname,
+                             metric_type,
+                             metric_id,
+                             table_info(&row.namespace, &row.table_name),
+                             metric_name,
+                             row.value,
+                             metadata.unit_suffix,
+                    )?;
+                }
+                else
+                {
+                    writeln!(rows, "{:20} {:8} {:70} {:15} {:6}",
+                             hostname,
+                             metric_type,
+                             metric_name,
+                             row.value,
+                             metadata.unit_suffix,
+                    )?;
+                }
+            }
+        }
+        let countsum_statistics = metrics::CountSumStatistics::create();
+        // selecting histograms with --metric-type adds the percentiles.
+        let show_percentiles = metric_types.iter().any(|selected| selected == "histogram");
+        for ((hostname, metric_type, metric_id, metric_name), row) in &self.btreemetriccountsum
+        {
+            if row.total_count != 0
+                && metric_type_selected(metric_types, "histogram")
+                && hostname_filter.is_match(hostname)
+                && stat_name_filter.is_match(metric_name)
+                && table_name_filter.is_match(&row.table_name)
+            {
+                let metadata = countsum_statistics.lookup(metric_name);
+                let percentiles_text = if show_percentiles { format!(" {}", format_percentiles(&row.percentiles)) } else { "".to_string() };
+                if *details_enable
+                {
+                    writeln!(rows, "{:20} {:8} {:32} {:30} {:70} {:15} avg: {} tot: {:>15} {:10}{}",
+                             hostname,
+                             metric_type,
+                             metric_id,
+                             table_info(&row.namespace, &row.table_name),
+                             metric_name,
+                             row.total_count,
+                             utility::format_float(row.total_sum as f64 / row.total_count as f64, 9, 0, config),
+                             row.total_sum,
+                             metadata.unit_suffix,
+                             percentiles_text,
+                    )?;
+                }
+                else
+                {
+                    writeln!(rows, "{:20} {:8} {:70} {:15} avg: {} tot: {:>15} {:10}{}",
+                             hostname,
+                             metric_type,
+                             metric_name,
+                             row.total_count,
+                             utility::format_float(row.total_sum as f64 / row.total_count as f64, 9, 0, config),
+                             row.total_sum,
+                             metadata.unit_suffix,
+                             percentiles_text,
+                    )?;
+                }
+            }
+        }
+        for ((hostname, _metric_type, _metric_id, metric_name), row) in &self.btreemetriccountsumrows
+        {
+            if row.count != 0
+                && metric_type_selected(metric_types, "counter")
+                && hostname_filter.is_match(hostname)
+                && stat_name_filter.is_match(metric_name)
+            {
+                writeln!(rows, "{:20} {:70} {:>15} avg: {} tot: {} ms, avg: {} tot: {:>15} rows",
+                         hostname,
+                         metric_name,
+                         row.count,
+                         utility::format_float((row.sum as f64 / 1000.0) / row.count as f64, 15, 3, config),
+                         utility::format_float(row.sum as f64 / 1000.0, 15, 3, config),
+                         utility::format_float(row.rows as f64 / row.count as f64, 15, 0, config),
+                         row.rows,
+                )?;
+            }
+        }
+        let values = self.btreemetricvalue
+            .iter()
+            .map(|(key, row)| (key, &row.table_name, &row.namespace, row.value as f64));
+        for computed in compute_metrics(values, computes)
+            .iter()
+            .filter(|computed| hostname_filter.is_match(&computed.hostname_port)
+                && table_name_filter.is_match(&computed.table_name))
+        {
+            print_computed_metric(computed, details_enable, &mut rows, config)?;
+        }
+        rows.finish()
+    }
+    /// Print the metrics that are outside the range set for them in the baseline.
+    ///
+    /// Metrics that are not in the baseline are ignored.
+    pub fn print_baseline_deviations(
+        &self,
+        allmetricbaseline: &AllMetricBaseline,
+        hostname_filter: &Regex,
+        stat_name_filter: &Regex,
+        table_name_filter: &Regex,
+        config: &Config,
+    ) -> Result<()>
+    {
+        let mut deviations = 0;
+        let values = self.btreemetricvalue
+            .iter()
+            .filter(|(_, row)| table_name_filter.is_match(&row.table_name))
+            .map(|(key, row)| (key, row.value as f64));
+        let countsums = self.btreemetriccountsum
+            .iter()
+            .filter(|(_, row)| table_name_filter.is_match(&row.table_name))
+            .map(|(key, row)| (key, row.total_count as f64));
+        let countsumrows = self.btreemetriccountsumrows
+            .iter()
+            .map(|(key, row)| (key, row.count as f64));
+
+        for ((hostname, metric_type, metric_id, metric_name), value) in values.chain(countsums).chain(countsumrows)
+        {
+            if !hostname_filter.is_match(hostname)
+                || !stat_name_filter.is_match(metric_name)
+            {
+                continue;
+            }
+            if let Some(baseline) = allmetricbaseline.deviation(metric_name, value)
+            {
+                deviations += 1;
+                writeln!(config.output(), "{:20} {:8} {:32} {:70} {:>15} {} expected: {}..{}",
+                         hostname,
+                         metric_type,
+                         metric_id,
+                         metric_name,
+                         value,
+                         if value < baseline.min { "<".red() } else { ">".red() },
+                         baseline.min,
+                         baseline.max,
+                )?;
+            }
+        }
+        if deviations == 0
+        {
+            writeln!(config.output(), "No metrics found outside the baseline ranges.")?;
+        }
+        Ok(())
+    }
+}
+
+impl AllMetricBaseline {
+    pub fn new() -> Self { Default::default() }
+    /// Read the baseline file, which is a CSV file with the header `metric,min,max`.
+    /// Lines starting with '#' are treated as comments.
+    pub fn read_baseline_file(
+        filename: &str,
+    ) -> Result<AllMetricBaseline>
+    {
+        let file = fs::File::open(filename)
+            .with_context(|| format!("Error opening baseline file: {}", filename))?;
+        AllMetricBaseline::parse_baseline(file)
+    }
+    fn parse_baseline<R: Read>(
+        reader: R,
+    ) -> Result<AllMetricBaseline>
+    {
+        let mut allmetricbaseline = AllMetricBaseline::new();
+        let mut reader = csv::ReaderBuilder::new()
+            .comment(Some(b'#'))
+            .trim(csv::Trim::All)
+            .from_reader(reader);
+        for row in reader.deserialize()
+        {
+            let metricbaseline: MetricBaseline = row
+                .with_context(|| "Error deserialize baseline row")?;
+            allmetricbaseline.metricbaseline.insert(metricbaseline.metric.clone(), metricbaseline);
+        }
+        Ok(allmetricbaseline)
+    }
+    /// Return the baseline of the metric if the value is outside of its range.
+    fn deviation(
+        &self,
+        metric_name: &str,
+        value: f64,
+    ) -> Option<&MetricBaseline>
+    {
+        self.metricbaseline
+            .get(metric_name)
+            .filter(|baseline| value < baseline.min || value > baseline.max)
+    }
+}
+
+impl MetricAssertion {
+    /// Parse an assertion expression: `[metric=]name [value|delta]op number`, op is one of <, <=, >, >=, =, !=.
+    pub fn parse(
+        expression: &str,
+    ) -> Result<MetricAssertion>
+    {
+        let regex = Regex::new(r"^\s*(?:metric=)?(?P<metric>\w+)(?:\s+(?P<field>value|delta))?\s*(?P<operator><=|>=|!=|==|=|<|>)\s*(?P<number>-?[0-9]+(?:\.[0-9]+)?)\s*$")?;
+        let captures = regex.captures(expression)
+            .with_context(|| format!("Invalid assertion: '{}', expected: 'name op number', such as 'metric=rpc_inbound_calls_created_total delta<1000'", expression))?;
+        Ok(MetricAssertion {
+            metric: captures["metric"].to_string(),
+            field: captures.name("field").map(|field| if field.as_str() == "value" { MetricAssertionField::Value } else { MetricAssertionField::Delta }),
+            operator: captures["operator"].to_string(),
+            number: captures["number"].parse()?,
+        })
+    }
+    pub fn check(
+        &self,
+        value: f64,
+    ) -> bool
+    {
+        match self.operator.as_str() {
+            "<" => value < self.number,
+            "<=" => value <= self.number,
+            ">" => value > self.number,
+            ">=" => value >= self.number,
+            "!=" => value != self.number,
+            _ => value == self.number,
+        }
+    }
+    /// Check all the values for the assertion, print the outcome per value, and return if all passed.
+    ///
+    /// If no values were found for the metric, the assertion fails.
+    fn evaluate<'a>(
+        &self,
+        field: &str,
+        values: impl Iterator<Item = (&'a String, &'a String, f64)>,
+        config: &Config,
+    ) -> Result<bool>
+    {
+        let mut found = false;
+        let mut passed = true;
+        for (hostname_port, metric_id, value) in values
+        {
+            found = true;
+            let check = self.check(value);
+            passed &= check;
+            writeln!(config.output(), "{} {:20} {:32} {} {}: {} {} {}",
+                     if check { "PASS".green() } else { "FAIL".red() },
+                     hostname_port,
+                     metric_id,
+                     self.metric,
+                     field,
+                     value,
+                     self.operator,
+                     self.number,
+            )?;
+        }
+        if !found
+        {
+            writeln!(config.output(), "{} metric {} not found", "FAIL".red(), self.metric)?;
+        }
+        Ok(found && passed)
+    }
+}
+
+impl MetricEntityDiff {
+    /// Evaluate the assertion against the diff, by default using the delta.
+    pub fn assert(
+        &self,
+        assertion: &MetricAssertion,
+        hostname_filter: &Regex,
+        config: &Config,
+    ) -> Result<bool>
+    {
+        let use_value = assertion.field == Some(MetricAssertionField::Value);
+        let values = self.btreemetricdiffvalue
+            .iter()
+            .map(|(key, row)| (key, if use_value { row.second_value as f64 } else { (row.second_value - row.first_value) as f64 }));
+        let countsums = self.btreemetricdiffcountsum
+            .iter()
+            .map(|(key, row)| (key, if use_value { row.second_total_count as f64 } else { row.second_total_count as f64 - row.first_total_count as f64 }));
+        let countsumrows = self.btreemetricdiffcountsumrows
+            .iter()
+            .map(|(key, row)| (key, if use_value { row.second_count as f64 } else { row.second_count as f64 - row.first_count as f64 }));
+        let rows = values
+            .chain(countsums)
+            .chain(countsumrows)
+            .filter(|((hostname_port, _, _, metric_name), _)| *metric_name == assertion.metric && hostname_filter.is_match(hostname_port))
+            .map(|((hostname_port, _, metric_id, _), value)| (hostname_port, metric_id, value));
+        assertion.evaluate(if use_value { "value" } else { "delta" }, rows, config)
+    }
+}
+
+impl MetricEntityPrint {
+    /// Evaluate the assertion against the values of a single snapshot.
+    pub fn assert(
+        &self,
+        assertion: &MetricAssertion,
+        hostname_filter: &Regex,
+        config: &Config,
+    ) -> Result<bool>
+    {
+        if assertion.field == Some(MetricAssertionField::Delta)
+        {
+            bail!("A delta assertion can only be used with a diff, such as --metrics-diff");
+        }
+        let values = self.btreemetricvalue
+            .iter()
+            .map(|(key, row)| (key, row.value as f64));
+        let countsums = self.btreemetriccountsum
+            .iter()
+            .map(|(key, row)| (key, row.total_count as f64));
+        let countsumrows = self.btreemetriccountsumrows
+            .iter()
+            .map(|(key, row)| (key, row.count as f64));
+        let rows = values
+            .chain(countsums)
+            .chain(countsumrows)
+            .filter(|((hostname_port, _, _, metric_name), _)| *metric_name == assertion.metric && hostname_filter.is_match(hostname_port))
+            .map(|((hostname_port, _, metric_id, _), value)| (hostname_port, metric_id, value));
+        assertion.evaluate("value", rows, config)
+    }
+}
+
+impl MetricEntityDiff {
+    /// The metrics with the biggest absolute delta, summed over all hosts per metric type and name, highest first.
+    ///
+    /// Each entry is (metric type, metric name, delta, rate per second). The delta of a countsum is the change of the count.
+    /// Gauges are only included with `gauges_enable`.
+    pub fn top_movers(
+        &self,
+        hostname_filter: &Regex,
+        stat_name_filter: &Regex,
+        gauges_enable: &bool,
+        limit: usize,
+    ) -> Vec<(String, String, i64, f64)>
+    {
+        let value_statistics = metrics::ValueStatistics::create();
+        let rate = |delta: i64, first: &DateTime<Local>, second: &DateTime<Local>| delta as f64 / (*second - *first).num_milliseconds() as f64 * 1000_f64;
+
+        let mut movers: BTreeMap<(String, String), (i64, f64)> = BTreeMap::new();
+        let values = self.btreemetricdiffvalue
+            .iter()
+            .filter(|((_, _, _, metric_name), row)| row.second_value > 0 && (*gauges_enable || value_statistics.lookup(metric_name).stat_type != "gauge"))
+            .map(|(key, row)| (key, row.second_value - row.first_value, rate(row.second_value - row.first_value, &row.first_snapshot_time, &row.second_snapshot_time)));
+        let countsums = self.btreemetricdiffcountsum
+            .iter()
+            .filter(|(_, row)| row.second_total_count > 0)
+            .map(|(key, row)| (key, row.second_total_count as i64 - row.first_total_count as i64, rate(row.second_total_count as i64 - row.first_total_count as i64, &row.first_snapshot_time, &row.second_snapshot_time)));
+        let countsumrows = self.btreemetricdiffcountsumrows
+            .iter()
+            .map(|(key, row)| (key, row.second_count as i64 - row.first_count as i64, rate(row.second_count as i64 - row.first_count as i64, &row.first_snapshot_time, &row.second_snapshot_time)));
+        for ((hostname_port, metric_type, _, metric_name), delta, rate) in values.chain(countsums).chain(countsumrows)
+        {
+            if hostname_filter.is_match(hostname_port) && stat_name_filter.is_match(metric_name)
+            {
+                let mover = movers.entry((metric_type.clone(), metric_name.clone())).or_default();
+                mover.0 += delta;
+                mover.1 += rate;
+            }
+        }
+        let mut movers: Vec<(String, String, i64, f64)> = movers
+            .into_iter()
+            .filter(|(_, (delta, _))| *delta != 0)
+            .map(|((metric_type, metric_name), (delta, rate))| (metric_type, metric_name, delta, rate))
+            .collect();
+        movers.sort_by_key(|(_, _, delta, _)| std::cmp::Reverse(delta.abs()));
+        movers.truncate(limit);
+        movers
+    }
+}
+
+/// The default regex for the names of the error metrics of `--only-errors`, which can be overridden with `--error-pattern`.
+const ERROR_METRIC_PATTERN: &str = "error|fail|reject|timeout|timed_out|retry|retries";
+/// The headers of the rows of [MetricEntityDiff::error_increases].
+const ERROR_INCREASE_HEADERS: [&str; 7] = ["hostname_port", "metric_type", "metric_id", "table", "metric_name", "increase", "/s"];
+
+impl MetricEntityDiff {
+    /// The error metrics that increased between the snapshots, for `--only-errors`: the counters and histograms of which the name
+    /// matches the error filter, and of which the value (or the count for a histogram) went up.
+    /// A decrease is not an error: a counter is only reset by a restart.
+    pub fn error_increases(
+        &self,
+        hostname_filter: &Regex,
+        table_name_filter: &Regex,
+        error_filter: &Regex,
+        config: &Config,
+    ) -> Vec<Vec<String>>
+    {
+        let value_statistics = metrics::ValueStatistics::create();
+        let rate = |delta: i64, first: &DateTime<Local>, second: &DateTime<Local>| delta as f64 / (*second - *first).num_milliseconds() as f64 * 1000_f64;
+
+        let values = self.btreemetricdiffvalue
+            .iter()
+            .filter(|((_, _, _, metric_name), _)| value_statistics.lookup(metric_name).stat_type != "gauge")
+            .map(|(key, row)| (key, &row.namespace, &row.table_name, row.second_value - row.first_value, rate(row.second_value - row.first_value, &row.first_snapshot_time, &row.second_snapshot_time)));
+        let countsums = self.btreemetricdiffcountsum
+            .iter()
+            .map(|(key, row)| (key, &row.namespace, &row.table_name, row.second_total_count as i64 - row.first_total_count as i64, rate(row.second_total_count as i64 - row.first_total_count as i64, &row.first_snapshot_time, &row.second_snapshot_time)));
+        values.chain(countsums)
+            .filter(|((hostname_port, _, _, metric_name), _, table_name, delta, _)| *delta > 0
+                && error_filter.is_match(metric_name)
+                && hostname_filter.is_match(hostname_port)
+                && table_name_filter.is_match(table_name))
+            .map(|((hostname_port, metric_type, metric_id, metric_name), namespace, table_name, delta, rate)| vec![
+                hostname_port.clone(),
+                metric_type.clone(),
+                metric_id.clone(),
+                table_info(namespace, table_name),
+                metric_name.clone(),
+                delta.to_string(),
+                utility::format_float(rate, 0, 3, config),
+            ])
+            .collect()
+    }
+}
+
+/// Print the metrics with the biggest absolute delta cluster-wide between the `--begin` snapshot and the current metrics.
+///
+/// The current metrics are read like the second snapshot of the adhoc metrics diff.
+async fn print_metrics_top_movers(
+    hosts: Vec<&str>,
+    ports: Vec<&str>,
+    parallel: usize,
+    options: &Opts,
+    config: &Config,
+) -> Result<()>
+{
+    let hostname_filter = utility::set_regex(&options.hostname_match);
+    let stat_name_filter = utility::set_regex(&options.stat_name_match);
+
+    // the end is the current metrics.
+    let begin_snapshot_row = snapshot::Snapshot::read_snapshot(options.begin.expect("--top-movers requires --begin"))?;
+    let begin_snapshot = begin_snapshot_row.number.to_string();
+
+    let mut metricentitydiff = MetricEntityDiff::new();
+    let mut allmetricentity = AllMetricEntity::new();
+    allmetricentity.metricentity = snapshot::read_snapshot_json(&begin_snapshot, "metrics", config)?;
+    metricentitydiff.first_snapshot(allmetricentity, &false, &false);
+    metricentitydiff.adhoc_read_second_snapshot(&hosts, &ports, parallel, &begin_snapshot_row.timestamp, false, false, config).await;
+
+    writeln!(config.output(), "Top {} metrics by absolute delta since snapshot {} ({})", options.limit, begin_snapshot, utility::format_timestamp(&begin_snapshot_row.timestamp, config))?;
+    let rows: Vec<Vec<String>> = metricentitydiff.top_movers(&hostname_filter, &stat_name_filter, &options.gauges_enable, options.limit)
+        .into_iter()
+        .map(|(metric_type, metric_name, delta, rate)| vec![
+            metric_type,
+            metric_name,
+            delta.to_string(),
+            format!("{} /s", utility::format_float(rate, 15, 3, config).trim_start()),
+        ])
+        .collect();
+    utility::print_table(&["metric_type", "metric_name", "delta", "rate"], &rows, config)?;
+    Ok(())
+}
+
+/// The metrics that the metrics diff computes from the RocksDB counters, as:
+/// (name, unit, the counters whose deltas are summed, the counter whose delta it is divided by).
+/// Without a counter to divide by, the summed delta is divided by the snapshot interval, which makes it a rate per second.
+///
+/// The write amplification is the bytes written to disk by flushes and compactions divided by the bytes written by the user.
+const COMPUTED_METRICS: [(&str, &str, &[&str], Option<&str>); 4] = [
+    ("computed_compaction_read_throughput", "bytes/s", &["rocksdb_compact_read_bytes"], None),
+    ("computed_compaction_write_throughput", "bytes/s", &["rocksdb_compact_write_bytes"], None),
+    ("computed_flush_write_throughput", "bytes/s", &["rocksdb_flush_write_bytes"], None),
+    ("computed_write_amplification", "ratio", &["rocksdb_flush_write_bytes", "rocksdb_compact_write_bytes"], Some("rocksdb_bytes_written")),
+];
+
+/// The id under which the metrics of a metric entity are diffed, and whether metrics of multiple entities are summed under it.
+///
+/// With `--rollup-table`, tablet metrics are summed per table, using the table id of the tablet.
+/// Without `--details-enable`, the table, tablet and cdc metrics are summed per server.
+fn metric_entity_id(
+    metricentity: &MetricEntity,
+    details_enable: &bool,
+    rollup_table: &bool,
+) -> (String, bool)
+{
+    if *rollup_table && metricentity.metrics_type == "tablet"
+    {
+        match metricentity.attributes.as_ref().and_then(|attributes| attributes.table_id.clone())
+        {
+            Some(table_id) => return (table_id, true),
+            None => warn!("Tablet {} has no table_id attribute, it is not rolled up", metricentity.id),
+        }
+    }
+    if !*details_enable
+        && (metricentity.metrics_type == "table"
+        || metricentity.metrics_type == "tablet"
+        || metricentity.metrics_type == "cdc"
+        || metricentity.metrics_type == "cdcsdk")
+    {
+        ("-".to_string(), true)
+    } else {
+        (metricentity.id.clone(), false)
+    }
+}
+impl MetricDiffThreshold {
+    /// Whether a change is below the delta threshold or the percent threshold.
+    /// A change without a percent change (a begin value of zero) is only compared with the delta threshold.
+    pub fn suppresses(
+        &self,
+        delta: f64,
+        pct: Option<f64>,
+    ) -> bool
+    {
+        self.delta.is_some_and(|threshold| delta.abs() < threshold)
+            || self.pct.zip(pct).is_some_and(|(threshold, pct)| pct.abs() < threshold)
+    }
+}
+/// Calculate the change of the second value relative to the first value in percent.
+/// If the first value is zero, there is no relative change, which is returned as None.
+fn percent_change(
+    first_value: f64,
+    second_value: f64,
+) -> Option<f64>
+{
+    if first_value == 0_f64
+    {
+        None
+    }
+    else
+    {
+        Some((second_value - first_value) / first_value * 100_f64)
+    }
+}
+
+/// The group of a metric for --diff-context, which is the part of the metric name before the first underscore for "prefix",
+/// such as "rocksdb" for rocksdb_bytes_read, or the namespace and table for "table".
+/// A metric that is not of a table has no group for "table".
+fn diff_context_group(
+    diff_context: &str,
+    metric_name: &str,
+    namespace: &str,
+    table_name: &str,
+) -> Option<String>
+{
+    match diff_context
+    {
+        "table" if !table_name.is_empty() => Some(format!("{}.{}", namespace, table_name)),
+        "table" => None,
+        _ => Some(metric_name.split('_').next().unwrap_or_default().to_string()),
+    }
+}
+
+/// Mark the line of a metric: dimmed for an unchanged metric that is shown as context with --diff-context,
+/// and red for a metric that is an outlier with --highlight-outliers.
+fn mark_line(
+    context: bool,
+    outlier: bool,
+    line: String,
+) -> String
+{
+    match (context, outlier)
+    {
+        (_, true) => line.red().to_string(),
+        (true, false) => line.dimmed().to_string(),
+        (false, false) => line,
+    }
+}
+
+/// The deltas of the hosts per metric (metric type, metric id, metric name), for --highlight-outliers.
+type MetricHostDeltas<'a> = BTreeMap<(&'a String, &'a String, &'a String), Vec<(&'a String, f64)>>;
+
+/// The minimal number of hosts a metric must be found on to determine outliers,
+/// with fewer hosts the standard deviation is not meaningful.
+const OUTLIER_MINIMAL_HOSTS: usize = 3;
+
+/// Return the hosts with a delta that is more than outlier_sigma standard deviations from the mean of the deltas of all hosts.
+/// If there are fewer than [OUTLIER_MINIMAL_HOSTS] hosts, or all deltas are equal, there are no outliers.
+fn outlier_hosts<'a>(
+    host_deltas: &[(&'a String, f64)],
+    outlier_sigma: f64,
+) -> Vec<&'a String>
+{
+    if host_deltas.len() < OUTLIER_MINIMAL_HOSTS
+    {
+        return Vec::new();
+    }
+    let mean = host_deltas.iter().map(|(_, delta)| delta).sum::<f64>() / host_deltas.len() as f64;
+    let standard_deviation = (host_deltas.iter().map(|(_, delta)| (delta - mean).powi(2)).sum::<f64>() / host_deltas.len() as f64).sqrt();
+    if standard_deviation == 0_f64
+    {
+        return Vec::new();
+    }
+    host_deltas
+        .iter()
+        .filter(|(_, delta)| (delta - mean).abs() > outlier_sigma * standard_deviation)
+        .map(|(hostname, _)| *hostname)
+        .collect()
+}
+
+/// Format the percent change, a change from zero is shown as 'new'.
+fn format_percent_change(
+    percent_change: Option<f64>,
+) -> String
+{
+    match percent_change {
+        Some(percent_change) => format!("{:+.1}%", percent_change),
+        None => "new".to_string(),
+    }
+}
+
+/// Print a computed metric, labeled as computed.
+fn print_computed_metric(
+    computed: &ComputedMetric,
+    details_enable: &bool,
+    rows: &mut utility::RowWriter,
+    config: &Config,
+) -> Result<()>
+{
+    if *details_enable
+    {
+        writeln!(rows, "{:20} {:8} {:32} {:30} {:70} {} {} (computed)",
+                 computed.hostname_port,
+                 computed.metric_type,
+                 computed.metric_id,
+                 computed.table_info,
+                 computed.name,
+                 utility::format_float(computed.value, 15, 3, config),
+                 computed.unit,
+        )?;
+    }
+    else
+    {
+        writeln!(rows, "{:20} {:8} {:70} {} {} (computed)",
+                 computed.hostname_port,
+                 computed.metric_type,
+                 computed.name,
+                 utility::format_float(computed.value, 15, 3, config),
+                 computed.unit,
+        )?;
+    }
+    Ok(())
+}
+
+/// The metric values by name, with the table name and namespace, per hostname_port, metric type and id.
+type ComputeEntities<'a> = BTreeMap<(&'a String, &'a String, &'a String), (&'a String, &'a String, BTreeMap<&'a str, f64>)>;
+
+/// Evaluate the `--compute` expressions for every hostname_port, metric type and id, using the values
+/// of the value statistics, which are the deltas for a diff.
+///
+/// A metric is only computed for a metric entity that has all the metrics of the expression, and is not computed on a division by zero.
+fn compute_metrics<'a>(
+    values: impl Iterator<Item = (&'a (String, String, String, String), &'a String, &'a String, f64)>,
+    computes: &[MetricCompute],
+) -> Vec<ComputedMetric>
+{
+    if computes.is_empty() { return Vec::new() };
+    let mut entities: ComputeEntities = BTreeMap::new();
+    for ((hostname_port, metric_type, metric_id, metric_name), table_name, namespace, value) in values
+    {
+        entities.entry((hostname_port, metric_type, metric_id))
+            .or_insert((table_name, namespace, BTreeMap::new()))
+            .2
+            .insert(metric_name.as_str(), value);
+    }
+
+    let mut computed_metrics = Vec::new();
+    for ((hostname_port, metric_type, metric_id), (table_name, namespace, values)) in entities
+    {
+        for compute in computes
+        {
+            let Some(value) = compute.expression.evaluate(&values) else { continue };
+            computed_metrics.push(ComputedMetric {
+                hostname_port: hostname_port.clone(),
+                metric_type: metric_type.clone(),
+                metric_id: metric_id.clone(),
+                table_name: table_name.clone(),
+                table_info: table_info(namespace, table_name),
+                name: compute.name.clone(),
+                unit: "",
+                value,
+            });
+        }
+    }
+    computed_metrics
+}
+
+/// Parse the `--compute` definitions, see [MetricCompute::parse].
+pub fn parse_computes(
+    definitions: &[String],
+) -> Result<Vec<MetricCompute>>
+{
+    definitions
+        .iter()
+        .map(|definition| MetricCompute::parse(definition))
+        .collect()
+}
+
+impl MetricCompute {
+    /// Parse a compute definition: `name=<name> expr=<expression>`, such as `name=hit_rate expr=rocksdb_block_cache_hit/(rocksdb_block_cache_hit+rocksdb_block_cache_miss)`.
+    pub fn parse(
+        definition: &str,
+    ) -> Result<MetricCompute>
+    {
+        let regex = Regex::new(r"^\s*name=(?P<name>\w+)\s+expr=(?P<expression>.+?)\s*$")?;
+        let captures = regex.captures(definition)
+            .with_context(|| format!("Invalid compute: '{}', expected: 'name=<name> expr=<expression>', such as 'name=hit_rate expr=rocksdb_block_cache_hit/(rocksdb_block_cache_hit+rocksdb_block_cache_miss)'", definition))?;
+        Ok(MetricCompute {
+            name: captures["name"].to_string(),
+            expression: MetricExpression::parse(&captures["expression"])
+                .with_context(|| format!("Invalid compute expression: '{}'", &captures["expression"]))?,
+        })
+    }
+}
+
+/// A token of a `--compute` expression.
+#[derive(Debug, PartialEq)]
+enum ExpressionToken {
+    Metric(String),
+    Number(f64),
+    /// An operator or a parenthesis.
+    Symbol(char),
+}
+
+type ExpressionTokens = std::iter::Peekable<std::vec::IntoIter<ExpressionToken>>;
+
+impl MetricExpression {
+    /// Parse an arithmetic expression of metric names, numbers, parentheses and the operators +, -, * and /.
+    /// Multiplication and division take precedence over addition and subtraction, operators of equal precedence are evaluated left to right.
+    pub fn parse(
+        expression: &str,
+    ) -> Result<MetricExpression>
+    {
+        let regex = Regex::new(r"\s*(?:(?P<number>[0-9]+(?:\.[0-9]+)?)|(?P<metric>[A-Za-z_]\w*)|(?P<symbol>[-+*/()])|(?P<invalid>\S))")?;
+        let mut tokens = Vec::new();
+        for captures in regex.captures_iter(expression)
+        {
+            if let Some(number) = captures.name("number") {
+                tokens.push(ExpressionToken::Number(number.as_str().parse()?));
+            } else if let Some(metric) = captures.name("metric") {
+                tokens.push(ExpressionToken::Metric(metric.as_str().to_string()));
+            } else if let Some(symbol) = captures.name("symbol") {
+                tokens.push(ExpressionToken::Symbol(symbol.as_str().chars().next().unwrap_or_default()));
+            } else if let Some(invalid) = captures.name("invalid") {
+                bail!("unexpected character: '{}'", invalid.as_str());
+            }
+        }
+        let mut tokens = tokens.into_iter().peekable();
+        let parsed = MetricExpression::parse_sum(&mut tokens)?;
+        if let Some(token) = tokens.next() { bail!("unexpected {:?}", token) };
+        Ok(parsed)
+    }
+    /// sum := product (('+' | '-') product)*
+    fn parse_sum(
+        tokens: &mut ExpressionTokens,
+    ) -> Result<MetricExpression>
+    {
+        let mut left = MetricExpression::parse_product(tokens)?;
+        while let Some(ExpressionToken::Symbol(operator @ ('+' | '-'))) = tokens.peek()
+        {
+            let operator = *operator;
+            tokens.next();
+            let right = MetricExpression::parse_product(tokens)?;
+            left = MetricExpression::Operation(Box::new(left), operator, Box::new(right));
+        }
+        Ok(left)
+    }
+    /// product := operand (('*' | '/') operand)*
+    fn parse_product(
+        tokens: &mut ExpressionTokens,
+    ) -> Result<MetricExpression>
+    {
+        let mut left = MetricExpression::parse_operand(tokens)?;
+        while let Some(ExpressionToken::Symbol(operator @ ('*' | '/'))) = tokens.peek()
+        {
+            let operator = *operator;
+            tokens.next();
+            let right = MetricExpression::parse_operand(tokens)?;
+            left = MetricExpression::Operation(Box::new(left), operator, Box::new(right));
+        }
+        Ok(left)
+    }
+    /// operand := metric | number | '(' sum ')'
+    fn parse_operand(
+        tokens: &mut ExpressionTokens,
+    ) -> Result<MetricExpression>
+    {
+        match tokens.next() {
+            Some(ExpressionToken::Metric(metric)) => Ok(MetricExpression::Metric(metric)),
+            Some(ExpressionToken::Number(number)) => Ok(MetricExpression::Number(number)),
+            Some(ExpressionToken::Symbol('(')) => {
+                let parsed = MetricExpression::parse_sum(tokens)?;
+                match tokens.next() {
+                    Some(ExpressionToken::Symbol(')')) => Ok(parsed),
+                    Some(token) => bail!("expected ')', found {:?}", token),
+                    None => bail!("expected ')', found the end of the expression"),
+                }
+            },
+            Some(token) => bail!("expected a metric name or number, found {:?}", token),
+            None => bail!("expected a metric name or number, found the end of the expression"),
+        }
+    }
+    /// Evaluate the expression with the metric values by name.
+    /// This returns None if a metric is not found, or on a division by zero.
+    pub fn evaluate(
+        &self,
+        values: &BTreeMap<&str, f64>,
+    ) -> Option<f64>
+    {
+        match self {
+            MetricExpression::Metric(metric) => values.get(metric.as_str()).copied(),
+            MetricExpression::Number(number) => Some(*number),
+            MetricExpression::Operation(left, operator, right) => {
+                let left = left.evaluate(values)?;
+                let right = right.evaluate(values)?;
+                match operator {
+                    '+' => Some(left + right),
+                    '-' => Some(left - right),
+                    '*' => Some(left * right),
+                    _ => if right == 0_f64 { None } else { Some(left / right) },
+                }
+            },
+        }
+    }
+}
+
+/// The metric type of a value statistic, based on the stat_type in the value statistics lookup table.
+/// Unknown statistics are considered counters, identical to how the metrics diff shows them.
+/// Countsum statistics are histograms, countsumrows statistics are counters.
+fn value_metric_type(
+    stat_type: &str,
+) -> &'static str
+{
+    if stat_type == "gauge" { "gauge" } else { "counter" }
+}
+
+/// Return if the metric type is selected with `--metric-type`; if no metric type is selected, all are.
+fn metric_type_selected(
+    metric_types: &[String],
+    metric_type: &str,
+) -> bool
+{
+    metric_types.is_empty() || metric_types.iter().any(|selected| selected == metric_type)
+}
+
+/// Return if the entity type of a metric is selected with `--granularity` (server, table or tablet); if no granularity is selected, all are.
+/// With a granularity, the other entity types, such as cluster and cdc, are left out.
+fn granularity_selected(
+    granularity: &Option<String>,
+    metrics_type: &str,
+) -> bool
+{
+    granularity.as_ref().is_none_or(|granularity| granularity == metrics_type)
+}
+
+impl MetricPercentiles {
+    /// Merge the percentiles of another entity, taking the highest value of each percentile.
+    pub fn merge(
+        &mut self,
+        other: &MetricPercentiles,
+    )
+    {
+        self.percentile_75 = self.percentile_75.max(other.percentile_75);
+        self.percentile_95 = self.percentile_95.max(other.percentile_95);
+        self.percentile_99 = self.percentile_99.max(other.percentile_99);
+        self.max = self.max.max(other.max);
+    }
+}
+
+/// Format the percentiles of a histogram.
+fn format_percentiles(
+    percentiles: &MetricPercentiles,
+) -> String
+{
+    format!("p75: {} p95: {} p99: {} max: {}", percentiles.percentile_75, percentiles.percentile_95, percentiles.percentile_99, percentiles.max)
+}
+
+/// Format the change of the percentiles of a histogram between two snapshots, unchanged percentiles are shown once.
+fn format_percentiles_change(
+    first: &MetricPercentiles,
+    second: &MetricPercentiles,
+) -> String
+{
+    let change = |first: u64, second: u64| -> String {
+        if first == second { second.to_string() } else { format!("{}->{}", first, second) }
+    };
+    format!("p75: {} p95: {} p99: {} max: {}",
+        change(first.percentile_75, second.percentile_75),
+        change(first.percentile_95, second.percentile_95),
+        change(first.percentile_99, second.percentile_99),
+        change(first.max, second.max),
+    )
+}
+
+/// Create the table information from the namespace and table name, as printed with `--details-enable`.
+fn table_info(
+    namespace: &String,
+    table_name: &String,
+) -> String
+{
+    if namespace.is_empty() && table_name.is_empty()
+    {
+        "".to_string()
+    }
+    else if namespace.is_empty()
+    {
+        table_name.clone()
+    }
+    else
+    {
+        format!("{}.{}", namespace, table_name)
+    }
+}
+
+pub async fn print_metrics(
+    hosts: Vec<&str>,
+    ports: Vec<&str>,
+    parallel: usize,
+    options: &Opts,
+    config: &Config,
+) -> Result<()>
+{
+    if options.top_movers {
+        if options.print_metrics.as_ref().unwrap().is_some() {
+            bail!("--top-movers compares a snapshot with the current metrics, and cannot be used with a --print-metrics snapshot number");
+        }
+        return print_metrics_top_movers(hosts, ports, parallel, options, config).await;
+    }
+    let hostname_filter = utility::set_regex(&options.hostname_match);
+    let stat_name_filter = utility::set_regex(&options.stat_name_match);
+    let table_name_filter = utility::set_regex(&options.table_name_match);
+    let computes = parse_computes(&options.compute)?;
+
+    let mut allmetricentity = AllMetricEntity::new();
+    match options.print_metrics.as_ref().unwrap() {
+        Some(snapshot_number) => {
+            allmetricentity.metricentity = snapshot::read_snapshot_json(snapshot_number, "metrics", config)?;
+        },
+        None => {
+            allmetricentity = AllMetricEntity::read_metrics(&hosts, &ports, parallel, config).await;
+        },
+    }
+    allmetricentity.metricentity.retain(|metricentity| granularity_selected(&options.granularity, &metricentity.metrics_type));
+    let mut metricentityprint = MetricEntityPrint::new();
+    metricentityprint.add_metrics(allmetricentity, &options.details_enable);
+
+    if !options.assert.is_empty() {
+        let mut failed = Vec::new();
+        for expression in &options.assert
+        {
+            let assertion = MetricAssertion::parse(expression)?;
+            if !metricentityprint.assert(&assertion, &hostname_filter, config)? {
+                failed.push(expression.as_str());
+            }
+        }
+        if !failed.is_empty() {
+            bail!("Assertion failed: {}", failed.join(", "));
+        }
+        return Ok(());
+    }
+    match &options.baseline {
+        Some(baseline_file) => {
+            let allmetricbaseline = AllMetricBaseline::read_baseline_file(baseline_file)?;
+            metricentityprint.print_baseline_deviations(&allmetricbaseline, &hostname_filter, &stat_name_filter, &table_name_filter, config)?;
+        },
+        None => {
+            metricentityprint.print(&hostname_filter, &stat_name_filter, &table_name_filter, &options.details_enable, &options.metric_type, &computes, config)?;
+        },
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::metrics::Metrics::{RejectedBooleanMetricValue, RejectedU64MetricValue};
+    use super::*;
+
+    #[test]
+    /// cdcsdk (change data capture software development kit) metrics value
+    /// Please mind type cdc has an extra, unique, attribute: stream_id. This is currently not parsed.
+    fn unit_parse_metrics_cdcsdk_value() {
+        let json = r#"
+[
+    {
+        "type": "cdcsdk",
+        "id": ":face4edb05934e77b564857878cf5015:4457a26b28a64393ac626504aba5f571",
+        "attributes": {
+            "stream_id": "face4edb05934e77b564857878cf5015",
+            "table_name": "table0",
+            "namespace_name": "test",
+            "table_id": "c70ffbbe28f14e84b0559c405ae20197"
+        },
+        "metrics": [
+            {
+                "name": "async_replication_sent_lag_micros",
+                "value": 0
+            }
+        ]
+    }
+]"#.to_string();
+        let result = AllMetricEntity::parse_metrics(json, "", "");
+        assert_eq!(result[0].metrics_type,"cdcsdk");
+        if let MetricValue { name, value } = &result[0].metrics[0]
+        {
+            assert_eq!(name.clone(), "async_replication_sent_lag_micros");
+            assert_eq!(value, &0);
+        };
+    }
+
+    #[test]
+    /// cdc (change data capture) metrics value
+    /// Please mind type cdc has an extra, unique, attribute: stream_id.
+    fn unit_parse_metrics_cdc_value() {
+        let json = r#"
+[
+    {
+        "type": "cdc",
+        "id": ":face4edb05934e77b564857878cf5015:4457a26b28a64393ac626504aba5f571",
+        "attributes": {
+            "stream_id": "face4edb05934e77b564857878cf5015",
+            "table_name": "table0",
+            "namespace_name": "test",
+            "table_id": "c70ffbbe28f14e84b0559c405ae20197"
+        },
+        "metrics": [
+            {
+                "name": "async_replication_sent_lag_micros",
+                "value": 0
+            }
+        ]
+    }
+]"#.to_string();
+        let result = AllMetricEntity::parse_metrics(json, "", "");
+        assert_eq!(result[0].metrics_type,"cdc");
+        if let MetricValue { name, value} = &result[0].metrics[0]
+                {
+                    assert_eq!(name.clone(), "async_replication_sent_lag_micros");
+                    assert_eq!(value, &0);
+                };
+    }
+
+        #[test]
+        /// cdc (change data capture) metrics value
+        /// Please mind type cdc has an extra, unique, attribute: stream_id.
+        fn unit_parse_metrics_cdc_countsum() {
+            let json = r#"
+    [
+        {
+            "type": "cdc",
+            "id": ":face4edb05934e77b564857878cf5015:4457a26b28a64393ac626504aba5f571",
+            "attributes": {
+                "stream_id": "face4edb05934e77b564857878cf5015",
                 "table_name": "table0",
                 "namespace_name": "test",
                 "table_id": "c70ffbbe28f14e84b0559c405ae20197"
@@ -1102,42 +3090,412 @@ mod tests {
         #[tokio::test]
         async fn integration_parse_metrics_master()
         {
+            let config = &Config::default();
             let hostname = utility::get_hostname_master();
             let port = utility::get_port_master();
-            let allmetricentity = AllMetricEntity::read_metrics(&vec![&hostname], &vec![&port], 1).await;
+            let allmetricentity = AllMetricEntity::read_metrics(&vec![&hostname], &vec![&port], 1, config).await;
             // a master will produce metrics.
             assert!(!allmetricentity.metricentity.is_empty());
         }
         #[tokio::test]
         async fn integration_parse_metrics_tserver() {
+            let config = &Config::default();
             let hostname = utility::get_hostname_tserver();
             let port = utility::get_port_tserver();
-            let allmetricentity = AllMetricEntity::read_metrics(&vec![&hostname], &vec![&port], 1).await;
+            let allmetricentity = AllMetricEntity::read_metrics(&vec![&hostname], &vec![&port], 1, config).await;
             // a tablet server will produce metrics.
             assert!(!allmetricentity.metricentity.is_empty());
         }
         #[tokio::test]
         async fn integration_parse_metrics_ysql() {
+            let config = &Config::default();
             let hostname = utility::get_hostname_ysql();
             let port = utility::get_port_ysql();
-            let allmetricentity = AllMetricEntity::read_metrics(&vec![&hostname], &vec![&port], 1).await;
+            let allmetricentity = AllMetricEntity::read_metrics(&vec![&hostname], &vec![&port], 1, config).await;
             // YSQL will produce metrics.
             assert!(!allmetricentity.metricentity.is_empty());
         }
         #[tokio::test]
         async fn integration_parse_metrics_ycql() {
+            let config = &Config::default();
             let hostname = utility::get_hostname_ycql();
             let port = utility::get_port_ycql();
-            let allmetricentity = AllMetricEntity::read_metrics(&vec![&hostname], &vec![&port], 1).await;
+            let allmetricentity = AllMetricEntity::read_metrics(&vec![&hostname], &vec![&port], 1, config).await;
             // YCQL will produce metrics.
             assert!(!allmetricentity.metricentity.is_empty());
         }
         #[tokio::test]
         async fn integration_parse_metrics_yedis() {
+            let config = &Config::default();
             let hostname = utility::get_hostname_yedis();
             let port = utility::get_port_yedis();
-            let allmetricentity = AllMetricEntity::read_metrics(&vec![&hostname], &vec![&port], 1).await;
+            let allmetricentity = AllMetricEntity::read_metrics(&vec![&hostname], &vec![&port], 1, config).await;
             // YEDIS will produce metrics.
             assert!(!allmetricentity.metricentity.is_empty());
         }
+        #[test]
+        fn unit_parse_metrics_baseline() {
+            let baseline = r#"metric,min,max
+# the number of tablet servers should be constant
+num_tablet_servers_live,3,3
+rpc_connections_alive, 0, 500
+"#;
+            let result = AllMetricBaseline::parse_baseline(baseline.as_bytes()).unwrap();
+            assert_eq!(result.metricbaseline.len(), 2);
+            assert!(result.deviation("num_tablet_servers_live", 3_f64).is_none());
+            assert!(result.deviation("num_tablet_servers_live", 2_f64).is_some());
+            assert!(result.deviation("rpc_connections_alive", 501_f64).is_some());
+            assert!(result.deviation("not_in_baseline", 1000_f64).is_none());
+        }
+        #[test]
+        fn unit_parse_metrics_assertion() {
+            let assertion = MetricAssertion::parse("metric=rpc_inbound_calls_created_total delta<1000").unwrap();
+            assert_eq!(assertion.metric, "rpc_inbound_calls_created_total");
+            assert_eq!(assertion.field, Some(MetricAssertionField::Delta));
+            assert_eq!(assertion.operator, "<");
+            assert!(assertion.check(999_f64));
+            assert!(!assertion.check(1000_f64));
+
+            let assertion = MetricAssertion::parse("threads_running >= 10").unwrap();
+            assert_eq!(assertion.field, None);
+            assert!(assertion.check(10_f64));
+
+            assert!(MetricAssertion::parse("threads_running ~ 10").is_err());
+        }
+        #[test]
+        fn unit_percent_change() {
+            assert_eq!(percent_change(200_f64, 300_f64), Some(50_f64));
+            assert_eq!(percent_change(0_f64, 300_f64), None);
+            assert_eq!(format_percent_change(Some(-25_f64)), "-25.0%");
+            assert_eq!(format_percent_change(None), "new");
+        }
+        #[test]
+        fn unit_metrics_diff_metric_renames() {
+            let first = r#"[{"hostname_port":"yb-1:9000","timestamp":null,"type":"server","id":"yb.tabletserver","attributes":null,"metrics":[{"name":"renamed_metric_old","value":1},{"name":"both_metric_old","value":1},{"name":"both_metric_new","value":1}]}]"#;
+            let second = r#"[{"hostname_port":"yb-1:9000","timestamp":null,"type":"server","id":"yb.tabletserver","attributes":null,"metrics":[{"name":"renamed_metric_new","value":5},{"name":"both_metric_new","value":2}]}]"#;
+            let metric_renames = parse_metric_renames("# comment\nold_name,new_name\nrenamed_metric_old,renamed_metric_new\nboth_metric_old,both_metric_new\nabsent_metric_old,absent_metric_new\n".as_bytes()).unwrap();
+            assert_eq!(metric_renames.len(), 3);
+            let mut first_allmetricentity = AllMetricEntity { metricentity: serde_json::from_str(first).unwrap() };
+            let mut second_allmetricentity = AllMetricEntity { metricentity: serde_json::from_str(second).unwrap() };
+            let applied = apply_metric_renames(&metric_renames, &mut first_allmetricentity, &mut second_allmetricentity);
+            assert_eq!(applied, vec![MetricRename { old_name: "renamed_metric_old".to_string(), new_name: "renamed_metric_new".to_string() }]);
+            assert!(metric_names(&first_allmetricentity).contains("renamed_metric_new"));
+            assert!(metric_names(&first_allmetricentity).contains("both_metric_old"));
+            // the rename also works from a newer to an older version.
+            let applied = apply_metric_renames(&metric_renames, &mut second_allmetricentity, &mut AllMetricEntity { metricentity: serde_json::from_str(first).unwrap() });
+            assert_eq!(applied.len(), 1);
+        }
+        #[test]
+        fn unit_metrics_builtin_metric_renames() {
+            let rename = |old_name: &str, new_name: &str| MetricRename { old_name: old_name.to_string(), new_name: new_name.to_string() };
+            let builtin_renames = [("renamed_metric_old", "renamed_metric_new"), ("corrected_metric_old", "wrong_metric_new"), ("disabled_metric_old", "disabled_metric_new")];
+            // without a rename file, the built-in renames are used, and a built-in rename is applied like a rename of the file.
+            let metric_renames = merge_metric_renames(&builtin_renames, Vec::new());
+            assert_eq!(metric_renames.len(), 3);
+            let first = r#"[{"hostname_port":"yb-1:9000","timestamp":null,"type":"server","id":"yb.tabletserver","attributes":null,"metrics":[{"name":"renamed_metric_old","value":1}]}]"#;
+            let second = r#"[{"hostname_port":"yb-1:9000","timestamp":null,"type":"server","id":"yb.tabletserver","attributes":null,"metrics":[{"name":"renamed_metric_new","value":5}]}]"#;
+            let mut metricentitydiff = MetricEntityDiff::new();
+            metricentitydiff.renamed_metrics = apply_metric_renames(&metric_renames, &mut AllMetricEntity { metricentity: serde_json::from_str(first).unwrap() }, &mut AllMetricEntity { metricentity: serde_json::from_str(second).unwrap() });
+            assert_eq!(metricentitydiff.renamed_metrics, vec![rename("renamed_metric_old", "renamed_metric_new")]);
+            assert_eq!(metricentitydiff.summary_line(1, 0), "metrics diff: 1 changed, 0 suppressed, renamed: renamed_metric_old -> renamed_metric_new");
+            // the rename file corrects a built-in rename, disables one with a rename to itself, and adds one.
+            let metric_renames = merge_metric_renames(&builtin_renames, vec![rename("corrected_metric_old", "corrected_metric_new"), rename("disabled_metric_old", "disabled_metric_old"), rename("file_metric_old", "file_metric_new")]);
+            assert_eq!(metric_renames, vec![
+                rename("corrected_metric_old", "corrected_metric_new"),
+                rename("file_metric_old", "file_metric_new"),
+                rename("renamed_metric_old", "renamed_metric_new"),
+            ]);
+        }
+
+        #[test]
+        fn unit_merge_baseline_metrics() {
+            let snapshot = |timestamp: &str, counter: i64, threads_running: Option<i64>, total_count: u64| {
+                let threads_running = threads_running.map(|value| format!(r#",{{"name":"threads_running","value":{}}}"#, value)).unwrap_or_default();
+                let metrics = format!(r#"[{{"hostname_port":"yb-1:9000","timestamp":"{}","type":"server","id":"yb.tabletserver","attributes":null,"metrics":[{{"name":"rpc_inbound_calls_created","value":{}}}{},{{"name":"handler_latency","total_count":{},"min":0,"mean":0.0,"percentile_75":0,"percentile_95":0,"percentile_99":0,"percentile_99_9":0,"percentile_99_99":0,"max":0,"total_sum":0}}]}}]"#, timestamp, counter, threads_running, total_count);
+                serde_json::from_str::<Vec<MetricEntity>>(&metrics).unwrap()
+            };
+            let baseline = merge_baseline_metrics(vec![
+                snapshot("2023-01-01T10:00:00+00:00", 100, Some(10), 5),
+                snapshot("2023-01-01T10:01:00+00:00", 200, Some(21), 15),
+                snapshot("2023-01-01T10:02:00+00:00", 300, None, 25),
+            ]);
+            assert_eq!(baseline.len(), 1);
+            assert_eq!(baseline[0].timestamp, snapshot("2023-01-01T10:00:00+00:00", 0, None, 0)[0].timestamp);
+            let values: Vec<(String, i64)> = baseline[0].metrics.iter().filter_map(|metric| match metric { MetricValue { name, value } => Some((name.clone(), *value)), _ => None }).collect();
+            // the counter is of the first snapshot, the gauge is the average of the snapshots that have it.
+            assert_eq!(values, vec![("rpc_inbound_calls_created".to_string(), 100), ("threads_running".to_string(), 16)]);
+            assert!(matches!(baseline[0].metrics[2], MetricCountSum { total_count: 5, .. }));
+        }
+
+        #[test]
+        fn unit_metrics_top_movers() {
+            let first = r#"[{"hostname_port":"yb-1:9000","timestamp":"2023-01-01T10:00:00+00:00","type":"server","id":"yb.tabletserver","attributes":null,"metrics":[{"name":"small_counter","value":10},{"name":"big_counter","value":100},{"name":"active_full_compaction_input_bytes_added","value":0}]},
+                            {"hostname_port":"yb-2:9000","timestamp":"2023-01-01T10:00:00+00:00","type":"server","id":"yb.tabletserver","attributes":null,"metrics":[{"name":"small_counter","value":10},{"name":"big_counter","value":100}]}]"#;
+            let second = r#"[{"hostname_port":"yb-1:9000","timestamp":"2023-01-01T10:00:10+00:00","type":"server","id":"yb.tabletserver","attributes":null,"metrics":[{"name":"small_counter","value":20},{"name":"big_counter","value":150},{"name":"active_full_compaction_input_bytes_added","value":1000}]},
+                             {"hostname_port":"yb-2:9000","timestamp":"2023-01-01T10:00:10+00:00","type":"server","id":"yb.tabletserver","attributes":null,"metrics":[{"name":"small_counter","value":20},{"name":"big_counter","value":150},{"name":"unchanged_counter","value":0}]}]"#;
+            let mut metricentitydiff = MetricEntityDiff::new();
+            let first: AllMetricEntity = AllMetricEntity { metricentity: serde_json::from_str(first).unwrap() };
+            let first_snapshot_time = first.metricentity[0].timestamp.unwrap();
+            metricentitydiff.first_snapshot(first, &false, &false);
+            metricentitydiff.second_snapshot(AllMetricEntity { metricentity: serde_json::from_str(second).unwrap() }, &false, &false, &first_snapshot_time);
+            let all = utility::set_regex(&None);
+            let top_movers = metricentitydiff.top_movers(&all, &all, &false, 10);
+            // the deltas are summed over the hosts, and the gauge is left out.
+            assert_eq!(top_movers, vec![
+                ("server".to_string(), "big_counter".to_string(), 100, 10_f64),
+                ("server".to_string(), "small_counter".to_string(), 20, 2_f64),
+            ]);
+            assert_eq!(metricentitydiff.top_movers(&all, &all, &true, 1)[0].1, "active_full_compaction_input_bytes_added");
+            assert_eq!(metricentitydiff.top_movers(&utility::set_regex(&Some("yb-1".to_string())), &all, &false, 10)[0].2, 50);
+        }
+
+        #[test]
+        fn unit_metrics_error_increases() {
+            let config = &Config::default();
+            let first = r#"[{"hostname_port":"yb-1:9000","timestamp":"2023-01-01T10:00:00+00:00","type":"server","id":"yb.tabletserver","attributes":null,"metrics":[{"name":"rpcs_timed_out_in_queue","value":5},{"name":"log_failed_appends","value":2},{"name":"rows_inserted","value":10}]},
+                            {"hostname_port":"yb-2:9000","timestamp":"2023-01-01T10:00:00+00:00","type":"server","id":"yb.tabletserver","attributes":null,"metrics":[{"name":"rpcs_timed_out_in_queue","value":5}]}]"#;
+            let second = r#"[{"hostname_port":"yb-1:9000","timestamp":"2023-01-01T10:00:10+00:00","type":"server","id":"yb.tabletserver","attributes":null,"metrics":[{"name":"rpcs_timed_out_in_queue","value":25},{"name":"log_failed_appends","value":2},{"name":"rows_inserted","value":20}]},
+                             {"hostname_port":"yb-2:9000","timestamp":"2023-01-01T10:00:10+00:00","type":"server","id":"yb.tabletserver","attributes":null,"metrics":[{"name":"rpcs_timed_out_in_queue","value":1}]}]"#;
+            let mut metricentitydiff = MetricEntityDiff::new();
+            let first: AllMetricEntity = AllMetricEntity { metricentity: serde_json::from_str(first).unwrap() };
+            let first_snapshot_time = first.metricentity[0].timestamp.unwrap();
+            metricentitydiff.first_snapshot(first, &false, &false);
+            metricentitydiff.second_snapshot(AllMetricEntity { metricentity: serde_json::from_str(second).unwrap() }, &false, &false, &first_snapshot_time);
+            let all = utility::set_regex(&None);
+            // the unchanged error counter, the decreased error counter and the counter that is not an error are left out.
+            let rows = metricentitydiff.error_increases(&all, &all, &Regex::new(ERROR_METRIC_PATTERN).unwrap(), config);
+            assert_eq!(rows.len(), 1);
+            assert_eq!(rows[0][0], "yb-1:9000");
+            assert_eq!(rows[0][4], "rpcs_timed_out_in_queue");
+            assert_eq!(rows[0][5], "20");
+            assert_eq!(rows[0][6], "2.000");
+            // the error pattern can be overridden.
+            let rows = metricentitydiff.error_increases(&all, &all, &Regex::new("inserted").unwrap(), config);
+            assert_eq!(rows[0][4], "rows_inserted");
+        }
+
+        #[test]
+        fn unit_metrics_summary() {
+            let first = r#"[{"hostname_port":"yb-1:9000","timestamp":null,"type":"server","id":"yb.tabletserver","attributes":null,"metrics":[{"name":"test_changed_counter","value":1},{"name":"test_unchanged_counter","value":1},{"name":"active_full_compaction_input_bytes_added","value":1}]},
+                            {"hostname_port":"yb-2:9000","timestamp":null,"type":"server","id":"yb.tabletserver","attributes":null,"metrics":[{"name":"test_changed_counter","value":1}]}]"#;
+            let second = r#"[{"hostname_port":"yb-1:9000","timestamp":null,"type":"server","id":"yb.tabletserver","attributes":null,"metrics":[{"name":"test_changed_counter","value":2},{"name":"test_unchanged_counter","value":1},{"name":"active_full_compaction_input_bytes_added","value":2}]},
+                             {"hostname_port":"yb-2:9000","timestamp":null,"type":"server","id":"yb.tabletserver","attributes":null,"metrics":[{"name":"test_changed_counter","value":3}]}]"#;
+            let mut metricentitydiff = MetricEntityDiff::new();
+            metricentitydiff.first_snapshot(AllMetricEntity { metricentity: serde_json::from_str(first).unwrap() }, &false, &false);
+            metricentitydiff.second_snapshot(AllMetricEntity { metricentity: serde_json::from_str(second).unwrap() }, &false, &false, &Local::now());
+            let all = utility::set_regex(&None);
+            let config = &Config::default();
+            let summary = |hostname_filter: &Regex, gauges_enable: bool, metric_types: &[String], diff_context: Option<String>, threshold: MetricDiffThreshold|
+                metricentitydiff.summary(hostname_filter, &all, &all, &false, &gauges_enable, metric_types, &diff_context, &threshold, config);
+            // the unchanged counter and the gauge are not counted.
+            let no_threshold = MetricDiffThreshold::default;
+            assert_eq!(summary(&all, false, &[], None, no_threshold()), (2, 0));
+            assert_eq!(summary(&utility::set_regex(&Some("yb-2".to_string())), false, &[], None, no_threshold()), (1, 0));
+            assert_eq!(summary(&all, false, &["histogram".to_string()], None, no_threshold()), (0, 0));
+            // the gauge is counted when the diff shows the gauges, the context line of the unchanged counter is not.
+            assert_eq!(summary(&all, true, &[], None, no_threshold()), (3, 0));
+            assert_eq!(summary(&all, false, &[], Some("prefix".to_string()), no_threshold()), (2, 0));
+            assert_eq!(metricentitydiff.lines(&all, &all, &all, &false, &false, &false, &[], &Some("prefix".to_string()), &None, &no_threshold(), config).0.len(), 3);
+            // the changed counter changed by 1 (100%) on yb-1, and by 2 (200%) on yb-2.
+            assert_eq!(summary(&all, false, &[], None, MetricDiffThreshold { delta: Some(2_f64), pct: None }), (1, 1));
+            assert_eq!(summary(&all, false, &[], None, MetricDiffThreshold { delta: None, pct: Some(150_f64) }), (1, 1));
+            assert_eq!(summary(&all, false, &[], None, MetricDiffThreshold { delta: Some(3_f64), pct: None }), (0, 2));
+        }
+
+        #[test]
+        fn unit_metrics_schema_diff() {
+            let first = r#"[{"hostname_port":"yb-1:9000","timestamp":null,"type":"server","id":"yb.tabletserver","attributes":null,"metrics":[{"name":"renamed_metric_old","value":1},{"name":"unchanged_metric","value":1}]},
+                            {"hostname_port":"yb-2:9000","timestamp":null,"type":"server","id":"yb.tabletserver","attributes":null,"metrics":[{"name":"unchanged_metric","value":1}]}]"#;
+            let second = r#"[{"hostname_port":"yb-1:9000","timestamp":null,"type":"server","id":"yb.tabletserver","attributes":null,"metrics":[{"name":"renamed_metric_new","value":2},{"name":"unchanged_metric","value":5}]}]"#;
+            let mut metricschemadiff = MetricSchemaDiff::new();
+            metricschemadiff.add_snapshot(AllMetricEntity { metricentity: serde_json::from_str(first).unwrap() }, true);
+            metricschemadiff.add_snapshot(AllMetricEntity { metricentity: serde_json::from_str(second).unwrap() }, false);
+            let (appeared, disappeared) = metricschemadiff.changes(&utility::set_regex(&None));
+            // yb-2:9000 is not in the second snapshot, and therefore its metrics are not reported as disappeared.
+            assert_eq!(appeared, vec![("renamed_metric_new".to_string(), vec!["yb-1:9000".to_string()])]);
+            assert_eq!(disappeared, vec![("renamed_metric_old".to_string(), vec!["yb-1:9000".to_string()])]);
+        }
+        #[test]
+        fn unit_metric_type_selected() {
+            assert_eq!(value_metric_type("gauge"), "gauge");
+            assert_eq!(value_metric_type("?"), "counter");
+            assert!(metric_type_selected(&[], "histogram"));
+            assert!(metric_type_selected(&["histogram".to_string()], "histogram"));
+            assert!(!metric_type_selected(&["histogram".to_string()], "counter"));
+        }
+        #[test]
+        fn unit_granularity_selected() {
+            assert!(granularity_selected(&None, "cluster"));
+            assert!(granularity_selected(&Some("server".to_string()), "server"));
+            assert!(!granularity_selected(&Some("server".to_string()), "tablet"));
+            assert!(!granularity_selected(&Some("table".to_string()), "cdc"));
+        }
+        #[test]
+        fn unit_metric_percentiles() {
+            let mut percentiles = MetricPercentiles { percentile_75: 10, percentile_95: 20, percentile_99: 30, max: 40 };
+            percentiles.merge(&MetricPercentiles { percentile_75: 15, percentile_95: 15, percentile_99: 35, max: 35 });
+            assert_eq!(percentiles, MetricPercentiles { percentile_75: 15, percentile_95: 20, percentile_99: 35, max: 40 });
+            assert_eq!(format_percentiles(&percentiles), "p75: 15 p95: 20 p99: 35 max: 40");
+            let second = MetricPercentiles { percentile_75: 15, percentile_95: 25, percentile_99: 35, max: 50 };
+            assert_eq!(format_percentiles_change(&percentiles, &second), "p75: 15 p95: 20->25 p99: 35 max: 40->50");
+        }
+        #[test]
+        fn unit_metrics_record_batch() {
+            use arrow::array::Array;
+            let json = r#"[{"hostname_port":"yb-1:9000","timestamp":null,"type":"tablet","id":"16add7b1248a45d2880e5527b2059b54","attributes":{"namespace_name":"yugabyte","table_name":"t","table_id":"000033e8000030008000000000004000"},
+                            "metrics":[{"name":"rows_inserted","value":10},{"name":"ql_read_latency","total_count":5,"min":1,"mean":2.0,"percentile_75":2,"percentile_95":3,"percentile_99":4,"percentile_99_9":4,"percentile_99_99":4,"max":4,"total_sum":10},{"name":"is_raft_leader","value":true}]}]"#;
+            let allmetricentity = AllMetricEntity { metricentity: serde_json::from_str(json).unwrap() };
+            let record_batch = allmetricentity.record_batch().unwrap();
+            // the rejected boolean value is not included.
+            assert_eq!(record_batch.num_rows(), 2);
+            assert_eq!(record_batch.num_columns(), 20);
+            let value = record_batch.column_by_name("value").unwrap().as_any().downcast_ref::<Int64Array>().unwrap();
+            assert_eq!(value.value(0), 10);
+            assert!(value.is_null(1));
+            let percentile_99 = record_batch.column_by_name("percentile_99").unwrap().as_any().downcast_ref::<UInt64Array>().unwrap();
+            assert_eq!(percentile_99.value(1), 4);
+        }
+        #[test]
+        fn unit_metrics_from_record_batches() {
+            let json = r#"[{"hostname_port":"yb-1:9000","timestamp":"2023-01-01T10:00:00.123456+00:00","type":"tablet","id":"tablet_1","attributes":{"namespace_name":"yugabyte","table_name":"t","table_id":"000033e8000030008000000000004000","partition":"hash_split: [0x0000, 0x5554]"},
+                            "metrics":[{"name":"rows_inserted","value":10},{"name":"ql_read_latency","total_count":5,"min":1,"mean":2.5,"percentile_75":2,"percentile_95":3,"percentile_99":4,"percentile_99_9":4,"percentile_99_99":4,"max":4,"total_sum":10}]},
+                           {"hostname_port":"yb-1:9000","timestamp":"2023-01-01T10:00:00.123456+00:00","type":"tablet","id":"tablet_2","attributes":null,"metrics":[{"name":"rows_inserted","value":-1}]},
+                           {"hostname_port":"yb-2:13000","timestamp":null,"type":"server","id":"yb.ysqlserver","attributes":null,"metrics":[{"name":"handler_latency_yb_ysqlserver_SQLProcessor_SelectStmt","count":3,"sum":30,"rows":6}]}]"#;
+            let allmetricentity = AllMetricEntity { metricentity: serde_json::from_str(json).unwrap() };
+            let read_back = AllMetricEntity::from_record_batches(&[allmetricentity.record_batch().unwrap()]).unwrap();
+            assert_eq!(serde_json::to_value(&read_back.metricentity).unwrap(), serde_json::to_value(&allmetricentity.metricentity).unwrap());
+            let attributes = read_back.metricentity[0].attributes.as_ref().unwrap();
+            assert_eq!(attributes.table_name, Some("t".to_string()));
+            assert_eq!(attributes.other.get("partition"), Some(&serde_json::Value::String("hash_split: [0x0000, 0x5554]".to_string())));
+            assert!(AllMetricEntity::from_record_batches(&[]).unwrap().metricentity.is_empty());
+        }
+        #[test]
+        fn unit_metrics_attributes_round_trip() {
+            let json = r#"[{"hostname_port":"yb-1:9000","timestamp":null,"type":"tablet","id":"16add7b1248a45d2880e5527b2059b54","attributes":{"namespace_name":"yugabyte","table_name":"t","table_id":"000033e8000030008000000000004000","table_type":"PGSQL_TABLE_TYPE","partition":"hash_split: [0x0000, 0x5554]"},"metrics":[{"name":"rows_inserted","value":10}]},
+                            {"hostname_port":"yb-1:9000","timestamp":null,"type":"server","id":"yb.tabletserver","metrics":[{"name":"rows_inserted","value":10}]}]"#;
+            let allmetricentity = AllMetricEntity { metricentity: serde_json::from_str(json).unwrap() };
+            // write and read the metrics like a snapshot does.
+            let stored = serde_json::to_string(&allmetricentity.metricentity).unwrap();
+            let allmetricentity = AllMetricEntity { metricentity: serde_json::from_str(&stored).unwrap() };
+            let attributes = allmetricentity.metricentity[0].attributes.as_ref().unwrap();
+            assert_eq!(attributes.table_name, Some("t".to_string()));
+            assert_eq!(attributes.other.len(), 2);
+            assert_eq!(attributes.labels(), "namespace_name=yugabyte,partition=hash_split: [0x0000, 0x5554],table_id=000033e8000030008000000000004000,table_name=t,table_type=PGSQL_TABLE_TYPE");
+            assert!(allmetricentity.metricentity[1].attributes.is_none());
+            let record_batch = allmetricentity.record_batch().unwrap();
+            let labels = record_batch.column_by_name("attributes").unwrap().as_any().downcast_ref::<StringArray>().unwrap();
+            assert_eq!(labels.value(0), attributes.labels());
+            assert!(arrow::array::Array::is_null(labels, 1));
+        }
+        #[test]
+        fn unit_metrics_computed_metrics() {
+            let first = r#"[{"hostname_port":"yb-1:9000","timestamp":"2023-01-01T10:00:00+00:00","type":"tablet","id":"tablet_1","attributes":null,"metrics":[{"name":"rocksdb_bytes_written","value":1000},{"name":"rocksdb_flush_write_bytes","value":1000},{"name":"rocksdb_compact_write_bytes","value":1000},{"name":"rocksdb_compact_read_bytes","value":500}]},
+                            {"hostname_port":"yb-2:9000","timestamp":"2023-01-01T10:00:00+00:00","type":"tablet","id":"tablet_2","attributes":null,"metrics":[{"name":"rocksdb_flush_write_bytes","value":1000}]}]"#;
+            let second = r#"[{"hostname_port":"yb-1:9000","timestamp":"2023-01-01T10:00:10+00:00","type":"tablet","id":"tablet_1","attributes":null,"metrics":[{"name":"rocksdb_bytes_written","value":2000},{"name":"rocksdb_flush_write_bytes","value":2000},{"name":"rocksdb_compact_write_bytes","value":3000},{"name":"rocksdb_compact_read_bytes","value":500}]},
+                             {"hostname_port":"yb-2:9000","timestamp":"2023-01-01T10:00:10+00:00","type":"tablet","id":"tablet_2","attributes":null,"metrics":[{"name":"rocksdb_flush_write_bytes","value":1500}]}]"#;
+            let first: AllMetricEntity = AllMetricEntity { metricentity: serde_json::from_str(first).unwrap() };
+            let first_snapshot_time = first.metricentity[0].timestamp.unwrap();
+            let mut metricentitydiff = MetricEntityDiff::new();
+            metricentitydiff.first_snapshot(first, &true, &false);
+            metricentitydiff.second_snapshot(AllMetricEntity { metricentity: serde_json::from_str(second).unwrap() }, &true, &false, &first_snapshot_time);
+            let computed_metrics = metricentitydiff.computed_metrics();
+            let computed: Vec<(&str, &str, f64)> = computed_metrics
+                .iter()
+                .map(|computed| (computed.hostname_port.as_str(), computed.name.as_str(), computed.value))
+                .collect();
+            // the compaction read bytes did not change, and yb-2 has no user bytes written for the write amplification.
+            assert_eq!(computed, vec![
+                ("yb-1:9000", "computed_compaction_write_throughput", 200_f64),
+                ("yb-1:9000", "computed_flush_write_throughput", 100_f64),
+                ("yb-1:9000", "computed_write_amplification", 3_f64),
+                ("yb-2:9000", "computed_flush_write_throughput", 50_f64),
+            ]);
+        }
+        #[test]
+        fn unit_metrics_compute() {
+            let compute = MetricCompute::parse("name=hit_rate expr=rocksdb_block_cache_hit / (rocksdb_block_cache_hit+rocksdb_block_cache_miss)").unwrap();
+            assert_eq!(compute.name, "hit_rate");
+            let values = BTreeMap::from([("rocksdb_block_cache_hit", 75_f64), ("rocksdb_block_cache_miss", 25_f64)]);
+            assert_eq!(compute.expression.evaluate(&values), Some(0.75));
+            // precedence and left to right evaluation
+            assert_eq!(MetricExpression::parse("2+3*4-10/5/2").unwrap().evaluate(&BTreeMap::new()), Some(13_f64));
+            assert_eq!(MetricExpression::parse("(2+3)*4").unwrap().evaluate(&BTreeMap::new()), Some(20_f64));
+            // a missing metric or a division by zero is not computed
+            assert_eq!(MetricExpression::parse("rocksdb_block_cache_hit/unknown").unwrap().evaluate(&values), None);
+            assert_eq!(MetricExpression::parse("rocksdb_block_cache_hit/(rocksdb_block_cache_miss-25)").unwrap().evaluate(&values), None);
+            for invalid in ["name=x expr=a+", "name=x expr=(a+b", "name=x expr=a b", "name=x expr=a%b", "expr=a/b"]
+            {
+                assert!(MetricCompute::parse(invalid).is_err(), "{}", invalid);
+            }
+
+            let first = r#"[{"hostname_port":"yb-1:9000","timestamp":"2023-01-01T10:00:00+00:00","type":"server","id":"yb.tabletserver","attributes":null,"metrics":[{"name":"rocksdb_block_cache_hit","value":100},{"name":"rocksdb_block_cache_miss","value":100}]},
+                            {"hostname_port":"yb-2:9000","timestamp":"2023-01-01T10:00:00+00:00","type":"server","id":"yb.tabletserver","attributes":null,"metrics":[{"name":"rocksdb_block_cache_hit","value":100}]}]"#;
+            let second = r#"[{"hostname_port":"yb-1:9000","timestamp":"2023-01-01T10:00:10+00:00","type":"server","id":"yb.tabletserver","attributes":null,"metrics":[{"name":"rocksdb_block_cache_hit","value":190},{"name":"rocksdb_block_cache_miss","value":110}]},
+                             {"hostname_port":"yb-2:9000","timestamp":"2023-01-01T10:00:10+00:00","type":"server","id":"yb.tabletserver","attributes":null,"metrics":[{"name":"rocksdb_block_cache_hit","value":200}]}]"#;
+            let first: AllMetricEntity = AllMetricEntity { metricentity: serde_json::from_str(first).unwrap() };
+            let first_snapshot_time = first.metricentity[0].timestamp.unwrap();
+            let mut metricentitydiff = MetricEntityDiff::new();
+            metricentitydiff.first_snapshot(first, &false, &false);
+            metricentitydiff.second_snapshot(AllMetricEntity { metricentity: serde_json::from_str(second).unwrap() }, &false, &false, &first_snapshot_time);
+            let deltas = metricentitydiff.btreemetricdiffvalue
+                .iter()
+                .map(|(key, row)| (key, &row.table_name, &row.namespace, (row.second_value - row.first_value) as f64));
+            let computed: Vec<(String, String, f64)> = compute_metrics(deltas, std::slice::from_ref(&compute))
+                .into_iter()
+                .map(|computed| (computed.hostname_port, computed.name, computed.value))
+                .collect();
+            // yb-2 has no block cache misses, so the hit rate is not computed.
+            assert_eq!(computed, vec![("yb-1:9000".to_string(), "hit_rate".to_string(), 0.9)]);
+
+            let mut metricentityprint = MetricEntityPrint::new();
+            metricentityprint.add_metrics(AllMetricEntity { metricentity: serde_json::from_str(second).unwrap() }, &false);
+            let values = metricentityprint.btreemetricvalue
+                .iter()
+                .map(|(key, row)| (key, &row.table_name, &row.namespace, row.value as f64));
+            let computed = compute_metrics(values, &[compute]);
+            assert_eq!(computed.len(), 1);
+            assert_eq!(computed[0].value, 190_f64 / 300_f64);
+        }
+        #[test]
+        fn unit_metrics_diff_rollup_table() {
+            let first = r#"[{"hostname_port":"yb-1:9000","timestamp":null,"type":"tablet","id":"tablet_a1","attributes":{"namespace_name":"yugabyte","table_name":"a","table_id":"table_a"},"metrics":[{"name":"rows_inserted","value":10}]},
+                            {"hostname_port":"yb-1:9000","timestamp":null,"type":"tablet","id":"tablet_a2","attributes":{"namespace_name":"yugabyte","table_name":"a","table_id":"table_a"},"metrics":[{"name":"rows_inserted","value":20}]},
+                            {"hostname_port":"yb-1:9000","timestamp":null,"type":"tablet","id":"tablet_b1","attributes":{"namespace_name":"yugabyte","table_name":"b","table_id":"table_b"},"metrics":[{"name":"rows_inserted","value":5}]}]"#;
+            let second = r#"[{"hostname_port":"yb-1:9000","timestamp":null,"type":"tablet","id":"tablet_a1","attributes":{"namespace_name":"yugabyte","table_name":"a","table_id":"table_a"},"metrics":[{"name":"rows_inserted","value":15}]},
+                             {"hostname_port":"yb-1:9000","timestamp":null,"type":"tablet","id":"tablet_a2","attributes":{"namespace_name":"yugabyte","table_name":"a","table_id":"table_a"},"metrics":[{"name":"rows_inserted","value":30}]},
+                             {"hostname_port":"yb-1:9000","timestamp":null,"type":"tablet","id":"tablet_b1","attributes":{"namespace_name":"yugabyte","table_name":"b","table_id":"table_b"},"metrics":[{"name":"rows_inserted","value":6}]}]"#;
+            let mut metricentitydiff = MetricEntityDiff::new();
+            metricentitydiff.first_snapshot(AllMetricEntity { metricentity: serde_json::from_str(first).unwrap() }, &true, &true);
+            metricentitydiff.second_snapshot(AllMetricEntity { metricentity: serde_json::from_str(second).unwrap() }, &true, &true, &Local::now());
+            assert_eq!(metricentitydiff.btreemetricdiffvalue.len(), 2);
+            let table_a = &metricentitydiff.btreemetricdiffvalue[&("yb-1:9000".to_string(), "tablet".to_string(), "table_a".to_string(), "rows_inserted".to_string())];
+            assert_eq!((table_a.first_value, table_a.second_value), (30, 45));
+            let table_b = &metricentitydiff.btreemetricdiffvalue[&("yb-1:9000".to_string(), "tablet".to_string(), "table_b".to_string(), "rows_inserted".to_string())];
+            assert_eq!((table_b.first_value, table_b.second_value), (5, 6));
+        }
+        #[test]
+        fn unit_metrics_diff_context_groups() {
+            let first = r#"[{"hostname_port":"yb-1:9000","timestamp":null,"type":"server","id":"yb.tabletserver","attributes":{},"metrics":[{"name":"rocksdb_bytes_read","value":10},{"name":"rocksdb_bytes_written","value":20},{"name":"log_bytes_logged","value":5}]}]"#;
+            let second = r#"[{"hostname_port":"yb-1:9000","timestamp":null,"type":"server","id":"yb.tabletserver","attributes":{},"metrics":[{"name":"rocksdb_bytes_read","value":15},{"name":"rocksdb_bytes_written","value":20},{"name":"log_bytes_logged","value":5}]}]"#;
+            let mut metricentitydiff = MetricEntityDiff::new();
+            metricentitydiff.first_snapshot(AllMetricEntity { metricentity: serde_json::from_str(first).unwrap() }, &false, &false);
+            metricentitydiff.second_snapshot(AllMetricEntity { metricentity: serde_json::from_str(second).unwrap() }, &false, &false, &Local::now());
+            let all = utility::set_regex(&None);
+            let context_groups = metricentitydiff.diff_context_groups(&all, &all, &all, &[], &Some("prefix".to_string()), &metrics::ValueStatistics::create());
+            assert_eq!(context_groups.len(), 1);
+            assert!(context_groups.contains(&("yb-1:9000".to_string(), "server".to_string(), "yb.tabletserver".to_string(), "rocksdb".to_string())));
+            assert!(metricentitydiff.diff_context_groups(&all, &all, &all, &[], &None, &metrics::ValueStatistics::create()).is_empty());
+            assert_eq!(diff_context_group("table", "rows_inserted", "yugabyte", "a"), Some("yugabyte.a".to_string()));
+            assert_eq!(diff_context_group("table", "rows_inserted", "", ""), None);
+        }
+        #[test]
+        fn unit_metrics_diff_outlier_hosts() {
+            let hostnames: Vec<String> = (1..=6).map(|nr| format!("yb-{}:9000", nr)).collect();
+            let mut host_deltas: Vec<(&String, f64)> = hostnames.iter().map(|hostname| (hostname, 100_f64)).collect();
+            assert!(outlier_hosts(&host_deltas, 2_f64).is_empty());
+            host_deltas[3].1 = 1000_f64;
+            assert_eq!(outlier_hosts(&host_deltas, 2_f64), vec![&hostnames[3]]);
+            assert!(outlier_hosts(&host_deltas, 3_f64).is_empty());
+            assert!(outlier_hosts(&host_deltas[..2], 0.5_f64).is_empty());
+        }
 }
@@ -1,7 +1,7 @@
 //! The structs
 //!
 use chrono::{DateTime, Local};
-use std::collections::{BTreeMap};
+use std::collections::{BTreeMap, BTreeSet};
 /// The root struct for deserializing `/metrics`.
 ///
 /// Struct to represent the metric entities found in the YugabyteDB
@@ -82,6 +82,9 @@ pub struct MetricEntity {
 ///             }
 /// ```
 /// For now, stream_id is only collected, not printed.
+///
+/// Any other attribute the endpoint provides is kept in `other`, so all attributes are stored in the snapshot.
+/// [Attributes::labels] returns all attributes as a single normalized `key=value` text.
 #[derive(Serialize, Deserialize, Debug, Default)]
 pub struct Attributes {
     pub stream_id: Option<String>,
@@ -90,6 +93,9 @@ pub struct Attributes {
     /// The table_id for a table is the table id, and thus does match the MetricEntity.id.
     /// The table_id for a tablet is the table id, while the MetricEntity.id is the tablet id, and thus is different.
     pub table_id: Option<String>,
+    /// The other attributes; snapshots taken before these were kept have none.
+    #[serde(flatten)]
+    pub other: BTreeMap<String, serde_json::Value>,
 }
 /// Enum to represent the possible metric types found the nested metrics json structure.
 /// These can be a number of valid metric types:
@@ -218,11 +224,24 @@ pub struct MetricDiffValues {
 ///
 /// The key fields are: `hostname_port`, `metric_type`, `metric_id`, `metric_name`
 type BTreeMetricDiffCountSum = BTreeMap<(String, String, String, String), MetricDiffCountSum>;
+/// The percentiles and maximum of a countsum (histogram) statistic.
+///
+/// YugabyteDB does not provide the median (percentile 50), the lowest percentile is percentile 75.
+/// When countsum statistics are summed, such as the tablet statistics per server, the highest value
+/// of each percentile is taken: percentiles cannot be added up, and the highest value is the upper bound.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MetricPercentiles {
+    pub percentile_75: u64,
+    pub percentile_95: u64,
+    pub percentile_99: u64,
+    pub max: u64,
+}
 /// The struct that holds the first and second snapshot statistics.
 ///
-/// Please mind the fields min, mean, percentile_* and max are not put in the diff struct.
+/// Please mind the fields min and mean are not put in the diff struct.
 /// There is no mathematical way to make sense of these figures between the snapshots.
-/// Also, these statistics are flushed upon query, making these rather random.
+/// The percentiles and max are kept to show how these changed between the snapshots,
+/// but these describe the histogram window at the time of the snapshot, not the period between the snapshots.
 #[derive(Debug, Default)]
 pub struct MetricDiffCountSum {
     pub table_name: String,
@@ -230,9 +249,11 @@ pub struct MetricDiffCountSum {
     pub first_snapshot_time: DateTime<Local>,
     pub first_total_sum: u64,
     pub first_total_count: u64,
+    pub first_percentiles: MetricPercentiles,
     pub second_snapshot_time: DateTime<Local>,
     pub second_total_sum: u64,
     pub second_total_count: u64,
+    pub second_percentiles: MetricPercentiles,
 }
 /// BTreeMap for storing a metricentity countsum.
 ///
@@ -258,4 +279,160 @@ pub struct MetricEntityDiff {
     pub btreemetricdiffvalue: BTreeMetricDiffValues,
     pub btreemetricdiffcountsum: BTreeMetricDiffCountSum,
     pub btreemetricdiffcountsumrows: BTreeMetricDiffCountSumRows,
-}
\ No newline at end of file
+    /// The metric renames that are applied to compare the snapshots.
+    pub renamed_metrics: Vec<MetricRename>,
+}
+/// A metric that the metrics diff computes from other metrics, such as the write amplification.
+#[derive(Debug, Default, PartialEq)]
+pub struct ComputedMetric {
+    pub hostname_port: String,
+    pub metric_type: String,
+    pub metric_id: String,
+    pub table_name: String,
+    /// The namespace and table name as shown with --details-enable.
+    pub table_info: String,
+    pub name: String,
+    pub unit: &'static str,
+    pub value: f64,
+}
+/// A metric that is computed from an expression set with `--compute`, such as:
+/// `name=hit_rate expr=rocksdb_block_cache_hit/(rocksdb_block_cache_hit+rocksdb_block_cache_miss)`.
+///
+/// The expression is evaluated per host and metric entity, using the values of the value statistics,
+/// or the deltas for a diff.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricCompute {
+    pub name: String,
+    pub expression: MetricExpression,
+}
+/// An arithmetic expression of metric names and numbers, with the operators +, -, * and /.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MetricExpression {
+    Metric(String),
+    Number(f64),
+    Operation(Box<MetricExpression>, char, Box<MetricExpression>),
+}
+/// The thresholds of `--diff-threshold` and `--pct-threshold`: a change in the metrics diff below a threshold is not shown.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct MetricDiffThreshold {
+    /// The minimal absolute delta of a counter, gauge or histogram count.
+    pub delta: Option<f64>,
+    /// The minimal absolute percent change.
+    pub pct: Option<f64>,
+}
+/// A metric name that is changed between YugabyteDB versions.
+///
+/// The renames can be added with a CSV file with the header `old_name,new_name`:
+/// ```text
+/// old_name,new_name
+/// renamed_metric_old,renamed_metric_new
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MetricRename {
+    pub old_name: String,
+    pub new_name: String,
+}
+// schema diff
+/// The hosts that have a metric name in the first and the second snapshot.
+#[derive(Debug, Default)]
+pub struct MetricSchemaHosts {
+    pub first_hosts: BTreeSet<String>,
+    pub second_hosts: BTreeSet<String>,
+}
+/// Wrapper struct for holding the metric names of both snapshots, ignoring the values.
+///
+/// The key of metricschema is `metric_name`.
+#[derive(Debug, Default)]
+pub struct MetricSchemaDiff {
+    pub metricschema: BTreeMap<String, MetricSchemaHosts>,
+    pub first_hosts: BTreeSet<String>,
+    pub second_hosts: BTreeSet<String>,
+}
+// print
+/// BTreeMap for storing the metricentity values of a single snapshot.
+///
+/// The key fields are: `hostname_port`, `metric_type`, `metric_id`, `metric_name`
+type BTreeMetricValues = BTreeMap<(String, String, String, String), MetricValues>;
+/// The struct that holds the value statistics of a single snapshot.
+#[derive(Debug, Default)]
+pub struct MetricValues {
+    pub table_name: String,
+    pub namespace: String,
+    pub value: i64,
+}
+/// BTreeMap for storing the metricentity countsums of a single snapshot.
+///
+/// The key fields are: `hostname_port`, `metric_type`, `metric_id`, `metric_name`
+type BTreeMetricCountSum = BTreeMap<(String, String, String, String), MetricCountSumValues>;
+/// The struct that holds the countsum statistics of a single snapshot.
+#[derive(Debug, Default)]
+pub struct MetricCountSumValues {
+    pub table_name: String,
+    pub namespace: String,
+    pub total_count: u64,
+    pub total_sum: u64,
+    pub percentiles: MetricPercentiles,
+}
+/// BTreeMap for storing the metricentity countsumrows of a single snapshot.
+///
+/// The key fields are: `hostname_port`, `metric_type`, `metric_id`, `metric_name`
+type BTreeMetricCountSumRows = BTreeMap<(String, String, String, String), MetricCountSumRowsValues>;
+/// The struct that holds the countsumrows statistics of a single snapshot.
+#[derive(Debug, Default)]
+pub struct MetricCountSumRowsValues {
+    pub count: u64,
+    pub sum: u64,
+    pub rows: u64,
+}
+/// Wrapper struct for holding the btreemaps for printing the metrics of a single snapshot.
+///
+/// Just like the diff, table, tablet and cdc metrics are summed per server, unless `--details-enable` is set.
+#[derive(Debug, Default)]
+pub struct MetricEntityPrint {
+    pub btreemetricvalue: BTreeMetricValues,
+    pub btreemetriccountsum: BTreeMetricCountSum,
+    pub btreemetriccountsumrows: BTreeMetricCountSumRows,
+}
+// baseline
+/// A row of a baseline file, holding the expected range of a metric.
+///
+/// The baseline file is a CSV file with the header `metric,min,max`, for example:
+/// ```text
+/// metric,min,max
+/// # the number of tablet servers should be constant
+/// num_tablet_servers_live,3,3
+/// rpc_connections_alive,0,500
+/// ```
+/// For value statistics the value is compared, for countsum statistics the total_count,
+/// and for countsumrows statistics the count.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MetricBaseline {
+    pub metric: String,
+    pub min: f64,
+    pub max: f64,
+}
+/// Wrapper struct for holding the baseline ranges per metric name.
+#[derive(Debug, Default)]
+pub struct AllMetricBaseline {
+    pub metricbaseline: BTreeMap<String, MetricBaseline>,
+}
+// assert
+/// The metric field an assertion is evaluated against.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MetricAssertionField {
+    /// The value in the snapshot, or the value in the end snapshot for a diff.
+    Value,
+    /// The difference between the begin and end snapshot.
+    Delta,
+}
+/// A simple assertion on a metric, in the form of `name op number`.
+///
+/// Examples: `rpc_inbound_calls_created_total<1000`, `metric=rpc_inbound_calls_created_total delta<1000`.
+/// If field is not specified, a diff uses the delta, and a print uses the value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricAssertion {
+    pub metric: String,
+    pub field: Option<MetricAssertionField>,
+    pub operator: String,
+    pub number: f64,
+}
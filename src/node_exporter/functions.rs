@@ -1,16 +1,56 @@
 //! The impls and functions
 //! 
-use std::{sync::mpsc::channel, time::Instant};
+use std::{collections::{BTreeMap, BTreeSet, HashMap}, sync::{Arc, mpsc::channel}, time::Instant};
+use arrow::{array::{ArrayRef, Float64Array, StringArray, TimestampMicrosecondArray}, record_batch::RecordBatch};
 use chrono::{DateTime, Local};
 use prometheus_parse::Value;
 use regex::Regex;
 use log::*;
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
 use crate::utility;
+use crate::utility::Config;
 use crate::snapshot;
-use crate::node_exporter::{NodeExporter, AllNodeExporter, NodeExporterDiff, NameCategoryDiff};
+use crate::node_exporter::{NodeExporter, AllNodeExporter, NodeExporterDiff, NameCategoryDiff, CpuUtilization};
 use crate::Opts;
 
+/// The CPU modes of the summarized `node_cpu_seconds_total` counters, in the order of the cpu utilization table.
+const CPU_MODES: [&str; 8] = ["user", "nice", "system", "irq", "softirq", "iowait", "steal", "idle"];
+const CPU_UTILIZATION_HEADERS: [&str; 11] = ["hostname_port", "cores", "busy", "user", "nice", "system", "irq", "softirq", "iowait", "steal", "idle"];
+
+/// Parse and validate the label matchers in the form of `key=regex`.
+/// Like a PromQL label matcher, the regex must match the entire label value.
+pub fn parse_label_matchers(
+    label_matchers: &[String],
+) -> Result<Vec<(String, Regex)>>
+{
+    let mut matchers = Vec::new();
+    for label_matcher in label_matchers
+    {
+        let (key, regex) = label_matcher
+            .split_once('=')
+            .with_context(|| format!("Invalid node_exporter label match: {}, expected: key=regex", label_matcher))?;
+        if key.is_empty()
+        {
+            bail!("Invalid node_exporter label match: {}, the key is empty", label_matcher);
+        }
+        let regex = Regex::new(&format!("^(?:{})$", regex))
+            .with_context(|| format!("Invalid regex in node_exporter label match: {}", label_matcher))?;
+        matchers.push((key.to_string(), regex));
+    }
+    Ok(matchers)
+}
+
+/// A series matches if it has every label of the matchers, with a value matching the regex.
+fn labels_match(
+    labels: &HashMap<String, String>,
+    matchers: &[(String, Regex)],
+) -> bool
+{
+    matchers
+        .iter()
+        .all(|(key, regex)| labels.get(key).is_some_and(|value| regex.is_match(value)))
+}
+
 impl AllNodeExporter {
     pub fn new() -> Self {
         Default::default()
@@ -20,13 +60,18 @@ impl AllNodeExporter {
         ports: &Vec<&str>,
         snapshot_number: i32,
         parallel: usize,
+        config: &Config,
     )  -> Result<()>
     {
         info!("begin snapshot");
         let timer = Instant::now();
 
-        let allnodeexporter = AllNodeExporter::read_nodeexporter(hosts, ports, parallel).await;
-        snapshot::save_snapshot_json(snapshot_number, "nodeexporter", allnodeexporter.nodeexporter)?;
+        let allnodeexporter = AllNodeExporter::read_nodeexporter(hosts, ports, parallel, config).await;
+        if config.parquet_output {
+            snapshot::save_snapshot_parquet(snapshot_number, "nodeexporter", allnodeexporter.record_batch()?, allnodeexporter.nodeexporter.len(), config)?;
+        } else {
+            snapshot::save_snapshot_json(snapshot_number, "nodeexporter", allnodeexporter.nodeexporter, config)?;
+        }
 
         info!("end snapshot: {:?}", timer.elapsed());
         Ok(())
@@ -34,7 +79,8 @@ impl AllNodeExporter {
     pub async fn read_nodeexporter(
         hosts: &Vec<&str>,
         ports: &Vec<&str>,
-        parallel: usize
+        parallel: usize,
+        config: &Config,
     ) -> AllNodeExporter
     {
         info!("begin parallel http read");
@@ -48,9 +94,9 @@ impl AllNodeExporter {
                     let tx = tx.clone();
                     s.spawn(move |_| {
                         let detail_snapshot_time = Local::now();
-                        let mut nodeexporter = AllNodeExporter::read_http(host, port);
+                        let mut nodeexporter = AllNodeExporter::read_http(host, port, config);
                         nodeexporter.iter_mut().for_each(|r| r.timestamp = detail_snapshot_time);
-                        nodeexporter.iter_mut().for_each(|r| r.hostname_port = format!("{}:{}", host, port));
+                        nodeexporter.iter_mut().for_each(|r| r.hostname_port = utility::hostname_port(host, port, config));
                         tx.send(nodeexporter).expect("error sending data via tx");
                     });
                 }
@@ -73,13 +119,63 @@ impl AllNodeExporter {
     pub fn read_http(
         host: &str,
         port: &str,
+        config: &Config,
     ) -> Vec<NodeExporter>
     {
-        let data_from_http = utility::http_get(host, port, "metrics?reset_histograms=false");
-        AllNodeExporter::parse_nodeexporter(data_from_http)
+        let data_from_http = utility::http_get_endpoint(host, port, "node_exporter", config);
+        AllNodeExporter::parse_nodeexporter(data_from_http, config)
+    }
+    /// Create a record batch with a row per node exporter value, for saving as parquet.
+    pub fn record_batch(
+        &self,
+    ) -> Result<RecordBatch>
+    {
+        RecordBatch::try_from_iter(vec![
+            ("hostname_port", Arc::new(StringArray::from_iter_values(self.nodeexporter.iter().map(|row| &row.hostname_port))) as ArrayRef),
+            ("timestamp", Arc::new(TimestampMicrosecondArray::from_iter_values(self.nodeexporter.iter().map(|row| row.timestamp.timestamp_micros())).with_timezone("UTC")) as ArrayRef),
+            ("name", Arc::new(StringArray::from_iter_values(self.nodeexporter.iter().map(|row| &row.name))) as ArrayRef),
+            ("exporter_type", Arc::new(StringArray::from_iter_values(self.nodeexporter.iter().map(|row| &row.exporter_type))) as ArrayRef),
+            ("labels", Arc::new(StringArray::from_iter_values(self.nodeexporter.iter().map(|row| &row.labels))) as ArrayRef),
+            ("category", Arc::new(StringArray::from_iter_values(self.nodeexporter.iter().map(|row| &row.category))) as ArrayRef),
+            ("value", Arc::new(Float64Array::from_iter_values(self.nodeexporter.iter().map(|row| row.value))) as ArrayRef),
+            ("exporter_timestamp", Arc::new(TimestampMicrosecondArray::from_iter_values(self.nodeexporter.iter().map(|row| row.exporter_timestamp.timestamp_micros())).with_timezone("UTC")) as ArrayRef),
+        ]).with_context(|| "Error creating the node exporter record batch")
+    }
+    /// Read the node exporter values back from the record batches of a parquet snapshot, see [AllNodeExporter::record_batch].
+    pub fn from_record_batches(
+        record_batches: &[RecordBatch],
+    ) -> Result<AllNodeExporter>
+    {
+        let mut allnodeexporter = AllNodeExporter::new();
+        for record_batch in record_batches
+        {
+            let hostname_port = snapshot::parquet_column::<StringArray>(record_batch, "hostname_port")?;
+            let timestamp = snapshot::parquet_column::<TimestampMicrosecondArray>(record_batch, "timestamp")?;
+            let name = snapshot::parquet_column::<StringArray>(record_batch, "name")?;
+            let exporter_type = snapshot::parquet_column::<StringArray>(record_batch, "exporter_type")?;
+            let labels = snapshot::parquet_column::<StringArray>(record_batch, "labels")?;
+            let category = snapshot::parquet_column::<StringArray>(record_batch, "category")?;
+            let value = snapshot::parquet_column::<Float64Array>(record_batch, "value")?;
+            let exporter_timestamp = snapshot::parquet_column::<TimestampMicrosecondArray>(record_batch, "exporter_timestamp")?;
+            for row in 0..record_batch.num_rows()
+            {
+                allnodeexporter.nodeexporter.push(NodeExporter {
+                    hostname_port: hostname_port.value(row).to_string(),
+                    timestamp: DateTime::from_timestamp_micros(timestamp.value(row)).with_context(|| "Invalid timestamp in parquet data")?.with_timezone(&Local),
+                    name: name.value(row).to_string(),
+                    exporter_type: exporter_type.value(row).to_string(),
+                    labels: labels.value(row).to_string(),
+                    category: category.value(row).to_string(),
+                    value: value.value(row),
+                    exporter_timestamp: DateTime::from_timestamp_micros(exporter_timestamp.value(row)).with_context(|| "Invalid timestamp in parquet data")?,
+                });
+            }
+        }
+        Ok(allnodeexporter)
     }
     fn parse_nodeexporter( 
-        node_exporter_data: String
+        node_exporter_data: String,
+        config: &Config,
     ) -> Vec<NodeExporter>
     {
         // This is the actual parsing
@@ -87,10 +183,12 @@ impl AllNodeExporter {
 
         // post processing
         let mut nodeexporter = Vec::new();
+        let label_matchers = &config.label_matchers;
         if !node_exporter_rows.samples.is_empty()
         {
             for sample in node_exporter_rows.samples
             {
+                if !labels_match(&sample.labels, label_matchers) { continue };
                 // Build a label of the different labels of a sample
                 let mut label_temp = sample.labels.values().cloned().collect::<Vec<String>>();
                 label_temp.sort();
@@ -172,16 +270,17 @@ impl NodeExporterDiff {
         begin_snapshot: &String,
         end_snapshot: &String,
         begin_snapshot_time: &DateTime<Local>,
+        config: &Config,
     ) -> Result<NodeExporterDiff>
     {
         let mut nodeexporterdiff = NodeExporterDiff::new();
 
         let mut allnodeexporter = AllNodeExporter::new();
-        allnodeexporter.nodeexporter = snapshot::read_snapshot_json(begin_snapshot, "nodeexporter")?;
+        allnodeexporter.nodeexporter = snapshot::read_snapshot_json(begin_snapshot, "nodeexporter", config)?;
         nodeexporterdiff.first_snapshot(allnodeexporter);
 
         let mut allnodeexporter = AllNodeExporter::new();
-        allnodeexporter.nodeexporter = snapshot::read_snapshot_json(end_snapshot, "nodeexporter")?;
+        allnodeexporter.nodeexporter = snapshot::read_snapshot_json(end_snapshot, "nodeexporter", config)?;
         nodeexporterdiff.second_snapshot(allnodeexporter, begin_snapshot_time);
 
         Ok(nodeexporterdiff)
@@ -235,15 +334,72 @@ impl NodeExporterDiff {
                 });
         }
     }
+    /// Calculate the CPU utilization per host from the summarized `node_cpu_seconds_total` counters.
+    /// The difference of a mode is divided by the elapsed time and the number of CPUs, so that all modes together add up to 100%.
+    /// The number of CPUs is the number of per-CPU idle series, which are in the 'detail' category.
+    pub fn cpu_utilization(&self) -> BTreeMap<String, CpuUtilization>
+    {
+        let mut cpu_utilization: BTreeMap<String, CpuUtilization> = BTreeMap::new();
+        for ((hostname_port, name, labels), diff_row) in &self.btreemapnodeexporterdiff {
+            if name == "node_cpu_seconds_total"
+                && diff_row.category == "detail"
+                && labels.ends_with("_idle")
+            {
+                cpu_utilization.entry(hostname_port.clone()).or_default().cores += 1;
+            }
+        }
+        for (hostname_port, utilization) in cpu_utilization.iter_mut() {
+            for mode in CPU_MODES {
+                let Some(diff_row) = self.btreemapnodeexporterdiff.get(&(hostname_port.clone(), "node_cpu_seconds_total".to_string(), format!("_{}", mode))) else { continue };
+                let elapsed_seconds = (diff_row.second_snapshot_time - diff_row.first_snapshot_time).num_milliseconds() as f64 / 1000.0;
+                if elapsed_seconds <= 0.0 { continue };
+                utilization.modes.insert(mode.to_string(), (diff_row.second_value - diff_row.first_value) / (elapsed_seconds * utilization.cores as f64) * 100.0);
+            }
+        }
+        cpu_utilization
+    }
+    fn print_cpu_utilization(
+        &self,
+        hostname_filter: &Regex,
+        stat_name_filter: &Regex,
+        config: &Config,
+    ) -> Result<usize>
+    {
+        if !stat_name_filter.is_match("node_cpu_seconds_total") { return Ok(0) };
+        let rows: Vec<Vec<String>> = self.cpu_utilization()
+            .iter()
+            .filter(|(hostname_port, utilization)| hostname_filter.is_match(hostname_port) && !utilization.modes.is_empty())
+            .map(|(hostname_port, utilization)| {
+                let mut row = vec![hostname_port.clone(), utilization.cores.to_string(), utilization.busy().map_or_else(|| "-".to_string(), |pct| format!("{:.1}", pct))];
+                row.extend(CPU_MODES.iter().map(|mode| utilization.modes.get(*mode).map_or_else(|| "-".to_string(), |pct| format!("{:.1}", pct))));
+                row
+            })
+            .collect();
+        if rows.is_empty() { return Ok(0) };
+        writeln!(config.output(), "CPU utilization (%)")?;
+        utility::print_table(&CPU_UTILIZATION_HEADERS, &rows, config)?;
+        Ok(rows.len())
+    }
+    /// Print the node_exporter diff, and return the number of changes: the changed counters and gauges, and the hosts of the cpu utilization.
     pub fn print(
         &self,
         hostname_filter: &Regex,
         stat_name_filter: &Regex,
         gauges_enable: &bool,
         details_enable: &bool,
-    )
+        config: &Config,
+    ) -> Result<usize>
     {
+        let mut changes = self.print_cpu_utilization(hostname_filter, stat_name_filter, config)?;
+        // the hosts of which the number of CPUs can't be determined have no cpu utilization, and get the raw cpu counters.
+        let cpu_utilization_hosts: BTreeSet<String> = self.cpu_utilization()
+            .into_iter()
+            .filter(|(_, utilization)| !utilization.modes.is_empty())
+            .map(|(hostname_port, _)| hostname_port)
+            .collect();
         for ((hostname_port, name, category), diff_row) in &self.btreemapnodeexporterdiff {
+            // the raw cpu counters are summarized in the cpu utilization table.
+            if ! *details_enable && name == "node_cpu_seconds_total" && cpu_utilization_hosts.contains(hostname_port) { continue };
             if hostname_filter.is_match(hostname_port)
                 && stat_name_filter.is_match(name)
                 && diff_row.second_value - diff_row.first_value != 0.0
@@ -251,13 +407,14 @@ impl NodeExporterDiff {
             {
                 if *details_enable && category == "summary" { continue };
                 if ! *details_enable && diff_row.category == "detail" { continue };
-                println!("{:20} {:8} {:73} {:19.6} {:15.3} /s",
+                changes += 1;
+                writeln!(config.output(), "{:20} {:8} {:73} {} {} /s",
                          hostname_port,
                          diff_row.exporter_type,
                          format!("{}{}", name, category),
-                         diff_row.second_value - diff_row.first_value,
-                         (diff_row.second_value - diff_row.first_value) / (diff_row.second_snapshot_time - diff_row.first_snapshot_time).num_seconds() as f64,
-                );
+                         utility::format_float(diff_row.second_value - diff_row.first_value, 19, 6, config),
+                         utility::format_float((diff_row.second_value - diff_row.first_value) / (diff_row.second_snapshot_time - diff_row.first_snapshot_time).num_seconds() as f64, 15, 3, config),
+                )?;
             }
             if hostname_filter.is_match(hostname_port)
                 && stat_name_filter.is_match(hostname_port)
@@ -266,24 +423,27 @@ impl NodeExporterDiff {
             {
                 if *details_enable && category == "summary" { continue };
                 if ! *details_enable && diff_row.category == "detail" { continue };
-                println!("{:20} {:8} {:73} {:19.6} {:+15}",
+                if diff_row.second_value != diff_row.first_value { changes += 1 };
+                writeln!(config.output(), "{:20} {:8} {:73} {} {:+15}",
                          hostname_port,
                          diff_row.exporter_type,
                          format!("{}{}", name, category),
-                         diff_row.second_value,
+                         utility::format_float(diff_row.second_value, 19, 6, config),
                          diff_row.second_value - diff_row.first_value
-                );
+                )?;
             }
         }
+        Ok(changes)
     }
     pub async fn adhoc_read_first_snapshot (
         &mut self,
         hosts: &Vec<&str>,
         ports: &Vec<&str>,
         parallel: usize,
+        config: &Config,
     )
     {
-        let allnodeexporter = AllNodeExporter::read_nodeexporter(hosts, ports, parallel).await;
+        let allnodeexporter = AllNodeExporter::read_nodeexporter(hosts, ports, parallel, config).await;
         self.first_snapshot(allnodeexporter);
     }
     pub async fn adhoc_read_second_snapshot(
@@ -292,9 +452,10 @@ impl NodeExporterDiff {
         ports: &Vec<&str>,
         parallel: usize,
         first_snapshot_time: &DateTime<Local>,
+        config: &Config,
     )
     {
-        let allnodeexporter = AllNodeExporter::read_nodeexporter(hosts, ports, parallel).await;
+        let allnodeexporter = AllNodeExporter::read_nodeexporter(hosts, ports, parallel, config).await;
         self.second_snapshot(allnodeexporter, first_snapshot_time);
     }
 }
@@ -542,12 +703,21 @@ fn linux_cpu_sum(nodeexporter: &mut Vec<NodeExporter>)
     }
 }
 
+impl CpuUtilization {
+    /// The percentage of the available CPU time that is not idle, which is all modes except idle together.
+    pub fn busy(&self) -> Option<f64>
+    {
+        self.modes.get("idle").map(|idle| 100.0 - idle)
+    }
+}
+
 pub async fn node_exporter_diff(
     options: &Opts,
+    config: &Config,
 ) -> Result<()>
 {
     if options.begin.is_none() || options.end.is_none() {
-        snapshot::Snapshot::print()?;
+        snapshot::Snapshot::print(config)?;
     }
     if options.snapshot_list { return Ok(()) };
 
@@ -556,8 +726,11 @@ pub async fn node_exporter_diff(
 
     let (begin_snapshot, end_snapshot, begin_snapshot_row) = snapshot::Snapshot::read_begin_end_snapshot_from_user(options.begin, options.end)?;
 
-    let nodeexporterdiff = NodeExporterDiff::snapshot_diff(&begin_snapshot, &end_snapshot, &begin_snapshot_row.timestamp)?;
-    nodeexporterdiff.print(&hostname_filter, &stat_name_filter, &options.gauges_enable, &options.details_enable);
+    snapshot::Snapshot::print_diff_header(&begin_snapshot, &end_snapshot, config)?;
+
+    let nodeexporterdiff = NodeExporterDiff::snapshot_diff(&begin_snapshot, &end_snapshot, &begin_snapshot_row.timestamp, config)?;
+    let changes = nodeexporterdiff.print(&hostname_filter, &stat_name_filter, &options.gauges_enable, &options.details_enable, config)?;
+    utility::add_diff_changes(changes, config);
 
     Ok(())
 }
@@ -569,6 +742,7 @@ mod tests {
 
     #[test]
     fn unit_parse_node_exporter_non_prometheus_data() {
+        let config = &Config::default();
         let fake_http_data = r#"
         [
     {
@@ -593,24 +767,41 @@ mod tests {
                 "value": 0
             },
         "#.to_string();
-        let result = AllNodeExporter::parse_nodeexporter(fake_http_data);
+        let result = AllNodeExporter::parse_nodeexporter(fake_http_data, config);
         assert_eq!(result.len(), 0);
     }
 
+    #[test]
+    fn unit_label_matchers() {
+        let matchers = parse_label_matchers(&["mountpoint=/|/data.*".to_string(), "device=sd.".to_string()]).unwrap();
+        let labels = |mountpoint: &str, device: &str| HashMap::from([("mountpoint".to_string(), mountpoint.to_string()), ("device".to_string(), device.to_string())]);
+        assert!(labels_match(&labels("/", "sda"), &matchers));
+        assert!(labels_match(&labels("/data1", "sdb"), &matchers));
+        assert!(!labels_match(&labels("/boot", "sda"), &matchers));
+        assert!(!labels_match(&labels("/", "nvme0n1"), &matchers));
+        assert!(!labels_match(&HashMap::from([("mountpoint".to_string(), "/".to_string())]), &matchers));
+        assert!(labels_match(&HashMap::new(), &[]));
+        assert!(parse_label_matchers(&["mountpoint".to_string()]).is_err());
+        assert!(parse_label_matchers(&["=/".to_string()]).is_err());
+        assert!(parse_label_matchers(&["mountpoint=(".to_string()]).is_err());
+    }
+
     #[test]
     fn unit_parse_node_exporter_data_gauge() {
+        let config = &Config::default();
         let fake_http_data = r#"
         # HELP go_memstats_gc_cpu_fraction The fraction of this program's available CPU time used by the GC since the program started.
         # TYPE go_memstats_gc_cpu_fraction gauge
         go_memstats_gc_cpu_fraction 2.4938682471175543e-06
         "#.to_string();
-        let result = AllNodeExporter::parse_nodeexporter(fake_http_data);
+        let result = AllNodeExporter::parse_nodeexporter(fake_http_data, config);
         assert_eq!(&result[0].name, "go_memstats_gc_cpu_fraction");
         assert_eq!(result[0].value, 2.4938682471175543e-6);
     }
 
     #[test]
     fn unit_parse_node_exporter_data_counter() {
+        let config = &Config::default();
         let fake_http_data = r#"
         # HELP node_network_transmit_packets_total Network device statistic transmit_packets.
         # TYPE node_network_transmit_packets_total counter
@@ -618,25 +809,27 @@ mod tests {
         node_network_transmit_packets_total{device="eth1"} 2716
         node_network_transmit_packets_total{device="lo"} 7085
         "#.to_string();
-        let result = AllNodeExporter::parse_nodeexporter(fake_http_data);
+        let result = AllNodeExporter::parse_nodeexporter(fake_http_data, config);
         assert_eq!(&result[0].name, "node_network_transmit_packets_total");
         assert_eq!(result[0].value, 680.0);
     }
 
     #[test]
     fn unit_parse_node_exporter_data_untyped() {
+        let config = &Config::default();
         let fake_http_data = r#"
         # HELP node_vmstat_pgfault /proc/vmstat information field pgfault.
         # TYPE node_vmstat_pgfault untyped
         node_vmstat_pgfault 718165
         "#.to_string();
-        let result = AllNodeExporter::parse_nodeexporter(fake_http_data);
+        let result = AllNodeExporter::parse_nodeexporter(fake_http_data, config);
         assert_eq!(&result[0].name, "node_vmstat_pgfault");
         assert_eq!(result[0].value, 718165.0);
     }
 
     #[test]
     fn unit_parse_node_exporter_data_summary() {
+        let config = &Config::default();
         let fake_http_data = r#"
         # HELP go_gc_duration_seconds A summary of the pause duration of garbage collection cycles.
         # TYPE go_gc_duration_seconds summary
@@ -648,12 +841,13 @@ mod tests {
         go_gc_duration_seconds_sum 0.000609084
         go_gc_duration_seconds_count 11
         "#.to_string();
-        let result = AllNodeExporter::parse_nodeexporter(fake_http_data);
+        let result = AllNodeExporter::parse_nodeexporter(fake_http_data, config);
         assert_eq!(result.len(), 0);
     }
 
     #[test]
     fn unit_parse_node_exporter_data_histogram() {
+        let config = &Config::default();
         let fake_http_data = r#"
 # HELP request_duration Time for HTTP request.
 # TYPE request_duration histogram
@@ -675,12 +869,113 @@ request_duration_bucket{le="+Inf",} 3.0
 request_duration_count 3.0
 request_duration_sum 22.978489699999997
         "#.to_string();
-        let result = AllNodeExporter::parse_nodeexporter(fake_http_data);
+        let result = AllNodeExporter::parse_nodeexporter(fake_http_data, config);
         assert_eq!(result.len(), 0);
     }
 
+    #[test]
+    fn unit_node_exporter_cpu_utilization() {
+        let config = &Config::default();
+        let cpu_seconds = |user: f64, system: f64, iowait: f64, idle: f64| format!(r#"
+        # HELP node_cpu_seconds_total Seconds the CPUs spent in each mode.
+        # TYPE node_cpu_seconds_total counter
+        node_cpu_seconds_total{{cpu="0",mode="idle"}} {idle}
+        node_cpu_seconds_total{{cpu="0",mode="iowait"}} {iowait}
+        node_cpu_seconds_total{{cpu="0",mode="system"}} {system}
+        node_cpu_seconds_total{{cpu="0",mode="irq"}} 0
+        node_cpu_seconds_total{{cpu="0",mode="nice"}} 0
+        node_cpu_seconds_total{{cpu="0",mode="softirq"}} 0
+        node_cpu_seconds_total{{cpu="0",mode="steal"}} 0
+        node_cpu_seconds_total{{cpu="0",mode="user"}} {user}
+        node_cpu_seconds_total{{cpu="1",mode="idle"}} {idle}
+        node_cpu_seconds_total{{cpu="1",mode="iowait"}} {iowait}
+        node_cpu_seconds_total{{cpu="1",mode="system"}} {system}
+        node_cpu_seconds_total{{cpu="1",mode="irq"}} 0
+        node_cpu_seconds_total{{cpu="1",mode="nice"}} 0
+        node_cpu_seconds_total{{cpu="1",mode="softirq"}} 0
+        node_cpu_seconds_total{{cpu="1",mode="steal"}} 0
+        node_cpu_seconds_total{{cpu="1",mode="user"}} {user}
+        "#);
+        let first_snapshot_time = Local::now();
+        let snapshot = |data: String, timestamp: DateTime<Local>| {
+            let mut allnodeexporter = AllNodeExporter::new();
+            allnodeexporter.nodeexporter = AllNodeExporter::parse_nodeexporter(data, config);
+            for row in allnodeexporter.nodeexporter.iter_mut() {
+                row.hostname_port = "localhost:9300".to_string();
+                row.timestamp = timestamp;
+            }
+            allnodeexporter
+        };
+        let mut nodeexporterdiff = NodeExporterDiff::new();
+        nodeexporterdiff.first_snapshot(snapshot(cpu_seconds(100.0, 50.0, 10.0, 1000.0), first_snapshot_time));
+        // 10 seconds with 2 cpus is 20 cpu seconds: 5 user, 2 system, 1 iowait and 12 idle.
+        nodeexporterdiff.second_snapshot(snapshot(cpu_seconds(102.5, 51.0, 10.5, 1006.0), first_snapshot_time + chrono::Duration::seconds(10)), &first_snapshot_time);
+        let result = nodeexporterdiff.cpu_utilization();
+        let utilization = &result["localhost:9300"];
+        assert_eq!(utilization.cores, 2);
+        assert_eq!(utilization.modes["user"], 25.0);
+        assert_eq!(utilization.modes["system"], 10.0);
+        assert_eq!(utilization.modes["iowait"], 5.0);
+        assert_eq!(utilization.modes["idle"], 60.0);
+        assert_eq!(utilization.modes["steal"], 0.0);
+        assert_eq!(utilization.busy(), Some(40.0));
+
+        // without the per-CPU series, the number of CPUs can't be determined, and there is no cpu utilization.
+        let mut nodeexporterdiff = NodeExporterDiff::new();
+        let summary_only = |allnodeexporter: AllNodeExporter| {
+            let mut allnodeexporter = allnodeexporter;
+            allnodeexporter.nodeexporter.retain(|row| row.category == "summary");
+            allnodeexporter
+        };
+        nodeexporterdiff.first_snapshot(summary_only(snapshot(cpu_seconds(100.0, 50.0, 10.0, 1000.0), first_snapshot_time)));
+        nodeexporterdiff.second_snapshot(summary_only(snapshot(cpu_seconds(102.5, 51.0, 10.5, 1006.0), first_snapshot_time + chrono::Duration::seconds(10))), &first_snapshot_time);
+        assert!(nodeexporterdiff.cpu_utilization().is_empty());
+    }
+
+    #[test]
+    fn unit_node_exporter_from_record_batches() {
+        let json = r#"[{"hostname_port":"yb-1:9300","timestamp":"2023-01-01T10:00:00.5+00:00","name":"node_load1","exporter_type":"gauge","labels":"","category":"all","value":0.5,"exporter_timestamp":"2023-01-01T10:00:00Z"},
+                       {"hostname_port":"yb-1:9300","timestamp":"2023-01-01T10:00:00.5+00:00","name":"node_network_receive_bytes_total","exporter_type":"counter","labels":"_eth0","category":"all","value":1024.0,"exporter_timestamp":"2023-01-01T10:00:00Z"}]"#;
+        let allnodeexporter = AllNodeExporter { nodeexporter: serde_json::from_str(json).unwrap() };
+        let read_back = AllNodeExporter::from_record_batches(&[allnodeexporter.record_batch().unwrap()]).unwrap();
+        assert_eq!(serde_json::to_value(&read_back.nodeexporter).unwrap(), serde_json::to_value(&allnodeexporter.nodeexporter).unwrap());
+    }
+
+    #[test]
+    fn unit_node_exporter_record_batch() {
+        use arrow::array::Array;
+        let config = &Config::default();
+        let node_exporter_data = r#"
+        # HELP node_load1 1m load average.
+        # TYPE node_load1 gauge
+        node_load1 0.5
+        # HELP node_network_receive_bytes_total Network device statistic receive_bytes.
+        # TYPE node_network_receive_bytes_total counter
+        node_network_receive_bytes_total{device="eth0"} 1024
+        "#.to_string();
+        let mut allnodeexporter = AllNodeExporter::new();
+        allnodeexporter.nodeexporter = AllNodeExporter::parse_nodeexporter(node_exporter_data, config);
+        for row in allnodeexporter.nodeexporter.iter_mut() {
+            row.hostname_port = "localhost:9300".to_string();
+        }
+        let record_batch = allnodeexporter.record_batch().unwrap();
+        assert_eq!(record_batch.num_rows(), allnodeexporter.nodeexporter.len());
+        assert_eq!(record_batch.num_columns(), 8);
+        let names = record_batch.column_by_name("name").unwrap().as_any().downcast_ref::<StringArray>().unwrap();
+        let values = record_batch.column_by_name("value").unwrap().as_any().downcast_ref::<Float64Array>().unwrap();
+        let labels = record_batch.column_by_name("labels").unwrap().as_any().downcast_ref::<StringArray>().unwrap();
+        let row = (0..names.len()).find(|row| names.value(*row) == "node_network_receive_bytes_total").unwrap();
+        assert_eq!(labels.value(row), "_eth0");
+        assert_eq!(values.value(row), 1024_f64);
+        let hostname_port = record_batch.column_by_name("hostname_port").unwrap().as_any().downcast_ref::<StringArray>().unwrap();
+        assert_eq!(hostname_port.value(row), "localhost:9300");
+        let timestamp = record_batch.column_by_name("timestamp").unwrap().as_any().downcast_ref::<TimestampMicrosecondArray>().unwrap();
+        assert_eq!(timestamp.value(row), allnodeexporter.nodeexporter[row].timestamp.timestamp_micros());
+    }
+
     #[tokio::test]
     async fn integration_parse_node_exporter() {
+        let config = &Config::default();
         let hostname = utility::get_hostname_node_exporter();
         if hostname == *"SKIP" {
             // workaround for allowing integration tests where no node exporter is present.
@@ -688,7 +983,7 @@ request_duration_sum 22.978489699999997
         }
         let port = utility::get_port_node_exporter();
 
-        let allnodeexporter = AllNodeExporter::read_nodeexporter(&vec![&hostname], &vec![&port], 1).await;
+        let allnodeexporter = AllNodeExporter::read_nodeexporter(&vec![&hostname], &vec![&port], 1, config).await;
 
         assert!(!allnodeexporter.nodeexporter.is_empty());
     }
@@ -37,4 +37,12 @@ type BTreeNodeExporterDiff = BTreeMap<(String, String, String), NameCategoryDiff
 #[derive(Default)]
 pub struct NodeExporterDiff {
     pub btreemapnodeexporterdiff: BTreeNodeExporterDiff,
-}
\ No newline at end of file
+}
+/// The CPU utilization of a host between two snapshots, derived from the `node_cpu_seconds_total` counters.
+#[derive(Debug, Default, PartialEq)]
+pub struct CpuUtilization {
+    /// The number of CPUs, which is the number of per-CPU idle series.
+    pub cores: usize,
+    /// The percentage of the available CPU time per mode, such as user, system, iowait and idle.
+    pub modes: BTreeMap<String, f64>,
+}
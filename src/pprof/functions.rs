@@ -4,6 +4,7 @@ use std::{fs, io::Write, sync::mpsc::channel, time::Instant, env};
 use log::*;
 use anyhow::{Result, Context};
 use crate::utility;
+use crate::utility::Config;
 
 pub struct Pprof;
 
@@ -13,12 +14,13 @@ impl Pprof {
         ports: &Vec<&str>,
         snapshot_number: i32,
         parallel: usize,
+        config: &Config,
     ) -> Result<()>
     {
         info!("begin snapshot");
         let timer = Instant::now();
 
-        Pprof::read_and_write_pprof(hosts, ports, snapshot_number, parallel).await?;
+        Pprof::read_and_write_pprof(hosts, ports, snapshot_number, parallel, config).await?;
 
         info!("end snapshot: {:?}", timer.elapsed());
 
@@ -29,6 +31,7 @@ impl Pprof {
         ports: &Vec<&str>,
         snapshot_number: i32,
         parallel: usize,
+        config: &Config,
     ) -> Result<()>
     {
         info!("begin parallel http read");
@@ -42,8 +45,8 @@ impl Pprof {
                 for port in ports {
                     let tx = tx.clone();
                     s.spawn(move |_| {
-                        let pprof_data = Pprof::read_http(host, port);
-                        tx.send((format!("{}:{}", host, port), pprof_data)).expect("error sending data via tx (pprof)");
+                        let pprof_data = Pprof::read_http(host, port, config);
+                        tx.send((utility::hostname_port(host, port, config), pprof_data)).expect("error sending data via tx (pprof)");
                     });
                 }
             }
@@ -74,9 +77,10 @@ impl Pprof {
     fn read_http(
         host: &str,
         port: &str,
+        config: &Config,
     ) -> String
     {
-        utility::http_get(host, port, "pprof/growth")
+        utility::http_get_endpoint(host, port, "pprof", config)
     }
 }
 
@@ -86,18 +90,20 @@ mod tests {
 
     #[tokio::test]
     async fn integration_parse_pprof_growth_tserver() {
+        let config = &Config::default();
         // currently, the pprof "parsing" is not much parsing.
         // What currently is done, is that the hostname:port/pprof/growth output is stored in a file in the snapshot directory named <hostname>:<port>_pprof_growth.
         let hostname = utility::get_hostname_tserver();
         let port = utility::get_port_tserver();
-        Pprof::read_and_write_pprof(&vec![&hostname], &vec![&port], -1, 1).await.unwrap();
+        Pprof::read_and_write_pprof(&vec![&hostname], &vec![&port], -1, 1, config).await.unwrap();
     }
     #[tokio::test]
     async fn integration_parse_pprof_growth_master() {
+        let config = &Config::default();
         // currently, the pprof "parsing" is not much parsing.
         // What currently is done, is that the hostname:port/pprof/growth output is stored in a file in the snapshot directory named <hostname>:<port>_pprof_growth.
         let hostname = utility::get_hostname_master();
         let port = utility::get_port_master();
-        Pprof::read_and_write_pprof(&vec![&hostname], &vec![&port], -1, 1).await.unwrap();
+        Pprof::read_and_write_pprof(&vec![&hostname], &vec![&port], -1, 1, config).await.unwrap();
     }
 }
@@ -4,10 +4,12 @@ use chrono::Local;
 use std::{fmt, sync::mpsc::channel, collections::BTreeMap, time::Instant};
 use log::*;
 use regex::Regex;
+use colored::*;
 use anyhow::Result;
 use crate::utility;
+use crate::utility::Config;
 use crate::snapshot;
-use crate::rpcs::{Rpcs, AllRpcs, CQLCallDetailsPB, RpcConnectionDetailsPB, RpcCallState, RequestHeader, StateType, CqlConnectionDetails, RemoteMethodPB};
+use crate::rpcs::{Rpcs, AllRpcs, RpcCallInProgressPB, CQLCallDetailsPB, RpcConnectionDetailsPB, RpcCallState, RequestHeader, StateType, CqlConnectionDetails, RemoteMethodPB};
 use crate::rpcs::Rpcs::{Ysql, Rpc};
 use crate::Opts;
 
@@ -22,6 +24,52 @@ impl fmt::Display for StateType {
     }
 }
 
+impl Rpcs {
+    /// Keep the trace of the `limit` slowest calls in flight, inbound and outbound, and remove the traces of the other calls.
+    pub fn keep_slowest_traces(
+        &mut self,
+        limit: usize,
+    )
+    {
+        if let Rpc { inbound_connections, outbound_connections, .. } = self
+        {
+            let mut calls_in_flight: Vec<&mut RpcCallInProgressPB> = inbound_connections
+                .iter_mut()
+                .flatten()
+                .filter_map(|inbound| inbound.calls_in_flight.as_mut())
+                .chain(outbound_connections
+                    .iter_mut()
+                    .flatten()
+                    .filter_map(|outbound| outbound.calls_in_flight.as_mut())
+                )
+                .flatten()
+                .collect();
+            calls_in_flight.sort_by_key(|call_in_flight| std::cmp::Reverse(call_in_flight.elapsed_millis.unwrap_or_default()));
+            for call_in_flight in calls_in_flight.into_iter().skip(limit)
+            {
+                call_in_flight.trace_buffer = None;
+            }
+        }
+    }
+}
+
+/// Print the trace of a call in flight indented below the call, a trace step per line.
+fn print_trace(
+    trace_buffer: &Option<String>,
+    config: &Config,
+) -> Result<()>
+{
+    for trace_step in trace_buffer
+        .as_deref()
+        .unwrap_or_default()
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+    {
+        writeln!(config.output(), "{:75} {}", "", trace_step.trim_end().dimmed())?;
+    }
+    Ok(())
+}
+
 impl AllRpcs {
     pub fn new() -> Self { Default::default() }
     pub async fn perform_snapshot(
@@ -29,13 +77,16 @@ impl AllRpcs {
         ports: &Vec<&str>,
         snapshot_number: i32,
         parallel: usize,
+        extra_data: &bool,
+        rpc_trace_limit: usize,
+        config: &Config,
     ) -> Result<()>
     {
         info!("begin snapshot");
         let timer = Instant::now();
 
-        let allrpcs = AllRpcs::read_rpcs(hosts, ports, parallel).await;
-        snapshot::save_snapshot_json(snapshot_number, "rpcs", allrpcs.rpcs)?;
+        let allrpcs = AllRpcs::read_rpcs(hosts, ports, parallel, extra_data, rpc_trace_limit, config).await;
+        snapshot::save_snapshot_json(snapshot_number, "rpcs", allrpcs.rpcs, config)?;
 
         info!("end snapshot: {:?}", timer.elapsed());
         Ok(())
@@ -44,6 +95,9 @@ impl AllRpcs {
         hosts: &Vec<&str>,
         ports: &Vec<&str>,
         parallel: usize,
+        extra_data: &bool,
+        rpc_trace_limit: usize,
+        config: &Config,
     ) -> AllRpcs
     {
         info!("begin parallel http read");
@@ -57,15 +111,15 @@ impl AllRpcs {
                     let tx = tx.clone();
                     s.spawn(move |_| {
                         let detail_snapshot_time = Local::now();
-                        let mut rpcs = AllRpcs::read_http(host, port);
+                        let mut rpcs = AllRpcs::read_http(host, port, extra_data, rpc_trace_limit, config);
                         match rpcs
                         {
                             Ysql { ref mut hostname_port, ref mut timestamp, .. } => {
-                                *hostname_port = Some(format!("{}:{}", host, port));
+                                *hostname_port = Some(utility::hostname_port(host, port, config));
                                 *timestamp = Some(detail_snapshot_time);
                             }
                             Rpc { ref mut hostname_port, ref mut timestamp, .. } => {
-                                *hostname_port = Some(format!("{}:{}", host, port));
+                                *hostname_port = Some(utility::hostname_port(host, port, config));
                                 *timestamp = Some(detail_snapshot_time);
                             }
                             _ => {}
@@ -90,10 +144,25 @@ impl AllRpcs {
     pub fn read_http(
         host: &str,
         port: &str,
+        extra_data: &bool,
+        rpc_trace_limit: usize,
+        config: &Config,
     ) -> Rpcs
     {
-        let data_from_http = utility::http_get(host, port, "rpcz");
-        AllRpcs::parse_rpcs(data_from_http, host, port)
+        // with extra data, the traces are requested too.
+        // rpcz returns the traces for all calls in flight, only the traces of the slowest calls are kept.
+        if *extra_data
+        {
+            let data_from_http = utility::http_get_endpoint(host, port, "rpc_traces", config);
+            let mut rpcs = AllRpcs::parse_rpcs(data_from_http, host, port);
+            rpcs.keep_slowest_traces(rpc_trace_limit);
+            rpcs
+        }
+        else
+        {
+            let data_from_http = utility::http_get_endpoint(host, port, "rpcs", config);
+            AllRpcs::parse_rpcs(data_from_http, host, port)
+        }
     }
     fn parse_rpcs(
         http_data: String,
@@ -109,6 +178,7 @@ impl AllRpcs {
         &self,
         details_enable: &bool,
         hostname_filter: &Regex,
+        config: &Config,
     ) -> Result<()>
     {
         let mut endpoint_count: BTreeMap<String, (usize, usize)> = BTreeMap::new();
@@ -226,19 +296,19 @@ impl AllRpcs {
                 let current_hostname = &endpoint.split(':').next().unwrap();
                 let current_port = &endpoint.split(':').nth(1).unwrap();
                 if *current_hostname != previous_hostname {
-                    println!("\n{}", "-".repeat(120));
+                    writeln!(config.output(), "\n{}", "-".repeat(120))?;
                     if !current_port.is_empty() {
-                        self.print_details(previous_hostname, details_enable, hostname_filter);
+                        self.print_details(previous_hostname, details_enable, hostname_filter, config)?;
                     }
 
-                    print!("{}", current_hostname);
+                    write!(config.output(), "{}", current_hostname)?;
                     previous_hostname = current_hostname.to_string();
                 }
-                print!("; port: {}, {}/{} act/tot", current_port, active, inactive);
+                write!(config.output(), "; port: {}, {}/{} act/tot", current_port, active, inactive)?;
             };
         }
-        println!("\n{}", "-".repeat(120));
-        self.print_details(previous_hostname, details_enable, hostname_filter);
+        writeln!(config.output(), "\n{}", "-".repeat(120))?;
+        self.print_details(previous_hostname, details_enable, hostname_filter, config)?;
 
 
         Ok(())
@@ -248,7 +318,8 @@ impl AllRpcs {
         hostname: String,
         details_enable: &bool,
         hostname_filter: &Regex,
-    )
+        config: &Config,
+    ) -> Result<()>
     {
         let mut activity_counter = 0;
         for rpcs in &self.rpcs
@@ -270,7 +341,7 @@ impl AllRpcs {
                                 // active inbound ysql
                                 if connection.backend_status == "active"
                                 {
-                                    println!("{:30}<-{:30} {:6} {:5} {:>6} ms db:{}, q:{}",
+                                    writeln!(config.output(), "{:30}<-{:30} {:6} {:5} {:>6} ms db:{}, q:{}",
                                              hostname_port
                                                  .clone()
                                                  .expect("hostname:port should be set"),
@@ -288,7 +359,7 @@ impl AllRpcs {
                                              connection.query
                                                  .as_ref()
                                                  .unwrap_or(&"".to_string()),
-                                    );
+                                    )?;
                                     activity_counter += 1;
                                 }
                                 // inactive inbound ysql
@@ -316,7 +387,7 @@ impl AllRpcs {
                                                  .unwrap_or_default(),
                                         )
                                     };
-                                    println!("{:30}<-{:30} {:6} {:5} {:>6} ms db:{}, q:{}",
+                                    writeln!(config.output(), "{:30}<-{:30} {:6} {:5} {:>6} ms db:{}, q:{}",
                                              hostname_port
                                                  .clone()
                                                  .expect("hostname:port should be set"),
@@ -331,7 +402,7 @@ impl AllRpcs {
                                              connection.query
                                                  .as_ref()
                                                  .unwrap_or(&"".to_string()),
-                                    );
+                                    )?;
                                     activity_counter += 1;
                                 }
                             }
@@ -389,7 +460,7 @@ impl AllRpcs {
                                         // first call in flight to be printed gets the full details
                                         if calls_in_flight_counter == 0
                                         {
-                                            print!("{:30}<-{:30} {:6} #{:>6} ",
+                                            write!(config.output(), "{:30}<-{:30} {:6} #{:>6} ",
                                                    hostname_port
                                                        .clone()
                                                        .expect("hostname:port should be set"),
@@ -397,14 +468,14 @@ impl AllRpcs {
                                                    inbound.state,
                                                    inbound.processed_call_count
                                                        .unwrap_or_default(),
-                                            );
+                                            )?;
                                         }
                                         else
                                         {
                                             // others get spaces. this allows to see the multiple calls in flight
-                                            print!("{:75} ", "");
+                                            write!(config.output(), "{:75} ", "")?;
                                         }
-                                        println!("{:>6} ms {:17} ks:{}, q: {}",
+                                        writeln!(config.output(), "{:>6} ms {:17} ks:{}, q: {}",
                                                  calls_in_flight.elapsed_millis
                                                      .unwrap_or_default(),
                                                  call_type,
@@ -420,7 +491,7 @@ impl AllRpcs {
                                                  call_details.sql_string
                                                      .as_ref()
                                                      .unwrap_or(&"".to_string()),
-                                        );
+                                        )?;
                                         activity_counter += 1;
                                         if calls_in_flight.cql_details
                                             .as_ref()
@@ -438,7 +509,7 @@ impl AllRpcs {
                                         // first call in flight to be printed gets the full details
                                         if calls_in_flight_counter == 0
                                         {
-                                            print!("{:30}<-{:30} {:6} #{:>6} ",
+                                            write!(config.output(), "{:30}<-{:30} {:6} #{:>6} ",
                                                    hostname_port
                                                        .clone()
                                                        .expect("hostname:port should be set"),
@@ -446,14 +517,14 @@ impl AllRpcs {
                                                    inbound.state,
                                                    inbound.processed_call_count
                                                        .unwrap_or_default(),
-                                            );
+                                            )?;
                                         }
                                         else
                                         {
                                             // others get spaces. this allows to see the multiple calls in flight
-                                            print!("{:75} ", "");
+                                            write!(config.output(), "{:75} ", "")?;
                                         }
-                                        println!("{:>6} ms {:17} {}:{}  (timeout: {} ms)",
+                                        writeln!(config.output(), "{:>6} ms {:17} {}:{}  (timeout: {} ms)",
                                             calls_in_flight.elapsed_millis
                                                .unwrap_or_default(),
                                             calls_in_flight.state
@@ -478,16 +549,18 @@ impl AllRpcs {
                                                .unwrap_or(&RequestHeader::default())
                                                .timeout_millis
                                                .unwrap_or_default(),
-                                        );
+                                        )?;
                                         activity_counter += 1;
                                     }
+                                    // the trace is only set for the slowest calls in a snapshot taken with extra data.
+                                    print_trace(&calls_in_flight.trace_buffer, config)?;
                                 }
                                 // inactive inbound connections.
                                 // inactive connections do not have calls in flight.
                                 if inbound.calls_in_flight.is_none()
                                     && *details_enable
                                 {
-                                    println!("{:30}<-{:30} {:6} #{:>6}",
+                                    writeln!(config.output(), "{:30}<-{:30} {:6} #{:>6}",
                                              hostname_port
                                                  .clone()
                                                  .expect("hostname:port should be set"),
@@ -495,7 +568,7 @@ impl AllRpcs {
                                              inbound.state,
                                              inbound.processed_call_count
                                                  .unwrap_or_default(),
-                                    );
+                                    )?;
                                     activity_counter += 1;
 
                                 }
@@ -515,7 +588,7 @@ impl AllRpcs {
                                         // first call in flight to be printed gets the full details
                                         if calls_in_flight_counter == 0
                                         {
-                                            print!("{:30}->{:30} {:6} #{:>6} ",
+                                            write!(config.output(), "{:30}->{:30} {:6} #{:>6} ",
                                                    hostname_port
                                                        .clone()
                                                        .expect("hostname:port should be set"),
@@ -523,14 +596,14 @@ impl AllRpcs {
                                                    outbound.state,
                                                    outbound.processed_call_count
                                                        .unwrap_or_default(),
-                                            );
+                                            )?;
                                         }
                                         else
                                         {
                                             // others get spaces. this allows to see the multiple calls in flight
-                                            print!("{:75} ", "");
+                                            write!(config.output(), "{:75} ", "")?;
                                         }
-                                        println!("{:>6} ms {:17} {}:{}  (timeout: {} ms)",
+                                        writeln!(config.output(), "{:>6} ms {:17} {}:{}  (timeout: {} ms)",
                                                  calls_in_flight.elapsed_millis
                                                      .unwrap_or_default(),
                                                  calls_in_flight.state
@@ -555,20 +628,21 @@ impl AllRpcs {
                                                      .unwrap_or(&RequestHeader::default())
                                                      .timeout_millis
                                                      .unwrap_or_default(),
-                                        );
+                                        )?;
                                         activity_counter += 1;
                                     }
                                     if calls_in_flight.cql_details.is_some()
                                     {
                                         error!("Found outbound calls_in_flight.cql_details?");
                                     }
+                                    print_trace(&calls_in_flight.trace_buffer, config)?;
                                 }
                                 // inactive outbound connections.
                                 // inactive connections do not have calls in flight.
                                 if outbound.calls_in_flight.is_none()
                                     && *details_enable
                                 {
-                                    println!("{:30}->{:30} {:6} #{:>6}",
+                                    writeln!(config.output(), "{:30}->{:30} {:6} #{:>6}",
                                              hostname_port
                                                  .clone()
                                                  .expect("hostname:port should be set"),
@@ -576,7 +650,7 @@ impl AllRpcs {
                                              outbound.state,
                                              outbound.processed_call_count
                                                  .unwrap_or_default(),
-                                    );
+                                    )?;
                                     activity_counter += 1;
                                 }
                             }
@@ -587,8 +661,107 @@ impl AllRpcs {
         }
         if activity_counter > 0
         {
-            println!("{}", "-".repeat(120));
+            writeln!(config.output(), "{}", "-".repeat(120))?;
+        }
+        Ok(())
+    }
+    /// Aggregate the active calls into groups: the number of calls and the total duration in milliseconds per group.
+    ///
+    /// The calls are the rpc and YCQL calls in flight, and the YSQL client backends. The group key is:
+    /// - method: the rpc service and method, `YCQL:<call type>` or `YSQL`.
+    /// - caller: the side that made the call, which is the remote address for inbound calls, and the server itself for outbound calls.
+    /// - state: the rpc call state or the YSQL backend status.
+    ///
+    /// The duration is the elapsed time of a call, and the query running time of a YSQL backend.
+    /// The groups are sorted by the number of calls, highest first.
+    pub fn group_by(
+        &self,
+        group_by: &str,
+        hostname_filter: &Regex,
+    ) -> Vec<(String, usize, u64)>
+    {
+        let mut groups: BTreeMap<String, (usize, u64)> = BTreeMap::new();
+        let mut add = |method: String, caller: &str, state: String, duration: u64| {
+            let key = match group_by {
+                "caller" => caller.to_string(),
+                "state" => state,
+                _ => method,
+            };
+            let group = groups.entry(key).or_default();
+            group.0 += 1;
+            group.1 += duration;
+        };
+        for rpcs in &self.rpcs
+        {
+            match rpcs
+            {
+                Ysql { connections, hostname_port, .. } if hostname_filter.is_match(hostname_port.as_deref().unwrap_or_default()) =>
+                {
+                    for connection in connections.iter().filter(|connection| connection.backend_type == "client backend")
+                    {
+                        add(
+                            "YSQL".to_string(),
+                            &format!("{}:{}", connection.host.as_deref().unwrap_or_default(), connection.port.as_deref().unwrap_or_default()),
+                            connection.backend_status.clone(),
+                            connection.query_running_for_ms.unwrap_or_default() as u64,
+                        );
+                    }
+                }
+                Rpc { inbound_connections, outbound_connections, hostname_port, .. } if hostname_filter.is_match(hostname_port.as_deref().unwrap_or_default()) =>
+                {
+                    let inbound_calls = inbound_connections
+                        .iter()
+                        .flatten()
+                        .flat_map(|inbound| inbound.calls_in_flight.iter().flatten().map(|call| (inbound.remote_ip.as_str(), call)));
+                    let outbound_calls = outbound_connections
+                        .iter()
+                        .flatten()
+                        .flat_map(|outbound| outbound.calls_in_flight.iter().flatten().map(|call| (hostname_port.as_deref().unwrap_or_default(), call)));
+                    for (caller, call) in inbound_calls.chain(outbound_calls)
+                    {
+                        let method = match (&call.header, &call.cql_details)
+                        {
+                            (Some(RequestHeader { remote_method: Some(remote_method), .. }), _) => format!("{}:{}", remote_method.service_name, remote_method.method_name),
+                            (_, Some(cql_details)) => format!("YCQL:{}", cql_details.call_type.as_deref().unwrap_or_default()),
+                            _ => continue,
+                        };
+                        add(
+                            method,
+                            caller,
+                            call.state.as_ref().map(|state| state.to_string()).unwrap_or_default(),
+                            call.elapsed_millis.unwrap_or_default(),
+                        );
+                    }
+                }
+                _ => {}
+            }
         }
+        let mut groups: Vec<(String, usize, u64)> = groups
+            .into_iter()
+            .map(|(key, (count, total_ms))| (key, count, total_ms))
+            .collect();
+        groups.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        groups
+    }
+    /// Print the active calls aggregated by method, caller or state, see [AllRpcs::group_by].
+    pub fn print_grouped(
+        &self,
+        group_by: &str,
+        hostname_filter: &Regex,
+        config: &Config,
+    ) -> Result<()>
+    {
+        let rows: Vec<Vec<String>> = self.group_by(group_by, hostname_filter)
+            .into_iter()
+            .map(|(key, count, total_ms)| vec![
+                key,
+                count.to_string(),
+                total_ms.to_string(),
+                format!("{:.1}", total_ms as f64 / count as f64),
+            ])
+            .collect();
+        utility::print_table(&[group_by, "count", "total_ms", "mean_ms"], &rows, config)?;
+        Ok(())
     }
 }
 
@@ -598,6 +771,7 @@ pub async fn print_rpcs(
     ports: Vec<&str>,
     parallel: usize,
     options: &Opts,
+    config: &Config,
 ) -> Result<()>
 {
     let hostname_filter = utility::set_regex(&options.hostname_match);
@@ -610,13 +784,19 @@ pub async fn print_rpcs(
         Some(snapshot_number) =>
         {
             let mut allrpcs = AllRpcs::new();
-            allrpcs.rpcs = snapshot::read_snapshot_json(snapshot_number, "rpcs")?;
-            allrpcs.print(&options.details_enable, &hostname_filter)?;
+            allrpcs.rpcs = snapshot::read_snapshot_json(snapshot_number, "rpcs", config)?;
+            match &options.rpc_group_by {
+                Some(group_by) => allrpcs.print_grouped(group_by, &hostname_filter, config)?,
+                None => allrpcs.print(&options.details_enable, &hostname_filter, config)?,
+            }
         }
         None =>
         {
-            let allrpcs = AllRpcs::read_rpcs(&hosts, &ports, parallel).await;
-            allrpcs.print(&options.details_enable, &hostname_filter)?;
+            let allrpcs = AllRpcs::read_rpcs(&hosts, &ports, parallel, &options.extra_data, options.rpc_trace_limit, config).await;
+            match &options.rpc_group_by {
+                Some(group_by) => allrpcs.print_grouped(group_by, &hostname_filter, config)?,
+                None => allrpcs.print(&options.details_enable, &hostname_filter, config)?,
+            }
         }
     }
     Ok(())
@@ -1319,11 +1499,161 @@ mod tests {
         }
     }
 
+    #[test]
+    fn unit_keep_slowest_traces() {
+        // rpcz?include_traces=true adds the trace_buffer to every call in flight.
+        let json = r#"
+{
+    "inbound_connections": [
+        {
+            "remote_ip": "192.168.66.82:51316",
+            "state": "OPEN",
+            "processed_call_count": 2,
+            "calls_in_flight": [
+                {
+                    "header": {
+                        "call_id": 7,
+                        "remote_method": {
+                            "service_name": "yb.tserver.TabletServerService",
+                            "method_name": "Write"
+                        },
+                        "timeout_millis": 60000
+                    },
+                    "trace_buffer": "1016 10:00:00.000100 (+     0us) service_pool.cc:221] Inserting onto call queue\n1016 10:00:00.000200 (+   100us) service_pool.cc:310] Handling call\n",
+                    "elapsed_millis": 1500,
+                    "state": "SENT"
+                },
+                {
+                    "header": {
+                        "call_id": 8,
+                        "remote_method": {
+                            "service_name": "yb.tserver.TabletServerService",
+                            "method_name": "Read"
+                        },
+                        "timeout_millis": 60000
+                    },
+                    "trace_buffer": "1016 10:00:01.000100 (+     0us) service_pool.cc:221] Inserting onto call queue\n",
+                    "elapsed_millis": 5,
+                    "state": "SENT"
+                }
+            ]
+        }
+    ],
+    "outbound_connections": [
+        {
+            "remote_ip": "192.168.66.80:7100",
+            "state": "OPEN",
+            "processed_call_count": 3526,
+            "calls_in_flight": [
+                {
+                    "header": {
+                        "call_id": 9,
+                        "remote_method": {
+                            "service_name": "yb.consensus.ConsensusService",
+                            "method_name": "UpdateConsensus"
+                        },
+                        "timeout_millis": 3000
+                    },
+                    "trace_buffer": "1016 10:00:00.500100 (+     0us) outbound_call.cc:100] Queued\n",
+                    "elapsed_millis": 200,
+                    "state": "SENT"
+                }
+            ],
+            "sending_bytes": 0
+        }
+    ]
+}
+        "#.to_string();
+        let mut result = AllRpcs::parse_rpcs(json, "", "");
+        result.keep_slowest_traces(2);
+        if let Rpc { inbound_connections, outbound_connections, .. } = result
+        {
+            let inbound_calls = inbound_connections.as_ref().unwrap()[0].calls_in_flight.as_ref().unwrap();
+            assert_eq!(inbound_calls[0].trace_buffer.as_ref().unwrap().lines().count(), 2);
+            assert!(inbound_calls[0].trace_buffer.as_ref().unwrap().contains("Handling call"));
+            assert!(inbound_calls[1].trace_buffer.is_none());
+            let outbound_calls = outbound_connections.as_ref().unwrap()[0].calls_in_flight.as_ref().unwrap();
+            assert!(outbound_calls[0].trace_buffer.as_ref().unwrap().contains("Queued"));
+        }
+        else
+        {
+            panic!("rpcz should parse as Rpc");
+        }
+    }
+
+    #[test]
+    fn unit_rpcs_group_by() {
+        let json = r#"
+{
+    "inbound_connections": [
+        {
+            "remote_ip": "192.168.66.82:51316",
+            "state": "OPEN",
+            "processed_call_count": 2,
+            "calls_in_flight": [
+                {
+                    "header": { "call_id": 7, "remote_method": { "service_name": "yb.tserver.TabletServerService", "method_name": "Write" }, "timeout_millis": 60000 },
+                    "elapsed_millis": 1500,
+                    "state": "SENT"
+                },
+                {
+                    "header": { "call_id": 8, "remote_method": { "service_name": "yb.tserver.TabletServerService", "method_name": "Write" }, "timeout_millis": 60000 },
+                    "elapsed_millis": 5,
+                    "state": "SENT"
+                }
+            ]
+        },
+        {
+            "remote_ip": "192.168.66.83:41000",
+            "state": "OPEN",
+            "processed_call_count": 10
+        }
+    ],
+    "outbound_connections": [
+        {
+            "remote_ip": "192.168.66.80:7100",
+            "state": "OPEN",
+            "processed_call_count": 3526,
+            "calls_in_flight": [
+                {
+                    "header": { "call_id": 9, "remote_method": { "service_name": "yb.consensus.ConsensusService", "method_name": "UpdateConsensus" }, "timeout_millis": 3000 },
+                    "elapsed_millis": 200,
+                    "state": "ON_OUTBOUND_QUEUE"
+                }
+            ],
+            "sending_bytes": 0
+        }
+    ]
+}
+        "#.to_string();
+        let mut rpcs = AllRpcs::parse_rpcs(json, "", "");
+        if let Rpc { ref mut hostname_port, .. } = rpcs
+        {
+            *hostname_port = Some("yb-1.local:9000".to_string());
+        }
+        let allrpcs = AllRpcs { rpcs: vec![rpcs] };
+        let hostname_filter = utility::set_regex(&None);
+        assert_eq!(allrpcs.group_by("method", &hostname_filter), vec![
+            ("yb.tserver.TabletServerService:Write".to_string(), 2, 1505),
+            ("yb.consensus.ConsensusService:UpdateConsensus".to_string(), 1, 200),
+        ]);
+        assert_eq!(allrpcs.group_by("caller", &hostname_filter), vec![
+            ("192.168.66.82:51316".to_string(), 2, 1505),
+            ("yb-1.local:9000".to_string(), 1, 200),
+        ]);
+        assert_eq!(allrpcs.group_by("state", &hostname_filter), vec![
+            ("SENT".to_string(), 2, 1505),
+            ("ON_OUTBOUND_QUEUE".to_string(), 1, 200),
+        ]);
+        assert!(allrpcs.group_by("method", &utility::set_regex(&Some("yb-2".to_string()))).is_empty());
+    }
+
     #[tokio::test]
     async fn integration_parse_rpcs_tserver() {
+        let config = &Config::default();
         let hostname = utility::get_hostname_tserver();
         let port = utility::get_port_tserver();
-        let allrpcs = AllRpcs::read_rpcs(&vec![&hostname], &vec![&port], 1_usize).await;
+        let allrpcs = AllRpcs::read_rpcs(&vec![&hostname], &vec![&port], 1_usize, &false, 0, config).await;
         for rpcs in allrpcs.rpcs {
             match rpcs {
                 Ysql { connections, .. } =>
@@ -1345,9 +1675,10 @@ mod tests {
 
     #[tokio::test]
     async fn integration_parse_rpcs_master() {
+        let config = &Config::default();
         let hostname = utility::get_hostname_master();
         let port = utility::get_port_master();
-        let allrpcs = AllRpcs::read_rpcs(&vec![&hostname], &vec![&port], 1_usize).await;
+        let allrpcs = AllRpcs::read_rpcs(&vec![&hostname], &vec![&port], 1_usize, &false, 0, config).await;
 
         for rpcs in allrpcs.rpcs {
             match rpcs {
@@ -1369,9 +1700,10 @@ mod tests {
 
     #[tokio::test]
     async fn integration_parse_rpcs_ysql() {
+        let config = &Config::default();
         let hostname = utility::get_hostname_ysql();
         let port = utility::get_port_ysql();
-        let allrpcs = AllRpcs::read_rpcs(&vec![&hostname], &vec![&port], 1_usize).await;
+        let allrpcs = AllRpcs::read_rpcs(&vec![&hostname], &vec![&port], 1_usize, &false, 0, config).await;
 
         for rpcs in allrpcs.rpcs {
             match rpcs {
@@ -9,6 +9,9 @@
 //! | YCQL         | port 12000   | [InboundConnection]                       |
 //! | YSQL         | port 13000   | [YsqlConnection]                          |
 //!
+//! With `--extra-data`, `/rpcz?include_traces=true` is read, and the traces of the `--rpc-trace-limit` slowest calls in flight
+//! of a server are kept in [RpcCallInProgressPB::trace_buffer], see [Rpcs::keep_slowest_traces].
+//!
 //! The rpc functionality is called from:
 //! - [crate::snapshot::perform_snapshot] -> [crate::rpcs::AllRpcs::perform_snapshot] (general snapshot, saves rpc data)
 //! - [crate] -> [print_rpcs] (print adhoc (live) or snapshot rpc info)
@@ -1,17 +1,21 @@
 //! Snapshot functions
 //!
 use log::*;
-use std::{env, fs, io::{stdin, stdout, Write}, path::Path, sync::Arc, time::Instant};
-use chrono::Local;
-use anyhow::{Context, Result};
+use std::{collections::{BTreeMap, HashSet}, env, fs, future::Future, io::{stdin, stdout, IsTerminal, Write}, path::Path, sync::{Arc, atomic::{AtomicI32, Ordering}}, time::{Duration, Instant}};
+use chrono::{DateTime, Local};
+use regex::Regex;
+use anyhow::{bail, Context, Result};
 use serde::{Deserialize, Serialize};
 use tokio::sync::Mutex;
+use arrow::{array::Array, record_batch::RecordBatch};
+use parquet::{arrow::{arrow_reader::ParquetRecordBatchReaderBuilder, ArrowWriter}, basic::Compression, file::properties::WriterProperties};
 use crate::{Opts, tablet_server_operations};
-use crate::{clocks, entities, gflags, isleader, loglines, masters, mems, memtrackers, metrics, node_exporter, pprof, rpcs, statements, threads, tablet_servers, utility, vars, versions, cluster_config, health_check, table_detail, tablet_detail, tasks, tablet_replication, drives};
-use crate::snapshot::Snapshot;
+use crate::{clocks, entities, gflags, isleader, loglines, masters, mems, memtrackers, metrics, node_exporter, pprof, rpcs, statements, threads, tablet_servers, utility, vars, versions, cluster_config, health_check, table_detail, tablet_detail, tasks, tablet_replication, drives, ysql_metrics, transactions, custom};
+use crate::snapshot::{AdhocDiffData, AdhocDiffType, ParquetDataType, Snapshot, SnapshotBundle, SnapshotManifest, SNAPSHOT_BUNDLE_FORMAT_VERSION, SNAPSHOT_FORMAT_VERSION};
+use crate::utility::Config;
 
 impl Snapshot {
-    /// This is a public function to:
+    /// This is a private function to, for the given timestamp and comment:
     /// - create the yb_stats.snapshots directory (if it exists, it does nothing).
     /// - open the yb_stats.snapshots/snapshot.index if it exists, and read it into a vec of Snapshot.
     /// - if it exists, get the highest snapshot number, otherwise snapshot_number remains 0.
@@ -19,49 +23,65 @@ impl Snapshot {
     /// - write the vec of Snapshot to yb_stats.snapshots/snapshot.index.
     /// - create the snapshot directory for the data as yb_stats.snapshots/<snapshot_number>.
     /// - return snapshot_number.
-    pub fn insert_new_snapshot_number(
-        snapshot_comment: &Option<String>
+    ///
+    /// A snapshot that is imported keeps the timestamp of the original snapshot, so a diff shows the original interval.
+    fn insert_snapshot_number(
+        timestamp: DateTime<Local>,
+        comment: String,
+    ) -> Result<i32>
+    {
+        let current_directory = env::current_dir()
+            .with_context(|| "Cannot evaluate current working directory" )?;
+        Snapshot::insert_snapshot_number_in(&current_directory.join("yb_stats.snapshots"), timestamp, comment)
+    }
+    /// Insert a snapshot in the yb_stats.snapshots directory, see [Snapshot::insert_snapshot_number].
+    fn insert_snapshot_number_in(
+        yb_stats_directory: &Path,
+        timestamp: DateTime<Local>,
+        comment: String,
     ) -> Result<i32>
     {
         info!("read_snapshot_number");
         let mut snapshots: Vec<Snapshot> = Vec::new();
         let mut snapshot_number: i32 = 0;
 
-        // setup yb_stats.snapshots if necessary
-        let current_directory = env::current_dir()
-            .with_context(|| "Cannot evaluate current working directory" )?;
-        let yb_stats_directory = current_directory.join("yb_stats.snapshots");
         // If the &yb_stats_directory does not exist, create it.
         // If it does exist already, nothing happens and continue.
-        fs::create_dir_all(&yb_stats_directory)
-            .with_context(|| format!("Cannot create directory: {}",&yb_stats_directory.clone().into_os_string().into_string().unwrap()))?;
+        fs::create_dir_all(yb_stats_directory)
+            .with_context(|| format!("Cannot create directory: {}", yb_stats_directory.display()))?;
         // If &yb_stats_directory/snapshot.index exists, read snapshots into snapshots vector,
         // and determine the highest snapshot number, add one and assign it to snapshot_number.
-        // If it doesn't exist, snapshot_number 0 is okay.
+        // If it doesn't exist, or all snapshots are deleted, snapshot_number 0 is okay.
         let snapshot_index = &yb_stats_directory.join("snapshot.index");
         if Path::new(&snapshot_index).exists() {
-            snapshots = Snapshot::read_snapshot_index()?;
-            let record_with_highest_snapshot_number = snapshots.iter().max_by_key(|k| k.number).unwrap();
-            snapshot_number = record_with_highest_snapshot_number.number + 1;
+            snapshots = Snapshot::read_snapshot_index_in(yb_stats_directory)?;
+            snapshot_number = snapshots.iter().map(|row| row.number).max().map_or(0, |number| number + 1);
         }
         // create a new snapshot vector and assign it the new_snapshot, and add it to the snapshots vector.
-        let new_snapshot: Snapshot = Snapshot { number: snapshot_number, timestamp: Local::now(), comment: snapshot_comment.clone().unwrap_or_default() };
+        let new_snapshot: Snapshot = Snapshot { number: snapshot_number, timestamp, comment, partial: false };
         snapshots.push(new_snapshot);
-        Snapshot::write_snapshot_index(snapshots)?;
+        Snapshot::write_snapshot_index_in(yb_stats_directory, snapshots)?;
         // Create the snapshot number directory in the &yb_stats_directory
         let current_snapshot_directory = &yb_stats_directory.join(snapshot_number.to_string());
         fs::create_dir_all(current_snapshot_directory)
             .with_context(|| format!("Cannot create directory: {}",&current_snapshot_directory.clone().into_os_string().into_string().unwrap()))?;
+        write_format_version(current_snapshot_directory, SNAPSHOT_FORMAT_VERSION)?;
         Ok(snapshot_number)
     }
     /// This is a private function to read the snapshots index file, and return a vector with the snapshots.
     fn read_snapshot_index(
     ) -> Result<Vec<Snapshot>>
     {
-        let mut snapshots: Vec<Snapshot> = Vec::new();
         let current_directory = env::current_dir()
             .with_context(|| "Cannot evaluate current working directory" )?;
-        let yb_stats_directory = current_directory.join("yb_stats.snapshots");
+        Snapshot::read_snapshot_index_in(&current_directory.join("yb_stats.snapshots"))
+    }
+    /// Read the snapshots index file of the yb_stats.snapshots directory, see [Snapshot::read_snapshot_index].
+    fn read_snapshot_index_in(
+        yb_stats_directory: &Path,
+    ) -> Result<Vec<Snapshot>>
+    {
+        let mut snapshots: Vec<Snapshot> = Vec::new();
         let snapshot_index = &yb_stats_directory.join("snapshot.index");
 
         let file = fs::File::open(snapshot_index)
@@ -76,14 +96,21 @@ impl Snapshot {
     }
     /// This is a private function to write the vector to the snapshots index file.
     /// The file gets truncated and overwritten upon write.
+    /// The index is written in the current layout, and therefore the current format version is written with it.
     fn write_snapshot_index(
         snapshots: Vec<Snapshot>
     ) -> Result<()>
     {
         let current_directory = env::current_dir()
             .with_context(|| "Cannot evaluate current working directory" )?;
-
-        let yb_stats_directory = current_directory.join("yb_stats.snapshots");
+        Snapshot::write_snapshot_index_in(&current_directory.join("yb_stats.snapshots"), snapshots)
+    }
+    /// Write the snapshots index file of the yb_stats.snapshots directory, see [Snapshot::write_snapshot_index].
+    fn write_snapshot_index_in(
+        yb_stats_directory: &Path,
+        snapshots: Vec<Snapshot>
+    ) -> Result<()>
+    {
         let snapshot_index = &yb_stats_directory.join("snapshot.index");
 
         // Open the snapshot.index file, but truncate it and write the new snapshots vector to it.
@@ -100,27 +127,48 @@ impl Snapshot {
         }
         writer.flush()
             .with_context(|| "Error flushing buffer")?;
+        write_format_version(yb_stats_directory, SNAPSHOT_FORMAT_VERSION)?;
         Ok(())
     }
     /// This is a public function that reads the snapshots file into a vector and print the contents of it.
     /// The main use is to display the current snapshots to the user.
     pub fn print(
+        config: &Config,
     ) -> Result<()>
     {
         let snapshots = Snapshot::read_snapshot_index()?;
         for row in &snapshots {
-            println!("{:>3} {:30} {:50}", row.number, row.timestamp, row.comment);
+            // a manifest that cannot be read is shown the same as no manifest; --snapshot-verify shows the error.
+            let manifest = SnapshotManifest::read(&row.number.to_string()).ok().flatten()
+                .map(|manifest| format!(" yb_stats {}, {} data types, {} errors", manifest.yb_stats_version, manifest.data_types.len(), manifest.errors.len()))
+                .unwrap_or_default();
+            writeln!(config.output(), "{:>3} {:30} {:50}{}{}", row.number, utility::format_timestamp(&row.timestamp, config), row.comment, if row.partial { " (partial)" } else { "" }, manifest)?;
         }
         Ok(())
     }
+    /// This is a public function to mark a snapshot as partial in the snapshot index, or remove the mark.
+    pub fn set_partial(
+        snapshot_number: i32,
+        partial: bool,
+    ) -> Result<()>
+    {
+        let mut snapshots = Snapshot::read_snapshot_index()?;
+        snapshots
+            .iter_mut()
+            .filter(|row| row.number == snapshot_number)
+            .for_each(|row| row.partial = partial);
+        Snapshot::write_snapshot_index(snapshots)
+    }
     /// This is a public function that validates begin and end provided values,
     /// and if these are not specified are requested interactively,
     /// after which the begin and end snapshot numbers and the struct with the begin snapshot are returned as record.
     ///
     /// If the begin or end value is provided (using the switches `-b`/`--begin` and `-e`/`--end`),
-    /// it will take that value and not ask for it.
+    /// it will take that value and not ask for it. If stdin is not a terminal, such as in a script,
+    /// both values must be provided, because there is nobody to ask.
     ///
-    /// Both begin and end snapshots are validated for their existence in the [Snapshot] vector.
+    /// Both begin and end snapshots are validated for their existence in the [Snapshot] vector,
+    /// see [validate_begin_end]. If begin is after end, these are swapped with a warning.
     /// Besides the begin and end snapshot values, the struct with the begin [Snapshot] is returned.
     /// The begin [Snapshot] struct is needed for the timestamp.
     pub fn read_begin_end_snapshot_from_user(
@@ -128,49 +176,101 @@ impl Snapshot {
         option_end: Option<i32>
     ) -> Result<(String, String, Snapshot)>
     {
-        let snapshots = Snapshot::read_snapshot_index()?;
-        let begin_snapshot= match option_begin {
+        let snapshots = Snapshot::read_snapshot_index()
+            .with_context(|| "No snapshots found in the current directory, create one with --snapshot")?;
+        if (option_begin.is_none() || option_end.is_none()) && !stdin().is_terminal()
+        {
+            bail!("A snapshot diff requires both --begin and --end, available snapshots: {}", format_snapshot_numbers(&snapshots));
+        }
+        let begin_snapshot = match option_begin {
             Some(nr) => nr,
-            None => {
-                print!("Enter begin snapshot: ");
-                let mut snap= String::new();
-                stdout().flush()?;
-                stdin().read_line(&mut snap).expect("Failed to read input.");
-                let snap: i32 = snap.trim().parse().expect("Invalid input");
-                snap
-            }
+            None => ask_snapshot_number("Enter begin snapshot: ")?,
         };
-        // begin_snapshot has to exists as row.number.
-        // if it does, assign to begin_snapshot_row, otherwise error out.
-        let begin_snapshot_row = snapshots.iter()
-            .find(|&row| row.number == begin_snapshot)
-            .with_context(|| format!("Unable to find begin snapshot number: {}", begin_snapshot))?;
-        // if option_end is filled out (Some), use it.
-        // if option_end is not filled out (None), read from stdin.
         let end_snapshot = match option_end {
             Some(nr) => nr,
-            None => {
-                print!("Enter end snapshot: ");
-                let mut snap = String::new();
-                stdout().flush()?;
-                stdin().read_line(&mut snap).expect("Failed to read input.");
-                let snap: i32 = snap.trim().parse().expect("Invalid input");
-                snap
-            }
+            None => ask_snapshot_number("Enter end snapshot: ")?,
         };
-        // end_snapshot has to exists as row.number, otherwise error out.
-        snapshots.iter()
-            .find(|&row| row.number == end_snapshot)
-            .with_context(|| format!("Unable to find end snapshot number: {}", end_snapshot))?;
+        let (begin_snapshot, end_snapshot) = validate_begin_end(&snapshots, begin_snapshot, end_snapshot)?;
+        let begin_snapshot_row = snapshots.iter()
+            .find(|&row| row.number == begin_snapshot)
+            .expect("the begin snapshot should be validated");
         // all information is found and collected, return it to the caller.
         Ok((begin_snapshot.to_string(), end_snapshot.to_string(), begin_snapshot_row.clone()))
     }
+    /// This is a public function that returns the begin and end snapshot numbers for `--last`:
+    /// the Nth snapshot from the last as begin, and the last snapshot as end.
+    pub fn read_last_begin_end(
+        last: usize,
+    ) -> Result<(i32, i32)>
+    {
+        let snapshots = Snapshot::read_snapshot_index()
+            .with_context(|| "No snapshots found in the current directory, create one with --snapshot")?;
+        last_begin_end(&snapshots, last)
+    }
+    /// This is a public function that validates a single snapshot number, and returns the struct of the snapshot.
+    pub fn read_snapshot(
+        snapshot_number: i32,
+    ) -> Result<Snapshot>
+    {
+        let snapshots = Snapshot::read_snapshot_index()
+            .with_context(|| "No snapshots found in the current directory, create one with --snapshot")?;
+        snapshots.iter()
+            .find(|&row| row.number == snapshot_number)
+            .cloned()
+            .with_context(|| format!("Snapshot {} does not exist, available snapshots: {}", snapshot_number, format_snapshot_numbers(&snapshots)))
+    }
+    /// This is a public function that prints the header of a diff report for stored snapshots.
+    ///
+    /// The header shows the number, timestamp and comment of the begin and end snapshot,
+    /// and the interval between both, so it's clear what is being diffed without having to look
+    /// at `--snapshot-list`.
+    pub fn print_diff_header(
+        begin_snapshot: &String,
+        end_snapshot: &String,
+        config: &Config,
+    ) -> Result<()>
+    {
+        // the summary of --summary-only has no header.
+        if config.summary_only
+        {
+            return Ok(());
+        }
+        let snapshots = Snapshot::read_snapshot_index()?;
+        let begin_snapshot_row = snapshots.iter()
+            .find(|row| row.number.to_string() == *begin_snapshot)
+            .with_context(|| format!("Unable to find begin snapshot number: {}", begin_snapshot))?;
+        let end_snapshot_row = snapshots.iter()
+            .find(|row| row.number.to_string() == *end_snapshot)
+            .with_context(|| format!("Unable to find end snapshot number: {}", end_snapshot))?;
+
+        writeln!(config.output(), "Begin snapshot: {:>3} {:30} {}", begin_snapshot_row.number, utility::format_timestamp(&begin_snapshot_row.timestamp, config), begin_snapshot_row.comment)?;
+        writeln!(config.output(), "End snapshot:   {:>3} {:30} {}", end_snapshot_row.number, utility::format_timestamp(&end_snapshot_row.timestamp, config), end_snapshot_row.comment)?;
+        writeln!(config.output(), "Interval:       {} seconds", utility::format_float((end_snapshot_row.timestamp - begin_snapshot_row.timestamp).num_milliseconds() as f64 / 1000_f64, 8, 3, config))?;
+        if !config.baseline_snapshots.is_empty()
+        {
+            writeln!(config.output(), "Baseline:       snapshots {} (metrics: gauges averaged, counters and histograms of snapshot {})", config.baseline_snapshots.iter().map(i32::to_string).collect::<Vec<_>>().join(","), begin_snapshot_row.number)?;
+        }
+        Ok(())
+    }
+}
+/// This function prints the header of a diff report for adhoc (in memory) snapshots.
+///
+/// There are no stored snapshots, so only the interval between the first and second snapshot is shown.
+pub fn print_adhoc_diff_header(
+    first_snapshot_time: &DateTime<Local>,
+    second_snapshot_time: &DateTime<Local>,
+    config: &Config,
+) -> Result<()>
+{
+    writeln!(config.output(), "Live adhoc diff, interval: {} seconds", utility::format_float((*second_snapshot_time - *first_snapshot_time).num_milliseconds() as f64 / 1000_f64, 8, 3, config))?;
+    Ok(())
 }
 /// This is the general yb_stat wide save_snapshot_json function.
 pub fn save_snapshot_json<T: Serialize>(
     snapshot_number: i32,
     filename: &str,
     vector: Vec<T>,
+    config: &Config,
 ) -> Result<()>
 {
     let current_directory = env::current_dir()?;
@@ -180,24 +280,464 @@ pub fn save_snapshot_json<T: Serialize>(
     fs::write(filepath, serde_json::to_string(&vector)
         .with_context(|| "Json serialization error")?
     ).with_context(|| format!("Error saving snapshot: {}", filepath.display()))?;
+    config.snapshot_row_counts.lock().unwrap().entry(snapshot_number).or_default().insert(filename.to_string(), vector.len());
+    Ok(())
+}
+
+/// Take the number of rows saved per data type for a snapshot, for the summary that is printed after a snapshot.
+fn take_snapshot_row_counts(
+    snapshot_number: i32,
+    config: &Config,
+) -> BTreeMap<String, usize>
+{
+    config.snapshot_row_counts.lock().unwrap()
+        .remove(&snapshot_number)
+        .unwrap_or_default()
+}
+
+/// Format the number of rows per data type as: "clocks: 3, entities: 311, ...".
+///
+/// A data type with zero rows, such as statements when YSQL statistics are not enabled, is shown as zero.
+fn format_row_counts(
+    row_counts: &BTreeMap<String, usize>,
+) -> String
+{
+    row_counts
+        .iter()
+        .map(|(data_type, rows)| format!("{}: {}", data_type, rows))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The file name of the manifest in a snapshot directory.
+const MANIFEST_FILE: &str = "manifest.json";
+
+impl SnapshotManifest {
+    /// Write the manifest into the snapshot directory.
+    fn write(
+        &self,
+        snapshot_number: i32,
+    ) -> Result<()>
+    {
+        let filepath = env::current_dir()?.join("yb_stats.snapshots").join(snapshot_number.to_string()).join(MANIFEST_FILE);
+        fs::write(&filepath, serde_json::to_string_pretty(self).with_context(|| "Json serialization error")?)
+            .with_context(|| format!("Error saving snapshot manifest: {}", filepath.display()))?;
+        Ok(())
+    }
+    /// Read the manifest of a snapshot, which is None for a snapshot without a manifest.
+    pub fn read(
+        snapshot_number: &str,
+    ) -> Result<Option<SnapshotManifest>>
+    {
+        let filepath = env::current_dir()?.join("yb_stats.snapshots").join(snapshot_number).join(MANIFEST_FILE);
+        if !filepath.exists()
+        {
+            return Ok(None);
+        }
+        let manifest = fs::read_to_string(&filepath)
+            .with_context(|| format!("Error reading snapshot manifest: {}", filepath.display()))?;
+        Ok(Some(serde_json::from_str(&manifest).with_context(|| format!("Error parsing snapshot manifest: {}", filepath.display()))?))
+    }
+}
+/// The file with the format version, see [SNAPSHOT_FORMAT_VERSION].
+const FORMAT_VERSION_FILE: &str = "format_version";
+
+/// A migration of the rows of a data type (the file name without .json) to the next format version.
+/// A migration works on the JSON data in memory, so a snapshot that cannot be written is migrated when it is read.
+type SnapshotMigration = fn(&str, &mut Vec<serde_json::Value>);
+
+/// The migrations of the layout of the snapshot data: the format version that is migrated from, a description,
+/// and the function that migrates the rows of a data type to the next version.
+/// A change of the layout, such as a renamed field or a field that needs a default, increases [SNAPSHOT_FORMAT_VERSION]
+/// and adds a migration here.
+///
+/// Version 1 is the first version with a format version. The data of version 0 has the same layout,
+/// only the snapshot index gets the partial column, so there is no migration of the data yet.
+const SNAPSHOT_MIGRATIONS: [(u32, &str, SnapshotMigration); 0] = [];
+
+/// Run the migrations from the format version of the data in order on the rows of a data type.
+/// Returns if a migration changed the rows.
+fn migrate_rows(
+    data_type: &str,
+    rows: &mut Vec<serde_json::Value>,
+    format_version: u32,
+    migrations: &[(u32, &str, SnapshotMigration)],
+) -> bool
+{
+    let mut migrated = false;
+    for (from_version, description, migration) in migrations.iter().filter(|(from_version, _, _)| *from_version >= format_version)
+    {
+        debug!("migrate {} from version {}: {}", data_type, from_version, description);
+        migration(data_type, rows);
+        migrated = true;
+    }
+    migrated
+}
+
+/// Read the format version in a directory, which is 0 for a directory without the format version file.
+fn read_format_version(
+    directory: &Path,
+) -> Result<u32>
+{
+    let filepath = directory.join(FORMAT_VERSION_FILE);
+    if !filepath.is_file()
+    {
+        return Ok(0);
+    }
+    let format_version = fs::read_to_string(&filepath)
+        .with_context(|| format!("Error reading format version: {}", filepath.display()))?;
+    format_version.trim().parse()
+        .with_context(|| format!("Invalid format version in {}: {}", filepath.display(), format_version.trim()))
+}
+
+/// Write the format version in a directory.
+fn write_format_version(
+    directory: &Path,
+    format_version: u32,
+) -> Result<()>
+{
+    let filepath = directory.join(FORMAT_VERSION_FILE);
+    fs::write(&filepath, format!("{}\n", format_version))
+        .with_context(|| format!("Error writing format version: {}", filepath.display()))
+}
+
+/// Read the format version of a snapshot directory, and refuse a snapshot of a newer version, which cannot be read reliably.
+fn read_snapshot_format_version(
+    snapshot_directory: &Path,
+) -> Result<u32>
+{
+    let format_version = read_format_version(snapshot_directory)?;
+    if format_version > SNAPSHOT_FORMAT_VERSION
+    {
+        bail!("Snapshot {} has format version {}, which is newer than the supported version {}, upgrade yb_stats to read it",
+            snapshot_directory.display(), format_version, SNAPSHOT_FORMAT_VERSION);
+    }
+    Ok(format_version)
+}
+
+/// Migrate a snapshot directory in place to the current format version: the data types that the migrations change are rewritten,
+/// and the current format version is written.
+/// Returns the format version it is migrated from, or None if it has the current version.
+fn migrate_snapshot_directory(
+    snapshot_directory: &Path,
+    migrations: &[(u32, &str, SnapshotMigration)],
+) -> Result<Option<u32>>
+{
+    let format_version = read_snapshot_format_version(snapshot_directory)?;
+    if format_version == SNAPSHOT_FORMAT_VERSION
+    {
+        return Ok(None);
+    }
+    for entry in fs::read_dir(snapshot_directory).with_context(|| format!("Error reading snapshot directory: {}", snapshot_directory.display()))?
+    {
+        let filepath = entry?.path();
+        let Some(data_type) = filepath.file_stem().and_then(|data_type| data_type.to_str()) else { continue };
+        if filepath.ends_with(MANIFEST_FILE)
+        {
+            continue;
+        }
+        if filepath.extension().is_some_and(|extension| extension == "json")
+        {
+            let mut rows: Vec<serde_json::Value> = serde_json::from_str(&fs::read_to_string(&filepath)
+                .with_context(|| format!("Error reading snapshot: {}", filepath.display()))?)
+                .with_context(|| format!("Json deserialization error: {}", filepath.display()))?;
+            if migrate_rows(data_type, &mut rows, format_version, migrations)
+            {
+                fs::write(&filepath, serde_json::to_string(&rows).with_context(|| "Json serialization error")?)
+                    .with_context(|| format!("Error saving snapshot: {}", filepath.display()))?;
+            }
+        }
+        else if filepath.extension().is_some_and(|extension| extension == "parquet")
+        {
+            let mut rows: Vec<serde_json::Value> = serde_json::from_str(&read_parquet_file(&filepath, data_type)?)
+                .with_context(|| format!("Json deserialization error: {}", filepath.display()))?;
+            if migrate_rows(data_type, &mut rows, format_version, migrations)
+            {
+                write_parquet_rows(&filepath, data_type, rows)?;
+            }
+        }
+    }
+    write_format_version(snapshot_directory, SNAPSHOT_FORMAT_VERSION)?;
+    Ok(Some(format_version))
+}
+
+/// Read the format version of the snapshot index, and refuse an index of a newer version.
+/// Returns None if there is no index.
+fn read_snapshot_index_format_version(
+    yb_stats_directory: &Path,
+) -> Result<Option<u32>>
+{
+    if !yb_stats_directory.join("snapshot.index").exists()
+    {
+        return Ok(None);
+    }
+    let format_version = read_format_version(yb_stats_directory)?;
+    if format_version > SNAPSHOT_FORMAT_VERSION
+    {
+        bail!("The snapshot index has format version {}, which is newer than the supported version {}, upgrade yb_stats to read it",
+            format_version, SNAPSHOT_FORMAT_VERSION);
+    }
+    Ok(Some(format_version))
+}
+
+/// Migrate the snapshot index to the current format version, which rewrites it in the current layout.
+/// Returns the format version it is migrated from, or None if it has the current version or there is no index.
+fn migrate_snapshot_index() -> Result<Option<u32>>
+{
+    let yb_stats_directory = env::current_dir()?.join("yb_stats.snapshots");
+    match read_snapshot_index_format_version(&yb_stats_directory)? {
+        Some(format_version) if format_version < SNAPSHOT_FORMAT_VERSION => {
+            Snapshot::write_snapshot_index_in(&yb_stats_directory, Snapshot::read_snapshot_index_in(&yb_stats_directory)?)?;
+            Ok(Some(format_version))
+        },
+        _ => Ok(None),
+    }
+}
+
+/// Check the format version of the snapshot index and of a snapshot before its data is read, with a warning for an older version.
+/// This is done once per run for the index, and once per snapshot, see [Config::snapshot_format_versions].
+/// Reading does not change the snapshots: the data of an older version is migrated in memory when it is read,
+/// `--snapshot-migrate-all` migrates the snapshots in place.
+/// Returns the format version of the data of the snapshot.
+fn snapshot_format_version_once(
+    yb_stats_directory: &Path,
+    snapshot_number: &str,
+    config: &Config,
+) -> Result<u32>
+{
+    if !config.snapshot_index_format_checked.swap(true, Ordering::Relaxed)
+    {
+        match read_snapshot_index_format_version(yb_stats_directory) {
+            Ok(Some(format_version)) if format_version < SNAPSHOT_FORMAT_VERSION => eprintln!("Warning: the snapshot index has format version {}, it is read as version {}, use --snapshot-migrate-all to migrate it.", format_version, SNAPSHOT_FORMAT_VERSION),
+            Ok(_) => {},
+            // the index of an older version is read in the current layout, the missing columns get their defaults.
+            Err(error) => warn!("Unable to check the format version of the snapshot index, it is read as is: {:#}", error),
+        }
+    }
+    let mut snapshot_format_versions = config.snapshot_format_versions.lock().unwrap();
+    if let Some(format_version) = snapshot_format_versions.get(snapshot_number)
+    {
+        return Ok(*format_version);
+    }
+    let snapshot_directory = yb_stats_directory.join(snapshot_number);
+    let format_version = if snapshot_directory.is_dir()
+    {
+        read_snapshot_format_version(&snapshot_directory)?
+    }
+    else
+    {
+        SNAPSHOT_FORMAT_VERSION
+    };
+    if format_version < SNAPSHOT_FORMAT_VERSION
+    {
+        eprintln!("Warning: snapshot {} has format version {}, it is migrated to version {} when read, use --snapshot-migrate-all to migrate it in place.", snapshot_number, format_version, SNAPSHOT_FORMAT_VERSION);
+    }
+    snapshot_format_versions.insert(snapshot_number.to_string(), format_version);
+    Ok(format_version)
+}
+
+/// Migrate the snapshot index and all snapshots to the current format version, for `--snapshot-migrate-all`.
+pub fn snapshot_migrate_all(
+    config: &Config,
+) -> Result<()>
+{
+    if let Some(format_version) = migrate_snapshot_index()?
+    {
+        writeln!(config.output(), "Snapshot index: migrated from format version {} to {}", format_version, SNAPSHOT_FORMAT_VERSION)?;
+    }
+    let snapshots = Snapshot::read_snapshot_index()
+        .with_context(|| "No snapshots found in the current directory, create one with --snapshot")?;
+    let mut migrated = 0;
+    for snapshot in &snapshots
+    {
+        let snapshot_directory = env::current_dir()?.join("yb_stats.snapshots").join(snapshot.number.to_string());
+        if !snapshot_directory.is_dir()
+        {
+            writeln!(config.output(), "Snapshot {}: directory not found, skipped", snapshot.number)?;
+            continue;
+        }
+        if let Some(format_version) = migrate_snapshot_directory(&snapshot_directory, &SNAPSHOT_MIGRATIONS)?
+        {
+            writeln!(config.output(), "Snapshot {}: migrated from format version {} to {}", snapshot.number, format_version, SNAPSHOT_FORMAT_VERSION)?;
+            migrated += 1;
+        }
+    }
+    writeln!(config.output(), "{} of {} snapshots migrated, all snapshots have format version {}.", migrated, snapshots.len(), SNAPSHOT_FORMAT_VERSION)?;
+    Ok(())
+}
+
+/// Parse the baseline snapshots of `--baseline-snapshots`, which requires at least two different snapshots.
+/// The snapshots are returned ordered by number.
+pub fn parse_baseline_snapshots(snapshot_numbers: &[i32]) -> Result<Vec<i32>>
+{
+    let mut snapshot_numbers = snapshot_numbers.to_vec();
+    snapshot_numbers.sort_unstable();
+    snapshot_numbers.dedup();
+    if snapshot_numbers.len() < 2
+    {
+        bail!("--baseline-snapshots requires at least two different snapshots, such as: 10,11,12");
+    }
+    Ok(snapshot_numbers)
+}
+
+/// This is the general yb_stat wide save_snapshot_parquet function, which saves a data type with `--format parquet`,
+/// see [PARQUET_DATA_TYPES]. The rows are the number of rows of the data type as [read_snapshot_json] reads these back,
+/// which is the row count of the snapshot summary and the manifest.
+pub fn save_snapshot_parquet(
+    snapshot_number: i32,
+    filename: &str,
+    record_batch: RecordBatch,
+    rows: usize,
+    config: &Config,
+) -> Result<()>
+{
+    let current_directory = env::current_dir()?;
+    let current_snapshot_directory = current_directory.join("yb_stats.snapshots").join(snapshot_number.to_string());
+
+    write_parquet_file(&current_snapshot_directory.join(format!("{}.{}", filename, "parquet")), record_batch)?;
+    config.snapshot_row_counts.lock().unwrap().entry(snapshot_number).or_default().insert(filename.to_string(), rows);
+    Ok(())
+}
+/// Write a record batch as a parquet file.
+fn write_parquet_file(
+    filepath: &Path,
+    record_batch: RecordBatch,
+) -> Result<()>
+{
+    let file = fs::File::create(filepath)
+        .with_context(|| format!("Cannot create file: {}", filepath.display()))?;
+    let properties = WriterProperties::builder().set_compression(Compression::SNAPPY).build();
+    let mut writer = ArrowWriter::try_new(file, record_batch.schema(), Some(properties))
+        .with_context(|| format!("Error creating parquet writer: {}", filepath.display()))?;
+    writer.write(&record_batch)
+        .with_context(|| format!("Error saving snapshot: {}", filepath.display()))?;
+    writer.close()
+        .with_context(|| format!("Error saving snapshot: {}", filepath.display()))?;
     Ok(())
 }
+/// Write the JSON rows of a data type as a parquet file, see [PARQUET_DATA_TYPES].
+fn write_parquet_rows(
+    filepath: &Path,
+    filename: &str,
+    rows: Vec<serde_json::Value>,
+) -> Result<()>
+{
+    let Some(parquet_data_type) = PARQUET_DATA_TYPES.iter().find(|parquet_data_type| parquet_data_type.filename == filename) else {
+        bail!("Data type {} cannot be saved as parquet: {}", filename, filepath.display());
+    };
+    write_parquet_file(filepath, (parquet_data_type.to_record_batch)(rows)?)
+}
+/// Read a parquet file of a data type as the JSON text of its rows, which is the same as the JSON file of the data type,
+/// so the parquet data is read the same as the JSON data, see [PARQUET_DATA_TYPES].
+fn read_parquet_file(
+    filepath: &Path,
+    filename: &str,
+) -> Result<String>
+{
+    let Some(parquet_data_type) = PARQUET_DATA_TYPES.iter().find(|parquet_data_type| parquet_data_type.filename == filename) else {
+        bail!("Data type {} cannot be read from parquet: {}", filename, filepath.display());
+    };
+    let file = fs::File::open(filepath)
+        .with_context(|| format!("Error reading snapshot: {}", filepath.display()))?;
+    let record_batches = ParquetRecordBatchReaderBuilder::try_new(file)
+        .and_then(|builder| builder.build())
+        .with_context(|| format!("Error creating parquet reader: {}", filepath.display()))?
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| format!("Error reading snapshot: {}", filepath.display()))?;
+    serde_json::to_string(&(parquet_data_type.from_record_batches)(&record_batches)?).with_context(|| "Json serialization error")
+}
+/// Convert the rows of a data type to JSON rows.
+fn to_json_rows<T: Serialize>(
+    rows: Vec<T>,
+) -> Result<Vec<serde_json::Value>>
+{
+    rows.into_iter()
+        .map(|row| serde_json::to_value(row).with_context(|| "Json serialization error"))
+        .collect()
+}
+/// Convert JSON rows to the rows of a data type.
+fn from_json_rows<T: for<'de> Deserialize<'de>>(
+    rows: Vec<serde_json::Value>,
+) -> Result<Vec<T>>
+{
+    rows.into_iter()
+        .map(|row| serde_json::from_value(row).with_context(|| "Json deserialization error"))
+        .collect()
+}
+/// The data types that are saved as parquet with `--format parquet`, the other data types are always saved as JSON.
+const PARQUET_DATA_TYPES: [ParquetDataType; 3] = [
+    ParquetDataType {
+        filename: "metrics",
+        to_record_batch: |rows| metrics::AllMetricEntity { metricentity: from_json_rows(rows)? }.record_batch(),
+        from_record_batches: |record_batches| to_json_rows(metrics::AllMetricEntity::from_record_batches(record_batches)?.metricentity),
+    },
+    ParquetDataType {
+        filename: "nodeexporter",
+        to_record_batch: |rows| node_exporter::AllNodeExporter { nodeexporter: from_json_rows(rows)? }.record_batch(),
+        from_record_batches: |record_batches| to_json_rows(node_exporter::AllNodeExporter::from_record_batches(record_batches)?.nodeexporter),
+    },
+    ParquetDataType {
+        filename: "statements",
+        to_record_batch: |rows| statements::AllStatements { statements: from_json_rows(rows)? }.record_batch(),
+        from_record_batches: |record_batches| to_json_rows(statements::AllStatements::from_record_batches(record_batches)?.statements),
+    },
+];
+/// Return the column of a record batch read from parquet, as the array type of the column.
+pub fn parquet_column<'a, A: Array + 'static>(
+    record_batch: &'a RecordBatch,
+    name: &str,
+) -> Result<&'a A>
+{
+    record_batch.column_by_name(name)
+        .and_then(|column| column.as_any().downcast_ref::<A>())
+        .with_context(|| format!("Parquet data has no column {} of the expected type", name))
+}
 /// This is the general yb_stat wide read_snapshot_json function.
 pub fn read_snapshot_json<T: for<'de> Deserialize<'de>>(
     snapshot_number: &String,
     filename: &str,
+    config: &Config,
 ) -> Result<Vec<T>>
 {
-    let current_directory = env::current_dir()?;
-    let current_snapshot_directory = current_directory.join("yb_stats.snapshots").join(snapshot_number);
-    let filepath = &current_snapshot_directory.join(format!("{}.{}", filename, "json"));
+    let yb_stats_directory = env::current_dir()?.join("yb_stats.snapshots");
+    let format_version = snapshot_format_version_once(&yb_stats_directory, snapshot_number, config)?;
+    read_snapshot_json_file(&yb_stats_directory.join(snapshot_number), filename, format_version, &SNAPSHOT_MIGRATIONS, config)
+}
+/// Read the data of a data type from a snapshot directory, of which the data has the given format version.
+/// Data of an older format version is migrated in memory with the migrations.
+fn read_snapshot_json_file<T: for<'de> Deserialize<'de>>(
+    snapshot_directory: &Path,
+    filename: &str,
+    format_version: u32,
+    migrations: &[(u32, &str, SnapshotMigration)],
+    config: &Config,
+) -> Result<Vec<T>>
+{
+    let filepath = &snapshot_directory.join(format!("{}.{}", filename, "json"));
+    let parquet_filepath = &snapshot_directory.join(format!("{}.{}", filename, "parquet"));
 
-    let vector = {
-        let read_from_file = fs::read_to_string(filepath)
-            .with_context(|| format!("Error reading snapshot: {}", &filepath.display()))?;
-        serde_json::from_str(&read_from_file).with_context(|| "Json deserialization error")?
+    // a data type that is saved with --format parquet is read as the JSON text of its rows.
+    let read_from_file = if !filepath.exists() && parquet_filepath.exists()
+    {
+        read_parquet_file(parquet_filepath, filename)?
+    }
+    else
+    {
+        fs::read_to_string(filepath)
+            .with_context(|| format!("Error reading snapshot: {}", &filepath.display()))?
     };
-    Ok(vector)
+    // with --anonymize, the hostnames are replaced in the data as read, the snapshot itself is not changed.
+    utility::learn_hostnames(&read_from_file, config);
+    let read_from_file = utility::anonymize_text(read_from_file, config);
+    if format_version == SNAPSHOT_FORMAT_VERSION
+    {
+        return serde_json::from_str(&read_from_file).with_context(|| "Json deserialization error");
+    }
+    let mut rows: Vec<serde_json::Value> = serde_json::from_str(&read_from_file).with_context(|| "Json deserialization error")?;
+    migrate_rows(filename, &mut rows, format_version, migrations);
+    rows.into_iter()
+        .map(|row| serde_json::from_value(row).with_context(|| "Json deserialization error"))
+        .collect()
 }
 /// The function to perform a general snapshot resulting in JSON files.
 pub async fn perform_snapshot(
@@ -205,12 +745,140 @@ pub async fn perform_snapshot(
     ports: Vec<&'static str>,
     parallel: usize,
     options: &Opts,
+    config: &Arc<Config>,
+) -> Result<()> {
+    // Warn for data that will be missing because no port has the role serving it, such as tablet server data with only --ports 7000.
+    if !options.silent && !options.summary_only
+    {
+        for (role, default_port, data) in utility::missing_port_roles(&ports, config)
+        {
+            eprintln!("Warning: no {} port in --ports (default port: {}), the snapshot will not contain {}.", role, default_port, data);
+        }
+    }
+
+    // The number of the snapshot that is in progress, or NO_SNAPSHOT.
+    let current_snapshot = Arc::new(AtomicI32::new(NO_SNAPSHOT));
+
+    // On an interrupt (Ctrl-C), no new http requests are done, but the requests in progress are finished,
+    // and the data that is read is saved, so the snapshot can be completed with --snapshot-retry.
+    // A second interrupt stops immediately.
+    // The handler runs in its own thread and runtime, because the snapshot tasks block the worker threads while reading.
+    let handler_current_snapshot = current_snapshot.clone();
+    let handler_config = config.clone();
+    std::thread::spawn(move || {
+        let Ok(runtime) = tokio::runtime::Builder::new_current_thread().enable_all().build() else { return };
+        runtime.block_on(async {
+            if tokio::signal::ctrl_c().await.is_ok()
+            {
+                eprintln!("Interrupted: finishing the requests in progress and saving the data that is read, interrupt again to stop immediately.");
+                utility::set_interrupted(&handler_config);
+                if tokio::signal::ctrl_c().await.is_ok()
+                {
+                    let snapshot_number = handler_current_snapshot.load(Ordering::Relaxed);
+                    if snapshot_number != NO_SNAPSHOT
+                    {
+                        Snapshot::set_partial(snapshot_number, true).ok();
+                        eprintln!("Stopped, snapshot {} is partial.", snapshot_number);
+                    }
+                    std::process::exit(130);
+                }
+            }
+        });
+    });
+
+    match options.snapshot_every {
+        None => create_snapshot(hosts, ports, parallel, options, &options.snapshot_comment, &current_snapshot, config).await,
+        Some(interval) => scheduled_snapshots(hosts, ports, parallel, options, interval, &current_snapshot, config).await,
+    }
+}
+
+/// The value of the current snapshot number when no snapshot is in progress.
+const NO_SNAPSHOT: i32 = -1;
+
+/// Take a snapshot every interval seconds with --snapshot-every, until --snapshot-count snapshots are taken, or until interrupted.
+///
+/// The interval is from the start of a snapshot to the start of the next; if a snapshot takes longer, the next starts immediately.
+/// Each snapshot gets the iteration as comment, after the --snapshot-comment if set.
+async fn scheduled_snapshots(
+    hosts: Vec<&'static str>,
+    ports: Vec<&'static str>,
+    parallel: usize,
+    options: &Opts,
+    interval: u64,
+    current_snapshot: &Arc<AtomicI32>,
+    config: &Arc<Config>,
+) -> Result<()>
+{
+    let start = Instant::now();
+    let mut iteration: u64 = 1;
+    loop
+    {
+        let comment = scheduled_snapshot_comment(&options.snapshot_comment, iteration, options.snapshot_count);
+        create_snapshot(hosts.clone(), ports.clone(), parallel, options, &Some(comment), current_snapshot, config).await?;
+        if options.snapshot_count.is_some_and(|count| iteration >= count) { break };
+
+        // wait for the next snapshot in small steps, so an interrupt stops waiting.
+        let next_snapshot = start + Duration::from_secs(interval * iteration);
+        while Instant::now() < next_snapshot && !utility::interrupted(config)
+        {
+            tokio::time::sleep(next_snapshot.saturating_duration_since(Instant::now()).min(Duration::from_millis(100))).await;
+        }
+        if utility::interrupted(config)
+        {
+            if !options.silent
+            {
+                writeln!(config.output(), "Interrupted, stopped after {} snapshots.", iteration)?;
+            }
+            break;
+        }
+        iteration += 1;
+    }
+    Ok(())
+}
+
+/// The comment of a snapshot taken with --snapshot-every, such as: "load test (snapshot 2/10)".
+fn scheduled_snapshot_comment(
+    snapshot_comment: &Option<String>,
+    iteration: u64,
+    snapshot_count: Option<u64>,
+) -> String
+{
+    let iteration = match snapshot_count {
+        Some(count) => format!("snapshot {}/{}", iteration, count),
+        None => format!("snapshot {}", iteration),
+    };
+    match snapshot_comment {
+        Some(comment) => format!("{} ({})", comment, iteration),
+        None => iteration,
+    }
+}
+
+/// Take a single snapshot with the given comment, and save the data in a new snapshot number.
+async fn create_snapshot(
+    hosts: Vec<&'static str>,
+    ports: Vec<&'static str>,
+    parallel: usize,
+    options: &Opts,
+    snapshot_comment: &Option<String>,
+    current_snapshot: &Arc<AtomicI32>,
+    config: &Arc<Config>,
 ) -> Result<()> {
     info!("begin snapshot");
     let timer = Instant::now();
 
-    let snapshot_number = Snapshot::insert_new_snapshot_number(&options.snapshot_comment)?;
+    let timestamp = Local::now();
+    let snapshot_number = Snapshot::insert_snapshot_number(timestamp, snapshot_comment.clone().unwrap_or_default())?;
     info!("using snapshot number: {}", snapshot_number);
+    current_snapshot.store(snapshot_number, Ordering::Relaxed);
+    // the requests that failed before this snapshot, such as for an earlier scheduled snapshot, are not part of it.
+    utility::take_failed_request_urls(config);
+    if options.keep_raw
+    {
+        let raw_directory = env::current_dir()?.join("yb_stats.snapshots").join(snapshot_number.to_string()).join("raw");
+        fs::create_dir_all(&raw_directory)
+            .with_context(|| format!("Error creating directory: {}", raw_directory.display()))?;
+        utility::set_raw_directory(Some(raw_directory), config);
+    }
 
     let arc_hosts = Arc::new(hosts);
     let arc_ports = Arc::new(ports);
@@ -220,198 +888,290 @@ pub async fn perform_snapshot(
 
     let arc_hosts_clone = arc_hosts.clone();
     let arc_ports_clone = arc_ports.clone();
+    let arc_config_clone = config.clone();
     let handle = tokio::spawn(async move {
-        metrics::AllMetricEntity::perform_snapshot(&arc_hosts_clone, &arc_ports_clone, snapshot_number, parallel).await.unwrap();
+        metrics::AllMetricEntity::perform_snapshot(&arc_hosts_clone, &arc_ports_clone, snapshot_number, parallel, &arc_config_clone).await.unwrap();
     });
     handles.push(handle);
 
     let arc_hosts_clone = arc_hosts.clone();
     let arc_ports_clone = arc_ports.clone();
+    let arc_config_clone = config.clone();
     let handle = tokio::spawn(async move {
-        statements::AllStatements::perform_snapshot(&arc_hosts_clone, &arc_ports_clone, snapshot_number, parallel).await.unwrap();
+        statements::AllStatements::perform_snapshot(&arc_hosts_clone, &arc_ports_clone, snapshot_number, parallel, &arc_config_clone).await.unwrap();
     });
     handles.push(handle);
 
     let arc_hosts_clone = arc_hosts.clone();
     let arc_ports_clone = arc_ports.clone();
+    let arc_config_clone = config.clone();
     let handle = tokio::spawn(async move {
-        node_exporter::AllNodeExporter::perform_snapshot(&arc_hosts_clone, &arc_ports_clone, snapshot_number, parallel).await.unwrap();
+        node_exporter::AllNodeExporter::perform_snapshot(&arc_hosts_clone, &arc_ports_clone, snapshot_number, parallel, &arc_config_clone).await.unwrap();
     });
     handles.push(handle);
 
     let arc_hosts_clone = arc_hosts.clone();
     let arc_ports_clone = arc_ports.clone();
+    let arc_config_clone = config.clone();
     let handle = tokio::spawn(async move {
-        isleader::AllIsLeader::perform_snapshot(&arc_hosts_clone, &arc_ports_clone, snapshot_number, parallel).await.unwrap();
+        isleader::AllIsLeader::perform_snapshot(&arc_hosts_clone, &arc_ports_clone, snapshot_number, parallel, &arc_config_clone).await.unwrap();
     });
     handles.push(handle);
 
     let arc_hosts_clone = arc_hosts.clone();
     let arc_ports_clone = arc_ports.clone();
+    let arc_config_clone = config.clone();
     let handle = tokio::spawn(async move {
-        entities::AllEntities::perform_snapshot(&arc_hosts_clone, &arc_ports_clone, snapshot_number, parallel).await.unwrap();
+        entities::AllEntities::perform_snapshot(&arc_hosts_clone, &arc_ports_clone, snapshot_number, parallel, &arc_config_clone).await.unwrap();
     });
     handles.push(handle);
 
     let arc_hosts_clone = arc_hosts.clone();
     let arc_ports_clone = arc_ports.clone();
+    let arc_config_clone = config.clone();
     let handle = tokio::spawn(async move {
-        masters::Masters::perform_snapshot(&arc_hosts_clone, &arc_ports_clone, snapshot_number, parallel).await.unwrap();
+        masters::Masters::perform_snapshot(&arc_hosts_clone, &arc_ports_clone, snapshot_number, parallel, &arc_config_clone).await.unwrap();
     });
     handles.push(handle);
 
     let arc_hosts_clone = arc_hosts.clone();
     let arc_ports_clone = arc_ports.clone();
+    let arc_config_clone = config.clone();
     let handle = tokio::spawn(async move {
-        tablet_servers::AllTabletServers::perform_snapshot(&arc_hosts_clone, &arc_ports_clone, snapshot_number, parallel).await.unwrap();
+        tablet_servers::AllTabletServers::perform_snapshot(&arc_hosts_clone, &arc_ports_clone, snapshot_number, parallel, &arc_config_clone).await.unwrap();
     });
     handles.push(handle);
 
     let arc_hosts_clone = arc_hosts.clone();
     let arc_ports_clone = arc_ports.clone();
+    let arc_config_clone = config.clone();
     let handle = tokio::spawn(async move {
-        vars::AllVars::perform_snapshot(&arc_hosts_clone, &arc_ports_clone, snapshot_number, parallel).await.unwrap();
+        vars::AllVars::perform_snapshot(&arc_hosts_clone, &arc_ports_clone, snapshot_number, parallel, &arc_config_clone).await.unwrap();
     });
     handles.push(handle);
 
     let arc_hosts_clone = arc_hosts.clone();
     let arc_ports_clone = arc_ports.clone();
+    let arc_config_clone = config.clone();
     let handle = tokio::spawn(async move {
-        versions::AllVersions::perform_snapshot(&arc_hosts_clone, &arc_ports_clone, snapshot_number, parallel).await.unwrap();
+        versions::AllVersions::perform_snapshot(&arc_hosts_clone, &arc_ports_clone, snapshot_number, parallel, &arc_config_clone).await.unwrap();
     });
     handles.push(handle);
 
     let arc_hosts_clone = arc_hosts.clone();
     let arc_ports_clone = arc_ports.clone();
+    let arc_config_clone = config.clone();
     let handle = tokio::spawn(async move {
-        gflags::AllGFlags::perform_snapshot(&arc_hosts_clone, &arc_ports_clone, snapshot_number, parallel).await.unwrap();
+        gflags::AllGFlags::perform_snapshot(&arc_hosts_clone, &arc_ports_clone, snapshot_number, parallel, &arc_config_clone).await.unwrap();
     });
     handles.push(handle);
 
     if !options.disable_threads {
         let arc_hosts_clone = arc_hosts.clone();
         let arc_ports_clone = arc_ports.clone();
+    let arc_config_clone = config.clone();
         let handle = tokio::spawn(async move {
-            threads::AllThreads::perform_snapshot(&arc_hosts_clone, &arc_ports_clone, snapshot_number, parallel).await.unwrap();
+            threads::AllThreads::perform_snapshot(&arc_hosts_clone, &arc_ports_clone, snapshot_number, parallel, &arc_config_clone).await.unwrap();
         });
         handles.push(handle);
     };
 
     let arc_hosts_clone = arc_hosts.clone();
     let arc_ports_clone = arc_ports.clone();
+    let arc_config_clone = config.clone();
     let handle = tokio::spawn(async move {
-        memtrackers::AllMemTrackers::perform_snapshot(&arc_hosts_clone, &arc_ports_clone, snapshot_number, parallel).await.unwrap();
+        memtrackers::AllMemTrackers::perform_snapshot(&arc_hosts_clone, &arc_ports_clone, snapshot_number, parallel, &arc_config_clone).await.unwrap();
     });
     handles.push(handle);
 
     let arc_hosts_clone = arc_hosts.clone();
     let arc_ports_clone = arc_ports.clone();
+    let arc_config_clone = config.clone();
     let handle = tokio::spawn(async move {
-        loglines::AllLogLines::perform_snapshot(&arc_hosts_clone, &arc_ports_clone, snapshot_number, parallel).await.unwrap();
+        loglines::AllLogLines::perform_snapshot(&arc_hosts_clone, &arc_ports_clone, snapshot_number, parallel, &arc_config_clone).await.unwrap();
     });
     handles.push(handle);
 
     let arc_hosts_clone = arc_hosts.clone();
     let arc_ports_clone = arc_ports.clone();
+    let arc_config_clone = config.clone();
+    let arc_extra_data_clone = arc_extra_data.clone();
+    let rpc_trace_limit = options.rpc_trace_limit;
     let handle = tokio::spawn(async move {
-        rpcs::AllRpcs::perform_snapshot(&arc_hosts_clone, &arc_ports_clone, snapshot_number, parallel).await.unwrap();
+        rpcs::AllRpcs::perform_snapshot(&arc_hosts_clone, &arc_ports_clone, snapshot_number, parallel, &arc_extra_data_clone, rpc_trace_limit, &arc_config_clone).await.unwrap();
     });
     handles.push(handle);
 
     let arc_hosts_clone = arc_hosts.clone();
     let arc_ports_clone = arc_ports.clone();
+    let arc_config_clone = config.clone();
     let handle = tokio::spawn(async move {
-        pprof::Pprof::perform_snapshot(&arc_hosts_clone, &arc_ports_clone, snapshot_number, parallel).await.unwrap();
+        pprof::Pprof::perform_snapshot(&arc_hosts_clone, &arc_ports_clone, snapshot_number, parallel, &arc_config_clone).await.unwrap();
     });
     handles.push(handle);
 
     let arc_hosts_clone = arc_hosts.clone();
     let arc_ports_clone = arc_ports.clone();
+    let arc_config_clone = config.clone();
     let handle = tokio::spawn(async move {
-        mems::Mems::perform_snapshot(&arc_hosts_clone, &arc_ports_clone, snapshot_number, parallel).await.unwrap();
+        mems::Mems::perform_snapshot(&arc_hosts_clone, &arc_ports_clone, snapshot_number, parallel, &arc_config_clone).await.unwrap();
     });
     handles.push(handle);
 
     let arc_hosts_clone = arc_hosts.clone();
     let arc_ports_clone = arc_ports.clone();
+    let arc_config_clone = config.clone();
     let handle = tokio::spawn(async move {
-        clocks::AllClocks::perform_snapshot(&arc_hosts_clone, &arc_ports_clone, snapshot_number, parallel).await.unwrap();
+        clocks::AllClocks::perform_snapshot(&arc_hosts_clone, &arc_ports_clone, snapshot_number, parallel, &arc_config_clone).await.unwrap();
     });
     handles.push(handle);
 
     let arc_hosts_clone = arc_hosts.clone();
     let arc_ports_clone = arc_ports.clone();
+    let arc_config_clone = config.clone();
     let handle = tokio::spawn(async move {
-        cluster_config::AllSysClusterConfigEntryPB::perform_snapshot(&arc_hosts_clone, &arc_ports_clone, snapshot_number, parallel).await.unwrap();
+        cluster_config::AllSysClusterConfigEntryPB::perform_snapshot(&arc_hosts_clone, &arc_ports_clone, snapshot_number, parallel, &arc_config_clone).await.unwrap();
     });
     handles.push(handle);
 
     let arc_hosts_clone = arc_hosts.clone();
     let arc_ports_clone = arc_ports.clone();
+    let arc_config_clone = config.clone();
     let handle = tokio::spawn(async move {
-        health_check::AllHealthCheck::perform_snapshot(&arc_hosts_clone, &arc_ports_clone, snapshot_number, parallel).await.unwrap();
+        health_check::AllHealthCheck::perform_snapshot(&arc_hosts_clone, &arc_ports_clone, snapshot_number, parallel, &arc_config_clone).await.unwrap();
     });
     handles.push(handle);
 
     let arc_hosts_clone = arc_hosts.clone();
     let arc_ports_clone = arc_ports.clone();
+    let arc_config_clone = config.clone();
     let arc_extra_data_clone = arc_extra_data.clone();
     let handle = tokio::spawn(async move {
-        table_detail::AllTables::perform_snapshot(&arc_hosts_clone, &arc_ports_clone, snapshot_number, parallel, &arc_extra_data_clone).await.unwrap();
+        table_detail::AllTables::perform_snapshot(&arc_hosts_clone, &arc_ports_clone, snapshot_number, parallel, &arc_extra_data_clone, &arc_config_clone).await.unwrap();
     });
     handles.push(handle);
 
     let arc_hosts_clone = arc_hosts.clone();
     let arc_ports_clone = arc_ports.clone();
+    let arc_config_clone = config.clone();
     let arc_extra_data_clone = arc_extra_data.clone();
     let handle = tokio::spawn(async move {
-        tablet_detail::AllTablets::perform_snapshot(&arc_hosts_clone, &arc_ports_clone, snapshot_number, parallel, &arc_extra_data_clone).await.unwrap();
+        tablet_detail::AllTablets::perform_snapshot(&arc_hosts_clone, &arc_ports_clone, snapshot_number, parallel, &arc_extra_data_clone, &arc_config_clone).await.unwrap();
+    });
+    handles.push(handle);
+
+    let arc_hosts_clone = arc_hosts.clone();
+    let arc_ports_clone = arc_ports.clone();
+    let arc_config_clone = config.clone();
+    let handle = tokio::spawn(async move {
+        tasks::AllTasks::perform_snapshot(&arc_hosts_clone, &arc_ports_clone, snapshot_number, parallel, &arc_config_clone).await.unwrap();
+    });
+    handles.push(handle);
+
+    let arc_hosts_clone = arc_hosts.clone();
+    let arc_ports_clone = arc_ports.clone();
+    let arc_config_clone = config.clone();
+    let handle = tokio::spawn(async move {
+        tablet_replication::AllTabletReplication::perform_snapshot(&arc_hosts_clone, &arc_ports_clone, snapshot_number, parallel, &arc_config_clone).await.unwrap();
+    });
+    handles.push(handle);
+
+    let arc_hosts_clone = arc_hosts.clone();
+    let arc_ports_clone = arc_ports.clone();
+    let arc_config_clone = config.clone();
+    let handle = tokio::spawn(async move {
+        tablet_server_operations::AllOperations::perform_snapshot(&arc_hosts_clone, &arc_ports_clone, snapshot_number, parallel, &arc_config_clone).await.unwrap();
     });
     handles.push(handle);
 
     let arc_hosts_clone = arc_hosts.clone();
     let arc_ports_clone = arc_ports.clone();
+    let arc_config_clone = config.clone();
     let handle = tokio::spawn(async move {
-        tasks::AllTasks::perform_snapshot(&arc_hosts_clone, &arc_ports_clone, snapshot_number, parallel).await.unwrap();
+        tablet_server_operations::AllCompactionQueues::perform_snapshot(&arc_hosts_clone, &arc_ports_clone, snapshot_number, parallel, &arc_config_clone).await.unwrap();
     });
     handles.push(handle);
 
     let arc_hosts_clone = arc_hosts.clone();
     let arc_ports_clone = arc_ports.clone();
+    let arc_config_clone = config.clone();
     let handle = tokio::spawn(async move {
-        tablet_replication::AllTabletReplication::perform_snapshot(&arc_hosts_clone, &arc_ports_clone, snapshot_number, parallel).await.unwrap();
+        drives::AllDrives::perform_snapshot(&arc_hosts_clone, &arc_ports_clone, snapshot_number, parallel, &arc_config_clone).await.unwrap();
     });
     handles.push(handle);
 
     let arc_hosts_clone = arc_hosts.clone();
     let arc_ports_clone = arc_ports.clone();
+    let arc_config_clone = config.clone();
     let handle = tokio::spawn(async move {
-        tablet_server_operations::AllOperations::perform_snapshot(&arc_hosts_clone, &arc_ports_clone, snapshot_number, parallel).await.unwrap();
+        ysql_metrics::AllYsqlMetrics::perform_snapshot(&arc_hosts_clone, &arc_ports_clone, snapshot_number, parallel, &arc_config_clone).await.unwrap();
     });
     handles.push(handle);
 
     let arc_hosts_clone = arc_hosts.clone();
     let arc_ports_clone = arc_ports.clone();
+    let arc_config_clone = config.clone();
+    let arc_extra_data_clone = arc_extra_data.clone();
     let handle = tokio::spawn(async move {
-        drives::AllDrives::perform_snapshot(&arc_hosts_clone, &arc_ports_clone, snapshot_number, parallel).await.unwrap();
+        transactions::AllTransactions::perform_snapshot(&arc_hosts_clone, &arc_ports_clone, snapshot_number, parallel, &arc_extra_data_clone, &arc_config_clone).await.unwrap();
     });
     handles.push(handle);
 
+    let custom_endpoints = custom::parse_custom_endpoints(&options.custom_endpoint)?;
+    if !custom_endpoints.is_empty()
+    {
+        let arc_hosts_clone = arc_hosts.clone();
+        let arc_ports_clone = arc_ports.clone();
+    let arc_config_clone = config.clone();
+        let handle = tokio::spawn(async move {
+            custom::AllCustomResponses::perform_snapshot(&arc_hosts_clone, &arc_ports_clone, snapshot_number, parallel, &custom_endpoints, &arc_config_clone).await.unwrap();
+        });
+        handles.push(handle);
+    };
+
     for handle in handles {
         handle.await.unwrap();
     }
+    utility::set_raw_directory(None, config);
+
+    let row_counts = take_snapshot_row_counts(snapshot_number, config);
+    SnapshotManifest {
+        yb_stats_version: env!("CARGO_PKG_VERSION").to_string(),
+        timestamp,
+        hosts: arc_hosts.iter().map(|host| host.to_string()).collect(),
+        ports: arc_ports.iter().map(|port| port.to_string()).collect(),
+        parallel,
+        extra_data: options.extra_data,
+        data_types: row_counts.clone(),
+        errors: utility::take_failed_request_urls(config),
+    }.write(snapshot_number)?;
+
+    if utility::interrupted(config)
+    {
+        Snapshot::set_partial(snapshot_number, true)?;
+        bail!("Snapshot {} is interrupted, and is saved as partial. Use --snapshot-retry {} to add the missing data.", snapshot_number, snapshot_number);
+    }
 
-    if !options.silent {
-        println!("snapshot number {}", snapshot_number);
+    if options.summary_only {
+        let summary = format!("snapshot {} created: {} hosts, {} errors, {:.1}s", snapshot_number, arc_hosts.len(), utility::failed_requests(config), timer.elapsed().as_secs_f64());
+        if utility::failed_requests(config) > 0 {
+            bail!("{}", summary);
+        }
+        writeln!(config.output(), "{}", summary)?;
+    } else if !options.silent {
+        writeln!(config.output(), "snapshot number {}", snapshot_number)?;
+        writeln!(config.output(), "{}", format_row_counts(&row_counts))?;
     }
 
-    info!("end snapshot: {:?}", timer.elapsed());
+    current_snapshot.store(NO_SNAPSHOT, Ordering::Relaxed);
+    info!("end snapshot: {:?}, requests skipped for data types not served by the port role: {}", timer.elapsed(), utility::skipped_requests(config));
     Ok(())
 }
 
 /// This function shows the difference report for the snapshot data that allows to show a difference:
 /// - metrics (value, coarse_histogram/countsum, ysql/countsumrows)
 /// - statements (ysql)
+/// - ysql metrics (connections and statement rates of the YSQL webservers)
 /// - node_exporter
 /// - entities (read via master leader)
 /// - masters (read via master leader)
@@ -421,46 +1181,70 @@ pub async fn perform_snapshot(
 /// - health check (read via master leader)
 pub async fn snapshot_diff(
     options: &Opts,
+    config: &Config,
 ) -> Result<()>
 {
     info!("snapshot diff");
     if options.begin.is_none() || options.end.is_none() {
-        Snapshot::print()?;
+        Snapshot::print(config)?;
     }
     if options.snapshot_list { return Ok(()) };
 
     let hostname_filter = utility::set_regex(&options.hostname_match);
     let stat_name_filter = utility::set_regex(&options.stat_name_match);
     let table_name_filter = utility::set_regex(&options.table_name_match);
+    let computes = metrics::parse_computes(&options.compute)?;
 
     let (begin_snapshot, end_snapshot, begin_snapshot_row) = Snapshot::read_begin_end_snapshot_from_user(options.begin, options.end)?;
+    Snapshot::print_diff_header(&begin_snapshot, &end_snapshot, config)?;
 
-    let metrics_diff = metrics::MetricEntityDiff::snapshot_diff(&begin_snapshot, &end_snapshot, &begin_snapshot_row.timestamp, &options.details_enable)?;
-    metrics_diff.print(&hostname_filter, &stat_name_filter, &table_name_filter, &options.details_enable, &options.gauges_enable).await;
+    let metrics_details_enable = options.details_enable || options.rollup_table;
+    let metric_renames = metrics::read_metric_renames(&options.metric_rename_file)?;
+    let metrics_diff = metrics::MetricEntityDiff::snapshot_diff(&begin_snapshot, &end_snapshot, &begin_snapshot_row.timestamp, &metrics_details_enable, &options.rollup_table, &metric_renames, config)?;
+    metrics_diff.print_renamed_metrics(config)?;
+    let mut changes = metrics_diff.print(&hostname_filter, &stat_name_filter, &table_name_filter, &metrics_details_enable, &options.gauges_enable, &options.sort_by_pct, &options.metric_type, &options.diff_context, &options.highlight_outliers.then_some(options.outlier_sigma), &computes, &metrics::MetricDiffThreshold { delta: options.diff_threshold, pct: options.pct_threshold }, config).await?;
 
-    let statements_diff = statements::StatementsDiff::snapshot_diff(&begin_snapshot, &end_snapshot, &begin_snapshot_row.timestamp)?;
-    statements_diff.print(&hostname_filter, options.sql_length).await;
+    let statements_diff = statements::StatementsDiff::snapshot_diff(&begin_snapshot, &end_snapshot, &begin_snapshot_row.timestamp, config)?;
+    changes += statements_diff.print(&hostname_filter, options.sql_length, config).await?;
 
-    let nodeexporter_diff = node_exporter::NodeExporterDiff::snapshot_diff(&begin_snapshot, &end_snapshot, &begin_snapshot_row.timestamp)?;
-    nodeexporter_diff.print(&hostname_filter, &stat_name_filter, &options.gauges_enable, &options.details_enable);
+    // snapshots taken with an older version of yb_stats do not contain the ysql metrics.
+    if snapshot_data_types(&begin_snapshot)?.iter().any(|data_type| data_type == "ysql_metrics")
+    {
+        let ysqlmetrics_diff = ysql_metrics::YsqlMetricsDiff::snapshot_diff(&begin_snapshot, &end_snapshot, config)?;
+        changes += ysqlmetrics_diff.print(&hostname_filter, config)?;
+    }
+
+    // snapshots taken with an older version of yb_stats do not contain the compaction queues.
+    if snapshot_data_types(&begin_snapshot)?.iter().any(|data_type| data_type == "compaction_queues")
+    {
+        let compactionqueues_diff = tablet_server_operations::CompactionQueuesDiff::snapshot_diff(&begin_snapshot, &end_snapshot, config)?;
+        changes += compactionqueues_diff.print(&hostname_filter, config)?;
+    }
+
+    let nodeexporter_diff = node_exporter::NodeExporterDiff::snapshot_diff(&begin_snapshot, &end_snapshot, &begin_snapshot_row.timestamp, config)?;
+    changes += nodeexporter_diff.print(&hostname_filter, &stat_name_filter, &options.gauges_enable, &options.details_enable, config)?;
 
-    let entities_diff = entities::EntitiesDiff::snapshot_diff(&begin_snapshot, &end_snapshot)?;
-    entities_diff.print();
+    let entities_diff = entities::EntitiesDiff::snapshot_diff(&begin_snapshot, &end_snapshot, config)?;
+    changes += entities_diff.print(config)?;
 
-    let masters_diff = masters::MastersDiff::snapshot_diff(&begin_snapshot, &end_snapshot)?;
-    masters_diff.print();
+    let masters_diff = masters::MastersDiff::snapshot_diff(&begin_snapshot, &end_snapshot, config)?;
+    changes += masters_diff.print(config)?;
 
-    let tabletservers_diff = tablet_servers::TabletServersDiff::snapshot_diff(&begin_snapshot, &end_snapshot)?;
-    tabletservers_diff.print();
+    let tabletservers_diff = tablet_servers::TabletServersDiff::snapshot_diff(&begin_snapshot, &end_snapshot, config)?;
+    changes += tabletservers_diff.print(config)?;
 
-    let vars_diff = vars::VarsDiff::snapshot_diff(&begin_snapshot, &end_snapshot)?;
-    vars_diff.print();
+    let vars_diff = vars::VarsDiff::snapshot_diff(&begin_snapshot, &end_snapshot, config)?;
+    changes += vars_diff.print(config)?;
 
-    let versions_diff = versions::VersionsDiff::snapshot_diff(&begin_snapshot, &end_snapshot)?;
-    versions_diff.print(&hostname_filter);
+    let versions_diff = versions::VersionsDiff::snapshot_diff(&begin_snapshot, &end_snapshot, config)?;
+    changes += versions_diff.print(&hostname_filter, config)?;
 
-    let healthcheck_diff = health_check::HealthCheckDiff::snapshot_diff(&begin_snapshot, &end_snapshot)?;
-    healthcheck_diff.print();
+    let healthcheck_diff = health_check::HealthCheckDiff::snapshot_diff(&begin_snapshot, &end_snapshot, config)?;
+    changes += healthcheck_diff.print(config)?;
+
+    let clusterconfig_diff = cluster_config::ClusterConfigDiff::snapshot_diff(&begin_snapshot, &end_snapshot, config)?;
+    changes += clusterconfig_diff.print(config)?;
+    utility::add_diff_changes(changes, config);
 
     Ok(())
 }
@@ -473,344 +1257,930 @@ pub async fn snapshot_diff(
 /// - vars
 /// - versions
 /// - health check (read via master leader)
+/// - cluster config blacklists (read via master leader)
 ///
 /// The purpose of this function is to quickly determine significant changes for a cluster, not to
 /// look into performance.
 pub async fn snapshot_nonmetrics_diff(
     options: &Opts,
+    config: &Config,
 ) -> Result<()>
 {
     info!("snapshot diff");
     if options.begin.is_none() || options.end.is_none() {
-        Snapshot::print()?;
+        Snapshot::print(config)?;
     }
     if options.snapshot_list { return Ok(()) };
 
     let hostname_filter = utility::set_regex(&options.hostname_match);
 
     let (begin_snapshot, end_snapshot, _) = Snapshot::read_begin_end_snapshot_from_user(options.begin, options.end)?;
+    Snapshot::print_diff_header(&begin_snapshot, &end_snapshot, config)?;
+
+    let entities_diff = entities::EntitiesDiff::snapshot_diff(&begin_snapshot, &end_snapshot, config)?;
+    let mut changes = entities_diff.print(config)?;
 
-    let entities_diff = entities::EntitiesDiff::snapshot_diff(&begin_snapshot, &end_snapshot)?;
-    entities_diff.print();
+    let masters_diff = masters::MastersDiff::snapshot_diff(&begin_snapshot, &end_snapshot, config)?;
+    changes += masters_diff.print(config)?;
 
-    let masters_diff = masters::MastersDiff::snapshot_diff(&begin_snapshot, &end_snapshot)?;
-    masters_diff.print();
+    let tabletservers_diff = tablet_servers::TabletServersDiff::snapshot_diff(&begin_snapshot, &end_snapshot, config)?;
+    changes += tabletservers_diff.print(config)?;
 
-    let tabletservers_diff = tablet_servers::TabletServersDiff::snapshot_diff(&begin_snapshot, &end_snapshot)?;
-    tabletservers_diff.print();
+    let vars_diff = vars::VarsDiff::snapshot_diff(&begin_snapshot, &end_snapshot, config)?;
+    changes += vars_diff.print(config)?;
 
-    let vars_diff = vars::VarsDiff::snapshot_diff(&begin_snapshot, &end_snapshot)?;
-    vars_diff.print();
+    let versions_diff = versions::VersionsDiff::snapshot_diff(&begin_snapshot, &end_snapshot, config)?;
+    changes += versions_diff.print(&hostname_filter, config)?;
 
-    let versions_diff = versions::VersionsDiff::snapshot_diff(&begin_snapshot, &end_snapshot)?;
-    versions_diff.print(&hostname_filter);
+    let healthcheck_diff = health_check::HealthCheckDiff::snapshot_diff(&begin_snapshot, &end_snapshot, config)?;
+    changes += healthcheck_diff.print(config)?;
 
-    let healthcheck_diff = health_check::HealthCheckDiff::snapshot_diff(&begin_snapshot, &end_snapshot)?;
-    healthcheck_diff.print();
+    let clusterconfig_diff = cluster_config::ClusterConfigDiff::snapshot_diff(&begin_snapshot, &end_snapshot, config)?;
+    changes += clusterconfig_diff.print(config)?;
+    utility::add_diff_changes(changes, config);
 
     Ok(())
 }
 
-/// Take "adhoc" (in memory) snapshots of metrics only:
-/// - metrics (value, coarse_histogram/countsum, ysql/countsumrows)
-///
-/// The idea here to reduce output when it's know lots of 'entities'/objects are created, or other
-/// changed would give too much output to be useful.
-pub async fn adhoc_metrics_diff(
-    hosts: Vec<&'static str>,
-    ports: Vec<&'static str>,
-    parallel: usize,
+/// The function to show the changes of the gflags, vars and cluster config between a begin and end snapshot
+/// in a unified diff format per host.
+pub async fn text_diff(
     options: &Opts,
+    config: &Config,
 ) -> Result<()>
 {
-    info!("ad-hoc metrics diff first snapshot begin");
-    let timer = Instant::now();
-
-    let stat_name_filter = utility::set_regex(&options.stat_name_match);
-    let hostname_filter = utility::set_regex(&options.hostname_match);
-    let table_name_filter = utility::set_regex(&options.table_name_match);
-
-    let first_snapshot_time = Local::now();
-
-    let metrics = Arc::new(Mutex::new(metrics::MetricEntityDiff::new()));
-
-    let hosts = Arc::new(hosts);
-    let ports = Arc::new(ports);
-
-    let mut handles = vec![];
-
-    let clone_metrics = metrics.clone();
-    let clone_hosts = hosts.clone();
-    let clone_ports = ports.clone();
-    let details_enable = options.details_enable;
-
-    let handle = tokio::spawn(async move {
-        clone_metrics.lock().await.adhoc_read_first_snapshot(&clone_hosts, &clone_ports, parallel, details_enable).await;
-    });
-    handles.push(handle);
-
-    for handle in handles {
-        handle.await.unwrap();
-    }
-    info!("ad-hoc metrics diff first snapshot end: {:?}", timer.elapsed());
-
-    println!("Begin ad-hoc in-memory snapshot created, press enter to create end snapshot for difference calculation.");
-    let mut input = String::new();
-    stdin().read_line(&mut input).expect("failed");
-
-    info!("ad-hoc metrics diff second snapshot begin");
-    let timer = Instant::now();
-
-    let second_snapshot_time = Local::now();
-
-    let mut handles = vec![];
-
-    let clone_metrics = metrics.clone();
-    let clone_hosts = hosts.clone();
-    let clone_ports = ports.clone();
-    let details_enable = options.details_enable;
-
-    let handle = tokio::spawn(async move {
-        clone_metrics.lock().await.adhoc_read_second_snapshot(&clone_hosts, &clone_ports, parallel, &first_snapshot_time, details_enable).await;
-    });
-    handles.push(handle);
-
-    for handle in handles {
-        handle.await.unwrap();
+    info!("text diff");
+    if options.begin.is_none() || options.end.is_none() {
+        Snapshot::print(config)?;
     }
+    if options.snapshot_list { return Ok(()) };
 
-    info!("ad-hoc metrics diff second snapshot end: {:?}", timer.elapsed());
+    let hostname_filter = utility::set_regex(&options.hostname_match);
 
-    println!("Time between snapshots: {:8.3} seconds", (second_snapshot_time - first_snapshot_time).num_milliseconds() as f64 / 1000_f64);
-    metrics.lock().await.print(&hostname_filter, &stat_name_filter, &table_name_filter, &options.details_enable, &options.gauges_enable).await;
+    let (begin_snapshot, end_snapshot, _) = Snapshot::read_begin_end_snapshot_from_user(options.begin, options.end)?;
+    Snapshot::print_diff_header(&begin_snapshot, &end_snapshot, config)?;
+
+    let mut first_gflags = gflags::AllGFlags::new();
+    first_gflags.gflags = read_snapshot_json_or_empty(&begin_snapshot, "gflags", config);
+    let mut second_gflags = gflags::AllGFlags::new();
+    second_gflags.gflags = read_snapshot_json_or_empty(&end_snapshot, "gflags", config);
+    let mut changes = utility::print_text_diff("gflags", &begin_snapshot, &end_snapshot, &first_gflags.text_per_host(), &second_gflags.text_per_host(), &hostname_filter, config)?;
+
+    let mut first_vars = vars::AllVars::new();
+    first_vars.vars = read_snapshot_json_or_empty(&begin_snapshot, "vars", config);
+    let mut second_vars = vars::AllVars::new();
+    second_vars.vars = read_snapshot_json_or_empty(&end_snapshot, "vars", config);
+    changes += utility::print_text_diff("vars", &begin_snapshot, &end_snapshot, &first_vars.text_per_host(), &second_vars.text_per_host(), &hostname_filter, config)?;
+
+    let mut first_cluster_config = cluster_config::AllSysClusterConfigEntryPB::new();
+    first_cluster_config.sysclusterconfigentrypb = read_snapshot_json_or_empty(&begin_snapshot, "cluster-config", config);
+    let mut second_cluster_config = cluster_config::AllSysClusterConfigEntryPB::new();
+    second_cluster_config.sysclusterconfigentrypb = read_snapshot_json_or_empty(&end_snapshot, "cluster-config", config);
+    changes += utility::print_text_diff("cluster-config", &begin_snapshot, &end_snapshot, &first_cluster_config.text_per_host()?, &second_cluster_config.text_per_host()?, &hostname_filter, config)?;
+    utility::add_diff_changes(changes, config);
 
     Ok(())
 }
 
-/// Take "adhoc" (in memory) snapshots of node_exporter only:
+/// Read a data type of a snapshot, or no rows if it can not be read, such as a data type that a snapshot of an older version does not have.
+fn read_snapshot_json_or_empty<T: for<'de> Deserialize<'de>>(
+    snapshot_number: &String,
+    data_type: &str,
+    config: &Config,
+) -> Vec<T>
+{
+    read_snapshot_json(snapshot_number, data_type, config).unwrap_or_else(|error| {
+        warn!("Unable to read {} of snapshot {}, it is diffed as empty: {:#}", data_type, snapshot_number, error);
+        Vec::new()
+    })
+}
+
+/// Retry getting the data for the host and port combinations that did not return data for an existing snapshot.
 ///
-/// The idea here to reduce output when it's know lots of 'entities'/objects are created, or other
-/// changed would give too much output to be useful.
-pub async fn adhoc_node_exporter_diff(
-    hosts: Vec<&'static str>,
-    ports: Vec<&'static str>,
+/// The manifest of the snapshot records the hosts and ports of the snapshot, and the requests that failed.
+/// For every data type, the combinations of which the request failed are fetched again, and the data that is found now
+/// is added to the snapshot. A data type that is missing in the snapshot is fetched from all the combinations that serve it.
+/// The failed requests in the manifest are updated: the requests that succeed now are removed, the requests that fail again are kept.
+/// A snapshot without a manifest (created by an older version, or imported) has no record of the failed requests, and cannot be retried.
+pub async fn snapshot_retry(
     parallel: usize,
     options: &Opts,
+    config: &Config,
 ) -> Result<()>
 {
-    info!("ad-hoc node_exporter diff first snapshot begin");
+    let snapshot_number = options.snapshot_retry.as_ref().unwrap();
+    info!("begin snapshot retry: {}", snapshot_number);
     let timer = Instant::now();
 
-    let stat_name_filter = utility::set_regex(&options.stat_name_match);
-    let hostname_filter = utility::set_regex(&options.hostname_match);
-
-    let first_snapshot_time = Local::now();
-
-    let node_exporter = Arc::new(Mutex::new(node_exporter::NodeExporterDiff::new()));
+    let Some(mut manifest) = SnapshotManifest::read(snapshot_number)? else {
+        bail!("Snapshot {} has no manifest (created by an older version or imported), the failed requests are unknown and cannot be retried", snapshot_number);
+    };
+    // the hosts and ports are the ones of the snapshot, not the ones currently set.
+    let hosts: Vec<&'static str> = manifest.hosts.iter().map(|host| &*Box::leak(host.clone().into_boxed_str())).collect();
+    let ports: Vec<&'static str> = manifest.ports.iter().map(|port| &*Box::leak(port.clone().into_boxed_str())).collect();
 
-    let hosts = Arc::new(hosts);
-    let ports = Arc::new(ports);
+    // the rows that are found now are added in the current layout, so a snapshot of an older version is migrated first.
+    let snapshot_directory = env::current_dir()?.join("yb_stats.snapshots").join(snapshot_number);
+    if let Some(format_version) = migrate_snapshot_directory(&snapshot_directory, &SNAPSHOT_MIGRATIONS)?
+    {
+        eprintln!("Warning: snapshot {} had format version {}, it is migrated to version {}.", snapshot_number, format_version, SNAPSHOT_FORMAT_VERSION);
+    }
 
-    let mut handles = vec![];
+    let retry = &RetryCombinations { hosts: &hosts, ports: &ports, errors: &manifest.errors };
+    let mut retried = HashSet::new();
+    retry_snapshot_json(snapshot_number, "metrics", "metrics", retry, &mut retried, |hosts, port| async move { metrics::AllMetricEntity::read_metrics(&hosts, &vec![port], parallel, config).await.metricentity }, config).await?;
+    retry_snapshot_json(snapshot_number, "statements", "statements", retry, &mut retried, |hosts, port| async move { statements::AllStatements::read_statements(&hosts, &vec![port], parallel, config).await.statements }, config).await?;
+    retry_snapshot_json(snapshot_number, "nodeexporter", "node_exporter", retry, &mut retried, |hosts, port| async move { node_exporter::AllNodeExporter::read_nodeexporter(&hosts, &vec![port], parallel, config).await.nodeexporter }, config).await?;
+    retry_snapshot_json(snapshot_number, "isleader", "isleader", retry, &mut retried, |hosts, port| async move { isleader::AllIsLeader::read_isleader(&hosts, &vec![port], parallel, config).await.isleader }, config).await?;
+    retry_snapshot_json(snapshot_number, "entities", "entities", retry, &mut retried, |hosts, port| async move { entities::AllEntities::read_entities(&hosts, &vec![port], parallel, config).await.entities }, config).await?;
+    retry_snapshot_json(snapshot_number, "masters", "masters", retry, &mut retried, |hosts, port| async move { masters::Masters::read_masters(&hosts, &vec![port], parallel, config).await.masters }, config).await?;
+    retry_snapshot_json(snapshot_number, "tablet_servers", "tablet_servers", retry, &mut retried, |hosts, port| async move { tablet_servers::AllTabletServers::read_tabletservers(&hosts, &vec![port], parallel, config).await.tabletservers }, config).await?;
+    retry_snapshot_json(snapshot_number, "vars", "vars", retry, &mut retried, |hosts, port| async move { vars::AllVars::read_vars(&hosts, &vec![port], parallel, config).await.vars }, config).await?;
+    retry_snapshot_json(snapshot_number, "versions", "versions", retry, &mut retried, |hosts, port| async move { versions::AllVersions::read_versions(&hosts, &vec![port], parallel, config).await.versions }, config).await?;
+    retry_snapshot_json(snapshot_number, "gflags", "gflags", retry, &mut retried, |hosts, port| async move { gflags::AllGFlags::read_gflags(&hosts, &vec![port], parallel, config).await.gflags }, config).await?;
+    retry_snapshot_json(snapshot_number, "threads", "threads", retry, &mut retried, |hosts, port| async move { threads::AllThreads::read_threads(&hosts, &vec![port], parallel, config).await.threads }, config).await?;
+    retry_snapshot_json(snapshot_number, "memtrackers", "memtrackers", retry, &mut retried, |hosts, port| async move { memtrackers::AllMemTrackers::read_memtrackers(&hosts, &vec![port], parallel, config).await.memtrackers }, config).await?;
+    retry_snapshot_json(snapshot_number, "cluster-config", "cluster_config", retry, &mut retried, |hosts, port| async move { cluster_config::AllSysClusterConfigEntryPB::read_cluster_config(&hosts, &vec![port], parallel, config).await.sysclusterconfigentrypb }, config).await?;
+    retry_snapshot_json(snapshot_number, "health-check", "health_check", retry, &mut retried, |hosts, port| async move { health_check::AllHealthCheck::read_health_check(&hosts, &vec![port], parallel, config).await.health_check }, config).await?;
+    retry_snapshot_json(snapshot_number, "tasks", "tasks", retry, &mut retried, |hosts, port| async move { tasks::AllTasks::read_tasks(&hosts, &vec![port], parallel, config).await.tasks }, config).await?;
+    retry_snapshot_json(snapshot_number, "drives", "drives", retry, &mut retried, |hosts, port| async move { drives::AllDrives::read_drives(&hosts, &vec![port], parallel, config).await.drives }, config).await?;
+    retry_snapshot_json(snapshot_number, "ysql_metrics", "ysql_metrics", retry, &mut retried, |hosts, port| async move { ysql_metrics::AllYsqlMetrics::read_ysql_metrics(&hosts, &vec![port], parallel, config).await.ysqlmetrics }, config).await?;
+    retry_snapshot_json(snapshot_number, "compaction_queues", "compaction_queues", retry, &mut retried, |hosts, port| async move { tablet_server_operations::AllCompactionQueues::read_compaction_queues(&hosts, &vec![port], parallel, config).await.compactionqueues }, config).await?;
+    let snapshot = snapshot_number.parse::<i32>()?;
+    retry_snapshot_text(&snapshot_directory, "mems", "mems", retry, &mut retried, |hosts, port| async move { mems::Mems::read_and_write_mems(&hosts, &vec![port], snapshot, parallel, config).await }, config).await?;
+    retry_snapshot_text(&snapshot_directory, "pprof_growth", "pprof", retry, &mut retried, |hosts, port| async move { pprof::Pprof::read_and_write_pprof(&hosts, &vec![port], snapshot, parallel, config).await }, config).await?;
+
+    // the manifest gets the row counts of the data types that are recovered, and the failed requests are updated per combination.
+    let row_counts = take_snapshot_row_counts(snapshot, config);
+    let failed_request_urls = utility::take_failed_request_urls(config);
+    let partial = partial_after_retry(&failed_request_urls, config);
+    manifest.data_types.extend(row_counts);
+    manifest.errors = errors_after_retry(&manifest.errors, &retried, failed_request_urls);
+    manifest.write(snapshot)?;
+
+    if !partial
+    {
+        Snapshot::set_partial(snapshot, false)?;
+    }
+    else if Snapshot::read_snapshot_index()?.iter().any(|row| row.number == snapshot && row.partial)
+    {
+        writeln!(config.output(), "Snapshot {} is still partial, because requests failed or were skipped. Use --snapshot-retry {} again to add the missing data.", snapshot_number, snapshot_number)?;
+    }
 
-    let clone_node_exporter = node_exporter.clone();
-    let clone_hosts = hosts.clone();
-    let clone_ports = ports.clone();
-    let handle = tokio::spawn(async move {
-        clone_node_exporter.lock().await.adhoc_read_first_snapshot(&clone_hosts, &clone_ports, parallel).await;
-    });
-    handles.push(handle);
+    info!("end snapshot retry: {:?}", timer.elapsed());
+    Ok(())
+}
 
-    for handle in handles {
-        handle.await.unwrap();
+/// The failed requests of the manifest after a retry: the requests that were retried are removed,
+/// and the requests of the retry that failed are added, so a request that failed again is kept.
+fn errors_after_retry(
+    errors: &[String],
+    retried: &HashSet<String>,
+    failed_request_urls: Vec<String>,
+) -> Vec<String>
+{
+    let mut errors: Vec<String> = errors.iter()
+        .filter(|error| !retried.contains(*error))
+        .cloned()
+        .collect();
+    for failed_request_url in failed_request_urls
+    {
+        if !errors.contains(&failed_request_url)
+        {
+            errors.push(failed_request_url);
+        }
     }
-    info!("ad-hoc node_exporter diff first snapshot end: {:?}", timer.elapsed());
+    errors
+}
 
-    println!("Begin ad-hoc in-memory snapshot created, press enter to create end snapshot for difference calculation.");
-    let mut input = String::new();
-    stdin().read_line(&mut input).expect("failed");
+/// Return if a snapshot is still partial after a retry.
+/// The mark is only removed if the retry read everything it tried: a request that failed again,
+/// or requests that were skipped because of an interrupt, leave data missing.
+fn partial_after_retry(
+    failed_request_urls: &[String],
+    config: &Config,
+) -> bool
+{
+    !failed_request_urls.is_empty() || utility::interrupted(config)
+}
 
-    info!("ad-hoc node_exporter diff second snapshot begin");
-    let timer = Instant::now();
+/// Delete one or more snapshots: the snapshot directories and the entries in the snapshot index.
+///
+/// The snapshot numbers to delete are shown, and must be confirmed, unless `--yes` is set.
+pub fn snapshot_delete(
+    options: &Opts,
+    config: &Config,
+) -> Result<()>
+{
+    let snapshots = Snapshot::read_snapshot_index()?;
+    let delete_numbers = parse_snapshot_numbers(options.snapshot_delete.as_ref().unwrap())?;
+    for number in &delete_numbers
+    {
+        if !snapshots.iter().any(|row| row.number == *number)
+        {
+            bail!("Unable to find snapshot number: {}", number);
+        }
+    }
+    writeln!(config.output(), "The following snapshots will be removed: {}", delete_numbers.iter().map(|number| number.to_string()).collect::<Vec<_>>().join(", "))?;
+    if !confirm("Remove these snapshots?", options.yes)?
+    {
+        writeln!(config.output(), "No snapshots removed.")?;
+        return Ok(());
+    }
 
-    let second_snapshot_time = Local::now();
+    let current_directory = env::current_dir()
+        .with_context(|| "Cannot evaluate current working directory" )?;
+    remove_snapshots(&current_directory.join("yb_stats.snapshots"), &delete_numbers)?;
+    writeln!(config.output(), "Removed {} snapshot(s).", delete_numbers.len())?;
+    Ok(())
+}
+/// Remove the snapshots from the yb_stats.snapshots directory: their directories, and their rows in the snapshot index.
+/// The index is kept when all snapshots are removed, and the next snapshot is number 0 again.
+fn remove_snapshots(
+    yb_stats_directory: &Path,
+    delete_numbers: &[i32],
+) -> Result<()>
+{
+    let snapshots = Snapshot::read_snapshot_index_in(yb_stats_directory)?;
+    for number in delete_numbers
+    {
+        let snapshot_directory = yb_stats_directory.join(number.to_string());
+        if snapshot_directory.exists()
+        {
+            fs::remove_dir_all(&snapshot_directory)
+                .with_context(|| format!("Error removing directory: {}", snapshot_directory.display()))?;
+        }
+        info!("removed snapshot: {}", number);
+    }
+    Snapshot::write_snapshot_index_in(yb_stats_directory, snapshots.into_iter().filter(|row| !delete_numbers.contains(&row.number)).collect())
+}
 
-    let mut handles = vec![];
+/// The data types stored in a snapshot, which are the names of the JSON and parquet files in the snapshot directory.
+pub fn snapshot_data_types(
+    snapshot_number: &str,
+) -> Result<Vec<String>>
+{
+    let snapshot_directory = env::current_dir()?.join("yb_stats.snapshots").join(snapshot_number);
+    let mut data_types: Vec<String> = fs::read_dir(&snapshot_directory)
+        .with_context(|| format!("Error reading snapshot directory: {}", snapshot_directory.display()))?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.file_name().to_str().and_then(|file_name| file_name.strip_suffix(".json").or_else(|| file_name.strip_suffix(".parquet"))).map(str::to_string))
+        .filter(|data_type| format!("{}.json", data_type) != MANIFEST_FILE)
+        .collect();
+    data_types.sort();
+    data_types.dedup();
+    Ok(data_types)
+}
 
-    let clone_node_exporter = node_exporter.clone();
-    let clone_hosts = hosts.clone();
-    let clone_ports = ports.clone();
-    let handle = tokio::spawn(async move {
-        clone_node_exporter.lock().await.adhoc_read_second_snapshot(&clone_hosts, &clone_ports, parallel, &first_snapshot_time).await;
-    });
-    handles.push(handle);
+/// Export a snapshot into a single JSON file: the snapshot metadata, all data types and the text files (mems and pprof).
+///
+/// The parquet files are not exported, these can be created from the JSON data.
+/// With --anonymize, the hostnames in the exported data are replaced, in the same way as for the output.
+pub fn snapshot_export(
+    options: &Opts,
+    config: &Config,
+) -> Result<()>
+{
+    let snapshot_number = options.snapshot_export.as_ref().unwrap();
+    let Some(snapshot) = Snapshot::read_snapshot_index()?.into_iter().find(|row| row.number.to_string() == *snapshot_number) else {
+        bail!("Unable to find snapshot number: {}", snapshot_number);
+    };
+    let export_file = options.to.as_ref().unwrap();
 
-    for handle in handles {
-        handle.await.unwrap();
+    let mut data = BTreeMap::new();
+    for data_type in snapshot_data_types(snapshot_number)?
+    {
+        data.insert(data_type.clone(), read_snapshot_json::<serde_json::Value>(snapshot_number, &data_type, config)?.into());
+    }
+    let snapshot_directory = env::current_dir()?.join("yb_stats.snapshots").join(snapshot_number);
+    let mut files = BTreeMap::new();
+    for entry in fs::read_dir(&snapshot_directory)
+        .with_context(|| format!("Error reading snapshot directory: {}", snapshot_directory.display()))?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_name().to_str().is_some_and(|file_name| file_name.ends_with(".txt")))
+    {
+        let text = fs::read_to_string(entry.path())
+            .with_context(|| format!("Error reading snapshot: {}", entry.path().display()))?;
+        files.insert(utility::anonymize_text(entry.file_name().to_string_lossy().to_string(), config), utility::anonymize_text(text, config));
     }
 
-    info!("ad-hoc node_exporter diff second snapshot end: {:?}", timer.elapsed());
-
-    println!("Time between snapshots: {:8.3} seconds", (second_snapshot_time - first_snapshot_time).num_milliseconds() as f64 / 1000_f64);
-    node_exporter.lock().await.print(&hostname_filter, &stat_name_filter, &options.gauges_enable, &options.details_enable);
-
+    // the data is read via read_snapshot_json, which migrates it to the current format version.
+    let snapshot_bundle = SnapshotBundle {
+        format_version: SNAPSHOT_BUNDLE_FORMAT_VERSION,
+        yb_stats_version: env!("CARGO_PKG_VERSION").to_string(),
+        snapshot_format_version: SNAPSHOT_FORMAT_VERSION,
+        snapshot,
+        data,
+        files,
+    };
+    fs::write(export_file, serde_json::to_string(&snapshot_bundle).with_context(|| "Json serialization error")?)
+        .with_context(|| format!("Error writing file: {}", export_file))?;
+    writeln!(config.output(), "Exported snapshot {} ({} data types, {} files) to {}", snapshot_number, snapshot_bundle.data.len(), snapshot_bundle.files.len(), export_file)?;
     Ok(())
 }
 
-pub async fn adhoc_nonmetrics_diff(
-    hosts: Vec<&'static str>,
-    ports: Vec<&'static str>,
-    parallel: usize,
+/// Import a snapshot that is exported with --snapshot-export as a new snapshot number.
+///
+/// The snapshot keeps the timestamp and comment of the exported snapshot.
+pub fn snapshot_import(
     options: &Opts,
+    config: &Config,
 ) -> Result<()>
 {
-    let timer = Instant::now();
-
-    let _stat_name_filter = utility::set_regex(&options.stat_name_match);
-    let hostname_filter = utility::set_regex(&options.hostname_match);
-    let _table_name_filter = utility::set_regex(&options.table_name_match);
+    let import_file = options.snapshot_import.as_ref().unwrap();
+    let snapshot_bundle: SnapshotBundle = serde_json::from_str(
+        &fs::read_to_string(import_file).with_context(|| format!("Error reading file: {}", import_file))?
+    ).with_context(|| format!("Json deserialization error, {} is not a snapshot export", import_file))?;
+    validate_snapshot_bundle(&snapshot_bundle)?;
+
+    let snapshot_number = Snapshot::insert_snapshot_number(snapshot_bundle.snapshot.timestamp, snapshot_bundle.snapshot.comment)?;
+    let snapshot_directory = env::current_dir()?.join("yb_stats.snapshots").join(snapshot_number.to_string());
+    for (data_type, vector) in snapshot_bundle.data
+    {
+        let filepath = snapshot_directory.join(format!("{}.json", data_type));
+        fs::write(&filepath, serde_json::to_string(&vector).with_context(|| "Json serialization error")?)
+            .with_context(|| format!("Error saving snapshot: {}", filepath.display()))?;
+    }
+    for (file_name, text) in snapshot_bundle.files
+    {
+        let filepath = snapshot_directory.join(file_name);
+        fs::write(&filepath, text)
+            .with_context(|| format!("Error saving snapshot: {}", filepath.display()))?;
+    }
+    // the data keeps the format version of the export, so the data of an older version is migrated when it is read.
+    write_format_version(&snapshot_directory, snapshot_bundle.snapshot_format_version)?;
+    writeln!(config.output(), "Imported snapshot {} from {} (exported by yb_stats {}) as snapshot {}", snapshot_bundle.snapshot.number, import_file, snapshot_bundle.yb_stats_version, snapshot_number)?;
+    Ok(())
+}
 
-    let first_snapshot_time = Local::now();
+/// Verify a snapshot: read the data of every data type, and compare the data types and row counts with the manifest.
+///
+/// A snapshot without a manifest (created by an older version, or imported) only gets the data checked.
+/// The requests that failed during the snapshot are shown, but are not a problem of the snapshot itself.
+pub fn snapshot_verify(
+    options: &Opts,
+    config: &Config,
+) -> Result<()>
+{
+    let snapshot_number = options.snapshot_verify.as_ref().unwrap();
+    if !Snapshot::read_snapshot_index()?.iter().any(|row| row.number.to_string() == *snapshot_number)
+    {
+        bail!("Unable to find snapshot number: {}", snapshot_number);
+    }
 
-    let entities = Arc::new(Mutex::new(entities::EntitiesDiff::new()));
-    let masters = Arc::new(Mutex::new(masters::MastersDiff::new()));
-    let tablet_servers = Arc::new(Mutex::new(tablet_servers::TabletServersDiff::new()));
-    let versions = Arc::new(Mutex::new(versions::VersionsDiff::new()));
-    let vars = Arc::new(Mutex::new(vars::VarsDiff::new()));
-    let health_check = Arc::new(Mutex::new(health_check::HealthCheckDiff::new()));
+    let mut problems = Vec::new();
+    let mut row_counts = BTreeMap::new();
+    for data_type in snapshot_data_types(snapshot_number)?
+    {
+        match read_snapshot_json::<serde_json::Value>(snapshot_number, &data_type, config)
+        {
+            Ok(rows) => { row_counts.insert(data_type, rows.len()); },
+            Err(error) => problems.push(format!("{}: {:#}", data_type, error)),
+        }
+    }
 
-    let hosts = Arc::new(hosts);
-    let ports = Arc::new(ports);
-    let _details_enable = options.details_enable;
+    match SnapshotManifest::read(snapshot_number)?
+    {
+        Some(manifest) => {
+            writeln!(config.output(), "Snapshot {} created by yb_stats {} at {}, hosts: {}, ports: {}, parallel: {}, extra data: {}",
+                snapshot_number,
+                manifest.yb_stats_version,
+                utility::format_timestamp(&manifest.timestamp, config),
+                manifest.hosts.join(","),
+                manifest.ports.join(","),
+                manifest.parallel,
+                manifest.extra_data,
+            )?;
+            problems.extend(manifest_problems(&manifest, &row_counts));
+            if !manifest.errors.is_empty()
+            {
+                writeln!(config.output(), "{} requests failed during the snapshot:", manifest.errors.len())?;
+                for error in &manifest.errors
+                {
+                    writeln!(config.output(), "  {}", error)?;
+                }
+            }
+        },
+        None => writeln!(config.output(), "Snapshot {} has no manifest (created by an older version or imported), only the data is checked.", snapshot_number)?,
+    }
+    writeln!(config.output(), "{}", format_row_counts(&row_counts))?;
 
-    let mut handles = vec![];
+    if !problems.is_empty()
+    {
+        for problem in &problems
+        {
+            writeln!(config.output(), "{}", problem)?;
+        }
+        bail!("Snapshot {} has {} problem(s)", snapshot_number, problems.len());
+    }
+    writeln!(config.output(), "Snapshot {} verified: {} data types, no problems found.", snapshot_number, row_counts.len())?;
+    Ok(())
+}
 
-    let clone_entities = entities.clone();
-    let clone_hosts = hosts.clone();
-    let clone_ports = ports.clone();
-    let handle = tokio::spawn(async move {
-        clone_entities.lock().await.adhoc_read_first_snapshot(&clone_hosts, &clone_ports, parallel).await;
-    });
-    handles.push(handle);
+/// The differences between the data types and row counts in the manifest and the data in the snapshot.
+fn manifest_problems(
+    manifest: &SnapshotManifest,
+    row_counts: &BTreeMap<String, usize>,
+) -> Vec<String>
+{
+    let mut problems = Vec::new();
+    for (data_type, manifest_rows) in &manifest.data_types
+    {
+        match row_counts.get(data_type)
+        {
+            None => problems.push(format!("{}: in the manifest with {} rows, but not readable in the snapshot", data_type, manifest_rows)),
+            Some(rows) if rows != manifest_rows => problems.push(format!("{}: {} rows, the manifest has {} rows", data_type, rows, manifest_rows)),
+            Some(_) => {},
+        }
+    }
+    for data_type in row_counts.keys().filter(|data_type| !manifest.data_types.contains_key(*data_type))
+    {
+        problems.push(format!("{}: not in the manifest", data_type));
+    }
+    problems
+}
 
-    let clone_masters = masters.clone();
-    let clone_hosts = hosts.clone();
-    let clone_ports = ports.clone();
-    let handle = tokio::spawn(async move {
-        clone_masters.lock().await.adhoc_read_first_snapshot(&clone_hosts, &clone_ports, parallel).await;
-    });
-    handles.push(handle);
+/// Validate a snapshot bundle before it is imported.
+///
+/// A bundle with a newer format version cannot be read reliably, and the data type and file names
+/// are used as file names in the snapshot directory, so these must be plain file names.
+fn validate_snapshot_bundle(
+    snapshot_bundle: &SnapshotBundle,
+) -> Result<()>
+{
+    if snapshot_bundle.format_version > SNAPSHOT_BUNDLE_FORMAT_VERSION
+    {
+        bail!("Snapshot export format version {} (yb_stats {}) is newer than the supported version {}, upgrade yb_stats to import it",
+            snapshot_bundle.format_version,
+            snapshot_bundle.yb_stats_version,
+            SNAPSHOT_BUNDLE_FORMAT_VERSION
+        );
+    }
+    if snapshot_bundle.snapshot_format_version > SNAPSHOT_FORMAT_VERSION
+    {
+        bail!("Snapshot format version {} (yb_stats {}) is newer than the supported version {}, upgrade yb_stats to import it",
+            snapshot_bundle.snapshot_format_version,
+            snapshot_bundle.yb_stats_version,
+            SNAPSHOT_FORMAT_VERSION
+        );
+    }
+    for name in snapshot_bundle.data.keys().chain(snapshot_bundle.files.keys())
+    {
+        if name.is_empty()
+            || name.starts_with('.')
+            || !name.chars().all(|character| character.is_ascii_alphanumeric() || "-_.:".contains(character))
+        {
+            bail!("Invalid data type or file name in snapshot export: {}", name);
+        }
+    }
+    Ok(())
+}
 
-    let clone_tablet_servers = tablet_servers.clone();
-    let clone_hosts = hosts.clone();
-    let clone_ports = ports.clone();
-    let handle = tokio::spawn(async move {
-        clone_tablet_servers.lock().await.adhoc_read_first_snapshot(&clone_hosts, &clone_ports, parallel).await;
-    });
-    handles.push(handle);
+/// Parse a list of snapshot numbers and ranges, such as: `3,5,7-9`.
+fn parse_snapshot_numbers(
+    snapshot_numbers: &str,
+) -> Result<Vec<i32>>
+{
+    let mut numbers = Vec::new();
+    for part in snapshot_numbers.split(',').map(str::trim).filter(|part| !part.is_empty())
+    {
+        let (first, last) = part.split_once('-').unwrap_or((part, part));
+        let first: i32 = first.trim().parse().with_context(|| format!("Invalid snapshot number: {}", part))?;
+        let last: i32 = last.trim().parse().with_context(|| format!("Invalid snapshot number: {}", part))?;
+        if first > last
+        {
+            bail!("Invalid snapshot range: {}", part);
+        }
+        numbers.extend(first..=last);
+    }
+    if numbers.is_empty()
+    {
+        bail!("No snapshot numbers specified");
+    }
+    numbers.sort();
+    numbers.dedup();
+    Ok(numbers)
+}
 
-    let clone_vars = vars.clone();
-    let clone_hosts = hosts.clone();
-    let clone_ports = ports.clone();
-    let handle = tokio::spawn(async move {
-        clone_vars.lock().await.adhoc_read_first_snapshot(&clone_hosts, &clone_ports, parallel).await;
-    });
-    handles.push(handle);
+/// Ask the user for a snapshot number, for [Snapshot::read_begin_end_snapshot_from_user].
+fn ask_snapshot_number(
+    question: &str,
+) -> Result<i32>
+{
+    print!("{}", question);
+    stdout().flush()?;
+    let mut answer = String::new();
+    stdin().read_line(&mut answer)?;
+    answer.trim()
+        .parse()
+        .with_context(|| format!("Invalid snapshot number: '{}'", answer.trim()))
+}
 
-    let clone_versions = versions.clone();
-    let clone_hosts = hosts.clone();
-    let clone_ports = ports.clone();
-    let handle = tokio::spawn(async move {
-        clone_versions.lock().await.adhoc_read_first_snapshot(&clone_hosts, &clone_ports, parallel).await;
-    });
-    handles.push(handle);
+/// Select the Nth snapshot from the last as begin, and the last snapshot as end.
+///
+/// The snapshots are ordered by number, so a removed snapshot does not count.
+fn last_begin_end(
+    snapshots: &[Snapshot],
+    last: usize,
+) -> Result<(i32, i32)>
+{
+    if last == 0
+    {
+        bail!("--last requires a number of 1 or higher");
+    }
+    let mut numbers: Vec<i32> = snapshots.iter().map(|row| row.number).collect();
+    numbers.sort_unstable();
+    numbers.dedup();
+    if numbers.len() <= last
+    {
+        bail!("--last {} requires at least {} snapshots, available snapshots: {}", last, last + 1, format_snapshot_numbers(snapshots));
+    }
+    Ok((numbers[numbers.len() - 1 - last], numbers[numbers.len() - 1]))
+}
 
-    let clone_health_check = health_check.clone();
-    let clone_hosts = hosts.clone();
-    let clone_ports = ports.clone();
-    let handle = tokio::spawn(async move {
-        clone_health_check.lock().await.adhoc_read_first_snapshot(&clone_hosts, &clone_ports, parallel).await;
-    });
-    handles.push(handle);
+/// Validate that the begin and end snapshot numbers exist, and return these as (begin, end).
+///
+/// A snapshot number that does not exist results in an error that lists the available snapshot numbers.
+/// If begin is after end, these are swapped, with a warning.
+fn validate_begin_end(
+    snapshots: &[Snapshot],
+    begin_snapshot: i32,
+    end_snapshot: i32,
+) -> Result<(i32, i32)>
+{
+    let missing: Vec<String> = [begin_snapshot, end_snapshot]
+        .iter()
+        .filter(|number| !snapshots.iter().any(|row| row.number == **number))
+        .map(|number| number.to_string())
+        .collect();
+    match missing.len()
+    {
+        0 => {},
+        1 => bail!("Snapshot {} does not exist, available snapshots: {}", missing[0], format_snapshot_numbers(snapshots)),
+        _ => bail!("Snapshots {} do not exist, available snapshots: {}", missing.join(" and "), format_snapshot_numbers(snapshots)),
+    }
+    if begin_snapshot > end_snapshot
+    {
+        eprintln!("Warning: begin snapshot {} is after end snapshot {}, using {} as begin and {} as end.", begin_snapshot, end_snapshot, end_snapshot, begin_snapshot);
+        return Ok((end_snapshot, begin_snapshot));
+    }
+    Ok((begin_snapshot, end_snapshot))
+}
 
+/// Format the numbers of the snapshots compactly, with consecutive numbers as range, such as: 1-5,8.
+/// This is the same notation as [parse_snapshot_numbers] accepts.
+fn format_snapshot_numbers(
+    snapshots: &[Snapshot],
+) -> String
+{
+    let mut numbers: Vec<i32> = snapshots.iter().map(|row| row.number).collect();
+    numbers.sort_unstable();
+    numbers.dedup();
+    if numbers.is_empty()
+    {
+        return "none".to_string();
+    }
+    let mut ranges: Vec<(i32, i32)> = Vec::new();
+    for number in numbers
+    {
+        match ranges.last_mut()
+        {
+            Some((_, last)) if *last + 1 == number => *last = number,
+            _ => ranges.push((number, number)),
+        }
+    }
+    ranges
+        .iter()
+        .map(|(first, last)| if first == last { first.to_string() } else { format!("{}-{}", first, last) })
+        .collect::<Vec<_>>()
+        .join(",")
+}
 
-    for handle in handles {
-        handle.await.unwrap();
+/// Ask the user to confirm a destructive action, which `--yes` confirms upfront.
+///
+/// If stdin is not a terminal, such as in a script, there is nobody to answer,
+/// so instead of waiting for input this returns an error.
+fn confirm(
+    question: &str,
+    yes: bool,
+) -> Result<bool>
+{
+    if yes
+    {
+        return Ok(true);
+    }
+    if !stdin().is_terminal()
+    {
+        bail!("Refusing to continue without confirmation: stdin is not a terminal, use --yes to confirm");
     }
-    info!("ad-hoc metrics diff first snapshot end: {:?}", timer.elapsed());
+    print!("{} [y/N]: ", question);
+    stdout().flush()?;
+    let mut answer = String::new();
+    stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
 
-    println!("Begin ad-hoc in-memory snapshot created, press enter to create end snapshot for difference calculation.");
-    let mut input = String::new();
-    stdin().read_line(&mut input).expect("failed");
+/// The hosts and ports of a snapshot, and its failed requests (`host:port/url`), from which the combinations to retry are taken.
+struct RetryCombinations<'a> {
+    hosts: &'a [&'static str],
+    ports: &'a [&'static str],
+    errors: &'a [String],
+}
 
-    let timer = Instant::now();
+/// Retry getting the data for a single data type of a snapshot, see [snapshot_retry].
+///
+/// The hosts to retry for a port are read together, with the parallelism of `--parallel`.
+/// The requests that are done are added to `retried`, to update the failed requests in the manifest.
+async fn retry_snapshot_json<T, F, Fut>(
+    snapshot_number: &String,
+    filename: &str,
+    endpoint_type: &str,
+    retry: &RetryCombinations<'_>,
+    retried: &mut HashSet<String>,
+    read: F,
+    config: &Config,
+) -> Result<()>
+where
+    T: Serialize,
+    F: Fn(Vec<&'static str>, &'static str) -> Fut,
+    Fut: Future<Output = Vec<T>>,
+{
+    let snapshot_directory = env::current_dir()?.join("yb_stats.snapshots").join(snapshot_number);
+    let parquet_filepath = snapshot_directory.join(format!("{}.parquet", filename));
+    let json = snapshot_directory.join(format!("{}.json", filename)).exists();
+    // a data type that is saved with --format parquet stays parquet.
+    let parquet = !json && parquet_filepath.exists();
+    let present = json || parquet;
+    let mut rows: Vec<serde_json::Value> = if present
+    {
+        match read_snapshot_json(snapshot_number, filename, config) {
+            Ok(rows) => rows,
+            Err(error) => {
+                warn!("{}: not retried, the data in the snapshot cannot be read: {:#}", filename, error);
+                return Ok(())
+            },
+        }
+    }
+    else
+    {
+        Vec::new()
+    };
 
-    let second_snapshot_time = Local::now();
-    let mut handles = vec![];
+    let mut retries = 0;
+    let mut recovered = 0;
+    for (port, hosts) in retry_hosts_per_port(retry, endpoint_type, present, config)
+    {
+        retries += hosts.len();
+        retried.extend(hosts.iter().map(|host| format!("{}:{}/{}", host, port, utility::endpoint_path(endpoint_type, config))));
+        let new_rows = read(hosts.clone(), port).await
+            .into_iter()
+            .map(|row| serde_json::to_value(row).with_context(|| "Json serialization error"))
+            .collect::<Result<Vec<_>>>()?;
+        // a data type can have a row for a host of which the request failed, such as the vars, which is not recovered.
+        let new_hostname_ports = hostname_ports(&new_rows);
+        recovered += hosts.iter()
+            .filter(|host| new_hostname_ports.contains(utility::hostname_port(host, port, config).as_str()) && !request_failed(host, port, endpoint_type, config))
+            .count();
+        rows.extend(new_rows);
+    }
+    if recovered > 0 && parquet
+    {
+        config.snapshot_row_counts.lock().unwrap().entry(snapshot_number.parse::<i32>()?).or_default().insert(filename.to_string(), rows.len());
+        write_parquet_rows(&parquet_filepath, filename, rows)?;
+    }
+    else if recovered > 0
+    {
+        save_snapshot_json(snapshot_number.parse::<i32>()?, filename, rows, config)?;
+    }
+    writeln!(config.output(), "{:20} retried: {:3} recovered: {:3}", filename, retries, recovered)?;
+    Ok(())
+}
 
-    let clone_entities = entities.clone();
-    let clone_hosts = hosts.clone();
-    let clone_ports = ports.clone();
-    let handle = tokio::spawn(async move {
-        clone_entities.lock().await.adhoc_read_second_snapshot(&clone_hosts, &clone_ports, parallel).await;
-    });
-    handles.push(handle);
+/// Retry getting the text files of a data type of a snapshot, which are saved per host and port as `<prefix>_<hostname:port>.txt`,
+/// such as the mems and pprof data, see [snapshot_retry].
+async fn retry_snapshot_text<F, Fut>(
+    snapshot_directory: &Path,
+    prefix: &str,
+    endpoint_type: &str,
+    retry: &RetryCombinations<'_>,
+    retried: &mut HashSet<String>,
+    read_and_write: F,
+    config: &Config,
+) -> Result<()>
+where
+    F: Fn(Vec<&'static str>, &'static str) -> Fut,
+    Fut: Future<Output = Result<()>>,
+{
+    let text_file = |host: &str, port: &str| snapshot_directory.join(format!("{}_{}.txt", prefix, utility::hostname_port(host, port, config)));
+    let present = retry.ports.iter().any(|port| retry.hosts.iter().any(|host| text_file(host, port).exists()));
 
-    let clone_masters = masters.clone();
-    let clone_hosts = hosts.clone();
-    let clone_ports = ports.clone();
-    let handle = tokio::spawn(async move {
-        clone_masters.lock().await.adhoc_read_second_snapshot(&clone_hosts, &clone_ports, parallel).await;
-    });
-    handles.push(handle);
+    let mut retries = 0;
+    let mut recovered = 0;
+    for (port, hosts) in retry_hosts_per_port(retry, endpoint_type, present, config)
+    {
+        retries += hosts.len();
+        retried.extend(hosts.iter().map(|host| format!("{}:{}/{}", host, port, utility::endpoint_path(endpoint_type, config))));
+        read_and_write(hosts.clone(), port).await?;
+        recovered += hosts.iter().filter(|host| text_file(host, port).exists()).count();
+    }
+    writeln!(config.output(), "{:20} retried: {:3} recovered: {:3}", prefix, retries, recovered)?;
+    Ok(())
+}
 
-    let clone_tablet_servers = tablet_servers.clone();
-    let clone_hosts = hosts.clone();
-    let clone_ports = ports.clone();
-    let handle = tokio::spawn(async move {
-        clone_tablet_servers.lock().await.adhoc_read_second_snapshot(&clone_hosts, &clone_ports, parallel).await;
-    });
-    handles.push(handle);
+/// The distinct hostname_port values of the rows of a data type.
+fn hostname_ports(
+    rows: &[serde_json::Value],
+) -> HashSet<&str>
+{
+    rows.iter()
+        .filter_map(|row| row.get("hostname_port").and_then(|hostname_port| hostname_port.as_str()))
+        .collect()
+}
 
-    let clone_vars = vars.clone();
-    let clone_hosts = hosts.clone();
-    let clone_ports = ports.clone();
-    let handle = tokio::spawn(async move {
-        clone_vars.lock().await.adhoc_read_second_snapshot(&clone_hosts, &clone_ports, parallel).await;
-    });
-    handles.push(handle);
+/// Return if the request for the endpoint of a data type of a host and port failed, since the failed requests were last taken.
+fn request_failed(
+    host: &str,
+    port: &str,
+    endpoint_type: &str,
+    config: &Config,
+) -> bool
+{
+    config.failed_request_urls.lock().unwrap().contains(&format!("{}:{}/{}", host, port, utility::endpoint_path(endpoint_type, config)))
+}
 
-    let clone_versions = versions.clone();
-    let clone_hosts = hosts.clone();
-    let clone_ports = ports.clone();
-    let handle = tokio::spawn(async move {
-        clone_versions.lock().await.adhoc_read_second_snapshot(&clone_hosts, &clone_ports, parallel).await;
-    });
-    handles.push(handle);
+/// Parse a failed request (`host:port/url`) into the host, the port and the url.
+fn parse_failed_request(
+    failed_request_url: &str,
+) -> Option<(&str, &str, &str)>
+{
+    let (hostname_port, url) = failed_request_url.split_once('/')?;
+    let (host, port) = hostname_port.rsplit_once(':')?;
+    Some((host, port, url))
+}
 
-    let clone_health_check = health_check.clone();
-    let clone_hosts = hosts.clone();
-    let clone_ports = ports.clone();
-    let handle = tokio::spawn(async move {
-        clone_health_check.lock().await.adhoc_read_second_snapshot(&clone_hosts, &clone_ports, parallel).await;
-    });
-    handles.push(handle);
+/// The hosts per port to retry for a data type, in the order of the ports and hosts of the snapshot. Ports without hosts to retry are left out.
+///
+/// If the data type is present in the snapshot, these are the combinations of which the request for the endpoint of the data type failed.
+/// If it is missing, these are all the combinations that serve the data type, based on the port roles.
+fn retry_hosts_per_port(
+    retry: &RetryCombinations<'_>,
+    endpoint_type: &str,
+    present: bool,
+    config: &Config,
+) -> Vec<(&'static str, Vec<&'static str>)>
+{
+    let path = utility::endpoint_path(endpoint_type, config);
+    let failed: HashSet<(&str, &str)> = retry.errors.iter()
+        .filter_map(|error| parse_failed_request(error))
+        .filter(|(_, _, url)| *url == path)
+        .map(|(host, port, _)| (host, port))
+        .collect();
+    retry.ports.iter()
+        .map(|port| (*port, retry.hosts.iter()
+            .filter(|host| if present { failed.contains(&(**host, *port)) } else { utility::endpoint_selected(host, port, endpoint_type, config) })
+            .copied()
+            .collect::<Vec<_>>()))
+        .filter(|(_, hosts)| !hosts.is_empty())
+        .collect()
+}
 
-    for handle in handles {
-        handle.await.unwrap();
+/// The data types that can be diffed with the adhoc (in memory) snapshot mode, in the order these are reported.
+/// The gflags and drives are only diffed when these are set with `--only`.
+pub const ADHOC_DIFF_TYPES: [AdhocDiffType; 11] = [
+    AdhocDiffType { name: "metrics", default: true, new: || AdhocDiffData::Metrics(metrics::MetricEntityDiff::new()) },
+    AdhocDiffType { name: "statements", default: true, new: || AdhocDiffData::Statements(statements::StatementsDiff::new()) },
+    AdhocDiffType { name: "node_exporter", default: true, new: || AdhocDiffData::NodeExporter(node_exporter::NodeExporterDiff::new()) },
+    AdhocDiffType { name: "entities", default: true, new: || AdhocDiffData::Entities(entities::EntitiesDiff::new()) },
+    AdhocDiffType { name: "masters", default: true, new: || AdhocDiffData::Masters(masters::MastersDiff::new()) },
+    AdhocDiffType { name: "tablet_servers", default: true, new: || AdhocDiffData::TabletServers(tablet_servers::TabletServersDiff::new()) },
+    AdhocDiffType { name: "vars", default: true, new: || AdhocDiffData::Vars(vars::VarsDiff::new()) },
+    AdhocDiffType { name: "versions", default: true, new: || AdhocDiffData::Versions(versions::VersionsDiff::new()) },
+    AdhocDiffType { name: "health_check", default: true, new: || AdhocDiffData::HealthCheck(health_check::HealthCheckDiff::new()) },
+    AdhocDiffType { name: "gflags", default: false, new: || AdhocDiffData::GFlags(gflags::AllGFlags::new(), gflags::AllGFlags::new()) },
+    AdhocDiffType { name: "drives", default: false, new: || AdhocDiffData::Drives(drives::AllDrives::new(), drives::AllDrives::new()) },
+];
+
+impl AdhocDiffData {
+    #[allow(clippy::too_many_arguments)]
+    async fn read_first_snapshot(
+        &mut self,
+        hosts: &Vec<&str>,
+        ports: &Vec<&str>,
+        parallel: usize,
+        details_enable: bool,
+        rollup_table: bool,
+        config: &Config,
+    )
+    {
+        match self {
+            AdhocDiffData::Metrics(metrics) => metrics.adhoc_read_first_snapshot(hosts, ports, parallel, details_enable, rollup_table, config).await,
+            AdhocDiffData::Statements(statements) => statements.adhoc_read_first_snapshot(hosts, ports, parallel, config).await,
+            AdhocDiffData::NodeExporter(node_exporter) => node_exporter.adhoc_read_first_snapshot(hosts, ports, parallel, config).await,
+            AdhocDiffData::Entities(entities) => entities.adhoc_read_first_snapshot(hosts, ports, parallel, config).await,
+            AdhocDiffData::Masters(masters) => masters.adhoc_read_first_snapshot(hosts, ports, parallel, config).await,
+            AdhocDiffData::TabletServers(tablet_servers) => tablet_servers.adhoc_read_first_snapshot(hosts, ports, parallel, config).await,
+            AdhocDiffData::Vars(vars) => vars.adhoc_read_first_snapshot(hosts, ports, parallel, config).await,
+            AdhocDiffData::Versions(versions) => versions.adhoc_read_first_snapshot(hosts, ports, parallel, config).await,
+            AdhocDiffData::HealthCheck(health_check) => health_check.adhoc_read_first_snapshot(hosts, ports, parallel, config).await,
+            AdhocDiffData::GFlags(first, _) => *first = gflags::AllGFlags::read_gflags(hosts, ports, parallel, config).await,
+            AdhocDiffData::Drives(first, _) => *first = drives::AllDrives::read_drives(hosts, ports, parallel, config).await,
+        }
     }
-    info!("ad-hoc metrics diff second snapshot end: {:?}", timer.elapsed());
+    #[allow(clippy::too_many_arguments)]
+    async fn read_second_snapshot(
+        &mut self,
+        hosts: &Vec<&str>,
+        ports: &Vec<&str>,
+        parallel: usize,
+        first_snapshot_time: &DateTime<Local>,
+        details_enable: bool,
+        rollup_table: bool,
+        config: &Config,
+    )
+    {
+        match self {
+            AdhocDiffData::Metrics(metrics) => metrics.adhoc_read_second_snapshot(hosts, ports, parallel, first_snapshot_time, details_enable, rollup_table, config).await,
+            AdhocDiffData::Statements(statements) => statements.adhoc_read_second_snapshot(hosts, ports, parallel, first_snapshot_time, config).await,
+            AdhocDiffData::NodeExporter(node_exporter) => node_exporter.adhoc_read_second_snapshot(hosts, ports, parallel, first_snapshot_time, config).await,
+            AdhocDiffData::Entities(entities) => entities.adhoc_read_second_snapshot(hosts, ports, parallel, config).await,
+            AdhocDiffData::Masters(masters) => masters.adhoc_read_second_snapshot(hosts, ports, parallel, config).await,
+            AdhocDiffData::TabletServers(tablet_servers) => tablet_servers.adhoc_read_second_snapshot(hosts, ports, parallel, config).await,
+            AdhocDiffData::Vars(vars) => vars.adhoc_read_second_snapshot(hosts, ports, parallel, config).await,
+            AdhocDiffData::Versions(versions) => versions.adhoc_read_second_snapshot(hosts, ports, parallel, config).await,
+            AdhocDiffData::HealthCheck(health_check) => health_check.adhoc_read_second_snapshot(hosts, ports, parallel, config).await,
+            AdhocDiffData::GFlags(_, second) => *second = gflags::AllGFlags::read_gflags(hosts, ports, parallel, config).await,
+            AdhocDiffData::Drives(_, second) => *second = drives::AllDrives::read_drives(hosts, ports, parallel, config).await,
+        }
+    }
+    async fn print(
+        &self,
+        options: &Opts,
+        hostname_filter: &Regex,
+        stat_name_filter: &Regex,
+        table_name_filter: &Regex,
+        computes: &[metrics::MetricCompute],
+        config: &Config,
+    ) -> Result<usize>
+    {
+        match self {
+            AdhocDiffData::Metrics(metrics) => metrics.print(hostname_filter, stat_name_filter, table_name_filter, &(options.details_enable || options.rollup_table), &options.gauges_enable, &options.sort_by_pct, &options.metric_type, &options.diff_context, &options.highlight_outliers.then_some(options.outlier_sigma), computes, &metrics::MetricDiffThreshold { delta: options.diff_threshold, pct: options.pct_threshold }, config).await,
+            AdhocDiffData::Statements(statements) => statements.print(hostname_filter, options.sql_length, config).await,
+            AdhocDiffData::NodeExporter(node_exporter) => node_exporter.print(hostname_filter, stat_name_filter, &options.gauges_enable, &options.details_enable, config),
+            AdhocDiffData::Entities(entities) => entities.print(config),
+            AdhocDiffData::Masters(masters) => masters.print(config),
+            AdhocDiffData::TabletServers(tablet_servers) => tablet_servers.print(config),
+            AdhocDiffData::Vars(vars) => vars.print(config),
+            AdhocDiffData::Versions(versions) => versions.print(hostname_filter, config),
+            AdhocDiffData::HealthCheck(health_check) => health_check.print(config),
+            AdhocDiffData::GFlags(first, second) => utility::print_text_diff("gflags", "begin", "end", &first.text_per_host(), &second.text_per_host(), hostname_filter, config),
+            AdhocDiffData::Drives(first, second) => utility::print_text_diff("drives", "begin", "end", &first.text_per_host(), &second.text_per_host(), hostname_filter, config),
+        }
+    }
+}
+
+/// Take "adhoc" (in memory) snapshots of metrics only:
+/// - metrics (value, coarse_histogram/countsum, ysql/countsumrows)
+///
+/// The idea here to reduce output when it's know lots of 'entities'/objects are created, or other
+/// changed would give too much output to be useful.
+pub async fn adhoc_metrics_diff(
+    hosts: Vec<&'static str>,
+    ports: Vec<&'static str>,
+    parallel: usize,
+    options: &Opts,
+    config: &Arc<Config>,
+) -> Result<()>
+{
+    adhoc_diff_data_types(hosts, ports, parallel, options, &["metrics"], config).await
+}
 
-    println!("Time between snapshots: {:8.3} seconds", (second_snapshot_time - first_snapshot_time).num_milliseconds() as f64 / 1000_f64);
-    entities.lock().await.print();
-    masters.lock().await.print();
-    tablet_servers.lock().await.print();
-    vars.lock().await.print();
-    versions.lock().await.print(&hostname_filter);
-    health_check.lock().await.print();
+/// Take "adhoc" (in memory) snapshots of node_exporter only:
+///
+/// The idea here to reduce output when it's know lots of 'entities'/objects are created, or other
+/// changed would give too much output to be useful.
+pub async fn adhoc_node_exporter_diff(
+    hosts: Vec<&'static str>,
+    ports: Vec<&'static str>,
+    parallel: usize,
+    options: &Opts,
+    config: &Arc<Config>,
+) -> Result<()>
+{
+    adhoc_diff_data_types(hosts, ports, parallel, options, &["node_exporter"], config).await
+}
 
-    Ok(())
+/// Take "adhoc" (in memory) snapshots of the default data types, except metrics, statements and node_exporter.
+pub async fn adhoc_nonmetrics_diff(
+    hosts: Vec<&'static str>,
+    ports: Vec<&'static str>,
+    parallel: usize,
+    options: &Opts,
+    config: &Arc<Config>,
+) -> Result<()>
+{
+    adhoc_diff_data_types(hosts, ports, parallel, options, &["entities", "masters", "tablet_servers", "vars", "versions", "health_check"], config).await
 }
+
 /// This function shows the difference report for the adhoc (in memory) snapshot data that allows to show a difference:
 /// - metrics (value, coarse_histogram/countsum, ysql/countsumrows)
 /// - statements (ysql)
@@ -820,214 +2190,446 @@ pub async fn adhoc_nonmetrics_diff(
 /// - tablet servers (read via master leader)
 /// - vars
 /// - versions
+/// - health_check
+///
+/// If `--only` is set, only the data types in the comma separated list are snapshotted and reported,
+/// which can also be the gflags and the drives.
 pub async fn adhoc_diff(
     hosts: Vec<&'static str>,
     ports: Vec<&'static str>,
     parallel: usize,
     options: &Opts,
+    config: &Arc<Config>,
 ) -> Result<()>
 {
-    info!("ad-hoc mode first snapshot begin");
+    let data_types = match &options.only {
+        Some(only) => parse_adhoc_diff_types(only)?,
+        None => ADHOC_DIFF_TYPES.iter().filter(|adhoc_type| adhoc_type.default).map(|adhoc_type| adhoc_type.name).collect(),
+    };
+    adhoc_diff_data_types(hosts, ports, parallel, options, &data_types, config).await
+}
+
+/// Parse the comma separated list of data types for the adhoc diff, and validate these.
+fn parse_adhoc_diff_types(
+    only: &str,
+) -> Result<Vec<&'static str>>
+{
+    only.split(',')
+        .map(|data_type| data_type.trim())
+        .filter(|data_type| !data_type.is_empty())
+        .map(|data_type| ADHOC_DIFF_TYPES
+            .iter()
+            .find(|adhoc_type| adhoc_type.name == data_type)
+            .map(|adhoc_type| adhoc_type.name)
+            .with_context(|| format!("Unknown data type for adhoc diff: {}, valid are: {}", data_type, ADHOC_DIFF_TYPES.map(|adhoc_type| adhoc_type.name).join(","))))
+        .collect()
+}
+
+/// Take a first (in memory) snapshot of the given data types, wait for enter, take a second snapshot, and report the difference.
+/// Nothing is written to the snapshot directory.
+async fn adhoc_diff_data_types(
+    hosts: Vec<&'static str>,
+    ports: Vec<&'static str>,
+    parallel: usize,
+    options: &Opts,
+    data_types: &[&str],
+    config: &Arc<Config>,
+) -> Result<()>
+{
+    info!("ad-hoc mode first snapshot begin: {}", data_types.join(","));
     let timer = Instant::now();
 
     let stat_name_filter = utility::set_regex(&options.stat_name_match);
     let hostname_filter = utility::set_regex(&options.hostname_match);
     let table_name_filter = utility::set_regex(&options.table_name_match);
+    let computes = metrics::parse_computes(&options.compute)?;
 
     let first_snapshot_time = Local::now();
 
-    let metrics = Arc::new(Mutex::new(metrics::MetricEntityDiff::new()));
-    let statements = Arc::new(Mutex::new(statements::StatementsDiff::new()));
-    let node_exporter = Arc::new(Mutex::new(node_exporter::NodeExporterDiff::new()));
-    let entities = Arc::new(Mutex::new(entities::EntitiesDiff::new()));
-    let masters = Arc::new(Mutex::new(masters::MastersDiff::new()));
-    let tablet_servers = Arc::new(Mutex::new(tablet_servers::TabletServersDiff::new()));
-    let versions = Arc::new(Mutex::new(versions::VersionsDiff::new()));
-    let vars = Arc::new(Mutex::new(vars::VarsDiff::new()));
-    let health_check = Arc::new(Mutex::new(health_check::HealthCheckDiff::new()));
+    // the data types are kept in the order of ADHOC_DIFF_TYPES, which is the order of the report.
+    let adhoc_diffs: Vec<Arc<Mutex<AdhocDiffData>>> = ADHOC_DIFF_TYPES
+        .iter()
+        .filter(|adhoc_type| data_types.contains(&adhoc_type.name))
+        .map(|adhoc_type| Arc::new(Mutex::new((adhoc_type.new)())))
+        .collect();
 
     let hosts = Arc::new(hosts);
     let ports = Arc::new(ports);
-    let details_enable = options.details_enable;
+    let metrics_details_enable = options.details_enable || options.rollup_table;
+    let rollup_table = options.rollup_table;
 
     let mut handles = vec![];
-
-    let clone_metrics = metrics.clone();
-    let clone_hosts = hosts.clone();
-    let clone_ports = ports.clone();
-    let handle = tokio::spawn(async move {
-        clone_metrics.lock().await.adhoc_read_first_snapshot(&clone_hosts, &clone_ports, parallel, details_enable).await;
-    });
-    handles.push(handle);
-
-    let clone_statements = statements.clone();
-    let clone_hosts = hosts.clone();
-    let clone_ports = ports.clone();
-    let handle = tokio::spawn(async move {
-        clone_statements.lock().await.adhoc_read_first_snapshot(&clone_hosts, &clone_ports, parallel).await;
-    });
-    handles.push(handle);
-
-    let clone_node_exporter = node_exporter.clone();
-    let clone_hosts = hosts.clone();
-    let clone_ports = ports.clone();
-    let handle = tokio::spawn(async move {
-        clone_node_exporter.lock().await.adhoc_read_first_snapshot(&clone_hosts, &clone_ports, parallel).await;
-    });
-    handles.push(handle);
-
-    let clone_entities = entities.clone();
-    let clone_hosts = hosts.clone();
-    let clone_ports = ports.clone();
-    let handle = tokio::spawn(async move {
-        clone_entities.lock().await.adhoc_read_first_snapshot(&clone_hosts, &clone_ports, parallel).await;
-    });
-    handles.push(handle);
-
-    let clone_masters = masters.clone();
-    let clone_hosts = hosts.clone();
-    let clone_ports = ports.clone();
-    let handle = tokio::spawn(async move {
-        clone_masters.lock().await.adhoc_read_first_snapshot(&clone_hosts, &clone_ports, parallel).await;
-    });
-    handles.push(handle);
-
-    let clone_tablet_servers = tablet_servers.clone();
-    let clone_hosts = hosts.clone();
-    let clone_ports = ports.clone();
-    let handle = tokio::spawn(async move {
-        clone_tablet_servers.lock().await.adhoc_read_first_snapshot(&clone_hosts, &clone_ports, parallel).await;
-    });
-    handles.push(handle);
-
-    let clone_vars = vars.clone();
-    let clone_hosts = hosts.clone();
-    let clone_ports = ports.clone();
-    let handle = tokio::spawn(async move {
-        clone_vars.lock().await.adhoc_read_first_snapshot(&clone_hosts, &clone_ports, parallel).await;
-    });
-    handles.push(handle);
-
-    let clone_versions = versions.clone();
-    let clone_hosts = hosts.clone();
-    let clone_ports = ports.clone();
-    let handle = tokio::spawn(async move {
-        clone_versions.lock().await.adhoc_read_first_snapshot(&clone_hosts, &clone_ports, parallel).await;
-    });
-    handles.push(handle);
-
-    let clone_health_check = health_check.clone();
-    let clone_hosts = hosts.clone();
-    let clone_ports = ports.clone();
-    let handle = tokio::spawn(async move {
-        clone_health_check.lock().await.adhoc_read_first_snapshot(&clone_hosts, &clone_ports, parallel).await;
-    });
-    handles.push(handle);
+    for adhoc_diff in &adhoc_diffs {
+        let clone_adhoc_diff = adhoc_diff.clone();
+        let clone_hosts = hosts.clone();
+        let clone_ports = ports.clone();
+        let clone_config = config.clone();
+        let handle = tokio::spawn(async move {
+            clone_adhoc_diff.lock().await.read_first_snapshot(&clone_hosts, &clone_ports, parallel, metrics_details_enable, rollup_table, &clone_config).await;
+        });
+        handles.push(handle);
+    }
 
     for handle in handles {
         handle.await.unwrap();
     }
-    info!("ad-hoc metrics diff first snapshot end: {:?}", timer.elapsed());
+    info!("ad-hoc mode first snapshot end: {:?}", timer.elapsed());
 
-    println!("Begin ad-hoc in-memory snapshot created, press enter to create end snapshot for difference calculation.");
+    writeln!(config.output(), "Begin ad-hoc in-memory snapshot created, press enter to create end snapshot for difference calculation.")?;
     let mut input = String::new();
     stdin().read_line(&mut input).expect("failed");
 
-    info!("ad-hoc metrics diff second snapshot begin");
+    info!("ad-hoc mode second snapshot begin");
     let timer = Instant::now();
 
     let second_snapshot_time = Local::now();
+
     let mut handles = vec![];
+    for adhoc_diff in &adhoc_diffs {
+        let clone_adhoc_diff = adhoc_diff.clone();
+        let clone_hosts = hosts.clone();
+        let clone_ports = ports.clone();
+        let clone_config = config.clone();
+        let handle = tokio::spawn(async move {
+            clone_adhoc_diff.lock().await.read_second_snapshot(&clone_hosts, &clone_ports, parallel, &first_snapshot_time, metrics_details_enable, rollup_table, &clone_config).await;
+        });
+        handles.push(handle);
+    }
 
-    let clone_metrics = metrics.clone();
-    let clone_hosts = hosts.clone();
-    let clone_ports = ports.clone();
-    let details_enable = options.details_enable;
+    for handle in handles {
+        handle.await.unwrap();
+    }
+    info!("ad-hoc mode second snapshot end: {:?}", timer.elapsed());
 
-    let handle = tokio::spawn(async move {
-        clone_metrics.lock().await.adhoc_read_second_snapshot(&clone_hosts, &clone_ports, parallel, &first_snapshot_time, details_enable).await;
-    });
-    handles.push(handle);
+    print_adhoc_diff_header(&first_snapshot_time, &second_snapshot_time, config)?;
+    for adhoc_diff in &adhoc_diffs {
+        adhoc_diff.lock().await.print(options, &hostname_filter, &stat_name_filter, &table_name_filter, &computes, config).await?;
+    }
 
-    let clone_statements = statements.clone();
-    let clone_hosts = hosts.clone();
-    let clone_ports = ports.clone();
-    let handle = tokio::spawn(async move {
-        clone_statements.lock().await.adhoc_read_second_snapshot(&clone_hosts, &clone_ports, parallel, &first_snapshot_time).await;
-    });
-    handles.push(handle);
+    Ok(())
+}
 
-    let clone_node_exporter = node_exporter.clone();
-    let clone_hosts = hosts.clone();
-    let clone_ports = ports.clone();
-    let handle = tokio::spawn(async move {
-        clone_node_exporter.lock().await.adhoc_read_second_snapshot(&clone_hosts, &clone_ports, parallel, &first_snapshot_time).await;
-    });
-    handles.push(handle);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn unit_validate_begin_end() {
+        let snapshots: Vec<Snapshot> = [1, 2, 3, 5].iter().map(|number| Snapshot { number: *number, timestamp: Local::now(), comment: String::new(), partial: false }).collect();
+        assert_eq!(format_snapshot_numbers(&snapshots), "1-3,5");
+        assert_eq!(format_snapshot_numbers(&[]), "none");
+        assert_eq!(validate_begin_end(&snapshots, 1, 5).unwrap(), (1, 5));
+        assert_eq!(validate_begin_end(&snapshots, 5, 2).unwrap(), (2, 5));
+        let error = validate_begin_end(&snapshots, 4, 6).unwrap_err().to_string();
+        assert!(validate_begin_end(&snapshots, 4, 5).unwrap_err().to_string().starts_with("Snapshot 4 does not exist"));
+        assert_eq!(error, "Snapshots 4 and 6 do not exist, available snapshots: 1-3,5");
+    }
 
-    let clone_entities = entities.clone();
-    let clone_hosts = hosts.clone();
-    let clone_ports = ports.clone();
-    let handle = tokio::spawn(async move {
-        clone_entities.lock().await.adhoc_read_second_snapshot(&clone_hosts, &clone_ports, parallel).await;
-    });
-    handles.push(handle);
+    #[test]
+    fn unit_parse_adhoc_diff_types() {
+        assert_eq!(parse_adhoc_diff_types("masters,versions").unwrap(), vec!["masters", "versions"]);
+        assert_eq!(parse_adhoc_diff_types(" gflags , drives,").unwrap(), vec!["gflags", "drives"]);
+        assert!(parse_adhoc_diff_types("").unwrap().is_empty());
+        let error = parse_adhoc_diff_types("masters,tablets").unwrap_err().to_string();
+        assert_eq!(error, "Unknown data type for adhoc diff: tablets, valid are: metrics,statements,node_exporter,entities,masters,tablet_servers,vars,versions,health_check,gflags,drives");
+    }
 
-    let clone_masters = masters.clone();
-    let clone_hosts = hosts.clone();
-    let clone_ports = ports.clone();
-    let handle = tokio::spawn(async move {
-        clone_masters.lock().await.adhoc_read_second_snapshot(&clone_hosts, &clone_ports, parallel).await;
-    });
-    handles.push(handle);
+    #[test]
+    fn unit_partial_after_retry() {
+        let config = Config::default();
+        assert!(!partial_after_retry(&[], &config));
+        assert!(partial_after_retry(&["localhost:9000/metrics".to_string()], &config));
+        // an interrupt skips the requests instead of failing them, which leaves the data missing too.
+        utility::set_interrupted(&config);
+        assert!(utility::http_get("localhost", "1", "metrics", &config).is_empty());
+        assert!(utility::take_failed_request_urls(&config).is_empty());
+        assert!(partial_after_retry(&[], &config));
+    }
 
-    let clone_tablet_servers = tablet_servers.clone();
-    let clone_hosts = hosts.clone();
-    let clone_ports = ports.clone();
-    let handle = tokio::spawn(async move {
-        clone_tablet_servers.lock().await.adhoc_read_second_snapshot(&clone_hosts, &clone_ports, parallel).await;
-    });
-    handles.push(handle);
+    #[test]
+    fn unit_snapshot_index_partial() {
+        // an index written by an older version has no partial column, its snapshots are not partial.
+        let index = "number,timestamp,comment\n1,2022-10-24T10:00:00+02:00,old\n";
+        let snapshots: Vec<Snapshot> = csv::Reader::from_reader(index.as_bytes()).deserialize().collect::<Result<_, _>>().unwrap();
+        assert!(!snapshots[0].partial);
+        let index = "number,timestamp,comment,partial\n1,2022-10-24T10:00:00+02:00,interrupted,true\n";
+        let snapshots: Vec<Snapshot> = csv::Reader::from_reader(index.as_bytes()).deserialize().collect::<Result<_, _>>().unwrap();
+        assert!(snapshots[0].partial);
+    }
 
-    let clone_vars = vars.clone();
-    let clone_hosts = hosts.clone();
-    let clone_ports = ports.clone();
-    let handle = tokio::spawn(async move {
-        clone_vars.lock().await.adhoc_read_second_snapshot(&clone_hosts, &clone_ports, parallel).await;
-    });
-    handles.push(handle);
+    #[test]
+    fn unit_retry_hosts_per_port() {
+        // a snapshot of which the metrics request of yb-2:9000 failed, and every request of yb-3, which was down.
+        let errors = vec![
+            "yb-2:9000/metrics?reset_histograms=false".to_string(),
+            "yb-3:7000/metrics?reset_histograms=false".to_string(),
+            "yb-3:7000/api/v1/masters".to_string(),
+            "yb-3:9000/metrics?reset_histograms=false".to_string(),
+            "yb-3:9000/api/v1/varz".to_string(),
+        ];
+        let retry = RetryCombinations { hosts: &["yb-1", "yb-2", "yb-3"], ports: &["7000", "9000"], errors: &errors };
+        let config = Config { port_roles: HashMap::from([("7000".to_string(), "master".to_string()), ("9000".to_string(), "tserver".to_string())]), ..Default::default() };
+        // only the failed combinations are retried, not every combination without rows.
+        assert_eq!(retry_hosts_per_port(&retry, "metrics", true, &config), vec![
+            ("7000", vec!["yb-3"]),
+            ("9000", vec!["yb-2", "yb-3"]),
+        ]);
+        assert_eq!(retry_hosts_per_port(&retry, "masters", true, &config), vec![("7000", vec!["yb-3"])]);
+        assert!(retry_hosts_per_port(&retry, "threads", true, &config).is_empty());
+        // a data type that is missing in the snapshot is retried from all the combinations that serve it.
+        assert_eq!(retry_hosts_per_port(&retry, "masters", false, &config), vec![("7000", vec!["yb-1", "yb-2", "yb-3"])]);
+        assert_eq!(retry_hosts_per_port(&retry, "tablets", false, &config), vec![("9000", vec!["yb-1", "yb-2", "yb-3"])]);
+
+        // the failed requests are updated per combination: yb-2 and yb-3:7000 succeed now, yb-3:9000 fails again.
+        let retried: HashSet<String> = [
+            "yb-2:9000/metrics?reset_histograms=false",
+            "yb-3:7000/metrics?reset_histograms=false",
+            "yb-3:9000/metrics?reset_histograms=false",
+            "yb-3:7000/api/v1/masters",
+            "yb-3:9000/api/v1/varz",
+        ].iter().map(|url| url.to_string()).collect();
+        let failed = vec!["yb-3:9000/metrics?reset_histograms=false".to_string(), "yb-3:9000/api/v1/varz".to_string()];
+        assert_eq!(errors_after_retry(&errors, &retried, failed), vec![
+            "yb-3:9000/metrics?reset_histograms=false",
+            "yb-3:9000/api/v1/varz",
+        ]);
+        // the requests that are not retried, such as the ones of data types that cannot be retried, are kept.
+        let errors = vec!["yb-1:9000/tablets".to_string()];
+        assert_eq!(errors_after_retry(&errors, &HashSet::new(), Vec::new()), errors);
+
+        assert_eq!(parse_failed_request("yb-1:9000/api/v1/varz"), Some(("yb-1", "9000", "api/v1/varz")));
+        assert_eq!(parse_failed_request("yb-1"), None);
+        let rows = vec![
+            serde_json::json!({ "hostname_port": "yb-1:7000", "name": "a" }),
+            serde_json::json!({ "hostname_port": "yb-1:7000", "name": "b" }),
+            serde_json::json!({ "hostname_port": "yb-2:9000" }),
+        ];
+        assert_eq!(hostname_ports(&rows).len(), 2);
+    }
 
-    let clone_versions = versions.clone();
-    let clone_hosts = hosts.clone();
-    let clone_ports = ports.clone();
-    let handle = tokio::spawn(async move {
-        clone_versions.lock().await.adhoc_read_second_snapshot(&clone_hosts, &clone_ports, parallel).await;
-    });
-    handles.push(handle);
+    #[test]
+    fn unit_parse_snapshot_numbers() {
+        assert_eq!(parse_snapshot_numbers("3").unwrap(), vec![3]);
+        assert_eq!(parse_snapshot_numbers("3,5,7-9").unwrap(), vec![3, 5, 7, 8, 9]);
+        assert_eq!(parse_snapshot_numbers(" 7 - 9 , 3 ").unwrap(), vec![3, 7, 8, 9]);
+        // duplicates and overlapping ranges are deleted once
+        assert_eq!(parse_snapshot_numbers("5,4-6,5,6").unwrap(), vec![4, 5, 6]);
+        assert_eq!(parse_snapshot_numbers("4-4").unwrap(), vec![4]);
+        // a reversed range is not silently turned around, because this selects snapshots to delete
+        assert!(parse_snapshot_numbers("9-7").is_err());
+        assert!(parse_snapshot_numbers("").is_err());
+        assert!(parse_snapshot_numbers(",").is_err());
+        assert!(parse_snapshot_numbers("a").is_err());
+        assert!(parse_snapshot_numbers("3,x").is_err());
+        assert!(parse_snapshot_numbers("-3").is_err());
+        assert!(parse_snapshot_numbers("3-").is_err());
+        assert!(parse_snapshot_numbers("1-2-3").is_err());
+    }
 
-    let clone_health_check = health_check.clone();
-    let clone_hosts = hosts.clone();
-    let clone_ports = ports.clone();
-    let handle = tokio::spawn(async move {
-        clone_health_check.lock().await.adhoc_read_second_snapshot(&clone_hosts, &clone_ports, parallel).await;
-    });
-    handles.push(handle);
+    #[test]
+    fn unit_remove_all_snapshots() {
+        let yb_stats_directory = env::temp_dir().join(format!("yb_stats_unit_remove_all_snapshots_{}", std::process::id()));
+        assert_eq!(Snapshot::insert_snapshot_number_in(&yb_stats_directory, Local::now(), String::new()).unwrap(), 0);
+        assert_eq!(Snapshot::insert_snapshot_number_in(&yb_stats_directory, Local::now(), String::new()).unwrap(), 1);
+        remove_snapshots(&yb_stats_directory, &[0, 1]).unwrap();
+        assert!(Snapshot::read_snapshot_index_in(&yb_stats_directory).unwrap().is_empty());
+        assert!(!yb_stats_directory.join("1").exists());
+        // a snapshot after all snapshots are removed starts at 0 again.
+        assert_eq!(Snapshot::insert_snapshot_number_in(&yb_stats_directory, Local::now(), String::new()).unwrap(), 0);
+        assert_eq!(Snapshot::read_snapshot_index_in(&yb_stats_directory).unwrap().len(), 1);
+        fs::remove_dir_all(&yb_stats_directory).unwrap();
+    }
 
-    for handle in handles {
-        handle.await.unwrap();
+    #[test]
+    fn unit_last_begin_end() {
+        let snapshots: Vec<Snapshot> = [1, 2, 3, 5].iter().map(|number| Snapshot { number: *number, timestamp: Local::now(), comment: String::new(), partial: false }).collect();
+        assert_eq!(last_begin_end(&snapshots, 1).unwrap(), (3, 5));
+        assert_eq!(last_begin_end(&snapshots, 3).unwrap(), (1, 5));
+        assert_eq!(last_begin_end(&snapshots, 4).unwrap_err().to_string(), "--last 4 requires at least 5 snapshots, available snapshots: 1-3,5");
+        assert!(last_begin_end(&snapshots[..1], 1).is_err());
+        assert!(last_begin_end(&snapshots, 0).is_err());
     }
-    info!("ad-hoc metrics diff second snapshot end: {:?}", timer.elapsed());
 
-    println!("Time between snapshots: {:8.3} seconds", (second_snapshot_time - first_snapshot_time).num_milliseconds() as f64 / 1000_f64);
-    metrics.lock().await.print(&hostname_filter, &stat_name_filter, &table_name_filter, &options.details_enable, &options.gauges_enable).await;
-    statements.lock().await.print(&hostname_filter, options.sql_length).await;
-    node_exporter.lock().await.print(&hostname_filter, &stat_name_filter, &options.gauges_enable, &options.details_enable);
-    entities.lock().await.print();
-    masters.lock().await.print();
-    tablet_servers.lock().await.print();
-    vars.lock().await.print();
-    versions.lock().await.print(&hostname_filter);
-    health_check.lock().await.print();
+    #[test]
+    fn unit_scheduled_snapshot_comment() {
+        assert_eq!(scheduled_snapshot_comment(&None, 2, Some(10)), "snapshot 2/10");
+        assert_eq!(scheduled_snapshot_comment(&None, 2, None), "snapshot 2");
+        assert_eq!(scheduled_snapshot_comment(&Some("load test".to_string()), 1, Some(3)), "load test (snapshot 1/3)");
+    }
+    #[test]
+    fn unit_snapshot_row_counts() {
+        let config = &Config::default();
+        config.snapshot_row_counts.lock().unwrap().insert(-2, BTreeMap::from([("threads".to_string(), 1902), ("metrics".to_string(), 48213), ("statements".to_string(), 0)]));
+        assert_eq!(format_row_counts(&take_snapshot_row_counts(-2, config)), "metrics: 48213, statements: 0, threads: 1902");
+        assert!(take_snapshot_row_counts(-2, config).is_empty());
+    }
+    #[test]
+    fn unit_manifest_problems() {
+        let manifest = SnapshotManifest {
+            yb_stats_version: "0.9.8".to_string(),
+            timestamp: Local::now(),
+            hosts: vec!["yb-1".to_string()],
+            ports: vec!["9000".to_string()],
+            parallel: 1,
+            extra_data: false,
+            data_types: BTreeMap::from([("metrics".to_string(), 10), ("threads".to_string(), 5), ("vars".to_string(), 3)]),
+            errors: Vec::new(),
+        };
+        let row_counts = BTreeMap::from([("metrics".to_string(), 10), ("threads".to_string(), 4), ("versions".to_string(), 1)]);
+        assert_eq!(manifest_problems(&manifest, &row_counts), vec![
+            "threads: 4 rows, the manifest has 5 rows",
+            "vars: in the manifest with 3 rows, but not readable in the snapshot",
+            "versions: not in the manifest",
+        ]);
+        assert!(manifest_problems(&manifest, &manifest.data_types).is_empty());
+    }
+    /// The versions data of a snapshot taken before the format version was added (version 0).
+    const VERSIONS_VERSION_0: &str = r#"[{"hostname_port":"yb-1:7000","timestamp":"2022-11-20T14:17:32.186214+01:00","git_hash":"d4f01a5e26b168585e59f9c1a95766ffdd9655b1","build_hostname":"alma8-gcp-cloud-jenkins-worker-3ako4v","build_timestamp":"16 Nov 2022 00:21:52 UTC","build_username":"jenkins","build_clean_repo":true,"build_id":"221","build_type":"RELEASE","version_number":"2.17.0.0","build_number":"24"}]"#;
+    /// Two migrations as these are added when the layout changes: a renamed field, and a field that gets a default.
+    const SYNTHETIC_MIGRATIONS: [(u32, &str, SnapshotMigration); 2] = [
+        (0, "rename build_id to build", |_, rows| rows.iter_mut()
+            .filter_map(|row| row.as_object_mut())
+            .for_each(|row| if let Some(build_id) = row.remove("build_id") { row.insert("build".to_string(), build_id); })),
+        (1, "add build_arch", |data_type, rows| if data_type == "versions" { rows.iter_mut()
+            .filter_map(|row| row.as_object_mut())
+            .for_each(|row| { row.entry("build_arch").or_insert("x86_64".into()); }) }),
+    ];
+
+    #[test]
+    fn unit_migrate_snapshot_directory() {
+        let snapshot_directory = env::temp_dir().join(format!("yb_stats_unit_migrate_{}", std::process::id()));
+        fs::create_dir_all(&snapshot_directory).unwrap();
+        fs::write(snapshot_directory.join("versions.json"), VERSIONS_VERSION_0).unwrap();
+        // a snapshot without a format version is version 0, and is migrated once.
+        assert_eq!(read_format_version(&snapshot_directory).unwrap(), 0);
+        assert_eq!(migrate_snapshot_directory(&snapshot_directory, &SNAPSHOT_MIGRATIONS).unwrap(), Some(0));
+        assert_eq!(read_format_version(&snapshot_directory).unwrap(), SNAPSHOT_FORMAT_VERSION);
+        assert_eq!(migrate_snapshot_directory(&snapshot_directory, &SNAPSHOT_MIGRATIONS).unwrap(), None);
+        let versions: Vec<versions::Version> = read_snapshot_json_file(&snapshot_directory, "versions", SNAPSHOT_FORMAT_VERSION, &SNAPSHOT_MIGRATIONS, &Config::default()).unwrap();
+        assert_eq!(versions[0].version_number, "2.17.0.0");
+        // a snapshot of a newer version is not read.
+        write_format_version(&snapshot_directory, SNAPSHOT_FORMAT_VERSION + 1).unwrap();
+        assert!(migrate_snapshot_directory(&snapshot_directory, &SNAPSHOT_MIGRATIONS).is_err());
+        assert!(read_snapshot_format_version(&snapshot_directory).is_err());
+        fs::remove_dir_all(&snapshot_directory).unwrap();
+    }
 
-    Ok(())
+    #[test]
+    fn unit_migrate_snapshot_directory_synthetic_migration() {
+        let snapshot_directory = env::temp_dir().join(format!("yb_stats_unit_migrate_synthetic_{}", std::process::id()));
+        fs::create_dir_all(&snapshot_directory).unwrap();
+        fs::write(snapshot_directory.join("versions.json"), VERSIONS_VERSION_0).unwrap();
+        // reading migrates the data of version 0 in memory only.
+        let rows: Vec<serde_json::Value> = read_snapshot_json_file(&snapshot_directory, "versions", 0, &SYNTHETIC_MIGRATIONS[..1], &Config::default()).unwrap();
+        assert_eq!(rows[0]["build"], "221");
+        assert!(rows[0].get("build_id").is_none());
+        assert_eq!(fs::read_to_string(snapshot_directory.join("versions.json")).unwrap(), VERSIONS_VERSION_0);
+        // the migration in place rewrites the data and the format version.
+        assert_eq!(migrate_snapshot_directory(&snapshot_directory, &SYNTHETIC_MIGRATIONS[..1]).unwrap(), Some(0));
+        assert_eq!(read_format_version(&snapshot_directory).unwrap(), SNAPSHOT_FORMAT_VERSION);
+        let rows: Vec<serde_json::Value> = read_snapshot_json_file(&snapshot_directory, "versions", SNAPSHOT_FORMAT_VERSION, &SYNTHETIC_MIGRATIONS[..1], &Config::default()).unwrap();
+        assert_eq!(rows[0]["build"], "221");
+        assert_eq!(rows[0]["version_number"], "2.17.0.0");
+        assert_eq!(migrate_snapshot_directory(&snapshot_directory, &SYNTHETIC_MIGRATIONS[..1]).unwrap(), None);
+        fs::remove_dir_all(&snapshot_directory).unwrap();
+    }
+
+    #[test]
+    fn unit_read_snapshot_parquet() {
+        let snapshot_directory = env::temp_dir().join(format!("yb_stats_unit_read_parquet_{}", std::process::id()));
+        fs::create_dir_all(&snapshot_directory).unwrap();
+        let metrics = r#"[{"hostname_port":"yb-1:9000","timestamp":"2023-01-01T10:00:00+00:00","type":"tablet","id":"tablet_1","attributes":{"stream_id":null,"namespace_name":"yugabyte","table_name":"t","table_id":"000033e8000030008000000000004000"},"metrics":[{"name":"rows_inserted","value":10},{"name":"rows_deleted","value":2}]},
+                          {"hostname_port":"yb-1:9000","timestamp":"2023-01-01T10:00:00+00:00","type":"server","id":"yb.tabletserver","attributes":null,"metrics":[{"name":"handler_latency","total_count":5,"min":1,"mean":2.0,"percentile_75":2,"percentile_95":3,"percentile_99":4,"percentile_99_9":4,"percentile_99_99":4,"max":4,"total_sum":10}]}]"#;
+        write_parquet_rows(&snapshot_directory.join("metrics.parquet"), "metrics", serde_json::from_str(metrics).unwrap()).unwrap();
+        // a data type saved with --format parquet is read the same as the JSON data, so a diff can use it.
+        let rows: Vec<metrics::MetricEntity> = read_snapshot_json_file(&snapshot_directory, "metrics", SNAPSHOT_FORMAT_VERSION, &SNAPSHOT_MIGRATIONS, &Config::default()).unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].metrics.len(), 2);
+        let rows: Vec<serde_json::Value> = read_snapshot_json_file(&snapshot_directory, "metrics", SNAPSHOT_FORMAT_VERSION, &SNAPSHOT_MIGRATIONS, &Config::default()).unwrap();
+        assert_eq!(serde_json::Value::from(rows), serde_json::to_value(serde_json::from_str::<Vec<metrics::MetricEntity>>(metrics).unwrap()).unwrap());
+        // the JSON file is read if both are present, and the other data types are not read from parquet.
+        fs::write(snapshot_directory.join("metrics.json"), "[]").unwrap();
+        assert!(read_snapshot_json_file::<serde_json::Value>(&snapshot_directory, "metrics", SNAPSHOT_FORMAT_VERSION, &SNAPSHOT_MIGRATIONS, &Config::default()).unwrap().is_empty());
+        fs::copy(snapshot_directory.join("metrics.parquet"), snapshot_directory.join("vars.parquet")).unwrap();
+        let error = read_snapshot_json_file::<serde_json::Value>(&snapshot_directory, "vars", SNAPSHOT_FORMAT_VERSION, &SNAPSHOT_MIGRATIONS, &Config::default()).unwrap_err().to_string();
+        assert!(error.starts_with("Data type vars cannot be read from parquet"));
+        fs::remove_dir_all(&snapshot_directory).unwrap();
+    }
+
+    #[test]
+    fn unit_snapshot_format_version_once_is_read_only() {
+        let yb_stats_directory = env::temp_dir().join(format!("yb_stats_unit_format_version_once_{}", std::process::id()));
+        fs::create_dir_all(yb_stats_directory.join("1")).unwrap();
+        fs::write(yb_stats_directory.join("1").join("versions.json"), VERSIONS_VERSION_0).unwrap();
+        Snapshot::write_snapshot_index_in(&yb_stats_directory, vec![Snapshot { number: 1, timestamp: Local::now(), comment: String::new(), partial: false }]).unwrap();
+        fs::remove_file(yb_stats_directory.join(FORMAT_VERSION_FILE)).ok();
+        let config = Config::default();
+        // the index and the snapshot of version 0 are checked once, and reading leaves them at version 0.
+        assert_eq!(snapshot_format_version_once(&yb_stats_directory, "1", &config).unwrap(), 0);
+        assert!(config.snapshot_index_format_checked.load(Ordering::Relaxed));
+        assert_eq!(config.snapshot_format_versions.lock().unwrap().get("1"), Some(&0));
+        assert!(!yb_stats_directory.join(FORMAT_VERSION_FILE).exists());
+        assert!(!yb_stats_directory.join("1").join(FORMAT_VERSION_FILE).exists());
+        // a snapshot that is not found is read as the current version.
+        assert_eq!(snapshot_format_version_once(&yb_stats_directory, "2", &config).unwrap(), SNAPSHOT_FORMAT_VERSION);
+        fs::remove_dir_all(&yb_stats_directory).unwrap();
+    }
+
+    #[test]
+    fn unit_migrate_snapshot_directory_not_writable() {
+        let snapshot_directory = env::temp_dir().join(format!("yb_stats_unit_migrate_not_writable_{}", std::process::id()));
+        fs::create_dir_all(&snapshot_directory).unwrap();
+        fs::write(snapshot_directory.join("versions.json"), VERSIONS_VERSION_0).unwrap();
+        // a directory in place of the format version file makes writing it fail, even for root.
+        fs::create_dir_all(snapshot_directory.join(FORMAT_VERSION_FILE)).unwrap();
+        assert!(migrate_snapshot_directory(&snapshot_directory, &SNAPSHOT_MIGRATIONS).is_err());
+        // reading is not affected, the data of version 0 is migrated in memory.
+        let versions: Vec<versions::Version> = read_snapshot_json_file(&snapshot_directory, "versions", 0, &SNAPSHOT_MIGRATIONS, &Config::default()).unwrap();
+        assert_eq!(versions[0].hostname_port.as_deref(), Some("yb-1:7000"));
+        assert_eq!(versions[0].version_number, "2.17.0.0");
+        fs::remove_dir_all(&snapshot_directory).unwrap();
+    }
+
+    #[test]
+    fn unit_migrate_rows() {
+        let version_0: Vec<serde_json::Value> = serde_json::from_str(VERSIONS_VERSION_0).unwrap();
+
+        let mut rows = version_0.clone();
+        assert!(migrate_rows("versions", &mut rows, 0, &SYNTHETIC_MIGRATIONS));
+        assert_eq!(rows[0]["build"], "221");
+        assert!(rows[0].get("build_id").is_none());
+        assert_eq!(rows[0]["build_arch"], "x86_64");
+        assert_eq!(rows[0]["version_number"], "2.17.0.0");
+
+        let mut rows = version_0.clone();
+        assert!(migrate_rows("versions", &mut rows, 1, &SYNTHETIC_MIGRATIONS));
+        assert_eq!(rows[0]["build_id"], "221");
+        assert_eq!(rows[0]["build_arch"], "x86_64");
+
+        let mut rows = version_0.clone();
+        assert!(!migrate_rows("versions", &mut rows, 2, &SYNTHETIC_MIGRATIONS));
+        assert_eq!(rows, version_0);
+        // the current version 0 data has no migrations.
+        assert!(!migrate_rows("versions", &mut rows, 0, &SNAPSHOT_MIGRATIONS));
+    }
+
+    #[test]
+    fn unit_validate_snapshot_bundle() {
+        let snapshot_bundle = |format_version: u32, data_type: &str| SnapshotBundle {
+            format_version,
+            yb_stats_version: "0.9.8".to_string(),
+            snapshot_format_version: SNAPSHOT_FORMAT_VERSION,
+            snapshot: Snapshot { number: 1, timestamp: Local::now(), comment: String::new(), partial: false },
+            data: BTreeMap::from([(data_type.to_string(), serde_json::Value::Array(Vec::new()))]),
+            files: BTreeMap::new(),
+        };
+        assert!(validate_snapshot_bundle(&snapshot_bundle(SNAPSHOT_BUNDLE_FORMAT_VERSION, "cluster-config")).is_ok());
+        assert!(validate_snapshot_bundle(&snapshot_bundle(SNAPSHOT_BUNDLE_FORMAT_VERSION + 1, "metrics")).is_err());
+        assert!(validate_snapshot_bundle(&snapshot_bundle(SNAPSHOT_BUNDLE_FORMAT_VERSION, "../metrics")).is_err());
+        assert!(validate_snapshot_bundle(&snapshot_bundle(SNAPSHOT_BUNDLE_FORMAT_VERSION, "")).is_err());
+        let mut newer_data = snapshot_bundle(SNAPSHOT_BUNDLE_FORMAT_VERSION, "metrics");
+        newer_data.snapshot_format_version = SNAPSHOT_FORMAT_VERSION + 1;
+        assert!(validate_snapshot_bundle(&newer_data).is_err());
+    }
 }
@@ -1,9 +1,90 @@
+use std::collections::BTreeMap;
 use chrono::{DateTime, Local};
+use anyhow::Result;
+use arrow::record_batch::RecordBatch;
+use crate::{drives, entities, gflags, health_check, masters, metrics, node_exporter, statements, tablet_servers, vars, versions};
 /// Struct to represent the snapshots metadata in yb_stats in a vector as well as on disk as CSV using serde.
 /// The comment can be empty, unless a snapshot is made with the `--snapshot-comment` flag and a comment.
+/// A snapshot is partial if it was interrupted, the field is missing in the index of older versions.
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Snapshot {
     pub number: i32,
     pub timestamp: DateTime<Local>,
     pub comment: String,
+    #[serde(default)]
+    pub partial: bool,
+}
+
+/// The version of the layout of the snapshot data and the snapshot index, which is written in the `format_version` file
+/// of every snapshot directory and of the yb_stats.snapshots directory. Snapshots without the file are version 0.
+/// This must be increased when the layout changes, with a migration for the older snapshots, see `SNAPSHOT_MIGRATIONS`.
+pub const SNAPSHOT_FORMAT_VERSION: u32 = 1;
+
+/// The version of the snapshot bundle layout of `--snapshot-export`.
+/// This must be increased when the layout changes, so an older yb_stats refuses a bundle it cannot read.
+pub const SNAPSHOT_BUNDLE_FORMAT_VERSION: u32 = 1;
+
+/// Struct to represent a complete snapshot as a single JSON document, for `--snapshot-export` and `--snapshot-import`.
+/// The data contains the JSON files of the snapshot keyed by data type (the file name without .json),
+/// the files contains the text files (mems and pprof) keyed by file name.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SnapshotBundle {
+    pub format_version: u32,
+    pub yb_stats_version: String,
+    /// The format version of the snapshot data, missing in the exports of older versions.
+    #[serde(default)]
+    pub snapshot_format_version: u32,
+    pub snapshot: Snapshot,
+    pub data: BTreeMap<String, serde_json::Value>,
+    pub files: BTreeMap<String, String>,
+}
+
+/// Struct to represent the `manifest.json` in a snapshot directory, which is written when a snapshot is created,
+/// so the snapshot describes what it contains without reading the data.
+/// Snapshots created by older versions, and imported snapshots, do not have a manifest.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SnapshotManifest {
+    pub yb_stats_version: String,
+    pub timestamp: DateTime<Local>,
+    pub hosts: Vec<String>,
+    pub ports: Vec<String>,
+    pub parallel: usize,
+    pub extra_data: bool,
+    /// The number of rows per data type (the file name without .json).
+    pub data_types: BTreeMap<String, usize>,
+    /// The requests (`host:port/url`) that failed.
+    pub errors: Vec<String>,
+}
+
+/// The in memory data of a data type of the adhoc diff, which holds the first and the second snapshot.
+pub enum AdhocDiffData {
+    Metrics(metrics::MetricEntityDiff),
+    Statements(statements::StatementsDiff),
+    NodeExporter(node_exporter::NodeExporterDiff),
+    Entities(entities::EntitiesDiff),
+    Masters(masters::MastersDiff),
+    TabletServers(tablet_servers::TabletServersDiff),
+    Vars(vars::VarsDiff),
+    Versions(versions::VersionsDiff),
+    HealthCheck(health_check::HealthCheckDiff),
+    /// The gflags of the first and second snapshot, which are reported as a text diff like --text-diff.
+    GFlags(gflags::AllGFlags, gflags::AllGFlags),
+    /// The drives of the first and second snapshot, which are reported as a text diff.
+    Drives(drives::AllDrives, drives::AllDrives),
+}
+
+/// A data type that is saved as parquet with `--format parquet`: the file name (without .parquet), the function that creates the record batch of its JSON rows,
+/// and the function that reads the JSON rows back from the record batches.
+pub struct ParquetDataType {
+    pub filename: &'static str,
+    pub to_record_batch: fn(Vec<serde_json::Value>) -> Result<RecordBatch>,
+    pub from_record_batches: fn(&[RecordBatch]) -> Result<Vec<serde_json::Value>>,
+}
+
+/// A data type of the adhoc diff: the name for `--only`, whether it is diffed without `--only`,
+/// and the function that creates its empty in memory data.
+pub struct AdhocDiffType {
+    pub name: &'static str,
+    pub default: bool,
+    pub new: fn() -> AdhocDiffData,
 }
@@ -1,14 +1,16 @@
 //! The the impls and functions.
 //!
 use chrono::{DateTime, Local};
-use std::{sync::mpsc::channel, time::Instant};
+use std::{sync::{Arc, mpsc::channel}, time::Instant};
+use arrow::{array::{Array, ArrayRef, Float64Array, Int64Array, StringArray, TimestampMicrosecondArray}, record_batch::RecordBatch};
 use regex::Regex;
 use substring::Substring;
 use log::*;
-use anyhow::Result;
+use anyhow::{Context, Result};
 use crate::utility;
+use crate::utility::Config;
 use crate::snapshot;
-use crate::statements::{Statements, AllStatements, StatementsDiff, GroupedStatements};
+use crate::statements::{Statement, Statements, AllStatements, StatementsDiff, GroupedStatements};
 use crate::Opts;
 
 impl AllStatements {
@@ -17,14 +19,19 @@ impl AllStatements {
         hosts: &Vec<&str>,
         ports: &Vec<&str>,
         snapshot_number: i32,
-        parallel: usize
+        parallel: usize,
+        config: &Config,
     ) -> Result<()>
     {
         info!("begin snapshot");
         let timer = Instant::now();
 
-        let allstatements = AllStatements::read_statements(hosts, ports, parallel).await;
-        snapshot::save_snapshot_json(snapshot_number, "statements", allstatements.statements)?;
+        let allstatements = AllStatements::read_statements(hosts, ports, parallel, config).await;
+        if config.parquet_output {
+            snapshot::save_snapshot_parquet(snapshot_number, "statements", allstatements.record_batch()?, allstatements.statements.len(), config)?;
+        } else {
+            snapshot::save_snapshot_json(snapshot_number, "statements", allstatements.statements, config)?;
+        }
 
         info!("end snapshot: {:?}", timer.elapsed());
         Ok(())
@@ -32,7 +39,8 @@ impl AllStatements {
     pub async fn read_statements (
         hosts: &Vec<&str>,
         ports: &Vec<&str>,
-        parallel: usize
+        parallel: usize,
+        config: &Config,
     ) -> AllStatements
     {
         info!("begin parallel http read");
@@ -46,9 +54,9 @@ impl AllStatements {
                     let tx = tx.clone();
                     s.spawn(move |_| {
                         let detail_snapshot_time = Local::now();
-                        let mut statements = AllStatements::read_http(host, port);
+                        let mut statements = AllStatements::read_http(host, port, config);
                         statements.timestamp = Some(detail_snapshot_time);
-                        statements.hostname_port = Some(format!("{}:{}", host, port));
+                        statements.hostname_port = Some(utility::hostname_port(host, port, config));
                         tx.send(statements).expect("error sending data via tx");
                     });
                 }
@@ -72,11 +80,81 @@ impl AllStatements {
     pub fn read_http(
         host: &str,
         port: &str,
+        config: &Config,
     ) -> Statements
     {
-        let data_from_http = utility::http_get(host, port, "statements");
+        let data_from_http = utility::http_get_endpoint(host, port, "statements", config);
         AllStatements::parse_statements(data_from_http, host, port)
     }
+    /// Create a record batch with a row per statement, for saving as parquet.
+    pub fn record_batch(
+        &self,
+    ) -> Result<RecordBatch>
+    {
+        let statements: Vec<(&Statements, &Statement)> = self.statements
+            .iter()
+            .flat_map(|statements| statements.statements.iter().map(move |statement| (statements, statement)))
+            .collect();
+        RecordBatch::try_from_iter(vec![
+            ("hostname_port", Arc::new(StringArray::from(statements.iter().map(|(statements, _)| statements.hostname_port.clone()).collect::<Vec<_>>())) as ArrayRef),
+            ("timestamp", Arc::new(TimestampMicrosecondArray::from(statements.iter().map(|(statements, _)| statements.timestamp.map(|timestamp| timestamp.timestamp_micros())).collect::<Vec<_>>()).with_timezone("UTC")) as ArrayRef),
+            ("query_id", Arc::new(Int64Array::from(statements.iter().map(|(_, statement)| statement.query_id).collect::<Vec<_>>())) as ArrayRef),
+            ("query", Arc::new(StringArray::from_iter_values(statements.iter().map(|(_, statement)| &statement.query))) as ArrayRef),
+            ("calls", Arc::new(Int64Array::from_iter_values(statements.iter().map(|(_, statement)| statement.calls))) as ArrayRef),
+            ("total_time", Arc::new(Float64Array::from_iter_values(statements.iter().map(|(_, statement)| statement.total_time))) as ArrayRef),
+            ("min_time", Arc::new(Float64Array::from_iter_values(statements.iter().map(|(_, statement)| statement.min_time))) as ArrayRef),
+            ("max_time", Arc::new(Float64Array::from_iter_values(statements.iter().map(|(_, statement)| statement.max_time))) as ArrayRef),
+            ("mean_time", Arc::new(Float64Array::from_iter_values(statements.iter().map(|(_, statement)| statement.mean_time))) as ArrayRef),
+            ("stddev_time", Arc::new(Float64Array::from_iter_values(statements.iter().map(|(_, statement)| statement.stddev_time))) as ArrayRef),
+            ("rows", Arc::new(Int64Array::from_iter_values(statements.iter().map(|(_, statement)| statement.rows))) as ArrayRef),
+        ]).with_context(|| "Error creating the statements record batch")
+    }
+    /// Read the statements back from the record batches of a parquet snapshot, see [AllStatements::record_batch].
+    /// The statements of a host are consecutive rows.
+    pub fn from_record_batches(
+        record_batches: &[RecordBatch],
+    ) -> Result<AllStatements>
+    {
+        let mut allstatements = AllStatements::new();
+        for record_batch in record_batches
+        {
+            let hostname_port = snapshot::parquet_column::<StringArray>(record_batch, "hostname_port")?;
+            let timestamp = snapshot::parquet_column::<TimestampMicrosecondArray>(record_batch, "timestamp")?;
+            let query_id = snapshot::parquet_column::<Int64Array>(record_batch, "query_id")?;
+            let query = snapshot::parquet_column::<StringArray>(record_batch, "query")?;
+            let calls = snapshot::parquet_column::<Int64Array>(record_batch, "calls")?;
+            let total_time = snapshot::parquet_column::<Float64Array>(record_batch, "total_time")?;
+            let min_time = snapshot::parquet_column::<Float64Array>(record_batch, "min_time")?;
+            let max_time = snapshot::parquet_column::<Float64Array>(record_batch, "max_time")?;
+            let mean_time = snapshot::parquet_column::<Float64Array>(record_batch, "mean_time")?;
+            let stddev_time = snapshot::parquet_column::<Float64Array>(record_batch, "stddev_time")?;
+            let rows = snapshot::parquet_column::<Int64Array>(record_batch, "rows")?;
+            for row in 0..record_batch.num_rows()
+            {
+                let statement = Statement {
+                    query_id: query_id.is_valid(row).then(|| query_id.value(row)),
+                    query: query.value(row).to_string(),
+                    calls: calls.value(row),
+                    total_time: total_time.value(row),
+                    min_time: min_time.value(row),
+                    max_time: max_time.value(row),
+                    mean_time: mean_time.value(row),
+                    stddev_time: stddev_time.value(row),
+                    rows: rows.value(row),
+                };
+                let row_hostname_port = hostname_port.is_valid(row).then(|| hostname_port.value(row).to_string());
+                let row_timestamp = timestamp.is_valid(row)
+                    .then(|| DateTime::from_timestamp_micros(timestamp.value(row)).map(|timestamp| timestamp.with_timezone(&Local)))
+                    .flatten();
+                match allstatements.statements.last_mut()
+                {
+                    Some(statements) if statements.hostname_port == row_hostname_port && statements.timestamp == row_timestamp => statements.statements.push(statement),
+                    _ => allstatements.statements.push(Statements { hostname_port: row_hostname_port, timestamp: row_timestamp, statements: vec![statement] }),
+                }
+            }
+        }
+        Ok(allstatements)
+    }
     fn parse_statements(
         http_data: String,
         host: &str,
@@ -97,16 +175,17 @@ impl StatementsDiff {
         begin_snapshot: &String,
         end_snapshot: &String,
         begin_snapshot_time: &DateTime<Local>,
+        config: &Config,
     ) -> Result<StatementsDiff>
     {
         let mut statementsdiff = StatementsDiff::new();
 
         let mut allstatements = AllStatements::new();
-        allstatements.statements = snapshot::read_snapshot_json(begin_snapshot, "statements")?;
+        allstatements.statements = snapshot::read_snapshot_json(begin_snapshot, "statements", config)?;
         statementsdiff.first_snapshot(allstatements);
 
         let mut allstatements = AllStatements::new();
-        allstatements.statements = snapshot::read_snapshot_json(end_snapshot, "statements")?;
+        allstatements.statements = snapshot::read_snapshot_json(end_snapshot, "statements", config)?;
         statementsdiff.second_snapshot(allstatements, begin_snapshot_time);
 
         Ok(statementsdiff)
@@ -166,12 +245,15 @@ impl StatementsDiff {
             }
         }
     }
+    /// Print the statements that are executed between the snapshots, and return the number of these statements.
     pub async fn print(
         &self,
         hostname_filter: &Regex,
         sql_length: usize,
-    )
+        config: &Config,
+    ) -> Result<usize>
     {
+        let mut rows = utility::RowWriter::new(config);
         for ((hostname, query), statements_row) in &self.btreestatementsdiff
         {
             if hostname_filter.is_match(hostname)
@@ -179,28 +261,32 @@ impl StatementsDiff {
             {
                 let adaptive_length = if query.len() < sql_length { query.len() } else { sql_length };
                 trace!("PRINT {}: second_calls: {}, first_calls: {}, query: {}", hostname, statements_row.second_calls, statements_row.first_calls, query.substring(0,adaptive_length).escape_default());
-                println!("{:20} {:10} avg: {:15.3} tot: {:15.3} ms avg: {:10} tot: {:10} rows: {:0adaptive_length$}",
+                writeln!(rows, "{:20} {:10} avg: {} tot: {} ms avg: {} tot: {:10} rows: {:0adaptive_length$}",
                          hostname,
                          statements_row.second_calls - statements_row.first_calls,
-                         (statements_row.second_total_time - statements_row.first_total_time) / (statements_row.second_calls as f64 - statements_row.first_calls as f64),
-                         statements_row.second_total_time - statements_row.first_total_time,
-                         (statements_row.second_rows - statements_row.first_rows) / (statements_row.second_calls - statements_row.first_calls),
+                         utility::format_float((statements_row.second_total_time - statements_row.first_total_time) / (statements_row.second_calls as f64 - statements_row.first_calls as f64), 15, 3, config),
+                         utility::format_float(statements_row.second_total_time - statements_row.first_total_time, 15, 3, config),
+                         utility::format_float((statements_row.second_rows - statements_row.first_rows) as f64 / (statements_row.second_calls - statements_row.first_calls) as f64, 10, 0, config),
                          statements_row.second_rows - statements_row.first_rows,
                          query.substring(0, adaptive_length).escape_default()
-                );
+                )?;
             } else {
                 trace!("SKIP {}: second_calls: {}, first_calls: {}, query: {}", hostname, statements_row.second_calls, statements_row.first_calls, query.escape_default());
             }
         }
+        let changes = rows.rows();
+        rows.finish()?;
+        Ok(changes)
     }
     pub async fn adhoc_read_first_snapshot(
         &mut self,
         hosts: &Vec<&str>,
         ports: &Vec<&str>,
         parallel: usize,
+        config: &Config,
     )
     {
-        let allstatements = AllStatements::read_statements(hosts, ports, parallel).await;
+        let allstatements = AllStatements::read_statements(hosts, ports, parallel, config).await;
         self.first_snapshot(allstatements);
     }
     pub async fn adhoc_read_second_snapshot(
@@ -209,19 +295,21 @@ impl StatementsDiff {
         ports: &Vec<&str>,
         parallel: usize,
         first_snapshot_time: &DateTime<Local>,
+        config: &Config,
     )
     {
-        let allstatements = AllStatements::read_statements(hosts, ports, parallel).await;
+        let allstatements = AllStatements::read_statements(hosts, ports, parallel, config).await;
         self.second_snapshot(allstatements, first_snapshot_time);
     }
 }
 
 pub async fn statements_diff(
     options: &Opts,
+    config: &Config,
 ) -> Result<()>
 {
     if options.begin.is_none() || options.end.is_none() {
-        snapshot::Snapshot::print()?;
+        snapshot::Snapshot::print(config)?;
     }
     if options.snapshot_list { return Ok(()) };
 
@@ -230,8 +318,11 @@ pub async fn statements_diff(
 
     let (begin_snapshot, end_snapshot, begin_snapshot_row) = snapshot::Snapshot::read_begin_end_snapshot_from_user(options.begin, options.end)?;
 
-    let statementsdiff = StatementsDiff::snapshot_diff(&begin_snapshot, &end_snapshot, &begin_snapshot_row.timestamp)?;
-    statementsdiff.print(&hostname_filter, sql_length).await;
+    snapshot::Snapshot::print_diff_header(&begin_snapshot, &end_snapshot, config)?;
+
+    let statementsdiff = StatementsDiff::snapshot_diff(&begin_snapshot, &end_snapshot, &begin_snapshot_row.timestamp, config)?;
+    let changes = statementsdiff.print(&hostname_filter, sql_length, config).await?;
+    utility::add_diff_changes(changes, config);
 
     Ok(())
 }
@@ -338,12 +429,73 @@ mod tests {
         assert_eq!(result.statements[0].min_time, 13.76067);
     }
 
+    #[test]
+    fn unit_statements_from_record_batches() {
+        let json = r#"[{"hostname_port":"yb-1:13000","timestamp":"2023-01-01T10:00:00+00:00","statements":[{"query_id":-7776630665575107081,"query":"select 1","calls":1,"total_time":0.60377,"min_time":0.60377,"max_time":0.60377,"mean_time":0.60377,"stddev_time":0.0,"rows":1},
+                                                                                                           {"query_id":null,"query":"select 2","calls":2,"total_time":1.5,"min_time":0.5,"max_time":1.0,"mean_time":0.75,"stddev_time":0.25,"rows":2}]},
+                       {"hostname_port":"yb-2:13000","timestamp":"2023-01-01T10:00:01+00:00","statements":[{"query_id":1,"query":"select 3","calls":3,"total_time":3.0,"min_time":1.0,"max_time":1.0,"mean_time":1.0,"stddev_time":0.0,"rows":3}]}]"#;
+        let allstatements = AllStatements { statements: serde_json::from_str(json).unwrap() };
+        let read_back = AllStatements::from_record_batches(&[allstatements.record_batch().unwrap()]).unwrap();
+        assert_eq!(read_back.statements.len(), 2);
+        assert_eq!(serde_json::to_value(&read_back.statements).unwrap(), serde_json::to_value(&allstatements.statements).unwrap());
+    }
+
+    #[test]
+    fn unit_statements_record_batch() {
+        use arrow::array::Array;
+        let statements_json = r#"
+{
+    "statements":
+    [
+        {
+            "query_id": -7776630665575107081,
+            "query": "select 1",
+            "calls": 1,
+            "total_time": 0.60377,
+            "min_time": 0.60377,
+            "max_time": 0.60377,
+            "mean_time": 0.60377,
+            "stddev_time": 0.0,
+            "rows": 1
+        },
+        {
+            "query": "select 2",
+            "calls": 2,
+            "total_time": 1.5,
+            "min_time": 0.5,
+            "max_time": 1.0,
+            "mean_time": 0.75,
+            "stddev_time": 0.25,
+            "rows": 2
+        }
+    ]
+}"#.to_string();
+        let mut statements = AllStatements::parse_statements(statements_json, "", "");
+        statements.hostname_port = Some("yb-1:13000".to_string());
+        let allstatements = AllStatements { statements: vec![statements] };
+        let record_batch = allstatements.record_batch().unwrap();
+        assert_eq!(record_batch.num_rows(), 2);
+        assert_eq!(record_batch.num_columns(), 11);
+        let hostname_port = record_batch.column_by_name("hostname_port").unwrap().as_any().downcast_ref::<StringArray>().unwrap();
+        assert_eq!(hostname_port.value(1), "yb-1:13000");
+        // a statement without a query_id, or a snapshot without a timestamp, is stored as null.
+        let query_id = record_batch.column_by_name("query_id").unwrap().as_any().downcast_ref::<Int64Array>().unwrap();
+        assert_eq!(query_id.value(0), -7776630665575107081);
+        assert!(query_id.is_null(1));
+        assert!(record_batch.column_by_name("timestamp").unwrap().is_null(0));
+        let calls = record_batch.column_by_name("calls").unwrap().as_any().downcast_ref::<Int64Array>().unwrap();
+        assert_eq!(calls.value(1), 2);
+        let mean_time = record_batch.column_by_name("mean_time").unwrap().as_any().downcast_ref::<Float64Array>().unwrap();
+        assert_eq!(mean_time.value(1), 0.75);
+    }
+
     #[tokio::test]
     async fn integration_parse_statements_ysql() {
+        let config = &Config::default();
         let hostname = utility::get_hostname_ysql();
         let port = utility::get_port_ysql();
 
-        let _allstoredstatements = AllStatements::read_statements(&vec![&hostname], &vec![&port], 1).await;
+        let _allstoredstatements = AllStatements::read_statements(&vec![&hostname], &vec![&port], 1, config).await;
         // likely in a test scenario, there are no SQL commands executed, and thus no rows are returned.
         // to make sure this test works in both the scenario of no statements, and with statements, perform no assertion.
     }
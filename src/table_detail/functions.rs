@@ -1,13 +1,15 @@
 //! The impls and functions
 //!
 use chrono::Local;
-use std::{sync::mpsc::channel, time::Instant};
+use std::{io::{stdin, stdout, IsTerminal, Write}, sync::mpsc::channel, time::Instant};
 use scraper::{Html, Selector};
 use log::*;
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
 
 use crate::isleader::AllIsLeader;
+use crate::entities::AllEntities;
 use crate::utility;
+use crate::utility::Config;
 use crate::snapshot;
 use crate::table_detail::{AllTables, Column, Tablet, Table, TableBasic, TableDetail, Task};
 use crate::Opts;
@@ -36,13 +38,14 @@ impl AllTables {
         snapshot_number: i32,
         parallel: usize,
         extra_data: &bool,
+        config: &Config,
     ) -> Result<()>
     {
         info!("begin snapshot");
         let timer = Instant::now();
 
-        let alltables = AllTables::read_tables(hosts, ports, parallel, extra_data).await;
-        snapshot::save_snapshot_json(snapshot_number, "tables", alltables.table)?;
+        let alltables = AllTables::read_tables(hosts, ports, parallel, extra_data, config).await;
+        snapshot::save_snapshot_json(snapshot_number, "tables", alltables.table, config)?;
 
         info!("end snapshot: {:?}", timer.elapsed());
 
@@ -53,6 +56,7 @@ impl AllTables {
         ports: &Vec<&str>,
         parallel: usize,
         extra_data: &bool,
+        config: &Config,
     ) -> AllTables
     {
         info!("begin parallel http read");
@@ -67,9 +71,9 @@ impl AllTables {
                     let tx = tx.clone();
                     s.spawn(move |_| {
                         let detail_snapshot_time = Local::now();
-                        let mut tables = AllTables::read_http(host, port, extra_data);
+                        let mut tables = AllTables::read_http(host, port, extra_data, config);
                         tables.timestamp = Some(detail_snapshot_time);
-                        tables.hostname_port = Some(format!("{}:{}", host, port));
+                        tables.hostname_port = Some(utility::hostname_port(host, port, config));
                         tx.send(tables).expect("error sending data via tx");
                     });
                 }
@@ -91,13 +95,14 @@ impl AllTables {
         host: &str,
         port: &str,
         extra_data: &bool,
+        config: &Config,
     ) -> Table
     {
-        let data_from_http = utility::http_get(host, port, "tables");
+        let data_from_http = utility::http_get_endpoint(host, port, "tables", config);
         let mut table = AllTables::parse_tables(data_from_http);
         if *extra_data
         {
-            AllTables::parse_tables_add_detail(host, port, &mut table);
+            AllTables::parse_tables_add_detail(host, port, &mut table, config);
         }
         table
     }
@@ -251,12 +256,13 @@ impl AllTables {
     fn parse_tables_add_detail(
         host: &str,
         port: &str,
-        tables: &mut Table
+        tables: &mut Table,
+        config: &Config,
     )
     {
         for row in tables.tablebasic.iter_mut()
         {
-            let data_from_http = utility::http_get(host, port, format!("table?id={}", row.uuid).as_str());
+            let data_from_http = utility::http_get(host, port, format!("table?id={}", row.uuid).as_str(), config);
             let detail = AllTables::parse_table_detail(data_from_http, &row.uuid);
             tables.tabledetail.push(Some(detail));
         }
@@ -393,6 +399,7 @@ impl AllTables {
         &self,
         uuid: &str,
         leader_hostname: String,
+        config: &Config,
     ) -> Result<()>
     {
         for alltables in &self.table
@@ -409,7 +416,7 @@ impl AllTables {
                 #[allow(clippy::collapsible_else_if)]
                 if alltables.tabledetail.is_empty()
                 {
-                    println!("print table detail requires the --extra-data switch");
+                    writeln!(config.output(), "print table detail requires the --extra-data switch")?;
                     return Ok(());
                 }
                 else
@@ -417,7 +424,7 @@ impl AllTables {
                     if let Some(table_detail) = alltables.tabledetail.iter()
                         .find(|row| row.as_ref().unwrap().uuid == *uuid)
                     {
-                        println!("Table UUID: {}, version: {}, type: {}, state: {}, keyspace: {}, object_type: {}, name: {}",
+                        writeln!(config.output(), "Table UUID: {}, version: {}, type: {}, state: {}, keyspace: {}, object_type: {}, name: {}",
                             uuid.to_owned(),
                             table_detail.as_ref().unwrap().version,
                             table_detail.as_ref().unwrap().detail_type,
@@ -425,22 +432,22 @@ impl AllTables {
                             keyspace,
                             object_type,
                             table_name,
-                        );
-                        println!("On disk size: {}", on_disk_size);
-                        println!("Replication info: {}", table_detail.as_ref().unwrap().replication_info);
-                        println!("Columns:");
+                        )?;
+                        writeln!(config.output(), "On disk size: {}", on_disk_size)?;
+                        writeln!(config.output(), "Replication info: {}", table_detail.as_ref().unwrap().replication_info)?;
+                        writeln!(config.output(), "Columns:")?;
                         for column in &table_detail.as_ref().unwrap().columns
                         {
-                            println!("{:4} {:32} {}",
+                            writeln!(config.output(), "{:4} {:32} {}",
                                 column.as_ref().unwrap().id,
                                 column.as_ref().unwrap().column,
                                 column.as_ref().unwrap().column_type,
-                            );
+                            )?;
                         }
-                        println!("Tablets:");
+                        writeln!(config.output(), "Tablets:")?;
                         for tablets in &table_detail.as_ref().unwrap().tablets
                         {
-                            println!("{} {}, Split depth: {}, State: {}, Hidden: {}, Message: {}, Raft: {}",
+                            writeln!(config.output(), "{} {}, Split depth: {}, State: {}, Hidden: {}, Message: {}, Raft: {}",
                                 tablets.as_ref().unwrap().id,
                                 tablets.as_ref().unwrap().partition,
                                 tablets.as_ref().unwrap().split_depth,
@@ -448,30 +455,30 @@ impl AllTables {
                                 tablets.as_ref().unwrap().hidden,
                                 tablets.as_ref().unwrap().message,
                                 tablets.as_ref().unwrap().raftconfig,
-                            );
+                            )?;
                         }
-                        println!("Tasks:");
+                        writeln!(config.output(), "Tasks:")?;
                         for tasks in &table_detail.as_ref().unwrap().tasks
                         {
-                            println!("{} {} {} {} {} {}",
+                            writeln!(config.output(), "{} {} {} {} {} {}",
                                 tasks.as_ref().unwrap().task_name,
                                 tasks.as_ref().unwrap().task_name,
                                 tasks.as_ref().unwrap().state,
                                 tasks.as_ref().unwrap().start_time,
                                 tasks.as_ref().unwrap().duration,
                                 tasks.as_ref().unwrap().description,
-                            );
+                            )?;
                         }
                     }
                     else
                     {
-                        println!("Error: no table detail found");
+                        writeln!(config.output(), "Error: no table detail found")?;
                     }
                 }
             }
             else
             {
-                println!("UUID: {} not found", uuid.to_owned());
+                writeln!(config.output(), "UUID: {} not found", uuid.to_owned())?;
             }
 
         }
@@ -485,24 +492,77 @@ pub async fn print_table_detail(
     ports: Vec<&str>,
     parallel: usize,
     options: &Opts,
+    config: &Config,
 ) -> Result<()>
 {
     match options.print_table_detail.as_ref().unwrap() {
         Some(snapshot_number) => {
             let mut alltables = AllTables::new();
-            alltables.table = snapshot::read_snapshot_json(snapshot_number, "tables")?;
-            let leader_hostname = AllIsLeader::return_leader_snapshot(snapshot_number)?;
-            alltables.print(&options.uuid, leader_hostname)?;
+            alltables.table = snapshot::read_snapshot_json(snapshot_number, "tables", config)?;
+            let leader_hostname = AllIsLeader::return_leader_snapshot(snapshot_number, config)?;
+            let uuid = match &options.table_name {
+                Some(table_name) => {
+                    let mut allentities = AllEntities::new();
+                    allentities.entities = snapshot::read_snapshot_json(snapshot_number, "entities", config)?;
+                    choose_table_id(table_name, allentities.find_tables(&leader_hostname, table_name))?
+                },
+                None => options.uuid.clone(),
+            };
+            alltables.print(&uuid, leader_hostname, config)?;
         },
         None => {
-            let alltables = AllTables::read_tables(&hosts, &ports, parallel, &options.extra_data).await;
-            let leader_hostname = AllIsLeader::return_leader_http(&hosts, &ports, parallel).await;
-            alltables.print(&options.uuid, leader_hostname)?;
+            let alltables = AllTables::read_tables(&hosts, &ports, parallel, &options.extra_data, config).await;
+            let leader_hostname = AllIsLeader::return_leader_http(&hosts, &ports, parallel, config).await;
+            let uuid = match &options.table_name {
+                Some(table_name) => {
+                    let allentities = AllEntities::read_entities(&hosts, &ports, parallel, config).await;
+                    choose_table_id(table_name, allentities.find_tables(&leader_hostname, table_name))?
+                },
+                None => options.uuid.clone(),
+            };
+            alltables.print(&uuid, leader_hostname, config)?;
         },
     }
     Ok(())
 }
 
+/// Choose the table id for `--table-name` from the tables found with the name, see [AllEntities::find_tables].
+///
+/// If the name is found more than once, such as in a YSQL and a YCQL keyspace, the tables are listed,
+/// and the table to use must be chosen; this requires stdin to be a terminal.
+fn choose_table_id(
+    table_name: &str,
+    tables: Vec<(String, String, String, String)>,
+) -> Result<String>
+{
+    match tables.len() {
+        0 => bail!("Table {} not found", table_name),
+        1 => return Ok(tables[0].3.clone()),
+        _ => {},
+    }
+    let candidates = tables.iter()
+        .enumerate()
+        .map(|(number, (keyspace_type, keyspace_name, table_name, table_id))| format!("{:3} {}.{}.{} {}", number + 1, keyspace_type, keyspace_name, table_name, table_id))
+        .collect::<Vec<_>>()
+        .join("\n");
+    if !stdin().is_terminal()
+    {
+        bail!("Table {} is found {} times, use --uuid with the table id:\n{}", table_name, tables.len(), candidates);
+    }
+    println!("Table {} is found {} times:\n{}", table_name, tables.len(), candidates);
+    print!("Choose a table (1-{}): ", tables.len());
+    stdout().flush()?;
+    let mut answer = String::new();
+    stdin().read_line(&mut answer)?;
+    answer.trim()
+        .parse::<usize>()
+        .ok()
+        .and_then(|number| number.checked_sub(1))
+        .and_then(|index| tables.get(index))
+        .map(|(_, _, _, table_id)| table_id.clone())
+        .with_context(|| format!("Invalid choice: '{}'", answer.trim()))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1256,10 +1316,11 @@ mod tests {
 
     #[tokio::test]
     async fn integration_parse_master_tables() {
+        let config = &Config::default();
         let hostname = utility::get_hostname_master();
         let port = utility::get_port_master();
 
-        let alltables = AllTables::read_tables(&vec![&hostname], &vec![&port], 1, &true).await;
+        let alltables = AllTables::read_tables(&vec![&hostname], &vec![&port], 1, &true, config).await;
         // the master returns more than one thread.
         assert!(!alltables.table.is_empty());
     }
@@ -1,17 +1,33 @@
 //! The impls and functions
 //!
-use chrono::Local;
-use std::{sync::mpsc::channel, time::Instant};
+use chrono::{DateTime, Local};
+use std::{cmp::{Ordering, Reverse}, collections::BTreeMap, sync::mpsc::channel, time::Instant};
 use scraper::{Html, Selector};
 use log::*;
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
 use crate::utility;
+use crate::utility::Config;
 use crate::snapshot;
-use crate::tablet_detail::{AllTablets, Tablet, TabletBasic, TabletDetail, Column, ConsensusStatus, Watermark, Message, TabletLogAnchor, Transactions, RocksDb, RocksDbFile};
+use crate::tablet_detail::{AllTablets, Tablet, TabletBasic, TabletDetail, Column, ConsensusStatus, Watermark, Message, TabletLogAnchor, Transactions, RocksDb, RocksDbFile, PeerProgress};
+use crate::metrics::{AllMetricEntity, Metrics};
+use crate::tablet_replication::AllTabletReplication;
+use crate::tablet_server_operations::AllOperations;
 use crate::Opts;
 
 impl Tablet {
     pub fn new() -> Self { Default::default() }
+    /// The intents size of a tablet, which is only known if the tablet detail is read with --extra-data.
+    fn intents_size(
+        &self,
+        tablet_id: &str,
+    ) -> Option<f64>
+    {
+        self.tabletdetail
+            .iter()
+            .flatten()
+            .find(|tabletdetail| tabletdetail.tablet_id == tablet_id)
+            .map(|tabletdetail| intents_size(&tabletdetail.rocksdb))
+    }
 }
 impl TabletDetail {
     pub fn new() -> Self { Default::default() }
@@ -46,13 +62,14 @@ impl AllTablets {
         snapshot_number: i32,
         parallel: usize,
         extra_data: &bool,
+        config: &Config,
     ) -> Result<()>
     {
         info!("begin snapshot");
         let timer = Instant::now();
 
-        let alltablets = AllTablets::read_tablets(hosts, ports, parallel, extra_data).await;
-        snapshot::save_snapshot_json(snapshot_number, "tablets", alltablets.tablet)?;
+        let alltablets = AllTablets::read_tablets(hosts, ports, parallel, extra_data, config).await;
+        snapshot::save_snapshot_json(snapshot_number, "tablets", alltablets.tablet, config)?;
 
         info!("end snapshot: {:?}", timer.elapsed());
 
@@ -63,6 +80,7 @@ impl AllTablets {
         ports: &Vec<&str>,
         parallel: usize,
         extra_data: &bool,
+        config: &Config,
     ) -> AllTablets
     {
         info!("begin parallel http read");
@@ -77,9 +95,9 @@ impl AllTablets {
                     let tx = tx.clone();
                     s.spawn(move |_| {
                         let detail_snapshot_time = Local::now();
-                        let mut tablets = AllTablets::read_http(host, port, extra_data);
+                        let mut tablets = AllTablets::read_http(host, port, extra_data, config);
                         tablets.timestamp = Some(detail_snapshot_time);
-                        tablets.hostname_port = Some(format!("{}:{}", host, port));
+                        tablets.hostname_port = Some(utility::hostname_port(host, port, config));
                         tx.send(tablets).expect("error sending data via tx");
                     });
                 }
@@ -101,17 +119,18 @@ impl AllTablets {
         host: &str,
         port: &str,
         extra_data: &bool,
+        config: &Config,
     ) -> Tablet
     {
-        let data_from_http = utility::http_get(host, port, "tablets");
+        let data_from_http = utility::http_get_endpoint(host, port, "tablets", config);
         let mut tablet = AllTablets::parse_tablets(data_from_http);
         if *extra_data
         {
-            AllTablets::parse_tablets_add_detail(host, port, &mut tablet);
+            AllTablets::parse_tablets_add_detail(host, port, &mut tablet, config);
         }
         tablet
     }
-    fn parse_tablets(
+    pub fn parse_tablets(
         http_data: String
     ) -> Tablet
     {
@@ -230,26 +249,27 @@ impl AllTablets {
     fn parse_tablets_add_detail(
         host: &str,
         port: &str,
-        tablets: &mut Tablet
+        tablets: &mut Tablet,
+        config: &Config,
     )
     {
         for row in tablets.tabletbasic.iter_mut()
         {
-            let data_from_http = utility::http_get(host, port, format!("tablet?id={}", row.tablet_id).as_str());
+            let data_from_http = utility::http_get(host, port, format!("tablet?id={}", row.tablet_id).as_str(), config);
             // This construction is because a tablet that is still shown but tombstoned will not have any details.
             // The page will say 'Tablet <UUID> has not yet started'
             if let Ok(mut detail) = AllTablets::parse_tablet_detail(data_from_http, &row.tablet_id)
             {
-                let data_from_http = utility::http_get(host, port, format!("tablet-consensus-status?id={}", row.tablet_id).as_str());
+                let data_from_http = utility::http_get(host, port, format!("tablet-consensus-status?id={}", row.tablet_id).as_str(), config);
                 let consensus_status = AllTablets::parse_tablet_detail_consensus_status(data_from_http);
                 detail.consensus_status = consensus_status;
-                let data_from_http = utility::http_get(host, port, format!("log-anchors?id={}", row.tablet_id).as_str());
+                let data_from_http = utility::http_get(host, port, format!("log-anchors?id={}", row.tablet_id).as_str(), config);
                 let loganchor = AllTablets::parse_tablet_detail_log_anchors(data_from_http);
                 detail.tabletloganchor = loganchor;
-                let data_from_http = utility::http_get(host, port, format!("transactions?id={}", row.tablet_id).as_str());
+                let data_from_http = utility::http_get(host, port, format!("transactions?id={}", row.tablet_id).as_str(), config);
                 let transaction = AllTablets::parse_tablet_detail_transactions(data_from_http);
                 detail.transactions = transaction;
-                let data_from_http = utility::http_get(host, port, format!("rocksdb?id={}", row.tablet_id).as_str());
+                let data_from_http = utility::http_get(host, port, format!("rocksdb?id={}", row.tablet_id).as_str(), config);
                 let rocksdb = AllTablets::parse_tablet_detail_rocksdb(data_from_http);
                 detail.rocksdb = rocksdb;
                 //detail
@@ -257,6 +277,41 @@ impl AllTablets {
             };
         }
     }
+    /// Read the consensus status of a single tablet from the tablet servers that have a peer of the tablet,
+    /// and add it as tablet detail, so the follower lag can be shown without reading the detail of all tablets with `--extra-data`.
+    pub async fn add_consensus_status(
+        &mut self,
+        tablet_id: &str,
+        parallel: usize,
+        config: &Config,
+    )
+    {
+        info!("begin parallel http read");
+        let timer = Instant::now();
+
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(parallel).build().unwrap();
+        let (tx, rx) = channel();
+        let tablets = &self.tablet;
+        pool.scope(move |s| {
+            for (index, tablet) in tablets.iter().enumerate().filter(|(_, tablet)| tablet.tabletbasic.iter().any(|tabletbasic| tabletbasic.tablet_id == tablet_id))
+            {
+                let Some((host, port)) = tablet.hostname_port.as_deref().and_then(|hostname_port| hostname_port.rsplit_once(':')) else { continue };
+                let tx = tx.clone();
+                s.spawn(move |_| {
+                    let data_from_http = utility::http_get(host, port, format!("tablet-consensus-status?id={}", tablet_id).as_str(), config);
+                    tx.send((index, AllTablets::parse_tablet_detail_consensus_status(data_from_http))).expect("error sending data via tx");
+                });
+            }
+        });
+
+        info!("end parallel http read {:?}", timer.elapsed());
+
+        for (index, consensus_status) in rx
+        {
+            self.tablet[index].tabletdetail.retain(|tabletdetail| tabletdetail.as_ref().is_none_or(|tabletdetail| tabletdetail.tablet_id != tablet_id));
+            self.tablet[index].tabletdetail.push(Some(TabletDetail { tablet_id: tablet_id.to_string(), consensus_status, ..Default::default() }));
+        }
+    }
     fn parse_tablet_detail(
         data_from_http: String,
         tablet_id: &str,
@@ -490,6 +545,7 @@ impl AllTablets {
     pub fn print(
         &self,
         uuid: &str,
+        config: &Config,
     ) -> Result<()>
     {
         for alltablets in &self.tablet
@@ -498,80 +554,80 @@ impl AllTablets {
                 .filter(|row| row.tablet_id == *uuid)
                 .map(|row| (row.namespace.clone(), row.table_name.clone(), row.on_disk_size.clone(), row.state.clone()))
             {
-                println!("{}\n General info:", alltablets.hostname_port.as_ref().unwrap());
-                println!("  Keyspace:       {}", keyspace);
-                println!("  Object name:    {}", table_name);
-                println!("  On disk sizes:  {}", on_disk_size);
-                println!("  State:          {}", state);
+                writeln!(config.output(), "{}\n General info:", alltablets.hostname_port.as_ref().unwrap())?;
+                writeln!(config.output(), "  Keyspace:       {}", keyspace)?;
+                writeln!(config.output(), "  Object name:    {}", table_name)?;
+                writeln!(config.output(), "  On disk sizes:  {}", on_disk_size)?;
+                writeln!(config.output(), "  State:          {}", state)?;
                 if state == *"RUNNING" && alltablets.tabletdetail.iter().any(|row| row.as_ref().unwrap().tablet_id == *uuid)
                 {
                     //
                     // consensus
                     //
-                    println!(" Consensus:");
+                    writeln!(config.output(), " Consensus:")?;
                     if let Some(consensus) = alltablets.tabletdetail.iter()
                         .find(|row| row.as_ref().unwrap().tablet_id == *uuid)
                         .map(|row| &row.as_ref().unwrap().consensus_status)
                     {
-                        println!("  State:          {}", consensus.state);
-                        println!("  Queue overview: {}", consensus.queue_overview.as_ref().unwrap_or(&"".to_string()));
-                        println!("  Watermark:");
+                        writeln!(config.output(), "  State:          {}", consensus.state)?;
+                        writeln!(config.output(), "  Queue overview: {}", consensus.queue_overview.as_ref().unwrap_or(&"".to_string()))?;
+                        writeln!(config.output(), "  Watermark:")?;
                         for watermark in &consensus.watermark
                         {
-                            println!("  - {}", watermark.as_ref().unwrap().watermark);
+                            writeln!(config.output(), "  - {}", watermark.as_ref().unwrap().watermark)?;
                         }
-                        println!("  Messages:");
+                        writeln!(config.output(), "  Messages:")?;
                         for message in &consensus.messages
                         {
-                            println!("  - Entry: {}, Opid: {}, mesg. type: {}, size: {}, status: {}",
+                            writeln!(config.output(), "  - Entry: {}, Opid: {}, mesg. type: {}, size: {}, status: {}",
                                 message.as_ref().unwrap().entry,
                                 message.as_ref().unwrap().opid,
                                 message.as_ref().unwrap().message_type,
                                 message.as_ref().unwrap().size,
                                 message.as_ref().unwrap().status,
-                            );
+                            )?;
 
                         }
                     }
                     //
                     // Tablet LogAnchor
                     //
-                    println!(" LogAnchor:");
+                    writeln!(config.output(), " LogAnchor:")?;
                     for rows in alltablets.tabletdetail.iter()
                         .filter(|row| row.as_ref().unwrap().tablet_id == *uuid)
                         .map(|row| &row.as_ref().unwrap().tabletloganchor.loganchor)
                     {
                        for row in rows
                        {
-                           println!("  {}", row);
+                           writeln!(config.output(), "  {}", row)?;
                        }
 
                     }
                     //
                     // Transactions
                     //
-                    println!(" Transactions:");
+                    writeln!(config.output(), " Transactions:")?;
                     for rows in alltablets.tabletdetail.iter()
                         .filter(|row| row.as_ref().unwrap().tablet_id == *uuid)
                         .map(|row| row.as_ref().unwrap().transactions.transactions.clone())
                     {
                         for row in rows
                         {
-                            println!("  - {}", row);
+                            writeln!(config.output(), "  - {}", row)?;
                         }
                     }
                     //
                     // Rocksdb
                     //
-                    println!(" Rocksdb:");
-                    println!("  IntentDB:");
+                    writeln!(config.output(), " Rocksdb:")?;
+                    writeln!(config.output(), "  IntentDB:")?;
                     for rows in alltablets.tabletdetail.iter()
                         .filter(|row| row.as_ref().unwrap().tablet_id == *uuid)
                         .map(|row| &row.as_ref().unwrap().rocksdb)
                     {
                         for file in &rows.intents_files
                         {
-                            println!("   {} {}, {} {}, {} {}, {}",
+                            writeln!(config.output(), "   {} {}, {} {}, {} {}, {}",
                                      file.split_whitespace().nth(1).unwrap(),
                                      file.split_whitespace().nth(2).unwrap(),
                                      file.split_whitespace().nth(5).unwrap(),
@@ -579,17 +635,17 @@ impl AllTablets {
                                      file.split_whitespace().nth(7).unwrap(),
                                      file.split_whitespace().nth(8).unwrap(),
                                      file.split_whitespace().nth(10).unwrap(),
-                            );
+                            )?;
                         }
                     }
-                    println!("  RegularDB:");
+                    writeln!(config.output(), "  RegularDB:")?;
                     for rows in alltablets.tabletdetail.iter()
                         .filter(|row| row.as_ref().unwrap().tablet_id == *uuid)
                         .map(|row| &row.as_ref().unwrap().rocksdb)
                     {
                         for file in &rows.regular_files
                         {
-                            println!("   {} {}, {} {}, {} {}, {}",
+                            writeln!(config.output(), "   {} {}, {} {}, {} {}, {}",
                                      file.split_whitespace().nth(1).unwrap(),
                                      file.split_whitespace().nth(2).unwrap(),
                                      file.split_whitespace().nth(5).unwrap(),
@@ -597,7 +653,7 @@ impl AllTablets {
                                      file.split_whitespace().nth(7).unwrap(),
                                      file.split_whitespace().nth(8).unwrap(),
                                      file.split_whitespace().nth(10).unwrap(),
-                            );
+                            )?;
                         }
                     }
                 }
@@ -606,6 +662,507 @@ impl AllTablets {
 
         Ok(())
     }
+    /// Print an overview of all tablets with their SST file count, SST files size, WAL files size and intents size.
+    ///
+    /// The SST and WAL sizes are taken from the "On-disk size" column of `/tablets`.
+    /// `/tablets` shows the size of the WAL files but not the number of WAL segments, so there is no WAL segment count.
+    /// If a tablet server version does not show a size, it is left blank.
+    /// The intents size is taken from the rocksdb files of the tablet, which are only read with --extra-data.
+    pub fn print_list(
+        &self,
+        sort_by: &Option<String>,
+        wal_size_warn: f64,
+        intents_size_warn: f64,
+        config: &Config,
+    ) -> Result<()>
+    {
+        let mut rows = Vec::new();
+        for alltablets in &self.tablet
+        {
+            for row in &alltablets.tabletbasic
+            {
+                rows.push((alltablets.hostname_port.as_deref().unwrap_or_default(), row, alltablets.intents_size(&row.tablet_id)));
+            }
+        }
+        if let Some(sort_by) = sort_by
+        {
+            sort_tablets(&mut rows, sort_by)?;
+        }
+
+        let rows: Vec<Vec<String>> = rows
+            .iter()
+            .map(|(hostname_port, row, intents_size)| vec![
+                hostname_port.to_string(),
+                row.namespace.clone(),
+                row.table_name.clone(),
+                row.tablet_id.clone(),
+                row.state.clone(),
+                row.num_sst_files.trim().to_string(),
+                on_disk_size_component(&row.on_disk_size, "SST Files").unwrap_or_default(),
+                on_disk_size_component(&row.on_disk_size, "WAL Files").unwrap_or_default(),
+                intents_size.map(utility::bytes_to_size).unwrap_or_default(),
+                size_flags(&row.on_disk_size, *intents_size, wal_size_warn, intents_size_warn),
+            ])
+            .collect();
+        utility::print_table(&["hostname_port", "namespace", "table_name", "tablet_id", "state", "sst_files", "sst_size", "wal_size", "intents_size", "flags"], &rows, config)?;
+
+        Ok(())
+    }
+    /// Print all tablets of a single tablet server with their role and sizes, sorted on the total size, largest first,
+    /// or on the column of sort_by.
+    ///
+    /// With details_enable, the read and write operations per second of each tablet are added, see [tablet_operations_rates].
+    #[allow(clippy::too_many_arguments)]
+    pub fn print_server(
+        &self,
+        server: &str,
+        sort_by: &Option<String>,
+        tablet_operations_rates: &BTreeMap<String, (f64, f64)>,
+        details_enable: &bool,
+        wal_size_warn: f64,
+        intents_size_warn: f64,
+        config: &Config,
+    ) -> Result<()>
+    {
+        let Some(tablets) = self.tablet.iter().find(|tablet| tablet.hostname_port.as_deref() == Some(server)) else {
+            bail!("Server {} not found in the tablet data, available are: {}",
+                server,
+                self.tablet.iter().filter_map(|tablet| tablet.hostname_port.clone()).collect::<Vec<_>>().join(", ")
+            );
+        };
+        let hostname = server.split(':').next().unwrap_or_default();
+
+        let mut rows: Vec<(&str, &TabletBasic, Option<f64>)> = tablets.tabletbasic.iter().map(|row| (server, row, tablets.intents_size(&row.tablet_id))).collect();
+        sort_tablets(&mut rows, sort_by.as_deref().unwrap_or("total_size"))?;
+
+        let rows: Vec<Vec<String>> = rows
+            .iter()
+            .map(|(_, row, intents_size)| {
+                let mut columns = vec![
+                    row.namespace.clone(),
+                    row.table_name.clone(),
+                    row.tablet_id.clone(),
+                    row.state.clone(),
+                    raft_role(&row.raftconfig, hostname).unwrap_or_default(),
+                    on_disk_size_component(&row.on_disk_size, "Total").unwrap_or_default(),
+                    on_disk_size_component(&row.on_disk_size, "SST Files").unwrap_or_default(),
+                    on_disk_size_component(&row.on_disk_size, "WAL Files").unwrap_or_default(),
+                    intents_size.map(utility::bytes_to_size).unwrap_or_default(),
+                    size_flags(&row.on_disk_size, *intents_size, wal_size_warn, intents_size_warn),
+                ];
+                if *details_enable
+                {
+                    let rates = tablet_operations_rates.get(&row.tablet_id);
+                    columns.push(rates.map(|(reads, _)| utility::format_float(*reads, 0, 3, config)).unwrap_or_default());
+                    columns.push(rates.map(|(_, writes)| utility::format_float(*writes, 0, 3, config)).unwrap_or_default());
+                }
+                columns
+            })
+            .collect();
+        if *details_enable
+        {
+            utility::print_table(&["namespace", "table_name", "tablet_id", "state", "role", "total_size", "sst_size", "wal_size", "intents_size", "flags", "reads/s", "writes/s"], &rows, config)?;
+        }
+        else
+        {
+            utility::print_table(&["namespace", "table_name", "tablet_id", "state", "role", "total_size", "sst_size", "wal_size", "intents_size", "flags"], &rows, config)?;
+        }
+
+        Ok(())
+    }
+    /// Print everything known about a single tablet: its peers with their role and sizes, the leader,
+    /// the current term, the replication state according to the master, and the operations in progress.
+    ///
+    /// The peers are the tablet servers that list the tablet in `/tablets`.
+    /// The current term is the highest term of the last received op ids in the consensus watermarks,
+    /// which are only reported by the leader. The follower lag is calculated from these watermarks, see [follower_lag_rows].
+    #[allow(clippy::too_many_arguments)]
+    pub fn print_tablet(
+        &self,
+        tablet_id: &str,
+        alltabletreplication: &AllTabletReplication,
+        alloperations: &AllOperations,
+        wal_size_warn: f64,
+        intents_size_warn: f64,
+        follower_lag_warn: u64,
+        config: &Config,
+    ) -> Result<()>
+    {
+        let peers: Vec<(&str, &TabletBasic, Option<f64>)> = self.tablet
+            .iter()
+            .flat_map(|tablet| tablet.tabletbasic
+                .iter()
+                .filter(|tabletbasic| tabletbasic.tablet_id == tablet_id)
+                .map(|tabletbasic| (tablet.hostname_port.as_deref().unwrap_or_default(), tabletbasic, tablet.intents_size(tablet_id)))
+            )
+            .collect();
+        let Some((_, first_peer, _)) = peers.first() else {
+            bail!("Tablet {} not found in the tablet data, use --print-tablet-detail without --tablet-id to list the tablets", tablet_id);
+        };
+
+        writeln!(config.output(), "Tablet:         {}", tablet_id)?;
+        writeln!(config.output(), "Keyspace:       {}", first_peer.namespace)?;
+        writeln!(config.output(), "Object name:    {}", first_peer.table_name)?;
+        writeln!(config.output(), "Table id:       {}", first_peer.table_uuid)?;
+        writeln!(config.output(), "Partition:      {}", first_peer.partition)?;
+        writeln!(config.output(), "Leader:         {}", peers.iter().find_map(|(_, tabletbasic, _)| raft_leader(&tabletbasic.raftconfig)).unwrap_or_else(|| "-".to_string()))?;
+        writeln!(config.output(), "Current term:   {}", self.tablet
+            .iter()
+            .flat_map(|tablet| tablet.tabletdetail.iter().flatten())
+            .filter(|tabletdetail| tabletdetail.tablet_id == tablet_id)
+            .filter_map(|tabletdetail| current_term(&tabletdetail.consensus_status))
+            .max()
+            .map(|term| term.to_string())
+            .unwrap_or_else(|| "-".to_string())
+        )?;
+        writeln!(config.output(), "Replication:    {}", replication_state(alltabletreplication, tablet_id))?;
+
+        writeln!(config.output(), "Peers:")?;
+        let rows: Vec<Vec<String>> = peers
+            .iter()
+            .map(|(hostname_port, tabletbasic, intents_size)| vec![
+                hostname_port.to_string(),
+                tabletbasic.state.clone(),
+                raft_role(&tabletbasic.raftconfig, hostname_port.split(':').next().unwrap_or_default()).unwrap_or_default(),
+                tabletbasic.num_sst_files.trim().to_string(),
+                on_disk_size_component(&tabletbasic.on_disk_size, "Total").unwrap_or_default(),
+                on_disk_size_component(&tabletbasic.on_disk_size, "SST Files").unwrap_or_default(),
+                on_disk_size_component(&tabletbasic.on_disk_size, "WAL Files").unwrap_or_default(),
+                intents_size.map(utility::bytes_to_size).unwrap_or_default(),
+                size_flags(&tabletbasic.on_disk_size, *intents_size, wal_size_warn, intents_size_warn),
+                tabletbasic.last_status.clone(),
+            ])
+            .collect();
+        utility::print_table(&["hostname_port", "state", "role", "sst_files", "total_size", "sst_size", "wal_size", "intents_size", "flags", "last_status"], &rows, config)?;
+
+        // only the leader has the consensus watermarks of the peers.
+        let leader_watermarks = self.tablet
+            .iter()
+            .flat_map(|tablet| tablet.tabletdetail
+                .iter()
+                .flatten()
+                .filter(|tabletdetail| tabletdetail.tablet_id == tablet_id && !tabletdetail.consensus_status.watermark.is_empty())
+                .map(|tabletdetail| (tablet.hostname_port.as_deref().unwrap_or_default(), peer_progress(&tabletdetail.consensus_status)))
+            )
+            .max_by_key(|(_, progress)| progress.iter().map(|peer| peer.last_received).max());
+        match leader_watermarks
+        {
+            Some((hostname_port, progress)) => {
+                writeln!(config.output(), "Follower lag (consensus watermarks on {}):", hostname_port)?;
+                utility::print_table(&["peer", "role", "member_type", "last_received", "last_applied", "lag_ops", "flags"], &follower_lag_rows(progress, follower_lag_warn), config)?;
+            },
+            None => {
+                writeln!(config.output(), "Follower lag: not available, no consensus watermarks found: the leader {} is unreachable, or the snapshot was taken without --extra-data",
+                         peers.iter().find_map(|(_, tabletbasic, _)| raft_leader(&tabletbasic.raftconfig)).unwrap_or_else(|| "-".to_string()))?;
+            },
+        }
+
+        let rows: Vec<Vec<String>> = alloperations.operations
+            .iter()
+            .flat_map(|operations| operations.tasks
+                .iter()
+                .flatten()
+                .filter(|operation| operation.tablet_id == tablet_id)
+                .map(|operation| vec![
+                    operations.hostname_port.clone().unwrap_or_default(),
+                    operation.op_id.clone(),
+                    operation.transaction_type.clone(),
+                    operation.total_time_in_flight.clone(),
+                    operation.description.clone(),
+                ])
+            )
+            .collect();
+        if rows.is_empty()
+        {
+            writeln!(config.output(), "Operations in progress: none")?;
+        }
+        else
+        {
+            writeln!(config.output(), "Operations in progress:")?;
+            utility::print_table(&["hostname_port", "op_id", "type", "time_in_flight", "description"], &rows, config)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Get the replication state of a tablet from the leaderless and under-replicated tablets reported by the master.
+fn replication_state(
+    alltabletreplication: &AllTabletReplication,
+    tablet_id: &str,
+) -> String
+{
+    for tabletreplication in &alltabletreplication.tablet_replication
+    {
+        if tabletreplication.leaderless_tablets.iter().flatten().any(|tablet| tablet.tablet_id == tablet_id)
+        {
+            return "LEADERLESS".to_string();
+        }
+        if let Some(tablet) = tabletreplication.under_replicated_tablets.iter().flatten().find(|tablet| tablet.tablet_id == tablet_id)
+        {
+            return format!("UNDER REPLICATED (replication count: {})", tablet.tablet_replication_count);
+        }
+    }
+    "ok".to_string()
+}
+
+/// Get the current term from the consensus watermarks.
+///
+/// A watermark contains the op id of the last received entry as term.index, such as: `last_received: 5.108`.
+fn current_term(
+    consensus_status: &ConsensusStatus,
+) -> Option<u64>
+{
+    consensus_status.watermark
+        .iter()
+        .flatten()
+        .filter_map(|watermark| watermark.watermark
+            .split_once("last_received: ")
+            .and_then(|(_, rest)| rest.split('.').next())
+            .and_then(|term| term.parse::<u64>().ok())
+        )
+        .max()
+}
+
+/// Get an op id (term.index), such as 5.108, as (term, index).
+fn op_id(
+    text: &str,
+) -> Option<(u64, u64)>
+{
+    let (term, index) = text.split_once('.')?;
+    Some((term.parse().ok()?, index.parse().ok()?))
+}
+
+/// Get the value of a field of a consensus watermark, which looks like:
+/// `{ peer: c4ba4bb2cea04a2eade78bed94406fb9 is_new: 0 last_received: 5.108 next_index: 109 .. member_type: VOTER num_sst_files: 0 last_applied: 5.108 }`
+fn watermark_field<'a>(
+    watermark: &'a str,
+    field: &str,
+) -> Option<&'a str>
+{
+    watermark
+        .split_once(format!(" {}: ", field).as_str())
+        .and_then(|(_, rest)| rest.split_whitespace().next())
+}
+
+/// Get the replication progress of the peers from the consensus watermarks.
+fn peer_progress(
+    consensus_status: &ConsensusStatus,
+) -> Vec<PeerProgress>
+{
+    consensus_status.watermark
+        .iter()
+        .flatten()
+        .filter_map(|watermark| Some(PeerProgress {
+            peer: watermark.peer.clone(),
+            member_type: watermark_field(&watermark.watermark, "member_type").unwrap_or_default().to_string(),
+            last_received: watermark_field(&watermark.watermark, "last_received").and_then(op_id)?,
+            last_applied: watermark_field(&watermark.watermark, "last_applied").and_then(op_id).map(|(_, index)| index),
+        }))
+        .collect()
+}
+
+/// The rows of the follower lag: the leader first, then the followers with the highest lag first.
+///
+/// The watermarks identify the peers by uuid only, therefore the leader is the peer with the highest last received op id:
+/// a follower only receives the ops of the leader. The lag is the number of ops a peer applied less than the leader,
+/// or received less if the version does not report the applied ops. A follower with a lag above follower_lag_warn is flagged.
+fn follower_lag_rows(
+    progress: Vec<PeerProgress>,
+    follower_lag_warn: u64,
+) -> Vec<Vec<String>>
+{
+    let progress_index = |peer: &PeerProgress| peer.last_applied.unwrap_or(peer.last_received.1);
+    let mut progress = progress;
+    progress.sort_by_key(|peer| Reverse(peer.last_received));
+    let Some(leader_index) = progress.first().map(progress_index) else { return Vec::new() };
+    progress[1..].sort_by_key(|peer| Reverse(leader_index.saturating_sub(progress_index(peer))));
+    progress
+        .iter()
+        .enumerate()
+        .map(|(position, peer)| {
+            let lag = leader_index.saturating_sub(progress_index(peer));
+            vec![
+                peer.peer.clone(),
+                if position == 0 { "leader" } else { "follower" }.to_string(),
+                peer.member_type.clone(),
+                format!("{}.{}", peer.last_received.0, peer.last_received.1),
+                peer.last_applied.map(|index| index.to_string()).unwrap_or_default(),
+                lag.to_string(),
+                if lag > follower_lag_warn { "LAGGING" } else { "" }.to_string(),
+            ]
+        })
+        .collect()
+}
+
+/// Get the read and write operations per tablet of a server from the tablet metrics.
+///
+/// These are the total counts of the ql_read_latency and ql_write_latency histograms of the tablet entities since the tablet was opened.
+/// The key is the tablet_id, the value is (reads, writes, the time the metrics were read).
+fn tablet_operations(
+    allmetricentity: AllMetricEntity,
+    server: &str,
+) -> BTreeMap<String, (u64, u64, Option<DateTime<Local>>)>
+{
+    let mut tablet_operations: BTreeMap<String, (u64, u64, Option<DateTime<Local>>)> = BTreeMap::new();
+    for metricentity in allmetricentity.metricentity
+        .into_iter()
+        .filter(|metricentity| metricentity.hostname_port.as_deref() == Some(server) && metricentity.metrics_type == "tablet")
+    {
+        for metric in metricentity.metrics
+        {
+            if let Metrics::MetricCountSum { name, total_count, .. } = metric
+            {
+                let operations = tablet_operations.entry(metricentity.id.clone()).or_default();
+                operations.2 = metricentity.timestamp;
+                match name.as_str()
+                {
+                    "ql_read_latency" => operations.0 += total_count,
+                    "ql_write_latency" => operations.1 += total_count,
+                    _ => {},
+                }
+            }
+        }
+    }
+    tablet_operations
+}
+
+/// Get the read and write operations per second per tablet of a server, between the tablet metrics of the begin and the end.
+///
+/// The interval is the time between the reads of the tablet metrics. A tablet that is not in the begin metrics, such as a tablet
+/// that is created or moved to the server after the begin, counts from zero at the begin timestamp,
+/// and a tablet with fewer operations at the end than at the begin is reopened, and counts from zero too.
+/// The key is the tablet_id, the value is (reads/s, writes/s).
+fn tablet_operations_rates(
+    begin: AllMetricEntity,
+    end: AllMetricEntity,
+    begin_timestamp: DateTime<Local>,
+    server: &str,
+) -> BTreeMap<String, (f64, f64)>
+{
+    let begin = tablet_operations(begin, server);
+    tablet_operations(end, server)
+        .into_iter()
+        .filter_map(|(tablet_id, (end_reads, end_writes, end_timestamp))| {
+            let (begin_reads, begin_writes, begin_time) = begin.get(&tablet_id).copied().unwrap_or((0, 0, Some(begin_timestamp)));
+            let seconds = (end_timestamp? - begin_time.unwrap_or(begin_timestamp)).num_milliseconds() as f64 / 1000_f64;
+            if seconds <= 0_f64
+            {
+                return None;
+            }
+            let reads = if end_reads < begin_reads { end_reads } else { end_reads - begin_reads };
+            let writes = if end_writes < begin_writes { end_writes } else { end_writes - begin_writes };
+            Some((tablet_id, (reads as f64 / seconds, writes as f64 / seconds)))
+        })
+        .collect()
+}
+
+/// Get the raft role of a host from the "RaftConfig" text, which looks like:
+/// `LEADER: yb-2.local FOLLOWER: yb-1.local FOLLOWER: yb-3.local`
+fn raft_role(
+    raftconfig: &str,
+    hostname: &str,
+) -> Option<String>
+{
+    raftconfig
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .chunks(2)
+        .find(|role_host| role_host.len() == 2 && role_host[1] == hostname)
+        .map(|role_host| role_host[0].trim_end_matches(':').to_lowercase())
+}
+
+/// Get the leader host from the "RaftConfig" text, see [raft_role].
+fn raft_leader(
+    raftconfig: &str,
+) -> Option<String>
+{
+    raftconfig
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .chunks(2)
+        .find(|role_host| role_host.len() == 2 && role_host[0] == "LEADER:")
+        .map(|role_host| role_host[1].to_string())
+}
+
+/// Get a component of the "On-disk size" text, such as "SST Files" or "WAL Files".
+///
+/// The "On-disk size" text looks like:
+/// `Total: 1.00M Consensus Metadata: 1.5K WAL Files: 1.00M SST Files: 0B SST Files Uncompressed: 0B`
+fn on_disk_size_component(
+    on_disk_size: &str,
+    component: &str,
+) -> Option<String>
+{
+    on_disk_size
+        .split_once(format!("{}: ", component).as_str())
+        .and_then(|(_, rest)| rest.split_whitespace().next())
+        .map(|size| size.to_string())
+}
+
+/// The size of the intents (provisional records) db of a tablet in bytes: the sum of the total_size of its SST files.
+///
+/// An intents file line looks like: `{ total_size: 1616571 base_size: 71889 uncompressed_size: 10043418 name_id: 84 ..`
+fn intents_size(
+    rocksdb: &RocksDb,
+) -> f64
+{
+    rocksdb.intents_files
+        .iter()
+        .filter_map(|file| file.split_once("total_size: ").and_then(|(_, rest)| rest.split_whitespace().next()))
+        .filter_map(|size| size.parse::<f64>().ok())
+        .sum()
+}
+
+/// Flag a tablet with a WAL size or intents size above the warning size:
+/// a large WAL indicates replication lag or slow flushes, large intents indicate long-running transactions.
+fn size_flags(
+    on_disk_size: &str,
+    intents_size: Option<f64>,
+    wal_size_warn: f64,
+    intents_size_warn: f64,
+) -> String
+{
+    let mut flags = Vec::new();
+    if on_disk_size_component(on_disk_size, "WAL Files").and_then(|size| utility::size_to_bytes(&size)).is_some_and(|size| size > wal_size_warn)
+    {
+        flags.push("LARGE WAL");
+    }
+    if intents_size.is_some_and(|size| size > intents_size_warn)
+    {
+        flags.push("LARGE INTENTS");
+    }
+    flags.join(",")
+}
+
+/// Sort the tablets, which are (hostname_port, tablet, intents size), on a column, largest first.
+fn sort_tablets(
+    rows: &mut [(&str, &TabletBasic, Option<f64>)],
+    sort_by: &str,
+) -> Result<()>
+{
+    match sort_by
+    {
+        "total_size" => rows.sort_by(|a, b| sort_size_descending(&a.1.on_disk_size, &b.1.on_disk_size, "Total")),
+        "sst_size" => rows.sort_by(|a, b| sort_size_descending(&a.1.on_disk_size, &b.1.on_disk_size, "SST Files")),
+        "wal_size" => rows.sort_by(|a, b| sort_size_descending(&a.1.on_disk_size, &b.1.on_disk_size, "WAL Files")),
+        "intents_size" => rows.sort_by(|a, b| b.2.partial_cmp(&a.2).unwrap_or(Ordering::Equal)),
+        "sst_files" => rows.sort_by_key(|row| Reverse(row.1.num_sst_files.trim().parse::<u64>().ok())),
+        other => bail!("Unknown sort column for tablets: {}, valid are: total_size, sst_size, sst_files, wal_size, intents_size", other),
+    }
+    Ok(())
+}
+
+/// Sort descending on the size of an "On-disk size" component, tablets without the size are sorted last.
+fn sort_size_descending(
+    first_on_disk_size: &str,
+    second_on_disk_size: &str,
+    component: &str,
+) -> Ordering
+{
+    let first = on_disk_size_component(first_on_disk_size, component).and_then(|size| utility::size_to_bytes(&size));
+    let second = on_disk_size_component(second_on_disk_size, component).and_then(|size| utility::size_to_bytes(&size));
+    second.partial_cmp(&first).unwrap_or(Ordering::Equal)
 }
 
 pub async fn print_tablet_detail(
@@ -613,19 +1170,74 @@ pub async fn print_tablet_detail(
     ports: Vec<&str>,
     parallel: usize,
     options: &Opts,
+    config: &Config,
 ) -> Result<()>
 {
+    let wal_size_warn = utility::size_to_bytes(&options.wal_size_warn)
+        .with_context(|| format!("Invalid WAL warning size: {}, expected a size such as 512M or 1G", options.wal_size_warn))?;
+    let intents_size_warn = utility::size_to_bytes(&options.intents_size_warn)
+        .with_context(|| format!("Invalid intents warning size: {}, expected a size such as 100M", options.intents_size_warn))?;
+    let mut alltablets = AllTablets::new();
     match options.print_tablet_detail.as_ref().unwrap() {
         Some(snapshot_number) => {
-            let mut alltablets = AllTablets::new();
-            alltablets.tablet = snapshot::read_snapshot_json(snapshot_number, "tablets")?;
-            alltablets.print(&options.uuid)?;
+            alltablets.tablet = snapshot::read_snapshot_json(snapshot_number, "tablets", config)?;
         },
         None => {
-            let alltablets = AllTablets::read_tablets(&hosts, &ports, parallel, &options.extra_data).await;
-            alltablets.print(&options.uuid)?;
+            alltablets = AllTablets::read_tablets(&hosts, &ports, parallel, &options.extra_data, config).await;
         },
     }
+    if let Some(tablet_id) = &options.tablet_id
+    {
+        let mut alltabletreplication = AllTabletReplication::new();
+        let mut alloperations = AllOperations::new();
+        match options.print_tablet_detail.as_ref().unwrap() {
+            Some(snapshot_number) => {
+                alltabletreplication.tablet_replication = snapshot::read_snapshot_json(snapshot_number, "tablet_replication", config)?;
+                alloperations.operations = snapshot::read_snapshot_json(snapshot_number, "tablet_server_operations", config)?;
+            },
+            None => {
+                alltabletreplication = AllTabletReplication::read_tablet_replication(&hosts, &ports, parallel, config).await;
+                alloperations = AllOperations::read_tablet_server_operations(&hosts, &ports, parallel, config).await;
+                if !options.extra_data
+                {
+                    alltablets.add_consensus_status(tablet_id, parallel, config).await;
+                }
+            },
+        }
+        alltablets.print_tablet(tablet_id, &alltabletreplication, &alloperations, wal_size_warn, intents_size_warn, options.follower_lag_warn, config)?;
+    }
+    else if !options.uuid.is_empty()
+    {
+        alltablets.print(&options.uuid, config)?;
+    }
+    else if let Some(server) = &options.server
+    {
+        let mut tablet_operations_rates = BTreeMap::new();
+        if options.details_enable
+        {
+            // the rates are between the --begin snapshot and the tablet detail: the snapshot or the current metrics.
+            let Some(begin) = options.begin else {
+                bail!("--server with --details-enable requires --begin, the snapshot the read and write operations per second are calculated from");
+            };
+            let begin_snapshot_row = snapshot::Snapshot::read_snapshot(begin)?;
+            let mut begin_metrics = AllMetricEntity::new();
+            begin_metrics.metricentity = snapshot::read_snapshot_json(&begin.to_string(), "metrics", config)?;
+            let mut end_metrics = AllMetricEntity::new();
+            match options.print_tablet_detail.as_ref().unwrap() {
+                Some(snapshot_number) => end_metrics.metricentity = snapshot::read_snapshot_json(snapshot_number, "metrics", config)?,
+                None => {
+                    let (host, port) = server.split_once(':').unwrap_or((server, ""));
+                    end_metrics = AllMetricEntity::read_metrics(&vec![host], &vec![port], parallel, config).await;
+                },
+            }
+            tablet_operations_rates = self::tablet_operations_rates(begin_metrics, end_metrics, begin_snapshot_row.timestamp, server);
+        }
+        alltablets.print_server(server, &options.sort_by, &tablet_operations_rates, &options.details_enable, wal_size_warn, intents_size_warn, config)?;
+    }
+    else
+    {
+        alltablets.print_list(&options.sort_by, wal_size_warn, intents_size_warn, config)?;
+    }
     Ok(())
 }
 
@@ -706,6 +1318,82 @@ mod tests {
         assert_eq!(result.tabletbasic[0].last_status, "transactions0");
     }
 
+    #[test]
+    fn unit_raft_role() {
+        let raftconfig = "LEADER: yb-2.local FOLLOWER: yb-1.local FOLLOWER: yb-3.local";
+        assert_eq!(raft_role(raftconfig, "yb-2.local"), Some("leader".to_string()));
+        assert_eq!(raft_role(raftconfig, "yb-3.local"), Some("follower".to_string()));
+        assert_eq!(raft_role(raftconfig, "yb-4.local"), None);
+        assert_eq!(raft_leader(raftconfig), Some("yb-2.local".to_string()));
+        assert_eq!(raft_leader("FOLLOWER: yb-1.local"), None);
+    }
+
+    #[test]
+    fn unit_current_term_and_replication_state() {
+        let mut consensus_status = ConsensusStatus::new();
+        assert_eq!(current_term(&consensus_status), None);
+        consensus_status.watermark.push(Some(Watermark { peer: "a".to_string(), watermark: "{ peer: a is_new: 0 last_received: 5.108 next_index: 109 }".to_string() }));
+        consensus_status.watermark.push(Some(Watermark { peer: "b".to_string(), watermark: "{ peer: b is_new: 0 last_received: 4.100 next_index: 101 }".to_string() }));
+        assert_eq!(current_term(&consensus_status), Some(5));
+
+        let alltabletreplication = AllTabletReplication { tablet_replication: serde_json::from_str(r#"[{"hostname_port":"yb-1:7000","timestamp":null,
+            "leaderless_tablets":[{"table_name":"t","table_uuid":"table_t","tablet_id":"tablet_1"}],
+            "under_replicated_tablets":[{"table_name":"t","table_uuid":"table_t","tablet_id":"tablet_2","tablet_replication_count":"2"}]}]"#).unwrap() };
+        assert_eq!(replication_state(&alltabletreplication, "tablet_1"), "LEADERLESS");
+        assert_eq!(replication_state(&alltabletreplication, "tablet_2"), "UNDER REPLICATED (replication count: 2)");
+        assert_eq!(replication_state(&alltabletreplication, "tablet_3"), "ok");
+    }
+
+    #[test]
+    fn unit_follower_lag_rows() {
+        let mut consensus_status = ConsensusStatus::new();
+        consensus_status.watermark.push(Some(Watermark { peer: "a".to_string(), watermark: "{ peer: a is_new: 0 last_received: 5.90 next_index: 91 last_known_committed_idx: 90 member_type: VOTER last_applied: 5.85 }".to_string() }));
+        consensus_status.watermark.push(Some(Watermark { peer: "b".to_string(), watermark: "{ peer: b is_new: 0 last_received: 5.2000 next_index: 2001 last_known_committed_idx: 2000 member_type: VOTER last_applied: 5.2000 }".to_string() }));
+        consensus_status.watermark.push(Some(Watermark { peer: "c".to_string(), watermark: "{ peer: c is_new: 0 last_received: 5.1990 next_index: 1991 last_known_committed_idx: 1990 member_type: VOTER last_applied: 5.1990 }".to_string() }));
+        consensus_status.watermark.push(Some(Watermark { peer: "d".to_string(), watermark: "{ peer: d is_new: 1 }".to_string() }));
+        let progress = peer_progress(&consensus_status);
+        // the new peer without a last received op id is left out.
+        assert_eq!(progress.len(), 3);
+        assert_eq!(progress[0], PeerProgress { peer: "a".to_string(), member_type: "VOTER".to_string(), last_received: (5, 90), last_applied: Some(85) });
+
+        // the leader (the highest last received op id) is first, then the followers with the highest lag first.
+        let rows = follower_lag_rows(progress, 1000);
+        assert_eq!(rows[0], vec!["b", "leader", "VOTER", "5.2000", "2000", "0", ""]);
+        assert_eq!(rows[1], vec!["a", "follower", "VOTER", "5.90", "85", "1915", "LAGGING"]);
+        assert_eq!(rows[2], vec!["c", "follower", "VOTER", "5.1990", "1990", "10", ""]);
+        assert!(follower_lag_rows(Vec::new(), 1000).is_empty());
+    }
+
+    #[test]
+    fn unit_on_disk_size_components() {
+        let on_disk_size = "Total: 1.00M Consensus Metadata: 1.5K WAL Files: 1.00M SST Files: 66.17K SST Files Uncompressed: 120.5K";
+        assert_eq!(on_disk_size_component(on_disk_size, "WAL Files"), Some("1.00M".to_string()));
+        assert_eq!(on_disk_size_component(on_disk_size, "SST Files"), Some("66.17K".to_string()));
+        assert_eq!(on_disk_size_component("", "SST Files"), None);
+        assert_eq!(utility::size_to_bytes("1.5K"), Some(1536_f64));
+        assert_eq!(utility::size_to_bytes("0B"), Some(0_f64));
+        assert_eq!(utility::size_to_bytes(""), None);
+        assert_eq!(utility::bytes_to_size(1000_f64), "1000B");
+        assert_eq!(utility::bytes_to_size(1536_f64), "1.50K");
+        assert_eq!(utility::bytes_to_size(3_f64 * 1024_f64.powi(3)), "3.00G");
+        assert_eq!(sort_size_descending("SST Files: 2.00M", "SST Files: 1.5K", "SST Files"), Ordering::Less);
+        assert_eq!(sort_size_descending("", "SST Files: 1.5K", "SST Files"), Ordering::Greater);
+    }
+
+    #[test]
+    fn unit_intents_size_and_size_flags() {
+        let mut rocksdb = RocksDb::new();
+        rocksdb.intents_files = vec![
+            "{ total_size: 1048576 base_size: 71889 uncompressed_size: 10043418 name_id: 84 }".to_string(),
+            "{ total_size: 2097152 base_size: 148559 uncompressed_size: 29723903 name_id: 83 }".to_string(),
+        ];
+        assert_eq!(intents_size(&rocksdb), 3145728_f64);
+        assert_eq!(intents_size(&RocksDb::new()), 0_f64);
+        let on_disk_size = "Total: 2.00G Consensus Metadata: 1.5K WAL Files: 1.50G SST Files: 512.00M SST Files Uncompressed: 1.00G";
+        assert_eq!(size_flags(on_disk_size, Some(3145728_f64), 1024_f64.powi(3), 1024_f64.powi(2)), "LARGE WAL,LARGE INTENTS");
+        assert_eq!(size_flags(on_disk_size, None, 2_f64 * 1024_f64.powi(3), 1024_f64.powi(2)), "");
+    }
+
     #[test]
     fn unit_parse_tablet_detail_consensus_status_only_state() {
         let tablets = r#"
@@ -1445,12 +2133,37 @@ mod tests {
         assert_eq!(result.intents_files_detail.len(), 0);
     }
 
+    #[test]
+    fn unit_tablet_operations_rates() {
+        let histogram = |name: &str, total_count: u64| format!(r#"{{"name":"{}","total_count":{},"min":0,"mean":0.0,"percentile_75":0,"percentile_95":0,"percentile_99":0,"percentile_99_9":0,"percentile_99_99":0,"max":0,"total_sum":0}}"#, name, total_count);
+        let tablet = |hostname_port: &str, tablet_id: &str, timestamp: &str, reads: u64, writes: u64| format!(r#"{{"hostname_port":"{}","timestamp":"{}","type":"tablet","id":"{}","attributes":null,"metrics":[{},{}]}}"#,
+            hostname_port, timestamp, tablet_id, histogram("ql_read_latency", reads), histogram("ql_write_latency", writes));
+        let begin = format!("[{},{},{}]",
+            tablet("yb-1:9000", "tablet-1", "2026-01-01T00:00:00+00:00", 100, 10),
+            tablet("yb-1:9000", "tablet-2", "2026-01-01T00:00:00+00:00", 500, 50),
+            tablet("yb-2:9000", "tablet-1", "2026-01-01T00:00:00+00:00", 0, 0));
+        let end = format!("[{},{},{},{}]",
+            tablet("yb-1:9000", "tablet-1", "2026-01-01T00:00:10+00:00", 200, 30),
+            tablet("yb-1:9000", "tablet-2", "2026-01-01T00:00:10+00:00", 20, 5),
+            tablet("yb-1:9000", "tablet-3", "2026-01-01T00:00:10+00:00", 40, 0),
+            tablet("yb-2:9000", "tablet-1", "2026-01-01T00:00:10+00:00", 1000, 1000));
+        let begin_timestamp = DateTime::parse_from_rfc3339("2026-01-01T00:00:00+00:00").unwrap().with_timezone(&Local);
+        let rates = tablet_operations_rates(AllMetricEntity { metricentity: serde_json::from_str(&begin).unwrap() }, AllMetricEntity { metricentity: serde_json::from_str(&end).unwrap() }, begin_timestamp, "yb-1:9000");
+        assert_eq!(rates.get("tablet-1"), Some(&(10_f64, 2_f64)));
+        // tablet-2 is reopened, its operations count from zero.
+        assert_eq!(rates.get("tablet-2"), Some(&(2_f64, 0.5_f64)));
+        // tablet-3 is not in the begin metrics, and counts from zero at the begin.
+        assert_eq!(rates.get("tablet-3"), Some(&(4_f64, 0_f64)));
+        assert_eq!(rates.len(), 3);
+    }
+
     #[tokio::test]
     async fn integration_parse_tablets() {
+        let config = &Config::default();
         let hostname = utility::get_hostname_tserver();
         let port = utility::get_port_tserver();
 
-        let alltablets = AllTablets::read_tablets(&vec![&hostname], &vec![&port], 1, &true).await;
+        let alltablets = AllTablets::read_tablets(&vec![&hostname], &vec![&port], 1, &true, config).await;
         // the tablet server returns more than one tablet.
         assert!(!alltablets.tablet.is_empty());
     }
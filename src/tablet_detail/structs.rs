@@ -97,3 +97,14 @@ pub struct RocksDbFile {
     pub filename: String,
     pub details: Vec<String>,
 }
+/// The replication progress of a peer of a tablet, from the consensus watermark of the peer on the leader.
+///
+/// The op ids are term.index, such as 5.108.
+#[derive(Debug, Default, PartialEq)]
+pub struct PeerProgress {
+    pub peer: String,
+    pub member_type: String,
+    pub last_received: (u64, u64),
+    /// The index of the last applied op, which older versions do not report.
+    pub last_applied: Option<u64>,
+}
@@ -6,6 +6,7 @@ use scraper::{Html, Selector};
 use log::*;
 use anyhow::Result;
 use crate::utility;
+use crate::utility::Config;
 use crate::snapshot;
 use crate::tablet_replication::{AllTabletReplication, LeaderlessTablet, TabletReplication, UnderReplicatedTablets};
 
@@ -19,13 +20,14 @@ impl AllTabletReplication {
         ports: &Vec<&str>,
         snapshot_number: i32,
         parallel: usize,
+        config: &Config,
     ) -> Result<()>
     {
         info!("begin snapshot");
         let timer = Instant::now();
 
-        let alltabletreplication = AllTabletReplication::read_tablet_replication(hosts, ports, parallel).await;
-        snapshot::save_snapshot_json(snapshot_number, "tablet_replication", alltabletreplication.tablet_replication)?;
+        let alltabletreplication = AllTabletReplication::read_tablet_replication(hosts, ports, parallel, config).await;
+        snapshot::save_snapshot_json(snapshot_number, "tablet_replication", alltabletreplication.tablet_replication, config)?;
 
         info!("end snapshot: {:?}", timer.elapsed());
 
@@ -35,6 +37,7 @@ impl AllTabletReplication {
         hosts: &Vec<&str>,
         ports: &Vec<&str>,
         parallel: usize,
+        config: &Config,
     ) -> AllTabletReplication
     {
         info!("begin parallel http read");
@@ -49,9 +52,9 @@ impl AllTabletReplication {
                     let tx = tx.clone();
                     s.spawn(move |_| {
                         let detail_snapshot_time = Local::now();
-                        let mut tabletreplication = AllTabletReplication::read_http(host, port);
+                        let mut tabletreplication = AllTabletReplication::read_http(host, port, config);
                         tabletreplication.timestamp = Some(detail_snapshot_time);
-                        tabletreplication.hostname_port = Some(format!("{}:{}", host, port));
+                        tabletreplication.hostname_port = Some(utility::hostname_port(host, port, config));
                         tx.send(tabletreplication).expect("error sending data via tx");
                     });
                 }
@@ -72,9 +75,10 @@ impl AllTabletReplication {
     fn read_http(
         host: &str,
         port: &str,
+        config: &Config,
     ) -> TabletReplication
     {
-        let data_from_http = utility::http_get(host, port, "tablet-replication");
+        let data_from_http = utility::http_get_endpoint(host, port, "tablet_replication", config);
         AllTabletReplication::parse_tablet_replication(data_from_http)
     }
     fn parse_tablet_replication(
@@ -152,20 +156,20 @@ impl AllTabletReplication {
             {
                 if row.hostname_port != previous_hostname_port
                 {
-                    println!("--------------------------------------------------------------------------------------------------------------------------------------");
-                    println!("Host: {}, Snapshot time: {}", &row.hostname_port.to_string(), row.timestamp);
-                    println!("--------------------------------------------------------------------------------------------------------------------------------------");
-                    println!("{:20} {:40} {:>20} {:>20} {:>20} {:50}",
+                    writeln!(config.output(), "--------------------------------------------------------------------------------------------------------------------------------------")?;
+                    writeln!(config.output(), "Host: {}, Snapshot time: {}", &row.hostname_port.to_string(), utility::format_timestamp(&row.timestamp))?;
+                    writeln!(config.output(), "--------------------------------------------------------------------------------------------------------------------------------------")?;
+                    writeln!(config.output(), "{:20} {:40} {:>20} {:>20} {:>20} {:50}",
                              "hostname_port",
                              "thread_name",
                              "cum_user_cpu_s",
                              "cum_kernel_cpu_s",
                              "cum_iowait_cpu_s",
-                             "stack");
-                    println!("--------------------------------------------------------------------------------------------------------------------------------------");
+                             "stack")?;
+                    writeln!(config.output(), "--------------------------------------------------------------------------------------------------------------------------------------")?;
                     previous_hostname_port = row.hostname_port.to_string();
                 };
-                println!("{:20} {:40} {:>20} {:>20} {:>20} {:50}", row.hostname_port, row.thread_name, row.cumulative_user_cpu_s, row.cumulative_kernel_cpu_s, row.cumulative_iowait_cpu_s, row.stack.replace('\n', ""));
+                writeln!(config.output(), "{:20} {:40} {:>20} {:>20} {:>20} {:50}", row.hostname_port, row.thread_name, row.cumulative_user_cpu_s, row.cumulative_kernel_cpu_s, row.cumulative_iowait_cpu_s, row.stack.replace('\n', ""))?;
             }
         }
 
@@ -292,10 +296,11 @@ mod tests {
     }
     #[tokio::test]
     async fn integration_parse_master_tasks() {
+        let config = &Config::default();
         let hostname = utility::get_hostname_master();
         let port = utility::get_port_master();
 
-        let _result = AllTabletReplication::read_tablet_replication(&vec![&hostname], &vec![&port], 1).await;
+        let _result = AllTabletReplication::read_tablet_replication(&vec![&hostname], &vec![&port], 1, config).await;
         // the master returns none or more tasks.
     }
 }
\ No newline at end of file
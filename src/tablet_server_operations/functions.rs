@@ -1,16 +1,20 @@
 //! The impls and functions
 //!
 use chrono::Local;
-use std::{sync::mpsc::channel, time::Instant};
+use std::{collections::BTreeMap, sync::mpsc::channel, time::Instant};
 use scraper::{Html, Selector};
 use log::*;
 use anyhow::Result;
 use regex::Regex;
 use crate::utility;
+use crate::utility::Config;
 use crate::snapshot;
-use crate::tablet_server_operations::{AllOperations, Operations, Operation};
+use crate::tablet_server_operations::{AllOperations, Operations, Operation, AllCompactionQueues, CompactionQueues, CompactionQueue, CompactionQueuesDiff, CompactionQueuesDiffFields, TabletServerEntity};
 use crate::Opts;
 
+/// The compaction types of the compaction task gauges, such as `nonactive_background_compaction_tasks_added`.
+const COMPACTION_TYPES: [&str; 3] = ["background", "full", "post_split"];
+
 impl Operations {
     pub fn new() -> Self{ Default::default() }
 }
@@ -21,13 +25,14 @@ impl AllOperations {
         ports: &Vec<&str>,
         snapshot_number: i32,
         parallel: usize,
+        config: &Config,
     ) -> Result<()>
     {
         info!("begin snapshot");
         let timer = Instant::now();
 
-        let alloperations = AllOperations::read_tablet_server_operations(hosts, ports, parallel).await;
-        snapshot::save_snapshot_json(snapshot_number, "tablet_server_operations", alloperations.operations)?;
+        let alloperations = AllOperations::read_tablet_server_operations(hosts, ports, parallel, config).await;
+        snapshot::save_snapshot_json(snapshot_number, "tablet_server_operations", alloperations.operations, config)?;
 
         info!("end snapshot: {:?}", timer.elapsed());
 
@@ -37,6 +42,7 @@ impl AllOperations {
         hosts: &Vec<&str>,
         ports: &Vec<&str>,
         parallel: usize,
+        config: &Config,
     ) -> AllOperations
     {
         info!("begin parallel http read");
@@ -51,9 +57,9 @@ impl AllOperations {
                     let tx = tx.clone();
                     s.spawn(move |_| {
                         let detail_snapshot_time = Local::now();
-                        let mut operations = AllOperations::read_http(host, port);
+                        let mut operations = AllOperations::read_http(host, port, config);
                         operations.timestamp = Some(detail_snapshot_time);
-                        operations.hostname_port = Some(format!("{}:{}", host, port));
+                        operations.hostname_port = Some(utility::hostname_port(host, port, config));
                         tx.send(operations).expect("error sending data via tx");
                     });
                 }
@@ -74,9 +80,10 @@ impl AllOperations {
     fn read_http(
         host: &str,
         port: &str,
+        config: &Config,
     ) -> Operations
     {
-        let data_from_http = utility::http_get(host, port, "operations");
+        let data_from_http = utility::http_get_endpoint(host, port, "tablet_server_operations", config);
         AllOperations::parse_tablet_server_operations(data_from_http)
     }
     fn parse_tablet_server_operations(
@@ -129,44 +136,332 @@ impl AllOperations {
     }
     pub fn print(
         &self,
-        hostname_filter: &Regex
+        hostname_filter: &Regex,
+        config: &Config,
     ) -> Result<()>
     {
         for operations in self.operations.iter().filter(|row| hostname_filter.is_match(row.hostname_port.as_ref().unwrap()))
         {
                 for task in &operations.tasks
                 {
-                    println!("{:20} {:32} {:10} {:10} {:10} {:80}",
+                    writeln!(config.output(), "{:20} {:32} {:10} {:10} {:10} {:80}",
                         operations.hostname_port.as_ref().unwrap(),
                         task.as_ref().unwrap().tablet_id,
                         task.as_ref().unwrap().op_id,
                         task.as_ref().unwrap().transaction_type,
                         task.as_ref().unwrap().total_time_in_flight,
                         task.as_ref().unwrap().description.get(..80).unwrap_or(&task.as_ref().unwrap().description),
-                    );
+                    )?;
                 }
         }
         Ok(())
     }
 }
 
+impl CompactionQueues {
+    pub fn new() -> Self { Default::default() }
+    /// The compaction queue of a compaction type, which is empty (None) if it is not found.
+    pub fn queue(&self, compaction_type: &str) -> CompactionQueue
+    {
+        self.queues.get(compaction_type).copied().unwrap_or_default()
+    }
+}
+
+impl AllCompactionQueues {
+    pub fn new() -> Self { Default::default() }
+    pub async fn perform_snapshot(
+        hosts: &Vec<&str>,
+        ports: &Vec<&str>,
+        snapshot_number: i32,
+        parallel: usize,
+        config: &Config,
+    ) -> Result<()>
+    {
+        info!("begin snapshot");
+        let timer = Instant::now();
+
+        let allcompactionqueues = AllCompactionQueues::read_compaction_queues(hosts, ports, parallel, config).await;
+        snapshot::save_snapshot_json(snapshot_number, "compaction_queues", allcompactionqueues.compactionqueues, config)?;
+
+        info!("end snapshot: {:?}", timer.elapsed());
+        Ok(())
+    }
+    pub async fn read_compaction_queues(
+        hosts: &Vec<&str>,
+        ports: &Vec<&str>,
+        parallel: usize,
+        config: &Config,
+    ) -> AllCompactionQueues
+    {
+        info!("begin parallel http read");
+        let timer = Instant::now();
+
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(parallel).build().unwrap();
+        let (tx, rx) = channel();
+        pool.scope(move |s| {
+            for host in hosts {
+                for port in ports {
+                    let tx = tx.clone();
+                    s.spawn(move |_| {
+                        let detail_snapshot_time = Local::now();
+                        let mut compactionqueues = AllCompactionQueues::read_http(host, port, config);
+                        compactionqueues.timestamp = Some(detail_snapshot_time);
+                        compactionqueues.hostname_port = Some(utility::hostname_port(host, port, config));
+                        tx.send(compactionqueues).expect("error sending data via tx");
+                    });
+                }
+            }
+        });
+
+        info!("end parallel http read {:?}", timer.elapsed());
+
+        let mut allcompactionqueues = AllCompactionQueues::new();
+
+        // a port that is not a tablet server has no queues.
+        for compactionqueues in rx.iter().filter(|row| !row.queues.is_empty())
+        {
+            allcompactionqueues.compactionqueues.push(compactionqueues);
+        }
+        allcompactionqueues.compactionqueues.sort_by(|a, b| a.hostname_port.cmp(&b.hostname_port));
+
+        allcompactionqueues
+    }
+    fn read_http(
+        host: &str,
+        port: &str,
+        config: &Config,
+    ) -> CompactionQueues
+    {
+        let data_from_http = utility::http_get_endpoint(host, port, "compaction_queues", config);
+        AllCompactionQueues::parse_compaction_queues(data_from_http, host, port)
+    }
+    /// Parse the compaction task gauges of the `yb.tabletserver` entity into the queued and running compaction tasks:
+    /// the queued tasks are the nonactive tasks added minus removed, the running tasks are the active tasks added minus removed.
+    ///
+    /// A tablet server without the gauges gets the compaction types with empty queues, so these are shown blank.
+    /// Without a `yb.tabletserver` entity, no queues are returned.
+    fn parse_compaction_queues(
+        http_data: String,
+        host: &str,
+        port: &str,
+    ) -> CompactionQueues
+    {
+        let entities: Vec<TabletServerEntity> = serde_json::from_str(&http_data)
+            .unwrap_or_else(|e| {
+                debug!("({}:{}) could not parse /metrics json data for compaction queues, error: {}", host, port, e);
+                Vec::new()
+            });
+        let mut compactionqueues = CompactionQueues::new();
+        for entity in entities.into_iter().filter(|entity| entity.entity_type == "server" && entity.id == "yb.tabletserver")
+        {
+            let gauges: BTreeMap<String, i64> = entity.metrics
+                .into_iter()
+                .filter_map(|metric| metric.value.map(|value| (metric.name, value)))
+                .collect();
+            let tasks = |prefix: &str, compaction_type: &str| -> Option<i64> {
+                let added = gauges.get(&format!("{}_{}_compaction_tasks_added", prefix, compaction_type))?;
+                let removed = gauges.get(&format!("{}_{}_compaction_tasks_removed", prefix, compaction_type))?;
+                Some(added - removed)
+            };
+            for compaction_type in COMPACTION_TYPES
+            {
+                compactionqueues.queues.insert(compaction_type.to_string(), CompactionQueue {
+                    queued: tasks("nonactive", compaction_type),
+                    running: tasks("active", compaction_type),
+                });
+            }
+        }
+        compactionqueues
+    }
+    pub fn print(
+        &self,
+        hostname_filter: &Regex,
+        config: &Config,
+    ) -> Result<()>
+    {
+        let format = |tasks: Option<i64>| tasks.map(|tasks| tasks.to_string()).unwrap_or_default();
+        let rows: Vec<Vec<String>> = self.compactionqueues
+            .iter()
+            .filter(|row| hostname_filter.is_match(row.hostname_port.as_ref().unwrap()))
+            .map(|row| {
+                let mut fields = vec![row.hostname_port.clone().unwrap()];
+                for compaction_type in COMPACTION_TYPES
+                {
+                    fields.push(format(row.queue(compaction_type).queued));
+                    fields.push(format(row.queue(compaction_type).running));
+                }
+                fields
+            })
+            .collect();
+        writeln!(config.output(), "Compaction queues (queued and running compaction tasks):")?;
+        utility::print_table(&COMPACTION_QUEUE_HEADERS, &rows, config)?;
+        Ok(())
+    }
+}
+
+/// The headers of the compaction queues: the queued and running tasks for every compaction type of [COMPACTION_TYPES].
+const COMPACTION_QUEUE_HEADERS: [&str; 7] = ["hostname_port", "background_queued", "background_running", "full_queued", "full_running", "post_split_queued", "post_split_running"];
+
+impl CompactionQueuesDiff {
+    pub fn new() -> Self { Default::default() }
+    pub fn snapshot_diff(
+        begin_snapshot: &String,
+        end_snapshot: &String,
+        config: &Config,
+    ) -> Result<CompactionQueuesDiff>
+    {
+        let mut compactionqueuesdiff = CompactionQueuesDiff::new();
+
+        let mut allcompactionqueues = AllCompactionQueues::new();
+        allcompactionqueues.compactionqueues = snapshot::read_snapshot_json(begin_snapshot, "compaction_queues", config)?;
+        compactionqueuesdiff.first_snapshot(allcompactionqueues);
+
+        let mut allcompactionqueues = AllCompactionQueues::new();
+        allcompactionqueues.compactionqueues = snapshot::read_snapshot_json(end_snapshot, "compaction_queues", config)?;
+        compactionqueuesdiff.second_snapshot(allcompactionqueues);
+
+        Ok(compactionqueuesdiff)
+    }
+    fn first_snapshot(
+        &mut self,
+        allcompactionqueues: AllCompactionQueues,
+    )
+    {
+        for compactionqueues in allcompactionqueues.compactionqueues
+        {
+            self.btreecompactionqueuesdiff
+                .entry(compactionqueues.hostname_port.clone().expect("hostname:port should be set"))
+                .and_modify(|_| error!("Duplicate hostname:port entry: {}", compactionqueues.hostname_port.clone().expect("hostname:port should be set")))
+                .or_insert(CompactionQueuesDiffFields {
+                    first: Some(compactionqueues.clone()),
+                    ..Default::default()
+                });
+        }
+    }
+    fn second_snapshot(
+        &mut self,
+        allcompactionqueues: AllCompactionQueues,
+    )
+    {
+        for compactionqueues in allcompactionqueues.compactionqueues
+        {
+            let hostname_port = compactionqueues.hostname_port.clone().expect("hostname:port should be set");
+            self.btreecompactionqueuesdiff
+                .entry(hostname_port)
+                .or_default()
+                .second = Some(compactionqueues);
+        }
+    }
+    /// The rows of the diff: the queued and running tasks in the end snapshot with the change.
+    /// Only the tablet servers that are in both snapshots, and of which a queue is not empty or changed, are shown,
+    /// so a backlog that grows or drains is visible.
+    fn rows(
+        &self,
+        hostname_filter: &Regex,
+    ) -> Vec<Vec<String>>
+    {
+        let with_change = |first: Option<i64>, second: Option<i64>| match (first, second) {
+            (Some(first), Some(second)) => format!("{} ({:+})", second, second - first),
+            (None, Some(second)) => second.to_string(),
+            (_, None) => String::new(),
+        };
+        let mut rows = Vec::new();
+        for (hostname_port, row) in self.btreecompactionqueuesdiff.iter().filter(|(hostname_port, _)| hostname_filter.is_match(hostname_port))
+        {
+            let (Some(first), Some(second)) = (&row.first, &row.second) else { continue };
+            let tasks = |queues: &CompactionQueues| queues.queues.values().map(|queue| queue.queued.unwrap_or_default() + queue.running.unwrap_or_default()).sum::<i64>();
+            if first.queues == second.queues && tasks(second) == 0
+            {
+                continue;
+            }
+            let mut fields = vec![hostname_port.clone()];
+            for compaction_type in COMPACTION_TYPES
+            {
+                fields.push(with_change(first.queue(compaction_type).queued, second.queue(compaction_type).queued));
+                fields.push(with_change(first.queue(compaction_type).running, second.queue(compaction_type).running));
+            }
+            rows.push(fields);
+        }
+        rows
+    }
+    /// Print the hosts of which the compaction queues changed or have tasks, and return the number of these hosts.
+    pub fn print(
+        &self,
+        hostname_filter: &Regex,
+        config: &Config,
+    ) -> Result<usize>
+    {
+        let rows = self.rows(hostname_filter);
+        if rows.is_empty()
+        {
+            return Ok(0);
+        }
+        writeln!(config.output(), "Compaction queues (queued and running compaction tasks):")?;
+        utility::print_table(&COMPACTION_QUEUE_HEADERS, &rows, config)?;
+        Ok(rows.len())
+    }
+}
+
+pub async fn compaction_queues_diff(
+    options: &Opts,
+    config: &Config,
+) -> Result<()>
+{
+    if options.begin.is_none() || options.end.is_none() {
+        snapshot::Snapshot::print(config)?;
+    }
+    if options.snapshot_list { return Ok(()) };
+
+    let hostname_filter = utility::set_regex(&options.hostname_match);
+
+    let (begin_snapshot, end_snapshot, _begin_snapshot_row) = snapshot::Snapshot::read_begin_end_snapshot_from_user(options.begin, options.end)?;
+
+    snapshot::Snapshot::print_diff_header(&begin_snapshot, &end_snapshot, config)?;
+
+    let compactionqueuesdiff = CompactionQueuesDiff::snapshot_diff(&begin_snapshot, &end_snapshot, config)?;
+    let changes = compactionqueuesdiff.print(&hostname_filter, config)?;
+    utility::add_diff_changes(changes, config);
+
+    Ok(())
+}
+
 pub async fn print_operations(
     hosts: Vec<&str>,
     ports: Vec<&str>,
     parallel: usize,
     options: &Opts,
+    config: &Config,
 ) -> Result<()>
 {
     let hostname_filter = utility::set_regex(&options.hostname_match);
     match options.print_tablet_server_operations.as_ref().unwrap() {
         Some(snapshot_number) => {
             let mut alloperations = AllOperations::new();
-            alloperations.operations = snapshot::read_snapshot_json(snapshot_number, "tablet_server_operations")?;
-            alloperations.print(&hostname_filter)?;
+            alloperations.operations = snapshot::read_snapshot_json(snapshot_number, "tablet_server_operations", config)?;
+            alloperations.print(&hostname_filter, config)?;
+            if options.details_enable
+            {
+                // snapshots taken with an older version of yb_stats do not contain the compaction queues.
+                if snapshot::snapshot_data_types(snapshot_number)?.iter().any(|data_type| data_type == "compaction_queues")
+                {
+                    let mut allcompactionqueues = AllCompactionQueues::new();
+                    allcompactionqueues.compactionqueues = snapshot::read_snapshot_json(snapshot_number, "compaction_queues", config)?;
+                    allcompactionqueues.print(&hostname_filter, config)?;
+                }
+                else
+                {
+                    writeln!(config.output(), "Snapshot {} does not contain the compaction queues.", snapshot_number)?;
+                }
+            }
         },
         None => {
-            let alloperations = AllOperations::read_tablet_server_operations(&hosts, &ports, parallel).await;
-            alloperations.print(&hostname_filter)?;
+            let alloperations = AllOperations::read_tablet_server_operations(&hosts, &ports, parallel, config).await;
+            alloperations.print(&hostname_filter, config)?;
+            if options.details_enable
+            {
+                let allcompactionqueues = AllCompactionQueues::read_compaction_queues(&hosts, &ports, parallel, config).await;
+                allcompactionqueues.print(&hostname_filter, config)?;
+            }
         },
     }
     Ok(())
@@ -230,12 +525,58 @@ mod tests {
         assert_eq!(result.tasks[0].as_ref().unwrap().total_time_in_flight, "28836 us.");
     }
 
+    #[test]
+    fn unit_parse_compaction_queues() {
+        let json = r#"
+[
+    {
+        "type": "server",
+        "id": "yb.tabletserver",
+        "metrics": [
+            { "name": "nonactive_background_compaction_tasks_added", "value": 12 },
+            { "name": "nonactive_background_compaction_tasks_removed", "value": 9 },
+            { "name": "active_background_compaction_tasks_added", "value": 9 },
+            { "name": "active_background_compaction_tasks_removed", "value": 8 },
+            { "name": "nonactive_full_compaction_tasks_added", "value": 1 }
+        ]
+    }
+]
+        "#.to_string();
+        let result = AllCompactionQueues::parse_compaction_queues(json, "", "");
+        assert_eq!(result.queue("background"), CompactionQueue { queued: Some(3), running: Some(1) });
+        // a gauge without the removed gauge, and a version without the gauges, are shown blank.
+        assert_eq!(result.queue("full"), CompactionQueue { queued: None, running: None });
+        assert_eq!(result.queue("post_split"), CompactionQueue { queued: None, running: None });
+        // a port that is not a tablet server has no queues.
+        let result = AllCompactionQueues::parse_compaction_queues(r#"[{"type": "server", "id": "yb.master", "metrics": []}]"#.to_string(), "", "");
+        assert!(result.queues.is_empty());
+    }
+
+    #[test]
+    fn unit_compaction_queues_diff() {
+        let compaction_queues = |hostname_port: &str, queued: i64| CompactionQueues {
+            hostname_port: Some(hostname_port.to_string()),
+            timestamp: None,
+            queues: BTreeMap::from([("background".to_string(), CompactionQueue { queued: Some(queued), running: Some(0) }), ("full".to_string(), CompactionQueue::default())]),
+        };
+        let mut compactionqueuesdiff = CompactionQueuesDiff::new();
+        compactionqueuesdiff.first_snapshot(AllCompactionQueues { compactionqueues: vec![compaction_queues("yb-1:9000", 2), compaction_queues("yb-2:9000", 0), compaction_queues("yb-3:9000", 5)] });
+        compactionqueuesdiff.second_snapshot(AllCompactionQueues { compactionqueues: vec![compaction_queues("yb-1:9000", 7), compaction_queues("yb-2:9000", 0), compaction_queues("yb-3:9000", 0)] });
+        let rows = compactionqueuesdiff.rows(&utility::set_regex(&None));
+        // the idle tablet server is left out, a backlog that grows or drains is shown.
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0][..3], ["yb-1:9000", "7 (+5)", "0 (+0)"]);
+        assert_eq!(rows[0][3], "");
+        assert_eq!(rows[1][..2], ["yb-3:9000", "0 (-5)"]);
+    }
+
     #[tokio::test]
     async fn integration_parse_tablet_server_operations() {
+        let config = &Config::default();
         let hostname = utility::get_hostname_tserver();
         let port = utility::get_port_tserver();
 
-        let _alloperations = AllOperations::read_tablet_server_operations(&vec![&hostname], &vec![&port], 1).await;
+        let _alloperations = AllOperations::read_tablet_server_operations(&vec![&hostname], &vec![&port], 1, config).await;
         // the master returns none or more tasks.
     }
 }
\ No newline at end of file
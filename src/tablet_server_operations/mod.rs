@@ -8,6 +8,16 @@
 //! The `/operations` endpoint is available on:
 //! - the tablet server, default port 9000
 //!
+//! This module also reads the compaction queues of the tablet servers from the compaction task gauges in
+//! `/metrics?metrics=compaction_tasks`: the queued (nonactive) and running (active) compaction tasks per compaction type,
+//! which show whether the tablet servers keep up with the writes. The gauges exist since version 2.17.2,
+//! a tablet server of an older version shows blank queues.
+//!
+//! The compaction queues functionality is called from:
+//! - [crate::snapshot::perform_snapshot] -> [crate::tablet_server_operations::AllCompactionQueues::perform_snapshot] (general snapshot)
+//! - [crate] -> [print_operations] (with --details-enable)
+//! - [crate] -> [compaction_queues_diff] (diff between snapshots, also part of --snapshot-diff)
+//!
 mod structs;
 mod functions;
 
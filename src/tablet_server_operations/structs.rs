@@ -1,6 +1,7 @@
 //! The structs
 //!
 use chrono::{DateTime, Local};
+use std::collections::BTreeMap;
 
 #[derive(Debug, Default, Serialize, Deserialize)]
 pub struct Operations {
@@ -24,3 +25,67 @@ pub struct Operation {
     pub total_time_in_flight: String,
     pub description: String,
 }
+/// The metric entities from `/metrics?metrics=compaction_tasks` on the tablet server, of which only the `yb.tabletserver` entity is used.
+///
+/// ```json
+/// [
+///     {
+///         "type": "server",
+///         "id": "yb.tabletserver",
+///         "metrics": [
+///             {
+///                 "name": "nonactive_background_compaction_tasks_added",
+///                 "value": 12
+///             },
+///             {
+///                 "name": "nonactive_background_compaction_tasks_removed",
+///                 "value": 9
+///             }
+///         ]
+///     }
+/// ]
+/// ```
+#[derive(Deserialize, Debug, Default)]
+pub struct TabletServerEntity {
+    #[serde(rename = "type")]
+    pub entity_type: String,
+    pub id: String,
+    pub metrics: Vec<TabletServerGauge>,
+}
+#[derive(Deserialize, Debug, Default)]
+pub struct TabletServerGauge {
+    pub name: String,
+    pub value: Option<i64>,
+}
+/// The compaction queues of a tablet server, as stored in the snapshot.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct CompactionQueues {
+    /// yb_stats added to allow understanding the source host.
+    pub hostname_port: Option<String>,
+    /// yb_stats added to allow understanding the timestamp.
+    pub timestamp: Option<DateTime<Local>>,
+    /// The compaction queue by compaction type: background, full and post_split.
+    pub queues: BTreeMap<String, CompactionQueue>,
+}
+/// The queued and running compaction tasks of a compaction type.
+/// These are None if the tablet server does not provide the compaction task gauges (before version 2.17.2).
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+pub struct CompactionQueue {
+    pub queued: Option<i64>,
+    pub running: Option<i64>,
+}
+#[derive(Debug, Default)]
+pub struct AllCompactionQueues {
+    pub compactionqueues: Vec<CompactionQueues>,
+}
+// diff
+type BTreeCompactionQueuesDiff = BTreeMap<String, CompactionQueuesDiffFields>;
+#[derive(Debug, Default)]
+pub struct CompactionQueuesDiff {
+    pub btreecompactionqueuesdiff: BTreeCompactionQueuesDiff,
+}
+#[derive(Debug, Default)]
+pub struct CompactionQueuesDiffFields {
+    pub first: Option<CompactionQueues>,
+    pub second: Option<CompactionQueues>,
+}
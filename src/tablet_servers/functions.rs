@@ -1,14 +1,16 @@
 //! The impls and functions
 //!
 use chrono::Local;
-use std::{sync::mpsc::channel, time::Instant};
+use std::{collections::BTreeSet, sync::mpsc::channel, time::Instant};
+use regex::Regex;
 use log::*;
 use colored::*;
-use anyhow::Result;
+use anyhow::{bail, Result};
 use crate::isleader::AllIsLeader;
 use crate::utility;
+use crate::utility::Config;
 use crate::snapshot;
-use crate::tablet_servers::{TabletServers, AllTabletServers, TabletServersDiff, TabletServersDiffFields};
+use crate::tablet_servers::{TabletServers, TabletServer, AllTabletServers, TabletServersDiff, TabletServersDiffFields, TabletServerLoad};
 use crate::Opts;
 
 impl TabletServers {
@@ -26,13 +28,14 @@ impl AllTabletServers {
         ports: &Vec<&str>,
         snapshot_number: i32,
         parallel: usize,
+        config: &Config,
     ) -> Result<()>
     {
         info!("begin snapshot");
         let timer = Instant::now();
 
-        let alltabletservers = AllTabletServers::read_tabletservers(hosts, ports, parallel).await;
-        snapshot::save_snapshot_json(snapshot_number,"tablet_servers", alltabletservers.tabletservers)?;
+        let alltabletservers = AllTabletServers::read_tabletservers(hosts, ports, parallel, config).await;
+        snapshot::save_snapshot_json(snapshot_number, "tablet_servers", alltabletservers.tabletservers, config)?;
 
         info!("end snapshot: {:?}", timer.elapsed());
 
@@ -42,6 +45,7 @@ impl AllTabletServers {
         hosts: &Vec<&str>,
         ports: &Vec<&str>,
         parallel: usize,
+        config: &Config,
     ) -> AllTabletServers
     {
         info!("begin parallel http read");
@@ -55,9 +59,9 @@ impl AllTabletServers {
                     let tx = tx.clone();
                     s.spawn(move |_| {
                         let detail_snapshot_time = Local::now();
-                        let mut tablet_servers = AllTabletServers::read_http(host, port);
+                        let mut tablet_servers = AllTabletServers::read_http(host, port, config);
                         tablet_servers.tabletservers.iter_mut().for_each(|(_,v)| v.timestamp = Some(detail_snapshot_time));
-                        tablet_servers.tabletservers.iter_mut().for_each(|(_,v)| v.hostname_port = Some(format!("{}:{}", host, port)));
+                        tablet_servers.tabletservers.iter_mut().for_each(|(_,v)| v.hostname_port = Some(utility::hostname_port(host, port, config)));
                         tablet_servers.tabletservers.iter_mut().for_each(|(k,v)| v.tablet_server_hostname_port = Some(k.to_string()));
                         tx.send(tablet_servers).expect("error sending data via tx");
                     });
@@ -82,9 +86,10 @@ impl AllTabletServers {
     pub fn read_http(
         host: &str,
         port: &str,
+        config: &Config,
     ) -> TabletServers
     {
-        let data_from_http = utility::http_get(host, port, "api/v1/tablet-servers");
+        let data_from_http = utility::http_get_endpoint(host, port, "tablet_servers", config);
         AllTabletServers::parse_tabletservers(data_from_http, host, port)
     }
     fn parse_tabletservers(
@@ -98,13 +103,25 @@ impl AllTabletServers {
                 TabletServers::new()
             })
     }
+    /// Print the tablet servers as reported by the master leader, or by all masters with details_enable.
+    ///
+    /// With heartbeat_warn, the tablet servers are sorted by the time since the last heartbeat to the master, longest first,
+    /// and a tablet server that did not heartbeat for longer than heartbeat_warn seconds is flagged.
+    /// A tablet server that heartbeats slowly is at risk of being marked dead by the master.
     pub fn print(
         &self,
         details_enable: &bool,
         leader_hostname: String,
+        heartbeat_warn: &Option<f64>,
+        config: &Config,
     ) -> Result<()>
     {
-        for row in &self.tabletservers {
+        let mut tabletservers: Vec<&TabletServer> = self.tabletservers.iter().collect();
+        if heartbeat_warn.is_some()
+        {
+            tabletservers.sort_by(|a, b| b.time_since_hb_sec.total_cmp(&a.time_since_hb_sec));
+        }
+        for row in tabletservers {
             // if details_enable is true then always continue
             // if details_enable is false, then hostname_port must be equal to leader_hostname,
             // so only the masters information from the master leader is printed.
@@ -115,99 +132,277 @@ impl AllTabletServers {
             }
             if *details_enable
             {
-                print!("{} ", row.hostname_port.as_ref().expect("hostname:port should be set"));
+                write!(config.output(), "{} ", row.hostname_port.as_ref().expect("hostname:port should be set"))?;
             };
-            print!("{:20} ", row.tablet_server_hostname_port.clone().expect("tablet-server hostname:port should be set"));
+            write!(config.output(), "{:20} ", row.tablet_server_hostname_port.clone().expect("tablet-server hostname:port should be set"))?;
             // this highlights the status row, which makes it easier to spot non-alive (dead) servers.
             if row.status == "ALIVE"
             {
-                print!("{} ", row.status.green());
+                write!(config.output(), "{} ", row.status.green())?;
             }
             else
             {
-                print!("{} ", row.status.red());
+                write!(config.output(), "{} ", row.status.red())?;
             };
-            println!("Placement: {}.{}.{}",
+            writeln!(config.output(), "Placement: {}.{}.{}",
                      row.cloud,
                      row.region,
                      row.zone
-            );
+            )?;
             if *details_enable
             {
-                print!("{} ", row.hostname_port.as_ref().expect("hostname:port should be set"));
+                write!(config.output(), "{} ", row.hostname_port.as_ref().expect("hostname:port should be set"))?;
             };
-            println!("{} HB time: {}, Uptime: {}, Ram {}",
+            writeln!(config.output(), "{} HB time: {}, Uptime: {}, Ram {}{}",
                      " ".repeat(20),
                      row.time_since_hb,
                      row.uptime_seconds,
-                     row.ram_used
-            );
+                     row.ram_used,
+                     if heartbeat_delayed(row, heartbeat_warn) { format!(" {}", "HEARTBEAT DELAYED".red()) } else { String::new() }
+            )?;
             if *details_enable
             {
-                print!("{} ", row.hostname_port.as_ref().expect("hostname:port should be set"));
+                write!(config.output(), "{} ", row.hostname_port.as_ref().expect("hostname:port should be set"))?;
             };
-            println!("{} SST files: nr: {}, size: {}, uncompressed: {}",
+            writeln!(config.output(), "{} SST files: nr: {}, size: {}, uncompressed: {}",
                      " ".repeat(20),
                      row.num_sst_files,
                      row.total_sst_file_size,
                      row.uncompressed_sst_file_size
-            );
+            )?;
             if *details_enable
             {
-                print!("{} ", row.hostname_port.as_ref().expect("hostname:port should be set"));
+                write!(config.output(), "{} ", row.hostname_port.as_ref().expect("hostname:port should be set"))?;
             };
-            println!("{} ops read: {}, write: {}",
+            writeln!(config.output(), "{} ops read: {}, write: {}",
                      " ".repeat(20),
                      row.read_ops_per_sec,
                      row.write_ops_per_sec
-            );
+            )?;
             if *details_enable
             {
-                print!("{} ", row.hostname_port.as_ref().expect("hostname:port should be set"));
+                write!(config.output(), "{} ", row.hostname_port.as_ref().expect("hostname:port should be set"))?;
             };
-            println!("{} tablets: active: {}, user (leader/total): {}/{}, system (leader/total): {}/{}",
+            writeln!(config.output(), "{} tablets: active: {}, user (leader/total): {}/{}, system (leader/total): {}/{}",
                      " ".repeat(20),
                      row.active_tablets,
                      row.user_tablets_leaders,
                      row.user_tablets_total,
                      row.system_tablets_leaders,
                      row.system_tablets_total
-            );
+            )?;
             for path_metric in row.path_metrics.iter()
             {
                 if *details_enable
                 {
-                    print!("{} ", row.hostname_port.as_ref().expect("hostname:port should be set"));
+                    write!(config.output(), "{} ", row.hostname_port.as_ref().expect("hostname:port should be set"))?;
                 };
-                println!("{} Path: {}, total: {}, used: {} ({:.2}%)",
+                writeln!(config.output(), "{} Path: {}, total: {}, used: {} ({}%)",
                          " ".repeat(20),
                          path_metric.path,
                          path_metric.total_space_size,
                          path_metric.space_used,
-                         (path_metric.space_used as f64 / path_metric.total_space_size as f64) * 100.0);
+                         utility::format_float((path_metric.space_used as f64 / path_metric.total_space_size as f64) * 100.0, 0, 2, config))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The dimensions of the load score of a tablet server.
+pub const LOAD_SCORE_DIMENSIONS: [&str; 4] = ["tablets", "leaders", "disk", "memory"];
+
+impl TabletServer {
+    /// The values of the load score dimensions: the user tablets, the user tablet leaders,
+    /// the used percentage of all drives together, and the ram used in bytes.
+    fn load_values(&self) -> [f64; 4] {
+        let space_used: u64 = self.path_metrics.iter().map(|path_metric| path_metric.space_used).sum();
+        let total_space_size: u64 = self.path_metrics.iter().map(|path_metric| path_metric.total_space_size).sum();
+        [
+            f64::from(self.user_tablets_total),
+            f64::from(self.user_tablets_leaders),
+            if total_space_size == 0 { 0.0 } else { space_used as f64 / total_space_size as f64 * 100.0 },
+            self.ram_used_bytes as f64,
+        ]
+    }
+}
+
+impl AllTabletServers {
+    /// The load score of the tablet servers as reported by the master leader, highest first.
+    ///
+    /// Every dimension is relative to the highest value of the cluster, so the tablet server with the highest value scores 1 for it.
+    /// The score is the weighted average of the dimensions times 100,
+    /// and the driver is the dimension that adds the most to the score.
+    fn load_scores(
+        &self,
+        leader_hostname: &str,
+        weights: &[f64; 4],
+    ) -> Vec<TabletServerLoad>
+    {
+        let tabletservers: Vec<&TabletServer> = self.tabletservers
+            .iter()
+            .filter(|tabletserver| tabletserver.hostname_port.as_deref() == Some(leader_hostname))
+            .collect();
+        let mut maximums = [0_f64; 4];
+        for tabletserver in &tabletservers
+        {
+            for (maximum, value) in maximums.iter_mut().zip(tabletserver.load_values())
+            {
+                *maximum = maximum.max(value);
             }
         }
+        let total_weight: f64 = weights.iter().sum();
+        let mut tabletserverloads: Vec<TabletServerLoad> = tabletservers
+            .into_iter()
+            .map(|tabletserver| {
+                let values = tabletserver.load_values();
+                let contributions: Vec<f64> = values
+                    .iter()
+                    .zip(maximums)
+                    .zip(weights)
+                    .map(|((value, maximum), weight)| if maximum > 0.0 { weight * value / maximum } else { 0.0 })
+                    .collect();
+                let (driver, _) = LOAD_SCORE_DIMENSIONS
+                    .iter()
+                    .zip(&contributions)
+                    .fold(("-", 0.0), |(driver, highest), (dimension, contribution)| if *contribution > highest { (*dimension, *contribution) } else { (driver, highest) });
+                TabletServerLoad {
+                    tablet_server_hostname_port: tabletserver.tablet_server_hostname_port.clone().unwrap_or_default(),
+                    status: tabletserver.status.clone(),
+                    ram_used: tabletserver.ram_used.clone(),
+                    values,
+                    score: contributions.iter().sum::<f64>() / total_weight * 100.0,
+                    driver,
+                }
+            })
+            .collect();
+        tabletserverloads.sort_by(|a, b| b.score.total_cmp(&a.score));
+        tabletserverloads
+    }
+    /// Print the tablet servers sorted by load score, followed by the most loaded tablet server and what to look at for the dimension that drives its score.
+    pub fn print_load_score(
+        &self,
+        leader_hostname: &str,
+        weights: &[f64; 4],
+        config: &Config,
+    ) -> Result<()>
+    {
+        let tabletserverloads = self.load_scores(leader_hostname, weights);
+        let rows: Vec<Vec<String>> = tabletserverloads
+            .iter()
+            .map(|load| vec![
+                load.tablet_server_hostname_port.clone(),
+                if load.status == "ALIVE" { load.status.green().to_string() } else { load.status.red().to_string() },
+                load.values[0].to_string(),
+                load.values[1].to_string(),
+                utility::format_float(load.values[2], 0, 2, config),
+                load.ram_used.clone(),
+                utility::format_float(load.score, 0, 1, config),
+                load.driver.to_string(),
+            ])
+            .collect();
+        utility::print_table(&["tablet_server", "status", "tablets", "leaders", "disk_used_pct", "memory", "load_score", "driver"], &rows, config)?;
+        let Some(most_loaded) = tabletserverloads.first().filter(|_| tabletserverloads.len() > 1) else { return Ok(()) };
+        let recommendation = match most_loaded.driver {
+            "tablets" => "the tablets are not spread evenly: check the load balancer (--print-cluster-config) and the placement of the tables",
+            "leaders" => "the leaders are not spread evenly: check the leader balancing and the preferred zones (--print-cluster-config)",
+            "disk" => "the drives are fuller than on the other tablet servers: check the sizes of the tablets (--print-tablet-detail) and the drives (--print-drives)",
+            "memory" => "the memory use is higher than on the other tablet servers: check the memory trackers (--print-memtrackers)",
+            _ => "the tablet servers have no load",
+        };
+        writeln!(config.output(), "
+Most loaded: {} (load score {}, driven by {}): {}",
+                 most_loaded.tablet_server_hostname_port,
+                 utility::format_float(most_loaded.score, 0, 1, config),
+                 most_loaded.driver,
+                 recommendation,
+        )?;
         Ok(())
     }
 }
 
+/// Parse the weights of the load score dimensions in the form of `tablets=2,leaders=1,disk=1,memory=1`.
+/// A dimension that is not set has the weight 1, and a dimension with weight 0 does not count.
+fn load_weights(
+    load_weights: &Option<String>,
+) -> Result<[f64; 4]>
+{
+    let load_weights: Vec<&str> = load_weights
+        .iter()
+        .flat_map(|load_weights| load_weights.split(','))
+        .filter(|load_weight| !load_weight.trim().is_empty())
+        .collect();
+    let weights = utility::parse_weights(&load_weights, &LOAD_SCORE_DIMENSIONS, &[1.0; 4], "load score dimension")?;
+    Ok([weights[0], weights[1], weights[2], weights[3]])
+}
+
+/// The hosts of the tablet servers with a placement (cloud.region.zone) matching the placement regex.
+fn placement_hosts(
+    alltabletservers: &AllTabletServers,
+    placement_filter: &Regex,
+) -> BTreeSet<String>
+{
+    alltabletservers.tabletservers
+        .iter()
+        .filter(|tabletserver| placement_filter.is_match(&format!("{}.{}.{}", tabletserver.cloud, tabletserver.region, tabletserver.zone)))
+        .filter_map(|tabletserver| tabletserver.tablet_server_hostname_port.as_deref())
+        .map(|hostname_port| hostname_port.rsplit_once(':').map(|(host, _)| host).unwrap_or(hostname_port).to_string())
+        .collect()
+}
+
+/// Resolve the tablet servers in the placement of `--placement-match` via the masters,
+/// which restricts reading the non-master ports to the hosts of these tablet servers, see [Config::placement_hosts].
+pub async fn resolve_placement_match(
+    placement_match: &Option<String>,
+    hosts: &Vec<&str>,
+    ports: &Vec<&str>,
+    parallel: usize,
+    config: &Config,
+) -> Result<Option<BTreeSet<String>>>
+{
+    let Some(placement_match) = placement_match else { return Ok(None) };
+    let placement_filter = Regex::new(placement_match)?;
+    let alltabletservers = AllTabletServers::read_tabletservers(hosts, ports, parallel, config).await;
+    if alltabletservers.tabletservers.is_empty()
+    {
+        bail!("No tablet servers found via the masters to resolve --placement-match, the ports must include a master port");
+    }
+    let placement_hosts = placement_hosts(&alltabletservers, &placement_filter);
+    if placement_hosts.is_empty()
+    {
+        bail!("No tablet servers have a placement (cloud.region.zone) matching --placement-match {}", placement_match);
+    }
+    info!("placement hosts: {:?}", placement_hosts);
+    Ok(Some(placement_hosts))
+}
+
+/// Whether the time since the last heartbeat of a tablet server to the master is longer than heartbeat_warn seconds.
+fn heartbeat_delayed(
+    tabletserver: &TabletServer,
+    heartbeat_warn: &Option<f64>,
+) -> bool
+{
+    heartbeat_warn.is_some_and(|heartbeat_warn| f64::from(tabletserver.time_since_hb_sec) > heartbeat_warn)
+}
+
 impl TabletServersDiff {
     pub fn new() -> Self { Default::default() }
     pub fn snapshot_diff(
         begin_snapshot: &String,
         end_snapshot: &String,
+        config: &Config,
     ) -> Result<TabletServersDiff>
     {
         let mut tabletserversdiff = TabletServersDiff::new();
 
         let mut tabletservers = AllTabletServers::new();
-        tabletservers.tabletservers = snapshot::read_snapshot_json(begin_snapshot, "tablet_servers")?;
-        let master_leader = AllIsLeader::return_leader_snapshot(begin_snapshot)?;
+        tabletservers.tabletservers = snapshot::read_snapshot_json(begin_snapshot, "tablet_servers", config)?;
+        let master_leader = AllIsLeader::return_leader_snapshot(begin_snapshot, config)?;
         tabletserversdiff.first_snapshot(tabletservers, master_leader);
 
         let mut tabletservers = AllTabletServers::new();
-        tabletservers.tabletservers = snapshot::read_snapshot_json(end_snapshot, "tablet_servers")?;
-        let master_leader = AllIsLeader::return_leader_snapshot(end_snapshot)?;
+        tabletservers.tabletservers = snapshot::read_snapshot_json(end_snapshot, "tablet_servers", config)?;
+        let master_leader = AllIsLeader::return_leader_snapshot(end_snapshot, config)?;
         tabletserversdiff.second_snapshot(tabletservers, master_leader);
 
         Ok(tabletserversdiff)
@@ -275,15 +470,18 @@ impl TabletServersDiff {
         }
         debug!("{:#?}", self.btreetabletserversdiff);
     }
+    /// Print the changes of the tablet servers, and return the number of changes: the added, removed, changed and restarted tablet servers.
     pub fn print(
         &self,
-    )
+        config: &Config,
+    ) -> Result<usize>
     {
         if ! self.master_found
         {
-            println!("Master leader was not found, skipping tablet servers diff.");
-            return;
+            writeln!(config.output(), "Master leader was not found, skipping tablet servers diff.")?;
+            return Ok(0);
         }
+        let mut changes = 0;
         for (hostname, status) in self.btreetabletserversdiff.iter() {
             // If first and second snapshot status fields are identical,
             // and the first_uptime is lesser than or equal to the second uptime,
@@ -297,45 +495,48 @@ impl TabletServersDiff {
             {
                 continue;
             }
+            changes += 1;
             if status.second_status == *""
             {
-                println!("{} Tserver:  {}, status: {}, uptime: {} s", "-".to_string().red(), hostname, status.first_status, status.first_uptime_seconds);
+                writeln!(config.output(), "{} Tserver:  {}, status: {}, uptime: {} s", "-".to_string().red(), hostname, status.first_status, status.first_uptime_seconds)?;
             }
             else if status.first_status == *""
             {
-                println!("{} Tserver:  {}, status: {}, uptime: {} s", "+".to_string().green(), hostname, status.second_status, status.second_uptime_seconds);
+                writeln!(config.output(), "{} Tserver:  {}, status: {}, uptime: {} s", "+".to_string().green(), hostname, status.second_status, status.second_uptime_seconds)?;
             }
             else
             {
-                print!("{} Tserver:  {}, ", "=".to_string().yellow(), hostname);
+                write!(config.output(), "{} Tserver:  {}, ", "=".to_string().yellow(), hostname)?;
                 if status.first_status != status.second_status
                 {
-                    print!("status: {}->{}, ", status.first_status.to_string().yellow(), status.second_status.to_string().yellow());
+                    write!(config.output(), "status: {}->{}, ", status.first_status.to_string().yellow(), status.second_status.to_string().yellow())?;
                 }
                 else
                 {
-                    print!("status: {}, ", status.first_status);
+                    write!(config.output(), "status: {}, ", status.first_status)?;
                 };
                 if status.second_uptime_seconds < status.first_uptime_seconds
                 {
-                    println!("uptime: {}->{}", status.first_uptime_seconds.to_string().yellow(), status.second_uptime_seconds.to_string().yellow());
+                    writeln!(config.output(), "uptime: {}->{}", status.first_uptime_seconds.to_string().yellow(), status.second_uptime_seconds.to_string().yellow())?;
                 }
                 else
                 {
-                    println!("uptime: {}", status.second_uptime_seconds);
+                    writeln!(config.output(), "uptime: {}", status.second_uptime_seconds)?;
                 };
             };
         }
+        Ok(changes)
     }
     pub async fn adhoc_read_first_snapshot(
         &mut self,
         hosts: &Vec<&str>,
         ports: &Vec<&str>,
         parallel: usize,
+        config: &Config,
     )
     {
-        let alltabletservers = AllTabletServers::read_tabletservers(hosts, ports, parallel).await;
-        let master_leader = AllIsLeader::return_leader_http(hosts, ports, parallel).await;
+        let alltabletservers = AllTabletServers::read_tabletservers(hosts, ports, parallel, config).await;
+        let master_leader = AllIsLeader::return_leader_http(hosts, ports, parallel, config).await;
         self.first_snapshot(alltabletservers, master_leader);
     }
     pub async fn adhoc_read_second_snapshot(
@@ -343,27 +544,32 @@ impl TabletServersDiff {
         hosts: &Vec<&str>,
         ports: &Vec<&str>,
         parallel: usize,
+        config: &Config,
     )
     {
-        let alltabletservers = AllTabletServers::read_tabletservers(hosts, ports, parallel).await;
-        let master_leader = AllIsLeader::return_leader_http(hosts, ports, parallel).await;
+        let alltabletservers = AllTabletServers::read_tabletservers(hosts, ports, parallel, config).await;
+        let master_leader = AllIsLeader::return_leader_http(hosts, ports, parallel, config).await;
         self.second_snapshot(alltabletservers, master_leader);
     }
 }
 
 pub async fn tablet_servers_diff(
     options: &Opts,
+    config: &Config,
 ) -> Result<()>
 {
     if options.begin.is_none() || options.end.is_none() {
-        snapshot::Snapshot::print()?;
+        snapshot::Snapshot::print(config)?;
     }
     if options.snapshot_list { return Ok(()) };
 
     let (begin_snapshot, end_snapshot, _begin_snapshot_row) = snapshot::Snapshot::read_begin_end_snapshot_from_user(options.begin, options.end)?;
 
-    let tabletserversdiff = TabletServersDiff::snapshot_diff(&begin_snapshot, &end_snapshot)?;
-    tabletserversdiff.print();
+    snapshot::Snapshot::print_diff_header(&begin_snapshot, &end_snapshot, config)?;
+
+    let tabletserversdiff = TabletServersDiff::snapshot_diff(&begin_snapshot, &end_snapshot, config)?;
+    let changes = tabletserversdiff.print(config)?;
+    utility::add_diff_changes(changes, config);
 
     Ok(())
 }
@@ -373,24 +579,39 @@ pub async fn print_tablet_servers(
     ports: Vec<&str>,
     parallel: usize,
     options: &Opts,
+    config: &Config,
 ) -> Result<()>
 {
     match options.print_tablet_servers.as_ref().unwrap() {
         Some(snapshot_number) => {
 
             let mut alltabletservers = AllTabletServers::new();
-            alltabletservers.tabletservers = snapshot::read_snapshot_json(snapshot_number, "tablet_servers")?;
-            let leader_hostname = AllIsLeader::return_leader_snapshot(snapshot_number)?;
+            alltabletservers.tabletservers = snapshot::read_snapshot_json(snapshot_number, "tablet_servers", config)?;
+            let leader_hostname = AllIsLeader::return_leader_snapshot(snapshot_number, config)?;
 
-            alltabletservers.print(&options.details_enable, leader_hostname)?;
+            if options.load_score
+            {
+                alltabletservers.print_load_score(&leader_hostname, &load_weights(&options.load_weights)?, config)?;
+            }
+            else
+            {
+                alltabletservers.print(&options.details_enable, leader_hostname, &options.heartbeat_warn, config)?;
+            }
 
         }
         None => {
 
-            let alltabletservers = AllTabletServers::read_tabletservers(&hosts, &ports, parallel).await;
-            let leader_hostname = AllIsLeader::return_leader_http(&hosts, &ports, parallel).await;
+            let alltabletservers = AllTabletServers::read_tabletservers(&hosts, &ports, parallel, config).await;
+            let leader_hostname = AllIsLeader::return_leader_http(&hosts, &ports, parallel, config).await;
 
-            alltabletservers.print(&options.details_enable, leader_hostname)?;
+            if options.load_score
+            {
+                alltabletservers.print_load_score(&leader_hostname, &load_weights(&options.load_weights)?, config)?;
+            }
+            else
+            {
+                alltabletservers.print(&options.details_enable, leader_hostname, &options.heartbeat_warn, config)?;
+            }
 
         }
     }
@@ -446,15 +667,63 @@ mod tests {
         let result = AllTabletServers::parse_tabletservers(json, "", "");
         for (_servername, serverstatus) in result.tabletservers.iter() {
             assert_eq!(serverstatus.status, "ALIVE");
+            assert!(!heartbeat_delayed(serverstatus, &None));
+            assert!(!heartbeat_delayed(serverstatus, &Some(1_f64)));
+            assert!(heartbeat_delayed(serverstatus, &Some(0.5_f64)));
         }
     }
 
+    #[test]
+    fn unit_placement_hosts() {
+        let tabletservers = r#"[{"hostname_port":"yb-1:7000","timestamp":null,"tablet_server_hostname_port":"yb-1:9000","time_since_hb":"0.5s","time_since_hb_sec":0.5,"status":"ALIVE","uptime_seconds":45,"ram_used":"0 B","ram_used_bytes":0,"num_sst_files":0,"total_sst_file_size":"0 B","total_sst_file_size_bytes":0,"uncompressed_sst_file_size":"0 B","uncompressed_sst_file_size_bytes":0,"path_metrics":[],"read_ops_per_sec":0,"write_ops_per_sec":0,"user_tablets_total":1,"user_tablets_leaders":0,"system_tablets_total":12,"system_tablets_leaders":0,"active_tablets":13,"cloud":"gcp","region":"us-west1","zone":"us-west1-a"},
+                                {"hostname_port":"yb-1:7000","timestamp":null,"tablet_server_hostname_port":"yb-2:9000","time_since_hb":"0.5s","time_since_hb_sec":0.5,"status":"ALIVE","uptime_seconds":45,"ram_used":"0 B","ram_used_bytes":0,"num_sst_files":0,"total_sst_file_size":"0 B","total_sst_file_size_bytes":0,"uncompressed_sst_file_size":"0 B","uncompressed_sst_file_size_bytes":0,"path_metrics":[],"read_ops_per_sec":0,"write_ops_per_sec":0,"user_tablets_total":1,"user_tablets_leaders":0,"system_tablets_total":12,"system_tablets_leaders":0,"active_tablets":13,"cloud":"gcp","region":"us-east1","zone":"us-east1-a"}]"#;
+        let alltabletservers = AllTabletServers { tabletservers: serde_json::from_str(tabletservers).unwrap() };
+        assert_eq!(placement_hosts(&alltabletservers, &Regex::new("gcp.us-west1.*").unwrap()).into_iter().collect::<Vec<_>>(), vec!["yb-1".to_string()]);
+        assert_eq!(placement_hosts(&alltabletservers, &Regex::new("gcp").unwrap()).len(), 2);
+        assert!(placement_hosts(&alltabletservers, &Regex::new("aws").unwrap()).is_empty());
+    }
+
+    #[test]
+    fn unit_load_score() {
+        let tabletserver = |tablet_server_hostname_port: &str, status: &str, tablets: i32, leaders: i32, space_used: u64, ram_used_bytes: i64| format!(
+            r#"{{"hostname_port":"yb-1:7000","timestamp":null,"tablet_server_hostname_port":"{}","time_since_hb":"0.5s","time_since_hb_sec":0.5,"status":"{}","uptime_seconds":45,"ram_used":"0 B","ram_used_bytes":{},"num_sst_files":0,"total_sst_file_size":"0 B","total_sst_file_size_bytes":0,"uncompressed_sst_file_size":"0 B","uncompressed_sst_file_size_bytes":0,"path_metrics":[{{"path":"/mnt/d0","space_used":{},"total_space_size":100}}],"read_ops_per_sec":0,"write_ops_per_sec":0,"user_tablets_total":{},"user_tablets_leaders":{},"system_tablets_total":12,"system_tablets_leaders":0,"active_tablets":13,"cloud":"local","region":"local","zone":"local"}}"#,
+            tablet_server_hostname_port, status, ram_used_bytes, space_used, tablets, leaders,
+        );
+        let tabletservers = format!("[{},{},{}]", tabletserver("yb-1:9000", "ALIVE", 10, 2, 40, 100), tabletserver("yb-2:9000", "ALIVE", 10, 8, 40, 100), tabletserver("yb-3:9000", "DEAD", 5, 0, 20, 50));
+        let mut alltabletservers = AllTabletServers { tabletservers: serde_json::from_str(&tabletservers).unwrap() };
+
+        let loads = alltabletservers.load_scores("yb-1:7000", &load_weights(&None).unwrap());
+        assert_eq!(loads.iter().map(|load| load.tablet_server_hostname_port.as_str()).collect::<Vec<_>>(), vec!["yb-2:9000", "yb-1:9000", "yb-3:9000"]);
+        assert_eq!(loads[0].score, 100.0);
+        assert_eq!(loads[0].values, [10.0, 8.0, 40.0, 100.0]);
+        // yb-1: tablets 1, leaders 0.25, disk 1, memory 1
+        assert_eq!(loads[1].score, 81.25);
+        assert_eq!(loads[1].driver, "tablets");
+        assert_eq!(loads[2].status, "DEAD");
+        assert_eq!(loads[2].score, 37.5);
+
+        let loads = alltabletservers.load_scores("yb-1:7000", &load_weights(&Some("tablets=0, leaders=2,disk=0,memory=0".to_string())).unwrap());
+        assert_eq!(loads[0].driver, "leaders");
+        assert_eq!(loads[1].score, 25.0);
+        assert_eq!(loads[2].driver, "-");
+
+        // only the tablet servers as reported by the master leader
+        alltabletservers.tabletservers[2].hostname_port = Some("yb-2:7000".to_string());
+        assert_eq!(alltabletservers.load_scores("yb-1:7000", &load_weights(&None).unwrap()).len(), 2);
+
+        assert!(load_weights(&Some("cpu=1".to_string())).is_err());
+        assert!(load_weights(&Some("tablets".to_string())).is_err());
+        assert!(load_weights(&Some("tablets=-1".to_string())).is_err());
+        assert!(load_weights(&Some("tablets=0,leaders=0,disk=0,memory=0".to_string())).is_err());
+    }
+
     #[tokio::test]
     async fn integration_parse_tabletserver() {
+        let config = &Config::default();
         let hostname = utility::get_hostname_master();
         let port = utility::get_port_master();
 
-        let alltabletservers = AllTabletServers::read_tabletservers(&vec![&hostname], &vec![&port], 1).await;
+        let alltabletservers = AllTabletServers::read_tabletservers(&vec![&hostname], &vec![&port], 1, config).await;
 
         assert!(!alltabletservers.tabletservers.is_empty());
     }
@@ -25,6 +25,9 @@
 //! - region
 //! - zone
 //!
+//! The tablet servers can be printed sorted by a load score ([TabletServerLoad]) of the user tablets, the user tablet leaders,
+//! the used percentage of the drives and the ram used.
+//!
 //!  The `/api/v1/tablet-servers` endpoint is only available on the masters, default port 7000.
 //! It is available on all the masters, the leader as well as the followers.
 //!
@@ -108,6 +108,20 @@ pub struct PathMetrics {
     pub space_used: u64,
     pub total_space_size: u64,
 }
+/// The load of a tablet server for the load score of `--load-score`.
+///
+/// The values are in the order of [crate::tablet_servers::LOAD_SCORE_DIMENSIONS]: user tablets, user tablet leaders, drive used percentage and ram used bytes.
+#[derive(Debug, PartialEq)]
+pub struct TabletServerLoad {
+    pub tablet_server_hostname_port: String,
+    pub status: String,
+    pub ram_used: String,
+    pub values: [f64; 4],
+    /// 0 (no load) to 100 (the highest value of the cluster for every dimension).
+    pub score: f64,
+    /// The dimension that adds the most to the score.
+    pub driver: &'static str,
+}
 /// This struct is used by yb_stats for saving and loading the tablet server data.
 ///
 #[derive(Serialize, Deserialize, Debug, Default)]
@@ -7,6 +7,7 @@ use log::*;
 use anyhow::Result;
 use crate::isleader::AllIsLeader;
 use crate::utility;
+use crate::utility::Config;
 use crate::snapshot;
 use crate::tasks::{AllTasks, TaskDetail, Tasks};
 use crate::Opts;
@@ -21,13 +22,14 @@ impl AllTasks {
         ports: &Vec<&str>,
         snapshot_number: i32,
         parallel: usize,
+        config: &Config,
     ) -> Result<()>
     {
         info!("begin snapshot");
         let timer = Instant::now();
 
-        let alltasks = AllTasks::read_tasks(hosts, ports, parallel).await;
-        snapshot::save_snapshot_json(snapshot_number, "tasks", alltasks.tasks)?;
+        let alltasks = AllTasks::read_tasks(hosts, ports, parallel, config).await;
+        snapshot::save_snapshot_json(snapshot_number, "tasks", alltasks.tasks, config)?;
 
         info!("end snapshot: {:?}", timer.elapsed());
 
@@ -37,6 +39,7 @@ impl AllTasks {
         hosts: &Vec<&str>,
         ports: &Vec<&str>,
         parallel: usize,
+        config: &Config,
     ) -> AllTasks
     {
         info!("begin parallel http read");
@@ -51,9 +54,9 @@ impl AllTasks {
                     let tx = tx.clone();
                     s.spawn(move |_| {
                         let detail_snapshot_time = Local::now();
-                        let mut tasks = AllTasks::read_http(host, port);
+                        let mut tasks = AllTasks::read_http(host, port, config);
                         tasks.timestamp = Some(detail_snapshot_time);
-                        tasks.hostname_port = Some(format!("{}:{}", host, port));
+                        tasks.hostname_port = Some(utility::hostname_port(host, port, config));
                         tx.send(tasks).expect("error sending data via tx");
                     });
                 }
@@ -74,9 +77,10 @@ impl AllTasks {
     fn read_http(
         host: &str,
         port: &str,
+        config: &Config,
     ) -> Tasks
     {
-        let data_from_http = utility::http_get(host, port, "tasks");
+        let data_from_http = utility::http_get_endpoint(host, port, "tasks", config);
         AllTasks::parse_tasks(data_from_http)
     }
     fn parse_tasks(
@@ -206,6 +210,7 @@ impl AllTasks {
         &self,
         details_enable: &bool,
         leader_hostname: String,
+        config: &Config,
     ) -> Result<()>
     {
         for master_row in &self.tasks {
@@ -218,9 +223,9 @@ impl AllTasks {
             {
                 if *details_enable
                 {
-                    print!("{} ", master_row.hostname_port.as_ref().unwrap());
+                    write!(config.output(), "{} ", master_row.hostname_port.as_ref().unwrap())?;
                 }
-                println!("{} {} {} {} {} {} {}",
+                writeln!(config.output(), "{} {} {} {} {} {} {}",
                     row.as_ref().unwrap().task_type,
                     row.as_ref().unwrap().status,
                     row.as_ref().unwrap().name,
@@ -228,7 +233,7 @@ impl AllTasks {
                     row.as_ref().unwrap().start_time,
                     row.as_ref().unwrap().duration,
                     row.as_ref().unwrap().description,
-                );
+                )?;
             }
         }
 
@@ -241,19 +246,20 @@ pub async fn print_tasks(
     ports: Vec<&str>,
     parallel: usize,
     options: &Opts,
+    config: &Config,
 ) -> Result<()>
 {
     match options.print_master_tasks.as_ref().unwrap() {
         Some(snapshot_number) => {
             let mut alltasks = AllTasks::new();
-            alltasks.tasks = snapshot::read_snapshot_json(snapshot_number, "tasks")?;
-            let leader_hostname = AllIsLeader::return_leader_snapshot(snapshot_number)?;
-            alltasks.print(&options.details_enable, leader_hostname)?;
+            alltasks.tasks = snapshot::read_snapshot_json(snapshot_number, "tasks", config)?;
+            let leader_hostname = AllIsLeader::return_leader_snapshot(snapshot_number, config)?;
+            alltasks.print(&options.details_enable, leader_hostname, config)?;
         },
         None => {
-            let alltasks = AllTasks::read_tasks(&hosts, &ports, parallel).await;
-            let leader_hostname = AllIsLeader::return_leader_http(&hosts, &ports, parallel).await;
-            alltasks.print(&options.details_enable, leader_hostname)?;
+            let alltasks = AllTasks::read_tasks(&hosts, &ports, parallel, config).await;
+            let leader_hostname = AllIsLeader::return_leader_http(&hosts, &ports, parallel, config).await;
+            alltasks.print(&options.details_enable, leader_hostname, config)?;
         },
     }
     Ok(())
@@ -432,10 +438,11 @@ mod tests {
 
     #[tokio::test]
     async fn integration_parse_master_tasks() {
+        let config = &Config::default();
         let hostname = utility::get_hostname_master();
         let port = utility::get_port_master();
 
-        let _alltasks = AllTasks::read_tasks(&vec![&hostname], &vec![&port], 1).await;
+        let _alltasks = AllTasks::read_tasks(&vec![&hostname], &vec![&port], 1, config).await;
         // the master returns none or more tasks.
     }
 }
\ No newline at end of file
@@ -1,16 +1,54 @@
 //! The impls and functions
 //!
 use chrono::Local;
-use std::{sync::mpsc::channel, time::Instant};
+use std::{collections::BTreeMap, sync::mpsc::channel, time::Instant};
 use regex::Regex;
 use scraper::{Html, Selector};
 use log::*;
 use anyhow::Result;
 use crate::utility;
+use crate::utility::Config;
 use crate::snapshot;
-use crate::threads::{Threads, AllThreads};
+use crate::threads::{Threads, AllThreads, ThreadsDiff, ThreadsDiffFields};
 use crate::Opts;
 
+const THREAD_CPU_HEADERS: [&str; 6] = ["hostname_port", "thread_name", "user_cpu_s", "kernel_cpu_s", "iowait_s", "cpu_s"];
+
+impl Threads {
+    /// The cumulative user, kernel and iowait times as seconds.
+    /// The times in `/threadz` have an 's' suffix, such as: "2.960s", a time that can't be parsed is 0.
+    fn cpu_times(&self) -> (f64, f64, f64) {
+        let seconds = |time: &str| time.trim().trim_end_matches('s').parse::<f64>().unwrap_or_default();
+        (seconds(&self.cumulative_user_cpu_s), seconds(&self.cumulative_kernel_cpu_s), seconds(&self.cumulative_iowait_cpu_s))
+    }
+}
+
+/// A row of the threads cpu tables: the hostname_port and thread name, the user, kernel and iowait time, and the cpu (user and kernel) time.
+/// The rows are ordered by hostname_port and by cpu time descending, and only the `limit` threads with the highest cpu time are kept per host.
+fn thread_cpu_rows(
+    mut threads: Vec<(String, String, (f64, f64, f64))>,
+    limit: usize,
+) -> Vec<Vec<String>>
+{
+    let cpu = |(user, kernel, _): &(f64, f64, f64)| user + kernel;
+    threads.sort_by(|a, b| a.0.cmp(&b.0).then(cpu(&b.2).total_cmp(&cpu(&a.2))));
+    let mut rows: Vec<Vec<String>> = Vec::new();
+    let mut previous_hostname_port = String::new();
+    let mut host_rows = 0;
+    for (hostname_port, thread_name, times) in threads
+    {
+        if hostname_port != previous_hostname_port
+        {
+            previous_hostname_port = hostname_port.clone();
+            host_rows = 0;
+        }
+        if host_rows == limit { continue };
+        host_rows += 1;
+        rows.push(vec![hostname_port, thread_name, format!("{:.3}", times.0), format!("{:.3}", times.1), format!("{:.3}", times.2), format!("{:.3}", cpu(&times))]);
+    }
+    rows
+}
+
 impl AllThreads {
     pub fn new() -> Self { Default::default() }
     pub async fn perform_snapshot(
@@ -18,13 +56,14 @@ impl AllThreads {
         ports: &Vec<&str>,
         snapshot_number: i32,
         parallel: usize,
+        config: &Config,
     ) -> Result<()>
     {
         info!("begin snapshot");
         let timer = Instant::now();
 
-        let allthreads = AllThreads::read_threads(hosts, ports, parallel).await;
-        snapshot::save_snapshot_json(snapshot_number, "threads", allthreads.threads)?;
+        let allthreads = AllThreads::read_threads(hosts, ports, parallel, config).await;
+        snapshot::save_snapshot_json(snapshot_number, "threads", allthreads.threads, config)?;
 
         info!("end snapshot: {:?}", timer.elapsed());
 
@@ -34,6 +73,7 @@ impl AllThreads {
         hosts: &Vec<&str>,
         ports: &Vec<&str>,
         parallel: usize,
+        config: &Config,
     ) -> AllThreads
     {
         info!("begin parallel http read");
@@ -48,9 +88,9 @@ impl AllThreads {
                     let tx = tx.clone();
                     s.spawn(move |_| {
                         let detail_snapshot_time = Local::now();
-                        let mut threads = AllThreads::read_http(host, port);
+                        let mut threads = AllThreads::read_http(host, port, config);
                         threads.iter_mut().for_each(|r| r.timestamp = detail_snapshot_time);
-                        threads.iter_mut().for_each(|r| r.hostname_port = format!("{}:{}", host, port));
+                        threads.iter_mut().for_each(|r| r.hostname_port = utility::hostname_port(host, port, config));
                         tx.send(threads).expect("error sending data via tx");
                     });
                 }
@@ -74,9 +114,10 @@ impl AllThreads {
     fn read_http(
         host: &str,
         port: &str,
+        config: &Config,
     ) -> Vec<Threads>
     {
-        let data_from_http = utility::http_get(host, port, "threadz?group=all");
+        let data_from_http = utility::http_get_endpoint(host, port, "threads", config);
         AllThreads::parse_threads(data_from_http)
     }
     fn parse_threads(
@@ -184,7 +225,8 @@ impl AllThreads {
     }
     pub fn print(
         &self,
-        hostname_filter: &Regex
+        hostname_filter: &Regex,
+        config: &Config,
     ) -> Result<()>
     {
         let mut previous_hostname_port = String::from("");
@@ -194,24 +236,162 @@ impl AllThreads {
             {
                 if row.hostname_port != previous_hostname_port
                 {
-                    println!("--------------------------------------------------------------------------------------------------------------------------------------");
-                    println!("Host: {}, Snapshot time: {}", &row.hostname_port.to_string(), row.timestamp);
-                    println!("--------------------------------------------------------------------------------------------------------------------------------------");
-                    println!("{:20} {:40} {:>20} {:>20} {:>20} {:50}",
+                    writeln!(config.output(), "--------------------------------------------------------------------------------------------------------------------------------------")?;
+                    writeln!(config.output(), "Host: {}, Snapshot time: {}", &row.hostname_port.to_string(), utility::format_timestamp(&row.timestamp, config))?;
+                    writeln!(config.output(), "--------------------------------------------------------------------------------------------------------------------------------------")?;
+                    writeln!(config.output(), "{:20} {:40} {:>20} {:>20} {:>20} {:50}",
                              "hostname_port",
                              "thread_name",
                              "cum_user_cpu_s",
                              "cum_kernel_cpu_s",
                              "cum_iowait_cpu_s",
-                             "stack");
-                    println!("--------------------------------------------------------------------------------------------------------------------------------------");
+                             "stack")?;
+                    writeln!(config.output(), "--------------------------------------------------------------------------------------------------------------------------------------")?;
                     previous_hostname_port = row.hostname_port.to_string();
                 };
-                println!("{:20} {:40} {:>20} {:>20} {:>20} {:50}", row.hostname_port, row.thread_name, row.cumulative_user_cpu_s, row.cumulative_kernel_cpu_s, row.cumulative_iowait_cpu_s, row.stack.replace('\n', ""));
+                writeln!(config.output(), "{:20} {:40} {:>20} {:>20} {:>20} {:50}", row.hostname_port, row.thread_name, row.cumulative_user_cpu_s, row.cumulative_kernel_cpu_s, row.cumulative_iowait_cpu_s, row.stack.replace('\n', ""))?;
             }
         }
         Ok(())
     }
+    /// Print the `limit` threads with the highest cumulative cpu (user and kernel) time per host.
+    pub fn print_cpu(
+        &self,
+        hostname_filter: &Regex,
+        limit: usize,
+        config: &Config,
+    ) -> Result<()>
+    {
+        let threads = self.threads.iter()
+            .filter(|row| hostname_filter.is_match(&row.hostname_port))
+            .map(|row| (row.hostname_port.clone(), row.thread_name.clone(), row.cpu_times()))
+            .collect();
+        utility::print_table(&THREAD_CPU_HEADERS, &thread_cpu_rows(threads, limit), config)?;
+        Ok(())
+    }
+}
+
+impl ThreadsDiff {
+    pub fn new() -> Self { Default::default() }
+    pub fn snapshot_diff(
+        begin_snapshot: &String,
+        end_snapshot: &String,
+        config: &Config,
+    ) -> Result<ThreadsDiff>
+    {
+        let mut threadsdiff = ThreadsDiff::new();
+
+        let mut allthreads = AllThreads::new();
+        allthreads.threads = snapshot::read_snapshot_json(begin_snapshot, "threads", config)?;
+        threadsdiff.first_snapshot(allthreads);
+
+        let mut allthreads = AllThreads::new();
+        allthreads.threads = snapshot::read_snapshot_json(end_snapshot, "threads", config)?;
+        threadsdiff.second_snapshot(allthreads);
+
+        Ok(threadsdiff)
+    }
+    fn first_snapshot(
+        &mut self,
+        allthreads: AllThreads,
+    )
+    {
+        for thread in allthreads.threads
+        {
+            let hostname_port_thread_name = (thread.hostname_port.clone(), thread.thread_name.clone());
+            self.btreethreadsdiff
+                .entry(hostname_port_thread_name.clone())
+                .and_modify(|_| error!("Duplicate hostname:port and thread name entry: {}, {}", hostname_port_thread_name.0, hostname_port_thread_name.1))
+                .or_insert(ThreadsDiffFields {
+                    first: Some(thread),
+                    ..Default::default()
+                });
+        }
+    }
+    fn second_snapshot(
+        &mut self,
+        allthreads: AllThreads,
+    )
+    {
+        for thread in allthreads.threads
+        {
+            let hostname_port_thread_name = (thread.hostname_port.clone(), thread.thread_name.clone());
+            self.btreethreadsdiff
+                .entry(hostname_port_thread_name)
+                .or_default()
+                .second = Some(thread);
+        }
+    }
+    /// The rows of the diff: the threads with the highest cpu time between the snapshots per host.
+    /// A thread that started after the begin snapshot has used all its time in the interval.
+    /// A thread that stopped before the end snapshot has no end time, and is counted per host as ended instead.
+    fn rows(
+        &self,
+        hostname_filter: &Regex,
+        limit: usize,
+    ) -> (Vec<Vec<String>>, BTreeMap<String, usize>)
+    {
+        let mut threads = Vec::new();
+        let mut ended: BTreeMap<String, usize> = BTreeMap::new();
+        for ((hostname_port, thread_name), row) in self.btreethreadsdiff.iter().filter(|((hostname_port, _), _)| hostname_filter.is_match(hostname_port))
+        {
+            let (first, second) = match (&row.first, &row.second) {
+                (_, None) => {
+                    *ended.entry(hostname_port.clone()).or_default() += 1;
+                    continue;
+                },
+                (first, Some(second)) => (first.as_ref().map(Threads::cpu_times).unwrap_or_default(), second.cpu_times()),
+            };
+            let times = (second.0 - first.0, second.1 - first.1, second.2 - first.2);
+            if times.0 + times.1 <= 0.0 { continue };
+            threads.push((hostname_port.clone(), thread_name.clone(), times));
+        }
+        (thread_cpu_rows(threads, limit), ended)
+    }
+    /// Print the threads with the most cpu time and the threads that ended, and return the number of these threads.
+    pub fn print(
+        &self,
+        hostname_filter: &Regex,
+        limit: usize,
+        config: &Config,
+    ) -> Result<usize>
+    {
+        let (rows, ended) = self.rows(hostname_filter, limit);
+        let changes = rows.len() + ended.values().sum::<usize>();
+        if !rows.is_empty()
+        {
+            writeln!(config.output(), "Threads with the most cpu time between the snapshots:")?;
+            utility::print_table(&THREAD_CPU_HEADERS, &rows, config)?;
+        }
+        for (hostname_port, count) in ended
+        {
+            writeln!(config.output(), "{}: {} threads ended between the snapshots", hostname_port, count)?;
+        }
+        Ok(changes)
+    }
+}
+
+pub async fn threads_diff(
+    options: &Opts,
+    config: &Config,
+) -> Result<()>
+{
+    if options.begin.is_none() || options.end.is_none() {
+        snapshot::Snapshot::print(config)?;
+    }
+    if options.snapshot_list { return Ok(()) };
+
+    let hostname_filter = utility::set_regex(&options.hostname_match);
+
+    let (begin_snapshot, end_snapshot, _begin_snapshot_row) = snapshot::Snapshot::read_begin_end_snapshot_from_user(options.begin, options.end)?;
+
+    snapshot::Snapshot::print_diff_header(&begin_snapshot, &end_snapshot, config)?;
+
+    let threadsdiff = ThreadsDiff::snapshot_diff(&begin_snapshot, &end_snapshot, config)?;
+    let changes = threadsdiff.print(&hostname_filter, options.limit, config)?;
+    utility::add_diff_changes(changes, config);
+
+    Ok(())
 }
 
 pub async fn print_threads(
@@ -219,19 +399,24 @@ pub async fn print_threads(
     ports: Vec<&str>,
     parallel: usize,
     options: &Opts,
+    config: &Config,
 ) -> Result<()>
 {
     let hostname_filter = utility::set_regex(&options.hostname_match);
-    match options.print_threads.as_ref().unwrap() {
+    let allthreads = match options.print_threads.as_ref().unwrap() {
         Some(snapshot_number) => {
             let mut allthreads = AllThreads::new();
-            allthreads.threads = snapshot::read_snapshot_json(snapshot_number, "threads")?;
-            allthreads.print(&hostname_filter)?;
+            allthreads.threads = snapshot::read_snapshot_json(snapshot_number, "threads", config)?;
+            allthreads
         },
         None => {
-            let allthreads = AllThreads::read_threads(&hosts, &ports, parallel).await;
-            allthreads.print(&hostname_filter)?;
+            AllThreads::read_threads(&hosts, &ports, parallel, config).await
         },
+    };
+    if options.thread_cpu {
+        allthreads.print_cpu(&hostname_filter, options.limit, config)?;
+    } else {
+        allthreads.print(&hostname_filter, config)?;
     }
     Ok(())
 }
@@ -379,21 +564,57 @@ server uuid 4ce571a18f8c4a9a8b35246222d12025 local time 2022-03-16 12:33:37.6344
         assert_eq!(result[0].stack, "__clone;start_thread;yb::Thread::SuperviseThread();yb::rpc::Reactor::RunThread();ev_run;epoll_poll;__GI_epoll_wait");
     }
 
+    #[test]
+    fn unit_threads_diff() {
+        let thread = |thread_name: &str, user: &str, kernel: &str| Threads {
+            hostname_port: "localhost:9000".to_string(),
+            thread_name: thread_name.to_string(),
+            cumulative_user_cpu_s: user.to_string(),
+            cumulative_kernel_cpu_s: kernel.to_string(),
+            cumulative_iowait_cpu_s: "0.000s".to_string(),
+            ..Default::default()
+        };
+        let mut threadsdiff = ThreadsDiff::new();
+        threadsdiff.first_snapshot(AllThreads { threads: vec![
+            thread("rpc_worker-100", "1.000s", "0.500s"),
+            thread("rpc_worker-101", "2.000s", "0.000s"),
+            thread("rpc_worker-102", "5.000s", "0.000s"),
+            thread("idle_worker-103", "1.000s", "0.000s"),
+        ]});
+        threadsdiff.second_snapshot(AllThreads { threads: vec![
+            thread("rpc_worker-100", "1.500s", "0.750s"),
+            thread("rpc_worker-101", "4.000s", "0.000s"),
+            thread("idle_worker-103", "1.000s", "0.000s"),
+            thread("rpc_worker-104", "0.100s", "0.000s"),
+        ]});
+        let (rows, ended) = threadsdiff.rows(&utility::set_regex(&None), 2);
+        assert_eq!(rows, vec![
+            vec!["localhost:9000", "rpc_worker-101", "2.000", "0.000", "0.000", "2.000"],
+            vec!["localhost:9000", "rpc_worker-100", "0.500", "0.250", "0.000", "0.750"],
+        ]);
+        assert_eq!(ended, BTreeMap::from([("localhost:9000".to_string(), 1)]));
+        let (rows, _) = threadsdiff.rows(&utility::set_regex(&None), 10);
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[2][1], "rpc_worker-104");
+    }
+
     #[tokio::test]
     async fn integration_parse_threadsdata_master() {
+        let config = &Config::default();
         let hostname = utility::get_hostname_master();
         let port = utility::get_port_master();
 
-        let allthreads = AllThreads::read_threads(&vec![&hostname], &vec![&port], 1).await;
+        let allthreads = AllThreads::read_threads(&vec![&hostname], &vec![&port], 1, config).await;
         // the master returns more than one thread.
         assert!(allthreads.threads.len() > 1);
     }
     #[tokio::test]
     async fn integration_parse_threadsdata_tserver() {
+        let config = &Config::default();
         let hostname = utility::get_hostname_tserver();
         let port = utility::get_port_tserver();
 
-        let allthreads = AllThreads::read_threads(&vec![&hostname], &vec![&port], 1).await;
+        let allthreads = AllThreads::read_threads(&vec![&hostname], &vec![&port], 1, config).await;
 
         // the tablet server returns more than one thread.
         assert!(allthreads.threads.len() > 1);
@@ -5,6 +5,9 @@
 //! This is an HTML table.
 //! The threads module parses the HTML table for the values.
 //!
+//! The cumulative cpu times can be shown as the threads with the most cpu time per host (`--print-threads --thread-cpu`),
+//! and as the threads with the most cpu time between two snapshots (`--threads-diff`), which works as a lightweight profiler.
+//!
 //! The `/threadz` endpoint is only available on:
 //! - the masters, default port 7000
 //! - the tablet servers, default port 9000
@@ -1,6 +1,7 @@
 //! The structs
 //!
 use chrono::{DateTime, Local};
+use std::collections::BTreeMap;
 
 #[derive(Debug, Default, Serialize, Deserialize)]
 pub struct Threads {
@@ -16,4 +17,16 @@ pub struct Threads {
 #[derive(Debug, Default)]
 pub struct AllThreads {
     pub threads: Vec<Threads>,
-}
\ No newline at end of file
+}
+// diff
+// (String, String) = (hostname_port, thread_name)
+type BTreeThreadsDiff = BTreeMap<(String, String), ThreadsDiffFields>;
+#[derive(Debug, Default)]
+pub struct ThreadsDiff {
+    pub btreethreadsdiff: BTreeThreadsDiff,
+}
+#[derive(Debug, Default)]
+pub struct ThreadsDiffFields {
+    pub first: Option<Threads>,
+    pub second: Option<Threads>,
+}
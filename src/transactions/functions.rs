@@ -0,0 +1,307 @@
+//! The impls and functions
+//!
+use chrono::{Local, TimeZone};
+use std::{collections::BTreeMap, sync::mpsc::channel, time::{Duration, Instant}};
+use scraper::{Html, Selector};
+use regex::Regex;
+use log::*;
+use anyhow::{Context, Result};
+use crate::utility;
+use crate::utility::Config;
+use crate::snapshot;
+use crate::tablet_detail::AllTablets;
+use crate::transactions::{AllTransactions, TabletTransaction, ActiveTransaction};
+use crate::Opts;
+
+impl AllTransactions {
+    pub fn new() -> Self { Default::default() }
+    /// The transactions are only added to the snapshot with --extra-data, because they require a request per tablet.
+    pub async fn perform_snapshot(
+        hosts: &Vec<&str>,
+        ports: &Vec<&str>,
+        snapshot_number: i32,
+        parallel: usize,
+        extra_data: &bool,
+        config: &Config,
+    ) -> Result<()>
+    {
+        if !*extra_data { return Ok(()) };
+        info!("begin snapshot");
+        let timer = Instant::now();
+
+        let alltransactions = AllTransactions::read_transactions(hosts, ports, parallel, config).await;
+        snapshot::save_snapshot_json(snapshot_number, "transactions", alltransactions.transactions, config)?;
+
+        info!("end snapshot: {:?}", timer.elapsed());
+        Ok(())
+    }
+    pub async fn read_transactions(
+        hosts: &Vec<&str>,
+        ports: &Vec<&str>,
+        parallel: usize,
+        config: &Config,
+    ) -> AllTransactions
+    {
+        info!("begin parallel http read");
+        let timer = Instant::now();
+
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(parallel).build().unwrap();
+        let (tx, rx) = channel();
+        pool.scope(move |s| {
+            for host in hosts {
+                for port in ports {
+                    let tx = tx.clone();
+                    s.spawn(move |_| {
+                        let detail_snapshot_time = Local::now();
+                        let mut transactions = AllTransactions::read_http(host, port, config);
+                        transactions.iter_mut().for_each(|transaction| {
+                            transaction.timestamp = Some(detail_snapshot_time);
+                            transaction.hostname_port = Some(utility::hostname_port(host, port, config));
+                        });
+                        tx.send(transactions).expect("error sending data via tx");
+                    });
+                }
+            }
+        });
+
+        info!("end parallel http read {:?}", timer.elapsed());
+
+        let mut alltransactions = AllTransactions::new();
+
+        for transactions in rx
+        {
+            alltransactions.transactions.extend(transactions);
+        }
+        alltransactions.transactions.sort_by(|a, b| (&a.hostname_port, &a.tablet_id, &a.transaction_id).cmp(&(&b.hostname_port, &b.tablet_id, &b.transaction_id)));
+
+        alltransactions
+    }
+    /// Read the transactions of the running user tablets of a tablet server.
+    /// The transaction status tablets in the system namespace show the transaction coordinator, which is not read.
+    fn read_http(
+        host: &str,
+        port: &str,
+        config: &Config,
+    ) -> Vec<TabletTransaction>
+    {
+        let data_from_http = utility::http_get_endpoint(host, port, "tablets", config);
+        let tablets = AllTablets::parse_tablets(data_from_http);
+        let mut transactions = Vec::new();
+        for tablet in tablets.tabletbasic
+            .iter()
+            .filter(|tablet| tablet.state == "RUNNING" && tablet.namespace != "system")
+        {
+            let data_from_http = utility::http_get(host, port, format!("transactions?id={}", tablet.tablet_id).as_str(), config);
+            transactions.extend(AllTransactions::parse_transactions(data_from_http, &tablet.tablet_id, &tablet.table_name));
+        }
+        transactions
+    }
+    /// Parse the transaction participant lines in the `<pre>` section of `/transactions?id=<tablet id>`.
+    /// The first line is a summary of the participant, and lines without a transaction id are skipped.
+    fn parse_transactions(
+        http_data: String,
+        tablet_id: &str,
+        table_name: &str,
+    ) -> Vec<TabletTransaction>
+    {
+        let html = Html::parse_document(&http_data);
+        let pre_selector = Selector::parse("pre").unwrap();
+        let transaction_id = Regex::new(r"transaction_id: ([0-9a-f-]+)").unwrap();
+        let isolation = Regex::new(r"isolation: (\w+)").unwrap();
+        let status_tablet = Regex::new(r"status_tablet: (\w+)").unwrap();
+        let start_time = Regex::new(r"start_time: \{ physical: (\d+)").unwrap();
+        let last_known_status = Regex::new(r"last_known_status: (\w+)").unwrap();
+        let capture = |regex: &Regex, line: &str| regex.captures(line).map(|captures| captures[1].to_string());
+
+        let mut transactions = Vec::new();
+        for pre in html.select(&pre_selector)
+        {
+            for line in pre.text().collect::<String>().lines()
+            {
+                let Some(transaction_id) = capture(&transaction_id, line) else { continue };
+                transactions.push(TabletTransaction {
+                    tablet_id: tablet_id.to_string(),
+                    table_name: table_name.to_string(),
+                    transaction_id,
+                    isolation: capture(&isolation, line).unwrap_or_default(),
+                    status_tablet: capture(&status_tablet, line).unwrap_or_default(),
+                    start_time_us: capture(&start_time, line).and_then(|start_time| start_time.parse().ok()),
+                    last_known_status: capture(&last_known_status, line).unwrap_or_default(),
+                    ..Default::default()
+                });
+            }
+        }
+        transactions
+    }
+    /// Join the tablet transactions on the transaction id, sorted by age, oldest first.
+    /// The age is the time between the start time and the time the transactions were read.
+    pub fn active_transactions(
+        &self,
+        hostname_filter: &Regex,
+    ) -> Vec<ActiveTransaction>
+    {
+        let mut active_transactions: BTreeMap<&str, ActiveTransaction> = BTreeMap::new();
+        for transaction in self.transactions
+            .iter()
+            .filter(|transaction| hostname_filter.is_match(transaction.hostname_port.as_deref().unwrap_or_default()))
+        {
+            let active_transaction = active_transactions
+                .entry(&transaction.transaction_id)
+                .or_insert_with(|| ActiveTransaction {
+                    transaction_id: transaction.transaction_id.clone(),
+                    isolation: transaction.isolation.clone(),
+                    start_time_us: transaction.start_time_us,
+                    ..Default::default()
+                });
+            active_transaction.statuses.insert(transaction.last_known_status.clone());
+            active_transaction.involved_tablets.insert(transaction.tablet_id.clone());
+            active_transaction.tables.insert(transaction.table_name.clone());
+            if let (Some(start_time_us), Some(timestamp)) = (transaction.start_time_us, transaction.timestamp)
+            {
+                let age_ms = (timestamp.timestamp_micros() - start_time_us) / 1000;
+                active_transaction.age_ms = Some(active_transaction.age_ms.map_or(age_ms, |current| current.max(age_ms)));
+            }
+        }
+        let mut active_transactions: Vec<ActiveTransaction> = active_transactions.into_values().collect();
+        active_transactions.sort_by(|a, b| b.age_ms.cmp(&a.age_ms).then_with(|| a.transaction_id.cmp(&b.transaction_id)));
+        active_transactions
+    }
+    /// Print the active transactions, oldest first.
+    /// With a minimum age, only the transactions with a known age of at least the minimum age are printed.
+    pub fn print(
+        &self,
+        hostname_filter: &Regex,
+        min_age: &Option<Duration>,
+        details_enable: &bool,
+        config: &Config,
+    ) -> Result<()>
+    {
+        let active_transactions: Vec<ActiveTransaction> = self.active_transactions(hostname_filter)
+            .into_iter()
+            .filter(|transaction| min_age.is_none_or(|min_age| transaction.age_ms.is_some_and(|age_ms| age_ms >= min_age.as_millis() as i64)))
+            .collect();
+        if active_transactions.is_empty()
+        {
+            writeln!(config.output(), "No active transactions{}.", if min_age.is_some() { " with the minimum age" } else { "" })?;
+            return Ok(());
+        }
+        let mut headers = vec!["transaction_id", "status", "isolation", "start_time", "age_s", "tablets", "tables"];
+        if *details_enable
+        {
+            headers.push("involved_tablets");
+        }
+        let rows: Vec<Vec<String>> = active_transactions
+            .iter()
+            .map(|transaction| {
+                let mut row = vec![
+                    transaction.transaction_id.clone(),
+                    transaction.statuses.iter().cloned().collect::<Vec<_>>().join(","),
+                    transaction.isolation.clone(),
+                    transaction.start_time_us
+                        .and_then(|start_time_us| Local.timestamp_micros(start_time_us).single())
+                        .map(|start_time| utility::format_timestamp(&start_time, config))
+                        .unwrap_or_default(),
+                    transaction.age_ms
+                        .map(|age_ms| utility::format_float(age_ms as f64 / 1000_f64, 10, 3, config).trim_start().to_string())
+                        .unwrap_or_default(),
+                    transaction.involved_tablets.len().to_string(),
+                    transaction.tables.iter().cloned().collect::<Vec<_>>().join(","),
+                ];
+                if *details_enable
+                {
+                    row.push(transaction.involved_tablets.iter().cloned().collect::<Vec<_>>().join(","));
+                }
+                row
+            })
+            .collect();
+        utility::print_table(&headers, &rows, config)?;
+        Ok(())
+    }
+}
+
+pub async fn print_transactions(
+    hosts: Vec<&str>,
+    ports: Vec<&str>,
+    parallel: usize,
+    options: &Opts,
+    config: &Config,
+) -> Result<()>
+{
+    let hostname_filter = utility::set_regex(&options.hostname_match);
+    let min_age = options.txn_min_age.as_deref().map(utility::parse_duration).transpose()?;
+    match options.print_transactions.as_ref().unwrap()
+    {
+        Some(snapshot_number) => {
+            let mut alltransactions = AllTransactions::new();
+            alltransactions.transactions = snapshot::read_snapshot_json(snapshot_number, "transactions", config)
+                .with_context(|| format!("Snapshot {} has no transactions, these are only added to a snapshot with --extra-data", snapshot_number))?;
+            alltransactions.print(&hostname_filter, &min_age, &options.details_enable, config)?;
+        },
+        None => {
+            let alltransactions = AllTransactions::read_transactions(&hosts, &ports, parallel, config).await;
+            alltransactions.print(&hostname_filter, &min_age, &options.details_enable, config)?;
+        },
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unit_parse_transactions() {
+        let html = r#"<!DOCTYPE html><html><body><div class='yb-main container-fluid'><h1>Transactions</h1>
+<pre>{ safe_time_for_participant: { physical: 1673260010000000 } remove_queue_size: 0 }
+{ metadata: { transaction_id: 9f1a6bd6-5c1c-4b9d-8a4b-8b5e5b4d9a11 isolation: SNAPSHOT_ISOLATION status_tablet: 8e0d3b1c2c6f4c3e9b2a7e5d4c3b2a19 priority: 12 start_time: { physical: 1673260000123456 } locality: GLOBAL old_status_tablet: } last_batch_data: { next_write_id: 1 hybrid_time: { physical: 1673260000223456 } } replicated_batches: [] local_commit_time: &lt;invalid&gt; last_known_status: PENDING last_known_status_hybrid_time: &lt;invalid&gt; status_waiters_size: 0 }
+{ metadata: { transaction_id: 0a2b3c4d-5e6f-4a1b-8c2d-3e4f5a6b7c8d isolation: SERIALIZABLE_ISOLATION status_tablet: 8e0d3b1c2c6f4c3e9b2a7e5d4c3b2a19 priority: 15 start_time: { physical: 1673260009000000 logical: 1 } locality: GLOBAL old_status_tablet: } last_batch_data: { next_write_id: 0 hybrid_time: &lt;invalid&gt; } replicated_batches: [] local_commit_time: &lt;invalid&gt; last_known_status: COMMITTED last_known_status_hybrid_time: { physical: 1673260009500000 } status_waiters_size: 0 }
+</pre></div></body></html>"#.to_string();
+        let result = AllTransactions::parse_transactions(html, "tablet_1", "t");
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].transaction_id, "9f1a6bd6-5c1c-4b9d-8a4b-8b5e5b4d9a11");
+        assert_eq!(result[0].isolation, "SNAPSHOT_ISOLATION");
+        assert_eq!(result[0].status_tablet, "8e0d3b1c2c6f4c3e9b2a7e5d4c3b2a19");
+        assert_eq!(result[0].start_time_us, Some(1673260000123456));
+        assert_eq!(result[0].last_known_status, "PENDING");
+        assert_eq!(result[0].tablet_id, "tablet_1");
+        assert_eq!(result[1].start_time_us, Some(1673260009000000));
+        assert_eq!(result[1].last_known_status, "COMMITTED");
+
+        // a page without transactions, or that cannot be read.
+        assert!(AllTransactions::parse_transactions(String::new(), "tablet_1", "t").is_empty());
+    }
+
+    #[test]
+    fn unit_active_transactions() {
+        let timestamp = Local.timestamp_micros(1673260010000000).single();
+        let transaction = |hostname_port: &str, tablet_id: &str, transaction_id: &str, start_time_us: i64, status: &str| TabletTransaction {
+            hostname_port: Some(hostname_port.to_string()),
+            timestamp,
+            tablet_id: tablet_id.to_string(),
+            table_name: "t".to_string(),
+            transaction_id: transaction_id.to_string(),
+            isolation: "SNAPSHOT_ISOLATION".to_string(),
+            start_time_us: Some(start_time_us),
+            last_known_status: status.to_string(),
+            ..Default::default()
+        };
+        let alltransactions = AllTransactions { transactions: vec![
+            transaction("yb-1:9000", "tablet_1", "txn_new", 1673260009000000, "PENDING"),
+            transaction("yb-1:9000", "tablet_1", "txn_old", 1673260000000000, "PENDING"),
+            transaction("yb-2:9000", "tablet_2", "txn_old", 1673260000000000, "PENDING"),
+            transaction("yb-2:9000", "tablet_3", "txn_old", 1673260000000000, "COMMITTED"),
+        ]};
+        let result = alltransactions.active_transactions(&utility::set_regex(&None));
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].transaction_id, "txn_old");
+        assert_eq!(result[0].age_ms, Some(10000));
+        assert_eq!(result[0].involved_tablets.iter().collect::<Vec<_>>(), vec!["tablet_1", "tablet_2", "tablet_3"]);
+        assert_eq!(result[0].statuses.iter().collect::<Vec<_>>(), vec!["COMMITTED", "PENDING"]);
+        assert_eq!(result[1].transaction_id, "txn_new");
+        assert_eq!(result[1].age_ms, Some(1000));
+
+        let result = alltransactions.active_transactions(&utility::set_regex(&Some("yb-2".to_string())));
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].involved_tablets.len(), 2);
+    }
+}
@@ -0,0 +1,23 @@
+//! Module for reading the active distributed transactions via `/transactions?id=<tablet id>` on the tablet servers.
+//!
+//! The `/transactions` endpoint of a tablet shows the transactions in the transaction participant of the tablet:
+//! the transactions that have provisional records (intents) in the tablet. These are read for the running
+//! user tablets shown by `/tablets`, which requires a request per tablet, and therefore the transactions are only
+//! added to a snapshot with `--extra-data`.
+//!
+//! The transactions of all tablets are joined on the transaction id into [ActiveTransaction], with the age based on
+//! the start time of the transaction, and the tablets it is involved in. Long-running transactions hold locks and cause
+//! intents to build up.
+//!
+//! The `/transactions` endpoint is available on:
+//! - the tablet servers, default port 9000
+//!
+//! The transactions functionality is called from:
+//! - [crate::snapshot::perform_snapshot] -> [crate::transactions::AllTransactions::perform_snapshot] (general snapshot, saves the transactions with --extra-data)
+//! - [crate] -> [print_transactions] (print adhoc (live) or snapshot transactions)
+//!
+mod structs;
+mod functions;
+
+pub use structs::*;
+pub use functions::*;
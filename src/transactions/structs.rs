@@ -0,0 +1,44 @@
+//! The structs
+//!
+use chrono::{DateTime, Local};
+use std::collections::BTreeSet;
+/// A transaction in the transaction participant of a tablet, from a line of `/transactions?id=<tablet id>`:
+///
+/// ```text
+/// { metadata: { transaction_id: 9f1a6bd6-5c1c-4b9d-8a4b-8b5e5b4d9a11 isolation: SNAPSHOT_ISOLATION status_tablet: 8e0d3b1c2c6f4c3e9b2a7e5d4c3b2a19 priority: 12 start_time: { physical: 1673260000123456 } locality: GLOBAL old_status_tablet: } last_batch_data: { next_write_id: 1 hybrid_time: { physical: 1673260000223456 } } replicated_batches: [] local_commit_time: <invalid> last_known_status: PENDING last_known_status_hybrid_time: <invalid> status_waiters_size: 0 }
+/// ```
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct TabletTransaction {
+    /// yb_stats added to allow understanding the source host.
+    pub hostname_port: Option<String>,
+    /// yb_stats added to allow understanding the timestamp.
+    pub timestamp: Option<DateTime<Local>>,
+    /// yb_stats added: the tablet of the transaction participant.
+    pub tablet_id: String,
+    /// yb_stats added: the table of the tablet.
+    pub table_name: String,
+    pub transaction_id: String,
+    pub isolation: String,
+    pub status_tablet: String,
+    /// The physical part of the start hybrid time, in microseconds since the epoch.
+    pub start_time_us: Option<i64>,
+    /// The last known status, such as PENDING or COMMITTED.
+    pub last_known_status: String,
+}
+#[derive(Debug, Default)]
+pub struct AllTransactions {
+    pub transactions: Vec<TabletTransaction>,
+}
+/// A transaction, joined on the transaction id from the tablet transactions of all tablet servers.
+#[derive(Debug, Default, PartialEq)]
+pub struct ActiveTransaction {
+    pub transaction_id: String,
+    pub isolation: String,
+    /// The last known statuses of the participants, which can differ per tablet.
+    pub statuses: BTreeSet<String>,
+    pub start_time_us: Option<i64>,
+    /// The time between the start time and the time the transactions were read, in milliseconds.
+    pub age_ms: Option<i64>,
+    pub involved_tablets: BTreeSet<String>,
+    pub tables: BTreeSet<String>,
+}
@@ -0,0 +1,418 @@
+//! The impls and functions
+//!
+use std::{cmp::Ordering, collections::{BTreeMap, BTreeSet}};
+use anyhow::{Result, bail};
+use regex::Regex;
+use serde_json::Value;
+use ratatui::{
+    DefaultTerminal,
+    Frame,
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout},
+    style::{Style, Stylize},
+    widgets::{Block, List, Paragraph, Row, Table},
+};
+use crate::snapshot;
+use crate::tui::{TuiApp, TuiTable, Focus};
+use crate::Opts;
+use crate::utility::Config;
+
+/// The maximum width of a column in the table, longer values are cut off.
+const MAX_COLUMN_WIDTH: usize = 50;
+/// The number of rows PageUp and PageDown move.
+const PAGE_ROWS: isize = 20;
+
+impl TuiTable {
+    pub fn new() -> Self { Default::default() }
+    /// Create a table from the JSON values of a data type.
+    ///
+    /// Nested objects are flattened into columns named `object.field`, arrays of plain values are joined with a comma.
+    /// The first field that is an array of objects, such as the metrics of a metric entity, gets a row per object,
+    /// other arrays of objects are shown as JSON.
+    pub fn from_json(values: Vec<Value>) -> TuiTable
+    {
+        let mut flattened_rows: Vec<BTreeMap<String, String>> = Vec::new();
+        for value in values
+        {
+            let Value::Object(object) = value else {
+                flattened_rows.push(BTreeMap::from([("value".to_string(), json_text(&value))]));
+                continue;
+            };
+            let mut fields = BTreeMap::new();
+            let mut row_per_object: Option<(String, Vec<Value>)> = None;
+            for (key, value) in object
+            {
+                match value
+                {
+                    Value::Array(array) if row_per_object.is_none() && !array.is_empty() && array.iter().all(Value::is_object) => {
+                        row_per_object = Some((key, array));
+                    },
+                    value => flatten_json(&key, &value, &mut fields),
+                }
+            }
+            match row_per_object
+            {
+                Some((key, array)) => {
+                    for object in array
+                    {
+                        let mut row = fields.clone();
+                        flatten_json(&key, &object, &mut row);
+                        flattened_rows.push(row);
+                    }
+                },
+                None => flattened_rows.push(fields),
+            }
+        }
+
+        // hostname_port is shown first, the other columns are in alphabetical order.
+        let mut headers: Vec<String> = flattened_rows
+            .iter()
+            .flat_map(|row| row.keys().cloned())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        if let Some(position) = headers.iter().position(|header| header == "hostname_port")
+        {
+            let hostname_port = headers.remove(position);
+            headers.insert(0, hostname_port);
+        }
+        let rows = flattened_rows
+            .iter()
+            .map(|row| headers.iter().map(|header| row.get(header).cloned().unwrap_or_default()).collect())
+            .collect();
+
+        TuiTable { headers, rows }
+    }
+}
+
+impl TuiApp {
+    pub fn new(
+        snapshot_number: &str,
+        data_types: Vec<String>,
+        config: &Config,
+    ) -> Self
+    {
+        let mut tuiapp = TuiApp {
+            snapshot_number: snapshot_number.to_string(),
+            data_types,
+            ..Default::default()
+        };
+        tuiapp.select_data_type(0, config);
+        tuiapp
+    }
+    /// Select a data type in the sidebar, and read its data from the snapshot.
+    fn select_data_type(
+        &mut self,
+        index: usize,
+        config: &Config,
+    )
+    {
+        let Some(data_type) = self.data_types.get(index).cloned() else { return };
+        self.sidebar_state.select(Some(index));
+        match snapshot::read_snapshot_json::<Value>(&self.snapshot_number, &data_type, config)
+        {
+            Ok(values) => {
+                self.table = TuiTable::from_json(values);
+                self.message = String::new();
+            },
+            Err(e) => {
+                self.table = TuiTable::new();
+                self.message = format!("{:#}", e);
+            },
+        }
+        self.sort_column = None;
+        self.sort_descending = false;
+        self.column_offset = 0;
+        self.table_state.select(if self.table.rows.is_empty() { None } else { Some(0) });
+    }
+    /// The rows that match the filter, in the sort order.
+    pub fn visible_rows(&self) -> Vec<&Vec<String>>
+    {
+        // an invalid regex, which happens while typing, does not filter.
+        let filter = Regex::new(&format!("(?i){}", self.filter)).ok();
+        let mut rows: Vec<&Vec<String>> = self.table.rows
+            .iter()
+            .filter(|row| filter.as_ref().is_none_or(|filter| row.iter().any(|value| filter.is_match(value))))
+            .collect();
+        if let Some(sort_column) = self.sort_column
+        {
+            rows.sort_by(|a, b| {
+                let ordering = compare_values(&a[sort_column], &b[sort_column]);
+                if self.sort_descending { ordering.reverse() } else { ordering }
+            });
+        }
+        rows
+    }
+    /// Move the selected row, limited to the visible rows.
+    fn move_row(
+        &mut self,
+        rows: isize,
+    )
+    {
+        let visible_rows = self.visible_rows().len();
+        if visible_rows == 0
+        {
+            self.table_state.select(None);
+            return;
+        }
+        let selected = self.table_state.selected().unwrap_or_default() as isize;
+        self.table_state.select(Some((selected + rows).clamp(0, visible_rows as isize - 1) as usize));
+    }
+    /// Handle a key press, returns false if the terminal UI should quit.
+    pub fn handle_key(
+        &mut self,
+        key_code: KeyCode,
+        config: &Config,
+    ) -> bool
+    {
+        match self.focus
+        {
+            Focus::Filter => {
+                match key_code
+                {
+                    KeyCode::Enter => self.focus = Focus::Table,
+                    KeyCode::Esc => {
+                        self.filter.clear();
+                        self.focus = Focus::Table;
+                    },
+                    KeyCode::Backspace => { self.filter.pop(); },
+                    KeyCode::Char(character) => self.filter.push(character),
+                    _ => {},
+                }
+                self.table_state.select(Some(0));
+                self.move_row(0);
+            },
+            Focus::Sidebar => {
+                let selected = self.sidebar_state.selected().unwrap_or_default();
+                match key_code
+                {
+                    KeyCode::Char('q') => return false,
+                    KeyCode::Up => self.select_data_type(selected.saturating_sub(1), config),
+                    KeyCode::Down => self.select_data_type(selected + 1, config),
+                    KeyCode::Enter | KeyCode::Right | KeyCode::Tab => self.focus = Focus::Table,
+                    KeyCode::Char('/') => self.focus = Focus::Filter,
+                    _ => {},
+                }
+            },
+            Focus::Table => {
+                match key_code
+                {
+                    KeyCode::Char('q') => return false,
+                    KeyCode::Up => self.move_row(-1),
+                    KeyCode::Down => self.move_row(1),
+                    KeyCode::PageUp => self.move_row(-PAGE_ROWS),
+                    KeyCode::PageDown => self.move_row(PAGE_ROWS),
+                    KeyCode::Home => self.move_row(isize::MIN / 2),
+                    KeyCode::End => self.move_row(isize::MAX / 2),
+                    KeyCode::Left => self.column_offset = self.column_offset.saturating_sub(1),
+                    KeyCode::Right if self.column_offset + 1 < self.table.headers.len() => self.column_offset += 1,
+                    KeyCode::Tab | KeyCode::Esc => self.focus = Focus::Sidebar,
+                    KeyCode::Char('/') => self.focus = Focus::Filter,
+                    // sort on the first shown column, sorting on it again reverses the order.
+                    KeyCode::Char('s') if !self.table.headers.is_empty() => {
+                        if self.sort_column == Some(self.column_offset)
+                        {
+                            self.sort_descending = !self.sort_descending;
+                        }
+                        else
+                        {
+                            self.sort_column = Some(self.column_offset);
+                            self.sort_descending = false;
+                        }
+                    },
+                    _ => {},
+                }
+            },
+        }
+        true
+    }
+    fn draw(
+        &mut self,
+        frame: &mut Frame,
+    )
+    {
+        let [main_area, status_area] = Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+        let sidebar_width = self.data_types.iter().map(|data_type| data_type.len()).max().unwrap_or_default().max(16) + 4;
+        let [sidebar_area, table_area] = Layout::horizontal([Constraint::Length(sidebar_width as u16), Constraint::Min(0)]).areas(main_area);
+        let focus_style = |focus: Focus| if self.focus == focus { Style::new().bold() } else { Style::new().dim() };
+
+        let sidebar = List::new(self.data_types.iter().map(String::as_str))
+            .block(Block::bordered().title(format!(" snapshot {} ", self.snapshot_number)).border_style(focus_style(Focus::Sidebar)))
+            .highlight_style(Style::new().reversed());
+        frame.render_stateful_widget(sidebar, sidebar_area, &mut self.sidebar_state);
+
+        let visible_rows: Vec<Vec<String>> = self.visible_rows()
+            .into_iter()
+            .map(|row| row.iter().skip(self.column_offset).map(|value| value.chars().take(MAX_COLUMN_WIDTH).collect()).collect())
+            .collect();
+        let headers: Vec<String> = self.table.headers
+            .iter()
+            .enumerate()
+            .skip(self.column_offset)
+            .map(|(column, header)| match self.sort_column {
+                Some(sort_column) if sort_column == column && self.sort_descending => format!("{} v", header),
+                Some(sort_column) if sort_column == column => format!("{} ^", header),
+                _ => header.clone(),
+            })
+            .collect();
+        let widths: Vec<Constraint> = headers
+            .iter()
+            .enumerate()
+            .map(|(column, header)| {
+                let width = visible_rows
+                    .iter()
+                    .map(|row| row[column].chars().count())
+                    .max()
+                    .unwrap_or_default()
+                    .max(header.chars().count())
+                    .min(MAX_COLUMN_WIDTH);
+                Constraint::Length(width as u16)
+            })
+            .collect();
+        let title = format!(" {} ({} of {} rows) ",
+                            self.sidebar_state.selected().and_then(|selected| self.data_types.get(selected)).cloned().unwrap_or_default(),
+                            visible_rows.len(),
+                            self.table.rows.len()
+        );
+        let table = Table::new(visible_rows.into_iter().map(Row::new), widths)
+            .header(Row::new(headers).bold().underlined())
+            .block(Block::bordered().title(title).border_style(focus_style(Focus::Table)))
+            .row_highlight_style(Style::new().reversed());
+        frame.render_stateful_widget(table, table_area, &mut self.table_state);
+
+        let status = match self.focus
+        {
+            Focus::Filter => format!("filter (regex, enter to apply, esc to clear): {}_", self.filter),
+            _ => format!("{}{}up/down: select  left/right: scroll columns  tab: switch pane  /: filter  s: sort on first column  q: quit",
+                         if self.message.is_empty() { String::new() } else { format!("{} | ", self.message) },
+                         if self.filter.is_empty() { String::new() } else { format!("filter: {} | ", self.filter) },
+            ),
+        };
+        frame.render_widget(Paragraph::new(status), status_area);
+    }
+    fn run(
+        &mut self,
+        terminal: &mut DefaultTerminal,
+        config: &Config,
+    ) -> Result<()>
+    {
+        loop
+        {
+            terminal.draw(|frame| self.draw(frame))?;
+            if let Event::Key(key) = event::read()?
+            {
+                if key.kind == KeyEventKind::Press && !self.handle_key(key.code, config)
+                {
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+/// Flatten a JSON value into fields, the fields of nested objects get the key of the object as prefix.
+fn flatten_json(
+    key: &str,
+    value: &Value,
+    fields: &mut BTreeMap<String, String>,
+)
+{
+    match value
+    {
+        Value::Object(object) => {
+            for (nested_key, nested_value) in object
+            {
+                flatten_json(&format!("{}.{}", key, nested_key), nested_value, fields);
+            }
+        },
+        Value::Array(array) if array.iter().all(|value| !value.is_object() && !value.is_array()) => {
+            fields.insert(key.to_string(), array.iter().map(json_text).collect::<Vec<_>>().join(","));
+        },
+        value => { fields.insert(key.to_string(), json_text(value)); },
+    }
+}
+
+/// The text of a JSON value: strings without quotes, null as empty, and other values as JSON.
+fn json_text(value: &Value) -> String
+{
+    match value
+    {
+        Value::String(string) => string.clone(),
+        Value::Null => String::new(),
+        value => value.to_string(),
+    }
+}
+
+/// Compare two values as numbers if both are numbers, otherwise as text.
+fn compare_values(
+    a: &str,
+    b: &str,
+) -> Ordering
+{
+    match (a.parse::<f64>(), b.parse::<f64>())
+    {
+        (Ok(a), Ok(b)) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+        _ => a.cmp(b),
+    }
+}
+
+pub async fn tui(
+    options: &Opts,
+    config: &Config,
+) -> Result<()>
+{
+    let snapshot_number = options.tui.as_ref().unwrap();
+    let data_types = snapshot::snapshot_data_types(snapshot_number)?;
+    if data_types.is_empty()
+    {
+        bail!("No data found in snapshot {}", snapshot_number);
+    }
+    let mut tuiapp = TuiApp::new(snapshot_number, data_types, config);
+
+    let mut terminal = ratatui::init();
+    let result = tuiapp.run(&mut terminal, config);
+    ratatui::restore();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unit_tui_table_from_json() {
+        let json = r#"[{"hostname_port":"yb-1:9000","type":"tablet","id":"tablet_1","attributes":{"table_name":"t"},"metrics":[{"name":"rows_inserted","value":10},{"name":"rows_deleted","value":2}]},
+                       {"hostname_port":"yb-2:9000","type":"server","id":"yb.tabletserver","attributes":null,"metrics":[]}]"#;
+        let table = TuiTable::from_json(serde_json::from_str(json).unwrap());
+        assert_eq!(table.headers, vec!["hostname_port", "attributes", "attributes.table_name", "id", "metrics", "metrics.name", "metrics.value", "type"]);
+        // a row per metric, and a single row for the entity without metrics.
+        assert_eq!(table.rows.len(), 3);
+        assert_eq!(table.rows[0], vec!["yb-1:9000", "", "t", "tablet_1", "", "rows_inserted", "10", "tablet"]);
+        assert_eq!(table.rows[2], vec!["yb-2:9000", "", "", "yb.tabletserver", "", "", "", "server"]);
+    }
+    #[test]
+    fn unit_tui_filter_and_sort() {
+        let config = &Config::default();
+        let mut tuiapp = TuiApp {
+            table: TuiTable {
+                headers: vec!["name".to_string(), "value".to_string()],
+                rows: vec![
+                    vec!["rows_inserted".to_string(), "10".to_string()],
+                    vec!["rows_deleted".to_string(), "9".to_string()],
+                    vec!["log_bytes".to_string(), "100".to_string()],
+                ],
+            },
+            focus: Focus::Table,
+            ..Default::default()
+        };
+        for key_code in [KeyCode::Char('/'), KeyCode::Char('R'), KeyCode::Char('o'), KeyCode::Char('w'), KeyCode::Enter, KeyCode::Right, KeyCode::Char('s')]
+        {
+            assert!(tuiapp.handle_key(key_code, config));
+        }
+        // the filter is case-insensitive, and the values are sorted as numbers.
+        assert_eq!(tuiapp.visible_rows().iter().map(|row| row[1].as_str()).collect::<Vec<_>>(), vec!["9", "10"]);
+        tuiapp.handle_key(KeyCode::Char('s'), config);
+        assert_eq!(tuiapp.visible_rows().iter().map(|row| row[1].as_str()).collect::<Vec<_>>(), vec!["10", "9"]);
+        assert!(!tuiapp.handle_key(KeyCode::Char('q'), config));
+    }
+}
@@ -0,0 +1,16 @@
+//! Module for the terminal UI to explore a snapshot (`--tui <snapshot number>`).
+//!
+//! The terminal UI shows the data types stored in the snapshot in a sidebar,
+//! and the data of the selected data type as a table in the main pane, which can be filtered and sorted.
+//! It only reads the stored snapshot data, it never fetches data from the cluster.
+//!
+//! The terminal UI is only built with the `tui` feature: `cargo build --release --features tui`.
+//!
+//! The tui functionality is called from:
+//! - [crate] -> [tui] (explores the data of a snapshot)
+//!
+mod structs;
+mod functions;
+
+pub use structs::*;
+pub use functions::*;
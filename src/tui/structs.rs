@@ -0,0 +1,39 @@
+//! The structs
+//!
+use ratatui::widgets::{ListState, TableState};
+
+/// The data of a data type as a table of strings.
+///
+/// The JSON objects of the snapshot data are flattened into columns, see [crate::tui::TuiTable::from_json].
+#[derive(Debug, Default, PartialEq)]
+pub struct TuiTable {
+    pub headers: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+/// The pane that has the keyboard focus.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum Focus {
+    #[default]
+    Sidebar,
+    Table,
+    Filter,
+}
+/// The state of the terminal UI.
+#[derive(Debug, Default)]
+pub struct TuiApp {
+    pub snapshot_number: String,
+    /// The data types in the snapshot, which are the names of the JSON files.
+    pub data_types: Vec<String>,
+    pub sidebar_state: ListState,
+    pub table: TuiTable,
+    pub table_state: TableState,
+    pub focus: Focus,
+    /// Regex filter on all columns of the table, case-insensitive.
+    pub filter: String,
+    pub sort_column: Option<usize>,
+    pub sort_descending: bool,
+    /// The number of columns that are scrolled out of view to the left.
+    pub column_offset: usize,
+    /// A message shown in the status line, such as an error reading the data type.
+    pub message: String,
+}
@@ -1,9 +1,13 @@
 //! Utilities
-//use port_scanner::scan_port_addr;
 use log::*;
-use std::{collections::HashMap, env, fs, io::Write, time::Duration};
-use anyhow::{Context, Result};
+use std::{collections::{BTreeMap, BTreeSet, HashMap}, env, fs, path::PathBuf, io::{stdin, stdout, BufWriter, ErrorKind, IsTerminal, Read, Write}, net::{TcpStream, ToSocketAddrs}, process::{Command, Stdio}, sync::{Arc, Mutex, MutexGuard, OnceLock, atomic::{AtomicUsize, Ordering}}, time::Duration};
+use chrono::{DateTime, Local, TimeZone, Utc};
+use chrono_tz::Tz;
+use anyhow::{bail, Context, Result};
 use regex::Regex;
+use clap::{ArgMatches, parser::ValueSource};
+use colored::*;
+use similar::TextDiff;
 //use qscan::{QScanner, QScanResult, QScanType, QscanTcpConnectState, QScanTcpConnectState};
 //use tokio::runtime::Runtime;
 
@@ -14,64 +18,911 @@ use crate::ACCEPT_INVALID_CERTS;
 use crate::DEFAULT_HOSTS;
 use crate::DEFAULT_PORTS;
 use crate::DEFAULT_PARALLEL;
+use crate::Opts;
+use crate::{node_exporter, snapshot};
+use crate::utility::{Config, DisplayTimezone, LineCounter, RowWriter};
 
-/*
-/// Scan the given host and port combination to see if it's reachable.
+impl Config {
+    /// Create the settings of a run from the options.
+    ///
+    /// The port roles and the placement hosts can change after this: the port roles get the roles of the discovered servers,
+    /// and the placement hosts are read from the cluster, see tablet_servers::placement_hosts.
+    pub fn from_opts(
+        options: &Opts,
+    ) -> Result<Config>
+    {
+        let config = Config {
+            follow_redirects: !options.no_follow_redirects,
+            endpoint_overrides: parse_endpoint_overrides(&options.endpoint_override)?,
+            port_roles: parse_port_roles(&options.port_role)?,
+            // a summary counts all rows, so these are not truncated.
+            max_rows: if options.summary_only { 0 } else { options.max_rows },
+            summary_only: options.summary_only,
+            raw_output: options.raw,
+            timezone: options.timezone.as_deref().map(parse_timezone).transpose()?.unwrap_or_default(),
+            anonymize: options.anonymize,
+            label_matchers: node_exporter::parse_label_matchers(&options.ne_label_match)?,
+            parquet_output: options.format.as_deref() == Some("parquet"),
+            baseline_snapshots: if options.baseline_snapshots.is_empty() { Vec::new() } else { snapshot::parse_baseline_snapshots(&options.baseline_snapshots)? },
+            response_sizes: options.sizes.then(|| Mutex::new(Vec::new())),
+            ..Default::default()
+        };
+        for (data_type, path) in &config.endpoint_overrides
+        {
+            info!("endpoint override: {}: /{} (default: /{})", data_type, path, ENDPOINT_PATHS.iter().find(|(endpoint_type, _)| endpoint_type == data_type).map(|(_, default_path)| *default_path).unwrap_or_default());
+        }
+        for (key, regex) in &config.label_matchers
+        {
+            info!("node_exporter label match: {}: {}", key, regex);
+        }
+        Ok(config)
+    }
+}
+
+impl Config {
+    /// Return the output of the print and diff commands to write to, see [Config::output].
+    ///
+    /// A write fails with [ErrorKind::BrokenPipe] when the pager is quit before all output is written,
+    /// which ends the command as normal, see [is_broken_pipe].
+    pub fn output(&self) -> MutexGuard<'_, Box<dyn Write + Send>>
+    {
+        self.output.lock().unwrap()
+    }
+}
+
+/// The pager command from the `PAGER` environment variable, or less if it is not set, split into the program and arguments.
+/// An empty `PAGER` or `cat` means no pager.
+fn pager_command(
+    pager: Option<String>,
+) -> Option<Vec<String>>
+{
+    let pager = pager.unwrap_or_else(|| "less".to_string());
+    let command: Vec<String> = pager.split_whitespace().map(str::to_string).collect();
+    match command.first().map(String::as_str) {
+        None | Some("cat") => None,
+        Some(_) => Some(command),
+    }
+}
+
+/// Start a pager and write the output to it, like git does, for the print and diff output.
+///
+/// The pager is only started if stdout is a terminal, so piped and scripted output is unchanged.
+/// With auto (not always), less is started with the `LESS` options FRX if `LESS` is not set:
+/// it exits at once if the output fits on the screen, so only long output is paged.
+/// If the pager can't be started, such as when it is not installed, the output goes to stdout.
+pub fn start_pager(
+    always: bool,
+    config: &Config,
+)
+{
+    if !stdout().is_terminal()
+    {
+        return;
+    }
+    let Some(command) = pager_command(env::var("PAGER").ok()) else { return };
+    let mut pager = Command::new(&command[0]);
+    pager.args(&command[1..]).stdin(Stdio::piped());
+    if env::var_os("LESS").is_none()
+    {
+        pager.env("LESS", if always { "RX" } else { "FRX" });
+    }
+    let mut child = match pager.spawn() {
+        Ok(child) => child,
+        Err(error) => {
+            info!("Unable to start pager {}, writing to stdout: {}", command.join(" "), error);
+            return;
+        },
+    };
+    let Some(stdin) = child.stdin.take() else { return };
+    *config.output() = Box::new(BufWriter::new(stdin));
+    *config.pager.lock().unwrap() = Some(child);
+}
+
+/// Stop the pager after all output is written: the output is flushed and the pipe to the pager is closed,
+/// which tells it that the output is complete, and the pager is waited for, so yb_stats ends when the pager is quit.
+/// Without a pager, the output is flushed.
+pub fn stop_pager(
+    config: &Config,
+)
+{
+    let mut output = config.output();
+    output.flush().ok();
+    *output = Box::new(stdout());
+    if let Some(mut child) = config.pager.lock().unwrap().take()
+    {
+        child.wait().ok();
+    }
+}
+
+/// Add the number of changes of a diff, the added, removed and changed rows, for the summary of a diff with `--summary-only`.
+pub fn add_diff_changes(
+    changes: usize,
+    config: &Config,
+)
+{
+    *config.diff_changes.lock().unwrap().get_or_insert(0) += changes;
+}
+
+/// Count the lines of the output instead of writing these, for the summary of a diff with `--summary-only`.
+/// The output is set back to stdout by [stop_pager].
+pub fn count_output_lines(
+    config: &Config,
+) -> Arc<AtomicUsize>
+{
+    let lines = Arc::new(AtomicUsize::new(0));
+    *config.output() = Box::new(LineCounter { lines: lines.clone() });
+    lines
+}
+
+impl Write for LineCounter {
+    fn write(
+        &mut self,
+        buf: &[u8],
+    ) -> std::io::Result<usize>
+    {
+        self.lines.fetch_add(buf.iter().filter(|&&byte| byte == b'\n').count(), Ordering::Relaxed);
+        Ok(buf.len())
+    }
+    fn flush(
+        &mut self,
+    ) -> std::io::Result<()>
+    {
+        Ok(())
+    }
+}
+
+/// Return if an error is a failed write because the output is closed, such as when the pager is quit or the output is piped to head.
+/// This is a normal end of the output, not an error.
+pub fn is_broken_pipe(
+    error: &anyhow::Error,
+) -> bool
+{
+    error.chain().any(|cause| cause.downcast_ref::<std::io::Error>().is_some_and(|io_error| io_error.kind() == ErrorKind::BrokenPipe))
+}
+
+/// Set that the user interrupted yb_stats, so http_get does not do new requests.
+pub fn set_interrupted(
+    config: &Config,
+)
+{
+    config.interrupted.store(true, Ordering::Relaxed);
+}
+
+/// Return if the user interrupted yb_stats.
+pub fn interrupted(
+    config: &Config,
+) -> bool
+{
+    config.interrupted.load(Ordering::Relaxed)
+}
+
+/// Add the hosts to the hostname mapping of --anonymize.
+///
+/// The hosts are added to the mapping in the order specified, so the pseudonyms follow that order.
+pub fn set_anonymize_hosts(
+    hosts: &[&str],
+    config: &Config,
+)
+{
+    if config.anonymize
+    {
+        for host in hosts
+        {
+            anonymize_hostname(host, config);
+        }
+    }
+}
+
+/// Return the pseudonym for a hostname, a hostname that is not in the mapping yet gets the next pseudonym.
+pub fn anonymize_hostname(
+    hostname: &str,
+    config: &Config,
+) -> String
+{
+    let mut mapping = config.anonymize_mapping.lock().unwrap();
+    let next_pseudonym = format!("node-{}", mapping.len() + 1);
+    mapping.entry(hostname.to_string()).or_insert(next_pseudonym).clone()
+}
+
+/// Create the hostname_port for a host and port, with the host replaced by its pseudonym with --anonymize.
+pub fn hostname_port(
+    host: &str,
+    port: &str,
+    config: &Config,
+) -> String
+{
+    if config.anonymize
+    {
+        format!("{}:{}", anonymize_hostname(host, config), port)
+    }
+    else
+    {
+        format!("{}:{}", host, port)
+    }
+}
+
+/// The fields with a host (and port) in the data, of which the hosts are added to the mapping of --anonymize.
+static HOST_FIELD_REGEX: OnceLock<Regex> = OnceLock::new();
+/// A word that can be a hostname, which is replaced if it is in the mapping of --anonymize.
+static HOSTNAME_REGEX: OnceLock<Regex> = OnceLock::new();
+
+/// Add the hosts of the hostname_port and server fields in the data to the mapping,
+/// so that hosts that are not specified with --hosts, such as the servers found via the masters, are anonymized too.
+pub fn learn_hostnames(
+    text: &str,
+    config: &Config,
+)
+{
+    if !config.anonymize
+    {
+        return;
+    }
+    for host in hostnames_in_text(text)
+    {
+        anonymize_hostname(host, config);
+    }
+}
+
+/// The hosts of the hostname_port and server fields in a text, in the order found.
+fn hostnames_in_text(
+    text: &str,
+) -> Vec<&str>
+{
+    HOST_FIELD_REGEX
+        .get_or_init(|| Regex::new(r#""(?:hostname_port|server)":\s*"([^":]+)(?::\d+)?""#).unwrap())
+        .captures_iter(text)
+        .filter_map(|captures| captures.get(1).map(|host| host.as_str()))
+        .collect()
+}
+
+/// Replace all the hostnames in the mapping by their pseudonym in a text, with --anonymize.
+pub fn anonymize_text(
+    text: String,
+    config: &Config,
+) -> String
+{
+    if !config.anonymize
+    {
+        return text;
+    }
+    replace_hostnames(text, &config.anonymize_mapping.lock().unwrap())
+}
+
+/// Replace the hostnames in a text by their pseudonym in the mapping.
+///
+/// Only complete hostnames are replaced, so a hostname such as "yb-1" does not change "yb-10".
+fn replace_hostnames(
+    text: String,
+    mapping: &BTreeMap<String, String>,
+) -> String
+{
+    if !mapping.keys().any(|hostname| text.contains(hostname.as_str()))
+    {
+        return text;
+    }
+    HOSTNAME_REGEX
+        .get_or_init(|| Regex::new(r"[A-Za-z0-9][A-Za-z0-9._-]*").unwrap())
+        .replace_all(&text, |captures: &regex::Captures| {
+            mapping.get(&captures[0]).cloned().unwrap_or_else(|| captures[0].to_string())
+        })
+        .into_owned()
+}
+
+/// Write the mapping of hostnames to pseudonyms as CSV to a file, so the output can be de-anonymized.
+pub fn write_anonymize_mapping(
+    filename: &Option<String>,
+    config: &Config,
+) -> Result<()>
+{
+    if let Some(filename) = filename
+    {
+        let mapping = config.anonymize_mapping.lock().unwrap();
+        let mut writer = csv::Writer::from_path(filename)
+            .with_context(|| format!("Cannot create file: {}", filename))?;
+        writer.write_record(["hostname", "pseudonym"])?;
+        for (hostname, pseudonym) in mapping.iter()
+        {
+            writer.write_record([hostname, pseudonym])?;
+        }
+        writer.flush()
+            .with_context(|| format!("Error writing file: {}", filename))?;
+    }
+    Ok(())
+}
+
+/// Format a calculated value right aligned with the width and precision,
+/// or with `--raw` with full precision and without padding.
+pub fn format_float(
+    value: f64,
+    width: usize,
+    precision: usize,
+    config: &Config,
+) -> String
+{
+    if config.raw_output
+    {
+        value.to_string()
+    }
+    else
+    {
+        format!("{:>width$.precision$}", value)
+    }
+}
+
+/// Parse the timezone option, which is 'local', 'utc' or a timezone name such as 'Europe/Amsterdam'.
+fn parse_timezone(
+    timezone: &str,
+) -> Result<DisplayTimezone>
+{
+    match timezone.to_lowercase().as_str() {
+        "local" => Ok(DisplayTimezone::Local),
+        "utc" => Ok(DisplayTimezone::Utc),
+        _ => timezone
+            .parse::<Tz>()
+            .map(DisplayTimezone::Tz)
+            .map_err(|error| anyhow::anyhow!("Invalid timezone: {}: {}", timezone, error)),
+    }
+}
+
+/// Format a timestamp for display in the timezone of --timezone.
+/// This only changes how timestamps are displayed, the stored timestamps are not changed.
+pub fn format_timestamp<T: TimeZone>(
+    timestamp: &DateTime<T>,
+    config: &Config,
+) -> String
+{
+    match config.timezone {
+        DisplayTimezone::Local => timestamp.with_timezone(&Local).to_string(),
+        DisplayTimezone::Utc => timestamp.with_timezone(&Utc).to_string(),
+        DisplayTimezone::Tz(tz) => timestamp.with_timezone(&tz).to_string(),
+    }
+}
+
+/// Convert a size as shown by the YugabyteDB web pages (1.5K, 2.00M, 0B) to bytes.
+pub fn size_to_bytes(
+    size: &str,
+) -> Option<f64>
+{
+    let (number, multiplier) = match size.chars().last()?
+    {
+        'B' => (&size[..size.len() - 1], 1_f64),
+        'K' => (&size[..size.len() - 1], 1024_f64),
+        'M' => (&size[..size.len() - 1], 1024_f64.powi(2)),
+        'G' => (&size[..size.len() - 1], 1024_f64.powi(3)),
+        'T' => (&size[..size.len() - 1], 1024_f64.powi(4)),
+        _ => (size, 1_f64),
+    };
+    number.parse::<f64>().ok().map(|number| number * multiplier)
+}
+
+/// Convert bytes to a size as shown by the YugabyteDB web pages (1.50K, 2.00M, 0B), see [size_to_bytes].
+pub fn bytes_to_size(
+    bytes: f64,
+) -> String
+{
+    if bytes < 1024_f64
+    {
+        return format!("{}B", bytes);
+    }
+    let (mut size, mut unit) = (bytes, "B");
+    for next_unit in ["K", "M", "G", "T"]
+    {
+        if size < 1024_f64 { break };
+        size /= 1024_f64;
+        unit = next_unit;
+    }
+    format!("{:.2}{}", size, unit)
+}
+
+/// Parse weights in the form of `name=weight`, such as the factors of `--health-score-weight` and the dimensions of `--load-weights`.
+/// Returns the weight of every name, in the order of the names: the parsed weight, or the default weight if it is not set.
+/// A weight must be a number of 0 or more, and the weights must not add up to 0.
+pub fn parse_weights(
+    weights: &[&str],
+    names: &[&str],
+    default_weights: &[f64],
+    what: &str,
+) -> Result<Vec<f64>>
+{
+    let mut parsed_weights = default_weights.to_vec();
+    for weight in weights
+    {
+        let (name, value) = weight
+            .split_once('=')
+            .with_context(|| format!("Invalid {} weight: {}, expected: name=weight", what, weight))?;
+        let Some(position) = names.iter().position(|known_name| *known_name == name.trim()) else {
+            bail!("Unknown {}: {}, valid are: {}", what, name, names.join(", "));
+        };
+        parsed_weights[position] = value
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .filter(|weight| weight.is_finite() && *weight >= 0.0)
+            .with_context(|| format!("Invalid {} weight: {}, the weight must be a number of 0 or more", what, weight))?;
+    }
+    if parsed_weights.iter().sum::<f64>() <= 0.0
+    {
+        bail!("The {} weights add up to 0", what);
+    }
+    Ok(parsed_weights)
+}
+
+/// Parse a duration as a number with a unit: ms, s, m or h, such as 500ms, 30s or 5m.
+/// A number without a unit is in seconds.
+pub fn parse_duration(
+    duration: &str,
+) -> Result<Duration>
+{
+    let regex = Regex::new(r"^\s*(?P<number>[0-9]+(?:\.[0-9]+)?)\s*(?P<unit>ms|s|m|h)?\s*$")?;
+    let captures = regex.captures(duration)
+        .with_context(|| format!("Invalid duration: '{}', expected a number with a unit: ms, s, m or h, such as 30s", duration))?;
+    let number: f64 = captures["number"].parse()?;
+    let seconds = match captures.name("unit").map(|unit| unit.as_str()) {
+        Some("ms") => number / 1000_f64,
+        Some("m") => number * 60_f64,
+        Some("h") => number * 3600_f64,
+        _ => number,
+    };
+    Ok(Duration::from_secs_f64(seconds))
+}
+
+/// Scan the given host and port combination to see if it's reachable: whether a connection can be made to any of the addresses of the host.
 pub fn scan_host_port(
     host: &str,
     port: &str,
 ) -> bool
 {
-    // this currently uses port_scanner, but can be slow (3s).
-    /*
-    if ! scan_port_addr( format!("{}:{}", host, port)) {
-        warn!("Port scanner: hostname:port {}:{} cannot be reached, skipping",host ,port);
-        false
-    } else {
-        true
+    match format!("{}:{}", host, port).to_socket_addrs()
+    {
+        Ok(mut addresses) => addresses.any(|address| TcpStream::connect_timeout(&address, Duration::from_millis(200)).is_ok()),
+        Err(error) => {
+            debug!("Port scanner: hostname:port {}:{} cannot be resolved: {}", host, port, error);
+            false
+        },
+    }
+}
+
+/// The default http endpoint paths for the data types.
+/// These can be overridden with `--endpoint-override type=path`.
+const ENDPOINT_PATHS: [(&str, &str); 28] = [
+    ("metrics", "metrics?reset_histograms=false"),
+    ("node_exporter", "metrics?reset_histograms=false"),
+    ("statements", "statements"),
+    ("ysql_metrics", "metrics?reset_histograms=false"),
+    ("isleader", "api/v1/is-leader"),
+    ("entities", "dump-entities"),
+    ("masters", "api/v1/masters"),
+    ("tablet_servers", "api/v1/tablet-servers"),
+    ("vars", "api/v1/varz"),
+    ("versions", "api/v1/version"),
+    ("gflags", "varz?raw"),
+    ("threads", "threadz?group=all"),
+    ("memtrackers", "mem-trackers"),
+    ("loglines", "logs"),
+    ("rpcs", "rpcz"),
+    ("rpc_traces", "rpcz?include_traces=true"),
+    ("pprof", "pprof/growth"),
+    ("mems", "memz?raw=true"),
+    ("clocks", "tablet-server-clocks"),
+    ("cluster_config", "api/v1/cluster-config"),
+    ("health_check", "api/v1/health-check"),
+    ("tables", "tables"),
+    ("tablets", "tablets"),
+    ("tasks", "tasks"),
+    ("tablet_replication", "tablet-replication"),
+    ("tablet_server_operations", "operations"),
+    ("compaction_queues", "metrics?metrics=compaction_tasks"),
+    ("drives", "drives"),
+];
+
+/// Parse and validate the endpoint overrides in the form of `type=path`.
+fn parse_endpoint_overrides(
+    endpoint_overrides: &[String],
+) -> Result<HashMap<String, String>>
+{
+    let mut overrides = HashMap::new();
+    for endpoint_override in endpoint_overrides
+    {
+        let (data_type, path) = endpoint_override
+            .split_once('=')
+            .with_context(|| format!("Invalid endpoint override: {}, expected: type=path", endpoint_override))?;
+        if !ENDPOINT_PATHS.iter().any(|(endpoint_type, _)| *endpoint_type == data_type)
+        {
+            bail!("Unknown endpoint type: {}, valid are: {}", data_type, ENDPOINT_PATHS.iter().map(|(endpoint_type, _)| *endpoint_type).collect::<Vec<_>>().join(","));
+        }
+        if path.trim_start_matches('/').is_empty()
+        {
+            bail!("Invalid endpoint override: {}, the path is empty", endpoint_override);
+        }
+        overrides.insert(data_type.to_string(), path.trim_start_matches('/').to_string());
+    }
+    Ok(overrides)
+}
+
+/// Return the http endpoint path for a data type, which is the override if set, otherwise the default.
+pub fn endpoint_path(
+    data_type: &str,
+    config: &Config,
+) -> String
+{
+    if let Some(path) = config.endpoint_overrides.get(data_type)
+    {
+        return path.clone();
     }
+    ENDPOINT_PATHS
+        .iter()
+        .find(|(endpoint_type, _)| *endpoint_type == data_type)
+        .map(|(_, path)| path.to_string())
+        .unwrap_or_else(|| panic!("endpoint type {} should be in ENDPOINT_PATHS", data_type))
+}
 
-     */
-    true
-    /* try with qscan crate
-    let mut scanner = QScanner::new(host, port);
-    scanner.set_timeout_ms(100);
-    scanner.set_ntries(1);
-    scanner.set_scan_type(QScanType::TcpConnect);
+/// The default roles of the web server ports, which determine the data types that are read from a port.
+/// These can be overridden with `--port-role port=role`. A port without a role gets all data types requested.
+const DEFAULT_PORT_ROLES: [(&str, &str); 5] = [
+    ("7000", "master"),
+    ("9000", "tserver"),
+    ("12000", "ycql"),
+    ("13000", "ysql"),
+    ("9300", "node_exporter"),
+];
 
-    let results: &Vec<QScanResult> = Runtime::new().unwrap().block_on(scanner.scan_tcp_connect());
+/// The data types that are served by the web server of a port role.
+/// The role "all" is not in this list, it gets all data types requested.
+const ROLE_ENDPOINTS: [(&str, &[&str]); 5] = [
+    ("master", &["metrics", "isleader", "entities", "masters", "tablet_servers", "vars", "versions", "gflags", "threads", "memtrackers", "loglines", "rpcs", "rpc_traces", "pprof", "mems", "clocks", "cluster_config", "health_check", "tables", "tasks", "tablet_replication", "drives"]),
+    ("tserver", &["metrics", "vars", "versions", "gflags", "threads", "memtrackers", "loglines", "rpcs", "rpc_traces", "pprof", "mems", "tablets", "tablet_server_operations", "compaction_queues", "drives"]),
+    ("ycql", &["metrics", "rpcs", "rpc_traces"]),
+    ("ysql", &["metrics", "statements", "ysql_metrics", "rpcs", "rpc_traces"]),
+    ("node_exporter", &["node_exporter"]),
+];
 
-    for result in resuts {
-        if let QScanResult::TcpConnect(sa) = result {
-            if sa.state == QScanTcpConnectState::Open {
-                true
-            } else {
-                false
-            }
+/// Parse and validate the port roles in the form of `port=role`, and add these to the default port roles.
+fn parse_port_roles(
+    port_roles: &[String],
+) -> Result<HashMap<String, String>>
+{
+    let mut roles: HashMap<String, String> = DEFAULT_PORT_ROLES
+        .iter()
+        .map(|(port, role)| (port.to_string(), role.to_string()))
+        .collect();
+    for port_role in port_roles
+    {
+        let (port, role) = port_role
+            .split_once('=')
+            .with_context(|| format!("Invalid port role: {}, expected: port=role", port_role))?;
+        if port.parse::<u16>().is_err()
+        {
+            bail!("Invalid port role: {}, the port is not a port number", port_role);
+        }
+        if role != "all" && !ROLE_ENDPOINTS.iter().any(|(endpoint_role, _)| *endpoint_role == role)
+        {
+            bail!("Unknown port role: {}, valid are: {},all", role, ROLE_ENDPOINTS.iter().map(|(endpoint_role, _)| *endpoint_role).collect::<Vec<_>>().join(","));
         }
+        roles.insert(port.to_string(), role.to_string());
+    }
+    Ok(roles)
+}
+
+/// Set the port roles, and log these.
+pub fn set_port_roles(
+    port_roles: &[String],
+    config: &mut Config,
+) -> Result<()>
+{
+    config.port_roles = parse_port_roles(port_roles)?;
+    for (port, role) in &config.port_roles
+    {
+        debug!("port role: {}: {}", port, role);
+    }
+    Ok(())
+}
+
+/// Return if a data type is served on a port, based on the role of the port.
+/// A port without a known role, or with the role "all", serves all data types.
+fn port_serves_endpoint(
+    roles: &HashMap<String, String>,
+    port: &str,
+    data_type: &str,
+) -> bool
+{
+    match roles.get(port)
+    {
+        Some(role) => ROLE_ENDPOINTS
+            .iter()
+            .find(|(endpoint_role, _)| endpoint_role == role)
+            .is_none_or(|(_, data_types)| data_types.contains(&data_type)),
+        None => true,
+    }
+}
+
+/// Return if a data type is read from a host and port, based on the role of the port and the placement of `--placement-match`.
+/// This is the same selection as [http_get_endpoint] does before a request.
+pub fn endpoint_selected(
+    host: &str,
+    port: &str,
+    data_type: &str,
+    config: &Config,
+) -> bool
+{
+    port_serves_endpoint(&config.port_roles, port, data_type)
+        && config.placement_hosts.as_ref().is_none_or(|placement_hosts| placement_selects(placement_hosts, &config.port_roles, host, port))
+}
+
+/// The port roles that a snapshot is expected to read from, with the default port and a description of the data.
+const EXPECTED_PORT_ROLES: [(&str, &str, &str); 4] = [
+    ("master", "7000", "cluster data such as entities, masters, tablet servers and cluster config"),
+    ("tserver", "9000", "tablet server data such as tserver metrics, tablets and operations"),
+    ("ycql", "12000", "ycql metrics"),
+    ("ysql", "13000", "ysql metrics and statements"),
+];
+
+/// Return the expected port roles that none of the ports has, as (role, default port, data description).
+/// A port without a known role, or with the role "all", could serve any role, and then nothing is reported as missing.
+fn missing_port_roles_in(
+    roles: &HashMap<String, String>,
+    ports: &[&str],
+) -> Vec<(&'static str, &'static str, &'static str)>
+{
+    if ports.iter().any(|port| roles.get(*port).is_none_or(|role| role == "all"))
+    {
+        return Vec::new();
+    }
+    EXPECTED_PORT_ROLES
+        .iter()
+        .filter(|(role, _, _)| !ports.iter().any(|port| roles.get(*port).is_some_and(|port_role| port_role == role)))
+        .copied()
+        .collect()
+}
+
+/// Return the expected port roles that none of the ports has, based on the port roles of --port-role.
+pub fn missing_port_roles(
+    ports: &[&str],
+    config: &Config,
+) -> Vec<(&'static str, &'static str, &'static str)>
+{
+    missing_port_roles_in(&config.port_roles, ports)
+}
+
+/// Return if the host and port are read with the placement hosts: all master ports are read,
+/// other ports only on the hosts in the placement.
+fn placement_selects(
+    placement_hosts: &BTreeSet<String>,
+    roles: &HashMap<String, String>,
+    host: &str,
+    port: &str,
+) -> bool
+{
+    placement_hosts.contains(host) || roles.get(port).is_some_and(|role| role == "master")
+}
+
+/// Return the number of http requests that are skipped, see [http_get_endpoint].
+pub fn skipped_requests(
+    config: &Config,
+) -> usize
+{
+    config.skipped_requests.load(Ordering::Relaxed)
+}
+
+/// Return the number of http requests that failed, see [http_get].
+pub fn failed_requests(
+    config: &Config,
+) -> usize
+{
+    config.failed_requests.load(Ordering::Relaxed)
+}
+
+/// Return the requests that failed since the last call, and clear these, so a snapshot gets the requests that failed for it.
+pub fn take_failed_request_urls(
+    config: &Config,
+) -> Vec<String>
+{
+    std::mem::take(&mut config.failed_request_urls.lock().unwrap())
+}
+
+/// The number of largest responses that print_response_sizes prints.
+const LARGEST_RESPONSES: usize = 10;
+
+/// Print the response sizes that are recorded with --sizes: the total per data type, biggest first, and the largest responses.
+pub fn print_response_sizes(
+    config: &Config,
+) -> Result<()>
+{
+    let Some(response_sizes) = &config.response_sizes else { return Ok(()) };
+    let response_sizes = response_sizes.lock().unwrap();
+    let (data_type_rows, largest_rows) = response_size_rows(&response_sizes);
+    writeln!(config.output(), "Response sizes per data type:")?;
+    print_table(&["data_type", "responses", "total", "largest"], &data_type_rows, config)?;
+    writeln!(config.output(), "Largest responses:")?;
+    print_table(&["hostname_port", "data_type", "size"], &largest_rows, config)?;
+    Ok(())
+}
+
+/// The rows of the response sizes per data type, sorted by the total size, and the rows of the largest responses.
+fn response_size_rows(
+    response_sizes: &[(String, String, usize)],
+) -> (Vec<Vec<String>>, Vec<Vec<String>>)
+{
+    // data type: (responses, total, largest)
+    let mut data_types: BTreeMap<&str, (usize, usize, usize)> = BTreeMap::new();
+    for (_, data_type, bytes) in response_sizes
+    {
+        let data_type = data_types.entry(data_type).or_default();
+        data_type.0 += 1;
+        data_type.1 += bytes;
+        data_type.2 = data_type.2.max(*bytes);
     }
+    let mut data_types: Vec<_> = data_types.into_iter().collect();
+    data_types.sort_by_key(|(_, (_, total, _))| std::cmp::Reverse(*total));
+    let data_type_rows = data_types
+        .into_iter()
+        .map(|(data_type, (responses, total, largest))| vec![
+            data_type.to_string(),
+            responses.to_string(),
+            bytes_to_size(total as f64),
+            bytes_to_size(largest as f64),
+        ])
+        .collect();
 
-     */
+    let mut largest: Vec<_> = response_sizes.iter().collect();
+    largest.sort_by(|first, second| second.2.cmp(&first.2).then_with(|| first.cmp(second)));
+    let largest_rows = largest
+        .into_iter()
+        .take(LARGEST_RESPONSES)
+        .map(|(hostname_port, data_type, bytes)| vec![hostname_port.clone(), data_type.clone(), bytes_to_size(*bytes as f64)])
+        .collect();
+    (data_type_rows, largest_rows)
 }
 
- */
+/// Reads the http endpoint of a data type, see [endpoint_path], and returns the result as String.
+///
+/// If the data type is not served by the role of the port, such as the masters data on a tablet server port,
+/// no request is done and an empty String is returned, which is the same as for an endpoint that cannot be read.
+pub fn http_get_endpoint(
+    host: &str,
+    port: &str,
+    data_type: &str,
+    config: &Config,
+) -> String
+{
+    if !port_serves_endpoint(&config.port_roles, port, data_type)
+    {
+        debug!("Skipped: {}:{} does not serve {}", host, port, data_type);
+        config.skipped_requests.fetch_add(1, Ordering::Relaxed);
+        return String::new();
+    }
+    if !config.placement_hosts.as_ref().is_none_or(|placement_hosts| placement_selects(placement_hosts, &config.port_roles, host, port))
+    {
+        debug!("Skipped: {}:{} is not in the placement of --placement-match", host, port);
+        config.skipped_requests.fetch_add(1, Ordering::Relaxed);
+        return String::new();
+    }
+    let response = http_get_bytes(host, port, &endpoint_path(data_type, config), config);
+    write_raw_response(host, port, data_type, &response, config);
+    let response = response_text(response, config);
+    if let Some(response_sizes) = &config.response_sizes
+    {
+        if !response.is_empty()
+        {
+            response_sizes.lock().unwrap().push((hostname_port(host, port, config), data_type.to_string(), response.len()));
+        }
+    }
+    response
+}
 
 /// Reads the http endpoint as specified by the caller, and returns the result as String.
+///
+/// After an interrupt, no request is done and an empty String is returned,
+/// which is the same as for an endpoint that cannot be read.
 pub fn http_get(
     host: &str,
     port: &str,
     url: &str,
+    config: &Config,
+) -> String
+{
+    let response = http_get_bytes(host, port, url, config);
+    write_raw_response(host, port, url, &response, config);
+    response_text(response, config)
+}
+
+/// Convert a response to text, with the hostnames replaced if --anonymize is set.
+/// The hosts in the response are added to the mapping first, so these are replaced too.
+fn response_text(
+    response: Vec<u8>,
+    config: &Config,
+) -> String
+{
+    if response.is_empty()
+    {
+        return String::new();
+    }
+    let text = String::from_utf8_lossy(&response).into_owned();
+    learn_hostnames(&text, config);
+    anonymize_text(text, config)
+}
+
+/// Set the directory to write the raw responses to with --keep-raw, or None to stop writing these.
+pub fn set_raw_directory(
+    raw_directory: Option<PathBuf>,
+    config: &Config,
+)
+{
+    *config.raw_directory.lock().unwrap() = raw_directory;
+}
+
+/// The filename of a raw response: the host, the port and the data type, or the url for a request that is not for a data type,
+/// with the characters that are not safe in a filename replaced by `_`.
+fn raw_response_filename(
+    host: &str,
+    port: &str,
+    name: &str,
 ) -> String
 {
+    format!("{}_{}_{}", host, port, name)
+        .chars()
+        .map(|character| if character.is_ascii_alphanumeric() || matches!(character, '.' | '-' | '_') { character } else { '_' })
+        .collect()
+}
+
+/// Write a response as is, as bytes, to the raw directory, if set with --keep-raw.
+/// A failure to write is logged, and doesn't fail the snapshot.
+fn write_raw_response(
+    host: &str,
+    port: &str,
+    name: &str,
+    response: &[u8],
+    config: &Config,
+)
+{
+    let Some(raw_directory) = config.raw_directory.lock().unwrap().clone() else { return };
+    if response.is_empty() { return };
+    let raw_file = raw_directory.join(raw_response_filename(host, port, name));
+    if let Err(e) = fs::write(&raw_file, response)
+    {
+        warn!("Error writing raw response: {}: {}", raw_file.display(), e);
+    }
+}
+
+/// Reads the http endpoint as specified by the caller, and returns the response body as is.
+fn http_get_bytes(
+    host: &str,
+    port: &str,
+    url: &str,
+    config: &Config,
+) -> Vec<u8>
+{
+    if interrupted(config)
+    {
+        debug!("Interrupted, skipped: {}:{}/{}", host, port, url);
+        return Vec::new();
+    }
+    let redirect_policy = if config.follow_redirects
+    {
+        reqwest::redirect::Policy::custom(|attempt| {
+            info!("Redirect encountered: {} to {}", attempt.previous().last().map(|url| url.as_str()).unwrap_or_default(), attempt.url());
+            // this is the same limit as reqwest's default policy
+            if attempt.previous().len() > 10 { attempt.error("too many redirects") } else { attempt.follow() }
+        })
+    }
+    else
+    {
+        reqwest::redirect::Policy::none()
+    };
     if let Ok(data_from_web_request) = reqwest::blocking::Client::builder()
         .connect_timeout(Duration::from_millis(200))
         .danger_accept_invalid_certs(ACCEPT_INVALID_CERTS)
+        .redirect(redirect_policy)
         .build()
         .unwrap()
         .get(format!("http://{}:{}/{}", host, port, url))
         .send()
     {
-        if ! &data_from_web_request.status().is_success()
+        if data_from_web_request.status().is_redirection()
+        {
+            info!("Redirect not followed: {}:{}/{} = {}, location: {}", host, port, url, &data_from_web_request.status(),
+                data_from_web_request.headers().get(reqwest::header::LOCATION).and_then(|location| location.to_str().ok()).unwrap_or_default());
+        }
+        else if ! &data_from_web_request.status().is_success()
         {
             debug!("Non success response: {}:{}/{} = {}", host, port, url, &data_from_web_request.status());
         }
@@ -79,21 +930,67 @@ pub fn http_get(
         {
            debug!("Success response: {}:{}/{} = {}", host, port, url, &data_from_web_request.status());
         }
-        data_from_web_request.text().unwrap()
+        data_from_web_request.bytes().unwrap().to_vec()
     } else {
         debug!("Non-Ok success response: {}:{}/{}", host, port, url);
-        String::new()
+        config.failed_requests.fetch_add(1, Ordering::Relaxed);
+        config.failed_request_urls.lock().unwrap().push(format!("{}:{}/{}", host, port, url));
+        Vec::new()
     }
 }
 
+/// Read a list of hosts from a file, or from stdin if the path is `-`.
+///
+/// The file contains one host per line. Blank lines and comments (starting with `#`) are ignored.
+/// The hosts are returned as a comma separated string, identical to how `--hosts` is specified.
+pub fn read_hosts_file(
+    path: &str,
+) -> Result<String>
+{
+    let hosts_text = if path == "-" {
+        info!("reading hosts from stdin");
+        let mut hosts_text = String::new();
+        stdin().read_to_string(&mut hosts_text)
+            .with_context(|| "Error reading hosts from stdin")?;
+        hosts_text
+    } else {
+        info!("reading hosts from file: {}", path);
+        fs::read_to_string(path)
+            .with_context(|| format!("Error reading hosts file: {}", path))?
+    };
+    Ok(parse_hosts_lines(&hosts_text))
+}
+/// Turn the lines of a hosts file into a comma separated hosts string.
+fn parse_hosts_lines(
+    hosts_text: &str,
+) -> String
+{
+    hosts_text
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default().trim())
+        .filter(|host| !host.is_empty())
+        .collect::<Vec<&str>>()
+        .join(",")
+}
+
 /// Take the hosts from the [Option] struct, and:
+/// - reads the hosts from a file or stdin if `--hosts-file` is set, or `--hosts` is set to `-`.
 /// - adds it to the changed_options hashmap if necessary.
-/// - returns a Vec<&str>.
+/// - returns a Vec<&str>, without duplicates, see [remove_duplicates].
 pub fn set_hosts(
     option: &Option<String>,
+    hosts_file: &Option<String>,
     changed_options: &mut HashMap<&str, String>,
-) -> Vec<&'static str>
+    silent: bool,
+) -> Result<Vec<&'static str>>
 {
+    // is --hosts-file set, or --hosts set to '-'?
+    // then read the hosts, and treat them as if they were specified with --hosts.
+    let option = &match (hosts_file, option) {
+        (Some(path), _) => Some(read_hosts_file(path)?),
+        (None, Some(hosts)) if hosts == "-" => Some(read_hosts_file("-")?),
+        (None, option) => option.clone(),
+    };
     // is --hosts/-H set?
     let hosts_string = if option.is_some() {
         info!("hosts argument set: using: {}", &option.as_ref().unwrap());
@@ -118,16 +1015,17 @@ pub fn set_hosts(
         }
     };
     let static_hosts: &'static str = Box::leak(hosts_string.into_boxed_str());
-    let hosts: Vec<&'static str> = static_hosts.split(',').collect();
-    hosts
+    let hosts: Vec<&'static str> = split_list(static_hosts);
+    Ok(remove_duplicates("hosts", hosts, silent))
 }
 
 /// Take the ports from the [Option] struct, and:
 /// - adds it to the changed_options hashmap if necessary.
-/// - returns a Vec<&str>.
+/// - returns a Vec<&str>, without duplicates, see [remove_duplicates].
 pub fn set_ports(
     option: &Option<String>,
     changed_options: &mut HashMap<&str, String>,
+    silent: bool,
 ) -> Vec<&'static str>
 {
     // is --ports/-p set?
@@ -154,17 +1052,116 @@ pub fn set_ports(
         }
     };
     let static_ports: &'static str = Box::leak(ports_string.into_boxed_str());
-    let ports: Vec<&'static str> = static_ports.split(',').collect();
-    ports
+    let ports: Vec<&'static str> = split_list(static_ports);
+    remove_duplicates("ports", ports, silent)
+}
+
+/// The hosts and ports for `--local`: localhost, and the ports of [DEFAULT_PORTS] that are listening on localhost,
+/// so a local single-node cluster is read without setting hosts and ports.
+/// The hosts and ports are not added to changed_options, so the hosts and ports that are saved remain unchanged.
+/// The ports that are not listening are printed, unless silent is set.
+pub fn set_local_hosts_ports(
+    silent: bool,
+) -> Result<(Vec<&'static str>, Vec<&'static str>)>
+{
+    let (ports, skipped): (Vec<&'static str>, Vec<&'static str>) = DEFAULT_PORTS
+        .split(',')
+        .partition(|port| scan_host_port(LOCAL_HOST, port));
+    if ports.is_empty()
+    {
+        bail!("None of the ports {} is listening on {}", DEFAULT_PORTS, LOCAL_HOST);
+    }
+    info!("local: using {}, listening ports: {}, skipped ports: {}", LOCAL_HOST, ports.join(","), skipped.join(","));
+    if !skipped.is_empty() && !silent
+    {
+        eprintln!("Ports not listening on {}, skipped: {}", LOCAL_HOST, skipped.join(","));
+    }
+    Ok((vec![LOCAL_HOST], ports))
+}
+
+/// The host of `--local`.
+const LOCAL_HOST: &str = "localhost";
+
+/// The cluster domain of the Kubernetes service DNS names of `--sts`.
+const KUBERNETES_CLUSTER_DOMAIN: &str = "svc.cluster.local";
+
+/// The hosts for `--sts`: the DNS names of the pods of a Kubernetes stateful set behind a headless service,
+/// which are `<name>-<ordinal>.<service>.<namespace>.svc.cluster.local` for the ordinals 0 up to the number of replicas.
+/// The service is the name of the stateful set, unless it is set with `--sts-service`.
+/// The hosts are not added to changed_options, so the hosts that are saved remain unchanged.
+pub fn set_statefulset_hosts(
+    name: &str,
+    service: &Option<String>,
+    replicas: usize,
+    namespace: &str,
+) -> Vec<&'static str>
+{
+    let service = service.as_deref().unwrap_or(name);
+    let hosts: Vec<&'static str> = (0..replicas)
+        .map(|ordinal| &*Box::leak(format!("{}-{}.{}.{}.{}", name, ordinal, service, namespace, KUBERNETES_CLUSTER_DOMAIN).into_boxed_str()))
+        .collect();
+    info!("statefulset: using hosts: {}", hosts.join(","));
+    hosts
+}
+
+/// Split a comma separated list of hosts or ports, without the spaces around the entries and without empty entries,
+/// so "yb-1, yb-2," is yb-1 and yb-2, and "yb-1,yb-1 " is found to be a duplicate.
+fn split_list(
+    list: &'static str,
+) -> Vec<&'static str>
+{
+    list.split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .collect()
+}
+
+/// Remove the duplicate hosts or ports, keeping the first occurrence, so every host:port combination is read once.
+/// A host or port that is read twice would be counted twice in the cluster wide sums, such as the leader counts.
+/// The removed duplicates are printed as warning, unless silent is set.
+fn remove_duplicates(
+    name: &str,
+    entries: Vec<&'static str>,
+    silent: bool,
+) -> Vec<&'static str>
+{
+    let mut seen = BTreeSet::new();
+    let (unique, duplicates): (Vec<&'static str>, Vec<&'static str>) = entries
+        .into_iter()
+        .partition(|entry| seen.insert(*entry));
+    if !duplicates.is_empty() && !silent
+    {
+        eprintln!("Warning: duplicate {} removed: {}", name, duplicates.join(","));
+    }
+    unique
+}
+
+/// The maximal parallelism that is chosen with `--parallel auto`.
+const AUTO_PARALLEL_MAX: usize = 16;
+/// The maximal parallelism per host that is chosen with `--parallel auto`.
+const AUTO_PARALLEL_PER_HOST: usize = 2;
+
+/// The parallelism for `--parallel auto`: a thread per host:port combination,
+/// but at most [AUTO_PARALLEL_PER_HOST] per host to not overwhelm a small cluster, and at most [AUTO_PARALLEL_MAX].
+fn auto_parallel(
+    hosts: usize,
+    ports: usize,
+) -> usize
+{
+    (hosts * ports)
+        .min(hosts * AUTO_PARALLEL_PER_HOST)
+        .clamp(1, AUTO_PARALLEL_MAX)
 }
 
 /// Take the parallel from the [Option] struct, and:
 /// - adds it to the changed_options hashmap if necessary.
-/// - returns a usize.
+/// - returns a usize, for "auto" based on the number of hosts and ports, see [auto_parallel].
 pub fn set_parallel(
     option: &Option<String>,
+    hosts: &[&str],
+    ports: &[&str],
     changed_options: &mut HashMap<&str, String>,
-) -> usize
+) -> Result<usize>
 {
     // is --parallel set?
     let parallel_string = if option.is_some() {
@@ -189,8 +1186,111 @@ pub fn set_parallel(
             }
         }
     };
-    let parallel: usize = parallel_string.parse().unwrap();
-    parallel
+    if parallel_string == "auto"
+    {
+        let parallel = auto_parallel(hosts.len(), ports.len());
+        info!("parallel auto: using: {} for {} hosts and {} ports", parallel, hosts.len(), ports.len());
+        return Ok(parallel);
+    }
+    let parallel: usize = parallel_string.parse()
+        .with_context(|| format!("Invalid parallel: {}, expected a number or auto", parallel_string))?;
+    Ok(parallel)
+}
+
+/// The source of a setting that is read from an argument, from the environment variable, or the default,
+/// in the same order as [set_hosts], [set_ports] and [set_parallel] use.
+///
+/// The variable is set via the .env file if it is in the .env file with the same value, because the .env file
+/// does not override a variable that is set in the environment already.
+fn setting_source(
+    argument_set: bool,
+    variable: &str,
+    dotenv_variables: &HashMap<String, String>,
+) -> String
+{
+    if argument_set {
+        return "argument".to_string();
+    }
+    match env::var(variable) {
+        Ok(value) if dotenv_variables.get(variable) == Some(&value) => format!(".env ({})", variable),
+        Ok(_) => format!("environment ({})", variable),
+        Err(_) => "default".to_string(),
+    }
+}
+
+/// The effective configuration for `--show-config`: the settings after merging the arguments, the .env file and the defaults.
+///
+/// Each row is the setting, the value and the source of the value. The hosts, ports and parallel can come from .env or the environment,
+/// and the hosts and ports from the servers discovered with `--discover-from`, the other settings are an argument or the default.
+/// Whether a setting is an argument is taken from the command line matches, so an argument with the default value is an argument too.
+pub fn effective_config(
+    options: &Opts,
+    matches: &ArgMatches,
+    hosts: &[&str],
+    ports: &[&str],
+    parallel: usize,
+    dotenv_filename: &str,
+    discovered: bool,
+) -> Vec<Vec<String>>
+{
+    // the iterator is deprecated in dotenv, but it is the way to read the .env file without setting the variables.
+    #[allow(deprecated)]
+    let dotenv_variables: HashMap<String, String> = dotenv::from_filename_iter(dotenv_filename)
+        .map(|variables| variables.filter_map(|variable| variable.ok()).collect())
+        .unwrap_or_default();
+    let argument_set = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+    let argument_or_default = |id: &str| if argument_set(id) { "argument" } else { "default" }.to_string();
+    let optional = |value: &Option<String>| value.clone().unwrap_or_default();
+    let parallel_source = setting_source(argument_set("parallel"), "YBSTATS_PARALLEL", &dotenv_variables);
+    let ports_source = setting_source(argument_set("ports"), "YBSTATS_PORTS", &dotenv_variables);
+    let (hosts_source, ports_source) = if options.local {
+        ("--local".to_string(), "--local".to_string())
+    } else if discovered {
+        ("--discover-from".to_string(), format!("{} and --discover-from", ports_source))
+    } else if options.sts.is_some() {
+        ("--sts".to_string(), ports_source)
+    } else {
+        (setting_source(argument_set("hosts") || argument_set("hosts_file"), "YBSTATS_HOSTS", &dotenv_variables), ports_source)
+    };
+    vec![
+        vec!["dotenv file".to_string(), dotenv_filename.to_string(), if std::path::Path::new(dotenv_filename).exists() { "found" } else { "not found" }.to_string()],
+        vec!["hosts".to_string(), hosts.join(","), hosts_source],
+        vec!["ports".to_string(), ports.join(","), ports_source],
+        vec!["parallel".to_string(), parallel.to_string(), parallel_source],
+        vec!["port roles".to_string(), options.port_role.join(","), argument_or_default("port_role")],
+        vec!["hostname match".to_string(), optional(&options.hostname_match), argument_or_default("hostname_match")],
+        vec!["stat name match".to_string(), optional(&options.stat_name_match), argument_or_default("stat_name_match")],
+        vec!["table name match".to_string(), optional(&options.table_name_match), argument_or_default("table_name_match")],
+        vec!["placement match".to_string(), optional(&options.placement_match), argument_or_default("placement_match")],
+        vec!["details enable".to_string(), options.details_enable.to_string(), argument_or_default("details_enable")],
+        vec!["gauges enable".to_string(), options.gauges_enable.to_string(), argument_or_default("gauges_enable")],
+        vec!["format".to_string(), options.format.clone().unwrap_or_else(|| "json".to_string()), argument_or_default("format")],
+        vec!["timezone".to_string(), options.timezone.clone().unwrap_or_else(|| "local".to_string()), argument_or_default("timezone")],
+        vec!["max rows".to_string(), options.max_rows.to_string(), argument_or_default("max_rows")],
+    ]
+}
+
+/// Initialize the logger for the yb_stats diagnostic logging, which is set with RUST_LOG.
+///
+/// If log_json is set, every log record is written as a JSON line with the fields timestamp, level, target and message,
+/// so the logging can be ingested by a log pipeline.
+pub fn init_logger(
+    log_json: bool,
+)
+{
+    let mut builder = env_logger::Builder::from_default_env();
+    if log_json
+    {
+        builder.format(|buf, record| {
+            writeln!(buf, "{}", serde_json::json!({
+                "timestamp": Local::now().to_rfc3339(),
+                "level": record.level().to_string(),
+                "target": record.target(),
+                "message": record.args().to_string(),
+            }))
+        });
+    }
+    builder.init();
 }
 
 /// Simple helper routine to create a regex from an `&Option<String>`.
@@ -204,21 +1304,42 @@ pub fn set_regex(
     }
 }
 
+/// The name of the file with the options of the previous run: '.env', or '.env.<profile>' with `--profile`.
+///
+/// The profile name is used as part of a filename in the current directory,
+/// and therefore can only contain letters, digits, '-' and '_'.
+pub fn dotenv_filename(
+    profile: &Option<String>,
+) -> Result<String>
+{
+    match profile {
+        None => Ok(".env".to_string()),
+        Some(profile) => {
+            if profile.is_empty() || !profile.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            {
+                bail!("Invalid profile name: '{}', the name can only contain letters, digits, '-' and '_'", profile);
+            }
+            Ok(format!(".env.{}", profile))
+        },
+    }
+}
+
 /// If writing the '.env' file is allowed via write_dotenv,
-/// take the changed_options hashmap, and write it.
+/// take the changed_options hashmap, and write it to the file set by [dotenv_filename].
 pub fn dotenv_writer(
     write_dotenv: bool,
     changed_options: HashMap<&str, String>,
+    dotenv_filename: &str,
 ) -> Result<()>
 {
     if !changed_options.is_empty() && write_dotenv {
-        info!("Writing .env file");
+        info!("Writing {} file", dotenv_filename);
         let mut file = fs::OpenOptions::new()
             .create(true)
             .write(true)
             .truncate(true)
-            .open(".env")
-            .with_context(|| "Error writing .env file in current directory")?;
+            .open(dotenv_filename)
+            .with_context(|| format!("Error writing {} file in current directory", dotenv_filename))?;
 
         for (key, value) in changed_options {
             file.write_all(format!("{}={}\n", key, value).as_bytes())?;
@@ -228,6 +1349,186 @@ pub fn dotenv_writer(
     Ok(())
 }
 
+/// The maximum width a column is padded to by [format_table].
+/// Values that are longer are printed in full, and push the rest of that line to the right.
+const TABLE_MAX_COLUMN_WIDTH: usize = 64;
+
+/// Format the headers and rows of a table, with the width of each column set to its widest value.
+///
+/// The last column is not padded, to prevent trailing spaces.
+pub fn format_table(
+    headers: &[&str],
+    rows: &[Vec<String>],
+) -> Vec<String>
+{
+    let mut widths: Vec<usize> = headers.iter().map(|header| header.len()).collect();
+    for row in rows
+    {
+        for (width, value) in widths.iter_mut().zip(row)
+        {
+            *width = (*width).max(value.chars().count());
+        }
+    }
+    widths.iter_mut().for_each(|width| *width = (*width).min(TABLE_MAX_COLUMN_WIDTH));
+
+    let format_line = |values: Vec<&str>| -> String {
+        let last = values.len().saturating_sub(1);
+        values
+            .iter()
+            .enumerate()
+            .map(|(nr, value)| if nr == last { value.to_string() } else { format!("{:width$}", value, width = widths[nr]) })
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+
+    let mut lines = vec![format_line(headers.to_vec())];
+    for row in rows
+    {
+        lines.push(format_line(row.iter().map(|value| value.as_str()).collect()));
+    }
+    lines
+}
+
+/// Print a table with the headers and rows, see [format_table].
+///
+/// If --max-rows is set, only that number of rows is printed, followed by the number of rows that are not shown, see [RowWriter].
+/// The rows are expected to be filtered and sorted already, so the most relevant rows are printed.
+pub fn print_table(
+    headers: &[&str],
+    rows: &[Vec<String>],
+    config: &Config,
+) -> Result<()>
+{
+    let mut lines = format_table(headers, rows).into_iter();
+    if let Some(header) = lines.next()
+    {
+        writeln!(config.output(), "{}", header)?;
+    }
+    let mut table_rows = RowWriter::new(config);
+    for line in lines
+    {
+        writeln!(table_rows, "{}", line)?;
+    }
+    table_rows.finish()
+}
+
+impl<'a> RowWriter<'a> {
+    pub fn new(
+        config: &'a Config,
+    ) -> Self
+    {
+        RowWriter { config, rows: 0 }
+    }
+    /// Write (part of) a row, which is called by write! and writeln!. A row is a line, so it is counted when the newline is written.
+    /// The text is written if --max-rows is not reached, otherwise it is counted only.
+    pub fn write_fmt(
+        &mut self,
+        text: std::fmt::Arguments,
+    ) -> std::io::Result<()>
+    {
+        let text = text.to_string();
+        if self.config.max_rows == 0 || self.rows < self.config.max_rows
+        {
+            self.config.output().write_all(text.as_bytes())?;
+        }
+        self.rows += text.matches('\n').count();
+        Ok(())
+    }
+    /// Write a header line, such as the host of the rows below it. The header is not written when --max-rows is reached,
+    /// so no header without rows is written.
+    pub fn header(
+        &mut self,
+        line: std::fmt::Arguments,
+    ) -> Result<()>
+    {
+        if self.config.max_rows == 0 || self.rows < self.config.max_rows
+        {
+            writeln!(self.config.output(), "{}", line)?;
+        }
+        Ok(())
+    }
+    /// The number of rows that are written, including the rows that are not shown because of --max-rows.
+    pub fn rows(
+        &self,
+    ) -> usize
+    {
+        self.rows
+    }
+    /// Write the number of rows that are not shown because of --max-rows, if any.
+    pub fn finish(
+        self,
+    ) -> Result<()>
+    {
+        if self.config.max_rows > 0 && self.rows > self.config.max_rows
+        {
+            writeln!(self.config.output(), "... {} more rows (use --max-rows 0 for all)", self.rows - self.config.max_rows)?;
+        }
+        Ok(())
+    }
+}
+
+/// Create the unified diff of a data type for a host between the begin and end snapshot, for --text-diff.
+/// The diff is empty if the text did not change.
+fn text_diff(
+    data_type: &str,
+    hostname_port: &str,
+    begin_snapshot: &str,
+    end_snapshot: &str,
+    first_text: &str,
+    second_text: &str,
+) -> String
+{
+    if first_text == second_text
+    {
+        return String::new();
+    }
+    TextDiff::from_lines(first_text, second_text)
+        .unified_diff()
+        .context_radius(3)
+        .header(&format!("{}/{}/{}", begin_snapshot, data_type, hostname_port), &format!("{}/{}/{}", end_snapshot, data_type, hostname_port))
+        .to_string()
+}
+
+/// Print the text of a data type for every host as a unified diff between the begin and end snapshot, for --text-diff.
+///
+/// The text per host is created by the data type, such as a line per gflag.
+/// A host that is found in one of the snapshots only is shown as completely added or removed.
+/// Returns the number of changes: the added and removed lines.
+pub fn print_text_diff(
+    data_type: &str,
+    begin_snapshot: &str,
+    end_snapshot: &str,
+    first: &BTreeMap<String, String>,
+    second: &BTreeMap<String, String>,
+    hostname_filter: &Regex,
+    config: &Config,
+) -> Result<usize>
+{
+    let mut changes = 0;
+    let hostnames: BTreeSet<&String> = first.keys().chain(second.keys()).collect();
+    for hostname_port in hostnames.into_iter().filter(|hostname_port| hostname_filter.is_match(hostname_port))
+    {
+        let diff = text_diff(
+            data_type,
+            hostname_port,
+            begin_snapshot,
+            end_snapshot,
+            first.get(hostname_port).map(String::as_str).unwrap_or_default(),
+            second.get(hostname_port).map(String::as_str).unwrap_or_default(),
+        );
+        // the first two lines are the --- and +++ headers, removed gflags lines start with --- too.
+        for (line_number, line) in diff.lines().enumerate()
+        {
+            if line_number < 2 { writeln!(config.output(), "{}", line.bold())? }
+            else if line.starts_with("@@") { writeln!(config.output(), "{}", line.cyan())? }
+            else if line.starts_with('+') { changes += 1; writeln!(config.output(), "{}", line.green())? }
+            else if line.starts_with('-') { changes += 1; writeln!(config.output(), "{}", line.red())? }
+            else { writeln!(config.output(), "{}", line)? }
+        }
+    }
+    Ok(changes)
+}
+
 /// Read environment variable for integration tests
 #[cfg(test)]
 pub fn get_hostname_master() -> String {
@@ -323,4 +1624,355 @@ pub fn get_port_node_exporter() -> String {
         Ok(value) => value,
         Err(_e) => { panic!("The environment variable PORT_NODE_EXPORTER should be set") },
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unit_statefulset_hosts() {
+        assert_eq!(set_statefulset_hosts("yb-tserver", &None, 2, "yb"), vec!["yb-tserver-0.yb-tserver.yb.svc.cluster.local", "yb-tserver-1.yb-tserver.yb.svc.cluster.local"]);
+        assert_eq!(set_statefulset_hosts("yb-master", &Some("yb-masters".to_string()), 1, "default"), vec!["yb-master-0.yb-masters.default.svc.cluster.local"]);
+        assert!(set_statefulset_hosts("yb-master", &None, 0, "default").is_empty());
+    }
+
+    #[test]
+    fn unit_effective_config() {
+        use clap::{CommandFactory, FromArgMatches};
+        let matches = Opts::command().get_matches_from(["yb_stats", "--hosts", "yb-1,yb-2", "--hostname-match", "yb-1", "--max-rows", "0"]);
+        let options = Opts::from_arg_matches(&matches).unwrap();
+        let config = effective_config(&options, &matches, &["yb-1", "yb-2"], &["7000"], 1, "/nonexistent/.env", false);
+        let setting = |name: &str| config.iter().find(|row| row[0] == name).unwrap().clone();
+        assert_eq!(setting("dotenv file"), vec!["dotenv file", "/nonexistent/.env", "not found"]);
+        assert_eq!(setting("hosts"), vec!["hosts", "yb-1,yb-2", "argument"]);
+        assert_eq!(setting("hostname match"), vec!["hostname match", "yb-1", "argument"]);
+        assert_eq!(setting("stat name match"), vec!["stat name match", "", "default"]);
+        assert_eq!(setting("timezone")[1], "local");
+        // an argument with the default value is an argument.
+        assert_eq!(setting("max rows"), vec!["max rows", "0", "argument"]);
+        // a variable is from .env if the .env file has the same value, otherwise from the environment.
+        let path = env::var("PATH").unwrap();
+        assert_eq!(setting_source(false, "YBSTATS_UNIT_EFFECTIVE_CONFIG_NOT_SET", &HashMap::new()), "default");
+        assert_eq!(setting_source(false, "PATH", &HashMap::new()), "environment (PATH)");
+        assert_eq!(setting_source(false, "PATH", &HashMap::from([("PATH".to_string(), path)])), ".env (PATH)");
+        assert_eq!(setting_source(false, "PATH", &HashMap::from([("PATH".to_string(), "/other".to_string())])), "environment (PATH)");
+        assert_eq!(setting_source(true, "PATH", &HashMap::new()), "argument");
+    }
+
+    #[test]
+    fn unit_parse_hosts_lines() {
+        let hosts_text = "# yugabyte nodes\nyb-1.local\n\n  yb-2.local  \nyb-3.local # rack 3\n#yb-4.local\n";
+        let result = parse_hosts_lines(hosts_text);
+        assert_eq!(result, "yb-1.local,yb-2.local,yb-3.local");
+    }
+
+    #[test]
+    fn unit_set_hosts_ports_remove_duplicates() {
+        let mut changed_options = HashMap::new();
+        let hosts = set_hosts(&Some("yb-1.local,yb-2.local,yb-1.local,yb-3.local,yb-2.local".to_string()), &None, &mut changed_options, true).unwrap();
+        assert_eq!(hosts, vec!["yb-1.local", "yb-2.local", "yb-3.local"]);
+        let ports = set_ports(&Some("7000,9000,7000".to_string()), &mut changed_options, true);
+        assert_eq!(ports, vec!["7000", "9000"]);
+        // the spaces around the entries and a trailing comma don't make a different host or port.
+        let hosts = set_hosts(&Some("yb-1.local, yb-2.local,yb-1.local ,".to_string()), &None, &mut changed_options, true).unwrap();
+        assert_eq!(hosts, vec!["yb-1.local", "yb-2.local"]);
+        let ports = set_ports(&Some("7000, 9000, 7000".to_string()), &mut changed_options, true);
+        assert_eq!(ports, vec!["7000", "9000"]);
+
+        // an inventory file with the same host twice, such as after merging inventories.
+        let hosts_file = env::temp_dir().join(format!("yb_stats_unit_hosts_{}", std::process::id()));
+        fs::write(&hosts_file, "# inventory a\nyb-2.local\nyb-1.local\n# inventory b\nyb-1.local # rack 1\nyb-3.local\n").unwrap();
+        let hosts = set_hosts(&None, &Some(hosts_file.to_string_lossy().to_string()), &mut changed_options, true).unwrap();
+        fs::remove_file(&hosts_file).unwrap();
+        assert_eq!(hosts, vec!["yb-2.local", "yb-1.local", "yb-3.local"]);
+    }
+
+    #[test]
+    fn unit_pager_command() {
+        assert_eq!(pager_command(None), Some(vec!["less".to_string()]));
+        assert_eq!(pager_command(Some("less -S".to_string())), Some(vec!["less".to_string(), "-S".to_string()]));
+        assert_eq!(pager_command(Some(" ".to_string())), None);
+        assert_eq!(pager_command(Some("cat".to_string())), None);
+    }
+
+    #[test]
+    fn unit_is_broken_pipe() {
+        let broken_pipe = anyhow::Error::from(std::io::Error::from(ErrorKind::BrokenPipe)).context("Writing the output");
+        assert!(is_broken_pipe(&broken_pipe));
+        assert!(!is_broken_pipe(&anyhow::Error::from(std::io::Error::from(ErrorKind::NotFound))));
+        assert!(!is_broken_pipe(&anyhow::anyhow!("Snapshot 1 not found")));
+    }
+
+    #[test]
+    fn unit_parse_duration() {
+        assert_eq!(parse_duration("500ms").unwrap(), Duration::from_millis(500));
+        assert_eq!(parse_duration("30s").unwrap(), Duration::from_secs(30));
+        assert_eq!(parse_duration("1.5m").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_duration("2h").unwrap(), Duration::from_secs(7200));
+        assert_eq!(parse_duration("10").unwrap(), Duration::from_secs(10));
+        assert!(parse_duration("10d").is_err());
+        assert!(parse_duration("").is_err());
+    }
+    #[test]
+    fn unit_parse_weights() {
+        let names = ["cpu", "memory"];
+        assert_eq!(parse_weights(&[], &names, &[1.0, 2.0], "dimension").unwrap(), vec![1.0, 2.0]);
+        assert_eq!(parse_weights(&["memory=0.5", " cpu = 3"], &names, &[1.0, 2.0], "dimension").unwrap(), vec![3.0, 0.5]);
+        assert!(parse_weights(&["memory"], &names, &[1.0, 2.0], "dimension").is_err());
+        assert!(parse_weights(&["disk=1"], &names, &[1.0, 2.0], "dimension").unwrap_err().to_string().contains("valid are: cpu, memory"));
+        assert!(parse_weights(&["cpu=-1"], &names, &[1.0, 2.0], "dimension").is_err());
+        assert!(parse_weights(&["cpu=0", "memory=0"], &names, &[1.0, 2.0], "dimension").is_err());
+    }
+    #[test]
+    fn unit_scan_host_port() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port().to_string();
+        assert!(scan_host_port("127.0.0.1", &port));
+        drop(listener);
+        assert!(!scan_host_port("127.0.0.1", &port));
+        assert!(!scan_host_port("127.0.0.1", "no_port"));
+    }
+    #[test]
+    fn unit_http_get_redirects() {
+        // a server that redirects /redirect to /target, like a load balancer can do.
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port().to_string();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut request = [0_u8; 1024];
+                let length = stream.read(&mut request).unwrap();
+                let response = if String::from_utf8_lossy(&request[..length]).starts_with("GET /redirect ") {
+                    "HTTP/1.1 302 Found\r\nLocation: /target\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                } else {
+                    "HTTP/1.1 200 OK\r\nContent-Length: 6\r\nConnection: close\r\n\r\ntarget"
+                };
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+        assert_eq!(http_get_bytes("127.0.0.1", &port, "redirect", &Config::default()), b"target");
+        // with --no-follow-redirects, the redirect is the response, which has no data, but is not a failed request.
+        let config = Config { follow_redirects: false, ..Default::default() };
+        assert!(http_get_bytes("127.0.0.1", &port, "redirect", &config).is_empty());
+        assert_eq!(config.failed_requests.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn unit_format_table() {
+        let rows = vec![
+            vec!["yb-1.us-east-1.compute.internal:9000".to_string(), "2.17.0.0".to_string(), "24".to_string()],
+            vec!["yb-2:9000".to_string(), "2.17.0.0".to_string(), "24".to_string()],
+        ];
+        let result = format_table(&["hostname_port", "version_number", "build_nr"], &rows);
+        assert_eq!(result[0], "hostname_port                        version_number build_nr");
+        assert_eq!(result[1], "yb-1.us-east-1.compute.internal:9000 2.17.0.0       24");
+        assert_eq!(result[2], "yb-2:9000                            2.17.0.0       24");
+    }
+
+    /// An output that can be read after it is written to via the config.
+    #[derive(Clone, Default)]
+    struct SharedOutput(std::sync::Arc<Mutex<Vec<u8>>>);
+    impl Write for SharedOutput {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+    impl SharedOutput {
+        fn take(&self) -> String {
+            String::from_utf8(std::mem::take(&mut *self.0.lock().unwrap())).unwrap()
+        }
+    }
+
+    #[test]
+    fn unit_max_rows() {
+        let output = SharedOutput::default();
+        let config = &Config { max_rows: 2, ..Default::default() };
+        *config.output() = Box::new(output.clone());
+
+        let mut rows = RowWriter::new(config);
+        rows.header(format_args!("Host: yb-1:9000")).unwrap();
+        write!(rows, "yb-1:9000 ").unwrap();
+        writeln!(rows, "row 1").unwrap();
+        writeln!(rows, "row 2").unwrap();
+        // a header after --max-rows is reached is not written, the rows are counted.
+        rows.header(format_args!("Host: yb-2:9000")).unwrap();
+        writeln!(rows, "row 3").unwrap();
+        writeln!(rows, "row 4").unwrap();
+        assert_eq!(rows.rows(), 4);
+        rows.finish().unwrap();
+        assert_eq!(output.take(), "Host: yb-1:9000\nyb-1:9000 row 1\nrow 2\n... 2 more rows (use --max-rows 0 for all)\n");
+
+        let table = vec![vec!["yb-1:9000".to_string()], vec!["yb-2:9000".to_string()], vec!["yb-3:9000".to_string()]];
+        print_table(&["hostname_port"], &table, config).unwrap();
+        assert_eq!(output.take(), "hostname_port\nyb-1:9000\nyb-2:9000\n... 1 more rows (use --max-rows 0 for all)\n");
+        print_table(&["hostname_port"], &table[..2], config).unwrap();
+        assert_eq!(output.take(), "hostname_port\nyb-1:9000\nyb-2:9000\n");
+
+        let config = &Config::default();
+        *config.output() = Box::new(output.clone());
+        print_table(&["hostname_port"], &table, config).unwrap();
+        assert_eq!(output.take(), "hostname_port\nyb-1:9000\nyb-2:9000\nyb-3:9000\n");
+    }
+
+    #[test]
+    fn unit_count_output_lines() {
+        let config = &Config::default();
+        let lines = count_output_lines(config);
+        write!(config.output(), "yb-1:9000 ").unwrap();
+        writeln!(config.output(), "row 1").unwrap();
+        print_table(&["hostname_port"], &[vec!["yb-1:9000".to_string()], vec!["yb-2:9000".to_string()]], config).unwrap();
+        assert_eq!(lines.load(Ordering::Relaxed), 4);
+    }
+
+    #[test]
+    fn unit_parse_timezone() {
+        assert_eq!(parse_timezone("UTC").unwrap(), DisplayTimezone::Utc);
+        assert_eq!(parse_timezone("local").unwrap(), DisplayTimezone::Local);
+        assert_eq!(parse_timezone("Europe/Amsterdam").unwrap(), DisplayTimezone::Tz(chrono_tz::Europe::Amsterdam));
+        assert!(parse_timezone("Mars/Olympus_Mons").is_err());
+    }
+
+    #[test]
+    fn unit_parse_endpoint_overrides() {
+        let result = parse_endpoint_overrides(&["versions=/api/v2/version".to_string()]).unwrap();
+        assert_eq!(result.get("versions").unwrap(), "api/v2/version");
+        assert!(parse_endpoint_overrides(&["version=api/v2/version".to_string()]).is_err());
+        assert!(parse_endpoint_overrides(&["versions".to_string()]).is_err());
+        assert!(parse_endpoint_overrides(&["versions=/".to_string()]).is_err());
+    }
+    #[test]
+    fn unit_placement_selects() {
+        let placement_hosts: BTreeSet<String> = ["yb-1".to_string()].into_iter().collect();
+        let roles = parse_port_roles(&[]).unwrap();
+        assert!(placement_selects(&placement_hosts, &roles, "yb-1", "9000"));
+        assert!(!placement_selects(&placement_hosts, &roles, "yb-2", "9000"));
+        assert!(!placement_selects(&placement_hosts, &roles, "yb-2", "9300"));
+        // the masters are read on all hosts.
+        assert!(placement_selects(&placement_hosts, &roles, "yb-2", "7000"));
+        assert!(!placement_selects(&placement_hosts, &HashMap::new(), "yb-2", "7000"));
+    }
+    #[test]
+    fn unit_dotenv_filename() {
+        assert_eq!(dotenv_filename(&None).unwrap(), ".env");
+        assert_eq!(dotenv_filename(&Some("prod".to_string())).unwrap(), ".env.prod");
+        assert_eq!(dotenv_filename(&Some("staging_2-a".to_string())).unwrap(), ".env.staging_2-a");
+        assert!(dotenv_filename(&Some("../prod".to_string())).is_err());
+        assert!(dotenv_filename(&Some("".to_string())).is_err());
+    }
+    #[test]
+    fn unit_auto_parallel() {
+        assert_eq!(auto_parallel(1, 1), 1);
+        assert_eq!(auto_parallel(3, 4), 6);
+        assert_eq!(auto_parallel(6, 1), 6);
+        assert_eq!(auto_parallel(20, 4), 16);
+        assert_eq!(auto_parallel(0, 4), 1);
+    }
+    #[test]
+    fn unit_missing_port_roles() {
+        let roles = parse_port_roles(&["17000=master".to_string(), "19000=all".to_string()]).unwrap();
+        assert!(missing_port_roles_in(&roles, &["7000", "9000", "12000", "13000"]).is_empty());
+        assert_eq!(missing_port_roles_in(&roles, &["7000"]).iter().map(|(role, _, _)| *role).collect::<Vec<_>>(), vec!["tserver", "ycql", "ysql"]);
+        assert_eq!(missing_port_roles_in(&roles, &["17000", "9000", "13000"]).iter().map(|(role, _, _)| *role).collect::<Vec<_>>(), vec!["ycql"]);
+        assert!(missing_port_roles_in(&roles, &["7000", "19000"]).is_empty());
+        assert!(missing_port_roles_in(&roles, &["7000", "18000"]).is_empty());
+    }
+
+    #[test]
+    fn unit_port_roles() {
+        let roles = parse_port_roles(&["17000=master".to_string(), "9000=all".to_string()]).unwrap();
+        assert!(port_serves_endpoint(&roles, "7000", "masters"));
+        assert!(!port_serves_endpoint(&roles, "13000", "masters"));
+        assert!(port_serves_endpoint(&roles, "13000", "statements"));
+        assert!(port_serves_endpoint(&roles, "17000", "cluster_config"));
+        assert!(!port_serves_endpoint(&roles, "17000", "statements"));
+        assert!(port_serves_endpoint(&roles, "9000", "masters"));
+        assert!(port_serves_endpoint(&roles, "19000", "masters"));
+        assert!(parse_port_roles(&["17000=leader".to_string()]).is_err());
+        assert!(parse_port_roles(&["master=7000".to_string()]).is_err());
+        assert!(parse_port_roles(&["17000".to_string()]).is_err());
+    }
+    #[test]
+    fn unit_anonymize_text() {
+        let mapping = BTreeMap::from([("yb-1".to_string(), "node-1".to_string()), ("192.168.66.80".to_string(), "node-2".to_string())]);
+        let result = replace_hostnames(r#"{"hostname_port":"yb-1:9000","addr":"192.168.66.80:7100","other":"yb-10:9000"}"#.to_string(), &mapping);
+        assert_eq!(result, r#"{"hostname_port":"node-1:9000","addr":"node-2:7100","other":"yb-10:9000"}"#);
+        assert_eq!(replace_hostnames("no hosts".to_string(), &mapping), "no hosts");
+        assert_eq!(hostnames_in_text(r#"[{"hostname_port":"yb-3.local:12000"},{"server":"yb-4"},{"server": "10.0.0.5:9100"},{"host":"yb-5"}]"#), vec!["yb-3.local", "yb-4", "10.0.0.5"]);
+    }
+    #[test]
+    fn unit_anonymize_response() {
+        let config = &Config { anonymize: true, ..Default::default() };
+        set_anonymize_hosts(&["yb-1"], config);
+        assert_eq!(hostname_port("yb-1", "9000", config), "node-1:9000");
+        // a host that is only in a response, such as a server found via the masters, is learned and replaced.
+        let result = response_text(br#"[{"hostname_port":"yb-1:9000"},{"server":"yb-2:9000","peer":"yb-2"}]"#.to_vec(), config);
+        assert_eq!(result, r#"[{"hostname_port":"node-1:9000"},{"server":"node-2:9000","peer":"node-2"}]"#);
+        assert_eq!(anonymize_hostname("yb-2", config), "node-2");
+    }
+    #[test]
+    fn unit_text_diff() {
+        let first = "--max_log_size=1800\n--rpc_bind_addresses=yb-1:7100\n";
+        let second = "--max_log_size=100\n--rpc_bind_addresses=yb-1:7100\n";
+        assert_eq!(text_diff("gflags", "yb-1:7000", "1", "2", first, second),
+                   "--- 1/gflags/yb-1:7000\n+++ 2/gflags/yb-1:7000\n@@ -1,2 +1,2 @@\n---max_log_size=1800\n+--max_log_size=100\n --rpc_bind_addresses=yb-1:7100\n");
+        assert_eq!(text_diff("gflags", "yb-1:7000", "1", "2", first, first), "");
+    }
+    #[test]
+    fn unit_print_text_diff_changes() {
+        let output = SharedOutput::default();
+        let config = &Config::default();
+        *config.output() = Box::new(output.clone());
+        let first = BTreeMap::from([
+            ("yb-1:7000".to_string(), "--max_log_size=1800\n--rpc_bind_addresses=yb-1:7100\n".to_string()),
+            ("yb-2:7000".to_string(), "--max_log_size=1800\n".to_string()),
+        ]);
+        let second = BTreeMap::from([
+            ("yb-1:7000".to_string(), "--max_log_size=100\n--rpc_bind_addresses=yb-1:7100\n".to_string()),
+            ("yb-3:7000".to_string(), "--max_log_size=1800\n".to_string()),
+        ]);
+        // a changed line is removed and added, a host in one snapshot only is removed or added completely.
+        assert_eq!(print_text_diff("gflags", "1", "2", &first, &second, &set_regex(&None), config).unwrap(), 4);
+        assert_eq!(print_text_diff("gflags", "1", "2", &first, &first, &set_regex(&None), config).unwrap(), 0);
+        output.take();
+        // the changes of the diffs are added up for the summary, none are counted before a diff counts its changes.
+        assert_eq!(*config.diff_changes.lock().unwrap(), None);
+        add_diff_changes(0, config);
+        assert_eq!(*config.diff_changes.lock().unwrap(), Some(0));
+        add_diff_changes(4, config);
+        add_diff_changes(2, config);
+        assert_eq!(*config.diff_changes.lock().unwrap(), Some(6));
+    }
+
+    #[test]
+    fn unit_raw_response_filename() {
+        let config = &Config::default();
+        assert_eq!(raw_response_filename("yb-1.local", "9000", "metrics"), "yb-1.local_9000_metrics");
+        assert_eq!(raw_response_filename("192.168.66.80", "9000", "tablet-consensus-status?id=8e0d3b1c"), "192.168.66.80_9000_tablet-consensus-status_id_8e0d3b1c");
+        // a path separator is replaced, so the file is always in the raw directory.
+        assert_eq!(raw_response_filename("yb-1.local", "7000", "../api/v1/masters"), "yb-1.local_7000_.._api_v1_masters");
+        assert_eq!(response_text(Vec::new(), config), "");
+        assert_eq!(response_text(b"heap profile".to_vec(), config), "heap profile");
+    }
+
+    #[test]
+    fn unit_response_size_rows() {
+        let response_sizes = vec![
+            ("yb-1:7000".to_string(), "metrics".to_string(), 2048_usize),
+            ("yb-1:9000".to_string(), "metrics".to_string(), 4096_usize),
+            ("yb-1:9000".to_string(), "threads".to_string(), 8192_usize),
+        ];
+        let (data_type_rows, largest_rows) = response_size_rows(&response_sizes);
+        assert_eq!(data_type_rows, vec![
+            vec!["threads", "1", "8.00K", "8.00K"],
+            vec!["metrics", "2", "6.00K", "4.00K"],
+        ]);
+        assert_eq!(largest_rows, vec![
+            vec!["yb-1:9000", "threads", "8.00K"],
+            vec!["yb-1:9000", "metrics", "4.00K"],
+            vec!["yb-1:7000", "metrics", "2.00K"],
+        ]);
+    }
+}
@@ -1,4 +1,7 @@
 //! Module for general utility functions.
+mod structs;
 mod functions;
 
+pub use structs::*;
+
 pub use functions::*;
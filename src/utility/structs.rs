@@ -0,0 +1,119 @@
+//! The structs
+//!
+use std::{collections::{BTreeMap, BTreeSet, HashMap}, io::{stdout, Write}, path::PathBuf, process::Child, sync::{Arc, Mutex, atomic::{AtomicBool, AtomicUsize}}};
+use chrono_tz::Tz;
+use regex::Regex;
+
+/// The timezone timestamps are displayed in.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum DisplayTimezone {
+    #[default]
+    Local,
+    Utc,
+    Tz(Tz),
+}
+
+/// The settings of a run, which are created once from the options via [Config::from_opts], and passed down to the functions that use these.
+///
+/// Next to the settings, this holds the state that is collected during the run, such as the hostname mapping of `--anonymize`,
+/// the failed requests and the output. This state uses interior mutability, because the requests are done in parallel.
+pub struct Config {
+    /// Whether http redirects are followed when getting data from the servers.
+    pub follow_redirects: bool,
+    /// The http endpoint paths that override the default paths of the data types, see `--endpoint-override`.
+    pub endpoint_overrides: HashMap<String, String>,
+    /// The roles of the ports, which determine the data types that are read from a port, see `--port-role`.
+    pub port_roles: HashMap<String, String>,
+    /// The hosts of the tablet servers in the placement of `--placement-match`, None means all hosts.
+    pub placement_hosts: Option<BTreeSet<String>>,
+    /// The maximum number of rows that are printed per table, 0 means all rows.
+    pub max_rows: usize,
+    /// Whether a snapshot or diff only prints a summary line, see `--summary-only`.
+    pub summary_only: bool,
+    /// Whether values are printed verbatim: full precision and no padding, instead of rounded to a fixed width.
+    pub raw_output: bool,
+    /// The timezone that timestamps are displayed in.
+    pub timezone: DisplayTimezone,
+    /// Whether hostnames are replaced by pseudonyms (node-1, node-2, ...) in the data that is read.
+    pub anonymize: bool,
+    /// The mapping of hostnames to pseudonyms, which is kept for the duration of a run.
+    pub anonymize_mapping: Mutex<BTreeMap<String, String>>,
+    /// The label matchers for filtering the node_exporter series during collection, see `--ne-label-match`.
+    pub label_matchers: Vec<(String, Regex)>,
+    /// Whether the metrics, node_exporter and statements data of a snapshot is written as parquet instead of json, see `--format`.
+    pub parquet_output: bool,
+    /// The snapshots that are merged into the baseline that is used as begin of the metrics diff, ordered by number.
+    pub baseline_snapshots: Vec<i32>,
+    /// The response sizes as (hostname_port, data type, bytes) of the http requests, only recorded with `--sizes`.
+    pub response_sizes: Option<Mutex<Vec<(String, String, usize)>>>,
+    /// Whether the user interrupted yb_stats, after which no new http requests are done.
+    /// This is shared with the interrupt handler of a snapshot, see snapshot::perform_snapshot.
+    pub interrupted: Arc<AtomicBool>,
+    /// The number of http requests that are skipped, because the data type is not served by the role of the port.
+    pub skipped_requests: AtomicUsize,
+    /// The number of http requests that failed, because the server could not be reached or did not respond.
+    pub failed_requests: AtomicUsize,
+    /// The requests (`host:port/url`) that failed since they were last taken via take_failed_request_urls.
+    pub failed_request_urls: Mutex<Vec<String>>,
+    /// The directory to write the raw responses to with `--keep-raw`, which is set during a snapshot.
+    pub raw_directory: Mutex<Option<PathBuf>>,
+    /// Whether the format version of the snapshot index is checked in this run, so an older index is warned about once.
+    pub snapshot_index_format_checked: AtomicBool,
+    /// The format versions of the snapshots that are read in this run, so every snapshot is checked (and warned about) once.
+    pub snapshot_format_versions: Mutex<BTreeMap<String, u32>>,
+    /// The number of rows saved per data type, per snapshot number, for the summary that is printed after a snapshot.
+    pub snapshot_row_counts: Mutex<BTreeMap<i32, BTreeMap<String, usize>>>,
+    /// The number of changes of the diffs of stored snapshots, the added, removed and changed rows, for the summary line of `--summary-only`,
+    /// see utility::add_diff_changes. None if no diff counted its changes.
+    pub diff_changes: Mutex<Option<usize>>,
+    /// The output of the print and diff commands: the stdin of the pager when paging, otherwise stdout, see utility::start_pager.
+    pub output: Mutex<Box<dyn Write + Send>>,
+    /// The pager the output is written to, which is waited for when the output is complete, see utility::stop_pager.
+    pub pager: Mutex<Option<Child>>,
+}
+
+/// Writes the rows of a table to the output up to `--max-rows` rows, and counts the rows that are not shown,
+/// which are reported by utility::RowWriter::finish. A row is a line that is written with write! and writeln!, a header, such as the host of the rows below it,
+/// is written with utility::RowWriter::header.
+pub struct RowWriter<'a> {
+    pub(super) config: &'a Config,
+    pub(super) rows: usize,
+}
+
+/// Counts the lines that are written to it instead of writing these, which is the output of a diff with `--summary-only`,
+/// see utility::count_output_lines. The lines are the summary of a diff that does not count its changes.
+pub struct LineCounter {
+    pub lines: Arc<AtomicUsize>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            follow_redirects: true,
+            endpoint_overrides: HashMap::new(),
+            port_roles: HashMap::new(),
+            placement_hosts: None,
+            max_rows: 0,
+            summary_only: false,
+            raw_output: false,
+            timezone: DisplayTimezone::Local,
+            anonymize: false,
+            anonymize_mapping: Mutex::new(BTreeMap::new()),
+            label_matchers: Vec::new(),
+            parquet_output: false,
+            baseline_snapshots: Vec::new(),
+            response_sizes: None,
+            interrupted: Arc::new(AtomicBool::new(false)),
+            skipped_requests: AtomicUsize::new(0),
+            failed_requests: AtomicUsize::new(0),
+            failed_request_urls: Mutex::new(Vec::new()),
+            raw_directory: Mutex::new(None),
+            snapshot_index_format_checked: AtomicBool::new(false),
+            snapshot_format_versions: Mutex::new(BTreeMap::new()),
+            snapshot_row_counts: Mutex::new(BTreeMap::new()),
+            diff_changes: Mutex::new(None),
+            output: Mutex::new(Box::new(stdout())),
+            pager: Mutex::new(None),
+        }
+    }
+}
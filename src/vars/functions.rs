@@ -2,29 +2,46 @@
 //!
 use chrono::Local;
 use regex::Regex;
-use std::{sync::mpsc::channel, time::Instant};
+use std::{sync::mpsc::channel, time::Instant, collections::BTreeMap};
 use log::*;
 use colored::*;
 use anyhow::Result;
 use crate::utility;
+use crate::utility::Config;
 use crate::snapshot;
 use crate::vars::{AllVars, Vars, VarsDiff, VarsDiffFields};
 use crate::Opts;
 
 impl AllVars {
     pub fn new() -> Self { Default::default() }
+    /// The vars of each host as text for --text-diff, a line per var: `name=value (type)`.
+    pub fn text_per_host(&self) -> BTreeMap<String, String>
+    {
+        self.vars
+            .iter()
+            .map(|vars| {
+                let mut lines: Vec<String> = vars.flags
+                    .iter()
+                    .map(|var| format!("{}={} ({})\n", var.name, var.value, var.vars_type))
+                    .collect();
+                lines.sort();
+                (vars.hostname_port.clone().expect("hostname:port should be set"), lines.concat())
+            })
+            .collect()
+    }
     pub async fn perform_snapshot(
         hosts: &Vec<&str>,
         ports: &Vec<&str>,
         snapshot_number: i32,
         parallel: usize,
+        config: &Config,
     ) -> Result<()>
     {
         info!("begin snapshot");
         let timer = Instant::now();
 
-        let allvars = AllVars::read_vars(hosts, ports, parallel).await;
-        snapshot::save_snapshot_json(snapshot_number, "vars", allvars.vars)?;
+        let allvars = AllVars::read_vars(hosts, ports, parallel, config).await;
+        snapshot::save_snapshot_json(snapshot_number, "vars", allvars.vars, config)?;
 
         info!("end snapshot: {:?}", timer.elapsed());
         Ok(())
@@ -33,6 +50,7 @@ impl AllVars {
         hosts: &Vec<&str>,
         ports: &Vec<&str>,
         parallel: usize,
+        config: &Config,
     ) -> AllVars
     {
         info!("begin parallel http read");
@@ -46,9 +64,9 @@ impl AllVars {
                     let tx = tx.clone();
                     s.spawn(move |_| {
                         let detail_snapshot_time = Local::now();
-                        let mut vars = AllVars::read_http(host, port);
+                        let mut vars = AllVars::read_http(host, port, config);
                         vars.timestamp = Some(detail_snapshot_time);
-                        vars.hostname_port = Some(format!("{}:{}", host, port));
+                        vars.hostname_port = Some(utility::hostname_port(host, port, config));
                         tx.send(vars).expect("error sending data via tx");
                     });
                 }
@@ -69,9 +87,10 @@ impl AllVars {
     pub fn read_http(
         host: &str,
         port: &str,
+        config: &Config,
     ) -> Vars
     {
-        let data_from_http = utility::http_get(host, port, "api/v1/varz");
+        let data_from_http = utility::http_get_endpoint(host, port, "vars", config);
         AllVars::parse_vars(data_from_http, host, port)
     }
     fn parse_vars(
@@ -91,8 +110,10 @@ impl AllVars {
         details_enable: &bool,
         hostname_filter: &Regex,
         stat_name_filter: &Regex,
-    )
+        config: &Config,
+    ) -> Result<()>
     {
+        let mut rows = utility::RowWriter::new(config);
         for host_entry in self.vars.iter()
         {
             if hostname_filter.is_match(&host_entry.hostname_port.clone().expect("hostname:port should be set"))
@@ -105,16 +126,17 @@ impl AllVars {
                         {
                             continue;
                         };
-                        println!("{:20} {:50} {:40} {}",
+                        writeln!(rows, "{:20} {:50} {:40} {}",
                                  &host_entry.hostname_port.clone().expect("hostname:port should be set"),
                                  flag.name,
                                  flag.value,
                                  flag.vars_type
-                        );
+                        )?;
                     }
                 };
             };
         };
+        rows.finish()
     }
 }
 
@@ -123,16 +145,17 @@ impl VarsDiff {
     pub fn snapshot_diff(
         begin_snapshot: &String,
         end_snapshot: &String,
+        config: &Config,
     ) -> Result<VarsDiff>
     {
         let mut varsdiff = VarsDiff::new();
 
         let mut allvars = AllVars::new();
-        allvars.vars = snapshot::read_snapshot_json(begin_snapshot, "vars")?;
+        allvars.vars = snapshot::read_snapshot_json(begin_snapshot, "vars", config)?;
         varsdiff.first_snapshot(allvars);
 
         let mut allvars = AllVars::new();
-        allvars.vars = snapshot::read_snapshot_json(end_snapshot, "vars")?;
+        allvars.vars = snapshot::read_snapshot_json(end_snapshot, "vars", config)?;
         varsdiff.second_snapshot(allvars);
 
         Ok(varsdiff)
@@ -180,10 +203,13 @@ impl VarsDiff {
             }
         }
     }
+    /// Print the changed vars, and return the number of changed vars.
     pub fn print(
         &self,
-    )
+        config: &Config,
+    ) -> Result<usize>
     {
+        let mut rows = utility::RowWriter::new(config);
         for ((hostname_port, name), row) in self.btreevarsdiff.iter() {
             if row.first_value == row.second_value
                 && row.first_vars_type == row.second_vars_type
@@ -194,34 +220,38 @@ impl VarsDiff {
             }
             else
             {
-                print!("{} {:20} Vars: {:50} ", "=".to_string().yellow(), hostname_port, name);
-                if row.first_value != row.second_value
+                let value = if row.first_value != row.second_value
                 {
-                    print!("{}->{} ", row.first_value.yellow(), row.second_value.yellow());
+                    format!("{}->{}", row.first_value.yellow(), row.second_value.yellow())
                 }
                 else
                 {
-                    print!("{} ", row.second_value);
+                    row.second_value.to_string()
                 };
-                if row.first_vars_type != row.second_vars_type
+                let vars_type = if row.first_vars_type != row.second_vars_type
                 {
-                    println!("{}->{}", row.first_vars_type.yellow(), row.second_vars_type.yellow());
+                    format!("{}->{}", row.first_vars_type.yellow(), row.second_vars_type.yellow())
                 }
                 else
                 {
-                    println!("{}", row.second_vars_type);
+                    row.second_vars_type.to_string()
                 };
+                writeln!(rows, "{} {:20} Vars: {:50} {} {}", "=".to_string().yellow(), hostname_port, name, value, vars_type)?;
             };
         }
+        let changes = rows.rows();
+        rows.finish()?;
+        Ok(changes)
     }
     pub async fn adhoc_read_first_snapshot(
         &mut self,
         hosts: &Vec<&str>,
         ports: &Vec<&str>,
         parallel: usize,
+        config: &Config,
     )
     {
-        let allvars = AllVars::read_vars(hosts, ports, parallel).await;
+        let allvars = AllVars::read_vars(hosts, ports, parallel, config).await;
         self.first_snapshot(allvars);
     }
     pub async fn adhoc_read_second_snapshot(
@@ -229,26 +259,31 @@ impl VarsDiff {
         hosts: &Vec<&str>,
         ports: &Vec<&str>,
         parallel: usize,
+        config: &Config,
     )
     {
-        let allvars = AllVars::read_vars(hosts, ports, parallel).await;
+        let allvars = AllVars::read_vars(hosts, ports, parallel, config).await;
         self.second_snapshot(allvars);
     }
 }
 
 pub async fn vars_diff(
     options: &Opts,
+    config: &Config,
 ) -> Result<()>
 {
     if options.begin.is_none() || options.end.is_none() {
-        snapshot::Snapshot::print()?;
+        snapshot::Snapshot::print(config)?;
     }
     if options.snapshot_list { return Ok(()) };
 
     let (begin_snapshot, end_snapshot, _begin_snapshot_row) = snapshot::Snapshot::read_begin_end_snapshot_from_user(options.begin, options.end)?;
 
-    let varsdiff = VarsDiff::snapshot_diff(&begin_snapshot, &end_snapshot)?;
-    varsdiff.print();
+    snapshot::Snapshot::print_diff_header(&begin_snapshot, &end_snapshot, config)?;
+
+    let varsdiff = VarsDiff::snapshot_diff(&begin_snapshot, &end_snapshot, config)?;
+    let changes = varsdiff.print(config)?;
+    utility::add_diff_changes(changes, config);
 
     Ok(())
 }
@@ -258,6 +293,7 @@ pub async fn print_vars(
     ports: Vec<&str>,
     parallel: usize,
     options: &Opts,
+    config: &Config,
 ) -> Result<()>
 {
     let hostname_filter = utility::set_regex(&options.hostname_match);
@@ -267,12 +303,12 @@ pub async fn print_vars(
         Some(snapshot_number) => {
 
             let mut allvars = AllVars::new();
-            allvars.vars = snapshot::read_snapshot_json(snapshot_number, "vars")?;
-            allvars.print(&options.details_enable, &hostname_filter, &stat_name_filter).await;
+            allvars.vars = snapshot::read_snapshot_json(snapshot_number, "vars", config)?;
+            allvars.print(&options.details_enable, &hostname_filter, &stat_name_filter, config).await?;
         }
         None => {
-            let allvars = AllVars::read_vars(&hosts, &ports, parallel).await;
-            allvars.print(&options.details_enable, &hostname_filter, &stat_name_filter).await;
+            let allvars = AllVars::read_vars(&hosts, &ports, parallel, config).await;
+            allvars.print(&options.details_enable, &hostname_filter, &stat_name_filter, config).await?;
         }
     }
     Ok(())
@@ -350,10 +386,11 @@ mod tests {
     #[tokio::test]
     async fn integration_parse_vars_master()
     {
+        let config = &Config::default();
         let hostname = utility::get_hostname_master();
         let port = utility::get_port_master();
 
-        let allvars = AllVars::read_vars(&vec![&hostname], &vec![&port], 1).await;
+        let allvars = AllVars::read_vars(&vec![&hostname], &vec![&port], 1, config).await;
 
         // the master must have gflags
         assert!(!allvars.vars.is_empty());
@@ -361,10 +398,11 @@ mod tests {
     #[tokio::test]
     async fn integration_parse_vars_tserver()
     {
+        let config = &Config::default();
         let hostname = utility::get_hostname_tserver();
         let port = utility::get_port_tserver();
 
-        let allvars = AllVars::read_vars(&vec![&hostname], &vec![&port], 1).await;
+        let allvars = AllVars::read_vars(&vec![&hostname], &vec![&port], 1, config).await;
 
         // the master must have gflags
         assert!(!allvars.vars.is_empty());
@@ -1,14 +1,15 @@
 //! The impls and functions
 //!
 use chrono::Local;
-use std::{sync::mpsc::channel, time::Instant};
+use std::{fmt, str::FromStr, sync::mpsc::channel, time::Instant};
 use colored::Colorize;
 use regex::Regex;
 use log::*;
-use anyhow::Result;
+use anyhow::{Result, Context, bail};
 use crate::utility;
+use crate::utility::Config;
 use crate::snapshot;
-use crate::versions::{Version, AllVersions, VersionsDiff, VersionsDiffFields};
+use crate::versions::{Version, AllVersions, VersionsDiff, VersionsDiffFields, VersionNumber};
 use crate::Opts;
 
 impl AllVersions {
@@ -20,13 +21,14 @@ impl AllVersions {
         ports: &Vec<&str>,
         snapshot_number: i32,
         parallel: usize,
+        config: &Config,
     ) -> Result<()>
     {
         info!("begin snapshot");
         let timer = Instant::now();
 
-        let allversions = AllVersions::read_versions(hosts, ports, parallel).await;
-        snapshot::save_snapshot_json(snapshot_number, "versions", allversions.versions)?;
+        let allversions = AllVersions::read_versions(hosts, ports, parallel, config).await;
+        snapshot::save_snapshot_json(snapshot_number, "versions", allversions.versions, config)?;
 
         info!("end snapshot: {:?}", timer.elapsed());
         Ok(())
@@ -35,6 +37,7 @@ impl AllVersions {
         hosts: &Vec<&str>,
         ports: &Vec<&str>,
         parallel: usize,
+        config: &Config,
     ) -> AllVersions
     {
         info!("begin parallel http read");
@@ -48,9 +51,9 @@ impl AllVersions {
                     let tx = tx.clone();
                     s.spawn(move |_| {
                         let detail_snapshot_time = Local::now();
-                        let mut version = AllVersions::read_http(host, port);
+                        let mut version = AllVersions::read_http(host, port, config);
                         version.timestamp = Some(detail_snapshot_time);
-                        version.hostname_port = Some(format!("{}:{}", host, port));
+                        version.hostname_port = Some(utility::hostname_port(host, port, config));
                         tx.send(version).expect("error sending data via tx");
                     });
                 }
@@ -72,9 +75,10 @@ impl AllVersions {
     pub fn read_http(
         host: &str,
         port: &str,
+        config: &Config,
     ) -> Version
     {
-        let data_from_http = utility::http_get(host, port, "api/v1/version");
+        let data_from_http = utility::http_get_endpoint(host, port, "versions", config);
         AllVersions::parse_version(data_from_http, host, port)
     }
     fn parse_version(
@@ -89,31 +93,123 @@ impl AllVersions {
                 Version::default()
             })
     }
+    /// Print the versions.
+    ///
+    /// If a minimum version is given, a column shows if the node meets it, and the number of nodes that do not is returned.
+    /// A node of which the version cannot be parsed does not meet the minimum version.
     pub fn print(
         &self,
         hostname_filter: &Regex,
-    )
+        min_version: &Option<VersionNumber>,
+        config: &Config,
+    ) -> Result<usize>
     {
-        println!("{:20} {:15} {:10} {:10} {:24} {:10}",
-                 "hostname_port",
-                 "version_number",
-                 "build_nr",
-                 "build_type",
-                 "build_timestamp",
-                 "git_hash"
-        );
-        for row in self.versions.iter() {
-            if hostname_filter.is_match(&row.hostname_port.clone().expect("hostname:port should be set")) {
-                println!("{:20} {:15} {:10} {:10} {:24} {:10}",
-                         row.hostname_port.as_ref().expect("hostname:port should be set"),
-                         row.version_number,
-                         row.build_number,
-                         row.build_type,
-                         row.build_timestamp,
-                         row.git_hash
-                );
-            }
+        let mut below_min_version = 0;
+        let rows: Vec<Vec<String>> = self.versions
+            .iter()
+            .filter(|row| hostname_filter.is_match(row.hostname_port.as_ref().expect("hostname:port should be set")))
+            .map(|row| {
+                let mut columns = vec![
+                    row.hostname_port.clone().expect("hostname:port should be set"),
+                    row.version_number.clone(),
+                    row.build_number.clone(),
+                    row.build_type.clone(),
+                    row.build_timestamp.clone(),
+                    row.git_hash.clone(),
+                ];
+                if let Some(min_version) = min_version
+                {
+                    match row.version()
+                    {
+                        Ok(version) if version >= *min_version => columns.push("yes".green().to_string()),
+                        Ok(_) => {
+                            below_min_version += 1;
+                            columns.push("NO".red().bold().to_string());
+                        },
+                        Err(e) => {
+                            warn!("{}: {}", row.hostname_port.clone().unwrap_or_default(), e);
+                            below_min_version += 1;
+                            columns.push("unknown".red().to_string());
+                        },
+                    }
+                }
+                columns
+            })
+            .collect();
+        match min_version
+        {
+            Some(min_version) => utility::print_table(&["hostname_port", "version_number", "build_nr", "build_type", "build_timestamp", "git_hash", &format!(">= {}", min_version)], &rows, config)?,
+            None => utility::print_table(&["hostname_port", "version_number", "build_nr", "build_type", "build_timestamp", "git_hash"], &rows, config)?,
         }
+        Ok(below_min_version)
+    }
+}
+
+impl Version {
+    /// The parsed version_number and build_number.
+    pub fn version(&self) -> Result<VersionNumber>
+    {
+        let mut version: VersionNumber = self.version_number.parse()?;
+        version.build = self.build_number
+            .parse()
+            .with_context(|| format!("Invalid build number: {}", self.build_number))?;
+        Ok(version)
+    }
+}
+
+impl FromStr for VersionNumber {
+    type Err = anyhow::Error;
+
+    /// Parse a version such as 2.14.1.0, optionally followed by a build number: 2.14.1.0-b36.
+    /// Missing version parts are zero, so 2.14 is 2.14.0.0, and a missing build number is 0.
+    fn from_str(version: &str) -> Result<Self>
+    {
+        let (version_number, build_number) = match version.split_once("-b") {
+            Some((version_number, build_number)) => (version_number, Some(build_number)),
+            None => (version, None),
+        };
+        let parts = version_number
+            .split('.')
+            .map(|part| part.parse::<u32>())
+            .collect::<Result<Vec<_>, _>>()
+            .with_context(|| format!("Invalid version: {}", version))?;
+        if parts.is_empty() || parts.len() > 4 {
+            bail!("Invalid version: {}, expected up to 4 parts, such as 2.14.1.0", version);
+        }
+        Ok(VersionNumber {
+            major: parts[0],
+            minor: parts.get(1).copied().unwrap_or_default(),
+            maintenance: parts.get(2).copied().unwrap_or_default(),
+            patch: parts.get(3).copied().unwrap_or_default(),
+            build: build_number
+                .map(|build_number| build_number.parse())
+                .transpose()
+                .with_context(|| format!("Invalid build number in version: {}", version))?
+                .unwrap_or_default(),
+        })
+    }
+}
+
+impl fmt::Display for VersionNumber {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}.{}.{}", self.major, self.minor, self.maintenance, self.patch)?;
+        if self.build > 0 {
+            write!(f, "-b{}", self.build)?;
+        }
+        Ok(())
+    }
+}
+
+/// The version as version number and build number, such as 2.14.1.0-b36, or empty if the node has no version.
+fn display_version(
+    version_number: &str,
+    build_number: &str,
+) -> String
+{
+    if version_number.is_empty() {
+        String::new()
+    } else {
+        format!("{}-b{}", version_number, build_number)
     }
 }
 
@@ -122,15 +218,16 @@ impl VersionsDiff {
     pub fn snapshot_diff(
         begin_snapshot: &String,
         end_snapshot: &String,
+        config: &Config,
     ) -> Result<VersionsDiff>
     {
         let mut allversions = AllVersions::new();
-        allversions.versions = snapshot::read_snapshot_json(begin_snapshot, "versions")?;
+        allversions.versions = snapshot::read_snapshot_json(begin_snapshot, "versions", config)?;
         let mut versionsdiff = VersionsDiff::new();
         versionsdiff.first_snapshot(allversions);
 
         let mut allversions = AllVersions::new();
-        allversions.versions = snapshot::read_snapshot_json(end_snapshot, "versions")?;
+        allversions.versions = snapshot::read_snapshot_json(end_snapshot, "versions", config)?;
         versionsdiff.second_snapshot(allversions);
 
         Ok(versionsdiff)
@@ -193,11 +290,14 @@ impl VersionsDiff {
                 });
         }
     }
+    /// Print the changes of the versions, and return the number of changes: the added, removed and changed servers.
     pub fn print(
         &self,
         hostname_filter: &Regex,
-    )
+        config: &Config,
+    ) -> Result<usize>
     {
+        let mut changes = 0;
         for (hostname, row) in self.btreeversionsdiff.iter().filter(|(k,_v)| hostname_filter.is_match(k))
         {
             #[allow(clippy::nonminimal_bool)]
@@ -216,85 +316,166 @@ impl VersionsDiff {
                 continue;
             }
             // okay, first and second fields are not equal
+            changes += 1;
             // is the a "first" entry empty, indicating it appeared between snapshots
-            else if row.first_git_hash.is_empty()
+            if row.first_git_hash.is_empty()
             {
-                print!("{} Versions: {:20}", "+".to_string().green(), hostname);
-                println!("{} b{} {} {} {}",
+                write!(config.output(), "{} Versions: {:20}", "+".to_string().green(), hostname)?;
+                writeln!(config.output(), "{} b{} {} {} {}",
                          row.second_version_number,
                          row.second_build_number,
                          row.second_build_type,
                          row.second_build_timestamp,
                          row.second_git_hash
-                );
+                )?;
             }
             // is a "second" entry empty, indicating it disappeared between snapshots
             else if row.second_git_hash.is_empty()
             {
-                print!("{} Versions: {:20}", "-".to_string().red(), hostname);
-                println!("{} b{} {} {} {}",
+                write!(config.output(), "{} Versions: {:20}", "-".to_string().red(), hostname)?;
+                writeln!(config.output(), "{} b{} {} {} {}",
                          row.first_version_number,
                          row.first_build_number,
                          row.first_build_type,
                          row.first_build_timestamp,
                          row.first_git_hash
-                );
+                )?;
             }
             else
             {
                 // first and second fields are set, but not equal: changed versions
-                print!("{} Versions: {:20} ", "=".to_string().yellow(), hostname);
+                write!(config.output(), "{} Versions: {:20} ", "=".to_string().yellow(), hostname)?;
                 if row.first_version_number != row.second_version_number
                 {
-                    print!("{}->{} ", row.first_version_number.yellow(), row.second_version_number.yellow());
+                    write!(config.output(), "{}->{} ", row.first_version_number.yellow(), row.second_version_number.yellow())?;
                 }
                 else
                 {
-                    print!("{} ", row.second_version_number);
+                    write!(config.output(), "{} ", row.second_version_number)?;
                 };
                 if row.first_build_number != row.second_build_number
                 {
-                    print!("b{}->b{} ", row.first_build_number.yellow(), row.second_build_number.yellow());
+                    write!(config.output(), "b{}->b{} ", row.first_build_number.yellow(), row.second_build_number.yellow())?;
                 }
                 else
                 {
-                    print!("b{} ", row.second_build_number);
+                    write!(config.output(), "b{} ", row.second_build_number)?;
                 };
                 if row.first_build_type != row.second_build_type
                 {
-                    print!("{}->{} ", row.first_build_type.yellow(), row.second_build_type.yellow());
+                    write!(config.output(), "{}->{} ", row.first_build_type.yellow(), row.second_build_type.yellow())?;
                 }
                 else
                 {
-                    print!("{} ", row.second_build_type);
+                    write!(config.output(), "{} ", row.second_build_type)?;
                 };
                 if row.first_build_timestamp != row.second_build_timestamp
                 {
-                    print!("{}->{} ", row.first_build_timestamp.yellow(), row.second_build_timestamp.yellow());
+                    write!(config.output(), "{}->{} ", row.first_build_timestamp.yellow(), row.second_build_timestamp.yellow())?;
                 }
                 else
                 {
-                    print!("{} ", row.second_build_timestamp);
+                    write!(config.output(), "{} ", row.second_build_timestamp)?;
                 };
                 if row.first_git_hash != row.second_git_hash
                 {
-                    println!("{}->{} ", row.first_git_hash.yellow(), row.second_git_hash.yellow());
+                    writeln!(config.output(), "{}->{} ", row.first_git_hash.yellow(), row.second_git_hash.yellow())?;
                 }
                 else
                 {
-                    println!("{} ", row.second_git_hash);
+                    writeln!(config.output(), "{} ", row.second_git_hash)?;
+                };
+            }
+        }
+        Ok(changes)
+    }
+    /// The rows of the upgrade path: the version of each node before and after, and how it changed.
+    /// With only_changed, the nodes of which the version did not change are left out.
+    fn upgrade_path_rows(
+        &self,
+        hostname_filter: &Regex,
+        only_changed: bool,
+    ) -> Vec<Vec<String>>
+    {
+        self.btreeversionsdiff
+            .iter()
+            .filter(|(hostname_port, _)| hostname_filter.is_match(hostname_port))
+            .filter_map(|(hostname_port, row)| {
+                let before = display_version(&row.first_version_number, &row.first_build_number);
+                let after = display_version(&row.second_version_number, &row.second_build_number);
+                let change = if before.is_empty() {
+                    "added".to_string()
+                } else if after.is_empty() {
+                    "removed".to_string()
+                } else if before == after {
+                    "unchanged".to_string()
+                } else {
+                    match (before.parse::<VersionNumber>(), after.parse::<VersionNumber>())
+                    {
+                        (Ok(first), Ok(second)) if first < second => "upgraded".to_string(),
+                        (Ok(first), Ok(second)) if first > second => "downgraded".to_string(),
+                        _ => "changed".to_string(),
+                    }
                 };
+                if only_changed && change == "unchanged"
+                {
+                    return None;
+                }
+                Some(vec![hostname_port.clone(), before, after, change])
+            })
+            .collect()
+    }
+    /// The lowest and highest version of the nodes in the begin (first) or end snapshot.
+    /// The versions that cannot be parsed are not taken into account.
+    fn version_range(
+        &self,
+        hostname_filter: &Regex,
+        first: bool,
+    ) -> Option<(VersionNumber, VersionNumber)>
+    {
+        let versions: Vec<VersionNumber> = self.btreeversionsdiff
+            .iter()
+            .filter(|(hostname_port, _)| hostname_filter.is_match(hostname_port))
+            .map(|(_, row)| if first {
+                display_version(&row.first_version_number, &row.first_build_number)
+            } else {
+                display_version(&row.second_version_number, &row.second_build_number)
+            })
+            .filter_map(|version| version.parse::<VersionNumber>().ok())
+            .collect();
+        Some((*versions.iter().min()?, *versions.iter().max()?))
+    }
+    /// Print the upgrade path: a table with the version of each node before and after,
+    /// followed by the lowest and highest version of the cluster at the begin and end.
+    /// Returns the number of nodes of which the version changed, was added or was removed.
+    pub fn print_upgrade_path(
+        &self,
+        hostname_filter: &Regex,
+        only_changed: bool,
+        config: &Config,
+    ) -> Result<usize>
+    {
+        let rows = self.upgrade_path_rows(hostname_filter, only_changed);
+        utility::print_table(&["hostname_port", "before", "after", "change"], &rows, config)?;
+        for (label, first) in [("begin", true), ("end", false)]
+        {
+            match self.version_range(hostname_filter, first)
+            {
+                Some((min_version, max_version)) => writeln!(config.output(), "Cluster version at {:5}: min {}, max {}", label, min_version, max_version)?,
+                None => writeln!(config.output(), "Cluster version at {:5}: unknown", label)?,
             }
         }
+        Ok(rows.iter().filter(|row| row[3] != "unchanged").count())
     }
     pub async fn adhoc_read_first_snapshot(
         &mut self,
         hosts: &Vec<&str>,
         ports: &Vec<&str>,
         parallel: usize,
+        config: &Config,
     )
     {
-        let allversions = AllVersions::read_versions(hosts, ports, parallel).await;
+        let allversions = AllVersions::read_versions(hosts, ports, parallel, config).await;
         self.first_snapshot(allversions);
     }
     pub async fn adhoc_read_second_snapshot(
@@ -302,29 +483,34 @@ impl VersionsDiff {
         hosts: &Vec<&str>,
         ports: &Vec<&str>,
         parallel: usize,
+        config: &Config,
     )
     {
-        let allversions = AllVersions::read_versions(hosts, ports, parallel).await;
+        let allversions = AllVersions::read_versions(hosts, ports, parallel, config).await;
         self.second_snapshot(allversions);
     }
 }
 
 pub async fn versions_diff(
     options: &Opts,
+    config: &Config,
 ) -> Result<()>
 {
     info!("versions diff");
 
     if options.begin.is_none() || options.end.is_none() {
-        snapshot::Snapshot::print()?;
+        snapshot::Snapshot::print(config)?;
     }
     if options.snapshot_list { return Ok(()) };
 
     let hostname_filter = utility::set_regex(&options.hostname_match);
 
     let (begin_snapshot, end_snapshot, _begin_snapshot_row) = snapshot::Snapshot::read_begin_end_snapshot_from_user(options.begin, options.end)?;
-    let versions_diff = VersionsDiff::snapshot_diff(&begin_snapshot, &end_snapshot)?;
-    versions_diff.print(&hostname_filter);
+
+    snapshot::Snapshot::print_diff_header(&begin_snapshot, &end_snapshot, config)?;
+    let versions_diff = VersionsDiff::snapshot_diff(&begin_snapshot, &end_snapshot, config)?;
+    let changes = versions_diff.print_upgrade_path(&hostname_filter, options.only_changed, config)?;
+    utility::add_diff_changes(changes, config);
 
     Ok(())
 }
@@ -334,22 +520,32 @@ pub async fn print_version(
     ports: Vec<&str>,
     parallel: usize,
     options: &Opts,
+    config: &Config,
 ) -> Result<()>
 {
     let hostname_filter = utility::set_regex(&options.hostname_match);
+    let min_version = options.min_version
+        .as_ref()
+        .map(|min_version| min_version.parse::<VersionNumber>())
+        .transpose()?;
 
-    match options.print_version.as_ref().unwrap() {
+    let below_min_version = match options.print_version.as_ref().unwrap() {
         Some(snapshot_number) => {
 
             let mut allversions = AllVersions::new();
-            allversions.versions = snapshot::read_snapshot_json(snapshot_number, "versions")?;
+            allversions.versions = snapshot::read_snapshot_json(snapshot_number, "versions", config)?;
 
-            allversions.print(&hostname_filter);
+            allversions.print(&hostname_filter, &min_version, config)?
         },
         None => {
-            let allversions = AllVersions::read_versions(&hosts, &ports, parallel).await;
-            allversions.print(&hostname_filter);
+            let allversions = AllVersions::read_versions(&hosts, &ports, parallel, config).await;
+            allversions.print(&hostname_filter, &min_version, config)?
         },
+    };
+    if let Some(min_version) = min_version {
+        if below_min_version > 0 {
+            bail!("{} node(s) below the minimum version {}", below_min_version, min_version);
+        }
     }
     Ok(())
 }
@@ -376,22 +572,69 @@ mod tests {
         assert_eq!(result.git_hash, "d142556567b5e1c83ea5c915ec7b9964492b2321");
     }
 
+    #[test]
+    fn unit_version_number_compare() {
+        let version_2_9: VersionNumber = "2.9.0.0".parse().unwrap();
+        let version_2_14: VersionNumber = "2.14.1.0".parse().unwrap();
+        // a string compare would consider 2.9 newer than 2.14.
+        assert!(version_2_9 < version_2_14);
+        assert!("2.14.1.0-b36".parse::<VersionNumber>().unwrap() > version_2_14);
+        assert_eq!("2.14".parse::<VersionNumber>().unwrap(), "2.14.0.0".parse::<VersionNumber>().unwrap());
+        assert_eq!("2.14.1.0-b36".parse::<VersionNumber>().unwrap().to_string(), "2.14.1.0-b36");
+        assert!("2.14.x.0".parse::<VersionNumber>().is_err());
+        assert!("2.14.1.0.1".parse::<VersionNumber>().is_err());
+        let version = Version { version_number: "2.11.2.0".to_string(), build_number: "89".to_string(), ..Default::default() };
+        assert_eq!(version.version().unwrap(), VersionNumber { major: 2, minor: 11, maintenance: 2, patch: 0, build: 89 });
+    }
+    #[test]
+    fn unit_versions_diff_upgrade_path() {
+        let version = |hostname_port: &str, version_number: &str, build_number: &str| Version {
+            hostname_port: Some(hostname_port.to_string()),
+            git_hash: format!("{}-{}", version_number, build_number),
+            version_number: version_number.to_string(),
+            build_number: build_number.to_string(),
+            ..Default::default()
+        };
+        let mut versionsdiff = VersionsDiff::new();
+        versionsdiff.first_snapshot(AllVersions { versions: vec![
+            version("yb-1.local:9000", "2.14.1.0", "36"),
+            version("yb-2.local:9000", "2.14.1.0", "36"),
+            version("yb-3.local:9000", "2.14.1.0", "36"),
+        ] });
+        versionsdiff.second_snapshot(AllVersions { versions: vec![
+            version("yb-1.local:9000", "2.16.0.0", "90"),
+            version("yb-2.local:9000", "2.14.1.0", "36"),
+            version("yb-4.local:9000", "2.16.0.0", "90"),
+        ] });
+        let hostname_filter = utility::set_regex(&None);
+        assert_eq!(versionsdiff.upgrade_path_rows(&hostname_filter, false), vec![
+            vec!["yb-1.local:9000", "2.14.1.0-b36", "2.16.0.0-b90", "upgraded"],
+            vec!["yb-2.local:9000", "2.14.1.0-b36", "2.14.1.0-b36", "unchanged"],
+            vec!["yb-3.local:9000", "2.14.1.0-b36", "", "removed"],
+            vec!["yb-4.local:9000", "", "2.16.0.0-b90", "added"],
+        ]);
+        assert_eq!(versionsdiff.upgrade_path_rows(&hostname_filter, true).len(), 3);
+        assert_eq!(versionsdiff.version_range(&hostname_filter, true), Some(("2.14.1.0-b36".parse().unwrap(), "2.14.1.0-b36".parse().unwrap())));
+        assert_eq!(versionsdiff.version_range(&hostname_filter, false), Some(("2.14.1.0-b36".parse().unwrap(), "2.16.0.0-b90".parse().unwrap())));
+    }
     #[tokio::test]
     async fn integration_parse_versiondata_master() {
+        let config = &Config::default();
         let hostname = utility::get_hostname_master();
         let port = utility::get_port_master();
 
-        let allversions = AllVersions::read_versions(&vec![&hostname], &vec![&port], 1).await;
+        let allversions = AllVersions::read_versions(&vec![&hostname], &vec![&port], 1, config).await;
 
         // each daemon should return one row.
         assert!(allversions.versions.len() == 1);
     }
     #[tokio::test]
     async fn integration_parse_versiondata_tserver() {
+        let config = &Config::default();
         let hostname = utility::get_hostname_tserver();
         let port = utility::get_port_tserver();
 
-        let allversions = AllVersions::read_versions(&vec![&hostname], &vec![&port], 1).await;
+        let allversions = AllVersions::read_versions(&vec![&hostname], &vec![&port], 1, config).await;
 
         // each daemon should return one row.
         assert!(allversions.versions.len() == 1);
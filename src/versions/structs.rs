@@ -41,6 +41,18 @@ pub struct Version {
 pub struct AllVersions {
     pub versions: Vec<Version>,
 }
+/// The parsed version of YugabyteDB, which uses a 4-part version scheme and a build number,
+/// such as 2.14.1.0 build 36 (also written as 2.14.1.0-b36).
+///
+/// The field order makes the derived ordering compare the version numerically, part by part, and then the build.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct VersionNumber {
+    pub major: u32,
+    pub minor: u32,
+    pub maintenance: u32,
+    pub patch: u32,
+    pub build: u32,
+}
 // diff
 /// BTreeMap for storing the version diff struct per `hostname_port`
 type BTreeVersionsDiff = BTreeMap<String, VersionsDiffFields>;
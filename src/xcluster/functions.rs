@@ -0,0 +1,226 @@
+//! The impls and functions
+//!
+use log::*;
+use colored::*;
+use anyhow::Result;
+use crate::isleader::AllIsLeader;
+use crate::cluster_config::AllSysClusterConfigEntryPB;
+use crate::metrics::{AllMetricEntity, Metrics};
+use crate::utility;
+use crate::utility::Config;
+use crate::snapshot;
+use crate::xcluster::{XCluster, XClusterReplicationGroup, XClusterStream, XClusterOutboundStream};
+use crate::Opts;
+
+impl XCluster {
+    pub fn new() -> Self { Default::default() }
+    /// Add the inbound replication groups from the consumer registry in the cluster config of the master leader.
+    fn add_cluster_config(
+        &mut self,
+        allsysclusterconfigentrypb: AllSysClusterConfigEntryPB,
+        leader_hostname: &str,
+    )
+    {
+        let consumer_registry = match allsysclusterconfigentrypb.sysclusterconfigentrypb
+            .into_iter()
+            .find(|r| r.hostname_port.as_deref() == Some(leader_hostname))
+            .and_then(|sysclusterconfigentrypb| sysclusterconfigentrypb.consumer_registry)
+        {
+            Some(consumer_registry) => consumer_registry,
+            None => {
+                debug!("No consumer registry found in the cluster config of master leader {}", leader_hostname);
+                return;
+            },
+        };
+
+        self.role = consumer_registry.role
+            .map(|role| format!("{:?}", role))
+            .unwrap_or_else(|| "-".to_string());
+        for producer in consumer_registry.producer_map
+        {
+            self.replication_groups.push(XClusterReplicationGroup {
+                replication_group_id: producer.key,
+                source_master_addresses: producer.value.master_addrs
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|address| format!("{}:{}", address.host, address.port))
+                    .collect(),
+                disabled: producer.value.disable_stream.unwrap_or_default(),
+                streams: producer.value.stream_map
+                    .into_iter()
+                    .map(|stream| XClusterStream {
+                        stream_id: stream.key,
+                        source_table_id: stream.value.producer_table_id,
+                        target_table_id: stream.value.consumer_table_id,
+                        target_tablets: stream.value.consumer_producer_tablet_map.len(),
+                    })
+                    .collect(),
+            });
+        }
+    }
+    /// Add the outbound streams from the cdc metric entities, and the table names from the table metric entities.
+    ///
+    /// A cdc metric entity is reported per stream per tablet by the tablet server hosting the tablet leader,
+    /// the lag of a stream is the highest lag of its tablets.
+    fn add_metrics(
+        &mut self,
+        allmetricentity: AllMetricEntity,
+    )
+    {
+        for metricentity in allmetricentity.metricentity
+        {
+            let attributes = metricentity.attributes.unwrap_or_default();
+            match metricentity.metrics_type.as_str()
+            {
+                "table" => {
+                    if let Some(table_name) = attributes.table_name
+                    {
+                        self.table_names.insert(metricentity.id, table_name);
+                    }
+                },
+                "cdc" => {
+                    let Some(stream_id) = attributes.stream_id else { continue };
+                    let outbound_stream = self.outbound_streams
+                        .entry(stream_id)
+                        .or_insert_with(|| XClusterOutboundStream {
+                            namespace: attributes.namespace_name.unwrap_or_default(),
+                            table_name: attributes.table_name.unwrap_or_default(),
+                            table_id: attributes.table_id.unwrap_or_default(),
+                            ..Default::default()
+                        });
+                    outbound_stream.tablets += 1;
+                    for metric in metricentity.metrics
+                    {
+                        if let Metrics::MetricValue { name, value } = metric
+                        {
+                            match name.as_str()
+                            {
+                                "async_replication_sent_lag_micros" => outbound_stream.sent_lag_micros = outbound_stream.sent_lag_micros.max(value),
+                                "async_replication_committed_lag_micros" => outbound_stream.committed_lag_micros = outbound_stream.committed_lag_micros.max(value),
+                                _ => {},
+                            }
+                        }
+                    }
+                },
+                _ => {},
+            }
+        }
+    }
+    pub fn print(
+        &self,
+        config: &Config,
+    ) -> Result<()>
+    {
+        if self.replication_groups.is_empty() && self.outbound_streams.is_empty()
+        {
+            writeln!(config.output(), "xCluster replication is not configured: no inbound replication groups in the cluster config, and no outbound streams in the cdc metrics.")?;
+            return Ok(());
+        }
+
+        if !self.replication_groups.is_empty()
+        {
+            writeln!(config.output(), "{} (role: {})", "Inbound replication groups".bold(), self.role)?;
+            for replication_group in &self.replication_groups
+            {
+                let status = if replication_group.disabled { "DISABLED".to_string().red() } else { "ENABLED".to_string().green() };
+                writeln!(config.output(), "Replication group: {} status: {} source masters: {}",
+                         replication_group.replication_group_id,
+                         status,
+                         replication_group.source_master_addresses.join(",")
+                )?;
+                let rows: Vec<Vec<String>> = replication_group.streams
+                    .iter()
+                    .map(|stream| vec![
+                        stream.stream_id.clone(),
+                        self.table_names.get(&stream.target_table_id).cloned().unwrap_or_else(|| "-".to_string()),
+                        stream.source_table_id.clone(),
+                        stream.target_table_id.clone(),
+                        stream.target_tablets.to_string(),
+                    ])
+                    .collect();
+                utility::print_table(&["stream_id", "table_name", "source_table_id", "target_table_id", "target_tablets"], &rows, config)?;
+            }
+        }
+
+        if !self.outbound_streams.is_empty()
+        {
+            writeln!(config.output(), "{}", "Outbound streams".bold())?;
+            let rows: Vec<Vec<String>> = self.outbound_streams
+                .iter()
+                .map(|(stream_id, outbound_stream)| vec![
+                    stream_id.clone(),
+                    outbound_stream.namespace.clone(),
+                    outbound_stream.table_name.clone(),
+                    outbound_stream.table_id.clone(),
+                    outbound_stream.tablets.to_string(),
+                    format_lag(outbound_stream.sent_lag_micros, config),
+                    format_lag(outbound_stream.committed_lag_micros, config),
+                ])
+                .collect();
+            utility::print_table(&["stream_id", "namespace", "table_name", "table_id", "tablets", "sent_lag", "committed_lag"], &rows, config)?;
+        }
+        Ok(())
+    }
+}
+
+/// Format a replication lag in microseconds as seconds.
+fn format_lag(lag_micros: i64, config: &Config) -> String
+{
+    format!("{} s", utility::format_float(lag_micros as f64 / 1_000_000_f64, 0, 3, config))
+}
+
+pub async fn print_xcluster(
+    hosts: Vec<&str>,
+    ports: Vec<&str>,
+    parallel: usize,
+    options: &Opts,
+    config: &Config,
+) -> Result<()>
+{
+    let mut xcluster = XCluster::new();
+    match options.print_xcluster.as_ref().unwrap() {
+        Some(snapshot_number) => {
+            let mut allsysclusterconfigentrypb = AllSysClusterConfigEntryPB::new();
+            allsysclusterconfigentrypb.sysclusterconfigentrypb = snapshot::read_snapshot_json(snapshot_number, "cluster-config", config)?;
+            let leader_hostname = AllIsLeader::return_leader_snapshot(snapshot_number, config)?;
+            xcluster.add_cluster_config(allsysclusterconfigentrypb, &leader_hostname);
+
+            let mut allmetricentity = AllMetricEntity::new();
+            allmetricentity.metricentity = snapshot::read_snapshot_json(snapshot_number, "metrics", config)?;
+            xcluster.add_metrics(allmetricentity);
+        },
+        None => {
+            let allsysclusterconfigentrypb = AllSysClusterConfigEntryPB::read_cluster_config(&hosts, &ports, parallel, config).await;
+            let leader_hostname = AllIsLeader::return_leader_http(&hosts, &ports, parallel, config).await;
+            xcluster.add_cluster_config(allsysclusterconfigentrypb, &leader_hostname);
+
+            let allmetricentity = AllMetricEntity::read_metrics(&hosts, &ports, parallel, config).await;
+            xcluster.add_metrics(allmetricentity);
+        },
+    }
+    xcluster.print(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unit_xcluster_outbound_streams() {
+        let config = &Config::default();
+        let json = r#"[{"hostname_port":"yb-1:9000","timestamp":null,"type":"cdc","id":"stream_a:tablet_1","attributes":{"stream_id":"stream_a","namespace_name":"yugabyte","table_name":"t","table_id":"table_t"},
+                         "metrics":[{"name":"async_replication_sent_lag_micros","value":1000},{"name":"async_replication_committed_lag_micros","value":2000}]},
+                        {"hostname_port":"yb-2:9000","timestamp":null,"type":"cdc","id":"stream_a:tablet_2","attributes":{"stream_id":"stream_a","namespace_name":"yugabyte","table_name":"t","table_id":"table_t"},
+                         "metrics":[{"name":"async_replication_sent_lag_micros","value":3000},{"name":"async_replication_committed_lag_micros","value":500}]},
+                        {"hostname_port":"yb-1:9000","timestamp":null,"type":"table","id":"table_t","attributes":{"namespace_name":"yugabyte","table_name":"t","table_id":"table_t"},"metrics":[]}]"#;
+        let mut xcluster = XCluster::new();
+        xcluster.add_metrics(AllMetricEntity { metricentity: serde_json::from_str(json).unwrap() });
+        assert_eq!(xcluster.outbound_streams.len(), 1);
+        let outbound_stream = &xcluster.outbound_streams["stream_a"];
+        assert_eq!(outbound_stream.tablets, 2);
+        assert_eq!(outbound_stream.sent_lag_micros, 3000);
+        assert_eq!(outbound_stream.committed_lag_micros, 2000);
+        assert_eq!(xcluster.table_names["table_t"], "t");
+        assert_eq!(format_lag(outbound_stream.sent_lag_micros, config), "0.003 s");
+    }
+}
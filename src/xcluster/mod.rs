@@ -0,0 +1,18 @@
+//! Module for the xCluster replication view.
+//!
+//! The xCluster replication setup is not available from a single endpoint, so it is put together from:
+//! - `/api/v1/cluster-config` on the master leader: the consumer registry contains the inbound replication groups
+//!   of the cluster when it is an xCluster target, with the source masters, streams and tables ([XClusterReplicationGroup]).
+//! - `/metrics` on the tablet servers: the cdc metric entities contain the outbound streams
+//!   of the cluster when it is an xCluster source, with the replication lag ([XClusterOutboundStream]).
+//!
+//! Both are stored in a snapshot already (cluster-config and metrics), so no additional snapshot data is saved.
+//!
+//! The xCluster functionality is called from:
+//! - [crate] -> [print_xcluster] (prints adhoc (live) or snapshot xCluster info)
+//!
+mod structs;
+mod functions;
+
+pub use structs::*;
+pub use functions::*;
@@ -0,0 +1,46 @@
+//! The structs
+//!
+use std::collections::BTreeMap;
+
+/// The xCluster replication of a cluster, as target (inbound) and as source (outbound).
+#[derive(Debug, Default)]
+pub struct XCluster {
+    /// The replication groups replicating into this cluster, from the consumer registry in the cluster config.
+    pub replication_groups: Vec<XClusterReplicationGroup>,
+    /// The role of this cluster as xCluster target: ACTIVE or STANDBY.
+    pub role: String,
+    /// The streams replicating out of this cluster, from the cdc metrics, per stream id.
+    pub outbound_streams: BTreeMap<String, XClusterOutboundStream>,
+    /// The table names per table id, from the table metrics, to show the names of the inbound tables.
+    pub table_names: BTreeMap<String, String>,
+}
+/// An inbound replication group.
+#[derive(Debug, Default)]
+pub struct XClusterReplicationGroup {
+    pub replication_group_id: String,
+    pub source_master_addresses: Vec<String>,
+    pub disabled: bool,
+    pub streams: Vec<XClusterStream>,
+}
+/// A stream of an inbound replication group, which replicates a single table.
+#[derive(Debug, Default)]
+pub struct XClusterStream {
+    pub stream_id: String,
+    pub source_table_id: String,
+    pub target_table_id: String,
+    /// The number of target tablets that the stream replicates into.
+    pub target_tablets: usize,
+}
+/// An outbound stream, summarized over the tablets and tablet servers.
+#[derive(Debug, Default)]
+pub struct XClusterOutboundStream {
+    pub namespace: String,
+    pub table_name: String,
+    pub table_id: String,
+    /// The number of source tablets with cdc metrics for the stream.
+    pub tablets: usize,
+    /// The highest async_replication_sent_lag_micros of the tablets.
+    pub sent_lag_micros: i64,
+    /// The highest async_replication_committed_lag_micros of the tablets.
+    pub committed_lag_micros: i64,
+}
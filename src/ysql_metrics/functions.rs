@@ -0,0 +1,455 @@
+//! The impls and functions
+//!
+use chrono::Local;
+use std::{sync::mpsc::channel, time::Instant};
+use regex::Regex;
+use log::*;
+use anyhow::Result;
+use crate::utility;
+use crate::utility::Config;
+use crate::snapshot;
+use crate::rpcs;
+use crate::ysql_metrics::{AllYsqlMetrics, YsqlMetrics, YsqlMetricsDiff, YsqlMetricsDiffFields, YsqlServerEntity, YsqlStatementCounter};
+use crate::Opts;
+
+/// The prefix of the SQLProcessor statement counters, which is removed for the statement name.
+const STATEMENT_PREFIX: &str = "handler_latency_yb_ysqlserver_SQLProcessor_";
+
+/// The statements that are shown as a rate in the diff, with the column heading.
+const STATEMENT_RATES: [(&str, &str); 7] = [
+    ("Transactions", "txn/s"),
+    ("CommitStmt", "commit/s"),
+    ("RollbackStmt", "rollback/s"),
+    ("SelectStmt", "select/s"),
+    ("InsertStmt", "insert/s"),
+    ("UpdateStmt", "update/s"),
+    ("DeleteStmt", "delete/s"),
+];
+
+impl YsqlMetrics {
+    pub fn new() -> Self { Default::default() }
+    /// The number of client backends, which are the connections.
+    pub fn connections(&self) -> i64
+    {
+        self.backends.values().sum()
+    }
+    /// The number of client backends with the backend status.
+    pub fn backends(&self, backend_status: &str) -> i64
+    {
+        self.backends.get(backend_status).copied().unwrap_or_default()
+    }
+    /// The counter of a SQLProcessor statement, zero if it is not found.
+    pub fn statement(&self, name: &str) -> YsqlStatementCounter
+    {
+        self.statements.get(name).copied().unwrap_or_default()
+    }
+    /// A port that does not provide YSQL metrics has no statements and no backends.
+    pub fn is_empty(&self) -> bool
+    {
+        self.statements.is_empty() && self.backends.is_empty()
+    }
+    /// The percentage of the maximum number of connections in use, if the maximum is known.
+    pub fn connections_used_pct(&self) -> Option<f64>
+    {
+        self.max_connections
+            .filter(|max_connections| *max_connections > 0)
+            .map(|max_connections| self.connections() as f64 / max_connections as f64 * 100.0)
+    }
+    /// Count the client backends by backend status.
+    /// The background processes, such as the checkpointer, are not connections and are not counted.
+    fn add_backends(
+        &mut self,
+        rpcs: rpcs::Rpcs,
+    )
+    {
+        if let rpcs::Rpcs::Ysql { connections, .. } = rpcs
+        {
+            for connection in connections.iter().filter(|connection| connection.backend_type == "client backend")
+            {
+                *self.backends.entry(connection.backend_status.clone()).or_default() += 1;
+            }
+        }
+    }
+}
+
+impl AllYsqlMetrics {
+    pub fn new() -> Self { Default::default() }
+    pub async fn perform_snapshot(
+        hosts: &Vec<&str>,
+        ports: &Vec<&str>,
+        snapshot_number: i32,
+        parallel: usize,
+        config: &Config,
+    ) -> Result<()>
+    {
+        info!("begin snapshot");
+        let timer = Instant::now();
+
+        let allysqlmetrics = AllYsqlMetrics::read_ysql_metrics(hosts, ports, parallel, config).await;
+        snapshot::save_snapshot_json(snapshot_number, "ysql_metrics", allysqlmetrics.ysqlmetrics, config)?;
+
+        info!("end snapshot: {:?}", timer.elapsed());
+        Ok(())
+    }
+    pub async fn read_ysql_metrics(
+        hosts: &Vec<&str>,
+        ports: &Vec<&str>,
+        parallel: usize,
+        config: &Config,
+    ) -> AllYsqlMetrics
+    {
+        info!("begin parallel http read");
+        let timer = Instant::now();
+
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(parallel).build().unwrap();
+        let (tx, rx) = channel();
+        pool.scope(move |s| {
+            for host in hosts {
+                for port in ports {
+                    let tx = tx.clone();
+                    s.spawn(move |_| {
+                        let detail_snapshot_time = Local::now();
+                        let mut ysqlmetrics = AllYsqlMetrics::read_http(host, port, config);
+                        ysqlmetrics.timestamp = Some(detail_snapshot_time);
+                        ysqlmetrics.hostname_port = Some(utility::hostname_port(host, port, config));
+                        tx.send(ysqlmetrics).expect("error sending data via tx");
+                    });
+                }
+            }
+        });
+
+        info!("end parallel http read {:?}", timer.elapsed());
+
+        let mut allysqlmetrics = AllYsqlMetrics::new();
+
+        for ysqlmetrics in rx.iter().filter(|row| !row.is_empty())
+        {
+            allysqlmetrics.ysqlmetrics.push(ysqlmetrics);
+        }
+        allysqlmetrics.ysqlmetrics.sort_by(|a, b| a.hostname_port.cmp(&b.hostname_port));
+
+        allysqlmetrics
+    }
+    fn read_http(
+        host: &str,
+        port: &str,
+        config: &Config,
+    ) -> YsqlMetrics
+    {
+        let data_from_http = utility::http_get_endpoint(host, port, "ysql_metrics", config);
+        let mut ysqlmetrics = AllYsqlMetrics::parse_ysql_metrics(data_from_http, host, port);
+        // the backends are only read from a port that provides the yb.ysqlserver entity.
+        if !ysqlmetrics.statements.is_empty()
+        {
+            ysqlmetrics.add_backends(rpcs::AllRpcs::read_http(host, port, &false, 0, config));
+        }
+        ysqlmetrics
+    }
+    fn parse_ysql_metrics(
+        http_data: String,
+        host: &str,
+        port: &str,
+    ) -> YsqlMetrics
+    {
+        let entities: Vec<YsqlServerEntity> = serde_json::from_str(&http_data)
+            .unwrap_or_else(|e| {
+                debug!("({}:{}) could not parse /metrics json data for ysql metrics, error: {}", host, port, e);
+                Vec::new()
+            });
+        let mut ysqlmetrics = YsqlMetrics::new();
+        for metric in entities
+            .into_iter()
+            .filter(|entity| entity.entity_type == "server" && entity.id == "yb.ysqlserver")
+            .flat_map(|entity| entity.metrics)
+        {
+            match metric.name.as_str()
+            {
+                "yb_ysqlserver_max_connection_total" => ysqlmetrics.max_connections = metric.value.or(metric.count),
+                "yb_ysqlserver_connection_over_limit_total" => ysqlmetrics.connections_over_limit = metric.value.or(metric.count),
+                name if name.starts_with(STATEMENT_PREFIX) => {
+                    ysqlmetrics.statements.insert(
+                        name.trim_start_matches(STATEMENT_PREFIX).to_string(),
+                        YsqlStatementCounter {
+                            count: metric.count.unwrap_or_default(),
+                            sum: metric.sum.unwrap_or_default(),
+                            rows: metric.rows.unwrap_or_default(),
+                        });
+                },
+                _ => {},
+            }
+        }
+        ysqlmetrics
+    }
+    pub fn print(
+        &self,
+        hostname_filter: &Regex,
+        config: &Config,
+    ) -> Result<()>
+    {
+        let rows: Vec<Vec<String>> = self.ysqlmetrics
+            .iter()
+            .filter(|row| hostname_filter.is_match(row.hostname_port.as_ref().unwrap()))
+            .map(|row| vec![
+                row.hostname_port.clone().unwrap(),
+                row.connections().to_string(),
+                row.backends("active").to_string(),
+                row.backends("idle").to_string(),
+                row.backends("idle in transaction").to_string(),
+                row.max_connections.map(|max_connections| max_connections.to_string()).unwrap_or_default(),
+                row.connections_used_pct().map(|pct| format!("{:.1}%", pct)).unwrap_or_default(),
+                row.connections_over_limit.map(|over_limit| over_limit.to_string()).unwrap_or_default(),
+                row.statement("Transactions").count.to_string(),
+                row.statement("CommitStmt").count.to_string(),
+                row.statement("RollbackStmt").count.to_string(),
+            ])
+            .collect();
+        utility::print_table(&["hostname_port", "connections", "active", "idle", "idle_in_txn", "max_connections", "used", "over_limit", "transactions", "commits", "rollbacks"], &rows, config)?;
+        Ok(())
+    }
+}
+
+impl YsqlMetricsDiff {
+    pub fn new() -> Self { Default::default() }
+    pub fn snapshot_diff(
+        begin_snapshot: &String,
+        end_snapshot: &String,
+        config: &Config,
+    ) -> Result<YsqlMetricsDiff>
+    {
+        let mut ysqlmetricsdiff = YsqlMetricsDiff::new();
+
+        let mut allysqlmetrics = AllYsqlMetrics::new();
+        allysqlmetrics.ysqlmetrics = snapshot::read_snapshot_json(begin_snapshot, "ysql_metrics", config)?;
+        ysqlmetricsdiff.first_snapshot(allysqlmetrics);
+
+        let mut allysqlmetrics = AllYsqlMetrics::new();
+        allysqlmetrics.ysqlmetrics = snapshot::read_snapshot_json(end_snapshot, "ysql_metrics", config)?;
+        ysqlmetricsdiff.second_snapshot(allysqlmetrics);
+
+        Ok(ysqlmetricsdiff)
+    }
+    fn first_snapshot(
+        &mut self,
+        allysqlmetrics: AllYsqlMetrics,
+    )
+    {
+        for ysqlmetrics in allysqlmetrics.ysqlmetrics
+        {
+            self.btreeysqlmetricsdiff
+                .entry(ysqlmetrics.hostname_port.clone().expect("hostname:port should be set"))
+                .and_modify(|_| error!("Duplicate hostname:port entry: {}", ysqlmetrics.hostname_port.clone().expect("hostname:port should be set")))
+                .or_insert(YsqlMetricsDiffFields {
+                    first: Some(ysqlmetrics.clone()),
+                    ..Default::default()
+                });
+        }
+    }
+    fn second_snapshot(
+        &mut self,
+        allysqlmetrics: AllYsqlMetrics,
+    )
+    {
+        for ysqlmetrics in allysqlmetrics.ysqlmetrics
+        {
+            let hostname_port = ysqlmetrics.hostname_port.clone().expect("hostname:port should be set");
+            self.btreeysqlmetricsdiff
+                .entry(hostname_port)
+                .or_default()
+                .second = Some(ysqlmetrics);
+        }
+    }
+    /// The rows of the diff: the connections in the end snapshot with the change, and the statement rates per second.
+    /// Only the YSQL webservers that are in both snapshots, and of which the connections or statements changed, are shown.
+    fn rows(
+        &self,
+        hostname_filter: &Regex,
+    ) -> Vec<Vec<String>>
+    {
+        let mut rows = Vec::new();
+        for (hostname_port, row) in self.btreeysqlmetricsdiff.iter().filter(|(hostname_port, _)| hostname_filter.is_match(hostname_port))
+        {
+            let (Some(first), Some(second)) = (&row.first, &row.second) else { continue };
+            if first.backends == second.backends
+                && first.statements == second.statements
+                && first.connections_over_limit == second.connections_over_limit
+            {
+                continue;
+            }
+            let seconds = (second.timestamp.unwrap_or_default() - first.timestamp.unwrap_or_default()).num_milliseconds() as f64 / 1000.0;
+            let with_change = |first_value: i64, second_value: i64| format!("{} ({:+})", second_value, second_value - first_value);
+            let mut fields = vec![
+                hostname_port.clone(),
+                with_change(first.connections(), second.connections()),
+                with_change(first.backends("active"), second.backends("active")),
+                with_change(first.backends("idle"), second.backends("idle")),
+                with_change(first.backends("idle in transaction"), second.backends("idle in transaction")),
+                second.connections_over_limit.map(|over_limit| (over_limit - first.connections_over_limit.unwrap_or_default()).to_string()).unwrap_or_default(),
+            ];
+            for (statement, _) in STATEMENT_RATES
+            {
+                let delta = second.statement(statement).count - first.statement(statement).count;
+                fields.push(if seconds > 0.0 { format!("{:.1}", delta as f64 / seconds) } else { String::new() });
+            }
+            rows.push(fields);
+        }
+        rows
+    }
+    /// Print the hosts of which the connections or statements changed, and return the number of these hosts.
+    pub fn print(
+        &self,
+        hostname_filter: &Regex,
+        config: &Config,
+    ) -> Result<usize>
+    {
+        let rows = self.rows(hostname_filter);
+        if rows.is_empty()
+        {
+            return Ok(0);
+        }
+        writeln!(config.output(), "YSQL webserver connections and statement rates (/metrics and /rpcz of the YSQL port):")?;
+        let mut headers = vec!["hostname_port", "connections", "active", "idle", "idle_in_txn", "over_limit"];
+        headers.extend(STATEMENT_RATES.iter().map(|(_, heading)| *heading));
+        utility::print_table(&headers, &rows, config)?;
+        Ok(rows.len())
+    }
+}
+
+pub async fn ysql_metrics_diff(
+    options: &Opts,
+    config: &Config,
+) -> Result<()>
+{
+    if options.begin.is_none() || options.end.is_none() {
+        snapshot::Snapshot::print(config)?;
+    }
+    if options.snapshot_list { return Ok(()) };
+
+    let hostname_filter = utility::set_regex(&options.hostname_match);
+
+    let (begin_snapshot, end_snapshot, _begin_snapshot_row) = snapshot::Snapshot::read_begin_end_snapshot_from_user(options.begin, options.end)?;
+
+    snapshot::Snapshot::print_diff_header(&begin_snapshot, &end_snapshot, config)?;
+
+    let ysqlmetricsdiff = YsqlMetricsDiff::snapshot_diff(&begin_snapshot, &end_snapshot, config)?;
+    let changes = ysqlmetricsdiff.print(&hostname_filter, config)?;
+    utility::add_diff_changes(changes, config);
+
+    Ok(())
+}
+
+pub async fn print_ysql_metrics(
+    hosts: Vec<&str>,
+    ports: Vec<&str>,
+    parallel: usize,
+    options: &Opts,
+    config: &Config,
+) -> Result<()>
+{
+    let hostname_filter = utility::set_regex(&options.hostname_match);
+    match options.print_ysql_metrics.as_ref().unwrap()
+    {
+        Some(snapshot_number) => {
+            let mut allysqlmetrics = AllYsqlMetrics::new();
+            allysqlmetrics.ysqlmetrics = snapshot::read_snapshot_json(snapshot_number, "ysql_metrics", config)?;
+            allysqlmetrics.print(&hostname_filter, config)?;
+        },
+        None => {
+            let allysqlmetrics = AllYsqlMetrics::read_ysql_metrics(&hosts, &ports, parallel, config).await;
+            allysqlmetrics.print(&hostname_filter, config)?;
+        },
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn unit_parse_ysql_metrics() {
+        let json = r#"
+[
+    {
+        "type": "server",
+        "id": "yb.ysqlserver",
+        "metrics": [
+            {
+                "name": "handler_latency_yb_ysqlserver_SQLProcessor_SelectStmt",
+                "count": 25,
+                "sum": 631456,
+                "rows": 26
+            },
+            {
+                "name": "handler_latency_yb_ysqlserver_SQLProcessor_Transactions",
+                "count": 40,
+                "sum": 0,
+                "rows": 0
+            },
+            {
+                "name": "yb_ysqlserver_max_connection_total",
+                "value": 300
+            },
+            {
+                "name": "yb_ysqlserver_active_connection_total",
+                "value": 3
+            }
+        ]
+    }
+]
+        "#.to_string();
+        let result = AllYsqlMetrics::parse_ysql_metrics(json, "", "");
+        assert_eq!(result.statement("SelectStmt"), YsqlStatementCounter { count: 25, sum: 631456, rows: 26 });
+        assert_eq!(result.statement("Transactions").count, 40);
+        assert_eq!(result.statement("DeleteStmt").count, 0);
+        assert_eq!(result.max_connections, Some(300));
+        assert_eq!(result.connections_over_limit, None);
+
+        // the tablet server metrics do not contain the yb.ysqlserver entity.
+        let json = r#"[{"type": "server", "id": "yb.tabletserver", "metrics": [{"name": "handler_latency_yb_tserver_TabletServerService_Read", "count": 1, "sum": 1, "rows": 1}]}]"#.to_string();
+        assert!(AllYsqlMetrics::parse_ysql_metrics(json, "", "").is_empty());
+    }
+
+    #[test]
+    fn unit_ysql_metrics_backends_and_diff() {
+        let json = r#"
+{
+    "connections": [
+        { "process_start_time": "2022-08-11 10:06:23.639902+00", "application_name": "", "backend_type": "checkpointer", "backend_status": "" },
+        { "process_start_time": "2022-08-11 10:06:23.639902+00", "application_name": "ysqlsh", "backend_type": "client backend", "backend_status": "active" },
+        { "process_start_time": "2022-08-11 10:06:23.639902+00", "application_name": "ysqlsh", "backend_type": "client backend", "backend_status": "idle" },
+        { "process_start_time": "2022-08-11 10:06:23.639902+00", "application_name": "ysqlsh", "backend_type": "client backend", "backend_status": "idle in transaction" },
+        { "process_start_time": "2022-08-11 10:06:23.639902+00", "application_name": "ysqlsh", "backend_type": "client backend", "backend_status": "idle" }
+    ]
+}
+        "#;
+        let mut second = YsqlMetrics {
+            hostname_port: Some("yb-1.local:13000".to_string()),
+            timestamp: Some(Local::now()),
+            max_connections: Some(8),
+            ..Default::default()
+        };
+        second.add_backends(serde_json::from_str(json).unwrap());
+        assert_eq!(second.connections(), 4);
+        assert_eq!(second.backends("idle"), 2);
+        assert_eq!(second.connections_used_pct(), Some(50.0));
+
+        let mut first = second.clone();
+        first.timestamp = Some(second.timestamp.unwrap() - Duration::seconds(10));
+        first.backends.insert("idle".to_string(), 1);
+        first.statements.insert("Transactions".to_string(), YsqlStatementCounter { count: 100, sum: 0, rows: 0 });
+        second.statements.insert("Transactions".to_string(), YsqlStatementCounter { count: 150, sum: 0, rows: 0 });
+
+        let mut ysqlmetricsdiff = YsqlMetricsDiff::new();
+        ysqlmetricsdiff.first_snapshot(AllYsqlMetrics { ysqlmetrics: vec![first] });
+        ysqlmetricsdiff.second_snapshot(AllYsqlMetrics { ysqlmetrics: vec![second] });
+        let rows = ysqlmetricsdiff.rows(&utility::set_regex(&None));
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0][0], "yb-1.local:13000");
+        assert_eq!(rows[0][1], "4 (+1)");
+        assert_eq!(rows[0][3], "2 (+1)");
+        // 50 transactions in 10 seconds
+        assert_eq!(rows[0][6], "5.0");
+        assert_eq!(rows[0][7], "0.0");
+    }
+}
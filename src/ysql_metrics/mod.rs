@@ -0,0 +1,22 @@
+//! Module for reading the YSQL specific metrics from the YSQL webservers.
+//!
+//! The YSQL webserver, default port 13000, provides its own metrics, which are not part of the tablet server metrics
+//! on port 9000, because the YSQL backends are separate PostgreSQL processes:
+//! - `/metrics`: the `yb.ysqlserver` entity, with the SQLProcessor statement counters (such as `Transactions`, `SelectStmt`),
+//!   and in newer versions the connection counters (such as `yb_ysqlserver_max_connection_total`).
+//! - `/rpcz`: the PostgreSQL backends, which are counted by backend status (active, idle, idle in transaction).
+//!
+//! These are read into [YsqlMetrics], a row per YSQL webserver, to diagnose connection pool exhaustion.
+//! Ports that do not provide the `yb.ysqlserver` entity or YSQL backends, such as the tablet servers, are not stored.
+//!
+//! The ysql metrics functionality is called from:
+//! - [crate::snapshot::perform_snapshot] -> [crate::ysql_metrics::AllYsqlMetrics::perform_snapshot] (general snapshot, saves ysql metrics data)
+//! - [crate::snapshot::snapshot_diff] -> [crate::ysql_metrics::YsqlMetricsDiff::snapshot_diff] (connection changes and statement rates)
+//! - [crate] -> [print_ysql_metrics] (print adhoc (live) or snapshot ysql metrics)
+//! - [crate] -> [ysql_metrics_diff] (print the diff of two snapshots)
+//!
+mod structs;
+mod functions;
+
+pub use structs::*;
+pub use functions::*;
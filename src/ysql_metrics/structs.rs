@@ -0,0 +1,80 @@
+//! The structs
+//!
+use chrono::{DateTime, Local};
+use std::collections::BTreeMap;
+/// The metric entities from `/metrics` on the YSQL webserver, of which only the `yb.ysqlserver` entity is used.
+///
+/// ```json
+/// [
+///     {
+///         "type": "server",
+///         "id": "yb.ysqlserver",
+///         "metrics": [
+///             {
+///                 "name": "handler_latency_yb_ysqlserver_SQLProcessor_SelectStmt",
+///                 "count": 25,
+///                 "sum": 631456,
+///                 "rows": 26
+///             },
+///             {
+///                 "name": "yb_ysqlserver_max_connection_total",
+///                 "value": 300
+///             }
+///         ]
+///     }
+/// ]
+/// ```
+#[derive(Deserialize, Debug, Default)]
+pub struct YsqlServerEntity {
+    #[serde(rename = "type")]
+    pub entity_type: String,
+    pub id: String,
+    pub metrics: Vec<YsqlServerMetric>,
+}
+/// A metric of the `yb.ysqlserver` entity: a countsumrows metric, or a value.
+#[derive(Deserialize, Debug, Default)]
+pub struct YsqlServerMetric {
+    pub name: String,
+    pub count: Option<i64>,
+    pub sum: Option<i64>,
+    pub rows: Option<i64>,
+    pub value: Option<i64>,
+}
+/// The YSQL metrics of a YSQL webserver, as stored in the snapshot.
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct YsqlMetrics {
+    /// yb_stats added to allow understanding the source host.
+    pub hostname_port: Option<String>,
+    /// yb_stats added to allow understanding the timestamp.
+    pub timestamp: Option<DateTime<Local>>,
+    /// The client backends by backend status from `/rpcz`, such as: active, idle, idle in transaction.
+    pub backends: BTreeMap<String, i64>,
+    /// The maximum number of connections (`yb_ysqlserver_max_connection_total`), not available in older versions.
+    pub max_connections: Option<i64>,
+    /// The number of connections refused because of the maximum (`yb_ysqlserver_connection_over_limit_total`), not available in older versions.
+    pub connections_over_limit: Option<i64>,
+    /// The SQLProcessor statement counters, by the name without the `handler_latency_yb_ysqlserver_SQLProcessor_` prefix.
+    pub statements: BTreeMap<String, YsqlStatementCounter>,
+}
+/// A SQLProcessor statement counter: the number of executions, the total time in microseconds, and the rows.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+pub struct YsqlStatementCounter {
+    pub count: i64,
+    pub sum: i64,
+    pub rows: i64,
+}
+#[derive(Debug, Default)]
+pub struct AllYsqlMetrics {
+    pub ysqlmetrics: Vec<YsqlMetrics>,
+}
+// diff
+type BTreeYsqlMetricsDiff = BTreeMap<String, YsqlMetricsDiffFields>;
+#[derive(Debug, Default)]
+pub struct YsqlMetricsDiff {
+    pub btreeysqlmetricsdiff: BTreeYsqlMetricsDiff,
+}
+#[derive(Debug, Default)]
+pub struct YsqlMetricsDiffFields {
+    pub first: Option<YsqlMetrics>,
+    pub second: Option<YsqlMetrics>,
+}